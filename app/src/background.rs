@@ -0,0 +1,481 @@
+// Implements the background work of the firmware, run by the cooperative scheduler in idle time.
+
+// Key Features:
+// - Platform clock for the scheduler: DWT cycle counter and time left until the PWM interrupt.
+// - Control tick statistics (rate and longest tick) reported once per second.
+// - Parameter save into the configuration flash area, deferred until the motor tolerates the
+//   bus stall.
+// - One-time factory data write into its own flash area, deferred the same way.
+// - Decoding of the host requests received over CAN (`can` feature), see `can.rs`.
+// - Parsing of the lines typed on the USB console (`usb` feature), see `usb.rs`.
+
+// Detailed Operation:
+// The executor calls `Background::run()` from its lowest priority context (RTIC idle, Embassy
+// thread executor). Every call polls each task once through `Scheduler::run()`; tasks return
+// quickly and keep their progress in their own state. `TICK_STATS` is filled by the control
+// task and drained by the statistics task, using atomics so neither blocks the other.
+// A save request is handed over the same way: the control task serializes the parameters into
+// `SAVE_IMAGE` and sets `SAVE_PENDING`, the save task copies the image into the flash writer
+// and clears the flag. The outcome returns through `SAVE_RESULT`, the control task counts the
+// parameters as saved only once the write succeeded and requests the next save after that.
+// Factory data is handed over like a save request through `FACTORY_DATA` and
+// `FACTORY_PENDING`, both writers share the flash controller and run one after the other. The
+// outcome of a factory write returns through `FACTORY_RESULT`, the control task reports the
+// data as stored only once it succeeded. The control task also publishes whether the motor
+// tolerates missed ticks, `CoreClock` then reports unlimited time so the writer's erase and
+// program steps are admitted; otherwise only the time until the next PWM interrupt counts and
+// they wait.
+// Requests of the communication links are handed over through an `Exchange`: the background
+// task decodes a request and hands it over, the control task applies it after its tick and
+// hands the answer back, which the background task then formats and sends.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::cell::UnsafeCell;
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+
+use cortex_m::peripheral::{DCB, DWT};
+
+use tunepulse_algo::{
+    factory_data::{FactoryData, FactoryWriter},
+    params::{
+        storage::{StorageError, PARAM_IMAGE_SIZE},
+        writer::{FlashError, FlashOps, FlashWriter, DOUBLE_WORD},
+    },
+    scheduler::{BackgroundTask, Budget, Clock, Poll, Scheduler},
+    MotorController,
+};
+use tunepulse_drivers::{flash, pwm::TimPWM};
+
+/// Core clock frequency (Hz), default clock configuration
+const CORE_FREQUENCY: u32 = 170_000_000;
+
+/// Cycles kept free before the PWM interrupt when admitting bus stalls (~2 µs)
+const STALL_MARGIN: u32 = 340;
+
+/// Number of background tasks
+const TASKS: usize = 2 + cfg!(feature = "can") as usize + cfg!(feature = "usb") as usize;
+/// Cycles granted per poll (~20 µs each)
+const BUDGETS: [u32; TASKS] = [3400; TASKS];
+
+/// Control tick statistics collected by the control task
+pub static TICK_STATS: TickStats = TickStats::new();
+
+/// Set while the motor tolerates missed control ticks
+static STALL_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Parameter image waiting for the save task
+static mut SAVE_IMAGE: [u8; PARAM_IMAGE_SIZE] = [0; PARAM_IMAGE_SIZE];
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+static SAVE_RESULT: WriteResult = WriteResult::new();
+
+/// Factory data waiting for the save task
+static mut FACTORY_DATA: FactoryData = FactoryData {
+    serial: 0,
+    date: 0,
+    hardware_rev: 0,
+    supply_trim: 0,
+    temp_offset: 0,
+};
+static FACTORY_PENDING: AtomicBool = AtomicBool::new(false);
+static FACTORY_RESULT: WriteResult = WriteResult::new();
+
+/// Counters of executed control ticks
+pub struct TickStats {
+    count: AtomicU32,      // Ticks since the last report
+    max_cycles: AtomicU32, // Longest tick since the last report
+}
+
+impl TickStats {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU32::new(0),
+            max_cycles: AtomicU32::new(0),
+        }
+    }
+
+    /// Records a tick lasting `cycles`
+    pub fn record(&self, cycles: u32) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.max_cycles.fetch_max(cycles, Ordering::Relaxed);
+    }
+
+    /// Returns (tick count, longest tick) and starts a new period
+    fn take(&self) -> (u32, u32) {
+        (
+            self.count.swap(0, Ordering::Relaxed),
+            self.max_cycles.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Outcome of a flash write handed back to the control task
+struct WriteResult(AtomicU8); // 0 - none, `RESULT_OK` or the `FlashError` code
+
+/// Successful write in `WriteResult`
+const RESULT_OK: u8 = u8::MAX;
+
+impl WriteResult {
+    const fn new() -> Self {
+        Self(AtomicU8::new(0))
+    }
+
+    fn publish(&self, result: Result<(), FlashError>) {
+        let code = match result {
+            Ok(()) => RESULT_OK,
+            Err(error) => error.code(),
+        };
+        self.0.store(code, Ordering::Release);
+    }
+
+    /// Returns the published outcome once
+    fn take(&self) -> Option<Result<(), FlashError>> {
+        match self.0.swap(0, Ordering::Acquire) {
+            0 => None,
+            RESULT_OK => Some(Ok(())),
+            code => FlashError::from_code(code).map(Err),
+        }
+    }
+}
+
+/// Request handed from a background task to the control task and its answer handed back, one
+/// exchange at a time
+pub struct Exchange<Q, A> {
+    state: AtomicU8,                // `FREE`, `REQUESTED` or `ANSWERED`
+    request: UnsafeCell<Option<Q>>, // Written by the background task while `FREE`
+    answer: UnsafeCell<Option<A>>,  // Written by the control task while `REQUESTED`
+}
+
+/// States of an `Exchange`
+const FREE: u8 = 0;
+const REQUESTED: u8 = 1;
+const ANSWERED: u8 = 2;
+
+// SAFETY: each cell is accessed by one side at a time, the state passes the ownership
+unsafe impl<Q: Send, A: Send> Sync for Exchange<Q, A> {}
+
+impl<Q, A> Exchange<Q, A> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(FREE),
+            request: UnsafeCell::new(None),
+            answer: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns true once the previous answer was taken, called by the background task
+    pub fn is_free(&self) -> bool {
+        self.state.load(Ordering::Acquire) == FREE
+    }
+
+    /// Hands `request` over, returns it back while the previous exchange isn't finished.
+    /// Called by the background task.
+    pub fn request(&self, request: Q) -> Result<(), Q> {
+        if !self.is_free() {
+            return Err(request);
+        }
+        // SAFETY: the control task doesn't touch the request while FREE
+        unsafe { *self.request.get() = Some(request) };
+        self.state.store(REQUESTED, Ordering::Release);
+        Ok(())
+    }
+
+    /// Answers a pending request with `apply`, called by the control task
+    pub fn serve(&self, apply: impl FnOnce(Q) -> A) {
+        if self.state.load(Ordering::Acquire) != REQUESTED {
+            return;
+        }
+        // SAFETY: the background task doesn't touch either cell while REQUESTED
+        let request = unsafe { (*self.request.get()).take() };
+        if let Some(request) = request {
+            unsafe { *self.answer.get() = Some(apply(request)) };
+        }
+        self.state.store(ANSWERED, Ordering::Release);
+    }
+
+    /// Returns the answer once, called by the background task
+    pub fn take_answer(&self) -> Option<A> {
+        if self.state.load(Ordering::Acquire) != ANSWERED {
+            return None;
+        }
+        // SAFETY: the control task doesn't touch the answer while ANSWERED
+        let answer = unsafe { (*self.answer.get()).take() };
+        self.state.store(FREE, Ordering::Release);
+        answer
+    }
+}
+
+impl<Q, A> Default for Exchange<Q, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Enables the DWT cycle counter used as the time base of background work
+pub fn enable_cycle_counter(dcb: &mut DCB, dwt: &mut DWT) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+}
+
+/// Scheduler time source on Cortex-M
+pub struct CoreClock;
+
+impl Clock for CoreClock {
+    fn now(&self) -> u32 {
+        DWT::cycle_count()
+    }
+
+    fn until_tick(&self) -> u32 {
+        if STALL_ALLOWED.load(Ordering::Relaxed) {
+            u32::MAX
+        } else {
+            TimPWM::cycles_to_update()
+        }
+    }
+}
+
+/// Publishes whether the motor tolerates missed ticks, called by the control task
+pub fn allow_stall(allowed: bool) {
+    STALL_ALLOWED.store(allowed, Ordering::Relaxed);
+}
+
+/// Hands the parameters over to the save task, call only after the previous save reported
+/// its result. Returns the error if the parameters don't fit the image.
+pub fn request_save(motor: &MotorController) -> Result<(), StorageError> {
+    // SAFETY: the save task only reads the image while SAVE_PENDING is set, which is cleared
+    // before the previous save reports its result
+    unsafe { motor.store_params(&mut *addr_of_mut!(SAVE_IMAGE)) }?;
+    SAVE_PENDING.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Returns the outcome of the last parameter save once it finished
+pub fn take_save_result() -> Option<Result<(), FlashError>> {
+    SAVE_RESULT.take()
+}
+
+/// Hands the factory data over to the save task, returns false if a write is still pending
+pub fn request_factory_write(data: FactoryData) -> bool {
+    if FACTORY_PENDING.load(Ordering::Acquire) {
+        return false;
+    }
+    // SAFETY: the save task only reads the data while FACTORY_PENDING is set
+    unsafe { *addr_of_mut!(FACTORY_DATA) = data };
+    FACTORY_PENDING.store(true, Ordering::Release);
+    true
+}
+
+/// Returns the outcome of the last factory data write once it finished
+pub fn take_factory_result() -> Option<Result<(), FlashError>> {
+    FACTORY_RESULT.take()
+}
+
+/// Configuration area of the internal flash
+pub struct ConfigFlash {
+    flash: flash::Flash,
+}
+
+impl ConfigFlash {
+    pub fn new(flash: flash::Flash) -> Self {
+        Self { flash }
+    }
+
+    /// Factory data area, sharing the flash controller with the configuration area
+    pub fn factory(&mut self) -> FactoryFlash<'_> {
+        FactoryFlash {
+            flash: &mut self.flash,
+        }
+    }
+
+    /// Returns the absolute address of `len` bytes at `offset`, checking the area bounds
+    fn address(offset: usize, len: usize) -> Result<u32, FlashError> {
+        area_address(flash::CONFIG_ADDRESS, flash::CONFIG_SIZE, offset, len)
+    }
+}
+
+impl FlashOps for ConfigFlash {
+    const PAGE_SIZE: usize = flash::PAGE_SIZE;
+    const ERASE_CYCLES: u32 = flash::ERASE_CYCLES;
+    const PROGRAM_CYCLES: u32 = flash::PROGRAM_CYCLES;
+
+    fn erase_page(&mut self, offset: usize) -> Result<(), FlashError> {
+        let address = Self::address(offset, flash::PAGE_SIZE)?;
+        self.flash.erase_page(address).map_err(map_error)
+    }
+
+    fn program(&mut self, offset: usize, word: [u8; DOUBLE_WORD]) -> Result<(), FlashError> {
+        let address = Self::address(offset, DOUBLE_WORD)?;
+        self.flash
+            .program(address, u64::from_le_bytes(word))
+            .map_err(map_error)
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), FlashError> {
+        let address = Self::address(offset, buf.len())?;
+        self.flash.read(address, buf).map_err(map_error)
+    }
+}
+
+/// Factory data area of the internal flash
+pub struct FactoryFlash<'a> {
+    flash: &'a mut flash::Flash,
+}
+
+impl FactoryFlash<'_> {
+    /// Returns the absolute address of `len` bytes at `offset`, checking the area bounds
+    fn address(offset: usize, len: usize) -> Result<u32, FlashError> {
+        area_address(flash::FACTORY_ADDRESS, flash::FACTORY_SIZE, offset, len)
+    }
+}
+
+impl FlashOps for FactoryFlash<'_> {
+    const PAGE_SIZE: usize = flash::PAGE_SIZE;
+    const ERASE_CYCLES: u32 = flash::ERASE_CYCLES;
+    const PROGRAM_CYCLES: u32 = flash::PROGRAM_CYCLES;
+
+    fn erase_page(&mut self, offset: usize) -> Result<(), FlashError> {
+        let address = Self::address(offset, flash::PAGE_SIZE)?;
+        self.flash.erase_page(address).map_err(map_error)
+    }
+
+    fn program(&mut self, offset: usize, word: [u8; DOUBLE_WORD]) -> Result<(), FlashError> {
+        let address = Self::address(offset, DOUBLE_WORD)?;
+        self.flash
+            .program(address, u64::from_le_bytes(word))
+            .map_err(map_error)
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), FlashError> {
+        let address = Self::address(offset, buf.len())?;
+        self.flash.read(address, buf).map_err(map_error)
+    }
+}
+
+/// Returns the absolute address of `len` bytes at `offset` of the area at `base`
+fn area_address(base: u32, size: usize, offset: usize, len: usize) -> Result<u32, FlashError> {
+    if offset + len > size {
+        return Err(FlashError::OutOfRange);
+    }
+    Ok(base + offset as u32)
+}
+
+fn map_error(error: flash::FlashError) -> FlashError {
+    match error {
+        flash::FlashError::OutOfRange => FlashError::OutOfRange,
+        flash::FlashError::Locked => FlashError::Locked,
+        flash::FlashError::WriteProtected => FlashError::WriteProtected,
+        flash::FlashError::Program => FlashError::Program,
+    }
+}
+
+/// Writes parameter images and factory data requested by the control task
+struct ParamSave {
+    writer: FlashWriter<ConfigFlash, PARAM_IMAGE_SIZE>,
+    factory: FactoryWriter,
+}
+
+impl BackgroundTask for ParamSave {
+    fn poll(&mut self, budget: &Budget) -> Poll {
+        let idle = !self.writer.is_busy() && !self.factory.is_busy();
+        if SAVE_PENDING.load(Ordering::Acquire) && idle {
+            // SAFETY: the control task doesn't touch the image while SAVE_PENDING is set
+            if !self.writer.start(unsafe { &*addr_of!(SAVE_IMAGE) }) {
+                // Image larger than a record slot, the control task still waits for a result
+                defmt::error!("PARAMS: Image doesn't fit the record");
+                SAVE_RESULT.publish(Err(FlashError::OutOfRange));
+            }
+            SAVE_PENDING.store(false, Ordering::Release);
+        } else if FACTORY_PENDING.load(Ordering::Acquire) && idle {
+            // SAFETY: the control task doesn't touch the data while FACTORY_PENDING is set
+            self.factory.start(unsafe { &*addr_of!(FACTORY_DATA) });
+            FACTORY_PENDING.store(false, Ordering::Release);
+        }
+        if let Some(result) = self.writer.take_result() {
+            match result {
+                Ok(()) => defmt::info!("PARAMS: Saved"),
+                Err(error) => defmt::error!("PARAMS: Save failed ({})", error),
+            }
+            SAVE_RESULT.publish(result);
+        }
+        if let Some(result) = self.factory.take_result() {
+            match result {
+                Ok(()) => defmt::info!("FACTORY: Data stored"),
+                Err(error) => defmt::error!("FACTORY: Write failed ({})", error),
+            }
+            FACTORY_RESULT.publish(result);
+        }
+        // Both writers program through the same flash controller, one at a time
+        match self.writer.flash_mut() {
+            Some(config) if self.factory.is_busy() => {
+                self.factory.poll(&mut config.factory(), budget)
+            }
+            _ => self.writer.poll(budget),
+        }
+    }
+}
+
+/// Reports control loop timing once per second
+struct LoopStats {
+    last: u32, // Cycle count of the last report
+}
+
+impl BackgroundTask for LoopStats {
+    fn poll(&mut self, budget: &Budget) -> Poll {
+        let now = DWT::cycle_count();
+        if now.wrapping_sub(self.last) < CORE_FREQUENCY {
+            return Poll::Idle;
+        }
+        self.last = now;
+        let (ticks, max_cycles) = TICK_STATS.take();
+        defmt::debug!(
+            "LOOP: {} ticks/s, longest tick {} cycles ({} left in budget)",
+            ticks,
+            max_cycles,
+            budget.remaining()
+        );
+        Poll::Idle
+    }
+}
+
+/// Background tasks and their scheduler
+pub struct Background {
+    scheduler: Scheduler<TASKS>,
+    stats: LoopStats,
+    save: ParamSave,
+    #[cfg(feature = "can")]
+    can: crate::can::CanRequests,
+    #[cfg(feature = "usb")]
+    usb: crate::usb::ConsoleLines,
+}
+
+impl Background {
+    pub fn new(config: ConfigFlash) -> Self {
+        Self {
+            scheduler: Scheduler::new(BUDGETS, STALL_MARGIN),
+            stats: LoopStats { last: 0 },
+            save: ParamSave {
+                writer: FlashWriter::new(config),
+                factory: FactoryWriter::new(),
+            },
+            #[cfg(feature = "can")]
+            can: crate::can::CanRequests::new(),
+            #[cfg(feature = "usb")]
+            usb: crate::usb::ConsoleLines::new(),
+        }
+    }
+
+    /// Polls all tasks once, returns true if work is pending
+    pub fn run(&mut self) -> bool {
+        let tasks: &mut [&mut dyn BackgroundTask; TASKS] = &mut [
+            &mut self.stats,
+            &mut self.save,
+            #[cfg(feature = "can")]
+            &mut self.can,
+            #[cfg(feature = "usb")]
+            &mut self.usb,
+        ];
+        self.scheduler.run(tasks, &CoreClock)
+    }
+}
@@ -0,0 +1,234 @@
+// Implements the firmware entry point on the Embassy executor, as an alternative to RTIC.
+
+// Key Features:
+// - Runs the same board bring-up and control pipeline as the RTIC application.
+// - Control tick and encoder read as tasks of an interrupt executor, LED and background work on
+//   the thread executor.
+// - Build with `cargo run --release --bin app_embassy --features embassy`.
+
+// Detailed Operation:
+// Hardware interrupts (PWM timer, DMA transfer complete, CAN receive) call the pipeline stages
+// directly and signal the tasks. The interrupt executor runs on the otherwise unused TIM7
+// interrupt at the same priority as the pipeline interrupts, so no stage preempts another one,
+// matching the single priority level of the RTIC application. Peripherals used by interrupts live in
+// critical section mutexes, everything else is moved into the task owning it.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#![no_main]
+#![no_std]
+
+use core::cell::RefCell;
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::peripheral::NVIC;
+use embassy_executor::{Executor, InterruptExecutor};
+use embassy_futures::yield_now;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_sync::signal::Signal;
+use hal::{
+    adc::Adc,
+    dma::Dma,
+    pac::{self, interrupt, Interrupt, ADC1, DMA1},
+};
+use static_cell::StaticCell;
+
+use tunepulse_algo::{
+    indication::{IndicationState, StatusIndicator},
+    MotorController,
+};
+use tunepulse_app::{
+    background::{self, Background},
+    board::{self, Board},
+    pipeline::{self, ControlTask, PeriodSequencer, Stage, LED_UPDATE_MS},
+};
+use tunepulse_drivers::{bridge, encoder_spi, gpio_io, pwm, status_led};
+
+/// Priority of the pipeline interrupts and the control executor (lowest level)
+const PRIORITY: u8 = 0xF0;
+
+/// Peripheral owned by interrupt handlers and tasks
+type Shared<T> = Mutex<CriticalSectionRawMutex, RefCell<Option<T>>>;
+
+/// Resources of the PWM timer interrupt
+struct PwmStage {
+    timer_pwm: pwm::TimPWM,
+    sequencer: PeriodSequencer,
+    adc1: Adc<ADC1>,
+}
+
+static PWM_STAGE: Shared<PwmStage> = Mutex::new(RefCell::new(None));
+static SPI1: Shared<encoder_spi::Spi1DMA> = Mutex::new(RefCell::new(None));
+static DMA: Shared<Dma<DMA1>> = Mutex::new(RefCell::new(None));
+
+static CONTROL_DUE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static ENCODER_DUE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static LED_STATE: Signal<CriticalSectionRawMutex, IndicationState> = Signal::new();
+
+static EXECUTOR_CONTROL: InterruptExecutor = InterruptExecutor::new();
+static EXECUTOR_THREAD: StaticCell<Executor> = StaticCell::new();
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    background::enable_cycle_counter(&mut cp.DCB, &mut cp.DWT);
+    #[cfg(feature = "telemetry-swo")]
+    tunepulse_app::telemetry::start(cp.ITM);
+
+    let Board {
+        timer_pwm,
+        spi1,
+        adc1,
+        dma1,
+        gpio_io,
+        status_led,
+        bridge,
+        config,
+        motor,
+    } = board::init(dp);
+
+    PWM_STAGE.lock(|stage| {
+        stage.replace(Some(PwmStage {
+            timer_pwm,
+            sequencer: PeriodSequencer::new(),
+            adc1,
+        }))
+    });
+    SPI1.lock(|spi| spi.replace(Some(spi1)));
+    DMA.lock(|dma| dma.replace(Some(dma1)));
+
+    // SAFETY: priorities are set before the interrupts are unmasked
+    unsafe {
+        cp.NVIC.set_priority(Interrupt::TIM2, PRIORITY);
+        cp.NVIC.set_priority(Interrupt::DMA1_CH1, PRIORITY);
+        cp.NVIC.set_priority(Interrupt::DMA1_CH2, PRIORITY);
+        cp.NVIC.set_priority(Interrupt::TIM7, PRIORITY);
+        NVIC::unmask(Interrupt::TIM2);
+        NVIC::unmask(Interrupt::DMA1_CH1);
+        NVIC::unmask(Interrupt::DMA1_CH2);
+        #[cfg(feature = "can")]
+        {
+            cp.NVIC.set_priority(Interrupt::FDCAN1_INTR0_IT, PRIORITY);
+            NVIC::unmask(Interrupt::FDCAN1_INTR0_IT);
+        }
+        #[cfg(feature = "usb")]
+        {
+            cp.NVIC.set_priority(Interrupt::USB_LP, PRIORITY);
+            NVIC::unmask(Interrupt::USB_LP);
+        }
+    }
+
+    let spawner = EXECUTOR_CONTROL.start(Interrupt::TIM7);
+    spawner.must_spawn(control(motor, gpio_io, bridge));
+    spawner.must_spawn(encoder_read());
+
+    let executor = EXECUTOR_THREAD.init(Executor::new());
+    executor.run(|spawner| {
+        spawner.must_spawn(led(status_led));
+        spawner.must_spawn(background(Background::new(config)));
+    })
+}
+
+#[embassy_executor::task]
+async fn control(
+    mut motor: MotorController,
+    mut gpio_io: gpio_io::GpioIo,
+    mut bridge: bridge::BridgeEnable,
+) {
+    let mut control = ControlTask::new();
+    loop {
+        CONTROL_DUE.wait().await;
+        if let Some(state) = control.tick(&mut motor, &mut gpio_io, &mut bridge) {
+            LED_STATE.signal(state);
+        }
+    }
+}
+
+#[embassy_executor::task]
+async fn encoder_read() {
+    loop {
+        ENCODER_DUE.wait().await;
+        SPI1.lock(|spi| spi.borrow_mut().as_mut().map(pipeline::encoder_begin_read));
+    }
+}
+
+#[embassy_executor::task]
+async fn led(mut status_led: status_led::StatusLed) {
+    let mut indicator = StatusIndicator::new();
+    loop {
+        let color = indicator.tick(LED_STATE.wait().await, LED_UPDATE_MS);
+        status_led.set(color.red, color.green, color.blue);
+    }
+}
+
+/// Background work, yields to the other thread mode tasks after every pass
+#[embassy_executor::task]
+async fn background(mut background: Background) {
+    loop {
+        background.run();
+        yield_now().await;
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    PWM_STAGE.lock(|stage| {
+        let mut stage = stage.borrow_mut();
+        let Some(stage) = stage.as_mut() else {
+            return;
+        };
+        // Alternate between PWM and encoder reading
+        match stage.sequencer.next(&mut stage.timer_pwm) {
+            Stage::Output => {
+                let (angle, check) = SPI1.lock(|spi| {
+                    spi.borrow_mut()
+                        .as_mut()
+                        .map_or((0, 0), |s| (s.get_angle(), s.get_check()))
+                });
+                if pipeline::output_stage(&mut stage.timer_pwm, angle, check) {
+                    CONTROL_DUE.signal(());
+                }
+            }
+            Stage::Sample => {
+                pipeline::sample_stage(&mut stage.adc1);
+                ENCODER_DUE.signal(());
+            }
+        }
+    });
+}
+
+#[interrupt]
+fn DMA1_CH2() {
+    SPI1.lock(|spi| spi.borrow_mut().as_mut().map(pipeline::encoder_end_read));
+}
+
+#[interrupt]
+fn DMA1_CH1() {
+    DMA.lock(|dma| dma.borrow_mut().as_mut().map(pipeline::adc_end_read));
+}
+
+#[cfg(feature = "can")]
+#[interrupt]
+fn FDCAN1_INTR0_IT() {
+    tunepulse_app::can::receive();
+}
+
+#[cfg(feature = "usb")]
+#[interrupt]
+fn USB_LP() {
+    tunepulse_app::usb::poll();
+}
+
+#[interrupt]
+unsafe fn TIM7() {
+    EXECUTOR_CONTROL.on_interrupt();
+}
+
+#[defmt::panic_handler]
+fn panic() -> ! {
+    cortex_m::asm::udf()
+}
@@ -0,0 +1,142 @@
+// Implements the hardware bring-up of the CLN17 board.
+
+// Key Features:
+// - Configures clocks, PWM timer, encoder SPI, ADC and DMA routing.
+// - Creates the motor controller with the board identification and the stored parameters.
+// - Returns all peripherals in one struct for the executor to distribute.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use hal::{
+    adc::{Adc, AdcDevice, AdcInterrupt, Align, InputType, SampleTime},
+    clocks::Clocks,
+    dma,
+    dma::{Dma, DmaChannel, DmaInput, DmaPeriph},
+    pac,
+    pac::{ADC1, DMA1},
+};
+
+use tunepulse_algo::{
+    device_info::BoardVariant,
+    factory_data::{FactoryData, FACTORY_IMAGE_SIZE},
+    motor_driver::{MotorType, PhasePattern},
+    params::{
+        storage::PARAM_IMAGE_SIZE,
+        writer::{self, FlashOps},
+    },
+    setpoint::Setpoint,
+    MotorController,
+};
+use tunepulse_drivers::{bridge, device_id, encoder_spi, flash, gpio_io, pwm, status_led};
+
+use crate::background::ConfigFlash;
+use crate::pipeline::{ADC1_SEQUENCE, SAMPLING_COUNT};
+
+/// Control loop frequency (Hz)
+pub const PWM_FREQUENCY: u16 = 20000;
+
+const MAX_SUP_VLTG: i32 = 69000;
+const RESISTANE: i32 = 2000;
+
+/// Example control current
+const CURRENT: i32 = 400;
+
+/// Peripherals and controller of the board
+pub struct Board {
+    pub timer_pwm: pwm::TimPWM,
+    pub spi1: encoder_spi::Spi1DMA,
+    pub adc1: Adc<ADC1>,
+    pub dma1: Dma<DMA1>,
+    pub gpio_io: gpio_io::GpioIo,
+    pub status_led: status_led::StatusLed,
+    pub bridge: bridge::BridgeEnable,
+    pub config: ConfigFlash,
+    pub motor: MotorController,
+}
+
+/// Initializes the board, the PWM timer is running when this returns
+pub fn init(dp: pac::Peripherals) -> Board {
+    let clock_cfg = Clocks::default();
+    clock_cfg.setup().unwrap();
+
+    let freq = PWM_FREQUENCY;
+    let sysclk_freq = clock_cfg.sysclk(); // System clock frequency in Hz
+    defmt::debug!("SYSTEM: Clock frequency is {} MHz", sysclk_freq / 1000000);
+    let bridge = bridge::BridgeEnable::new();
+
+    let mut timer_pwm = pwm::TimPWM::new(dp.TIM2, &clock_cfg, freq);
+    timer_pwm.begin();
+    let mut motor = MotorController::new(
+        MotorType::STEP,
+        PhasePattern::ABCD,
+        freq,
+        MAX_SUP_VLTG,
+        RESISTANE,
+    );
+    motor.set_hardware_id(BoardVariant::Cln17, device_id::uid());
+    motor.device_info().log();
+
+    // Restore the saved configuration, defaults are kept if none is stored
+    let mut config = ConfigFlash::new(flash::Flash::new(dp.FLASH));
+    let mut image = [0; PARAM_IMAGE_SIZE];
+    let len = writer::load(&config, &mut image).unwrap_or(0);
+    motor.load_params(&image[..len]).ok();
+    motor.set_setpoint(Setpoint::Current(CURRENT));
+
+    // Factory data has its own area, parameter saves and restores never touch it
+    let mut factory = [0; FACTORY_IMAGE_SIZE];
+    config.factory().read(0, &mut factory).ok();
+    match FactoryData::decode(&factory) {
+        Some(data) => {
+            data.log();
+            motor.set_factory_data(data);
+        }
+        None => defmt::warn!("FACTORY: No factory data stored"),
+    }
+
+    let spi1 = encoder_spi::Spi1DMA::new(dp.SPI1);
+    let gpio_io = gpio_io::GpioIo::new();
+    #[cfg(feature = "step-dir")]
+    let gpio_io = crate::step_dir::start(dp.TIM4, gpio_io, &motor);
+    #[cfg(feature = "can")]
+    crate::can::start(dp.FDCAN1, &clock_cfg, &motor);
+    #[cfg(feature = "usb")]
+    crate::usb::start(dp.USB);
+    let status_led = status_led::StatusLed::new();
+
+    let dma1 = Dma::new(dp.DMA1);
+    dma::enable_mux1();
+    dma::mux(DmaPeriph::Dma1, DmaChannel::C3, DmaInput::Spi1Tx);
+    dma::mux(DmaPeriph::Dma1, DmaChannel::C2, DmaInput::Spi1Rx);
+    dma::mux(DmaPeriph::Dma1, DmaChannel::C1, DmaInput::Adc1);
+
+    let mut adc1 = Adc::new_adc1(
+        dp.ADC1,
+        AdcDevice::One,
+        Default::default(),
+        clock_cfg.systick(),
+    );
+
+    for (i, channel) in ADC1_SEQUENCE.iter().enumerate() {
+        adc1.set_sequence(*channel, i as u8 + 1);
+        adc1.set_input_type(*channel, InputType::SingleEnded);
+        adc1.set_sample_time(*channel, SampleTime::T2);
+    }
+    adc1.set_sequence_len(SAMPLING_COUNT as u8);
+
+    adc1.set_align(Align::Left);
+    adc1.enable_interrupt(AdcInterrupt::EndOfSequence);
+
+    Board {
+        timer_pwm,
+        spi1,
+        adc1,
+        dma1,
+        gpio_io,
+        status_led,
+        bridge,
+        config,
+        motor,
+    }
+}
@@ -0,0 +1,260 @@
+// Implements the CAN link of the firmware (`can` feature): host requests and periodic
+// telemetry of the protocol over FDCAN1, or a CANopen CiA 402 slave.
+
+// Key Features:
+// - Frames are taken from the receive FIFO by its interrupt and queued for the background task.
+// - Requests are decoded and their replies sent by the background task, the control task only
+//   applies them.
+// - Replies, motion events, odometry and electrical telemetry and status reports are sent
+//   with the identifiers of the node (see `tunepulse_algo::protocol::can`).
+// - With `can_protocol` set to CANopen the frames go to the CiA 402 slave of the controller
+//   (see `tunepulse_algo::protocol::canopen`) instead.
+// - Node number follows the `can_node` parameter at runtime, bus-off recovers on its own.
+
+// Detailed Operation:
+// `start()` takes FDCAN1 during initialization and joins the bus with the node number and the
+// bit rate of the parameters. `receive()` belongs to the FDCAN1 interrupt line 0 and moves the
+// frames from the 3 frame hardware FIFO into a queue, it runs at the priority of the control
+// task and never preempts it. `CanRequests` runs in the background scheduler: it takes the
+// oldest frame, decodes the request and hands it to the control task through `REQUESTS`, a
+// frame that isn't a valid request is answered right there. After every control tick
+// `service()` applies the handed over request and returns the reply, which the background task
+// sends as soon as the transmit FIFO has room. The next request waits until the reply left, so
+// the replies keep the order of the requests. The background task touches the link only inside
+// short critical sections, the interrupt and the control task can't preempt it meanwhile.
+// After the request `service()` sends the pending events, telemetry and status reports in this
+// order, the periodic frames simply wait or are replaced by newer ones.
+// A request arriving with a full queue is dropped and logged, the host sees a missing reply.
+// The protocol is chosen at startup. Running CANopen the acceptance filters pass NMT, SYNC and
+// the frames of the node, they are handed over undecoded: the slave lives in the controller and
+// its object dictionary follows the drive. `service()` then lets the slave send its SDO
+// replies, emergency messages, PDOs and heartbeat.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr::addr_of_mut;
+
+use hal::{clocks::Clocks, pac::FDCAN1};
+
+use tunepulse_algo::{
+    protocol::{
+        can::{self, CanProtocol},
+        canopen::{self, CanTransport},
+        commands::{self, ReplyResult, Request},
+        Frame, Transport,
+    },
+    scheduler::{BackgroundTask, Budget, Poll},
+    MotorController,
+};
+use tunepulse_drivers::can::{CanBus, CanFrame, MAX_DATA};
+
+use crate::background::Exchange;
+
+/// Frames waiting for the background task
+const REQUEST_QUEUE: usize = 4;
+
+/// Request handed over to the control task
+enum CanRequest {
+    Host(Request),     // Decoded request of the protocol
+    CanOpen(CanFrame), // Frame for the CANopen slave
+}
+
+/// Requests applied by the control task, answered with the reply of the protocol
+static REQUESTS: Exchange<CanRequest, Option<Frame>> = Exchange::new();
+
+/// Bus with the node number it is filtered for
+struct CanLink {
+    bus: CanBus,
+    protocol: CanProtocol,               // Protocol chosen at startup
+    node: u8,                            // Node number of the filters and the identifiers
+    requests: [CanFrame; REQUEST_QUEUE], // Received frames, oldest at `head`
+    head: usize,                         // Index of the oldest frame
+    len: usize,                          // Number of queued frames
+}
+
+impl CanLink {
+    /// Takes the oldest queued frame
+    fn pop(&mut self) -> Option<CanFrame> {
+        if self.len == 0 {
+            return None;
+        }
+        let frame = self.requests[self.head];
+        self.head = (self.head + 1) % REQUEST_QUEUE;
+        self.len -= 1;
+        Some(frame)
+    }
+}
+
+impl Transport for CanLink {
+    fn send(&mut self, frame: &Frame) -> bool {
+        self.bus
+            .transmit(can::frame_identifier(frame, self.node), frame)
+    }
+}
+
+impl CanTransport for CanLink {
+    fn transmit(&mut self, id: u16, data: &[u8]) -> bool {
+        self.bus.transmit(id, data)
+    }
+}
+
+/// Runs `f` with the acceptance filters of a node
+fn with_filters<R>(protocol: CanProtocol, node: u8, f: impl FnOnce(&[(u16, u16)]) -> R) -> R {
+    match protocol {
+        CanProtocol::TunePulse => f(&[can::request_filter(node)]),
+        CanProtocol::CanOpen => f(&canopen::filters(node)),
+    }
+}
+
+static mut CAN: Option<CanLink> = None;
+
+/// Joins the bus, call during initialization before the control task and the receive interrupt
+pub fn start(fdcan: FDCAN1, clock_cfg: &Clocks, motor: &MotorController) {
+    let (node, bitrate, protocol) = motor.can_config();
+    let bus = with_filters(protocol, node, |filters| {
+        CanBus::new(fdcan, clock_cfg, bitrate * 1000, filters)
+    });
+    let canopen = protocol == CanProtocol::CanOpen;
+    defmt::info!(
+        "CAN: Node {} at {} kbit/s, CANopen {}",
+        node,
+        bitrate,
+        canopen
+    );
+    // SAFETY: written once before the users of the link are started
+    unsafe {
+        *addr_of_mut!(CAN) = Some(CanLink {
+            bus,
+            protocol,
+            node,
+            requests: [CanFrame {
+                id: 0,
+                len: 0,
+                data: [0; MAX_DATA],
+            }; REQUEST_QUEUE],
+            head: 0,
+            len: 0,
+        });
+    }
+}
+
+/// Queues the received frames, bound to the FDCAN1 interrupt line 0
+pub fn receive() {
+    // SAFETY: the interrupt and the control task run at the same priority, neither preempts
+    // the other while accessing the link
+    let Some(link) = (unsafe { (*addr_of_mut!(CAN)).as_mut() }) else {
+        return;
+    };
+    if link.bus.clear_interrupts() {
+        defmt::warn!("CAN: Receive FIFO overrun");
+    }
+    while let Some(received) = link.bus.receive() {
+        if link.len == REQUEST_QUEUE {
+            defmt::warn!("CAN: Request queue full, request dropped");
+            continue;
+        }
+        link.requests[(link.head + link.len) % REQUEST_QUEUE] = received;
+        link.len += 1;
+    }
+}
+
+/// Runs `f` with the link inside a critical section, `None` before `start()`
+fn with_link<R>(f: impl FnOnce(&mut CanLink) -> R) -> Option<R> {
+    cortex_m::interrupt::free(|_| {
+        // SAFETY: neither the interrupt nor the control task runs inside the critical section
+        unsafe { (*addr_of_mut!(CAN)).as_mut() }.map(f)
+    })
+}
+
+/// Applies the request handed over by the background task and sends the pending frames, call
+/// from the control task after the tick
+pub fn service(motor: &mut MotorController) {
+    // SAFETY: the interrupt and the control task run at the same priority, neither preempts
+    // the other while accessing the link, the background task only accesses it inside
+    // critical sections
+    let Some(link) = (unsafe { (*addr_of_mut!(CAN)).as_mut() }) else {
+        return;
+    };
+    if link.bus.recover() {
+        defmt::warn!("CAN: Bus-off, recovering");
+    }
+    let (node, _, _) = motor.can_config();
+    if node != link.node {
+        with_filters(link.protocol, node, |filters| link.bus.set_filters(filters));
+        link.node = node;
+        defmt::info!("CAN: Node {}", node);
+    }
+
+    REQUESTS.serve(|request| match request {
+        CanRequest::Host(request) => Some(motor.apply_request(request)),
+        CanRequest::CanOpen(frame) => {
+            motor.canopen_receive(frame.id, frame.data());
+            None
+        }
+    });
+    if link.protocol == CanProtocol::CanOpen {
+        motor.flush_canopen(link);
+        return;
+    }
+    motor.flush_events(link);
+    motor.flush_odometry(link);
+    motor.flush_status(link);
+}
+
+/// Decodes the queued frames and sends the replies, a task of the background scheduler
+pub struct CanRequests {
+    reply: Option<Frame>, // Reply waiting for room in the transmit FIFO
+}
+
+impl CanRequests {
+    pub const fn new() -> Self {
+        Self { reply: None }
+    }
+}
+
+impl Default for CanRequests {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundTask for CanRequests {
+    fn poll(&mut self, _budget: &Budget) -> Poll {
+        if let Some(reply) = REQUESTS.take_answer() {
+            self.reply = reply;
+        }
+        if let Some(reply) = self.reply {
+            if !with_link(|link| link.send(&reply)).unwrap_or(true) {
+                return Poll::Pending;
+            }
+            self.reply = None;
+        }
+        if !REQUESTS.is_free() {
+            return Poll::Idle; // Answered after the next control tick
+        }
+        let Some((protocol, received)) = with_link(|link| (link.protocol, link.pop())) else {
+            return Poll::Idle;
+        };
+        let Some(received) = received else {
+            return Poll::Idle;
+        };
+        let request = match protocol {
+            CanProtocol::CanOpen => CanRequest::CanOpen(received),
+            CanProtocol::TunePulse => {
+                let frame = can::frame_from_data(received.data());
+                match Request::decode(&frame) {
+                    Some(request) => CanRequest::Host(request),
+                    None => {
+                        self.reply =
+                            Some(commands::command_reply(frame[1], ReplyResult::InvalidFrame));
+                        return Poll::Pending;
+                    }
+                }
+            }
+        };
+        // Free as checked above, only this task hands requests over
+        let _ = REQUESTS.request(request);
+        Poll::Idle
+    }
+}
@@ -0,0 +1,24 @@
+// Implements the board bring-up and the control pipeline shared by the firmware entry points.
+
+// Key Features:
+// - Executor independent: used by the RTIC application (main.rs) and the Embassy one
+//   (bin/embassy.rs, `embassy` feature), so both run the same pipeline.
+// - Each entry point only binds the pipeline stages to its interrupts and tasks and runs the
+//   background scheduler in its idle context.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#![no_std]
+
+pub mod background;
+pub mod board;
+#[cfg(feature = "can")]
+pub mod can;
+pub mod pipeline;
+#[cfg(feature = "step-dir")]
+pub mod step_dir;
+#[cfg(feature = "telemetry-swo")]
+pub mod telemetry;
+#[cfg(feature = "usb")]
+pub mod usb;
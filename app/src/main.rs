@@ -4,46 +4,28 @@
 use defmt_rtt as _;
 use panic_probe as _;
 
-use hal::{
-    self,
-    adc::{Adc, AdcDevice, AdcInterrupt, Align, InputType, SampleTime},
-    clocks::Clocks,
-    dma,
-    dma::{Dma, DmaChannel, DmaInput, DmaInterrupt, DmaPeriph},
-    pac,
-    pac::{ADC1, DMA1},
-    timer::TimerInterrupt,
-};
+use hal::{self, pac};
 
 // Import custom modules from tunepulse_rs crate
 use tunepulse_algo::{
-    inputs_dump::{DataInputsBit, InputsDump},
-    motor_driver::{MotorType, PhasePattern},
+    indication::{IndicationState, StatusIndicator},
     MotorController,
 };
-
-use cortex_m;
-
-const MANDATORY_FIELDS: u32 = DataInputsBit::SUPPLY as u32 | DataInputsBit::ANGLE as u32;
-static mut TELEMETRY: InputsDump<MANDATORY_FIELDS> = InputsDump::new();
-static mut PWM: [i16; 4] = [0; 4];
-
-static mut SPI_READ_BUF: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
-const SPI_WRITE_BUF: [u8; 4] = [0x80, 0x20, 0x00, 0x00];
-
-const I_CH1: u8 = 4;
-const I_CH2: u8 = 15;
-const VSENS: u8 = 3;
-
-const SAMPLING_COUNT: usize = 3;
-const ADC1_SEQUENCE: [u8; SAMPLING_COUNT] = [I_CH1, I_CH2, VSENS];
-
-static mut ADC_READ_BUF: [u16; SAMPLING_COUNT] = [0; SAMPLING_COUNT];
+use tunepulse_app::{
+    background::{self, Background},
+    board,
+    pipeline::{self, ControlTask, PeriodSequencer, Stage, LED_UPDATE_MS},
+};
 
 #[rtic::app(device = pac, peripherals = true, dispatchers = [TIM7])]
 mod app {
     use super::*;
 
+    use hal::{
+        adc::Adc,
+        dma::Dma,
+        pac::{ADC1, DMA1},
+    };
     use tunepulse_drivers::*;
 
     #[shared]
@@ -54,175 +36,117 @@ mod app {
     #[local]
     struct Local {
         timer_pwm: pwm::TimPWM,
-        underflow: bool,
+        sequencer: PeriodSequencer,
         motor: MotorController,
+        gpio_io: gpio_io::GpioIo,
+        bridge: bridge::BridgeEnable,
+        status_led: status_led::StatusLed,
+        background: Background,
         dma1: Dma<DMA1>,
         adc1: Adc<ADC1>,
     }
 
     #[init]
     fn init(ctx: init::Context) -> (Shared, Local) {
-        let dp = ctx.device;
-        let clock_cfg = Clocks::default();
-        clock_cfg.setup().unwrap();
-
-        let freq = 20000;
-        let sysclk_freq = clock_cfg.sysclk(); // System clock frequency in Hz
-        defmt::debug!("SYSTEM: Clock frequency is {} MHz", sysclk_freq / 1000000);
-        init_driver_pins();
-
-        let mut timer_pwm = pwm::TimPWM::new(dp.TIM2, &clock_cfg, freq);
-        timer_pwm.begin();
-        const MAX_SUP_VLTG: i32 = 69000;
-        const RESISTANE: i32 = 2000;
-        let motor = MotorController::new(
-            MotorType::STEP,
-            PhasePattern::ABCD,
-            freq,
-            MAX_SUP_VLTG,
-            RESISTANE,
-        );
-
-        let spi1 = encoder_spi::Spi1DMA::new(dp.SPI1);
-
-        let dma1 = Dma::new(dp.DMA1);
-        dma::enable_mux1();
-        dma::mux(DmaPeriph::Dma1, DmaChannel::C3, DmaInput::Spi1Tx);
-        dma::mux(DmaPeriph::Dma1, DmaChannel::C2, DmaInput::Spi1Rx);
-        dma::mux(DmaPeriph::Dma1, DmaChannel::C1, DmaInput::Adc1);
-
-        let mut adc1 = Adc::new_adc1(
-            dp.ADC1,
-            AdcDevice::One,
-            Default::default(),
-            clock_cfg.systick(),
-        );
-
-        for i in 0..SAMPLING_COUNT {
-            adc1.set_sequence(ADC1_SEQUENCE[i], i as u8 + 1);
-            adc1.set_input_type(ADC1_SEQUENCE[i], InputType::SingleEnded);
-            adc1.set_sample_time(ADC1_SEQUENCE[i], SampleTime::T2);
-        }
-        adc1.set_sequence_len(SAMPLING_COUNT as u8);
-
-        adc1.set_align(Align::Left);
-        adc1.enable_interrupt(AdcInterrupt::EndOfSequence);
+        let mut core = ctx.core;
+        background::enable_cycle_counter(&mut core.DCB, &mut core.DWT);
+        #[cfg(feature = "telemetry-swo")]
+        tunepulse_app::telemetry::start(core.ITM);
+        let board = board::init(ctx.device);
 
         (
-            Shared { spi1 },
+            Shared { spi1: board.spi1 },
             Local {
-                adc1,
-                timer_pwm,
-                underflow: true,
-                motor,
-                dma1,
+                adc1: board.adc1,
+                timer_pwm: board.timer_pwm,
+                sequencer: PeriodSequencer::new(),
+                motor: board.motor,
+                gpio_io: board.gpio_io,
+                bridge: board.bridge,
+                status_led: board.status_led,
+                background: Background::new(board.config),
+                dma1: board.dma1,
             },
         )
     }
 
-    fn init_driver_pins() {
-        let mut dr_reset = pinout::driver::RESET.init();
-        dr_reset.set_high();
-
-        let mut dr_en = pinout::driver::ENABLE.init();
-        dr_en.set_high();
+    // Background work in the time left by the control loop
+    #[idle(local = [background])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {
+            if !cx.local.background.run() {
+                cortex_m::asm::wfi();
+            }
+        }
     }
 
-    #[task(binds = TIM2, shared = [spi1], local = [timer_pwm, underflow, adc1])]
+    #[task(binds = TIM2, shared = [spi1], local = [timer_pwm, sequencer, adc1])]
     fn tim2_period_elapsed(mut cx: tim2_period_elapsed::Context) {
-        // Clear the update interrupt flag
-        cx.local
-            .timer_pwm
-            .get_timer()
-            .clear_interrupt(TimerInterrupt::Update);
-
-        // Toggle the underflow flag
-        *cx.local.underflow = !*cx.local.underflow;
-
         // Alternate between PWM and encoder reading
-        if *cx.local.underflow {
-            cx.local.timer_pwm.apply_pwm(unsafe { PWM });
-            let adc_sup_voltage = unsafe { ADC_READ_BUF[2] };
-
-            // Get encoder angle
-            let pos: u16 = cx.shared.spi1.lock(|spi1| spi1.get_angle());
-            unsafe { TELEMETRY.set_angle_raw(pos) };
-            unsafe { TELEMETRY.set_supply_adc(adc_sup_voltage) };
-
-            // Instead of calling motor.tick() directly, spawn the new task:
-            unsafe {
-                if TELEMETRY.is_updated() == true {
+        match cx.local.sequencer.next(cx.local.timer_pwm) {
+            Stage::Output => {
+                // Get encoder angle
+                let (pos, check) = cx
+                    .shared
+                    .spi1
+                    .lock(|spi1| (spi1.get_angle(), spi1.get_check()));
+                if pipeline::output_stage(cx.local.timer_pwm, pos, check) {
                     motor_tick_cmd::spawn().ok();
                 }
             }
-        } else {
-            // Start ADC DMA reading
-            unsafe {
-                cx.local.adc1.read_dma(
-                    &mut ADC_READ_BUF,
-                    &ADC1_SEQUENCE,
-                    DmaChannel::C1,
-                    Default::default(),
-                    DmaPeriph::Dma1,
-                )
-            };
-
-            // Start SPI encoder read
-            encoder_begin_read::spawn().expect("Failed to spawn encoder_begin_read");
+            Stage::Sample => {
+                pipeline::sample_stage(cx.local.adc1);
+                // Start SPI encoder read
+                encoder_begin_read::spawn().expect("Failed to spawn encoder_begin_read");
+            }
         }
     }
 
-    // New task (command) with priority 1 that calls motor.tick():
-    #[task(priority = 1, local = [motor])]
+    #[task(priority = 1, local = [motor, gpio_io, bridge, control: ControlTask = ControlTask::new()])]
     async fn motor_tick_cmd(cx: motor_tick_cmd::Context) {
-        // Example control voltage
-        let current = 400;
-        // Safely retrieve TELEMETRY data and call motor.tick()
-        let data = unsafe { TELEMETRY.get_data() };
-        let pwm = cx.local.motor.tick(current, data);
-        unsafe { PWM = pwm };
+        let local = cx.local;
+        if let Some(state) = local.control.tick(local.motor, local.gpio_io, local.bridge) {
+            led_update::spawn(state).ok();
+        }
+    }
+
+    #[task(priority = 1, local = [status_led, indicator: StatusIndicator = StatusIndicator::new()])]
+    async fn led_update(cx: led_update::Context, state: IndicationState) {
+        let color = cx.local.indicator.tick(state, LED_UPDATE_MS);
+        cx.local.status_led.set(color.red, color.green, color.blue);
     }
 
     #[task(priority = 1, shared = [spi1])]
     async fn encoder_begin_read(mut cx: encoder_begin_read::Context) {
-        cx.shared.spi1.lock(|spi1| unsafe {
-            spi1.start();
-            spi1.get_spi().transfer_dma(
-                &SPI_WRITE_BUF,
-                &mut SPI_READ_BUF,
-                DmaChannel::C3,
-                DmaChannel::C2,
-                Default::default(),
-                Default::default(),
-                DmaPeriph::Dma1,
-            );
-        });
+        cx.shared.spi1.lock(pipeline::encoder_begin_read);
     }
 
     #[task(binds = DMA1_CH2, shared = [spi1], priority = 1)]
     fn encoder_end_read(mut cx: encoder_end_read::Context) {
-        dma::clear_interrupt(
-            DmaPeriph::Dma1,
-            DmaChannel::C2,
-            DmaInterrupt::TransferComplete,
-        );
-        cx.shared.spi1.lock(|spi1| {
-            spi1.get_spi()
-                .stop_dma(DmaChannel::C3, Some(DmaChannel::C2), DmaPeriph::Dma1);
-            spi1.get_spi()
-                .cleanup_dma(DmaPeriph::Dma1, DmaChannel::C3, Some(DmaChannel::C2));
-            spi1.end(unsafe { SPI_READ_BUF });
-        });
+        cx.shared.spi1.lock(pipeline::encoder_end_read);
     }
 
     #[task(binds = DMA1_CH1, local = [dma1], priority = 1)]
     fn adc_end_read(cx: adc_end_read::Context) {
-        dma::clear_interrupt(
-            DmaPeriph::Dma1,
-            DmaChannel::C1,
-            DmaInterrupt::TransferComplete,
-        );
-        cx.local.dma1.stop(DmaChannel::C1);
+        pipeline::adc_end_read(cx.local.dma1);
+    }
+
+    // Host requests received over CAN, decoded in idle and applied by the control task after
+    // its tick. Without the `can` feature the peripheral stays off and never raises the
+    // interrupt.
+    #[task(binds = FDCAN1_INTR0_IT, priority = 1)]
+    fn can_receive(_: can_receive::Context) {
+        #[cfg(feature = "can")]
+        tunepulse_app::can::receive();
+    }
+
+    // Enumeration and packet transfers of the USB console, whose lines are parsed in idle and
+    // executed by the control task after its tick. Without the `usb` feature the peripheral
+    // stays off.
+    #[task(binds = USB_LP, priority = 1)]
+    fn usb_poll(_: usb_poll::Context) {
+        #[cfg(feature = "usb")]
+        tunepulse_app::usb::poll();
     }
 }
 
@@ -0,0 +1,328 @@
+// Implements the stages of the control period, independent of the executor running them.
+
+// Key Features:
+// - Alternates output and sampling stages on the center aligned PWM timer interrupt.
+// - Collects encoder and ADC results through DMA into the double buffered input dump.
+// - Runs the controller tick, spare pins and LED indication rate division.
+// - Streams the scope samples over SWO after the tick (`telemetry-swo` feature).
+// - Applies the host requests received over CAN after the tick (`can` feature), decoding and
+//   replies are left to the background task.
+// - Executes the commands typed on the USB console after the tick (`usb` feature), parsing
+//   and answers are left to the background task.
+// - Blanks current samples taken next to a switching edge of the duties active at the time.
+
+// Detailed Operation:
+// The PWM timer interrupt fires twice per period. `PeriodSequencer::next()` tells which
+// stage to run:
+// - `Stage::Output`: `output_stage()` applies the last computed PWM and stores the encoder
+//   angle and supply voltage. When all mandatory inputs are collected the executor has to
+//   run `ControlTask::tick()` at a lower priority.
+// - `Stage::Sample`: `sample_stage()` starts the ADC DMA sequence, the executor then runs
+//   `encoder_begin_read()` at a lower priority. `encoder_end_read()` and `adc_end_read()`
+//   belong to the DMA transfer complete interrupts. With the encoder read pair check
+//   configured by the controller `encoder_end_read()` starts the second read, and the
+//   repeated pair of the EMC profile, itself.
+// The currents are sampled around the counter peak with the duties the output stage applied
+// at the valley before, the next output stage checks them against the blanking window of the
+// controller before the new duties are applied and keeps the last clean sample otherwise.
+// DMA buffers, the PWM command and the input dump are statics owned by this module, the
+// executor guarantees the stages don't preempt each other on the same resource (RTIC
+// priorities, Embassy interrupt priorities).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, AtomicI16, AtomicU16, AtomicU32, Ordering};
+
+use cortex_m::peripheral::DWT;
+use hal::{
+    adc::Adc,
+    dma,
+    dma::{Dma, DmaChannel, DmaInterrupt, DmaPeriph},
+    pac::{ADC1, DMA1},
+    timer::TimerInterrupt,
+};
+
+use tunepulse_algo::{
+    analog::current_blanking,
+    indication::IndicationState,
+    inputs_dump::{DataInputsBit, InputsDump},
+    MotorController,
+};
+use tunepulse_drivers::{bridge, encoder_spi, gpio_io, pwm};
+
+use crate::background::{self, TICK_STATS};
+use crate::board::PWM_FREQUENCY;
+
+const MANDATORY_FIELDS: u32 = DataInputsBit::SUPPLY as u32 | DataInputsBit::ANGLE as u32;
+static mut TELEMETRY: InputsDump<MANDATORY_FIELDS> = InputsDump::new();
+static mut PWM: [i16; 4] = [0; 4];
+/// Duties applied by the last output stage, active during the following current sample
+static mut APPLIED: [i16; 4] = [0; 4];
+/// Last clean current sample, held while samples are blanked
+static mut CURRENTS: [u16; 4] = [0; 4];
+/// Current sample instant relative to the PWM period center (i1.15 period)
+static CURRENT_PHASE: AtomicI16 = AtomicI16::new(0);
+/// Current sample blanking window set by the control task (i1.15 period, 0 - off)
+static BLANKING_WINDOW: AtomicI16 = AtomicI16::new(0);
+/// Current samples blanked since the last control tick
+static BLANKED_SAMPLES: AtomicU32 = AtomicU32::new(0);
+
+static mut SPI_READ_BUF: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+/// Encoder sample instant of the last read relative to the PWM period center (i1.15 period)
+static SAMPLE_PHASE: AtomicI16 = AtomicI16::new(0);
+/// Encoder read pair check set by the control task: threshold (0 - single read) and retry
+static ENCODER_THRESHOLD: AtomicU16 = AtomicU16::new(0);
+static ENCODER_RETRY: AtomicBool = AtomicBool::new(false);
+/// Encoder read pairs repeated since the last control tick
+static ENCODER_RETRIES: AtomicU32 = AtomicU32::new(0);
+const SPI_WRITE_BUF: [u8; 4] = encoder_spi::READ_COMMAND;
+
+const I_CH1: u8 = 4;
+const I_CH2: u8 = 15;
+const VSENS: u8 = 3;
+
+pub const SAMPLING_COUNT: usize = 3;
+pub const ADC1_SEQUENCE: [u8; SAMPLING_COUNT] = [I_CH1, I_CH2, VSENS];
+
+static mut ADC_READ_BUF: [u16; SAMPLING_COUNT] = [0; SAMPLING_COUNT];
+
+/// Period of the status LED update
+pub const LED_UPDATE_MS: u32 = 10;
+const LED_UPDATE_TICKS: u32 = PWM_FREQUENCY as u32 * LED_UPDATE_MS / 1000; // Motor ticks between LED updates
+
+/// Work of the current half of the PWM period
+pub enum Stage {
+    /// Apply PWM and store the sampled inputs
+    Output,
+    /// Start ADC and encoder acquisition
+    Sample,
+}
+
+/// Selects the stage from the half of the PWM period
+pub struct PeriodSequencer;
+
+impl PeriodSequencer {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Acknowledges the PWM timer interrupt and returns the stage to run
+    pub fn next(&mut self, timer_pwm: &mut pwm::TimPWM) -> Stage {
+        timer_pwm
+            .get_timer()
+            .clear_interrupt(TimerInterrupt::Update);
+
+        // Follow the counter direction instead of toggling, interrupts merged during a bus
+        // stall (flash write) would swap the stages otherwise
+        if timer_pwm.is_counting_up() {
+            Stage::Output
+        } else {
+            Stage::Sample
+        }
+    }
+}
+
+impl Default for PeriodSequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Applies the PWM and stores the inputs, returns true when the controller tick is due
+///
+/// # Arguments
+/// * `angle` - Encoder angle of the last completed read
+/// * `check` - Second read of the same sample, equal to `angle` with single reads
+pub fn output_stage(timer_pwm: &mut pwm::TimPWM, angle: u16, check: u16) -> bool {
+    // SAFETY: the PWM timer interrupt is the only reader, the control task the only writer
+    // of these statics and neither preempts the other in the middle of an access
+    unsafe {
+        let sample = *addr_of!(ADC_READ_BUF);
+        let window = BLANKING_WINDOW.load(Ordering::Relaxed);
+        let phase = CURRENT_PHASE.load(Ordering::Relaxed);
+        if current_blanking::is_clear(window, &*addr_of!(APPLIED), phase) {
+            *addr_of_mut!(CURRENTS) = [sample[0], sample[1], 0, 0];
+        } else {
+            BLANKED_SAMPLES.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let pwm = *addr_of!(PWM);
+        timer_pwm.apply_pwm(pwm);
+        *addr_of_mut!(APPLIED) = pwm;
+        let adc_sup_voltage = sample[2];
+
+        let telemetry = &mut *addr_of_mut!(TELEMETRY);
+        telemetry.set_angle_raw(angle);
+        telemetry.set_angle_check(check);
+        telemetry.set_sample_phase(SAMPLE_PHASE.load(Ordering::Relaxed));
+        telemetry.set_supply_adc(adc_sup_voltage);
+        telemetry.set_current_adc(*addr_of!(CURRENTS));
+        telemetry.is_updated()
+    }
+}
+
+/// Starts the ADC DMA sequence
+pub fn sample_stage(adc1: &mut Adc<ADC1>) {
+    // The current channels convert first, right after this instant
+    CURRENT_PHASE.store(pwm::TimPWM::phase_from_center(), Ordering::Relaxed);
+    // SAFETY: the buffer is only read by the output stage, half a period later
+    unsafe {
+        adc1.read_dma(
+            &mut *addr_of_mut!(ADC_READ_BUF),
+            &ADC1_SEQUENCE,
+            DmaChannel::C1,
+            Default::default(),
+            DmaPeriph::Dma1,
+        )
+    };
+}
+
+/// Starts the encoder SPI DMA transfer
+pub fn encoder_begin_read(spi1: &mut encoder_spi::Spi1DMA) {
+    spi1.set_check(
+        ENCODER_THRESHOLD.load(Ordering::Relaxed),
+        ENCODER_RETRY.load(Ordering::Relaxed),
+    );
+    spi1.start();
+    // The encoder latches the angle on chip select, timestamp it against the modulation
+    SAMPLE_PHASE.store(pwm::TimPWM::phase_from_center(), Ordering::Relaxed);
+    encoder_transfer(spi1);
+}
+
+/// Starts the DMA transfer of one encoder read, chip select already asserted
+fn encoder_transfer(spi1: &mut encoder_spi::Spi1DMA) {
+    // SAFETY: the read buffer is only accessed again after the transfer completed
+    unsafe {
+        spi1.get_spi().transfer_dma(
+            &SPI_WRITE_BUF,
+            &mut *addr_of_mut!(SPI_READ_BUF),
+            DmaChannel::C3,
+            DmaChannel::C2,
+            Default::default(),
+            Default::default(),
+            DmaPeriph::Dma1,
+        );
+    }
+}
+
+/// Completes the encoder transfer, bound to the SPI RX DMA interrupt
+pub fn encoder_end_read(spi1: &mut encoder_spi::Spi1DMA) {
+    dma::clear_interrupt(
+        DmaPeriph::Dma1,
+        DmaChannel::C2,
+        DmaInterrupt::TransferComplete,
+    );
+    spi1.get_spi()
+        .stop_dma(DmaChannel::C3, Some(DmaChannel::C2), DmaPeriph::Dma1);
+    spi1.get_spi()
+        .cleanup_dma(DmaPeriph::Dma1, DmaChannel::C3, Some(DmaChannel::C2));
+    // SAFETY: the transfer is complete
+    match spi1.end(unsafe { *addr_of!(SPI_READ_BUF) }) {
+        encoder_spi::ReadStep::Done => return,
+        encoder_spi::ReadStep::Check => {}
+        encoder_spi::ReadStep::Retry => {
+            ENCODER_RETRIES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    // Next read of the pair, chip select toggled so the encoder latches a new sample
+    spi1.start();
+    encoder_transfer(spi1);
+}
+
+/// Completes the ADC sequence, bound to the ADC DMA interrupt
+pub fn adc_end_read(dma1: &mut Dma<DMA1>) {
+    dma::clear_interrupt(
+        DmaPeriph::Dma1,
+        DmaChannel::C1,
+        DmaInterrupt::TransferComplete,
+    );
+    dma1.stop(DmaChannel::C1);
+}
+
+/// Controller tick with the low rate work derived from it
+pub struct ControlTask {
+    led_ticks: u32,
+}
+
+impl ControlTask {
+    pub const fn new() -> Self {
+        Self { led_ticks: 0 }
+    }
+
+    /// Runs the controller on the collected inputs, returns the state to indicate when the
+    /// status LED is due for an update
+    pub fn tick(
+        &mut self,
+        motor: &mut MotorController,
+        gpio_io: &mut gpio_io::GpioIo,
+        bridge: &mut bridge::BridgeEnable,
+    ) -> Option<IndicationState> {
+        let start = DWT::cycle_count();
+
+        #[cfg(feature = "step-dir")]
+        crate::step_dir::feed(motor);
+
+        // SAFETY: the input dump is double buffered, the PWM command is read by the output
+        // stage which doesn't run in the middle of this write
+        unsafe {
+            let data = (*addr_of_mut!(TELEMETRY)).get_data();
+            *addr_of_mut!(PWM) = motor.tick(data);
+        }
+        bridge.set(motor.bridge_enabled()); // Freewheel fault reaction floats the phases
+        let (threshold, retry) = motor.encoder_check();
+        ENCODER_THRESHOLD.store(threshold, Ordering::Relaxed);
+        ENCODER_RETRY.store(retry, Ordering::Relaxed);
+        motor.count_encoder_retries(ENCODER_RETRIES.swap(0, Ordering::Relaxed));
+        BLANKING_WINDOW.store(motor.current_blanking(), Ordering::Relaxed);
+        motor.count_blanked_samples(BLANKED_SAMPLES.swap(0, Ordering::Relaxed));
+
+        // Update spare pins according to their configured functions
+        gpio_io.set_inputs(motor.io_input_mask());
+        let levels = motor.tick_io(gpio_io.read());
+        gpio_io.write(levels);
+
+        // Flash writes wait for the motor to tolerate missed ticks
+        background::allow_stall(motor.tolerates_stall());
+        if motor.take_save_request() {
+            match background::request_save(motor) {
+                Ok(()) => motor.params_queued(),
+                Err(error) => defmt::error!("PARAMS: Image not stored ({})", error),
+            }
+        }
+        if let Some(result) = background::take_save_result() {
+            motor.params_saved(result);
+        }
+        // The request stays pending until the save task takes the data
+        if let Some(data) = motor.factory_request() {
+            if background::request_factory_write(data) {
+                motor.factory_write_started();
+            }
+        }
+        if let Some(result) = background::take_factory_result() {
+            motor.factory_write_done(result);
+        }
+        #[cfg(feature = "telemetry-swo")]
+        crate::telemetry::stream(motor);
+        #[cfg(feature = "can")]
+        crate::can::service(motor);
+        #[cfg(feature = "usb")]
+        crate::usb::service(motor);
+        TICK_STATS.record(DWT::cycle_count().wrapping_sub(start));
+
+        // Hand the state over to the LED task at a much lower rate
+        self.led_ticks += 1;
+        if self.led_ticks >= LED_UPDATE_TICKS {
+            self.led_ticks = 0;
+            return Some(motor.indication_state());
+        }
+        None
+    }
+}
+
+impl Default for ControlTask {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,67 @@
+// Implements the STEP/DIR input of the drop-in stepper driver variant (`step-dir` feature).
+
+// Key Features:
+// - Counts the STEP/DIR or quadrature pulses on IO0/IO1 with TIM4.
+// - Feeds the count as master position of the electronic gearing every control tick.
+// - Mode follows the `step_input` parameter at runtime.
+
+// Detailed Operation:
+// `start()` takes TIM4 during initialization and reserves IO0 and IO1, the spare pin functions
+// can't use them in this variant. Before every control tick `feed()` reads the counter and
+// passes it to `MotorController::set_master_position()`. The controller engages the gearing
+// once it is ready, the gear ratio sets the motion per step: 65536 divided by the steps per
+// revolution (e.g. numerator 512, denominator 25 for 200 steps with 16 microsteps), a negative
+// numerator reverses the direction. With the input off nothing is fed, the master position can
+// still come over the protocol.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr::addr_of_mut;
+
+use hal::pac::TIM4;
+
+use tunepulse_algo::{math_integer::motion::gearing::StepInput, MotorController};
+use tunepulse_drivers::{
+    gpio_io::GpioIo,
+    pinout,
+    step_dir_input::{InputMode, StepDirInput},
+};
+
+static mut STEP_DIR: Option<StepDirInput> = None;
+
+/// Counting mode of the timer for a step input selection, `None` while it is off
+fn input_mode(input: StepInput) -> Option<InputMode> {
+    match input {
+        StepInput::Off => None,
+        StepInput::StepDir => Some(InputMode::StepDir),
+        StepInput::Quadrature => Some(InputMode::Quadrature),
+    }
+}
+
+/// Takes the timer and the input pins, call during initialization before the control task runs
+pub fn start(tim: TIM4, mut gpio_io: GpioIo, motor: &MotorController) -> GpioIo {
+    let mode = input_mode(motor.step_input()).unwrap_or(InputMode::StepDir);
+    let input = StepDirInput::new(tim, mode);
+    gpio_io.reserve(pinout::step_dir::IO_MASK);
+    // SAFETY: written once before the control task, the only other user, is started
+    unsafe {
+        *addr_of_mut!(STEP_DIR) = Some(input);
+    }
+    gpio_io
+}
+
+/// Feeds the counted position to the gearing, call from the control task before the tick
+pub fn feed(motor: &mut MotorController) {
+    // SAFETY: only the control task accesses the input after `start()`
+    let Some(input) = (unsafe { (*addr_of_mut!(STEP_DIR)).as_mut() }) else {
+        return;
+    };
+    let Some(mode) = input_mode(motor.step_input()) else {
+        return;
+    };
+    if mode != input.mode() {
+        input.set_mode(mode);
+    }
+    motor.set_master_position(input.tick());
+}
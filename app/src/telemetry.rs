@@ -0,0 +1,76 @@
+// Implements the scope telemetry transport over ITM/SWO (`telemetry-swo` feature), for debug
+// probes which read SWO faster or more reliably than RTT.
+
+// Key Features:
+// - Streams the scope samples through the ITM stimulus port `SWO_PORT`.
+// - Same point framing as the byte stream links (UART, USB CDC), the host reuses its deframer.
+// - Non-blocking, the control task only queues points and feeds the port FIFO.
+
+// Detailed Operation:
+// `start()` takes the ITM peripheral during initialization, before the control interrupts run.
+// After every control tick `stream()` hands the captured samples to `SwoLink` through
+// `MotorController::flush_scope()`, which prefixes every point with `TELEMETRY_SYNC` and queues
+// it, then moves as many queued bytes to the stimulus port as its FIFO takes. Samples the queue
+// can't take stay in the scope buffer. The debug probe configures the SWO clock and enables the
+// port when the capture starts (`plotter --swo <chip>`), until then nothing is queued.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr::addr_of_mut;
+
+use cortex_m::peripheral::ITM;
+
+use tunepulse_algo::{
+    protocol::TELEMETRY_SYNC,
+    scope::{TelemetryLink, POINT_SIZE},
+    MotorController,
+};
+use tunepulse_drivers::itm::ItmPort;
+
+/// ITM stimulus port carrying the telemetry, port 0 is left for text output
+pub const SWO_PORT: u8 = 1;
+
+/// Queue size (bytes), holds a few samples of both channels
+const SWO_QUEUE_SIZE: usize = 256;
+
+/// Telemetry link over the ITM stimulus port
+pub struct SwoLink {
+    port: ItmPort<SWO_QUEUE_SIZE>,
+}
+
+impl TelemetryLink for SwoLink {
+    fn send(&mut self, points: &[u8]) -> bool {
+        let count = points.len() / POINT_SIZE;
+        if count * (POINT_SIZE + 1) > self.port.free() || !self.port.is_enabled() {
+            return false;
+        }
+        for point in points.chunks_exact(POINT_SIZE) {
+            self.port.write(&[TELEMETRY_SYNC]);
+            self.port.write(point);
+        }
+        true
+    }
+}
+
+static mut SWO: Option<SwoLink> = None;
+
+/// Takes the ITM peripheral, call during initialization before the control task runs
+pub fn start(itm: ITM) {
+    // SAFETY: written once before the control task, the only other user, is started
+    unsafe {
+        *addr_of_mut!(SWO) = Some(SwoLink {
+            port: ItmPort::new(itm, SWO_PORT),
+        });
+    }
+}
+
+/// Streams the captured scope samples, call from the control task after the tick
+pub fn stream(motor: &mut MotorController) {
+    // SAFETY: only the control task accesses the link after `start()`
+    let Some(link) = (unsafe { (*addr_of_mut!(SWO)).as_mut() }) else {
+        return;
+    };
+    motor.flush_scope(link);
+    link.port.poll();
+}
@@ -0,0 +1,138 @@
+// Implements the USB console of the firmware (`usb` feature): the text console of the controller
+// on a virtual serial port (CDC-ACM) for setup from any terminal, no host tools needed.
+
+// Key Features:
+// - Enumerates as serial port on the USB connector, e.g. /dev/ttyACM0 or COMx.
+// - Commands are typed line by line and answered with one line each (see
+//   `tunepulse_algo::protocol::console`, `help` lists them).
+// - Lines are parsed and answered by the background task, the control task only executes the
+//   commands.
+// - Served next to the binary protocol, both can change the drive at the same time.
+
+// Detailed Operation:
+// `start()` takes the peripheral during initialization and connects to the bus. `poll()`
+// belongs to the USB_LP interrupt and runs the enumeration and the packet transfers, it runs
+// at the priority of the control task and never preempts it. `ConsoleLines` runs in the
+// background scheduler: it takes the received characters into the line buffer, parses a
+// completed line and hands the command to the control task through `COMMANDS`, a line that
+// isn't a valid command is answered right there. After every control tick `service()`
+// executes the handed over command, the background task writes the answer to the transmit
+// buffer and it leaves with the next packets. The remaining characters wait until the answer
+// is written, commands are executed one at a time in the order they were typed. The
+// background task touches the serial port only inside short critical sections, one for each
+// character read and each piece of the answer written, so the interrupt and the control task
+// are held back no longer than a buffer copy. Answers written while no host has the port
+// configured are dropped.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::fmt::{self, Write};
+use core::ptr::addr_of_mut;
+
+use hal::pac::USB;
+
+use tunepulse_algo::{
+    protocol::console::{self, ConsoleCommand, ConsoleReply, LineBuffer},
+    scheduler::{BackgroundTask, Budget, Poll},
+    MotorController,
+};
+use tunepulse_drivers::usb::UsbSerial;
+
+use crate::background::Exchange;
+
+/// Commands executed by the control task, answered with the reply to write
+static COMMANDS: Exchange<ConsoleCommand, ConsoleReply> = Exchange::new();
+
+static mut SERIAL: Option<UsbSerial> = None;
+
+/// Connects to the bus, call during initialization before the control task and the interrupt
+pub fn start(usb: USB) {
+    let serial = UsbSerial::new(usb);
+    defmt::info!("USB: Console connected");
+    // SAFETY: written once before the users of the serial port are started
+    unsafe {
+        *addr_of_mut!(SERIAL) = Some(serial);
+    }
+}
+
+/// Handles the events of the peripheral, bound to the USB_LP interrupt
+pub fn poll() {
+    // SAFETY: the interrupt and the control task run at the same priority, neither preempts
+    // the other, the background task only accesses the serial port inside critical sections
+    let Some(serial) = (unsafe { (*addr_of_mut!(SERIAL)).as_mut() }) else {
+        return;
+    };
+    serial.poll();
+}
+
+/// Executes the command handed over by the background task, call from the control task after
+/// the tick
+pub fn service(motor: &mut MotorController) {
+    COMMANDS.serve(|command| motor.apply_console(command));
+}
+
+/// Runs `f` with the serial port inside a critical section, `None` before `start()`
+fn with_serial<R>(f: impl FnOnce(&mut UsbSerial) -> R) -> Option<R> {
+    cortex_m::interrupt::free(|_| {
+        // SAFETY: neither the interrupt nor the control task runs inside the critical section
+        unsafe { (*addr_of_mut!(SERIAL)).as_mut() }.map(f)
+    })
+}
+
+/// Transmit buffer of the serial port written from the background task
+struct Answer;
+
+impl Write for Answer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        with_serial(|serial| serial.write_str(s)).unwrap_or(Ok(()))
+    }
+}
+
+/// Parses the typed lines and writes the answers, a task of the background scheduler
+pub struct ConsoleLines {
+    line: LineBuffer, // Characters of the current command line
+}
+
+impl ConsoleLines {
+    pub const fn new() -> Self {
+        Self {
+            line: LineBuffer::new(),
+        }
+    }
+}
+
+impl Default for ConsoleLines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundTask for ConsoleLines {
+    fn poll(&mut self, _budget: &Budget) -> Poll {
+        // The answer can't fail, what doesn't fit the transmit buffer is dropped
+        if let Some(reply) = COMMANDS.take_answer() {
+            let _ = console::write_reply(&mut Answer, &reply);
+        }
+        if !COMMANDS.is_free() {
+            return Poll::Idle; // Executed after the next control tick
+        }
+        let mut byte = [0u8; 1];
+        while with_serial(|serial| serial.read(&mut byte)) == Some(1) {
+            let Some(line) = self.line.push(byte[0]) else {
+                continue;
+            };
+            match line.and_then(ConsoleCommand::parse) {
+                // Free as checked above, only this task hands commands over
+                Ok(command) => {
+                    let _ = COMMANDS.request(command);
+                }
+                Err(error) => {
+                    let _ = console::write_result(&mut Answer, Err(error.description()));
+                }
+            }
+            return Poll::Pending;
+        }
+        Poll::Idle
+    }
+}
@@ -1,6 +1,10 @@
 MEMORY
 {
   /* NOTE K = KiBi = 1024 bytes */
-  FLASH : ORIGIN = 0x08000000, LENGTH = 128K
+  FLASH : ORIGIN = 0x08000000, LENGTH = 122K
+  /* Page below the configuration holds the factory data, written once in production */
+  FACTORY : ORIGIN = 0x0801E800, LENGTH = 2K
+  /* Last two pages hold the stored configuration, kept out of the program image */
+  CONFIG : ORIGIN = 0x0801F000, LENGTH = 4K
   RAM : ORIGIN = 0x20000000, LENGTH = 32K
 }
@@ -97,7 +97,8 @@ mod app {
                 .stop_dma(DmaChannel::C3, Some(DmaChannel::C2), DmaPeriph::Dma1);
             spi1.get_spi()
                 .cleanup_dma(DmaPeriph::Dma1, DmaChannel::C3, Some(DmaChannel::C2));
-            res = spi1.end(unsafe { SPI_READ_BUF });
+            spi1.end(unsafe { SPI_READ_BUF });
+            res = spi1.get_angle();
         });
 
         cx.local.encoder.tick(res);
@@ -0,0 +1,251 @@
+//! Telemetry input backends.
+//!
+//! Every backend delivers a stream of 9 byte telemetry points
+//! ([id (u8), tick (u32 LE), value (f32 LE)]), the transport specific framing is removed here:
+//! - RTT: the `telemetry` up channel (channel 0 on firmware without named channels), one
+//!   debug probe per device.
+//! - SWO: ITM stimulus port `SWO_PORT` (firmware `telemetry-swo` feature), the points are
+//!   framed like on serial links.
+//! - Serial (UART, USB CDC): points are prefixed by `TELEMETRY_SYNC` and interleaved with
+//!   8 byte protocol frames, which are skipped.
+//! - UDP: every datagram holds whole points.
+
+use probe_rs::architecture::arm::component::TraceSink;
+use probe_rs::architecture::arm::SwoConfig;
+use probe_rs::rtt::{Rtt, UpChannel};
+use probe_rs::{Permissions, Probe, Session};
+use std::io::Read;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+pub type Error = Box<dyn std::error::Error>;
+
+/// Size of a telemetry point
+const POINT_SIZE: usize = 9;
+/// Name of the RTT up channel carrying telemetry points
+const RTT_TELEMETRY: &str = "telemetry";
+/// ITM stimulus port carrying telemetry points, see `tunepulse_app::telemetry::SWO_PORT`
+const SWO_PORT: u8 = 1;
+/// Core clock of the target feeding the TPIU (Hz)
+const SWO_TRACE_CLOCK: u32 = 170_000_000;
+/// Prefix of a telemetry point on byte stream links
+const TELEMETRY_SYNC: u8 = 0xA5;
+/// Size of a protocol frame
+const FRAME_SIZE: usize = 8;
+/// Protocol frame types, see `tunepulse_algo::protocol::FrameType`
+const FRAME_TYPES: &[u8] = &[
+    0x10, 0x11, 0x12, 0x13, 0x20, 0x21, 0x22, 0x30, 0x31, 0x40, 0x41, 0x50, 0x51, 0x60, 0x61, 0x70,
+    0x80, 0x81, 0x90, 0x91, 0x92, 0xA0, 0xA1, 0xB0, 0xB1, 0xB2, 0xC0, 0xC1, 0xC2, 0xC3, 0xC4, 0xC5,
+    0xD0, 0xD1, 0xE0, 0xE1, 0xE2, 0xE3, 0xF0, 0xF1,
+];
+
+/// Source of telemetry points
+pub trait Backend {
+    /// Reads available points into `buf`, returns the number of bytes read (0 if none)
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Second handle for sending protocol commands, `None` if the link can't carry them
+    fn command_port(&self) -> Option<Box<dyn serialport::SerialPort>> {
+        None
+    }
+}
+
+/// Telemetry through the debug probe
+pub struct RttBackend {
+    session: Session,
+    channel: UpChannel,
+}
+
+impl RttBackend {
+    /// Attaches to the chip through the debug probe with the given index
+    pub fn open(chip: &str, probe: usize) -> Result<Self, Error> {
+        let probe = Probe::list_all()
+            .get(probe)
+            .ok_or(format!("debug probe {probe} not found"))?
+            .open()?;
+        let mut session = probe.attach(chip, Permissions::default())?;
+        let memory_map = session.target().memory_map.clone();
+        let mut core = session.core(0)?;
+        let mut rtt = Rtt::attach(&mut core, &memory_map)?;
+        drop(core);
+
+        let index = rtt
+            .up_channels()
+            .iter()
+            .position(|channel| channel.name() == Some(RTT_TELEMETRY))
+            .unwrap_or(0);
+        let channel = rtt
+            .up_channels()
+            .take(index)
+            .ok_or("Failed to get RTT channel")?;
+        Ok(Self { session, channel })
+    }
+}
+
+impl Backend for RttBackend {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut core = self.session.core(0)?;
+        Ok(self.channel.read(&mut core, buf)?)
+    }
+}
+
+/// Telemetry through the SWO pin of the debug probe
+pub struct SwoBackend {
+    session: Session,
+    itm: Vec<u8>,     // Received trace bytes not yet decoded
+    payload: Vec<u8>, // Stimulus port bytes not yet deframed
+}
+
+impl SwoBackend {
+    /// Attaches to the chip through the debug probe with the given index and starts the SWO
+    /// capture, which enables ITM and the stimulus ports of the firmware
+    pub fn open(chip: &str, probe: usize, baud: u32) -> Result<Self, Error> {
+        let probe = Probe::list_all()
+            .get(probe)
+            .ok_or(format!("debug probe {probe} not found"))?
+            .open()?;
+        let mut session = probe.attach(chip, Permissions::default())?;
+        let config = SwoConfig::new(SWO_TRACE_CLOCK).set_baud(baud);
+        session.setup_tracing(0, TraceSink::Swo(config))?;
+        Ok(Self {
+            session,
+            itm: Vec::new(),
+            payload: Vec::new(),
+        })
+    }
+}
+
+impl Backend for SwoBackend {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.itm.extend_from_slice(&self.session.read_trace_data()?);
+        decode_itm(&mut self.itm, SWO_PORT, &mut self.payload);
+        Ok(deframe(&mut self.payload, buf))
+    }
+}
+
+/// Moves the payload of the software packets of stimulus `port` contained in `raw` to `out`.
+/// Other packets (sync, overflow, timestamps, hardware sources) are skipped.
+fn decode_itm(raw: &mut Vec<u8>, port: u8, out: &mut Vec<u8>) {
+    let mut read = 0;
+    while read < raw.len() {
+        let header = raw[read];
+        let size = match header & 0b11 {
+            // Sync (zeros ended by 0x80), overflow and single byte timestamps
+            0 if header == 0x80 || header & 0x80 == 0 => {
+                read += 1;
+                continue;
+            }
+            0 => {
+                // Timestamp or extension packet, continuation bytes have bit 7 set
+                let Some(len) = raw[read + 1..].iter().position(|byte| byte & 0x80 == 0) else {
+                    break;
+                };
+                read += len + 2;
+                continue;
+            }
+            0b11 => 4,
+            size => size as usize,
+        };
+        if read + 1 + size > raw.len() {
+            break;
+        }
+        // Bit 2 clear: software source, the port index is in the upper bits
+        if header & 0b100 == 0 && header >> 3 == port {
+            out.extend_from_slice(&raw[read + 1..read + 1 + size]);
+        }
+        read += 1 + size;
+    }
+    raw.drain(..read);
+}
+
+/// Telemetry through a serial port shared with the protocol
+pub struct SerialBackend {
+    port: Box<dyn serialport::SerialPort>,
+    raw: Vec<u8>, // Received bytes not yet deframed
+}
+
+impl SerialBackend {
+    pub fn open(path: &str, baud: u32) -> Result<Self, Error> {
+        let port = serialport::new(path, baud)
+            .timeout(Duration::from_millis(10))
+            .open()?;
+        Ok(Self {
+            port,
+            raw: Vec::new(),
+        })
+    }
+}
+
+impl Backend for SerialBackend {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut chunk = [0u8; 4096];
+        match self.port.read(&mut chunk) {
+            Ok(count) => self.raw.extend_from_slice(&chunk[..count]),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(deframe(&mut self.raw, buf))
+    }
+
+    fn command_port(&self) -> Option<Box<dyn serialport::SerialPort>> {
+        self.port.try_clone().ok()
+    }
+}
+
+/// Moves the points contained in `raw` to `out`, returns the number of bytes written.
+/// Protocol frames are dropped, unknown bytes are skipped until the stream is in sync again.
+fn deframe(raw: &mut Vec<u8>, out: &mut [u8]) -> usize {
+    let mut read = 0;
+    let mut written = 0;
+    while read < raw.len() {
+        let rest = &raw[read..];
+        if rest[0] == TELEMETRY_SYNC {
+            if rest.len() < 1 + POINT_SIZE || written + POINT_SIZE > out.len() {
+                break;
+            }
+            out[written..written + POINT_SIZE].copy_from_slice(&rest[1..1 + POINT_SIZE]);
+            written += POINT_SIZE;
+            read += 1 + POINT_SIZE;
+        } else if FRAME_TYPES.contains(&rest[0]) {
+            if rest.len() < FRAME_SIZE {
+                break;
+            }
+            read += FRAME_SIZE;
+        } else {
+            read += 1;
+        }
+    }
+    raw.drain(..read);
+    written
+}
+
+/// Telemetry received as UDP datagrams
+pub struct UdpBackend {
+    socket: UdpSocket,
+}
+
+impl UdpBackend {
+    pub fn bind(port: u16) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_read_timeout(Some(Duration::from_millis(10)))?;
+        Ok(Self { socket })
+    }
+}
+
+impl Backend for UdpBackend {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.socket.recv(buf) {
+            // Drop a truncated point at the end of a datagram
+            Ok(count) => Ok(count / POINT_SIZE * POINT_SIZE),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(0)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Export of the current plot to PNG or SVG for documentation and issue reports.
+//!
+//! The plot is drawn again from the buffered samples instead of capturing the window: the
+//! image has its own resolution, a legend with the channel labels, axis labels and the
+//! high-contrast colors of the selected theme. Channels are reduced to the min and max of every
+//! pixel column like on screen, so spikes survive at any resolution and SVG files stay small.
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::theme::Theme;
+
+/// Width of exported images (px), also the number of columns channels are reduced to
+pub const WIDTH: u32 = 1920;
+/// Height of one plot in exported images (px)
+const PANEL_HEIGHT: u32 = 480;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+        }
+    }
+}
+
+/// One line of an exported plot
+pub struct Series {
+    pub label: String,
+    pub color: egui::Color32,
+    pub points: Vec<[f64; 2]>,
+}
+
+/// One plot of the export, plots are stacked top to bottom
+pub struct Panel {
+    pub x_label: String,
+    pub y_label: String,
+    pub x_range: Option<(f64, f64)>, // Shown X range, span of the points if none
+    pub series: Vec<Series>,
+}
+
+/// File name with the current time, next to the recordings
+pub fn default_path(format: Format) -> PathBuf {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or(0);
+    PathBuf::from(format!("tunepulse-{}.{}", secs, format.extension()))
+}
+
+/// Draws the plots into an image file
+pub fn export(
+    path: &Path,
+    format: Format,
+    panels: &[Panel],
+    theme: Theme,
+) -> Result<(), Box<dyn Error>> {
+    let size = (WIDTH, PANEL_HEIGHT * panels.len().max(1) as u32);
+    match format {
+        Format::Png => draw(
+            BitMapBackend::new(path, size).into_drawing_area(),
+            panels,
+            theme,
+        ),
+        Format::Svg => draw(
+            SVGBackend::new(path, size).into_drawing_area(),
+            panels,
+            theme,
+        ),
+    }
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    panels: &[Panel],
+    theme: Theme,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let background = rgb(theme.background());
+    let foreground = rgb(theme.foreground());
+    root.fill(&background)?;
+
+    let areas = root.split_evenly((panels.len().max(1), 1));
+    for (area, panel) in areas.iter().zip(panels) {
+        let (x_range, y_range) = ranges(panel);
+        let mut chart = ChartBuilder::on(area)
+            .margin(16)
+            .x_label_area_size(56)
+            .y_label_area_size(96)
+            .build_cartesian_2d(x_range.clone(), y_range)?;
+        chart
+            .configure_mesh()
+            .x_desc(panel.x_label.as_str())
+            .y_desc(panel.y_label.as_str())
+            .axis_style(foreground)
+            .bold_line_style(foreground.mix(0.2))
+            .light_line_style(foreground.mix(0.08))
+            .label_style(("sans-serif", 18).into_font().color(&foreground))
+            .axis_desc_style(("sans-serif", 20).into_font().color(&foreground))
+            .draw()?;
+
+        for series in &panel.series {
+            let style = rgb(theme.line(series.color)).stroke_width(2);
+            // Points outside the shown range would be drawn over the neighbouring plots
+            let points = series
+                .points
+                .iter()
+                .filter(|[x, _]| x_range.contains(x))
+                .map(|[x, y]| (*x, *y));
+            chart
+                .draw_series(LineSeries::new(points, style))?
+                .label(series.label.as_str())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 24, y)], style));
+        }
+        if !panel.series.is_empty() {
+            chart
+                .configure_series_labels()
+                .position(SeriesLabelPosition::UpperRight)
+                .background_style(background.mix(0.85))
+                .border_style(foreground)
+                .label_font(("sans-serif", 18).into_font().color(&foreground))
+                .draw()?;
+        }
+    }
+    root.present()?;
+    Ok(())
+}
+
+/// X and Y range of a plot, Y with a small margin around the shown points
+fn ranges(panel: &Panel) -> (std::ops::Range<f64>, std::ops::Range<f64>) {
+    let points = || panel.series.iter().flat_map(|series| &series.points);
+    let (x_min, x_max) = panel.x_range.unwrap_or_else(|| {
+        points().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), [x, _]| {
+            (min.min(*x), max.max(*x))
+        })
+    });
+    let (y_min, y_max) = points()
+        .filter(|[x, _]| (x_min..=x_max).contains(x))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), [_, y]| {
+            (min.min(*y), max.max(*y))
+        });
+    let margin = if y_max > y_min {
+        (y_max - y_min) * 0.05
+    } else {
+        0.5
+    };
+    (span(x_min, x_max), span(y_min - margin, y_max + margin))
+}
+
+/// Range between two bounds, a unit range without points or around a single value
+fn span(min: f64, max: f64) -> std::ops::Range<f64> {
+    if !min.is_finite() || !max.is_finite() {
+        return 0.0..1.0;
+    }
+    if max <= min {
+        return min - 0.5..max + 0.5;
+    }
+    min..max
+}
+
+fn rgb(color: egui::Color32) -> RGBColor {
+    RGBColor(color.r(), color.g(), color.b())
+}
@@ -0,0 +1,335 @@
+//! Expressions for computed (derived) channels.
+//!
+//! Syntax:
+//! - Channel references: `#<id>`, e.g. `#1 - #3`, channels of further sources as
+//!   `#<source>:<id>`, e.g. `#1:2 - #2` (source 0 is the first one)
+//! - Numbers: `60`, `0.5`, `1e-3`
+//! - Operators: `+ - * / ^` and parentheses, unary minus
+//! - Functions: `abs(a)`, `sqrt(a)`, `min(a, b)`, `max(a, b)`, `atan2(y, x)`, `deg(a)`
+//!
+//! Example: RPM from speed in counts per second: `#2 * 60 / 65536`
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::source::{channel_id, ChannelId};
+
+/// Parse error with position in the source text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.pos + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Channel(ChannelId),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Abs,
+    Sqrt,
+    Min,
+    Max,
+    Atan2,
+    Deg,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => Func::Abs,
+            "sqrt" => Func::Sqrt,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "atan2" => Func::Atan2,
+            "deg" => Func::Deg,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Min | Func::Max | Func::Atan2 => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    root: Node,
+    inputs: Vec<ChannelId>,
+}
+
+impl Expr {
+    /// Parses an expression
+    pub fn parse(src: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            src: src.as_bytes(),
+            pos: 0,
+        };
+        let root = parser.expr()?;
+        parser.skip_spaces();
+        if parser.pos < src.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        let mut inputs = Vec::new();
+        collect_inputs(&root, &mut inputs);
+        inputs.sort_unstable();
+        inputs.dedup();
+        Ok(Self { root, inputs })
+    }
+
+    /// Channel IDs referenced by the expression
+    pub fn inputs(&self) -> &[ChannelId] {
+        &self.inputs
+    }
+
+    /// Evaluates with the latest channel values, `None` until all inputs were received
+    pub fn eval(&self, values: &HashMap<ChannelId, f32>) -> Option<f64> {
+        eval(&self.root, values)
+    }
+}
+
+fn collect_inputs(node: &Node, inputs: &mut Vec<ChannelId>) {
+    match node {
+        Node::Number(_) => {}
+        Node::Channel(id) => inputs.push(*id),
+        Node::Neg(a) => collect_inputs(a, inputs),
+        Node::Binary(_, a, b) => {
+            collect_inputs(a, inputs);
+            collect_inputs(b, inputs);
+        }
+        Node::Call(_, args) => args.iter().for_each(|arg| collect_inputs(arg, inputs)),
+    }
+}
+
+fn eval(node: &Node, values: &HashMap<ChannelId, f32>) -> Option<f64> {
+    Some(match node {
+        Node::Number(value) => *value,
+        Node::Channel(id) => *values.get(id)? as f64,
+        Node::Neg(a) => -eval(a, values)?,
+        Node::Binary(op, a, b) => {
+            let (a, b) = (eval(a, values)?, eval(b, values)?);
+            match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+                Op::Pow => a.powf(b),
+            }
+        }
+        Node::Call(func, args) => {
+            let a = eval(&args[0], values)?;
+            match func {
+                Func::Abs => a.abs(),
+                Func::Sqrt => a.sqrt(),
+                Func::Deg => a.to_degrees(),
+                Func::Min => a.min(eval(&args[1], values)?),
+                Func::Max => a.max(eval(&args[1], values)?),
+                Func::Atan2 => a.atan2(eval(&args[1], values)?),
+            }
+        }
+    })
+}
+
+/// Recursive descent parser, one method per precedence level
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            pos: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.src.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_spaces();
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), ParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c as char)))
+        }
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => Op::Add,
+                Some(b'-') => Op::Sub,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'*') => Op::Mul,
+                Some(b'/') => Op::Div,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        if self.eat(b'-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    /// power := atom ('^' unary)?  (right associative)
+    fn power(&mut self) -> Result<Node, ParseError> {
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            return Ok(Node::Binary(
+                Op::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    /// atom := number | '#' [source ':'] id | name '(' args ')' | '(' expr ')'
+    fn atom(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let node = self.expr()?;
+                self.expect(b')')?;
+                Ok(node)
+            }
+            Some(b'#') => {
+                self.pos += 1;
+                let first = self.channel_number()?;
+                if self.src.get(self.pos) == Some(&b':') {
+                    self.pos += 1;
+                    let id = self.channel_number()?;
+                    return Ok(Node::Channel(channel_id(first as usize, id)));
+                }
+                Ok(Node::Channel(channel_id(0, first)))
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => {
+                let start = self.pos;
+                let mut text = self
+                    .take_while(|c| c.is_ascii_digit() || c == b'.')
+                    .to_string();
+                // Exponent
+                if matches!(self.src.get(self.pos), Some(b'e' | b'E')) {
+                    self.pos += 1;
+                    text.push('e');
+                    if let Some(&sign @ (b'+' | b'-')) = self.src.get(self.pos) {
+                        self.pos += 1;
+                        text.push(sign as char);
+                    }
+                    text.push_str(self.take_while(|c| c.is_ascii_digit()));
+                }
+                text.parse::<f64>()
+                    .map(Node::Number)
+                    .map_err(|_| ParseError {
+                        pos: start,
+                        message: "invalid number".to_string(),
+                    })
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                let name = self.take_while(|c| c.is_ascii_alphanumeric());
+                let func = Func::from_name(name).ok_or(ParseError {
+                    pos: start,
+                    message: format!("unknown function '{}'", name),
+                })?;
+                self.expect(b'(')?;
+                let mut args = vec![self.expr()?];
+                while self.eat(b',') {
+                    args.push(self.expr()?);
+                }
+                self.expect(b')')?;
+                if args.len() != func.arity() {
+                    return Err(ParseError {
+                        pos: start,
+                        message: format!("'{}' takes {} argument(s)", name, func.arity()),
+                    });
+                }
+                Ok(Node::Call(func, args))
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    /// Source index or channel ID of a channel reference
+    fn channel_number(&mut self) -> Result<u8, ParseError> {
+        let start = self.pos;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits.parse::<u8>().map_err(|_| ParseError {
+            pos: start,
+            message: "expected channel ID 0..255".to_string(),
+        })
+    }
+
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.src.get(self.pos).is_some_and(|c| pred(*c)) {
+            self.pos += 1;
+        }
+        // Only ASCII characters are accepted by the predicates
+        let src: &'a [u8] = self.src;
+        std::str::from_utf8(&src[start..self.pos]).unwrap_or_default()
+    }
+}
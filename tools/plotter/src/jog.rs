@@ -0,0 +1,95 @@
+//! Jog buttons for commissioning, sent as protocol commands through the serial link.
+//!
+//! The firmware stops a jog that isn't repeated within its timeout, so the command is resent
+//! while a button is held and a stop command follows the release. A plotter that hangs or
+//! loses the link therefore stops the axis as well. The torque cap and the speed are drive
+//! parameters (`jog_torque_ma`, `jog_speed`).
+
+use egui::Color32;
+use serialport::SerialPort;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Command frame type, see `tunepulse_algo::protocol::FrameType`
+const COMMAND: u8 = 0x20;
+/// Jog commands, see `tunepulse_algo::protocol::commands::Command`
+const JOG_FORWARD: u8 = 17;
+const JOG_BACKWARD: u8 = 18;
+const JOG_STOP: u8 = 19;
+/// Interval the held jog command is repeated in, well within the default jog timeout
+const REPEAT: Duration = Duration::from_millis(50);
+
+/// Port for commands, set by the reader thread once the link is open (serial links only)
+pub type CommandPort = Arc<Mutex<Option<Box<dyn SerialPort>>>>;
+
+pub struct JogButtons {
+    port: CommandPort,
+    held: Option<u8>,      // Jog command repeated while the button is held
+    sent: Instant,         // Time the command was last sent
+    error: Option<String>, // Last write error
+}
+
+impl JogButtons {
+    pub fn new(port: CommandPort) -> Self {
+        Self {
+            port,
+            held: None,
+            sent: Instant::now(),
+            error: None,
+        }
+    }
+
+    /// Shows the buttons once a command port is available
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if self.port.lock().unwrap().is_none() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Jog:");
+            let backward = ui.button("◀").on_hover_text("Hold to jog backward");
+            let forward = ui.button("▶").on_hover_text("Hold to jog forward");
+            let wanted = if backward.is_pointer_button_down_on() {
+                Some(JOG_BACKWARD)
+            } else if forward.is_pointer_button_down_on() {
+                Some(JOG_FORWARD)
+            } else {
+                None
+            };
+            self.update(wanted);
+
+            // A held button doesn't cause repaints by itself, keep repeating the command
+            if self.held.is_some() {
+                ui.ctx().request_repaint_after(REPEAT / 2);
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    }
+
+    /// Sends the jog command when it changed or is due for repetition, stop on release
+    fn update(&mut self, wanted: Option<u8>) {
+        let due = match wanted {
+            Some(command) => self.held != Some(command) || self.sent.elapsed() >= REPEAT,
+            None => self.held.is_some(),
+        };
+        if due {
+            self.send(wanted.unwrap_or(JOG_STOP));
+        }
+        self.held = wanted;
+    }
+
+    fn send(&mut self, command: u8) {
+        let frame = [COMMAND, command, 0, 0, 0, 0, 0, 0];
+        let mut port = self.port.lock().unwrap();
+        let Some(port) = port.as_mut() else {
+            return;
+        };
+        self.error = port
+            .write_all(&frame)
+            .err()
+            .map(|e| format!("Jog failed: {e}"));
+        self.sent = Instant::now();
+    }
+}
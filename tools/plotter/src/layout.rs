@@ -0,0 +1,63 @@
+//! Stacked plot layout: every plot has its own Y axis and shows the channels assigned to it.
+//! Channels are assigned by dragging their label onto the header of a plot, channels not
+//! assigned to any plot are shown in the first one. The layout is stored between sessions.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::source::ChannelId;
+
+/// Storage key of the layout
+pub const LAYOUT_KEY: &str = "plot_layout";
+
+/// One plot of the stack
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlotPanel {
+    pub channels: BTreeSet<ChannelId>, // Channel IDs shown in this plot
+}
+
+/// Stack of plots sharing the time axis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layout {
+    pub panels: Vec<PlotPanel>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            panels: vec![PlotPanel::default()],
+        }
+    }
+}
+
+impl Layout {
+    /// Index of the plot showing a channel
+    pub fn panel_of(&self, id: ChannelId) -> usize {
+        self.panels
+            .iter()
+            .position(|panel| panel.channels.contains(&id))
+            .unwrap_or(0)
+    }
+
+    /// Moves a channel to a plot
+    pub fn assign(&mut self, id: ChannelId, panel: usize) {
+        for p in &mut self.panels {
+            p.channels.remove(&id);
+        }
+        if let Some(p) = self.panels.get_mut(panel) {
+            p.channels.insert(id);
+        }
+    }
+
+    /// Appends an empty plot
+    pub fn add_panel(&mut self) {
+        self.panels.push(PlotPanel::default());
+    }
+
+    /// Removes a plot, its channels fall back to the first plot
+    pub fn remove_panel(&mut self, panel: usize) {
+        if self.panels.len() > 1 && panel < self.panels.len() {
+            self.panels.remove(panel);
+        }
+    }
+}
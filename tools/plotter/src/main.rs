@@ -1,61 +1,118 @@
+mod backend;
+mod export;
+mod expr;
+mod jog;
+mod layout;
+mod record;
+mod session;
+mod source;
+mod store;
+mod theme;
+mod timebase;
+
+use backend::{Backend, RttBackend, SerialBackend, SwoBackend, UdpBackend};
 use crossbeam_queue::ArrayQueue;
 use eframe::{run_native, App, NativeOptions};
 use egui::Color32;
-use egui_plot::{Plot, Points};
-use probe_rs::rtt::Rtt;
-use probe_rs::{Permissions, Probe};
-use std::time::Duration;
-use std::time::Instant;
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+use expr::Expr;
+use jog::{CommandPort, JogButtons};
+use layout::{Layout, LAYOUT_KEY};
+use record::{Recorder, ReplaySpeed};
+use session::{ChannelSettings, DerivedSettings, Session, SESSION_KEY};
+use source::{channel_id, source_of, telemetry_id, ChannelId, Source};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use std::{
     sync::{Arc, Mutex},
     thread,
 };
+use store::ChannelStore;
+use theme::Theme;
+use tunepulse_host::resample::Hold;
+use tunepulse_host::telemetry::{Decoder, Point, POINT_SIZE, TIMEBASE_ID};
+
+/// Samples kept per channel
+const HISTORY_LENGTH: usize = 100_000;
+const BUFFER_MULTIPLE: usize = 4096;
+const BUFFER_SIZE: usize = POINT_SIZE * BUFFER_MULTIPLE;
+/// Points buffered between the reader thread and the UI, several frames at full rate
+const QUEUE_CAPACITY: usize = 1 << 18;
+/// Pairs drawn in XY mode, older pairs are skipped evenly above this
+const XY_MAX_POINTS: usize = 20_000;
+/// Computed channels get IDs counting down from here in the range of the source of their first
+/// input, device channels count up from 0 (255 is the time base point)
+const DERIVED_ID_BASE: u8 = TIMEBASE_ID - 1;
+/// Tick rate assumed until a device reports its rate
+const MANUAL_RATE: f64 = 1.0;
 
-const HISTORY_LENGTH: usize = 10000;
-const STRUCT_SIZE: usize = core::mem::size_of::<RawDataPoint>();
-const BUFFER_MULTIPLE: usize = 32;
-const BUFFER_SIZE: usize = STRUCT_SIZE * BUFFER_MULTIPLE;
-
-#[repr(C, packed)]
-#[derive(Copy, Clone)]
-struct RawDataPoint {
-    id: u8,
-    timestamp: u32,
-    value: f32,
+/// Point queued for the UI
+struct ReceivedPoint {
+    source: u8, // Index of the source it was read from
+    host: f64,  // Session time it arrived at (s)
+    point: Point,
 }
 
 struct ProcessedDataPoint {
-    id: u8,
-    time: f32,
+    id: ChannelId,
+    time: f64, // Ticks since the first sample of its source
     data: f32,
 }
 
+/// How the captured channels are displayed
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlotMode {
+    /// Every visible channel against time
+    TimeSeries,
+    /// One channel against another (e.g. I alpha vs I beta)
+    Xy { x: ChannelId, y: ChannelId },
+}
+
+/// Channel computed from device channels on the host
+struct DerivedChannel {
+    id: ChannelId,  // Channel ID the results are stored under
+    source: String, // Expression text
+    expr: Expr,     // Parsed expression
+}
+
 struct PlotApp {
-    data_queue: Arc<ArrayQueue<RawDataPoint>>,
+    data_queue: Arc<ArrayQueue<ReceivedPoint>>,
     paused: Arc<Mutex<bool>>,
-    display_data: Vec<ProcessedDataPoint>,
-    visible_ids: std::collections::HashSet<u8>,
-    known_ids: std::collections::HashSet<u8>,
-    history_length: usize,
+    dropped: Arc<AtomicUsize>, // Points lost because the queue was full
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    replay: Option<Arc<ReplaySpeed>>, // Speed of the replay, `None` in a live session
+    record_error: Option<String>,
+    store: ChannelStore,
+    visible_ids: std::collections::HashSet<ChannelId>,
+    known_ids: std::collections::HashSet<ChannelId>,
+    mode: PlotMode,
+    derived: Vec<DerivedChannel>,
+    latest: HashMap<ChannelId, f32>, // Latest value of every device channel
+    expr_input: String,
+    expr_error: Option<String>,
+    layout: Layout,
+    names: HashMap<ChannelId, String>, // Names given to channels by the user
+    colors: HashMap<ChannelId, Color32>, // Colors chosen by the user
+    project: String,                   // Path of the project file
+    project_error: Option<String>,
+    theme: Theme,
+    view: Option<(f64, f64)>, // Time range shown in the stacked plots (s)
+    exported: Option<Result<PathBuf, String>>, // File of the last export or its error
+    sources: Vec<Source>,     // Attached devices, time base and alignment of each
+    jog: JogButtons,
 }
 
 impl ProcessedDataPoint {
-    fn new(time: f32, id: u8, data: f32) -> Self {
+    fn new(time: f64, id: ChannelId, data: f32) -> Self {
         Self { time, id, data }
     }
 
-    fn to_point(&self) -> Points {
-        Points::new(vec![[self.time as f64, self.data as f64]])
-    }
-
-    fn to_point_with_color(&self, color: Color32) -> Points {
-        Points::new(vec![[self.time as f64, self.data as f64]]).color(color)
-    }
-
-    fn from_raw(raw: &RawDataPoint) -> Self {
+    fn from_raw(id: ChannelId, raw: &Point, time: f64) -> Self {
         Self {
-            time: raw.timestamp as f32,
-            id: raw.id,
+            time,
+            id,
             data: raw.value,
         }
     }
@@ -63,6 +120,9 @@ impl ProcessedDataPoint {
 
 impl App for PlotApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             // Add controls panel above the plot
             ui.horizontal(|ui| {
@@ -79,140 +139,960 @@ impl App for PlotApp {
                 }
 
                 // Add history length slider
-                ui.add(
-                    egui::Slider::new(&mut self.history_length, 100..=50000)
-                        .text("History Length")
-                        .logarithmic(true),
-                );
-
-                // Collect unique IDs from display data
-                let unique_ids: std::collections::HashSet<u8> =
-                    self.display_data.iter().map(|point| point.id).collect();
-
-                for id in unique_ids {
-                    self.known_ids.insert(id);
+                if ui
+                    .add(
+                        egui::Slider::new(&mut self.store.capacity, 100..=1_000_000)
+                            .text("History Length")
+                            .logarithmic(true),
+                    )
+                    .changed()
+                {
+                    self.store.trim();
                 }
+                ui.label(format!(
+                    "{} samples, {} dropped",
+                    self.store.len(),
+                    self.dropped.load(Ordering::Relaxed)
+                ));
 
                 // Add toggle buttons for each ID
-                // Use known_ids instead of scanning display data
-                for &id in self.known_ids.iter() {
+                let mut ids: Vec<ChannelId> = self.known_ids.iter().copied().collect();
+                ids.sort_unstable();
+                for id in ids {
                     let mut visible = self.visible_ids.contains(&id);
-                    if ui.checkbox(&mut visible, format!("ID {}", id)).changed() {
+                    let response = ui.checkbox(&mut visible, self.channel_label(id));
+                    if response.changed() {
                         if visible {
                             self.visible_ids.insert(id);
                         } else {
                             self.visible_ids.remove(&id);
                         }
                     }
+                    response.context_menu(|ui| self.channel_menu(ui, id));
                 }
             });
 
+            self.record_controls(ui);
+            self.project_controls(ui);
+            self.export_controls(ui);
+            self.mode_controls(ui);
+            self.derived_controls(ui);
+            self.timebase_controls(ui);
+            self.jog.show(ui);
+
             // Drain queue into display buffer when not paused
             if !*self.paused.lock().unwrap() {
-                while let Some(point) = self.data_queue.pop() {
-                    self.display_data.push(ProcessedDataPoint::from_raw(&point));
+                while let Some(received) = self.data_queue.pop() {
+                    let index = self.source_index(received.source);
+                    let aligned = self.sources.len() > 1;
+                    let source = &mut self.sources[index];
+                    let point = received.point;
+                    if point.id == TIMEBASE_ID {
+                        source.timebase.set_device_rate(point.value);
+                        continue;
+                    }
+                    let time = source.timebase.extend(point.timestamp);
+                    if aligned {
+                        source.align(time, received.host);
+                    }
+                    let id = channel_id(index, point.id);
+                    let point = ProcessedDataPoint::from_raw(id, &point, time);
+                    self.update_derived(&point);
+                    self.push(&point);
+                }
+
+                for (index, source) in self.sources.iter_mut().enumerate() {
+                    if let Some(oldest) = self.store.oldest(index) {
+                        source.timebase.forget_before(oldest);
+                    }
                 }
+            }
 
-                // Maintain history length
-                if self.display_data.len() > self.history_length {
-                    self.display_data
-                        .drain(0..self.display_data.len() - self.history_length);
+            match self.mode {
+                PlotMode::TimeSeries => self.show_stacked(ui),
+                PlotMode::Xy { x, y } => {
+                    let pairs = self.xy_pairs(x, y);
+                    Plot::new("XY Data")
+                        .view_aspect(1.0)
+                        .data_aspect(1.0) // Equal scales keep a circle round
+                        .x_axis_label(self.channel_label(x))
+                        .y_axis_label(self.channel_label(y))
+                        .show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(PlotPoints::from(pairs)).color(self.color(y)));
+                        });
                 }
             }
+        });
+
+        if !*self.paused.lock().unwrap() {
+            ctx.request_repaint();
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SESSION_KEY, &self.session());
+    }
+}
+
+impl PlotApp {
+    /// Display name of a channel: the name given by the user, computed channels show their
+    /// expression, channels are prefixed by the name of their source if there are several
+    fn channel_label(&self, id: ChannelId) -> String {
+        let prefix = match self.sources.get(source_of(id)) {
+            Some(source) if self.sources.len() > 1 => format!("{}: ", source.name),
+            _ => String::new(),
+        };
+        let number = telemetry_id(id);
+        if let Some(name) = self.names.get(&id) {
+            return format!("{}{}", prefix, name);
+        }
+        match self.derived.iter().find(|channel| channel.id == id) {
+            Some(channel) => format!("{}ID {} = {}", prefix, number, channel.source),
+            None => format!("{}ID {}", prefix, number),
+        }
+    }
+
+    /// Color of a channel, chosen by the user or derived from the ID
+    fn color(&self, id: ChannelId) -> Color32 {
+        self.colors
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| id_to_color(id))
+    }
+
+    /// Index of a source, sources first seen in a replay are added
+    fn source_index(&mut self, source: u8) -> usize {
+        let index = source as usize;
+        while self.sources.len() <= index {
+            let name = format!("dev{}", self.sources.len());
+            self.sources.push(Source::new(name, MANUAL_RATE));
+        }
+        index
+    }
+
+    /// Source a channel belongs to
+    fn source(&self, id: ChannelId) -> &Source {
+        &self.sources[source_of(id)]
+    }
+
+    /// Stacked plots with independent Y axes and a shared time axis
+    fn show_stacked(&mut self, ui: &mut egui::Ui) {
+        let mut ids: Vec<ChannelId> = self.known_ids.iter().copied().collect();
+        ids.sort_unstable();
+
+        let count = self.layout.panels.len();
+        let header = ui.spacing().interact_size.y + 2.0 * ui.spacing().item_spacing.y;
+        let height = (ui.available_height() / count as f32 - header).max(80.0);
+        let mut dropped = None;
+        let mut removed = None;
+        let synchronized = self.sources.iter().all(|s| s.timebase.is_synchronized());
+
+        for index in 0..count {
+            // Header is a drop zone, its channel labels can be dragged to other plots
+            ui.horizontal(|ui| {
+                let (_, payload) = ui.dnd_drop_zone::<ChannelId, _>(
+                    egui::Frame::default().inner_margin(2.0),
+                    |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Plot {}:", index + 1));
+                            for &id in ids.iter().filter(|id| self.layout.panel_of(**id) == index) {
+                                let label = self.channel_label(id);
+                                ui.dnd_drag_source(egui::Id::new(("channel", id)), id, |ui| {
+                                    ui.colored_label(self.color(id), label);
+                                });
+                            }
+                        });
+                    },
+                );
+                if let Some(id) = payload {
+                    dropped = Some((*id, index));
+                }
+                if count > 1 && ui.small_button("✖").on_hover_text("Remove plot").clicked() {
+                    removed = Some(index);
+                }
+            });
 
-            Plot::new("Real-time Data")
-                .view_aspect(2.0)
+            let shown = Plot::new(("plot", index))
+                .height(height)
+                .x_axis_label(if synchronized {
+                    "Time [s]"
+                } else {
+                    "Time [s] (assumed tick rate)"
+                })
+                .link_axis("time", true, false)
+                .link_cursor("time", true, false)
                 .show(ui, |plot_ui| {
-                    // Only show points for visible IDs
-                    for point in &self.display_data {
-                        if self.visible_ids.contains(&point.id) {
-                            plot_ui.points(point.to_point_with_color(id_to_color(point.id)));
+                    let auto = plot_ui.auto_bounds().x;
+                    let bounds = plot_ui.plot_bounds();
+                    let columns = plot_ui.response().rect.width() as usize;
+
+                    // One line per visible channel assigned to this plot
+                    for &id in &ids {
+                        if !self.visible_ids.contains(&id) || self.layout.panel_of(id) != index {
+                            continue;
+                        }
+                        // Follow the whole history while auto scaling, otherwise only the view
+                        let source = self.source(id);
+                        let range = if auto {
+                            f64::NEG_INFINITY..=f64::INFINITY
+                        } else {
+                            source.ticks(bounds.min()[0])..=source.ticks(bounds.max()[0])
+                        };
+                        let scale = source.timebase.seconds(1.0);
+                        if let Some(channel) = self.store.get(id) {
+                            let points = channel.decimated(range, columns, scale, source.offset());
+                            plot_ui.line(
+                                Line::new(PlotPoints::from(points))
+                                    .color(self.color(id))
+                                    .name(self.channel_label(id)),
+                            );
+                        }
+                    }
+                    for source in &self.sources {
+                        for gap in source.timebase.gaps() {
+                            plot_ui.vline(
+                                VLine::new(source.seconds(*gap))
+                                    .color(Color32::GRAY)
+                                    .style(egui_plot::LineStyle::dashed_loose()),
+                            );
                         }
                     }
+                    (bounds.min()[0], bounds.max()[0])
                 });
+            self.view = Some(shown.inner);
+        }
+
+        if let Some((id, index)) = dropped {
+            self.layout.assign(id, index);
+        }
+        if let Some(index) = removed {
+            self.layout.remove_panel(index);
+        }
+    }
+
+    /// Recording of a live session or speed of a replay
+    fn record_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let Some(speed) = &self.replay {
+                ui.label("Replay speed:");
+                let mut value = speed.get();
+                if ui
+                    .add(
+                        egui::Slider::new(&mut value, 0.1..=100.0)
+                            .logarithmic(true)
+                            .suffix("x"),
+                    )
+                    .changed()
+                {
+                    speed.set(value);
+                }
+                return;
+            }
+
+            let mut recorder = self.recorder.lock().unwrap();
+            match recorder.as_ref() {
+                Some(active) => {
+                    let text = format!(
+                        "Recording to {} ({} kB)",
+                        active.path().display(),
+                        active.bytes() / 1024
+                    );
+                    if ui.button("Stop recording").clicked() {
+                        *recorder = None;
+                    }
+                    ui.colored_label(Color32::RED, text);
+                }
+                None => {
+                    if ui.button("Record").clicked() {
+                        match Recorder::create(&record::default_path()) {
+                            Ok(active) => {
+                                *recorder = Some(active);
+                                self.record_error = None;
+                            }
+                            Err(e) => self.record_error = Some(e.to_string()),
+                        }
+                    }
+                    if let Some(error) = &self.record_error {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                }
+            }
         });
+    }
 
-        if !*self.paused.lock().unwrap() {
-            ctx.request_repaint();
+    /// Saving and loading of the project file
+    fn project_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Project:");
+            ui.add(egui::TextEdit::singleline(&mut self.project).desired_width(200.0));
+            let path = PathBuf::from(&self.project);
+            if ui.button("Save").clicked() {
+                self.project_error = self.session().save(&path).err();
+            }
+            if ui.button("Load").clicked() {
+                self.project_error = match Session::load(&path) {
+                    Ok(session) => {
+                        self.apply_session(session);
+                        None
+                    }
+                    Err(e) => Some(e),
+                };
+            }
+            if let Some(error) = &self.project_error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    }
+
+    /// Context menu of a channel: name and color shown instead of the defaults
+    fn channel_menu(&mut self, ui: &mut egui::Ui, id: ChannelId) {
+        let mut name = self.names.get(&id).cloned().unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            let edit = egui::TextEdit::singleline(&mut name)
+                .hint_text(format!("ID {}", telemetry_id(id)))
+                .desired_width(120.0);
+            if ui.add(edit).changed() {
+                if name.trim().is_empty() {
+                    self.names.remove(&id);
+                } else {
+                    self.names.insert(id, name.clone());
+                }
+            }
+        });
+        // Inline picker, a nested popup would close the menu
+        let mut color = self.color(id);
+        if egui::color_picker::color_picker_color32(
+            ui,
+            &mut color,
+            egui::color_picker::Alpha::Opaque,
+        ) {
+            self.colors.insert(id, color);
+        }
+        if ui.button("Reset").clicked() {
+            self.names.remove(&id);
+            self.colors.remove(&id);
+            ui.close_menu();
         }
     }
-}
 
-fn connect_and_read(
-    data_queue: Arc<ArrayQueue<RawDataPoint>>,
-    paused: Arc<Mutex<bool>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let probe = Probe::list_all()[0].open()?;
-    let mut session = probe.attach("STM32G431CBTx", Permissions::default())?;
-    let memory_map = session.target().memory_map.clone();
-    let mut core = session.core(0)?;
-    let mut rtt = Rtt::attach(&mut core, &memory_map)?;
+    /// View configuration of the session, channels with default settings are left out
+    fn session(&self) -> Session {
+        let ids: std::collections::BTreeSet<ChannelId> = self
+            .visible_ids
+            .iter()
+            .chain(self.names.keys())
+            .chain(self.colors.keys())
+            .chain(self.derived.iter().map(|channel| &channel.id))
+            .copied()
+            .collect();
+        let channels = ids
+            .into_iter()
+            .map(|id| ChannelSettings {
+                id,
+                visible: self.visible_ids.contains(&id),
+                name: self.names.get(&id).cloned(),
+                color: self.colors.get(&id).map(|c| [c.r(), c.g(), c.b()]),
+            })
+            .collect();
+        let derived = self
+            .derived
+            .iter()
+            .map(|channel| DerivedSettings {
+                id: channel.id,
+                expr: channel.source.clone(),
+            })
+            .collect();
+        let xy = match self.mode {
+            PlotMode::TimeSeries => None,
+            PlotMode::Xy { x, y } => Some([x, y]),
+        };
+        Session {
+            history: Some(self.store.capacity),
+            xy,
+            channels,
+            derived,
+            layout: self.layout.clone(),
+            theme: self.theme,
+        }
+    }
 
-    let mut buf = vec![0u8; BUFFER_SIZE]; // Increased buffer size
+    /// Replaces the view configuration, computed channels start empty and fill with the
+    /// samples received from now on
+    fn apply_session(&mut self, session: Session) {
+        if let Some(history) = session.history {
+            self.store.capacity = history.clamp(100, 1_000_000);
+            self.store.trim();
+        }
+        for channel in std::mem::take(&mut self.derived) {
+            self.store.remove(channel.id);
+            self.known_ids.remove(&channel.id);
+        }
+        for saved in session.derived {
+            if self.is_derived(saved.id) {
+                continue;
+            }
+            match Expr::parse(&saved.expr) {
+                Ok(expr) => self.derived.push(DerivedChannel {
+                    id: saved.id,
+                    source: saved.expr,
+                    expr,
+                }),
+                Err(e) => eprintln!("Skipped computed channel {}: {}", saved.expr, e),
+            }
+        }
+        self.visible_ids = session
+            .channels
+            .iter()
+            .filter(|channel| channel.visible)
+            .map(|channel| channel.id)
+            .collect();
+        self.names = session
+            .channels
+            .iter()
+            .filter_map(|channel| Some((channel.id, channel.name.clone()?)))
+            .collect();
+        self.colors = session
+            .channels
+            .iter()
+            .filter_map(|channel| {
+                let [r, g, b] = channel.color?;
+                Some((channel.id, Color32::from_rgb(r, g, b)))
+            })
+            .collect();
+        self.mode = match session.xy {
+            Some([x, y]) => PlotMode::Xy { x, y },
+            None => PlotMode::TimeSeries,
+        };
+        self.theme = session.theme;
+        self.layout = session.layout;
+        if self.layout.panels.is_empty() {
+            self.layout = Layout::default();
+        }
+    }
 
-    // Get the channel once, outside the loop
-    let channel = rtt
-        .up_channels()
-        .take(0)
-        .ok_or("Failed to get RTT channel")?;
+    /// Theme selection and export of the current plot
+    fn export_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            ui.selectable_value(&mut self.theme, Theme::Dark, "Dark");
+            ui.selectable_value(&mut self.theme, Theme::Light, "Light");
 
-    loop {
-        let loop_start = Instant::now();
-        let mut upload_start = Instant::now();
+            ui.label("Export:");
+            for format in [export::Format::Png, export::Format::Svg] {
+                let text = format.extension().to_uppercase();
+                if ui.button(text).clicked() {
+                    let path = export::default_path(format);
+                    let panels = self.export_panels();
+                    self.exported = Some(
+                        export::export(&path, format, &panels, self.theme)
+                            .map(|()| path)
+                            .map_err(|e| e.to_string()),
+                    );
+                }
+            }
+            match &self.exported {
+                Some(Ok(path)) => {
+                    ui.label(format!("Saved {}", path.display()));
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(Color32::RED, error);
+                }
+                None => {}
+            }
+        });
+    }
 
-        if paused.try_lock().map(|guard| *guard).unwrap_or(false) {
-            thread::sleep(Duration::from_millis(100));
-            continue;
+    /// Plots as shown, with every sample of the shown range reduced to the export resolution
+    fn export_panels(&self) -> Vec<export::Panel> {
+        let columns = export::WIDTH as usize;
+        if let PlotMode::Xy { x, y } = self.mode {
+            return vec![export::Panel {
+                x_label: self.channel_label(x),
+                y_label: self.channel_label(y),
+                x_range: None,
+                series: vec![export::Series {
+                    label: self.channel_label(y),
+                    color: self.color(y),
+                    points: self.xy_pairs(x, y),
+                }],
+            }];
         }
 
-        let read_start = Instant::now();
-        match channel.read(&mut core, &mut buf) {
-            Ok(count) => {
-                let num_points = count / STRUCT_SIZE;
+        let synchronized = self.sources.iter().all(|s| s.timebase.is_synchronized());
+        let x_label = if synchronized {
+            "Time [s]"
+        } else {
+            "Time [s] (assumed tick rate)"
+        };
+        let mut ids: Vec<ChannelId> = self.visible_ids.iter().copied().collect();
+        ids.sort_unstable();
+        (0..self.layout.panels.len())
+            .map(|index| {
+                let series = ids
+                    .iter()
+                    .filter(|id| self.layout.panel_of(**id) == index)
+                    .filter_map(|&id| {
+                        let channel = self.store.get(id)?;
+                        let source = self.source(id);
+                        let range = match self.view {
+                            Some((start, end)) => source.ticks(start)..=source.ticks(end),
+                            None => f64::NEG_INFINITY..=f64::INFINITY,
+                        };
+                        let scale = source.timebase.seconds(1.0);
+                        Some(export::Series {
+                            label: self.channel_label(id),
+                            color: self.color(id),
+                            points: channel.decimated(range, columns, scale, source.offset()),
+                        })
+                    })
+                    .collect();
+                export::Panel {
+                    x_label: x_label.to_string(),
+                    y_label: String::new(),
+                    x_range: self.view,
+                    series,
+                }
+            })
+            .collect()
+    }
+
+    /// Tick rate of every source and gap detection settings
+    fn timebase_controls(&mut self, ui: &mut egui::Ui) {
+        let named = self.sources.len() > 1;
+        ui.horizontal(|ui| {
+            for source in &mut self.sources {
+                let name = if named {
+                    format!("{} tick rate", source.name)
+                } else {
+                    "Tick rate".to_string()
+                };
+                let timebase = &mut source.timebase;
+                if timebase.is_synchronized() {
+                    ui.label(format!("{}: {} Hz (device)", name, timebase.rate()));
+                } else {
+                    ui.label(format!("{}:", name));
+                    ui.add(
+                        egui::DragValue::new(&mut timebase.manual_rate)
+                            .range(1.0..=1e7)
+                            .suffix(" Hz"),
+                    )
+                    .on_hover_text("Used until the device reports its tick rate");
+                }
+            }
+            let Some(first) = self.sources.first_mut() else {
+                return;
+            };
+            ui.label("Gap threshold:");
+            ui.add(
+                egui::DragValue::new(&mut first.timebase.gap_seconds)
+                    .range(0.001..=60.0)
+                    .speed(0.01)
+                    .suffix(" s"),
+            );
+            let gap_seconds = first.timebase.gap_seconds;
+            let mut gaps = 0;
+            for source in &mut self.sources {
+                source.timebase.gap_seconds = gap_seconds;
+                gaps += source.timebase.gaps().len();
+            }
+            ui.label(format!("Gaps: {}", gaps));
+        });
+    }
+
+    /// Expression entry and list of computed channels
+    fn derived_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Computed:");
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.expr_input)
+                    .hint_text("e.g. #2 * 60 / 65536")
+                    .desired_width(200.0),
+            );
+            let submit = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || submit {
+                self.add_derived();
+            }
+            if let Some(error) = &self.expr_error {
+                ui.colored_label(Color32::RED, error);
+            }
+
+            let mut remove = None;
+            for channel in &self.derived {
+                if ui
+                    .button(format!("✖ {}", self.channel_label(channel.id)))
+                    .on_hover_text("Remove")
+                    .clicked()
+                {
+                    remove = Some(channel.id);
+                }
+            }
+            if let Some(id) = remove {
+                self.derived.retain(|channel| channel.id != id);
+                self.store.remove(id);
+                self.known_ids.remove(&id);
+                self.visible_ids.remove(&id);
+            }
+        });
+    }
 
-                let conversion_start = Instant::now();
+    /// Parses the entered expression and adds it as a new channel
+    fn add_derived(&mut self) {
+        let expr = match Expr::parse(&self.expr_input) {
+            Ok(expr) => expr,
+            Err(e) => {
+                self.expr_error = Some(e.to_string());
+                return;
+            }
+        };
+        if let Some(input) = expr.inputs().iter().find(|id| self.is_derived(**id)) {
+            self.expr_error = Some(format!("#{} is a computed channel", input));
+            return;
+        }
+        // Stored on the time axis of the source of the first input
+        let source = expr.inputs().first().map_or(0, |id| source_of(*id));
+        let Some(id) = (0..self.derived.len() as u8 + 1)
+            .map(|n| channel_id(source, DERIVED_ID_BASE - n))
+            .find(|id| !self.is_derived(*id))
+        else {
+            self.expr_error = Some("no free channel ID".to_string());
+            return;
+        };
+        self.derived.push(DerivedChannel {
+            id,
+            source: std::mem::take(&mut self.expr_input),
+            expr,
+        });
+        self.visible_ids.insert(id);
+        self.expr_error = None;
+    }
 
-                if num_points > 0 {
-                    // Process data in chunks for better efficiency
-                    let points = unsafe {
-                        std::slice::from_raw_parts(
-                            buf[..count].as_ptr() as *const RawDataPoint,
-                            num_points,
-                        )
-                    };
+    fn is_derived(&self, id: ChannelId) -> bool {
+        self.derived.iter().any(|channel| channel.id == id)
+    }
 
-                    upload_start = Instant::now();
+    /// Stores a received or computed point
+    fn push(&mut self, point: &ProcessedDataPoint) {
+        self.store.push(point.id, point.time, point.data as f64);
+        self.known_ids.insert(point.id);
+    }
 
-                    for point in points {
-                        if data_queue.push(*point).is_err() {
-                            // Queue is full, might want to log this
-                            break;
-                        }
+    /// Recomputes the channels depending on a received point.
+    /// Inputs captured in the same tick update a single computed point instead of adding one
+    /// point per input (the store replaces a sample with the same time). Inputs of another
+    /// source are converted to the time axis of the computed channel.
+    fn update_derived(&mut self, point: &ProcessedDataPoint) {
+        self.latest.insert(point.id, point.data);
+        let mut computed = Vec::new();
+        for channel in &self.derived {
+            if !channel.expr.inputs().contains(&point.id) {
+                continue;
+            }
+            let Some(value) = channel.expr.eval(&self.latest) else {
+                continue; // Not all inputs received yet
+            };
+            let time = if source_of(channel.id) == source_of(point.id) {
+                point.time
+            } else {
+                let seconds = self.source(point.id).seconds(point.time);
+                self.source(channel.id).ticks(seconds)
+            };
+            computed.push(ProcessedDataPoint::new(time, channel.id, value as f32));
+        }
+        for point in &computed {
+            self.push(point);
+        }
+    }
+
+    /// Pairs samples of two channels into XY points.
+    /// Every Y sample is paired with the latest X sample not newer than it, so samples captured
+    /// in the same tick are paired exactly and channels sent at different rates are paired with
+    /// the held value of the other one. Channels of different sources are paired on the aligned
+    /// time axis.
+    fn xy_pairs(&self, x: ChannelId, y: ChannelId) -> Vec<[f64; 2]> {
+        let (Some(xs), Some(ys)) = (self.store.get(x), self.store.get(y)) else {
+            return Vec::new();
+        };
+        let (xs, ys) = (xs.samples(), ys.samples());
+        let same_source = source_of(x) == source_of(y);
+        let (x_source, y_source) = (self.source(x), self.source(y));
+        let step = ys.len().div_ceil(XY_MAX_POINTS).max(1);
+        let mut pairs = Vec::with_capacity(ys.len() / step + 1);
+        let mut held = Hold::new(xs);
+        for [time, value] in ys.iter().step_by(step) {
+            let time = if same_source {
+                *time
+            } else {
+                x_source.ticks(y_source.seconds(*time))
+            };
+            if let Some(x_value) = held.at(time) {
+                pairs.push([x_value, *value]);
+            }
+        }
+        pairs
+    }
+
+    /// Plot mode selection, channel pair selection in XY mode
+    fn mode_controls(&mut self, ui: &mut egui::Ui) {
+        let mut ids: Vec<ChannelId> = self.known_ids.iter().copied().collect();
+        ids.sort_unstable();
+
+        ui.horizontal(|ui| {
+            let mut xy = matches!(self.mode, PlotMode::Xy { .. });
+            if ui.checkbox(&mut xy, "XY mode").changed() {
+                self.mode = if xy {
+                    // Default to the first two channels seen
+                    let x = ids.first().copied().unwrap_or(0);
+                    let y = ids.get(1).copied().unwrap_or(x);
+                    PlotMode::Xy { x, y }
+                } else {
+                    PlotMode::TimeSeries
+                };
+            }
+
+            if self.mode == PlotMode::TimeSeries && ui.button("Add plot").clicked() {
+                self.layout.add_panel();
+            }
+
+            let labels: Vec<(ChannelId, String)> =
+                ids.iter().map(|&id| (id, self.channel_label(id))).collect();
+            if let PlotMode::Xy { x, y } = &mut self.mode {
+                id_selector(ui, "X", x, &labels);
+                id_selector(ui, "Y", y, &labels);
+                if ui.button("Swap").clicked() {
+                    std::mem::swap(x, y);
+                }
+            }
+        });
+    }
+}
+
+/// Combo box selecting one of the known channels, listed with their labels
+fn id_selector(ui: &mut egui::Ui, label: &str, id: &mut ChannelId, labels: &[(ChannelId, String)]) {
+    let selected = match labels.iter().find(|(candidate, _)| *candidate == *id) {
+        Some((_, text)) => text.clone(),
+        None => format!("ID {}", telemetry_id(*id)),
+    };
+    egui::ComboBox::from_label(label)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (candidate, text) in labels {
+                ui.selectable_value(id, *candidate, text.as_str());
+            }
+        });
+}
+
+/// Decodes the telemetry byte stream of a source into points for the UI and records it if
+/// requested
+struct PointSink {
+    source: u8,     // Index of the source
+    start: Instant, // Start of the session, shared by all sources
+    queue: Arc<ArrayQueue<ReceivedPoint>>,
+    dropped: Arc<AtomicUsize>,
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    decoder: Decoder,
+}
+
+impl PointSink {
+    /// Sink of another source sharing queue, counters and recorder
+    fn for_source(&self, source: u8) -> Self {
+        Self {
+            source,
+            start: self.start,
+            queue: self.queue.clone(),
+            dropped: self.dropped.clone(),
+            recorder: self.recorder.clone(),
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Session time now (s)
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    /// Decodes the bytes which arrived at the given session time (s)
+    fn feed(&mut self, bytes: &[u8], host: f64) {
+        let mut recorder = self.recorder.lock().unwrap();
+        if let Some(active) = recorder.as_mut() {
+            if let Err(e) = active.write(self.source, bytes) {
+                eprintln!("Recording stopped: {}", e);
+                *recorder = None;
+            }
+        }
+        drop(recorder);
+
+        let points = self.decoder.feed(bytes);
+        let complete = points.len();
+        for (index, point) in points.into_iter().enumerate() {
+            let received = ReceivedPoint {
+                source: self.source,
+                host,
+                point,
+            };
+            if self.queue.push(received).is_err() {
+                // Queue is full, the UI can't keep up
+                self.dropped.fetch_add(complete - index, Ordering::Relaxed);
+                break;
+            }
+        }
+    }
+}
+
+/// Telemetry source selected on the command line
+enum Input {
+    Rtt { chip: String, probe: usize },
+    Swo { chip: String, probe: usize, baud: u32 },
+    Serial { path: String, baud: u32 },
+    Udp { port: u16 },
+    Replay { path: PathBuf, speed: f64 },
+}
+
+impl Input {
+    /// Parses `plotter [--project <file>] [<input> [--name <label>]]...` with the inputs
+    /// `--rtt <chip>[@<probe>]`, `--swo <chip>[@<probe>] [--swo-baud <n>]`,
+    /// `--serial <port> [--baud <n>]`, `--udp <port>` and `--replay <file> [--speed <x>]`.
+    /// Every input adds a source with its own channel IDs, RTT with the default chip on the
+    /// first probe if none is given. `--baud`, `--swo-baud` and `--speed` apply to the input
+    /// before them and to all inputs after them. A replay brings the
+    /// sources of its recording and can't be combined with other inputs. The view
+    /// configuration is loaded from the project file if given, from the last session otherwise.
+    fn from_args() -> Result<(Vec<(String, Input)>, Option<PathBuf>), String> {
+        let mut inputs: Vec<(String, Input)> = Vec::new();
+        let mut project = None;
+        let mut baud = 921_600;
+        let mut swo_baud = 2_000_000;
+        let mut speed = 1.0;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            let input = match arg.as_str() {
+                "--rtt" => {
+                    let (chip, probe) = parse_chip(&value()?)?;
+                    Input::Rtt { chip, probe }
+                }
+                "--swo" => {
+                    let (chip, probe) = parse_chip(&value()?)?;
+                    Input::Swo {
+                        chip,
+                        probe,
+                        baud: swo_baud,
+                    }
+                }
+                "--serial" => Input::Serial {
+                    path: value()?,
+                    baud,
+                },
+                "--udp" => {
+                    let port = value()?.parse().map_err(|_| "invalid UDP port")?;
+                    Input::Udp { port }
+                }
+                "--replay" => Input::Replay {
+                    path: value()?.into(),
+                    speed,
+                },
+                // Options may follow the input they apply to
+                "--baud" => {
+                    baud = value()?.parse().map_err(|_| "invalid baud rate")?;
+                    if let Some((_, Input::Serial { baud: b, .. })) = inputs.last_mut() {
+                        *b = baud;
+                    }
+                    continue;
+                }
+                "--swo-baud" => {
+                    swo_baud = value()?.parse().map_err(|_| "invalid SWO baud rate")?;
+                    if let Some((_, Input::Swo { baud: b, .. })) = inputs.last_mut() {
+                        *b = swo_baud;
                     }
+                    continue;
                 }
+                "--speed" => {
+                    speed = value()?.parse().map_err(|_| "invalid speed")?;
+                    if let Some((_, Input::Replay { speed: s, .. })) = inputs.last_mut() {
+                        *s = speed;
+                    }
+                    continue;
+                }
+                "--project" => {
+                    project = Some(value()?.into());
+                    continue;
+                }
+                "--name" => {
+                    let name = value()?;
+                    let (last, _) = inputs.last_mut().ok_or("--name follows an input")?;
+                    *last = name;
+                    continue;
+                }
+                _ => return Err(format!("unknown argument: {}", arg)),
+            };
+            inputs.push((format!("dev{}", inputs.len()), input));
+        }
+        if inputs.is_empty() {
+            let input = Input::Rtt {
+                chip: "STM32G431CBTx".to_string(),
+                probe: 0,
+            };
+            inputs.push(("dev0".to_string(), input));
+        }
+        let replay = inputs
+            .iter()
+            .any(|(_, input)| matches!(input, Input::Replay { .. }));
+        if replay && inputs.len() > 1 {
+            return Err("--replay can't be combined with other inputs".to_string());
+        }
+        Ok((inputs, project))
+    }
+
+    fn open(&self) -> Result<Box<dyn Backend>, backend::Error> {
+        Ok(match self {
+            Input::Rtt { chip, probe } => Box::new(RttBackend::open(chip, *probe)?),
+            Input::Swo { chip, probe, baud } => Box::new(SwoBackend::open(chip, *probe, *baud)?),
+            Input::Serial { path, baud } => Box::new(SerialBackend::open(path, *baud)?),
+            Input::Udp { port } => Box::new(UdpBackend::bind(*port)?),
+            Input::Replay { .. } => unreachable!("replay has no backend"),
+        })
+    }
+}
+
+/// Parses `<chip>[@<probe>]`, the first probe if none is given
+fn parse_chip(value: &str) -> Result<(String, usize), String> {
+    let (chip, probe) = match value.split_once('@') {
+        Some((chip, probe)) => (chip, probe.parse().map_err(|_| "invalid probe index")?),
+        None => (value, 0),
+    };
+    Ok((chip.to_string(), probe))
+}
 
-                let finish = Instant::now();
+/// Reads the backend until it fails
+fn read_loop(
+    backend: &mut dyn Backend,
+    mut sink: PointSink,
+    paused: Arc<Mutex<bool>>,
+) -> Result<(), backend::Error> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    loop {
+        if paused.try_lock().map(|guard| *guard).unwrap_or(false) {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
 
-                // println!(
-                //     "Loop: {:?}, Read: {:?}, Convert: {:?}, Upload: {:?}, Finished: {:?}",
-                //     loop_start.elapsed(),
-                //     read_start.elapsed(),
-                //     conversion_start.elapsed(),
-                //     upload_start.elapsed(),
-                //     finish.elapsed()
-                // );
+        match backend.read(&mut buf) {
+            Ok(0) => thread::sleep(Duration::from_millis(1)),
+            Ok(count) => {
+                let host = sink.now();
+                sink.feed(&buf[..count], host);
             }
             Err(e) => {
-                eprintln!("Error reading RTT channel: {:?}", e);
+                eprintln!("Error reading telemetry: {:?}", e);
                 thread::sleep(Duration::from_millis(10));
             }
         }
     }
 }
 
-fn id_to_color(id: u8) -> Color32 {
-    // Use the golden ratio to get a good distribution of hues
-    let hue = (id as f32 / 2.0) % 1.0;
+fn id_to_color(id: ChannelId) -> Color32 {
+    // Use the golden ratio to get a good distribution of hues, shifted per source
+    let hue = (telemetry_id(id) as f32 / 2.0 + source_of(id) as f32 * 0.618) % 1.0;
     // Keep saturation and value high for good visibility
     let (r, g, b) = hsv_to_rgb(hue, 0.8, 0.9);
     Color32::from_rgb(r, g, b)
@@ -239,27 +1119,122 @@ fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
 }
 
 fn main() {
-    let data_queue = Arc::new(ArrayQueue::new(BUFFER_SIZE));
+    let data_queue = Arc::new(ArrayQueue::new(QUEUE_CAPACITY));
     let paused = Arc::new(Mutex::new(false));
+    let dropped = Arc::new(AtomicUsize::new(0));
 
-    let data_queue_clone = data_queue.clone();
-    let paused_clone = paused.clone();
+    let recorder = Arc::new(Mutex::new(None));
 
-    thread::spawn(move || {
-        if let Err(e) = connect_and_read(data_queue_clone, paused_clone) {
-            eprintln!("Error in data collection: {:?}", e);
+    let (inputs, project) = match Input::from_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
         }
-    });
+    };
+
+    let sink = PointSink {
+        source: 0,
+        start: Instant::now(),
+        queue: data_queue.clone(),
+        dropped: dropped.clone(),
+        recorder: recorder.clone(),
+        decoder: Decoder::new(),
+    };
+    let command_port: CommandPort = Arc::new(Mutex::new(None));
+    let mut sources = Vec::new();
+    let mut replay = None;
+    for (index, (name, input)) in inputs.into_iter().enumerate() {
+        sources.push(Source::new(name, MANUAL_RATE));
+        let paused_clone = paused.clone();
+        match input {
+            Input::Replay { path, speed } => {
+                let speed = Arc::new(ReplaySpeed::new(speed));
+                let speed_clone = speed.clone();
+                let mut sinks = HashMap::new();
+                let template = sink.for_source(0);
+                thread::spawn(move || {
+                    let result = record::replay(&path, speed_clone, paused_clone, |s, t, b| {
+                        let sink = sinks.entry(s).or_insert_with(|| template.for_source(s));
+                        sink.feed(b, t);
+                    });
+                    match result {
+                        Ok(()) => println!("Replay finished"),
+                        Err(e) => eprintln!("Error in replay: {:?}", e),
+                    }
+                });
+                replay = Some(speed);
+            }
+            input => {
+                let sink = sink.for_source(index as u8);
+                let command_port_clone = command_port.clone();
+                thread::spawn(move || {
+                    let result = input.open().and_then(|mut backend| {
+                        // Jog commands go to the first source
+                        if index == 0 {
+                            *command_port_clone.lock().unwrap() = backend.command_port();
+                        }
+                        read_loop(backend.as_mut(), sink, paused_clone)
+                    });
+                    if let Err(e) = result {
+                        eprintln!("Error in data collection ({}): {:?}", index, e);
+                    }
+                });
+            }
+        }
+    }
 
     let app = PlotApp {
         data_queue,
         paused,
-        display_data: Vec::with_capacity(HISTORY_LENGTH),
+        dropped,
+        recorder,
+        replay,
+        record_error: None,
+        store: ChannelStore::new(HISTORY_LENGTH),
         visible_ids: std::collections::HashSet::new(),
         known_ids: std::collections::HashSet::new(),
-        history_length: HISTORY_LENGTH,
+        mode: PlotMode::TimeSeries,
+        derived: Vec::new(),
+        latest: HashMap::new(),
+        expr_input: String::new(),
+        expr_error: None,
+        layout: Layout::default(),
+        names: HashMap::new(),
+        colors: HashMap::new(),
+        project: project
+            .clone()
+            .unwrap_or_else(session::default_project)
+            .display()
+            .to_string(),
+        project_error: None,
+        theme: Theme::default(),
+        view: None,
+        exported: None,
+        sources,
+        jog: JogButtons::new(command_port),
     };
 
     let options = NativeOptions::default();
-    run_native("Real-time Plot", options, Box::new(|_cc| Ok(Box::new(app)))).unwrap();
+    run_native(
+        "Real-time Plot",
+        options,
+        Box::new(move |cc| {
+            let mut app = app;
+            let storage = cc.storage;
+            if let Some(path) = &project {
+                match Session::load(path) {
+                    Ok(session) => app.apply_session(session),
+                    Err(e) => app.project_error = Some(e),
+                }
+            } else if let Some(session) = storage.and_then(|s| eframe::get_value(s, SESSION_KEY)) {
+                app.apply_session(session);
+            } else if let Some(layout) = storage.and_then(|s| eframe::get_value(s, LAYOUT_KEY)) {
+                // Layout saved by a version without sessions
+                app.layout = layout;
+            }
+            Ok(Box::new(app))
+        }),
+    )
+    .unwrap();
 }
@@ -0,0 +1,160 @@
+//! Recording of the raw telemetry byte stream and its replay.
+//!
+//! File layout (little endian):
+//! - Header: `MAGIC` (8 bytes, the last byte is the format version)
+//! - Chunk:  [host time since the start of the recording in µs (u64), source index (u8),
+//!   length (u32), bytes]
+//!
+//! Chunks hold the bytes exactly as read from the device, so a replay goes through the same
+//! decoding as a live session. Replay waits between chunks according to the recorded host time
+//! divided by the replay speed. Recordings of version 1 have no source index, all their chunks
+//! belong to the first source.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// File signature including the format version
+const MAGIC: &[u8; 8] = b"TPREC\0\0\x02";
+
+/// Format version without source index
+const VERSION_SINGLE: u8 = 1;
+
+/// Writes received chunks to a recording file
+pub struct Recorder {
+    file: BufWriter<File>,
+    path: PathBuf,
+    start: Instant,
+    bytes: u64, // Payload bytes written
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            start: Instant::now(),
+            bytes: 0,
+        })
+    }
+
+    /// Appends a chunk of a source stamped with the current host time
+    pub fn write(&mut self, source: u8, data: &[u8]) -> io::Result<()> {
+        let time = self.start.elapsed().as_micros() as u64;
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&[source])?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(data)?;
+        self.bytes += data.len() as u64;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+/// Default name of a new recording, unique per second
+pub fn default_path() -> PathBuf {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or(0);
+    PathBuf::from(format!("tunepulse-{}.tprec", secs))
+}
+
+/// Replay speed shared with the UI, stored as `f64` bits
+pub struct ReplaySpeed(AtomicU64);
+
+impl ReplaySpeed {
+    pub fn new(speed: f64) -> Self {
+        Self(AtomicU64::new(speed.to_bits()))
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, speed: f64) {
+        self.0.store(speed.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Plays a recording back, passing every chunk to `sink` at its recorded time
+///
+/// # Arguments
+/// * `path` - Recording to play
+/// * `speed` - Replay speed, 1.0 is real time
+/// * `paused` - Holds the replay while set
+/// * `sink` - Receives the source index, the recorded host time (s) and the recorded bytes
+pub fn replay(
+    path: &Path,
+    speed: Arc<ReplaySpeed>,
+    paused: Arc<Mutex<bool>>,
+    mut sink: impl FnMut(u8, f64, &[u8]),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    let single = magic[7] == VERSION_SINGLE;
+    if magic[..7] != MAGIC[..7] || !(single || magic[7] == MAGIC[7]) {
+        return Err(format!("{} is not a TunePulse recording", path.display()).into());
+    }
+
+    // Position in the recording (µs) and the host instant it was reached at
+    let mut position = 0u64;
+    let mut reached = Instant::now();
+    let mut header = [0u8; 13];
+    let header_len = if single { 12 } else { 13 };
+    let mut data = Vec::new();
+    loop {
+        match file.read_exact(&mut header[..header_len]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        let time = u64::from_le_bytes(header[0..8].try_into().unwrap());
+        let (source, len) = if single {
+            (0, &header[8..12])
+        } else {
+            (header[8], &header[9..13])
+        };
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        data.resize(len, 0);
+        file.read_exact(&mut data)?;
+
+        // Wait until the chunk is due, speed changes apply from the current position
+        loop {
+            if *paused.lock().unwrap() {
+                thread::sleep(Duration::from_millis(50));
+                reached = Instant::now();
+                continue;
+            }
+            let speed = speed.get().max(0.01);
+            position += (reached.elapsed().as_micros() as f64 * speed) as u64;
+            reached = Instant::now();
+            if position >= time {
+                break;
+            }
+            let remaining = (time - position) as f64 / speed;
+            thread::sleep(Duration::from_micros(remaining.min(50_000.0) as u64));
+        }
+        sink(source, time as f64 / 1e6, &data);
+    }
+}
@@ -0,0 +1,66 @@
+//! Plotter view configuration saved to a project file and restored on start.
+//!
+//! A project file (TOML) holds everything needed to rebuild the view of a tuning session:
+//! visible channels with their names and colors, computed channels, the plot layout, the plot
+//! mode, the theme and the history length. Channels are stored by channel ID, so a project fits
+//! every device streaming the same signals. The last session is also kept in the eframe storage,
+//! a restart without a project file continues with the previous view.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::layout::Layout;
+use crate::source::ChannelId;
+use crate::theme::Theme;
+
+/// Storage key of the last session
+pub const SESSION_KEY: &str = "plot_session";
+
+/// Display settings of one channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSettings {
+    pub id: ChannelId,
+    pub visible: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // Shown instead of the channel ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>, // RGB, replaces the color derived from the ID
+}
+
+/// Computed channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedSettings {
+    pub id: ChannelId,
+    pub expr: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<usize>, // Samples kept per channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xy: Option<[ChannelId; 2]>, // Channels shown in XY mode, time series if none
+    pub channels: Vec<ChannelSettings>,
+    pub derived: Vec<DerivedSettings>,
+    pub layout: Layout,
+    pub theme: Theme,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Project file used when none is given on the command line
+pub fn default_project() -> PathBuf {
+    PathBuf::from("tunepulse-plot.toml")
+}
@@ -0,0 +1,88 @@
+//! Telemetry sources of a session, one per attached device.
+//!
+//! - Channel IDs: every source has its own range of 256 channel IDs, the source index is the
+//!   high byte of a `ChannelId` and the telemetry ID the low byte. The first source keeps the
+//!   plain telemetry IDs, so layouts and expressions of single device sessions stay valid.
+//! - Time alignment: every device counts its own ticks at its own rate. With more than one
+//!   source the host arrival time of the samples places each time axis on the session clock:
+//!   the smallest difference between arrival and sample time within a window belongs to the
+//!   sample with the least link latency. Taking it anew every window follows the drift of the
+//!   device oscillators, the remaining error is the link latency jitter (around a millisecond
+//!   on USB).
+
+use crate::timebase::TimeBase;
+
+/// Channel key: source index (high byte) and telemetry ID (low byte)
+pub type ChannelId = u16;
+
+/// Duration of an alignment window (s)
+const ALIGN_WINDOW: f64 = 2.0;
+
+pub fn channel_id(source: usize, id: u8) -> ChannelId {
+    ((source as ChannelId) << 8) | id as ChannelId
+}
+
+pub fn source_of(channel: ChannelId) -> usize {
+    (channel >> 8) as usize
+}
+
+pub fn telemetry_id(channel: ChannelId) -> u8 {
+    channel as u8
+}
+
+pub struct Source {
+    pub name: String,       // Shown in front of the channel labels with several sources
+    pub timebase: TimeBase, // Tick counter of the device
+    offset: f64,            // Session time of the first sample (s)
+    candidate: f64,         // Smallest offset seen in the current window
+    window_end: f64,        // Session time the current window closes at
+    rate: f64,              // Tick rate the offsets were measured with
+}
+
+impl Source {
+    pub fn new(name: String, manual_rate: f64) -> Self {
+        Self {
+            name,
+            timebase: TimeBase::new(manual_rate),
+            offset: 0.0,
+            candidate: f64::INFINITY,
+            window_end: 0.0,
+            rate: 0.0,
+        }
+    }
+
+    /// Places the time axis on the session clock
+    ///
+    /// # Arguments
+    /// * `ticks` - Ticks since the first sample
+    /// * `host` - Session time the sample arrived at (s)
+    pub fn align(&mut self, ticks: f64, host: f64) {
+        // A new tick rate invalidates the offsets measured so far
+        if self.timebase.rate() != self.rate {
+            self.rate = self.timebase.rate();
+            self.candidate = f64::INFINITY;
+            self.window_end = 0.0;
+        }
+        self.candidate = self.candidate.min(host - self.timebase.seconds(ticks));
+        if host >= self.window_end {
+            self.offset = self.candidate;
+            self.candidate = f64::INFINITY;
+            self.window_end = host + ALIGN_WINDOW;
+        }
+    }
+
+    /// Session time (s) of a position on the time axis of this source
+    pub fn seconds(&self, ticks: f64) -> f64 {
+        self.offset + self.timebase.seconds(ticks)
+    }
+
+    /// Position on the time axis of this source at a session time (s)
+    pub fn ticks(&self, seconds: f64) -> f64 {
+        (seconds - self.offset) * self.timebase.rate()
+    }
+
+    /// Session time of the first sample (s)
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
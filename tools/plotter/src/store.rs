@@ -0,0 +1,156 @@
+//! Sample storage for the plots.
+//!
+//! Every channel has its own ring buffer of `[time, value]` pairs, so the history length is
+//! per channel and a fast channel doesn't push slow ones out of the window. For display only the
+//! visible time range is taken and reduced to a min/max pair per pixel column, which keeps the
+//! cost of a frame bounded by the plot width instead of the number of stored samples while
+//! preserving peaks.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::RangeInclusive;
+
+use crate::source::{source_of, ChannelId};
+
+/// Samples of one channel, ordered by time
+#[derive(Default)]
+pub struct ChannelBuffer {
+    samples: VecDeque<[f64; 2]>, // [ticks since the first sample, value]
+}
+
+impl ChannelBuffer {
+    /// Appends a sample, a sample with the time of the newest one replaces it
+    fn push(&mut self, time: f64, value: f64, capacity: usize) {
+        match self.samples.back_mut() {
+            Some(last) if last[0] == time => last[1] = value,
+            _ => self.samples.push_back([time, value]),
+        }
+        self.trim(capacity);
+    }
+
+    fn trim(&mut self, capacity: usize) {
+        let excess = self.samples.len().saturating_sub(capacity);
+        self.samples.drain(..excess);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Time of the oldest sample
+    pub fn oldest(&self) -> Option<f64> {
+        self.samples.front().map(|sample| sample[0])
+    }
+
+    pub fn samples(&self) -> &VecDeque<[f64; 2]> {
+        &self.samples
+    }
+
+    /// Samples in a time range reduced to at most `2 * columns` points, times scaled by `scale`
+    /// and shifted by `offset`
+    ///
+    /// One sample on each side of the range is included so lines reach the plot edges.
+    pub fn decimated(
+        &self,
+        range: RangeInclusive<f64>,
+        columns: usize,
+        scale: f64,
+        offset: f64,
+    ) -> Vec<[f64; 2]> {
+        let start = self
+            .samples
+            .partition_point(|sample| sample[0] < *range.start())
+            .saturating_sub(1);
+        let end = (self
+            .samples
+            .partition_point(|sample| sample[0] <= *range.end())
+            + 1)
+        .min(self.samples.len());
+        let columns = columns.max(1);
+
+        if end - start <= 2 * columns {
+            return self
+                .samples
+                .range(start..end)
+                .map(|[time, value]| [time * scale + offset, *value])
+                .collect();
+        }
+
+        // Min and max of every column, emitted in time order
+        let mut points = Vec::with_capacity(2 * columns);
+        let per_column = (end - start).div_ceil(columns);
+        let mut column = start;
+        while column < end {
+            let column_end = (column + per_column).min(end);
+            let (mut min, mut max) = (column, column);
+            for i in column..column_end {
+                if self.samples[i][1] < self.samples[min][1] {
+                    min = i;
+                }
+                if self.samples[i][1] > self.samples[max][1] {
+                    max = i;
+                }
+            }
+            for i in [min.min(max), min.max(max)] {
+                let [time, value] = self.samples[i];
+                points.push([time * scale + offset, value]);
+                if min == max {
+                    break;
+                }
+            }
+            column = column_end;
+        }
+        points
+    }
+}
+
+/// Buffers of all channels
+pub struct ChannelStore {
+    channels: BTreeMap<ChannelId, ChannelBuffer>,
+    pub capacity: usize, // Samples kept per channel
+}
+
+impl ChannelStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            channels: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    /// Appends a sample to a channel
+    pub fn push(&mut self, id: ChannelId, time: f64, value: f64) {
+        self.channels
+            .entry(id)
+            .or_default()
+            .push(time, value, self.capacity);
+    }
+
+    /// Applies a changed capacity to all channels
+    pub fn trim(&mut self) {
+        for channel in self.channels.values_mut() {
+            channel.trim(self.capacity);
+        }
+    }
+
+    pub fn get(&self, id: ChannelId) -> Option<&ChannelBuffer> {
+        self.channels.get(&id)
+    }
+
+    pub fn remove(&mut self, id: ChannelId) {
+        self.channels.remove(&id);
+    }
+
+    /// Time of the oldest stored sample of a source (its ticks)
+    pub fn oldest(&self, source: usize) -> Option<f64> {
+        self.channels
+            .iter()
+            .filter(|(id, _)| source_of(**id) == source)
+            .filter_map(|(_, channel)| channel.oldest())
+            .reduce(f64::min)
+    }
+
+    /// Total number of stored samples
+    pub fn len(&self) -> usize {
+        self.channels.values().map(ChannelBuffer::len).sum()
+    }
+}
@@ -0,0 +1,51 @@
+//! Color themes of the plotter window and of exported plots.
+//!
+//! The window follows the egui dark or light visuals. Exports use the high-contrast variant of
+//! the same theme: pure background, text and axes in the opposite color, and channel colors
+//! darkened on a light background so thin lines stay readable in print.
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+
+    /// Background of exported plots
+    pub fn background(self) -> Color32 {
+        match self {
+            Theme::Dark => Color32::BLACK,
+            Theme::Light => Color32::WHITE,
+        }
+    }
+
+    /// Text, axes and legend border of exported plots
+    pub fn foreground(self) -> Color32 {
+        match self {
+            Theme::Dark => Color32::WHITE,
+            Theme::Light => Color32::BLACK,
+        }
+    }
+
+    /// Channel color in exported plots
+    pub fn line(self, color: Color32) -> Color32 {
+        match self {
+            Theme::Dark => color,
+            Theme::Light => {
+                let darken = |c: u8| (c as f32 * 0.7) as u8;
+                Color32::from_rgb(darken(color.r()), darken(color.g()), darken(color.b()))
+            }
+        }
+    }
+}
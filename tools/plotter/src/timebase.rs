@@ -0,0 +1,99 @@
+//! Conversion of firmware tick counters into a continuous time axis.
+//!
+//! - Tick rate: taken from the time base point (`TIMEBASE_ID`) sent by the firmware, a manually
+//!   entered rate is used until it arrives.
+//! - Wraparound: the 32-bit tick counter is extended to 64 bits, so the axis keeps increasing
+//!   after the counter wraps.
+//! - Gaps: a jump larger than the gap threshold (lost samples, paused stream) or a counter
+//!   moving backwards (device reset) is recorded as a gap. After a reset the time continues
+//!   from the last sample instead of jumping back.
+
+/// Counters moving back by less than this are samples received out of order
+const REORDER_WINDOW: u32 = 1 << 16;
+
+pub struct TimeBase {
+    pub manual_rate: f64, // Ticks per second used until the firmware reports its rate
+    device_rate: Option<f64>, // Ticks per second reported by the firmware
+    pub gap_seconds: f64, // Jumps longer than this are reported as gaps
+    last: Option<u32>,    // Last received tick counter
+    extended: u64,        // Extended tick counter of the last sample
+    origin: Option<u64>,  // Extended counter of the first sample, plotted as time zero
+    gaps: Vec<f64>,       // Extended counters where gaps were detected
+}
+
+impl TimeBase {
+    pub fn new(manual_rate: f64) -> Self {
+        Self {
+            manual_rate,
+            device_rate: None,
+            gap_seconds: 0.5,
+            last: None,
+            extended: 0,
+            origin: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Handles the time base point
+    pub fn set_device_rate(&mut self, rate: f32) {
+        if rate.is_finite() && rate > 0.0 {
+            self.device_rate = Some(rate as f64);
+        }
+    }
+
+    /// Tick rate used for the conversion
+    pub fn rate(&self) -> f64 {
+        self.device_rate
+            .unwrap_or(self.manual_rate.max(f64::MIN_POSITIVE))
+    }
+
+    /// True once the firmware reported its tick rate
+    pub fn is_synchronized(&self) -> bool {
+        self.device_rate.is_some()
+    }
+
+    /// Extends a received tick counter, returns ticks since the first sample
+    pub fn extend(&mut self, tick: u32) -> f64 {
+        let Some(last) = self.last else {
+            self.last = Some(tick);
+            self.origin = Some(self.extended);
+            return 0.0;
+        };
+
+        let forward = tick.wrapping_sub(last);
+        let backward = last.wrapping_sub(tick);
+        if forward < 1 << 31 {
+            self.extended += forward as u64;
+            if forward as f64 > self.gap_seconds * self.rate() {
+                self.gaps.push(self.relative(self.extended));
+            }
+        } else if backward < REORDER_WINDOW {
+            // Late sample, keep the counter
+            return self.relative(self.extended - backward as u64);
+        } else {
+            // Counter restarted, continue the axis from the last sample
+            self.gaps.push(self.relative(self.extended));
+        }
+        self.last = Some(tick);
+        self.relative(self.extended)
+    }
+
+    /// Converts ticks since the first sample into seconds
+    pub fn seconds(&self, ticks: f64) -> f64 {
+        ticks / self.rate()
+    }
+
+    /// Positions of detected gaps (ticks since the first sample)
+    pub fn gaps(&self) -> &[f64] {
+        &self.gaps
+    }
+
+    /// Drops gaps older than the given position
+    pub fn forget_before(&mut self, ticks: f64) {
+        self.gaps.retain(|gap| *gap >= ticks);
+    }
+
+    fn relative(&self, extended: u64) -> f64 {
+        extended.saturating_sub(self.origin.unwrap_or(0)) as f64
+    }
+}
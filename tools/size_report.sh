@@ -0,0 +1,61 @@
+#!/usr/bin/env bash
+# Flash and RAM usage of the release firmware with all subsystems and with the minimal feature
+# set, fails if an image exceeds the FLASH or RAM region of memory.x. Meant as CI step:
+#
+#     tools/size_report.sh                      # full and minimal build
+#     tools/size_report.sh --features tuning    # minimal build plus the listed features
+#
+# Needs llvm-size (cargo-binutils `rust-size` or the LLVM tools of the system).
+set -euo pipefail
+
+ROOT="$(cd "$(dirname "$0")/.." && pwd)"
+TARGET=thumbv7em-none-eabihf
+ELF="$ROOT/target/$TARGET/release/app"
+SIZE="$(command -v llvm-size || command -v rust-size || true)"
+if [ -z "$SIZE" ]; then
+    echo "llvm-size not found, install the LLVM tools or cargo-binutils" >&2
+    exit 2
+fi
+
+# Region length from memory.x in bytes (K = 1024 bytes)
+region() {
+    local len
+    len="$(sed -n "s/^ *$1 *: *ORIGIN *= *[0-9a-fA-Fx]*, *LENGTH *= *\([0-9]*\)K.*/\1/p" \
+        "$ROOT/memory.x")"
+    echo $((len * 1024))
+}
+FLASH_LIMIT="$(region FLASH)"
+RAM_LIMIT="$(region RAM)"
+
+# Size of the named sections of the built image
+sections() {
+    "$SIZE" -A "$ELF" | awk -v list="$1" '
+        BEGIN { n = split(list, names, " "); for (i = 1; i <= n; i++) wanted[names[i]] = 1 }
+        ($1 in wanted) { sum += $2 }
+        END { print sum + 0 }'
+}
+
+failed=0
+report() {
+    local name="$1"
+    shift
+    cargo build -q --release -p app --bin app --manifest-path "$ROOT/Cargo.toml" "$@"
+    local flash ram
+    flash="$(sections ".vector_table .text .rodata .data")"
+    ram="$(sections ".data .bss .uninit")"
+    printf "%-10s flash %7d / %7d (%3d%%)   ram %6d / %6d (%3d%%)\n" "$name" \
+        "$flash" "$FLASH_LIMIT" $((flash * 100 / FLASH_LIMIT)) \
+        "$ram" "$RAM_LIMIT" $((ram * 100 / RAM_LIMIT))
+    if [ "$flash" -gt "$FLASH_LIMIT" ] || [ "$ram" -gt "$RAM_LIMIT" ]; then
+        echo "$name image doesn't fit the target" >&2
+        failed=1
+    fi
+}
+
+if [ "$#" -gt 0 ]; then
+    report custom --no-default-features "$@"
+else
+    report full
+    report minimal --no-default-features
+fi
+exit "$failed"
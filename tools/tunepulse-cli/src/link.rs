@@ -0,0 +1,193 @@
+// Links carrying protocol frames and telemetry between host and drive.
+
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use probe_rs::rtt::Rtt;
+use probe_rs::{Permissions, Probe, Session};
+
+use crate::protocol::{Frame, FRAME_SIZE};
+
+/// Name of RTT channels carrying protocol frames
+const RTT_PROTOCOL: &str = "protocol";
+/// Name of RTT up channel carrying telemetry points
+const RTT_TELEMETRY: &str = "telemetry";
+/// Name of RTT up channel carrying the slow status reports
+const RTT_STATUS: &str = "status";
+
+/// Reply timeout
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+pub type Error = Box<dyn std::error::Error>;
+
+pub trait Link {
+    /// Sends a frame to the drive
+    fn send(&mut self, frame: &Frame) -> Result<(), Error>;
+
+    /// Receives raw bytes from the protocol stream, returns number of bytes read
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Receives raw telemetry bytes
+    fn telemetry(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Receives raw bytes from the slow status channel
+    fn status(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Sends a request and waits for the reply of the given type, skipping events
+    fn request(&mut self, frame: &Frame, reply: u8) -> Result<Frame, Error> {
+        self.send(frame)?;
+        let start = Instant::now();
+        let mut pending = Vec::new();
+        let mut buf = [0u8; 64];
+        while start.elapsed() < TIMEOUT {
+            let count = self.receive(&mut buf)?;
+            pending.extend_from_slice(&buf[..count]);
+            while pending.len() >= FRAME_SIZE {
+                let frame: Frame = pending[..FRAME_SIZE].try_into().unwrap();
+                pending.drain(..FRAME_SIZE);
+                if frame[0] == reply {
+                    return Ok(frame);
+                }
+            }
+            if count == 0 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        Err("no reply from drive".into())
+    }
+}
+
+/// Link through the debug probe using RTT channels
+pub struct RttLink {
+    session: Session,
+    rtt: Rtt,
+    protocol_up: usize,
+    protocol_down: usize,
+    telemetry_up: usize,
+    status_up: usize,
+}
+
+impl RttLink {
+    pub fn open(chip: &str) -> Result<Self, Error> {
+        let probe = Probe::list_all()
+            .first()
+            .ok_or("no debug probe found")?
+            .open()?;
+        let mut session = probe.attach(chip, Permissions::default())?;
+        let memory_map = session.target().memory_map.clone();
+        let mut core = session.core(0)?;
+        let mut rtt = Rtt::attach(&mut core, &memory_map)?;
+        drop(core);
+
+        let protocol_up = find_channel(rtt.up_channels().iter().map(|c| c.name()), RTT_PROTOCOL)?;
+        let protocol_down =
+            find_channel(rtt.down_channels().iter().map(|c| c.name()), RTT_PROTOCOL)?;
+        let telemetry_up = find_channel(rtt.up_channels().iter().map(|c| c.name()), RTT_TELEMETRY)?;
+        // Firmware without a dedicated channel sends the reports with the protocol frames
+        let status_up = find_channel(rtt.up_channels().iter().map(|c| c.name()), RTT_STATUS)
+            .unwrap_or(protocol_up);
+
+        Ok(Self {
+            session,
+            rtt,
+            protocol_up,
+            protocol_down,
+            telemetry_up,
+            status_up,
+        })
+    }
+}
+
+fn find_channel<'a>(
+    names: impl Iterator<Item = Option<&'a str>>,
+    name: &str,
+) -> Result<usize, Error> {
+    names
+        .enumerate()
+        .find(|(_, n)| *n == Some(name))
+        .map(|(idx, _)| idx)
+        .ok_or_else(|| format!("RTT channel '{name}' not found").into())
+}
+
+impl Link for RttLink {
+    fn send(&mut self, frame: &Frame) -> Result<(), Error> {
+        let mut core = self.session.core(0)?;
+        let channel = self
+            .rtt
+            .down_channels()
+            .get(self.protocol_down)
+            .ok_or("RTT channel lost")?;
+        let mut written = 0;
+        while written < frame.len() {
+            written += channel.write(&mut core, &frame[written..])?;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut core = self.session.core(0)?;
+        let channel = self
+            .rtt
+            .up_channels()
+            .get(self.protocol_up)
+            .ok_or("RTT channel lost")?;
+        Ok(channel.read(&mut core, buf)?)
+    }
+
+    fn telemetry(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut core = self.session.core(0)?;
+        let channel = self
+            .rtt
+            .up_channels()
+            .get(self.telemetry_up)
+            .ok_or("RTT channel lost")?;
+        Ok(channel.read(&mut core, buf)?)
+    }
+
+    fn status(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut core = self.session.core(0)?;
+        let channel = self
+            .rtt
+            .up_channels()
+            .get(self.status_up)
+            .ok_or("RTT channel lost")?;
+        Ok(channel.read(&mut core, buf)?)
+    }
+}
+
+/// Link through a serial port, telemetry points are interleaved with protocol frames
+pub struct SerialLink {
+    port: Box<dyn serialport::SerialPort>,
+}
+
+impl SerialLink {
+    pub fn open(path: &str, baud: u32) -> Result<Self, Error> {
+        let port = serialport::new(path, baud)
+            .timeout(Duration::from_millis(10))
+            .open()?;
+        Ok(Self { port })
+    }
+}
+
+impl Link for SerialLink {
+    fn send(&mut self, frame: &Frame) -> Result<(), Error> {
+        self.port.write_all(frame)?;
+        Ok(())
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.port.read(buf) {
+            Ok(count) => Ok(count),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn telemetry(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.receive(buf)
+    }
+
+    fn status(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.receive(buf)
+    }
+}
@@ -0,0 +1,961 @@
+// Command-line tool for TunePulse configuration and firmware interaction.
+//
+// Talks to the drive either through the debug probe (RTT) or a serial port using the fixed
+// 8 byte frames of the TunePulse protocol.
+
+mod link;
+mod params;
+mod protocol;
+mod sync;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use link::{Error, Link, RttLink, SerialLink};
+use params::ParamId;
+use protocol::{
+    AccessLevel, Command, InjectionPoint, MotorType, PhasePattern, ScopeSignal, StepOutcome,
+};
+use tunepulse_host::telemetry::{Decoder, TIMEBASE_ID};
+use tunepulse_params::codes;
+
+/// Interval the jog command is repeated in, well within the default jog timeout
+const JOG_REPEAT_MS: u64 = 50;
+
+#[derive(Parser)]
+#[command(
+    name = "tunepulse",
+    version,
+    about = "TunePulse drive configuration tool"
+)]
+struct Cli {
+    /// Target chip used for the RTT link
+    #[arg(long, default_value = "STM32G431CBTx")]
+    chip: String,
+
+    /// Use serial port instead of the debug probe
+    #[arg(long)]
+    serial: Option<String>,
+
+    /// Serial port baud rate
+    #[arg(long, default_value_t = 115200)]
+    baud: u32,
+
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// Show driver status
+    Status,
+    /// Print the slow status reports: status, fault, supply, temperature, error counters
+    /// (published at the `status_rate_hz` rate)
+    Health {
+        /// Stop after the number of reports
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Exchange time beacons and print the mapping of device ticks to the host wall clock
+    TimeSync {
+        /// Number of beacons
+        #[arg(long, default_value_t = 10)]
+        count: u32,
+        /// Interval between beacons (ms)
+        #[arg(long, default_value_t = 200)]
+        interval_ms: u64,
+    },
+    /// Show firmware version and hardware identification
+    Info,
+    /// List known parameters
+    List,
+    /// Print parameter definitions as JSON
+    ParamsJson,
+    /// Print the names of the status, fault, event and report codes as JSON
+    CodesJson,
+    /// Read parameters (all if none given)
+    Get { params: Vec<String> },
+    /// Write a parameter
+    Set { param: String, value: u32 },
+    /// Change hot-tunable parameters while running, applied together between control ticks
+    Tune {
+        /// Assignments in the form `name=value`
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Start calibration
+    Calibrate {
+        /// Only refine the zero electrical angle against the stored table
+        #[arg(long)]
+        quick: bool,
+    },
+    /// Swap motor type and phase connection of the disabled drive, restarts the calibration
+    Reconfigure {
+        motor: MotorType,
+        /// Phase connection pattern of the new motor
+        #[arg(long, value_enum, default_value = "abcd")]
+        pattern: PhasePattern,
+    },
+    /// Execute a drive command
+    Exec { command: Command },
+    /// Jog with the configured speed and torque cap, stops when the time is over
+    Jog {
+        /// Jog backward instead of forward
+        #[arg(long)]
+        backward: bool,
+        /// Jog duration (ms)
+        #[arg(long, default_value_t = 1000)]
+        duration_ms: u64,
+    },
+    /// Show the position latched by the capture input
+    Capture {
+        /// Arm the capture for the next edge after reading
+        #[arg(long)]
+        arm: bool,
+    },
+    /// Stream telemetry points to stdout
+    Stream {
+        /// Telemetry ids to show (all if none given)
+        #[arg(long = "id")]
+        ids: Vec<u8>,
+        /// Stop after the number of points
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Route internal signals to the scope channels and stream them
+    Scope {
+        /// Up to two signals to capture
+        #[arg(required = true, num_args = 1..=2)]
+        signals: Vec<ScopeSignal>,
+        /// Capture every n-th control tick
+        #[arg(long, default_value_t = 1)]
+        decimation: u16,
+        /// Send min/max/mean of each decimation window instead of its last value
+        #[arg(long)]
+        envelope: bool,
+        /// Stop after the number of points
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Measure the frequency response and print it as CSV
+    Bode {
+        /// Loop node to excite
+        #[arg(long, value_enum, default_value = "current")]
+        point: InjectionPoint,
+        /// Measured response signal
+        #[arg(long, value_enum, default_value = "position")]
+        response: ScopeSignal,
+        /// Excitation amplitude in units of the excited node
+        #[arg(long)]
+        amplitude: u32,
+        /// Start frequency (Hz)
+        #[arg(long, default_value_t = 1.0)]
+        from: f64,
+        /// End frequency (Hz)
+        #[arg(long, default_value_t = 1000.0)]
+        to: f64,
+        /// Number of points
+        #[arg(long, default_value_t = 20)]
+        points: u32,
+        /// Periods measured at every point
+        #[arg(long, default_value_t = 5)]
+        periods: u32,
+    },
+    /// Run the current and velocity step response tests and show the results
+    StepTest,
+    /// Save all parameters to a TOML file
+    Save { file: PathBuf },
+    /// Load parameters from a TOML file
+    Load { file: PathBuf },
+    /// Download the calibration table into a binary file
+    TableExport { file: PathBuf },
+    /// Upload a calibration table exported from this or an identical unit
+    TableImport {
+        file: PathBuf,
+        /// Table of a golden unit, only the offset is trimmed to this unit (single point)
+        #[arg(long)]
+        golden: bool,
+    },
+    /// Run a quick self-test of the drive
+    SelfTest,
+    /// Run the on-device production test and show the report, fails if the unit failed
+    ProductionTest,
+    /// Show the calibration report: table deviation, trim spread and torque ripple
+    CalibrationReport,
+    /// Run the first-run setup wizard on the disabled drive and show its results
+    Wizard,
+    /// Show the peak current, temperature, speed and following error since the last clear
+    Peaks {
+        /// Clear every register by the read returning it
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Unlock an access level for protected parameters, kept until `lock` or reset
+    Unlock { level: AccessLevel },
+    /// Return to the user access level
+    Lock,
+    /// Show the factory data of the unit
+    FactoryInfo,
+    /// Write the factory data, once per unit (unlocks the factory level for the write)
+    FactoryWrite {
+        /// Serial number
+        #[arg(long)]
+        serial: u32,
+        /// Hardware revision of the board
+        #[arg(long)]
+        hw_rev: u16,
+        /// Calibration date (unix time), now if not given
+        #[arg(long)]
+        date: Option<u32>,
+        /// Supply voltage measurement gain correction (0.01 %)
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        supply_trim: i16,
+        /// Temperature sensor offset (raw ADC)
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        temp_offset: i16,
+    },
+}
+
+/// Configuration file layout
+#[derive(Serialize, Deserialize)]
+struct Config {
+    params: BTreeMap<String, u32>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Error> {
+    if let Cmd::List = cli.command {
+        for p in params::PARAMS.iter() {
+            println!(
+                "{:>3} {:<20} {:<8} {:<3} {:<3} {:<8} default {:<10} range {}..={}",
+                p.id as u16,
+                p.name,
+                p.kind.name(),
+                p.unit,
+                if p.hot { "hot" } else { "" },
+                p.access.name(),
+                p.default,
+                p.min,
+                p.max
+            );
+        }
+        return Ok(());
+    }
+    if let Cmd::ParamsJson = cli.command {
+        let mut json = String::new();
+        tunepulse_params::write_json(&mut json)?;
+        print!("{json}");
+        return Ok(());
+    }
+    if let Cmd::CodesJson = cli.command {
+        let mut json = String::new();
+        codes::write_codes_json(&mut json)?;
+        print!("{json}");
+        return Ok(());
+    }
+
+    let mut link: Box<dyn Link> = match &cli.serial {
+        Some(path) => Box::new(SerialLink::open(path, cli.baud)?),
+        None => Box::new(RttLink::open(&cli.chip)?),
+    };
+    let link = link.as_mut();
+
+    match cli.command {
+        Cmd::List | Cmd::ParamsJson | Cmd::CodesJson => unreachable!(),
+        Cmd::Status => {
+            let status = read_status(link)?;
+            let reply = link.request(&protocol::state_read(), protocol::STATE)?;
+            let state = protocol::State::decode(&reply);
+            println!("status:   {}", status.status_name());
+            if let Some(stage) = state.stage_name() {
+                println!("stage:    {stage} ({} %)", state.progress);
+            }
+            println!("fault:    {}", fault_name(status.fault));
+            println!("flags:    {:#04x}", status.flags);
+            println!("position: {}", status.position);
+            println!("energized: {}", if state.energized { "yes" } else { "no" });
+            println!("at velocity: {}", if state.at_velocity { "yes" } else { "no" });
+            println!("zero speed: {}", if state.zero_speed { "yes" } else { "no" });
+            if status.unsaved {
+                println!("parameters changed but not saved, run `exec save-params`");
+            }
+        }
+        Cmd::Health { count } => health(link, count)?,
+        Cmd::TimeSync { count, interval_ms } => time_sync(link, count, interval_ms)?,
+        Cmd::Info => {
+            let info = read_device_info(link)?;
+            let [major, minor, patch] = info.version;
+            let dirty = if info.git_dirty { "-dirty" } else { "" };
+            println!(
+                "firmware:   {major}.{minor}.{patch} ({:08x}{dirty})",
+                info.git_hash
+            );
+            println!("board:      {}", info.board_name());
+            let uid: String = info.uid.iter().map(|b| format!("{b:02x}")).collect();
+            println!("uid:        {uid}");
+            println!("protocol:   {}", info.protocol);
+            println!("parameters: {}", info.param_count);
+            println!("calibrated: {}", info.calibrated);
+        }
+        Cmd::Get { params: keys } => {
+            let defs: Vec<_> = if keys.is_empty() {
+                params::PARAMS.iter().collect()
+            } else {
+                keys.iter()
+                    .map(|k| find_param(k))
+                    .collect::<Result<_, _>>()?
+            };
+            for def in defs {
+                println!("{} = {}", def.name, read_param(link, def.id as u16)?);
+            }
+        }
+        Cmd::Set { param, value } => {
+            let def = find_param(&param)?;
+            check_compatible(link)?;
+            write_param(link, def.id as u16, value)?;
+            println!("{} = {}", def.name, value);
+        }
+        Cmd::Tune { values } => {
+            let mut staged = Vec::new();
+            for assignment in &values {
+                let (key, value) = assignment
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected name=value, got '{assignment}'"))?;
+                let def = find_param(key)?;
+                if !def.hot {
+                    return Err(format!("'{}' can't be changed while running", def.name).into());
+                }
+                staged.push((def, value.parse::<u32>()?));
+            }
+            check_compatible(link)?;
+            for (def, value) in &staged {
+                if let Err(e) = stage_param(link, def.id as u16, *value) {
+                    execute(link, Command::DiscardStaged)?;
+                    return Err(format!("{}: {e}", def.name).into());
+                }
+            }
+            execute(link, Command::ApplyStaged)?;
+            for (def, value) in &staged {
+                println!("{} = {}", def.name, value);
+            }
+        }
+        Cmd::Calibrate { quick } => {
+            let command = if quick {
+                Command::QuickRecalibrate
+            } else {
+                Command::Calibrate
+            };
+            execute(link, command)?;
+            println!("calibration started");
+        }
+        Cmd::Reconfigure { motor, pattern } => {
+            let arg = protocol::reconfigure_arg(motor, pattern);
+            let frame = protocol::command_arg(Command::ReconfigureMotor, arg);
+            let reply = link.request(&frame, protocol::COMMAND_RESULT)?;
+            protocol::check_result(reply[1])
+                .map_err(|error| format!("{error} (disable the drive and wait until idle)"))?;
+            println!("reconfigured to {motor:?} {pattern:?}, calibration started");
+        }
+        Cmd::Exec { command } => {
+            execute(link, command)?;
+            println!("ok");
+        }
+        Cmd::Jog {
+            backward,
+            duration_ms,
+        } => {
+            let command = if backward {
+                Command::JogBackward
+            } else {
+                Command::JogForward
+            };
+            // The drive stops by itself if the repetitions stop, e.g. the tool is interrupted
+            let start = std::time::Instant::now();
+            while start.elapsed() < std::time::Duration::from_millis(duration_ms) {
+                execute(link, command)?;
+                std::thread::sleep(std::time::Duration::from_millis(JOG_REPEAT_MS));
+            }
+            execute(link, Command::JogStop)?;
+            println!("ok");
+        }
+        Cmd::Capture { arm } => {
+            let reply = link.request(&protocol::capture_read(), protocol::CAPTURE)?;
+            let capture = protocol::Capture::decode(&reply);
+            match capture.position {
+                Some(position) => println!("position: {position}"),
+                None => println!("position: none"),
+            }
+            println!("count:    {}", capture.count);
+            println!("armed:    {}", capture.armed || arm);
+            if arm {
+                execute(link, Command::ArmCapture)?;
+            }
+        }
+        Cmd::Stream { ids, count } => stream(link, &ids, count)?,
+        Cmd::Scope {
+            signals,
+            decimation,
+            envelope,
+            count,
+        } => {
+            check_compatible(link)?;
+            let ch1 = signals.get(1).map_or(0, |signal| *signal as u32);
+            stage_param(link, ParamId::ScopeChannel0 as u16, signals[0] as u32)?;
+            stage_param(link, ParamId::ScopeChannel1 as u16, ch1)?;
+            stage_param(link, ParamId::ScopeDecimation as u16, decimation as u32)?;
+            let mask = if envelope { 0b11 } else { 0 };
+            stage_param(link, ParamId::ScopeEnvelope as u16, mask)?;
+            execute(link, Command::ApplyStaged)?;
+            let ids: Vec<u8> = signals.iter().map(|signal| *signal as u8).collect();
+            stream(link, &ids, count)?;
+        }
+        Cmd::Bode {
+            point,
+            response,
+            amplitude,
+            from,
+            to,
+            points,
+            periods,
+        } => {
+            check_compatible(link)?;
+            let config = [
+                (ParamId::ExcitationPoint, point as u32),
+                (ParamId::ExcitationAmplitude, amplitude),
+                (ParamId::ExcitationFrequency, (from * 1000.0) as u32),
+                (ParamId::ExcitationFrequencyEnd, (to * 1000.0) as u32),
+                (ParamId::BodePoints, points),
+                (ParamId::BodePeriods, periods),
+                (ParamId::BodeResponse, response as u32),
+            ];
+            for (id, value) in config {
+                stage_param(link, id as u16, value)?;
+            }
+            execute(link, Command::ApplyStaged)?;
+            execute(link, Command::StartFrequencyResponse)?;
+            while read_status(link)?.flags & protocol::STATUS_MEASURING != 0 {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            println!("frequency_hz,gain_db,phase_deg");
+            for index in 0..points as u8 {
+                let page0 = link.request(&protocol::response_read(index, 0), protocol::RESPONSE)?;
+                let page1 = link.request(&protocol::response_read(index, 1), protocol::RESPONSE)?;
+                let Some(p) = protocol::ResponsePoint::decode(&page0, &page1) else {
+                    break; // Measurement aborted
+                };
+                println!("{:.3},{:.2},{:.1}", p.frequency_hz, p.gain_db, p.phase_deg);
+            }
+        }
+        Cmd::StepTest => {
+            execute(link, Command::StartStepTest)?;
+            while read_status(link)?.flags & protocol::STATUS_MEASURING != 0 {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            for (index, name) in protocol::STEP_TESTS.iter().enumerate() {
+                let frame = protocol::step_result_read(index as u8);
+                let reply = link.request(&frame, protocol::STEP_RESULT)?;
+                match protocol::step_result(&reply) {
+                    Some(result) => {
+                        let rise = result.rise_time.map_or("not reached".into(), |rise| {
+                            format!("{:.1} ms", rise * 1000.0)
+                        });
+                        println!(
+                            "{name:8} rise: {rise}, overshoot: {:.1} %, steady-state error: {:.1} %",
+                            result.overshoot_pct, result.error_pct
+                        );
+                    }
+                    None => println!("{name:8} aborted"),
+                }
+            }
+        }
+        Cmd::ProductionTest => production_test(link)?,
+        Cmd::Wizard => wizard(link)?,
+        Cmd::CalibrationReport => {
+            for def in codes::CALIBRATION_METRICS.iter() {
+                let frame = protocol::calibration_report_read(def.code);
+                let reply = link.request(&frame, protocol::CALIBRATION_REPORT)?;
+                let value = protocol::calibration_metric(&reply)
+                    .map_or("not measured".into(), |value| with_unit(value, def.unit));
+                println!("{}: {value}", def.name);
+            }
+        }
+        Cmd::Peaks { clear } => {
+            for def in codes::PEAK_VALUES.iter() {
+                let reply = link.request(&protocol::peak_read(def.code, clear), protocol::PEAK)?;
+                let value = protocol::peak_value(&reply)
+                    .map_or("not sampled".into(), |value| with_unit(value, def.unit));
+                println!("{}: {value}", def.name);
+            }
+        }
+        Cmd::Unlock { level } => {
+            unlock(link, level)?;
+            println!("{level:?} access unlocked");
+        }
+        Cmd::Lock => {
+            execute(link, Command::Lock)?;
+            println!("locked");
+        }
+        Cmd::FactoryInfo => {
+            let Some(data) = read_factory_data(link)? else {
+                return Err("unit has no factory data".into());
+            };
+            println!("serial:       {}", data.serial);
+            println!("hardware rev: {}", data.hardware_rev);
+            println!("calibrated:   {} (unix)", data.date);
+            println!("supply trim:  {} (0.01 %)", data.supply_trim);
+            println!("temp offset:  {} (ADC)", data.temp_offset);
+        }
+        Cmd::FactoryWrite {
+            serial,
+            hw_rev,
+            date,
+            supply_trim,
+            temp_offset,
+        } => {
+            if read_factory_data(link)?.is_some() {
+                return Err("unit already has factory data".into());
+            }
+            let date = date.unwrap_or_else(|| {
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+                now.map_or(0, |now| now.as_secs() as u32)
+            });
+            let data = protocol::FactoryData {
+                serial,
+                hardware_rev: hw_rev,
+                date,
+                supply_trim,
+                temp_offset,
+            };
+            unlock(link, AccessLevel::Factory)?;
+            let result = write_factory_data(link, &data);
+            execute(link, Command::Lock)?;
+            result?;
+            // The write runs in the background, the data reads back once it was accepted
+            if read_factory_data(link)? != Some(data) {
+                return Err("factory data readback mismatch".into());
+            }
+            println!("factory data written, serial {serial}");
+        }
+        Cmd::Save { file } => {
+            let mut config = Config {
+                params: BTreeMap::new(),
+            };
+            for def in params::PARAMS.iter() {
+                config
+                    .params
+                    .insert(def.name.to_string(), read_param(link, def.id as u16)?);
+            }
+            std::fs::write(&file, toml::to_string(&config)?)?;
+            println!(
+                "saved {} parameters to {}",
+                config.params.len(),
+                file.display()
+            );
+        }
+        Cmd::Load { file } => {
+            let config: Config = toml::from_str(&std::fs::read_to_string(&file)?)?;
+            check_compatible(link)?;
+            for (name, value) in &config.params {
+                let def = find_param(name)?;
+                write_param(link, def.id as u16, *value)?;
+            }
+            println!(
+                "loaded {} parameters from {}",
+                config.params.len(),
+                file.display()
+            );
+        }
+        Cmd::TableExport { file } => {
+            execute(link, Command::ExportTable)?;
+            let image = read_table(link)?;
+            std::fs::write(&file, &image)?;
+            println!(
+                "saved calibration table ({} bytes) to {}",
+                image.len(),
+                file.display()
+            );
+        }
+        Cmd::TableImport { file, golden } => {
+            let image = std::fs::read(&file)?;
+            protocol::check_table_image(&image)?;
+            for (index, chunk) in image.chunks(protocol::TABLE_CHUNK_SIZE).enumerate() {
+                let mut data = [0; protocol::TABLE_CHUNK_SIZE];
+                data[..chunk.len()].copy_from_slice(chunk);
+                let offset = (index * protocol::TABLE_CHUNK_SIZE) as u16;
+                let frame = protocol::table_write(offset, data);
+                let reply = link.request(&frame, protocol::TABLE_DATA)?;
+                protocol::table_data(&reply)?;
+            }
+            if golden {
+                execute(link, Command::ImportGoldenTable)?;
+                println!(
+                    "loaded golden table from {}, offset trim started",
+                    file.display()
+                );
+            } else {
+                execute(link, Command::ImportTable)?;
+                println!("loaded calibration table from {}", file.display());
+            }
+        }
+        Cmd::SelfTest => self_test(link)?,
+    }
+    Ok(())
+}
+
+fn find_param(key: &str) -> Result<&'static params::ParamDef, Error> {
+    params::find(key).ok_or_else(|| format!("unknown parameter '{key}'").into())
+}
+
+fn read_param(link: &mut dyn Link, id: u16) -> Result<u32, Error> {
+    let reply = link.request(&protocol::param_read(id), protocol::PARAM_VALUE)?;
+    Ok(protocol::param_value(&reply)?)
+}
+
+fn write_param(link: &mut dyn Link, id: u16, value: u32) -> Result<(), Error> {
+    let reply = link.request(&protocol::param_write(id, value), protocol::PARAM_VALUE)?;
+    protocol::param_value(&reply)?;
+    Ok(())
+}
+
+fn stage_param(link: &mut dyn Link, id: u16, value: u32) -> Result<(), Error> {
+    let reply = link.request(&protocol::param_stage(id, value), protocol::PARAM_VALUE)?;
+    protocol::param_value(&reply)?;
+    Ok(())
+}
+
+fn execute(link: &mut dyn Link, command: Command) -> Result<(), Error> {
+    let reply = link.request(&protocol::command(command), protocol::COMMAND_RESULT)?;
+    Ok(protocol::check_result(reply[1])?)
+}
+
+fn read_status(link: &mut dyn Link) -> Result<protocol::Status, Error> {
+    let reply = link.request(&protocol::status_read(), protocol::STATUS)?;
+    Ok(protocol::Status::decode(&reply))
+}
+
+fn read_device_info(link: &mut dyn Link) -> Result<protocol::DeviceInfo, Error> {
+    let mut pages = Vec::new();
+    for page in 0..protocol::DEVICE_INFO_PAGES {
+        pages.push(link.request(&protocol::device_info_read(page), protocol::DEVICE_INFO)?);
+    }
+    Ok(protocol::DeviceInfo::decode(&pages).ok_or("incomplete device information")?)
+}
+
+fn unlock(link: &mut dyn Link, level: AccessLevel) -> Result<(), Error> {
+    let frame = protocol::command_arg(Command::Unlock, level.key());
+    let reply = link.request(&frame, protocol::COMMAND_RESULT)?;
+    Ok(protocol::check_result(reply[1])?)
+}
+
+/// Stages all factory data pages and stores them, needs the factory access level
+fn write_factory_data(link: &mut dyn Link, data: &protocol::FactoryData) -> Result<(), Error> {
+    for (page, payload) in (0..).zip(data.pages()) {
+        let frame = protocol::factory_write(page, payload);
+        let reply = link.request(&frame, protocol::FACTORY_DATA)?;
+        if reply[1] != page {
+            return Err("factory data staging refused".into());
+        }
+    }
+    execute(link, Command::WriteFactoryData)
+}
+
+/// Reads the factory data, `None` if the unit has none
+fn read_factory_data(link: &mut dyn Link) -> Result<Option<protocol::FactoryData>, Error> {
+    let mut pages = Vec::new();
+    for page in 0..protocol::FACTORY_PAGES {
+        pages.push(link.request(&protocol::factory_read(page), protocol::FACTORY_DATA)?);
+    }
+    Ok(protocol::FactoryData::decode(&pages))
+}
+
+/// Reads the table image prepared by `Command::ExportTable`, the header tells its length
+fn read_table(link: &mut dyn Link) -> Result<Vec<u8>, Error> {
+    let mut image = Vec::new();
+    let mut len = protocol::TABLE_HEADER_SIZE;
+    while image.len() < len {
+        let frame = protocol::table_read(image.len() as u16);
+        let reply = link.request(&frame, protocol::TABLE_DATA)?;
+        image.extend_from_slice(&protocol::table_data(&reply)?);
+        if image.len() == protocol::TABLE_HEADER_SIZE {
+            len += protocol::table_payload_len(&image);
+        }
+    }
+    image.truncate(len);
+    protocol::check_table_image(&image)?;
+    Ok(image)
+}
+
+/// Refuses to write parameters to firmware built against a different parameter table
+fn check_compatible(link: &mut dyn Link) -> Result<(), Error> {
+    let info = read_device_info(link)?;
+    if info.protocol != protocol::PROTOCOL_VERSION {
+        return Err(format!(
+            "firmware protocol v{} is not supported (expected v{})",
+            info.protocol,
+            protocol::PROTOCOL_VERSION
+        )
+        .into());
+    }
+    if info.param_count as usize != params::PARAM_COUNT {
+        return Err(format!(
+            "firmware has {} parameters, this tool knows {}; update the tool",
+            info.param_count,
+            params::PARAM_COUNT
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn stream(link: &mut dyn Link, ids: &[u8], count: Option<usize>) -> Result<(), Error> {
+    let mut buf = [0u8; 4096];
+    let mut decoder = Decoder::new();
+    let mut printed = 0;
+    loop {
+        let read = link.telemetry(&mut buf)?;
+        for point in decoder.feed(&buf[..read]) {
+            if point.id == TIMEBASE_ID || (!ids.is_empty() && !ids.contains(&point.signal())) {
+                continue;
+            }
+            println!("{}\t{}\t{}", point.timestamp, point.id, point.value);
+            printed += 1;
+            if count.is_some_and(|count| printed >= count) {
+                return Ok(());
+            }
+        }
+        if read == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+}
+
+/// Prints the reports of the slow status channel
+fn health(link: &mut dyn Link, count: Option<usize>) -> Result<(), Error> {
+    let mut buf = [0u8; 256];
+    let mut pending = Vec::new();
+    let mut report = protocol::StatusReport::default();
+    let mut printed = 0;
+    loop {
+        let read = link.status(&mut buf)?;
+        pending.extend_from_slice(&buf[..read]);
+        while pending.len() >= protocol::FRAME_SIZE {
+            let frame: protocol::Frame = pending[..protocol::FRAME_SIZE].try_into().unwrap();
+            pending.drain(..protocol::FRAME_SIZE);
+            if frame[0] != protocol::STATUS_REPORT || !report.apply(&frame) {
+                continue;
+            }
+            let unsaved = if report.unsaved { " (unsaved)" } else { "" };
+            println!(
+                "{}{unsaved}\tfault {}\tsupply {} mV\ttemperature {}\t\
+                 encoder rejected {} retries {}\toverflows {}",
+                report.status_name(),
+                fault_name(report.fault),
+                report.supply_mv,
+                report.temperature,
+                report.encoder_rejected,
+                report.encoder_retries,
+                report.overflows
+            );
+            printed += 1;
+            if count.is_some_and(|count| printed >= count) {
+                return Ok(());
+            }
+        }
+        if read == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+}
+
+/// Exchanges time beacons and prints the fitted tick to wall clock relation
+fn time_sync(link: &mut dyn Link, count: u32, interval_ms: u64) -> Result<(), Error> {
+    let mut clock = sync::TickClock::default();
+    let mut last = 0;
+    for seq in 0..count {
+        let sent = std::time::SystemTime::now();
+        let frame = protocol::time_beacon(seq as u8, sync::beacon_time(sent));
+        let reply = link.request(&frame, protocol::TIME_SYNC)?;
+        let received = std::time::SystemTime::now();
+        let (reply_seq, tick) = protocol::time_sync(&reply);
+        if reply_seq != seq as u8 {
+            continue;
+        }
+        last = clock.add(sent, received, tick);
+        let round_trip = received.duration_since(sent).unwrap_or_default();
+        println!(
+            "{seq:3}\ttick {tick:10}\tround trip {:.2} ms",
+            round_trip.as_secs_f64() * 1e3
+        );
+        std::thread::sleep(std::time::Duration::from_millis(interval_ms));
+    }
+    let Some(fit) = clock.fit() else {
+        return Err("not enough replies to fit the clock".into());
+    };
+    println!("tick rate:  {:.3} Hz", 1.0 / fit.tick_period);
+    println!("tick 0 at:  {:.6} s (unix)", fit.origin);
+    println!("tick {last} at: {:.6} s (unix)", fit.wall_time(last));
+    if let Some(best) = clock.best_round_trip() {
+        println!(
+            "accuracy:   +/-{:.2} ms (half the best round trip)",
+            best * 1e3 / 2.0
+        );
+    }
+    Ok(())
+}
+
+/// Runs the production test and prints the report
+fn production_test(link: &mut dyn Link) -> Result<(), Error> {
+    execute(link, Command::StartProductionTest)?;
+    while read_status(link)?.flags & protocol::STATUS_MEASURING != 0 {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let mut unit_passed = false;
+    for def in codes::PRODUCTION_METRICS.iter() {
+        let frame = protocol::production_result_read(def.code);
+        let reply = link.request(&frame, protocol::PRODUCTION_RESULT)?;
+        let Some(result) = protocol::ProductionResult::decode(&reply) else {
+            return Err("production test didn't finish".into());
+        };
+        let verdict = if result.passed { " OK " } else { "FAIL" };
+        let value = result
+            .value
+            .map_or("not measured".into(), |value| with_unit(value, def.unit));
+        println!("[{verdict}] {}: {value}", def.name);
+        unit_passed = result.unit_passed;
+    }
+    if !unit_passed {
+        return Err("production test failed".into());
+    }
+    println!("production test passed");
+    Ok(())
+}
+
+/// Runs the setup wizard and prints the outcome of every step with its results
+fn wizard(link: &mut dyn Link) -> Result<(), Error> {
+    let frame = protocol::command(Command::StartWizard);
+    let reply = link.request(&frame, protocol::COMMAND_RESULT)?;
+    protocol::check_result(reply[1])
+        .map_err(|error| format!("{error} (disable the drive and wait until idle)"))?;
+    while read_status(link)?.flags & protocol::STATUS_MEASURING != 0 {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    let mut results = Vec::new();
+    for def in codes::WIZARD_RESULTS.iter() {
+        let frame = protocol::wizard_result_read(def.code);
+        let reply = link.request(&frame, protocol::WIZARD_RESULT)?;
+        let result = protocol::WizardResult::decode(&reply).ok_or("wizard result missing")?;
+        results.push(result);
+    }
+    // Outcome of each step from its results, calibration has none and passed once the gain
+    // suggestion ran
+    let mut outcomes = [StepOutcome::Pending; codes::WIZARD_STEPS.len()];
+    for (&step, result) in protocol::WIZARD_RESULT_STEPS.iter().zip(&results) {
+        outcomes[step as usize] = result.outcome;
+    }
+    outcomes[4] = if outcomes[5] != StepOutcome::Pending {
+        StepOutcome::Passed
+    } else if outcomes[3] == StepOutcome::Passed {
+        StepOutcome::Failed // Calibration failed or the wizard was aborted during it
+    } else {
+        StepOutcome::Pending
+    };
+    for step in codes::WIZARD_STEPS.iter() {
+        println!("[{:?}] {}", outcomes[step.code as usize], step.name);
+        let entries = codes::WIZARD_RESULTS.iter().zip(&results);
+        let entries = entries.zip(protocol::WIZARD_RESULT_STEPS);
+        for ((def, result), _) in entries.filter(|(_, result_step)| *result_step == step.code) {
+            let Some(value) = result.value else {
+                continue;
+            };
+            let value = match def.code {
+                6 => codes::find_code(&codes::MOTOR_PRESETS, value as u8)
+                    .map_or(value.to_string(), |preset| {
+                        format!("{value} ({})", preset.name)
+                    }),
+                _ => with_unit(value, def.unit),
+            };
+            println!("    {}: {value}", def.name);
+        }
+    }
+    let passed = outcomes
+        .iter()
+        .all(|outcome| matches!(outcome, StepOutcome::Passed | StepOutcome::Skipped));
+    if !passed {
+        return Err("setup wizard failed".into());
+    }
+    println!("setup passed, apply the suggestion with `set motor_preset <preset>`");
+    Ok(())
+}
+
+/// Formats a reported value with its unit
+fn with_unit(value: impl std::fmt::Display, unit: &str) -> String {
+    format!("{value} {unit}").trim_end().to_string()
+}
+
+/// Formats a fault code with its name
+fn fault_name(code: u8) -> String {
+    format!("{} ({code})", codes::code_name(&codes::FAULT_CODES, code))
+}
+
+/// Checks communication, driver state and parameter consistency
+fn self_test(link: &mut dyn Link) -> Result<(), Error> {
+    let mut failed = 0;
+    let mut check = |name: &str, result: Result<String, String>| match result {
+        Ok(info) => println!("[ OK ] {name}: {info}"),
+        Err(info) => {
+            println!("[FAIL] {name}: {info}");
+            failed += 1;
+        }
+    };
+
+    let status = read_status(link)?;
+    check(
+        "driver status",
+        match status.status {
+            1 => Ok(status.status_name().into()),
+            _ => Err(status.status_name().into()),
+        },
+    );
+    check(
+        "fault",
+        match status.fault {
+            0 => Ok("none".into()),
+            code => Err(fault_name(code)),
+        },
+    );
+
+    for def in params::PARAMS.iter() {
+        let result = match read_param(link, def.id as u16) {
+            Ok(v) if v >= def.min && v <= def.max => Ok(v.to_string()),
+            Ok(v) => Err(format!("{v} outside {}..={}", def.min, def.max)),
+            Err(e) => Err(e.to_string()),
+        };
+        check(def.name, result);
+    }
+
+    // Write the current value back to verify the write path without changing configuration
+    let id = ParamId::EventMask as u16;
+    let mask = read_param(link, id)?;
+    check(
+        "parameter write",
+        write_param(link, id, mask)
+            .map(|_| "ok".into())
+            .map_err(|e| e.to_string()),
+    );
+
+    if failed > 0 {
+        return Err(format!("self-test failed: {failed} check(s)").into());
+    }
+    println!("self-test passed");
+    Ok(())
+}
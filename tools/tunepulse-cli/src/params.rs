@@ -0,0 +1,11 @@
+// Parameter lookup on top of the definitions shared with the firmware.
+
+pub use tunepulse_params::{ParamDef, ParamId, PARAMS, PARAM_COUNT};
+
+/// Finds a parameter by name or numeric identifier
+pub fn find(key: &str) -> Option<&'static ParamDef> {
+    match key.parse::<u16>() {
+        Ok(id) => ParamId::from_raw(id).map(|id| &PARAMS[id as usize]),
+        Err(_) => tunepulse_params::find_by_name(key),
+    }
+}
@@ -0,0 +1,701 @@
+// Host side of the TunePulse protocol, mirrors `tunepulse_algo::protocol`.
+
+use tunepulse_host::step::StepMetrics;
+use tunepulse_host::units;
+use tunepulse_params::codes;
+
+/// Protocol revision this tool was built for
+pub const PROTOCOL_VERSION: u8 = 1;
+
+pub const FRAME_SIZE: usize = 8;
+pub type Frame = [u8; FRAME_SIZE];
+
+pub const PARAM_READ: u8 = 0x10;
+pub const PARAM_WRITE: u8 = 0x11;
+pub const PARAM_VALUE: u8 = 0x12;
+pub const PARAM_STAGE: u8 = 0x13;
+pub const COMMAND: u8 = 0x20;
+pub const COMMAND_RESULT: u8 = 0x21;
+pub const STATUS_READ: u8 = 0x30;
+pub const STATUS: u8 = 0x31;
+pub const DEVICE_INFO_READ: u8 = 0x40;
+pub const DEVICE_INFO: u8 = 0x41;
+pub const RESPONSE_READ: u8 = 0x50;
+pub const RESPONSE: u8 = 0x51;
+pub const CAPTURE_READ: u8 = 0x60;
+pub const CAPTURE: u8 = 0x61;
+pub const STEP_RESULT_READ: u8 = 0x80;
+pub const STEP_RESULT: u8 = 0x81;
+pub const TABLE_READ: u8 = 0x90;
+pub const TABLE_WRITE: u8 = 0x91;
+pub const TABLE_DATA: u8 = 0x92;
+pub const PRODUCTION_RESULT_READ: u8 = 0xA0;
+pub const PRODUCTION_RESULT: u8 = 0xA1;
+pub const FACTORY_READ: u8 = 0xB0;
+pub const FACTORY_WRITE: u8 = 0xB1;
+pub const FACTORY_DATA: u8 = 0xB2;
+pub const CALIBRATION_REPORT_READ: u8 = 0xC0;
+pub const CALIBRATION_REPORT: u8 = 0xC1;
+pub const WIZARD_RESULT_READ: u8 = 0xC2;
+pub const WIZARD_RESULT: u8 = 0xC3;
+pub const PEAK_READ: u8 = 0xC4;
+pub const PEAK: u8 = 0xC5;
+pub const STATE_READ: u8 = 0xD0;
+pub const STATE: u8 = 0xD1;
+pub const STATUS_REPORT: u8 = 0xE2;
+pub const TIME_BEACON: u8 = 0xF0;
+pub const TIME_SYNC: u8 = 0xF1;
+
+/// Status flag: frequency response measurement, step or production test, setup wizard running
+pub const STATUS_MEASURING: u8 = 1 << 5;
+
+/// Number of device information pages
+pub const DEVICE_INFO_PAGES: u8 = 4;
+
+/// Number of factory data pages
+pub const FACTORY_PAGES: u8 = 3;
+
+/// Commands understood by the firmware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[repr(u8)]
+pub enum Command {
+    Calibrate = 1,
+    QuickRecalibrate = 2,
+    StartSequence = 3,
+    StopSequence = 4,
+    Enable = 5,
+    Disable = 6,
+    ApplyStaged = 7,
+    DiscardStaged = 8,
+    StartExcitation = 9,
+    StopExcitation = 10,
+    StartFrequencyResponse = 11,
+    SaveParams = 12,
+    ArmCapture = 13,
+    StartFollow = 14,
+    StopFollow = 15,
+    ClearCollision = 16,
+    JogForward = 17,
+    JogBackward = 18,
+    JogStop = 19,
+    StartStepTest = 20,
+    ExportTable = 21,
+    ImportTable = 22,
+    ImportGoldenTable = 23,
+    StartProductionTest = 24,
+    WriteFactoryData = 25,
+    Unlock = 26,
+    Lock = 27,
+    ReconfigureMotor = 28,
+    StartWizard = 29,
+    AbortWizard = 30,
+    ClearPeaks = 31,
+}
+
+/// Access levels which can be unlocked, see `tunepulse_params::AccessLevel`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AccessLevel {
+    Advanced,
+    Factory,
+}
+
+impl AccessLevel {
+    /// Key unlocking the level
+    pub fn key(self) -> u32 {
+        match self {
+            AccessLevel::Advanced => tunepulse_params::ADVANCED_KEY,
+            AccessLevel::Factory => tunepulse_params::FACTORY_KEY,
+        }
+    }
+}
+
+/// Motor types, see `MotorType` of the firmware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[repr(u16)]
+pub enum MotorType {
+    Dc = 0xFFFF,
+    Bldc = 3,
+    Step = 4,
+}
+
+/// Phase connection patterns, see `PhasePattern` of the firmware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[repr(u8)]
+pub enum PhasePattern {
+    Abcd = 0b11100100,
+    Acdb = 0b01111000,
+    Adbc = 0b10011100,
+    Dcab = 0b01001011,
+}
+
+/// Argument of `Command::ReconfigureMotor`
+pub fn reconfigure_arg(motor: MotorType, pattern: PhasePattern) -> u32 {
+    motor as u32 | (pattern as u32) << 16
+}
+
+/// Loop node excited by the signal generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[repr(u8)]
+pub enum InjectionPoint {
+    Current = 0,
+    Velocity = 1,
+    Position = 2,
+}
+
+/// Internal signals which can be routed to the scope channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[repr(u8)]
+pub enum ScopeSignal {
+    Position = 1,
+    Speed = 2,
+    Target = 3,
+    PositionError = 4,
+    AngleEl = 5,
+    CurrentCommand = 6,
+    FrictionCurrent = 7,
+    DampingCurrent = 8,
+    LoadTorque = 9,
+    LoadAngle = 10,
+    SupplyVoltage = 11,
+    DutyA = 12,
+    DutyB = 13,
+    DutyC = 14,
+    DutyD = 15,
+    Excitation = 16,
+    Overflows = 17,
+    SampleOffset = 18,
+    AngleRaw = 19,
+    AngleFiltered = 20,
+    AngleElCorrected = 21,
+    EncoderGlitches = 22,
+    EncoderErrors = 23,
+    CurrentD = 24,
+    CurrentQ = 25,
+}
+
+/// Converts reply result code into a readable error
+pub fn check_result(code: u8) -> Result<(), String> {
+    if code == 0 {
+        return Ok(());
+    }
+    match codes::find_code(&codes::REPLY_RESULTS, code) {
+        Some(def) => Err(def.description.into()),
+        None => Err(format!("error code {code}")),
+    }
+}
+
+pub fn param_read(id: u16) -> Frame {
+    let id = id.to_le_bytes();
+    [PARAM_READ, 0, id[0], id[1], 0, 0, 0, 0]
+}
+
+pub fn param_write(id: u16, value: u32) -> Frame {
+    let id = id.to_le_bytes();
+    let v = value.to_le_bytes();
+    [PARAM_WRITE, 0, id[0], id[1], v[0], v[1], v[2], v[3]]
+}
+
+pub fn param_stage(id: u16, value: u32) -> Frame {
+    let id = id.to_le_bytes();
+    let v = value.to_le_bytes();
+    [PARAM_STAGE, 0, id[0], id[1], v[0], v[1], v[2], v[3]]
+}
+
+pub fn command(command: Command) -> Frame {
+    [COMMAND, command as u8, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn command_arg(command: Command, arg: u32) -> Frame {
+    let a = arg.to_le_bytes();
+    [COMMAND, command as u8, 0, 0, a[0], a[1], a[2], a[3]]
+}
+
+pub fn status_read() -> Frame {
+    [STATUS_READ, 0, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn device_info_read(page: u8) -> Frame {
+    [DEVICE_INFO_READ, page, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn response_read(index: u8, page: u8) -> Frame {
+    [RESPONSE_READ, index, page, 0, 0, 0, 0, 0]
+}
+
+pub fn capture_read() -> Frame {
+    [CAPTURE_READ, 0, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn step_result_read(test: u8) -> Frame {
+    [STEP_RESULT_READ, test, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn production_result_read(metric: u8) -> Frame {
+    [PRODUCTION_RESULT_READ, metric, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn calibration_report_read(metric: u8) -> Frame {
+    [CALIBRATION_REPORT_READ, metric, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn wizard_result_read(result: u8) -> Frame {
+    [WIZARD_RESULT_READ, result, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn peak_read(value: u8, clear: bool) -> Frame {
+    [PEAK_READ, value, clear as u8, 0, 0, 0, 0, 0]
+}
+
+pub fn state_read() -> Frame {
+    [STATE_READ, 0, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn time_beacon(seq: u8, host_ms: u32) -> Frame {
+    let t = host_ms.to_le_bytes();
+    [TIME_BEACON, seq, 0, 0, t[0], t[1], t[2], t[3]]
+}
+
+pub fn factory_read(page: u8) -> Frame {
+    [FACTORY_READ, page, 0, 0, 0, 0, 0, 0]
+}
+
+pub fn factory_write(page: u8, data: [u8; 6]) -> Frame {
+    let mut frame = [FACTORY_WRITE, page, 0, 0, 0, 0, 0, 0];
+    frame[2..].copy_from_slice(&data);
+    frame
+}
+
+pub fn table_read(offset: u16) -> Frame {
+    let offset = offset.to_le_bytes();
+    [TABLE_READ, 0, offset[0], offset[1], 0, 0, 0, 0]
+}
+
+pub fn table_write(offset: u16, data: [u8; TABLE_CHUNK_SIZE]) -> Frame {
+    let offset = offset.to_le_bytes();
+    [
+        TABLE_WRITE,
+        0,
+        offset[0],
+        offset[1],
+        data[0],
+        data[1],
+        data[2],
+        data[3],
+    ]
+}
+
+/// Value of a `ParamValue` reply
+pub fn param_value(frame: &Frame) -> Result<u32, String> {
+    check_result(frame[1])?;
+    Ok(u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]))
+}
+
+/// Sequence number and tick counter of a `TimeSync` reply
+pub fn time_sync(frame: &Frame) -> (u8, u32) {
+    (
+        frame[1],
+        u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+    )
+}
+
+/// Chunk carried by a `TableData` reply
+pub fn table_data(frame: &Frame) -> Result<[u8; TABLE_CHUNK_SIZE], String> {
+    check_result(frame[1])?;
+    Ok([frame[4], frame[5], frame[6], frame[7]])
+}
+
+/// Bytes carried by a table frame
+pub const TABLE_CHUNK_SIZE: usize = 4;
+
+/// Size of the table image header, see `tunepulse_algo::protocol::table_transfer`
+pub const TABLE_HEADER_SIZE: usize = 12;
+
+/// Magic of a table image ("TC")
+const TABLE_IMAGE_MAGIC: u16 = 0x4354;
+
+/// Payload length stored in the header of a table image
+pub fn table_payload_len(header: &[u8]) -> usize {
+    u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize
+}
+
+/// Checks magic, length and CRC of a table image
+pub fn check_table_image(image: &[u8]) -> Result<(), String> {
+    if image.len() < TABLE_HEADER_SIZE
+        || u16::from_le_bytes([image[0], image[1]]) != TABLE_IMAGE_MAGIC
+    {
+        return Err("not a table image".into());
+    }
+    let payload = &image[TABLE_HEADER_SIZE..];
+    if payload.len() != table_payload_len(image) {
+        return Err("table image truncated".into());
+    }
+    if crc32(payload) != u32::from_le_bytes(image[8..12].try_into().unwrap()) {
+        return Err("table image CRC mismatch".into());
+    }
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3) as used by the firmware images
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Decoded capture reply
+#[derive(Debug, Clone, Copy)]
+pub struct Capture {
+    /// Latched position, `None` if nothing was captured yet
+    pub position: Option<i32>,
+    pub armed: bool,
+    pub count: u16,
+}
+
+impl Capture {
+    pub fn decode(frame: &Frame) -> Self {
+        let position = i32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        Self {
+            position: (frame[1] & 1 != 0).then_some(position),
+            armed: frame[1] & 2 != 0,
+            count: u16::from_le_bytes([frame[2], frame[3]]),
+        }
+    }
+}
+
+/// Names of the step response tests in the order they are run
+pub const STEP_TESTS: [&str; 2] = ["current", "velocity"];
+
+/// Decodes a step response test reply, `None` if the test didn't finish. The device doesn't
+/// measure the settling time.
+pub fn step_result(frame: &Frame) -> Option<StepMetrics> {
+    if frame[1] == 0xFF {
+        return None;
+    }
+    let rise = u16::from_le_bytes([frame[2], frame[3]]); // 0.1 ms
+    Some(StepMetrics {
+        rise_time: (rise != 0xFFFF).then_some(rise as f64 / 10_000.0),
+        overshoot_pct: u16::from_le_bytes([frame[4], frame[5]]) as f64 / 10.0,
+        error_pct: i16::from_le_bytes([frame[6], frame[7]]) as f64 / 10.0,
+        settling_time: None,
+    })
+}
+
+/// Decoded production test metric reply
+#[derive(Debug, Clone, Copy)]
+pub struct ProductionResult {
+    /// Measured value, `None` if the metric wasn't measured
+    pub value: Option<i32>,
+    /// Value within the limits
+    pub passed: bool,
+    /// All metrics of the unit passed
+    pub unit_passed: bool,
+}
+
+impl ProductionResult {
+    /// Decodes the reply, `None` if there's no report
+    pub fn decode(frame: &Frame) -> Option<Self> {
+        if frame[1] == 0xFF {
+            return None;
+        }
+        let value = i32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        Some(Self {
+            value: (frame[2] & 2 != 0).then_some(value),
+            passed: frame[2] & 1 != 0,
+            unit_passed: frame[2] & 4 != 0,
+        })
+    }
+}
+
+/// Decodes a calibration report reply, `None` if the metric wasn't measured
+pub fn calibration_metric(frame: &Frame) -> Option<i32> {
+    (frame[1] != 0xFF && frame[2] & 1 != 0)
+        .then(|| i32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]))
+}
+
+/// Decodes a peak-hold reply, `None` if the register wasn't sampled since it was cleared
+pub fn peak_value(frame: &Frame) -> Option<u32> {
+    (frame[1] != 0xFF && frame[2] & 1 != 0)
+        .then(|| u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]))
+}
+
+/// Step measuring each setup wizard result, see `codes::WIZARD_RESULTS`
+pub const WIZARD_RESULT_STEPS: [u8; 7] = [0, 1, 2, 2, 3, 3, 5];
+
+/// Outcome of a setup wizard step, see `codes::STEP_OUTCOMES`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Pending,
+    Running,
+    Passed,
+    Failed,
+    Skipped,
+    Aborted,
+}
+
+/// Decoded setup wizard result reply
+#[derive(Debug, Clone, Copy)]
+pub struct WizardResult {
+    /// Outcome of the step measuring the result
+    pub outcome: StepOutcome,
+    /// Measured value, `None` if the result wasn't measured
+    pub value: Option<i32>,
+}
+
+impl WizardResult {
+    /// Decodes the reply, `None` if the result doesn't exist
+    pub fn decode(frame: &Frame) -> Option<Self> {
+        if frame[1] == 0xFF {
+            return None;
+        }
+        let outcome = match frame[2] {
+            1 => StepOutcome::Running,
+            2 => StepOutcome::Passed,
+            3 => StepOutcome::Failed,
+            4 => StepOutcome::Skipped,
+            5 => StepOutcome::Aborted,
+            _ => StepOutcome::Pending,
+        };
+        let value = i32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        Some(Self {
+            outcome,
+            value: (frame[3] & 1 != 0).then_some(value),
+        })
+    }
+}
+
+/// Status byte bit set while the parameters differ from the saved set
+const STATUS_UNSAVED: u8 = 1 << 7;
+
+/// Decoded status reply
+#[derive(Debug, Clone, Copy)]
+pub struct Status {
+    pub status: u8,
+    /// Parameters changed since they were saved, lost on reset
+    pub unsaved: bool,
+    pub fault: u8,
+    pub flags: u8,
+    pub position: i32,
+}
+
+impl Status {
+    pub fn decode(frame: &Frame) -> Self {
+        Self {
+            status: frame[1] & !STATUS_UNSAVED,
+            unsaved: frame[1] & STATUS_UNSAVED != 0,
+            fault: frame[2],
+            flags: frame[3],
+            position: i32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+        }
+    }
+
+    pub fn status_name(&self) -> &'static str {
+        status_name(self.status)
+    }
+}
+
+fn status_name(status: u8) -> &'static str {
+    codes::code_name(&codes::DRIVER_STATUS, status)
+}
+
+/// Report of the slow status channel, assembled from its pages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusReport {
+    pub status: u8,
+    /// Parameters changed since they were saved, lost on reset
+    pub unsaved: bool,
+    pub fault: u8,
+    pub supply_mv: u16,
+    /// Temperature sensor reading (raw ADC)
+    pub temperature: u16,
+    pub encoder_rejected: u16,
+    pub encoder_retries: u16,
+    pub overflows: u16,
+}
+
+impl StatusReport {
+    /// Takes over a page, returns true once the last page completed the report
+    pub fn apply(&mut self, frame: &Frame) -> bool {
+        let word = |i: usize| u16::from_le_bytes([frame[i], frame[i + 1]]);
+        match frame[1] {
+            0 => {
+                self.status = frame[2] & !STATUS_UNSAVED;
+                self.unsaved = frame[2] & STATUS_UNSAVED != 0;
+                self.fault = frame[3];
+                self.supply_mv = word(4);
+                self.temperature = word(6);
+                false
+            }
+            1 => {
+                self.encoder_rejected = word(2);
+                self.encoder_retries = word(4);
+                self.overflows = word(6);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn status_name(&self) -> &'static str {
+        status_name(self.status)
+    }
+}
+
+/// Names of the calibration sub-stages, see `tunepulse_algo::motor_driver::CalibrationStage`
+const CALIBRATION_STAGES: [&str; 8] = [
+    "startup",
+    "settling",
+    "motion test",
+    "forward pass",
+    "backward pass",
+    "check",
+    "torque ripple",
+    "trim",
+];
+
+/// Decoded detailed state reply
+#[derive(Debug, Clone, Copy)]
+pub struct State {
+    /// Calibration sub-stage, `None` unless calibrating
+    pub stage: Option<u8>,
+    /// Calibration progress (percent)
+    pub progress: u8,
+    /// Bridge drives current through the windings
+    pub energized: bool,
+    /// Speed settled within the velocity window of the velocity setpoint
+    pub at_velocity: bool,
+    /// Speed settled below the zero speed threshold
+    pub zero_speed: bool,
+}
+
+impl State {
+    pub fn decode(frame: &Frame) -> Self {
+        Self {
+            stage: (frame[2] != 0xFF).then_some(frame[2]),
+            progress: frame[3],
+            energized: frame[4] & 1 != 0,
+            at_velocity: frame[4] & 1 << 1 != 0,
+            zero_speed: frame[4] & 1 << 2 != 0,
+        }
+    }
+
+    pub fn stage_name(&self) -> Option<&'static str> {
+        let stage = self.stage?;
+        Some(CALIBRATION_STAGES.get(stage as usize).unwrap_or(&"unknown"))
+    }
+}
+
+/// Device information assembled from all pages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceInfo {
+    pub version: [u8; 3],
+    pub board: u8,
+    pub calibrated: bool,
+    pub git_dirty: bool,
+    pub protocol: u8,
+    pub git_hash: u32,
+    pub param_count: u16,
+    pub uid: [u8; 12],
+}
+
+impl DeviceInfo {
+    /// Decodes the pages read in order, `None` if a page is missing
+    pub fn decode(pages: &[Frame]) -> Option<Self> {
+        if pages.len() < DEVICE_INFO_PAGES as usize
+            || pages.iter().zip(0..).any(|(frame, page)| frame[1] != page)
+        {
+            return None;
+        }
+        let mut uid = [0; 12];
+        uid[..6].copy_from_slice(&pages[2][2..]);
+        uid[6..].copy_from_slice(&pages[3][2..]);
+        Some(Self {
+            version: [pages[0][2], pages[0][3], pages[0][4]],
+            board: pages[0][5],
+            calibrated: pages[0][6] & 1 != 0,
+            git_dirty: pages[0][6] & 2 != 0,
+            protocol: pages[0][7],
+            git_hash: u32::from_le_bytes(pages[1][2..6].try_into().unwrap()),
+            param_count: u16::from_le_bytes([pages[1][6], pages[1][7]]),
+            uid,
+        })
+    }
+
+    pub fn board_name(&self) -> &'static str {
+        match self.board {
+            1 => "CLN17",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// Factory data block assembled from all pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FactoryData {
+    pub serial: u32,
+    pub hardware_rev: u16,
+    /// Calibration date (unix time)
+    pub date: u32,
+    /// Supply voltage measurement gain correction (0.01 %)
+    pub supply_trim: i16,
+    /// Temperature sensor offset (raw ADC)
+    pub temp_offset: i16,
+}
+
+impl FactoryData {
+    /// Decodes the pages read in order, `None` if the unit has no factory data
+    pub fn decode(pages: &[Frame]) -> Option<Self> {
+        if pages.len() < FACTORY_PAGES as usize
+            || pages.iter().zip(0..).any(|(frame, page)| frame[1] != page)
+        {
+            return None;
+        }
+        Some(Self {
+            serial: u32::from_le_bytes(pages[0][2..6].try_into().unwrap()),
+            hardware_rev: u16::from_le_bytes([pages[0][6], pages[0][7]]),
+            date: u32::from_le_bytes(pages[1][2..6].try_into().unwrap()),
+            supply_trim: i16::from_le_bytes([pages[1][6], pages[1][7]]),
+            temp_offset: i16::from_le_bytes([pages[2][2], pages[2][3]]),
+        })
+    }
+
+    /// Payloads of all pages in order
+    pub fn pages(&self) -> [[u8; 6]; FACTORY_PAGES as usize] {
+        let mut pages = [[0; 6]; FACTORY_PAGES as usize];
+        pages[0][..4].copy_from_slice(&self.serial.to_le_bytes());
+        pages[0][4..].copy_from_slice(&self.hardware_rev.to_le_bytes());
+        pages[1][..4].copy_from_slice(&self.date.to_le_bytes());
+        pages[1][4..].copy_from_slice(&self.supply_trim.to_le_bytes());
+        pages[2][..2].copy_from_slice(&self.temp_offset.to_le_bytes());
+        pages
+    }
+}
+
+/// Frequency response point assembled from both pages
+#[derive(Debug, Clone, Copy)]
+pub struct ResponsePoint {
+    pub frequency_hz: f64,
+    pub gain_db: f64,
+    pub phase_deg: f64,
+}
+
+impl ResponsePoint {
+    /// Decodes page 0 and page 1 replies, `None` if the point wasn't measured
+    pub fn decode(page0: &Frame, page1: &Frame) -> Option<Self> {
+        if page0[2] != 0 || page1[2] != 1 {
+            return None;
+        }
+        let frequency = u32::from_le_bytes([page0[3], page0[4], page0[5], 0]);
+        let phase = i16::from_le_bytes([page0[6], page0[7]]);
+        let gain = u32::from_le_bytes([page1[3], page1[4], page1[5], page1[6]]);
+        Some(Self {
+            frequency_hz: frequency as f64 / 1000.0,
+            gain_db: units::gain_db(gain as f64),
+            phase_deg: units::phase_degrees(phase as f64),
+        })
+    }
+}
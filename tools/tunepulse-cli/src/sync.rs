@@ -0,0 +1,124 @@
+// Mapping of device ticks to host wall clock from time beacon exchanges.
+//
+// Every beacon yields one sample: the tick counter the device replied with and the midpoint
+// of the host send and receive time. A least squares line through the samples gives the wall
+// clock of tick zero and the real tick period, which includes the drift of the device
+// oscillator. Samples delayed by a busy link (round trip well above the best one) are left out.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Samples with a round trip above this multiple of the best one are ignored
+const ROUND_TRIP_LIMIT: f64 = 2.0;
+
+/// One beacon exchange
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    host: f64,       // Host wall clock at the reply (s since the unix epoch)
+    tick: i64,       // Extended device tick counter
+    round_trip: f64, // Time between beacon and reply (s)
+}
+
+/// Fitted relation between device ticks and host wall clock
+#[derive(Debug, Clone, Copy)]
+pub struct ClockFit {
+    /// Wall clock of extended tick zero (s since the unix epoch)
+    pub origin: f64,
+    /// Real duration of one tick (s)
+    pub tick_period: f64,
+}
+
+impl ClockFit {
+    /// Wall clock of an extended tick (s since the unix epoch)
+    pub fn wall_time(&self, tick: i64) -> f64 {
+        self.origin + tick as f64 * self.tick_period
+    }
+}
+
+/// Collects beacon exchanges of one device
+#[derive(Debug, Default)]
+pub struct TickClock {
+    samples: Vec<Sample>,
+    last: Option<(u32, i64)>, // Last raw and extended tick counter
+}
+
+impl TickClock {
+    /// Extends a 32-bit tick counter, continuing across the wraparound
+    pub fn extend(&mut self, tick: u32) -> i64 {
+        let extended = match self.last {
+            Some((raw, extended)) => extended + tick.wrapping_sub(raw) as i32 as i64,
+            None => tick as i64,
+        };
+        self.last = Some((tick, extended));
+        extended
+    }
+
+    /// Adds a beacon exchange
+    ///
+    /// * `sent` - Host wall clock when the beacon was sent
+    /// * `received` - Host wall clock when the reply arrived
+    /// * `tick` - Tick counter of the reply
+    ///
+    /// Returns the extended tick counter of the reply
+    pub fn add(&mut self, sent: SystemTime, received: SystemTime, tick: u32) -> i64 {
+        let round_trip = received
+            .duration_since(sent)
+            .unwrap_or(Duration::ZERO)
+            .as_secs_f64();
+        let sample = Sample {
+            host: unix_seconds(sent) + round_trip / 2.0,
+            tick: self.extend(tick),
+            round_trip,
+        };
+        self.samples.push(sample);
+        sample.tick
+    }
+
+    /// Best round trip of the collected exchanges (s)
+    pub fn best_round_trip(&self) -> Option<f64> {
+        self.samples.iter().map(|s| s.round_trip).reduce(f64::min)
+    }
+
+    /// Fits the clock relation, `None` until two usable exchanges span some ticks
+    pub fn fit(&self) -> Option<ClockFit> {
+        let limit = self.best_round_trip()? * ROUND_TRIP_LIMIT;
+        let used: Vec<&Sample> = self
+            .samples
+            .iter()
+            .filter(|s| s.round_trip <= limit)
+            .collect();
+        let n = used.len() as f64;
+        // Relative to the first sample, keeps the sums well conditioned
+        let (host0, tick0) = (used.first()?.host, used.first()?.tick);
+        let (mut sx, mut sy, mut sxx, mut sxy) = (0.0, 0.0, 0.0, 0.0);
+        for s in &used {
+            let x = (s.tick - tick0) as f64;
+            let y = s.host - host0;
+            sx += x;
+            sy += y;
+            sxx += x * x;
+            sxy += x * y;
+        }
+        let denominator = n * sxx - sx * sx;
+        if used.len() < 2 || denominator <= 0.0 {
+            return None;
+        }
+        let tick_period = (n * sxy - sx * sy) / denominator;
+        let intercept = (sy - tick_period * sx) / n;
+        Some(ClockFit {
+            origin: host0 + intercept - tick0 as f64 * tick_period,
+            tick_period,
+        })
+    }
+}
+
+/// Host wall clock in seconds since the unix epoch
+pub fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs_f64()
+}
+
+/// Host wall clock as carried by a time beacon (ms, wrapping)
+pub fn beacon_time(time: SystemTime) -> u32 {
+    (unix_seconds(time) * 1000.0) as u64 as u32
+}
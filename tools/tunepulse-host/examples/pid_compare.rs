@@ -0,0 +1,226 @@
+//! Compares the integer PID running on the device with its floating point mirror.
+//!
+//! Both controllers drive their own copy of a first order plant from rest to the target, the
+//! integer one with the rounded error like on the device. The harness prints the step
+//! response metrics of both loops and the difference of their outputs and responses, which
+//! is the error the fixed point math adds for the given gains. With `--csv` every tick is
+//! written for plotting.
+//!
+//! Gains are ratios (1.0 = 100 %), the integer PID gets them in percent like its parameters:
+//! `cargo run --example pid_compare -- --kp 2 --ki 0.5 --target 10000 --csv pid.csv`
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use tunepulse_algo::math_float::controllers::pid::PID as FloatPid;
+use tunepulse_algo::math_integer::controllers::pid::PID as IntegerPid;
+use tunepulse_host::step::{self, StepMetrics};
+
+struct Args {
+    kp: f64,
+    ki: f64,
+    kd: f64,
+    kff: f64,             // Feedforward of the target
+    target: i16,          // Setpoint the loops step to
+    limit: i16,           // Output limit of both controllers
+    tau: f64,             // Time constant of the plant (ticks)
+    iterations: usize,    // Ticks simulated
+    csv: Option<PathBuf>, // Per tick output file
+}
+
+impl Args {
+    /// Parses `pid_compare [--kp x] [--ki x] [--kd x] [--kff x] [--target n] [--limit n]
+    /// [--tau ticks] [--iterations n] [--csv file]`
+    fn parse() -> Result<Self, String> {
+        let mut parsed = Args {
+            kp: 1.0,
+            ki: 0.5,
+            kd: 0.0,
+            kff: 0.0,
+            target: 10_000,
+            limit: i16::MAX,
+            tau: 50.0,
+            iterations: 2_000,
+            csv: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("{} needs a value", arg))?;
+            let invalid = || format!("invalid value for {}: {}", arg, value);
+            match arg.as_str() {
+                "--kp" => parsed.kp = value.parse().map_err(|_| invalid())?,
+                "--ki" => parsed.ki = value.parse().map_err(|_| invalid())?,
+                "--kd" => parsed.kd = value.parse().map_err(|_| invalid())?,
+                "--kff" => parsed.kff = value.parse().map_err(|_| invalid())?,
+                "--target" => parsed.target = value.parse().map_err(|_| invalid())?,
+                "--limit" => parsed.limit = value.parse().map_err(|_| invalid())?,
+                "--tau" => parsed.tau = value.parse().map_err(|_| invalid())?,
+                "--iterations" => parsed.iterations = value.parse().map_err(|_| invalid())?,
+                "--csv" => parsed.csv = Some(value.into()),
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// First order lag, the output follows the input with the time constant
+struct Plant {
+    output: f64,
+    tau: f64, // Ticks
+}
+
+impl Plant {
+    fn new(tau: f64) -> Self {
+        Self {
+            output: 0.0,
+            tau: tau.max(1.0),
+        }
+    }
+
+    fn tick(&mut self, input: f64) {
+        self.output += (input - self.output) / self.tau;
+    }
+}
+
+/// One simulated tick of both loops
+struct Row {
+    integer_output: f64,
+    float_output: f64,
+    integer_response: f64,
+    float_response: f64,
+}
+
+/// Largest and RMS difference of two series
+fn difference(a: impl Iterator<Item = f64>, b: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (mut max, mut sum, mut count) = (0.0f64, 0.0, 0);
+    for (a, b) in a.zip(b) {
+        max = max.max((a - b).abs());
+        sum += (a - b) * (a - b);
+        count += 1;
+    }
+    (max, (sum / count.max(1) as f64).sqrt())
+}
+
+fn print_metrics(name: &str, metrics: Option<StepMetrics>) {
+    let Some(metrics) = metrics else {
+        println!("{name:8} no step response");
+        return;
+    };
+    let ticks = |time: Option<f64>| time.map_or("not reached".into(), |t| format!("{t} ticks"));
+    println!(
+        "{name:8} rise: {}, overshoot: {:.2} %, steady-state error: {:.3} %, settling: {}",
+        ticks(metrics.rise_time),
+        metrics.overshoot_pct,
+        metrics.error_pct,
+        ticks(metrics.settling_time)
+    );
+}
+
+fn main() {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let percent = |gain: f64| (gain * 100.0).round() as i32;
+    let mut integer_pid = IntegerPid::new(
+        percent(args.kp),
+        percent(args.ki),
+        percent(args.kd),
+        percent(args.kff),
+    );
+    let mut float_pid = FloatPid::new(
+        args.kp as f32,
+        args.ki as f32,
+        args.kd as f32,
+        args.kff as f32,
+    );
+    let mut integer_plant = Plant::new(args.tau);
+    let mut float_plant = Plant::new(args.tau);
+
+    let mut rows = Vec::with_capacity(args.iterations);
+    for _ in 0..args.iterations {
+        let error = (args.target as f64 - integer_plant.output).round();
+        let error = error.clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        integer_pid.tick(error, args.target, args.limit);
+        integer_plant.tick(integer_pid.output() as f64);
+
+        let error = args.target as f32 - float_plant.output as f32;
+        float_pid.tick(error, args.target as f32, args.limit as f32);
+        float_plant.tick(float_pid.output() as f64);
+
+        rows.push(Row {
+            integer_output: integer_pid.output() as f64,
+            float_output: float_pid.output() as f64,
+            integer_response: integer_plant.output,
+            float_response: float_plant.output,
+        });
+    }
+
+    if let Some(path) = &args.csv {
+        let result = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            writeln!(
+                out,
+                "tick,integer_output,float_output,integer_response,float_response"
+            )?;
+            for (tick, row) in rows.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{},{},{},{:.3},{:.3}",
+                    tick,
+                    row.integer_output,
+                    row.float_output,
+                    row.integer_response,
+                    row.float_response
+                )?;
+            }
+            out.flush()
+        });
+        if let Err(e) = result {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    // Plants start at rest, the step is at tick 0
+    let response = |value: fn(&Row) -> f64| {
+        let samples: Vec<[f64; 2]> = std::iter::once([-1.0, 0.0])
+            .chain(
+                rows.iter()
+                    .enumerate()
+                    .map(|(t, row)| [t as f64, value(row)]),
+            )
+            .collect();
+        step::analyze(&samples, 0.0, args.target as f64)
+    };
+    print_metrics("integer", response(|row| row.integer_response));
+    print_metrics("float", response(|row| row.float_response));
+
+    let (max, rms) = difference(
+        rows.iter().map(|row| row.integer_output),
+        rows.iter().map(|row| row.float_output),
+    );
+    let limit = args.limit as f64;
+    println!(
+        "output error:   max {:.1} ({:.3} % of limit), rms {:.2}",
+        max,
+        max * 100.0 / limit,
+        rms
+    );
+    let (max, rms) = difference(
+        rows.iter().map(|row| row.integer_response),
+        rows.iter().map(|row| row.float_response),
+    );
+    println!(
+        "response error: max {:.1} ({:.3} % of target), rms {:.2}",
+        max,
+        max * 100.0 / args.target as f64,
+        rms
+    );
+}
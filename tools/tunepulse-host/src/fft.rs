@@ -0,0 +1,83 @@
+//! Amplitude spectrum of uniformly sampled signals.
+//!
+//! Radix-2 FFT, signals are zero padded to the next power of two. The mean is removed and a
+//! Hann window applied before the transform, so neither the DC level nor the edges of the
+//! record smear over the spectrum. Amplitudes are corrected for the window gain: a sine of
+//! amplitude A shows a peak of about A.
+
+use std::f64::consts::PI;
+
+/// Complex FFT in place, the length must be a power of two
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    assert!(
+        n.is_power_of_two() && im.len() == n,
+        "FFT length must be a power of two"
+    );
+    if n == 1 {
+        return;
+    }
+
+    // Bit reversed order, then butterflies of growing length
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let (w_im, w_re) = (-2.0 * PI / len as f64).sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut t_re, mut t_im) = (1.0, 0.0); // Twiddle factor
+            for a in start..start + len / 2 {
+                let b = a + len / 2;
+                let re_b = re[b] * t_re - im[b] * t_im;
+                let im_b = re[b] * t_im + im[b] * t_re;
+                re[b] = re[a] - re_b;
+                im[b] = im[a] - im_b;
+                re[a] += re_b;
+                im[a] += im_b;
+                (t_re, t_im) = (t_re * w_re - t_im * w_im, t_re * w_im + t_im * w_re);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Amplitude spectrum as `[frequency (Hz), amplitude]` from DC to the Nyquist frequency
+///
+/// # Arguments
+/// * `values` - Signal sampled at a fixed rate
+/// * `rate` - Sample rate (Hz)
+pub fn spectrum(values: &[f64], rate: f64) -> Vec<[f64; 2]> {
+    if values.len() < 2 {
+        return Vec::new();
+    }
+    let n = values.len().next_power_of_two();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let last = (values.len() - 1) as f64;
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    let mut gain = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / last).cos();
+        re[i] = (value - mean) * window;
+        gain += window;
+    }
+    fft(&mut re, &mut im);
+    (0..=n / 2)
+        .map(|k| [k as f64 * rate / n as f64, re[k].hypot(im[k]) * 2.0 / gain])
+        .collect()
+}
+
+/// Highest peak of a spectrum above DC
+pub fn peak(spectrum: &[[f64; 2]]) -> Option<[f64; 2]> {
+    spectrum
+        .iter()
+        .skip(1)
+        .copied()
+        .max_by(|a, b| a[1].total_cmp(&b[1]))
+}
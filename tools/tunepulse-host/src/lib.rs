@@ -0,0 +1,13 @@
+//! Host-side decoding and analysis of TunePulse data, shared by the plotter and the CLI.
+//!
+//! - `telemetry`: decoding of the telemetry point stream.
+//! - `units`: conversion of raw device units (encoder counts, Q16 values) to physical units.
+//! - `resample`: sample-and-hold and uniform resampling of irregularly sampled channels.
+//! - `fft`: amplitude spectrum of a uniformly sampled signal.
+//! - `step`: rise time, overshoot, settling time and steady-state error of a step response.
+
+pub mod fft;
+pub mod resample;
+pub mod step;
+pub mod telemetry;
+pub mod units;
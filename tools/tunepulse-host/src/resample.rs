@@ -0,0 +1,71 @@
+//! Resampling of channels sampled at their own, irregular times.
+//!
+//! Samples are `[time, value]` pairs in time order, like the channel buffers of the plotter.
+//! `Hold` gives the value a channel had at a given time (the latest sample not newer than
+//! it), which pairs samples captured in the same tick exactly and holds channels sent at a
+//! lower rate. `uniform` interpolates linearly onto a fixed period, as needed by the FFT.
+
+use std::iter::Peekable;
+
+/// Sample-and-hold lookup for increasing times
+pub struct Hold<I: Iterator> {
+    samples: Peekable<I>,
+    value: Option<f64>, // Latest sample not newer than the last requested time
+}
+
+impl<'a, I: Iterator<Item = &'a [f64; 2]>> Hold<I> {
+    pub fn new(samples: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            samples: samples.into_iter().peekable(),
+            value: None,
+        }
+    }
+
+    /// Value at a time, `None` before the first sample. Times must not decrease between calls.
+    pub fn at(&mut self, time: f64) -> Option<f64> {
+        while let Some(sample) = self.samples.next_if(|sample| sample[0] <= time) {
+            self.value = Some(sample[1]);
+        }
+        self.value
+    }
+}
+
+/// Values at a fixed period from the first to the last sample, linearly interpolated
+pub fn uniform(samples: &[[f64; 2]], period: f64) -> Vec<f64> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    if period <= 0.0 {
+        return Vec::new();
+    }
+    let count = ((last[0] - first[0]) / period) as usize + 1;
+    let mut values = Vec::with_capacity(count);
+    let mut next = 0;
+    for n in 0..count {
+        let time = first[0] + n as f64 * period;
+        while next + 1 < samples.len() && samples[next + 1][0] <= time {
+            next += 1;
+        }
+        let [t0, v0] = samples[next];
+        let value = match samples.get(next + 1) {
+            Some(&[t1, v1]) if t1 > t0 => v0 + (v1 - v0) * (time - t0) / (t1 - t0),
+            _ => v0,
+        };
+        values.push(value);
+    }
+    values
+}
+
+/// Median time between samples, the sample period of a channel with dropouts
+pub fn median_period(samples: &[[f64; 2]]) -> Option<f64> {
+    let mut periods: Vec<f64> = samples
+        .windows(2)
+        .map(|pair| pair[1][0] - pair[0][0])
+        .filter(|period| *period > 0.0)
+        .collect();
+    if periods.is_empty() {
+        return None;
+    }
+    periods.sort_unstable_by(f64::total_cmp);
+    Some(periods[periods.len() / 2])
+}
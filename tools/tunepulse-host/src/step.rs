@@ -0,0 +1,64 @@
+//! Step response metrics of recorded samples.
+//!
+//! Same definitions as the on-device step test, so host and device results compare directly:
+//! - Baseline: mean of the samples before the step.
+//! - Rise time: from 10 % to 90 % of the step, `None` if 90 % is never reached.
+//! - Overshoot: peak above the step in % of the step.
+//! - Steady-state error: step minus the mean of the last quarter after the step in % of the
+//!   step (positive - the response stays below the step).
+//!
+//! The settling time is only computed on the host: time from the step until the response
+//! stays within `SETTLING_BAND` of its steady-state level. Negative steps are measured in the
+//! direction of the step, so the metrics keep their sign convention.
+
+/// Settling band around the steady-state level (fraction of the step)
+pub const SETTLING_BAND: f64 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepMetrics {
+    pub rise_time: Option<f64>,     // From 10 % to 90 % of the step (s)
+    pub overshoot_pct: f64,         // Peak above the step (% of the step)
+    pub error_pct: f64,             // Step minus steady-state level (% of the step)
+    pub settling_time: Option<f64>, // Until the response stays in the band (s), `None` if never
+}
+
+/// Computes the metrics of a step response, `None` without samples before and after the step
+/// or with a zero step
+///
+/// # Arguments
+/// * `samples` - `[time (s), value]` pairs in time order
+/// * `start` - Time of the step (s)
+/// * `step` - Commanded step height (units of the samples)
+pub fn analyze(samples: &[[f64; 2]], start: f64, step: f64) -> Option<StepMetrics> {
+    let split = samples.partition_point(|sample| sample[0] < start);
+    let (before, after) = samples.split_at(split);
+    if before.is_empty() || after.is_empty() || step == 0.0 {
+        return None;
+    }
+    let baseline = before.iter().map(|sample| sample[1]).sum::<f64>() / before.len() as f64;
+    // Response as a fraction of the step
+    let fraction = |sample: &[f64; 2]| (sample[1] - baseline) / step;
+
+    let crossing = |level: f64| after.iter().find(|s| fraction(s) >= level).map(|s| s[0]);
+    let rise_time = match (crossing(0.1), crossing(0.9)) {
+        (Some(low), Some(high)) => Some(high - low),
+        _ => None,
+    };
+    let peak = after.iter().map(fraction).fold(f64::NEG_INFINITY, f64::max);
+    let tail = &after[after.len() * 3 / 4..];
+    let steady = tail.iter().map(fraction).sum::<f64>() / tail.len() as f64;
+    let settling_time = match after
+        .iter()
+        .rposition(|s| (fraction(s) - steady).abs() > SETTLING_BAND)
+    {
+        Some(last) => after.get(last + 1).map(|s| s[0] - start),
+        None => Some(0.0),
+    };
+
+    Some(StepMetrics {
+        rise_time,
+        overshoot_pct: ((peak - 1.0) * 100.0).max(0.0),
+        error_pct: (1.0 - steady) * 100.0,
+        settling_time,
+    })
+}
@@ -0,0 +1,67 @@
+//! Decoding of the telemetry stream.
+//!
+//! The firmware sends packed 9 byte points: channel ID, tick counter (u32 LE) and value
+//! (f32 LE). Reads of the link may end in the middle of a point, the decoder keeps the
+//! incomplete bytes until the rest arrives. Channels sent as envelope add the window minimum
+//! and maximum as points with `ENVELOPE_MIN` or `ENVELOPE_MAX` set in the ID.
+
+/// Size of an encoded point
+pub const POINT_SIZE: usize = 9;
+
+/// Telemetry point carrying the tick rate instead of a signal value
+pub const TIMEBASE_ID: u8 = 0xFF;
+
+/// ID flag of the window minimum of an enveloped channel
+pub const ENVELOPE_MIN: u8 = 0x40;
+
+/// ID flag of the window maximum of an enveloped channel
+pub const ENVELOPE_MAX: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub id: u8,         // Channel ID
+    pub timestamp: u32, // Tick counter when the value was sampled
+    pub value: f32,
+}
+
+impl Point {
+    pub fn decode(bytes: &[u8; POINT_SIZE]) -> Self {
+        Self {
+            id: bytes[0],
+            timestamp: u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]),
+            value: f32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]),
+        }
+    }
+
+    /// Signal ID without the envelope flags, the time base point keeps its ID
+    pub fn signal(&self) -> u8 {
+        if self.id == TIMEBASE_ID {
+            return self.id;
+        }
+        self.id & !(ENVELOPE_MIN | ENVELOPE_MAX)
+    }
+}
+
+/// Splits the byte stream of one link into points
+#[derive(Debug, Default)]
+pub struct Decoder {
+    pending: Vec<u8>, // Incomplete point of the previous read
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the complete points of the received bytes
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Point> {
+        self.pending.extend_from_slice(bytes);
+        let points: Vec<Point> = self
+            .pending
+            .chunks_exact(POINT_SIZE)
+            .map(|chunk| Point::decode(chunk.try_into().unwrap()))
+            .collect();
+        self.pending.drain(..points.len() * POINT_SIZE);
+        points
+    }
+}
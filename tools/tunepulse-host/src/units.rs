@@ -0,0 +1,46 @@
+//! Conversion of raw device units to physical units.
+//!
+//! Positions are encoder counts with 65536 counts per mechanical turn, speeds counts per
+//! second. Gains and other ratios are Q16 fixed point (65536 = 1.0), phases and angles a
+//! 16-bit fraction of a full turn.
+
+/// Encoder counts per mechanical turn
+pub const COUNTS_PER_TURN: f64 = 65536.0;
+
+/// Value of one in Q16 fixed point
+pub const Q16_ONE: f64 = 65536.0;
+
+/// Position in turns
+pub fn turns(counts: f64) -> f64 {
+    counts / COUNTS_PER_TURN
+}
+
+/// Position in degrees
+pub fn degrees(counts: f64) -> f64 {
+    counts * 360.0 / COUNTS_PER_TURN
+}
+
+/// Speed in revolutions per minute
+pub fn rpm(counts_per_second: f64) -> f64 {
+    counts_per_second * 60.0 / COUNTS_PER_TURN
+}
+
+/// Q16 fixed point value as a ratio
+pub fn q16(raw: f64) -> f64 {
+    raw / Q16_ONE
+}
+
+/// Q16 gain in decibels
+pub fn gain_db(raw: f64) -> f64 {
+    20.0 * q16(raw).log10()
+}
+
+/// 16-bit fraction of a turn in degrees, signed angles pass the sign through
+pub fn phase_degrees(raw: f64) -> f64 {
+    raw * 360.0 / 65536.0
+}
+
+/// Millivolts in volts
+pub fn volts(millivolts: f64) -> f64 {
+    millivolts / 1000.0
+}
@@ -0,0 +1,99 @@
+"""Publishes the drive position as ROS 2 odometry of a wheel.
+
+The drive sends position and velocity frames at the rate set by `odometry_rate_hz`, this
+example converts them to `nav_msgs/Odometry` of a wheel rolling along the x axis. Sample times
+are taken from the drive sequence numbers, so link latency doesn't add jitter to the
+odometry. Without ROS 2 (`rclpy`) installed the messages are printed instead.
+
+    python examples/odometry.py /dev/ttyACM0 --rate 250 --radius 0.05
+"""
+
+import argparse
+import math
+import time
+
+from tunepulse import Device
+
+
+class SampleClock:
+    """Unwraps the 8-bit sequence numbers into sample times, counts lost samples"""
+
+    def __init__(self, rate):
+        self.rate = rate
+        self.count = None  # Unwrapped sequence number
+        self.lost = 0
+        self.origin = time.time()  # Host time of sequence 0
+
+    def update(self, seq):
+        if self.count is None:
+            self.count = seq
+        else:
+            gap = (seq - self.count) & 0xFF
+            self.lost += max(gap - 1, 0)
+            self.count += gap
+        return self.origin + self.count / self.rate
+
+
+def to_odometry(sample, stamp, radius):
+    """Odometry of the wheel as plain dict matching nav_msgs/Odometry"""
+    return {
+        "stamp": stamp,
+        "frame_id": "odom",
+        "child_frame_id": "base_link",
+        "x": sample.turns * 2 * math.pi * radius,
+        "vx": sample.turns_per_second * 2 * math.pi * radius,
+    }
+
+
+def ros_publisher(topic):
+    """Returns a function publishing odometry dicts, None without rclpy"""
+    try:
+        import rclpy
+        from nav_msgs.msg import Odometry
+    except ImportError:
+        return None
+
+    rclpy.init()
+    node = rclpy.create_node("tunepulse_odometry")
+    publisher = node.create_publisher(Odometry, topic, 10)
+
+    def publish(odom):
+        msg = Odometry()
+        msg.header.stamp.sec = int(odom["stamp"])
+        msg.header.stamp.nanosec = int((odom["stamp"] % 1) * 1e9)
+        msg.header.frame_id = odom["frame_id"]
+        msg.child_frame_id = odom["child_frame_id"]
+        msg.pose.pose.position.x = odom["x"]
+        msg.pose.pose.orientation.w = 1.0
+        msg.twist.twist.linear.x = odom["vx"]
+        publisher.publish(msg)
+
+    return publish
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("port")
+    parser.add_argument("--rate", type=int, default=250, help="samples per second")
+    parser.add_argument("--radius", type=float, default=0.05, help="wheel radius (m)")
+    parser.add_argument("--topic", default="odom")
+    args = parser.parse_args()
+
+    publish = ros_publisher(args.topic) or print
+    with Device(args.port) as drive:
+        drive.set("odometry_rate_hz", args.rate)
+        clock = SampleClock(args.rate)
+        try:
+            with drive.subscribe_odometry() as samples:
+                for sample in samples:
+                    stamp = clock.update(sample.seq)
+                    publish(to_odometry(sample, stamp, args.radius))
+        except KeyboardInterrupt:
+            pass
+        finally:
+            drive.set("odometry_rate_hz", 0)
+            print(f"lost samples: {clock.lost}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,74 @@
+#!/usr/bin/env python3
+"""Generates tunepulse/codes.py from the code tables of the tunepulse_params crate.
+
+The tables are taken from `tunepulse codes-json` (built from ../tunepulse-cli) or from a JSON
+file given as argument. Run it whenever the firmware gets a new fault, event or report code:
+
+    python gen_codes.py            # uses the CLI
+    python gen_codes.py codes.json
+"""
+
+import json
+import pathlib
+import subprocess
+import sys
+
+HERE = pathlib.Path(__file__).resolve().parent
+OUTPUT = HERE / "tunepulse" / "codes.py"
+CLI_MANIFEST = HERE.parent / "tunepulse-cli" / "Cargo.toml"
+
+
+def load_tables():
+    if len(sys.argv) > 1:
+        return json.loads(pathlib.Path(sys.argv[1]).read_text())
+    json_text = subprocess.run(
+        ["cargo", "run", "-q", "--manifest-path", str(CLI_MANIFEST), "--", "codes-json"],
+        check=True,
+        capture_output=True,
+        text=True,
+    ).stdout
+    return json.loads(json_text)
+
+
+def generate(tables):
+    lines = [
+        "# Generated by gen_codes.py from the tunepulse_params crate, do not edit.",
+        "",
+        "from dataclasses import dataclass",
+        "",
+        "",
+        "@dataclass(frozen=True)",
+        "class Code:",
+        "    code: int",
+        "    name: str",
+        "    unit: str  # unit of a reported value, empty if none",
+        "    description: str",
+        "",
+        "",
+        "def name(table, code):",
+        '    """Name of a code, "unknown" if the table doesn\'t know it"""',
+        '    return table[code].name if 0 <= code < len(table) else "unknown"',
+    ]
+    for table, codes in tables.items():
+        lines += ["", "", f"{table.upper()} = ("]
+        for c in codes:
+            lines.append(
+                f"    Code({c['code']}, {c['name']!r}, {c['unit']!r}, {c['description']!r}),"
+            )
+        lines.append(")")
+    lines.append("")
+    return "\n".join(lines)
+
+
+def main():
+    tables = load_tables()
+    for table, codes in tables.items():
+        if [c["code"] for c in codes] != list(range(len(codes))):
+            sys.exit(f"{table} must list codes without gaps")
+    OUTPUT.write_text(generate(tables))
+    count = sum(len(codes) for codes in tables.values())
+    print(f"{OUTPUT.relative_to(HERE)}: {len(tables)} tables, {count} codes")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,81 @@
+#!/usr/bin/env python3
+"""Generates tunepulse/params.py from the parameter table of the tunepulse_params crate.
+
+The table is taken from `tunepulse params-json` (built from ../tunepulse-cli) or from a JSON
+file given as argument. Run it whenever parameters are added to the firmware:
+
+    python gen_params.py            # uses the CLI
+    python gen_params.py params.json
+"""
+
+import json
+import pathlib
+import subprocess
+import sys
+
+HERE = pathlib.Path(__file__).resolve().parent
+OUTPUT = HERE / "tunepulse" / "params.py"
+CLI_MANIFEST = HERE.parent / "tunepulse-cli" / "Cargo.toml"
+
+
+def load_table():
+    if len(sys.argv) > 1:
+        return json.loads(pathlib.Path(sys.argv[1]).read_text())
+    json_text = subprocess.run(
+        ["cargo", "run", "-q", "--manifest-path", str(CLI_MANIFEST), "--", "params-json"],
+        check=True,
+        capture_output=True,
+        text=True,
+    ).stdout
+    return json.loads(json_text)
+
+
+def generate(table):
+    lines = [
+        "# Generated by gen_params.py from the tunepulse_params crate, do not edit.",
+        "",
+        "from dataclasses import dataclass",
+        "from enum import IntEnum",
+        "",
+        "",
+        "class ParamId(IntEnum):",
+    ]
+    lines += [f"    {p['name'].upper()} = {p['id']}" for p in table]
+    lines += [
+        "",
+        "",
+        "@dataclass(frozen=True)",
+        "class ParamDef:",
+        "    id: ParamId",
+        "    name: str",
+        "    kind: str  # unsigned, signed, bool or mask",
+        "    unit: str",
+        "    default: int",
+        "    min: int",
+        "    max: int",
+        "    hot: bool  # may be tuned while running",
+        "    access: str  # user, advanced or factory level needed to write it",
+        "",
+        "",
+        "PARAMS = (",
+    ]
+    for p in table:
+        lines.append(
+            f"    ParamDef(ParamId.{p['name'].upper()}, {p['name']!r}, {p['type']!r}, "
+            f"{p['unit']!r}, {p['default']}, {p['min']}, {p['max']}, {p['hot']!r}, "
+            f"{p['access']!r}),"
+        )
+    lines += [")", "", f"PARAM_COUNT = {len(table)}", ""]
+    return "\n".join(lines)
+
+
+def main():
+    table = load_table()
+    if [p["id"] for p in table] != list(range(len(table))):
+        sys.exit("parameter table must be ordered by identifier")
+    OUTPUT.write_text(generate(table))
+    print(f"{OUTPUT.relative_to(HERE)}: {len(table)} parameters")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,56 @@
+"""Host side of the TunePulse protocol for lab automation and hardware tests."""
+
+from .clock import TickClock
+from .device import Device, IncompatibleDevice, Jog, Subscription, param_def
+from .params import PARAM_COUNT, PARAMS, ParamDef, ParamId
+from .protocol import (
+    CanFunction,
+    Command,
+    DeviceInfo,
+    Electrical,
+    FactoryData,
+    MotorType,
+    Odometry,
+    PhasePattern,
+    Point,
+    ProductionResult,
+    ReplyError,
+    ScopeSignal,
+    SetpointMode,
+    Status,
+    StatusReport,
+    StepOutcome,
+    StepResult,
+    WizardResult,
+)
+
+__all__ = [
+    "CanFunction",
+    "Command",
+    "Device",
+    "DeviceInfo",
+    "Electrical",
+    "FactoryData",
+    "IncompatibleDevice",
+    "Jog",
+    "MotorType",
+    "Odometry",
+    "PARAM_COUNT",
+    "PARAMS",
+    "ParamDef",
+    "ParamId",
+    "PhasePattern",
+    "Point",
+    "ProductionResult",
+    "ReplyError",
+    "ScopeSignal",
+    "SetpointMode",
+    "Status",
+    "StatusReport",
+    "StepOutcome",
+    "StepResult",
+    "Subscription",
+    "TickClock",
+    "WizardResult",
+    "param_def",
+]
@@ -0,0 +1,100 @@
+"""Mapping of device ticks to host wall clock from time beacon exchanges.
+
+Every beacon yields one sample: the tick counter the device replied with and the midpoint of
+the host send and receive time. A least squares line through the recent samples gives the
+wall clock of a tick including the drift of the device oscillator, samples delayed by a busy
+link (round trip well above the best one) are left out. Devices synchronized to the same host
+clock share one time axis, so telemetry of several axes can be merged by wall clock.
+"""
+
+import threading
+from collections import deque
+
+WINDOW = 32  # Beacon exchanges kept for the fit, old ones age out as the oscillator drifts
+ROUND_TRIP_LIMIT = 2.0  # Samples with a round trip above this multiple of the best are ignored
+
+
+class TickClock:
+    """Beacon exchanges of one device and the fitted tick to wall clock relation"""
+
+    def __init__(self, window=WINDOW):
+        self._samples = deque(maxlen=window)  # (host time (s), extended tick, round trip (s))
+        self._last = None  # Last raw and extended tick counter
+        self._fit = None  # (reference tick, wall clock at the reference (s), tick period (s))
+        self._lock = threading.Lock()
+
+    def add(self, sent, received, tick):
+        """Adds a beacon exchange
+
+        sent, received: host wall clock (s, time.time()) of the beacon and of its reply
+        tick: tick counter of the reply
+        """
+        with self._lock:
+            extended = self._extend(tick)
+            round_trip = max(received - sent, 0.0)
+            self._samples.append((sent + round_trip / 2, extended, round_trip))
+            self._fit = self._fit_samples()
+            return extended
+
+    def _extend(self, tick):
+        if self._last is None:
+            extended = tick
+        else:
+            raw, extended = self._last
+            extended += _signed_delta(tick, raw)
+        self._last = (tick, extended)
+        return extended
+
+    def _fit_samples(self):
+        limit = min(s[2] for s in self._samples) * ROUND_TRIP_LIMIT
+        used = [s for s in self._samples if s[2] <= limit]
+        if len(used) < 2:
+            return None
+        host0, tick0, _ = used[0]
+        n = len(used)
+        xs = [tick - tick0 for _, tick, _ in used]
+        ys = [host - host0 for host, _, _ in used]
+        sx, sy = sum(xs), sum(ys)
+        sxx = sum(x * x for x in xs)
+        sxy = sum(x * y for x, y in zip(xs, ys))
+        denominator = n * sxx - sx * sx
+        if denominator <= 0:
+            return None
+        period = (n * sxy - sx * sy) / denominator
+        return tick0, host0 + (sy - period * sx) / n, period
+
+    @property
+    def synchronized(self):
+        """True once two beacon exchanges spanning some ticks were fitted"""
+        return self._fit is not None
+
+    @property
+    def tick_period(self):
+        """Real duration of one tick (s), None until synchronized"""
+        fit = self._fit
+        return None if fit is None else fit[2]
+
+    @property
+    def best_round_trip(self):
+        """Best round trip of the kept exchanges (s), bounds the accuracy of the mapping"""
+        with self._lock:
+            return min((s[2] for s in self._samples), default=None)
+
+    def wall_time(self, tick):
+        """Host wall clock (s, time.time()) of a raw 32-bit tick, None until synchronized
+
+        The tick is taken as the nearest one to the last beacon, so timestamps within
+        about 2^31 ticks of a beacon map correctly across the counter wraparound.
+        """
+        with self._lock:
+            if self._fit is None or self._last is None:
+                return None
+            raw, extended = self._last
+            reference, wall, period = self._fit
+            return wall + (extended + _signed_delta(tick, raw) - reference) * period
+
+
+def _signed_delta(tick, reference):
+    """Difference of two 32-bit tick counters, taken the short way around"""
+    delta = (tick - reference) & 0xFFFFFFFF
+    return delta - (1 << 32) if delta & 0x80000000 else delta
@@ -0,0 +1,149 @@
+# Generated by gen_codes.py from the tunepulse_params crate, do not edit.
+
+from dataclasses import dataclass
+
+
+@dataclass(frozen=True)
+class Code:
+    code: int
+    name: str
+    unit: str  # unit of a reported value, empty if none
+    description: str
+
+
+def name(table, code):
+    """Name of a code, "unknown" if the table doesn't know it"""
+    return table[code].name if 0 <= code < len(table) else "unknown"
+
+
+DRIVER_STATUS = (
+    Code(0, 'calibrating', '', 'Encoder calibration running'),
+    Code(1, 'ready', '', 'Calibrated, normal operation'),
+    Code(2, 'error', '', 'Error state, see the fault code'),
+)
+
+
+FAULT_CODES = (
+    Code(0, 'none', '', 'No fault present'),
+    Code(1, 'calibration_failed', '', 'Encoder calibration failed'),
+    Code(2, 'supply_undervoltage', '', 'Supply voltage too low for operation'),
+    Code(3, 'supply_overvoltage', '', 'Supply voltage exceeds the allowed maximum'),
+    Code(4, 'stall', '', 'Motor stalled or lost synchronism'),
+    Code(5, 'master_lost', '', 'Master position of the electronic gearing stopped updating'),
+    Code(6, 'calibration_loaded', '', 'Calibration aborted, the axis load skewed the table'),
+    Code(7, 'calibration_timeout', '', "Calibration stage timed out, the motor doesn't follow"),
+    Code(8, 'following_error', '', 'Position error stayed outside the following error window'),
+)
+
+
+MOTION_EVENTS = (
+    Code(0, 'target_reached', '', 'Position settled within the in-position window'),
+    Code(1, 'homing_complete', '', 'Homing sequence finished'),
+    Code(2, 'fault_raised', '', 'Fault raised, argument: fault code'),
+    Code(3, 'limit_hit', '', 'Limit switch or soft limit hit'),
+    Code(4, 'calibration_done', '', 'Calibration finished'),
+    Code(5, 'position_captured', '', 'Capture input latched the position'),
+    Code(6, 'collision_detected', '', 'Load torque step detected as collision'),
+    Code(7, 'jog_aborted', '', 'Jog stopped at the torque limit'),
+    Code(8, 'step_test_done', '', 'Step response test finished, argument 1 if aborted'),
+    Code(9, 'production_test_done', '', 'Production test finished, argument: failed metrics'),
+    Code(10, 'unsaved_changes', '', 'Disabled with unsaved parameter changes'),
+    Code(11, 'encoder_degraded', '', 'Rejected encoder samples bridged'),
+    Code(12, 'encoder_recovered', '', 'Encoder samples accepted again'),
+    Code(13, 'calibration_load_warning', '', 'Calibrated on a lightly loaded axis'),
+    Code(14, 'wizard_progress', '', 'Setup wizard step started or finished'),
+    Code(15, 'flash_write_failed', '', 'Parameter save or factory data write failed'),
+    Code(16, 'stall_warning', '', 'Load angle close to pull-out, argument: filtered load angle'),
+)
+
+
+REPLY_RESULTS = (
+    Code(0, 'ok', '', 'accepted'),
+    Code(1, 'unknown_param', '', 'unknown parameter'),
+    Code(2, 'out_of_range', '', 'value out of range'),
+    Code(3, 'unknown_command', '', 'unknown command'),
+    Code(4, 'rejected', '', 'rejected in current state'),
+    Code(5, 'invalid_frame', '', 'invalid frame'),
+    Code(6, 'access_denied', '', 'access level locked, unlock it first'),
+)
+
+
+STATUS_FLAGS = (
+    Code(0, 'enabled', '', 'Drive output enabled'),
+    Code(1, 'in_position', '', 'Position within the in-position window'),
+    Code(2, 'standstill', '', 'Position hold at standstill'),
+    Code(3, 'sequence', '', 'Motion sequence running'),
+    Code(4, 'excitation', '', 'Test signal running'),
+    Code(5, 'measuring', '', 'Measurement, test or setup wizard running'),
+    Code(6, 'following', '', 'Electronic gearing engaged'),
+    Code(7, 'collision', '', 'Collision latched'),
+)
+
+
+CALIBRATION_METRICS = (
+    Code(0, 'table_deviation', 'counts', 'Largest table deviation from an ideal encoder'),
+    Code(1, 'trim_spread', 'el counts', 'Electrical offset spread of the last trim'),
+    Code(2, 'torque_ripple', '‰', 'Torque ripple on the corrected angle'),
+    Code(3, 'torque_ripple_raw', '‰', 'Torque ripple on the raw encoder angle'),
+    Code(4, 'pass_hysteresis', 'counts', 'Mean hysteresis between the calibration passes'),
+    Code(5, 'load_flags', '', 'Load check flags: 1 friction, 2 asymmetry, 4 settling'),
+    Code(6, 'load_angle_ripple', 'el counts', 'Load angle ripple in operation since the calibration'),
+)
+
+
+PRODUCTION_METRICS = (
+    Code(0, 'self_test', '', 'Failed self-test checks'),
+    Code(1, 'supply_mv', 'mV', 'Supply voltage'),
+    Code(2, 'temperature_adc', 'ADC', 'Temperature sensor reading'),
+    Code(3, 'table_deviation', 'counts', 'Largest table deviation from an ideal encoder'),
+    Code(4, 'trim_spread', 'el counts', 'Electrical offset spread of the trim'),
+)
+
+
+WIZARD_STEPS = (
+    Code(0, 'supply_check', '', 'Supply charged and bridge enabled'),
+    Code(1, 'wiring_check', '', 'Current through both coils'),
+    Code(2, 'impedance', '', 'Winding resistance and inductance'),
+    Code(3, 'encoder_check', '', 'Encoder follows an electrical turn'),
+    Code(4, 'calibration', '', 'Encoder calibration'),
+    Code(5, 'gain_suggestion', '', 'Closest motor preset'),
+)
+
+
+STEP_OUTCOMES = (
+    Code(0, 'pending', '', 'Not reached'),
+    Code(1, 'running', '', 'Running'),
+    Code(2, 'passed', '', 'Passed'),
+    Code(3, 'failed', '', 'Failed'),
+    Code(4, 'skipped', '', 'Skipped, not measurable on this board'),
+    Code(5, 'aborted', '', 'Aborted'),
+)
+
+
+WIZARD_RESULTS = (
+    Code(0, 'supply_mv', 'mV', 'Supply voltage'),
+    Code(1, 'open_coils', '', 'Open coils: 1 coil A, 2 coil B'),
+    Code(2, 'resistance', 'mOhm', 'Phase resistance'),
+    Code(3, 'inductance', 'uH', 'Phase inductance'),
+    Code(4, 'encoder_travel', 'counts', 'Encoder travel of one electrical turn'),
+    Code(5, 'pole_pairs', '', 'Pole pairs from the encoder travel'),
+    Code(6, 'suggested_preset', '', 'Closest motor preset, see `MOTOR_PRESETS`'),
+)
+
+
+PEAK_VALUES = (
+    Code(0, 'current', 'mA', 'Largest measured current magnitude'),
+    Code(1, 'temperature_adc', 'ADC', 'Largest temperature sensor reading'),
+    Code(2, 'speed', 'counts/s', 'Largest speed magnitude'),
+    Code(3, 'following_error', 'counts', 'Largest position error beyond the deadband'),
+)
+
+
+MOTOR_PRESETS = (
+    Code(0, 'custom', '', 'Data configured by the board or the host'),
+    Code(1, 'nema17', '', 'NEMA17 stepper, 1.8°, ~1.7 A'),
+    Code(2, 'nema17_fine', '', 'NEMA17 stepper, 0.9°, ~2 A'),
+    Code(3, 'nema23', '', 'NEMA23 stepper, 1.8°, ~2.8 A'),
+    Code(4, 'gimbal2208', '', '2208 gimbal BLDC, 12N14P'),
+    Code(5, 'gimbal4108', '', '4108 gimbal BLDC, 24N22P'),
+)
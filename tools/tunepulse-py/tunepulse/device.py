@@ -0,0 +1,554 @@
+"""Connection to a TunePulse drive over a serial port (UART or USB CDC).
+
+A reader thread splits the incoming stream into replies, which are matched to the pending
+request, and telemetry points, which are delivered to all subscriptions.
+"""
+
+import queue
+import threading
+import time
+
+from . import protocol
+from .clock import TickClock
+from .params import PARAM_COUNT, PARAMS, ParamDef, ParamId
+from .protocol import Command, FrameType, MotorType, PhasePattern, SetpointMode
+
+TIMEOUT = 0.5  # Reply timeout (s)
+JOG_REPEAT = 0.05  # Jog command interval (s), well within the default jog timeout
+BEACON_INTERVAL = 1.0  # Time beacon interval (s) of the background clock synchronization
+
+
+class IncompatibleDevice(Exception):
+    """Firmware uses a different protocol version or parameter table"""
+
+
+def param_def(param):
+    """Finds a parameter by ParamId, numeric identifier or name"""
+    if isinstance(param, ParamDef):
+        return param
+    if isinstance(param, str):
+        for p in PARAMS:
+            if p.name == param:
+                return p
+        raise KeyError(f"unknown parameter {param!r}")
+    return PARAMS[int(param)]
+
+
+def _encode(p, value):
+    """Checks a value against the table and converts it to the raw u32"""
+    if p.kind == "signed":
+        raw = int(value) & 0xFFFFFFFF
+        low, high = _signed(p.min), _signed(p.max)
+        ok = low <= int(value) <= high
+    else:
+        raw = int(value)
+        ok = p.min <= raw <= p.max
+    if not ok:
+        raise ValueError(f"{p.name}: {value} out of range")
+    return raw
+
+
+def _decode(p, raw):
+    if p.kind == "signed":
+        return _signed(raw)
+    if p.kind == "bool":
+        return bool(raw)
+    return raw
+
+
+def _signed(raw):
+    return raw - (1 << 32) if raw & 0x80000000 else raw
+
+
+class Subscription:
+    """Telemetry points, odometry samples or status reports since subscribing, iterate to consume"""
+
+    def __init__(self, device, ids):
+        self._device = device
+        self.ids = None if ids is None else frozenset(ids)
+        self.queue = queue.Queue()
+
+    def _offer(self, point):
+        if self.ids is None or point.id in self.ids:
+            self.queue.put(point)
+
+    def get(self, timeout=None):
+        """Next point, raises queue.Empty after `timeout` seconds"""
+        return self.queue.get(timeout=timeout)
+
+    def __iter__(self):
+        while True:
+            yield self.queue.get()
+
+    def close(self):
+        self._device._unsubscribe(self)
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
+
+
+class Jog:
+    """Running jog, repeats the jog command until stopped
+
+    The drive stops by itself when the repetitions stop (script crashed, link lost) and when
+    the jog torque cap is reached, an aborted jog ends the repetitions.
+    """
+
+    def __init__(self, device, cmd):
+        self._device = device
+        self._cmd = cmd
+        self._stop = threading.Event()
+        device.command(cmd)  # Raises if the drive rejects the jog
+        self._thread = threading.Thread(target=self._repeat, daemon=True)
+        self._thread.start()
+
+    def _repeat(self):
+        while not self._stop.wait(JOG_REPEAT):
+            try:
+                self._device.command(self._cmd)
+            except (protocol.ReplyError, TimeoutError):
+                return  # Aborted at the torque cap or link lost, the drive has stopped
+
+    @property
+    def running(self):
+        """False once the drive aborted the jog"""
+        return self._thread.is_alive()
+
+    def stop(self):
+        """Stops the jog, also re-arms the jog after an abort"""
+        self._stop.set()
+        self._thread.join()
+        self._device.command(Command.JOG_STOP)
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.stop()
+
+
+class Device:
+    """TunePulse drive connected through a serial port
+
+    Example:
+        with Device("/dev/ttyACM0") as drive:
+            drive.set("damping_gain", 128)
+            drive.command(Command.ENABLE)
+            with drive.subscribe() as points:
+                print(points.get(timeout=1.0))
+    """
+
+    def __init__(self, port, baud=115200, check=True):
+        import serial  # pyserial, imported here so the protocol helpers work without it
+
+        self._port = serial.Serial(port, baud, timeout=0.01)
+        self._deframer = protocol.Deframer()
+        self._replies = queue.Queue()
+        self._request_lock = threading.Lock()
+        self._subscriptions = []
+        self._odometry = []
+        self._electrical = []
+        self._status = []
+        self._health = None  # First page of the status report being received
+        self.clock = TickClock()  # Device ticks to host wall clock, fed by time beacons
+        self._beacon_seq = 0
+        self._beacon_stop = threading.Event()
+        self._beacons = None
+        self._subscriptions_lock = threading.Lock()
+        self._running = True
+        self._reader = threading.Thread(target=self._read_loop, daemon=True)
+        self._reader.start()
+        if check:
+            self.check_compatible()
+
+    def close(self):
+        self._beacon_stop.set()
+        if self._beacons is not None:
+            self._beacons.join()
+        self._running = False
+        self._reader.join()
+        self._port.close()
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
+
+    # Requests
+
+    def request(self, frame, reply):
+        """Sends a frame and waits for the reply of the given type"""
+        with self._request_lock:
+            while not self._replies.empty():
+                self._replies.get_nowait()  # Drop stale replies
+            self._port.write(frame)
+            deadline = time.monotonic() + TIMEOUT
+            while (remaining := deadline - time.monotonic()) > 0:
+                try:
+                    frame = self._replies.get(timeout=remaining)
+                except queue.Empty:
+                    break
+                if frame[0] == reply:
+                    return frame
+            raise TimeoutError("no reply from drive")
+
+    def get(self, param):
+        """Reads a parameter"""
+        p = param_def(param)
+        frame = self.request(protocol.param_read(p.id), FrameType.PARAM_VALUE)
+        return _decode(p, protocol.param_value(frame))
+
+    def set(self, param, value):
+        """Writes a parameter"""
+        p = param_def(param)
+        frame = self.request(protocol.param_write(p.id, _encode(p, value)), FrameType.PARAM_VALUE)
+        protocol.param_value(frame)
+
+    def tune(self, **values):
+        """Stages hot-tunable parameters and applies them together between control ticks"""
+        try:
+            for name, value in values.items():
+                p = param_def(name)
+                frame = protocol.param_stage(p.id, _encode(p, value))
+                protocol.param_value(self.request(frame, FrameType.PARAM_VALUE))
+        except Exception:
+            self.command(Command.DISCARD_STAGED)
+            raise
+        self.command(Command.APPLY_STAGED)
+
+    def command(self, cmd, arg=0):
+        """Executes a command"""
+        frame = self.request(protocol.command(Command(cmd), arg), FrameType.COMMAND_RESULT)
+        protocol.check_result(frame[1])
+
+    def unlock(self, level):
+        """Unlocks the "advanced" or "factory" access level until lock() or reset"""
+        self.command(Command.UNLOCK, protocol.ACCESS_KEYS[level])
+
+    def lock(self):
+        """Returns to the user access level"""
+        self.command(Command.LOCK)
+
+    def reconfigure(self, motor, pattern=PhasePattern.ABCD):
+        """Swaps motor type and phase connection of the disabled drive and restarts the calibration
+
+        Refused while the drive is enabled, the windings are energized or a test runs.
+        """
+        motor, pattern = MotorType(motor), PhasePattern(pattern)
+        self.command(Command.RECONFIGURE_MOTOR, motor | pattern << 16)
+
+    def status(self):
+        frame = self.request(protocol.status_read(), FrameType.STATUS)
+        return protocol.Status.decode(frame)
+
+    def state(self):
+        """Reads the calibration sub-stage and progress, whether the windings are energized and
+        the at target velocity and zero speed flags"""
+        frame = self.request(protocol.state_read(), FrameType.STATE)
+        return protocol.State.decode(frame)
+
+    def capture(self, arm=False):
+        """Reads the position latched by the capture input, optionally arming the next capture"""
+        frame = self.request(protocol.capture_read(), FrameType.CAPTURE)
+        if arm:
+            self.command(Command.ARM_CAPTURE)
+        return protocol.Capture.decode(frame)
+
+    def jog(self, backward=False):
+        """Starts a jog with the jog_speed and jog_torque_ma parameters
+
+        Example:
+            with drive.jog():
+                time.sleep(1.0)
+        """
+        return Jog(self, Command.JOG_BACKWARD if backward else Command.JOG_FORWARD)
+
+    def step_test(self, timeout=30.0):
+        """Runs the current and velocity step response tests configured by the step_*
+        parameters, returns the results by test name (None if a test was aborted)"""
+        self.command(Command.START_STEP_TEST)
+        deadline = time.monotonic() + timeout
+        while self.status().flags & protocol.STATUS_MEASURING:
+            if time.monotonic() > deadline:
+                self.command(Command.STOP_EXCITATION)
+                raise TimeoutError("step test didn't finish")
+            time.sleep(0.1)
+        results = {}
+        for index, name in enumerate(protocol.STEP_TESTS):
+            frame = self.request(protocol.step_result_read(index), FrameType.STEP_RESULT)
+            results[name] = protocol.StepResult.decode(frame)
+        return results
+
+    def production_test(self, timeout=30.0):
+        """Runs the on-device production test against the production_* limits, returns the
+        results by metric name, the unit passed if all results passed"""
+        self.command(Command.START_PRODUCTION_TEST)
+        deadline = time.monotonic() + timeout
+        while self.status().flags & protocol.STATUS_MEASURING:
+            if time.monotonic() > deadline:
+                raise TimeoutError("production test didn't finish")
+            time.sleep(0.1)
+        results = {}
+        for index, name in enumerate(protocol.PRODUCTION_METRICS):
+            frame = self.request(
+                protocol.production_result_read(index), FrameType.PRODUCTION_RESULT
+            )
+            result = protocol.ProductionResult.decode(frame)
+            if result is None:
+                raise RuntimeError("production test didn't finish")
+            results[name] = result
+        return results
+
+    def wizard(self, timeout=60.0):
+        """Runs the first-run setup wizard on the disabled drive, returns the results by name
+
+        Every result carries the outcome of its step, a failed step ends the wizard. The
+        suggested preset is applied by writing it to the motor_preset parameter.
+        """
+        self.command(Command.START_WIZARD)
+        deadline = time.monotonic() + timeout
+        while self.status().flags & protocol.STATUS_MEASURING:
+            if time.monotonic() > deadline:
+                self.command(Command.ABORT_WIZARD)
+                raise TimeoutError("setup wizard didn't finish")
+            time.sleep(0.2)
+        results = {}
+        for index, name in enumerate(protocol.WIZARD_RESULTS):
+            frame = self.request(protocol.wizard_result_read(index), FrameType.WIZARD_RESULT)
+            results[name] = protocol.WizardResult.decode(frame)
+        return results
+
+    def calibration_report(self):
+        """Reads the calibration report, returns the metrics by name (None if not measured)
+
+        The torque ripple is measured after a calibration with ripple_test set. The load flags
+        (protocol.LOAD_*) name the signs of a loaded axis the calibration passes showed.
+        """
+        return {
+            name: protocol.calibration_metric(
+                self.request(protocol.calibration_report_read(index), FrameType.CALIBRATION_REPORT)
+            )
+            for index, name in enumerate(protocol.CALIBRATION_METRICS)
+        }
+
+    def peaks(self, clear=False):
+        """Reads the peak-hold registers, returns the values by name (None if not sampled)
+
+        With clear set every register is cleared by the read returning it, so no peak between
+        the reads gets lost.
+        """
+        return {
+            name: protocol.peak_value(
+                self.request(protocol.peak_read(index, clear), FrameType.PEAK)
+            )
+            for index, name in enumerate(protocol.PEAK_VALUES)
+        }
+
+    def export_table(self):
+        """Downloads the calibration table as image, e.g. to back it up or clone it"""
+        self.command(Command.EXPORT_TABLE)
+        image = bytearray()
+        length = protocol.TABLE_HEADER_SIZE
+        while len(image) < length:
+            frame = self.request(protocol.table_read(len(image)), FrameType.TABLE_DATA)
+            image += protocol.table_data(frame)
+            if len(image) == protocol.TABLE_HEADER_SIZE:
+                length += protocol.table_payload_len(image)
+        image = bytes(image[:length])
+        protocol.check_table_image(image)
+        return image
+
+    def import_table(self, image, golden=False):
+        """Uploads a calibration table image from export_table(), rejected while the motor
+        produces torque
+
+        A golden unit table (golden=True) is aligned to this unit by a single point offset
+        trim, the drive reports ready again once the trim finished.
+        """
+        image = bytes(image)
+        protocol.check_table_image(image)
+        for offset in range(0, len(image), protocol.TABLE_CHUNK_SIZE):
+            chunk = image[offset : offset + protocol.TABLE_CHUNK_SIZE]
+            chunk += bytes(protocol.TABLE_CHUNK_SIZE - len(chunk))
+            frame = self.request(protocol.table_write(offset, chunk), FrameType.TABLE_DATA)
+            protocol.table_data(frame)
+        self.command(Command.IMPORT_GOLDEN_TABLE if golden else Command.IMPORT_TABLE)
+
+    def master_position(self, position):
+        """Sends the master position followed by the electronic gearing, returns the status"""
+        frame = self.request(protocol.master_position(position), FrameType.STATUS)
+        return protocol.Status.decode(frame)
+
+    def setpoint(self, mode, value):
+        """Sets the command followed in normal operation (see SetpointMode), returns the status"""
+        frame = self.request(protocol.setpoint(SetpointMode(mode), value), FrameType.STATUS)
+        return protocol.Status.decode(frame)
+
+    def info(self):
+        pages = [
+            self.request(protocol.device_info_read(page), FrameType.DEVICE_INFO)
+            for page in range(protocol.DEVICE_INFO_PAGES)
+        ]
+        return protocol.DeviceInfo.decode(pages)
+
+    def factory_data(self):
+        """Reads the factory data, None if the unit has none"""
+        pages = [
+            self.request(protocol.factory_read(page), FrameType.FACTORY_DATA)
+            for page in range(protocol.FACTORY_PAGES)
+        ]
+        return protocol.FactoryData.decode(pages)
+
+    def write_factory_data(self, data):
+        """Writes the FactoryData once, rejected if the unit already holds factory data. The
+        factory access level is unlocked for the write."""
+        self.unlock("factory")
+        try:
+            for page, payload in enumerate(data.pages()):
+                frame = self.request(protocol.factory_write(page, payload), FrameType.FACTORY_DATA)
+                if frame[1] != page:
+                    raise RuntimeError("factory data staging refused")
+            self.command(Command.WRITE_FACTORY_DATA)
+        finally:
+            self.lock()
+
+    def check_compatible(self):
+        """Raises IncompatibleDevice if the firmware doesn't match this package"""
+        info = self.info()
+        if info.protocol != protocol.PROTOCOL_VERSION:
+            raise IncompatibleDevice(
+                f"protocol version {info.protocol}, expected {protocol.PROTOCOL_VERSION}"
+            )
+        if info.param_count != PARAM_COUNT:
+            raise IncompatibleDevice(
+                f"{info.param_count} parameters, expected {PARAM_COUNT} "
+                "(regenerate params.py with gen_params.py)"
+            )
+
+    # Time synchronization
+
+    def beacon(self):
+        """Exchanges one time beacon, returns the tick counter of the reply"""
+        seq = self._beacon_seq
+        self._beacon_seq = (seq + 1) & 0xFF
+        sent = time.time()
+        frame = self.request(protocol.time_beacon(seq, int(sent * 1000)), FrameType.TIME_SYNC)
+        received = time.time()
+        reply_seq, tick = protocol.time_sync(frame)
+        if reply_seq == seq:
+            self.clock.add(sent, received, tick)
+        return tick
+
+    def sync_clock(self, interval=BEACON_INTERVAL, count=4):
+        """Synchronizes the clock with `count` quick beacons, then keeps it in sync
+
+        Beacons are repeated every `interval` seconds from a background thread until close(),
+        so wall_time() follows the drift of the device oscillator.
+        """
+        for _ in range(count):
+            self.beacon()
+            time.sleep(0.01)
+        if self._beacons is None:
+            self._beacons = threading.Thread(
+                target=self._beacon_loop, args=(interval,), daemon=True
+            )
+            self._beacons.start()
+
+    def _beacon_loop(self, interval):
+        while not self._beacon_stop.wait(interval):
+            try:
+                self.beacon()
+            except TimeoutError:
+                continue  # Busy or lost link, the fit keeps the last good exchanges
+
+    def wall_time(self, tick):
+        """Host wall clock (s, time.time()) of a telemetry tick, None before sync_clock()"""
+        return self.clock.wall_time(tick)
+
+    # Telemetry
+
+    def scope(self, channel0, channel1=0, decimation=1):
+        """Routes internal signals (see ScopeSignal in the firmware) to the scope channels"""
+        self.set(ParamId.SCOPE_CHANNEL0, int(channel0))
+        self.set(ParamId.SCOPE_CHANNEL1, int(channel1))
+        self.set(ParamId.SCOPE_DECIMATION, decimation)
+
+    def subscribe(self, ids=None):
+        """Subscribes to telemetry points, all or only the given identifiers"""
+        subscription = Subscription(self, ids)
+        with self._subscriptions_lock:
+            self._subscriptions.append(subscription)
+        return subscription
+
+    def subscribe_odometry(self):
+        """Subscribes to the odometry samples enabled by the odometry_rate_hz parameter"""
+        subscription = Subscription(self, None)
+        with self._subscriptions_lock:
+            self._odometry.append(subscription)
+        return subscription
+
+    def subscribe_electrical(self):
+        """Subscribes to the current and supply samples sent with the odometry samples"""
+        subscription = Subscription(self, None)
+        with self._subscriptions_lock:
+            self._electrical.append(subscription)
+        return subscription
+
+    def subscribe_status(self):
+        """Subscribes to the slow status reports enabled by the status_rate_hz parameter"""
+        subscription = Subscription(self, None)
+        with self._subscriptions_lock:
+            self._status.append(subscription)
+        return subscription
+
+    def _unsubscribe(self, subscription):
+        with self._subscriptions_lock:
+            for subscriptions in (
+                self._subscriptions,
+                self._odometry,
+                self._electrical,
+                self._status,
+            ):
+                if subscription in subscriptions:
+                    subscriptions.remove(subscription)
+
+    def _status_page(self, frame):
+        if frame[1] == 0:
+            self._health = frame
+        elif frame[1] == 1 and self._health is not None:
+            report = protocol.StatusReport.decode(self._health, frame)
+            self._health = None
+            with self._subscriptions_lock:
+                for subscription in self._status:
+                    subscription._offer(report)
+
+    def _read_loop(self):
+        while self._running:
+            data = self._port.read(4096)
+            if not data:
+                continue
+            frames, points = self._deframer.feed(data)
+            for frame in frames:
+                if frame[0] == FrameType.ODOMETRY:
+                    sample = protocol.Odometry.decode(frame)
+                    with self._subscriptions_lock:
+                        for subscription in self._odometry:
+                            subscription._offer(sample)
+                elif frame[0] == FrameType.ELECTRICAL:
+                    sample = protocol.Electrical.decode(frame)
+                    with self._subscriptions_lock:
+                        for subscription in self._electrical:
+                            subscription._offer(sample)
+                elif frame[0] == FrameType.STATUS_REPORT:
+                    self._status_page(frame)
+                elif frame[0] != FrameType.EVENT:
+                    self._replies.put(frame)
+            if points:
+                with self._subscriptions_lock:
+                    for subscription in self._subscriptions:
+                        for point in points:
+                            subscription._offer(point)
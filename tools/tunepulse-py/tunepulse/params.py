@@ -0,0 +1,240 @@
+# Generated by gen_params.py from the tunepulse_params crate, do not edit.
+
+from dataclasses import dataclass
+from enum import IntEnum
+
+
+class ParamId(IntEnum):
+    EVENT_MASK = 0
+    IO_PIN0 = 1
+    IO_PIN1 = 2
+    IO_PIN2 = 3
+    IO_PIN3 = 4
+    BRAKE_RELEASE_MS = 5
+    BRAKE_ENGAGE_MS = 6
+    BEEP_ENABLE = 7
+    BEEP_CURRENT_MA = 8
+    DAMPING_GAIN = 9
+    DAMPING_LIMIT_MA = 10
+    DISTURBANCE_FEEDBACK = 11
+    HOLD_FILTER_ALPHA = 12
+    SCOPE_CH0 = 13
+    SCOPE_CH1 = 14
+    SCOPE_DECIMATION = 15
+    EXCITATION_POINT = 16
+    EXCITATION_WAVEFORM = 17
+    EXCITATION_AMPLITUDE = 18
+    EXCITATION_FREQ_MHZ = 19
+    EXCITATION_FREQ_END_MHZ = 20
+    EXCITATION_DURATION_MS = 21
+    BODE_POINTS = 22
+    BODE_PERIODS = 23
+    BODE_RESPONSE = 24
+    CAPTURE_MODE = 25
+    COMPARE_POS0 = 26
+    COMPARE_POS1 = 27
+    COMPARE_POS2 = 28
+    COMPARE_POS3 = 29
+    COMPARE_COUNT = 30
+    COMPARE_ACTION = 31
+    COMPARE_DIRECTION = 32
+    COMPARE_PULSE_US = 33
+    GEAR_NUMERATOR = 34
+    GEAR_DENOMINATOR = 35
+    GEAR_OFFSET = 36
+    FOLLOW_MAX_SPEED = 37
+    FOLLOW_TIMEOUT_MS = 38
+    ODOMETRY_RATE_HZ = 39
+    SPEED_WINDOW = 40
+    SPEED_FILTER_ALPHA = 41
+    SPEED_UNIT = 42
+    COLLISION_THRESHOLD_MA = 43
+    COLLISION_DEBOUNCE_MS = 44
+    COLLISION_REACTION = 45
+    COLLISION_REVERSE = 46
+    COLLISION_TORQUE_MA = 47
+    JOG_SPEED = 48
+    JOG_TORQUE_MA = 49
+    JOG_TIMEOUT_MS = 50
+    STEP_CURRENT_MA = 51
+    STEP_SPEED = 52
+    STEP_DURATION_MS = 53
+    STEP_TRAVEL = 54
+    ENCODER_INVERT = 55
+    ENCODER_OFFSET = 56
+    FILTER_ALPHA_SLOW = 57
+    FILTER_ALPHA_FAST = 58
+    FILTER_SPEED_SLOW = 59
+    FILTER_SPEED_FAST = 60
+    FILTER_ORDER = 61
+    FAULT_REACTION_SENSOR = 62
+    FAULT_REACTION_SUPPLY = 63
+    FAULT_REACTION_MOTION = 64
+    FAULT_RAMP_TIME = 65
+    SUPPLY_ENABLE_VOLTAGE = 66
+    SUPPLY_MAX_SLOPE = 67
+    SUPPLY_SETTLE_TIME = 68
+    SCOPE_ANGLE_MASK = 69
+    PRODUCTION_SUPPLY_MIN = 70
+    PRODUCTION_SUPPLY_MAX = 71
+    PRODUCTION_TEMP_MIN = 72
+    PRODUCTION_TEMP_MAX = 73
+    PRODUCTION_DEVIATION_MAX = 74
+    PRODUCTION_SPREAD_MAX = 75
+    ENCODER_GLITCH_THRESHOLD = 76
+    ENCODER_EMC_MODE = 77
+    RIPPLE_TEST = 78
+    SUPPLY_UNDERVOLTAGE = 79
+    SUPPLY_OVERVOLTAGE = 80
+    SUPPLY_MISS_TIME = 81
+    SUPPLY_GRACE_TIME = 82
+    STATUS_RATE_HZ = 83
+    DEAD_TIME_COMPENSATION = 84
+    CURRENT_BLANKING_NS = 85
+    MOTOR_PRESET = 86
+    CURRENT_LOOP_KP = 87
+    CURRENT_LOOP_KI = 88
+    ANGLE_SOURCE = 89
+    OBSERVER_BANDWIDTH = 90
+    SENSORLESS_MIN_EMF = 91
+    SENSORLESS_START_SPEED = 92
+    SCOPE_ENVELOPE = 93
+    STEP_INPUT = 94
+    CAN_NODE = 95
+    CAN_BITRATE = 96
+    CAN_PROTOCOL = 97
+    FOLLOWING_ERROR_WINDOW = 98
+    FOLLOWING_ERROR_TIMEOUT_MS = 99
+    VELOCITY_WINDOW = 100
+    VELOCITY_WINDOW_MS = 101
+    ZERO_SPEED_THRESHOLD = 102
+    ZERO_SPEED_MS = 103
+    STEP_LOSS_THRESHOLD = 104
+    STEP_LOSS_MIN_SPEED_HZ = 105
+    STEP_LOSS_DEBOUNCE_MS = 106
+
+
+@dataclass(frozen=True)
+class ParamDef:
+    id: ParamId
+    name: str
+    kind: str  # unsigned, signed, bool or mask
+    unit: str
+    default: int
+    min: int
+    max: int
+    hot: bool  # may be tuned while running
+    access: str  # user, advanced or factory level needed to write it
+
+
+PARAMS = (
+    ParamDef(ParamId.EVENT_MASK, 'event_mask', 'mask', '', 4294967295, 0, 4294967295, False, 'user'),
+    ParamDef(ParamId.IO_PIN0, 'io_pin0', 'unsigned', '', 0, 0, 511, False, 'user'),
+    ParamDef(ParamId.IO_PIN1, 'io_pin1', 'unsigned', '', 0, 0, 511, False, 'user'),
+    ParamDef(ParamId.IO_PIN2, 'io_pin2', 'unsigned', '', 0, 0, 511, False, 'user'),
+    ParamDef(ParamId.IO_PIN3, 'io_pin3', 'unsigned', '', 0, 0, 511, False, 'user'),
+    ParamDef(ParamId.BRAKE_RELEASE_MS, 'brake_release_ms', 'unsigned', 'ms', 0, 0, 2000, False, 'advanced'),
+    ParamDef(ParamId.BRAKE_ENGAGE_MS, 'brake_engage_ms', 'unsigned', 'ms', 0, 0, 2000, False, 'advanced'),
+    ParamDef(ParamId.BEEP_ENABLE, 'beep_enable', 'bool', '', 1, 0, 1, False, 'user'),
+    ParamDef(ParamId.BEEP_CURRENT_MA, 'beep_current_ma', 'unsigned', 'mA', 300, 0, 1000, False, 'advanced'),
+    ParamDef(ParamId.DAMPING_GAIN, 'damping_gain', 'unsigned', '', 0, 0, 65535, True, 'user'),
+    ParamDef(ParamId.DAMPING_LIMIT_MA, 'damping_limit_ma', 'unsigned', 'mA', 0, 0, 2000, True, 'advanced'),
+    ParamDef(ParamId.DISTURBANCE_FEEDBACK, 'disturbance_feedback', 'unsigned', '', 0, 0, 255, True, 'user'),
+    ParamDef(ParamId.HOLD_FILTER_ALPHA, 'hold_filter_alpha', 'unsigned', '', 224, 0, 255, True, 'user'),
+    ParamDef(ParamId.SCOPE_CH0, 'scope_ch0', 'unsigned', '', 0, 0, 25, True, 'user'),
+    ParamDef(ParamId.SCOPE_CH1, 'scope_ch1', 'unsigned', '', 0, 0, 25, True, 'user'),
+    ParamDef(ParamId.SCOPE_DECIMATION, 'scope_decimation', 'unsigned', '', 1, 1, 65535, True, 'user'),
+    ParamDef(ParamId.EXCITATION_POINT, 'excitation_point', 'unsigned', '', 0, 0, 2, True, 'user'),
+    ParamDef(ParamId.EXCITATION_WAVEFORM, 'excitation_waveform', 'unsigned', '', 0, 0, 3, True, 'user'),
+    ParamDef(ParamId.EXCITATION_AMPLITUDE, 'excitation_amplitude', 'unsigned', '', 0, 0, 65536, True, 'user'),
+    ParamDef(ParamId.EXCITATION_FREQ_MHZ, 'excitation_freq_mhz', 'unsigned', 'mHz', 1000, 0, 10000000, True, 'user'),
+    ParamDef(ParamId.EXCITATION_FREQ_END_MHZ, 'excitation_freq_end_mhz', 'unsigned', 'mHz', 1000, 0, 10000000, True, 'user'),
+    ParamDef(ParamId.EXCITATION_DURATION_MS, 'excitation_duration_ms', 'unsigned', 'ms', 1000, 0, 600000, True, 'user'),
+    ParamDef(ParamId.BODE_POINTS, 'bode_points', 'unsigned', '', 20, 0, 64, True, 'user'),
+    ParamDef(ParamId.BODE_PERIODS, 'bode_periods', 'unsigned', '', 5, 0, 1000, True, 'user'),
+    ParamDef(ParamId.BODE_RESPONSE, 'bode_response', 'unsigned', '', 1, 0, 16, True, 'user'),
+    ParamDef(ParamId.CAPTURE_MODE, 'capture_mode', 'unsigned', '', 0, 0, 1, False, 'user'),
+    ParamDef(ParamId.COMPARE_POS0, 'compare_pos0', 'signed', '', 0, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.COMPARE_POS1, 'compare_pos1', 'signed', '', 0, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.COMPARE_POS2, 'compare_pos2', 'signed', '', 0, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.COMPARE_POS3, 'compare_pos3', 'signed', '', 0, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.COMPARE_COUNT, 'compare_count', 'unsigned', '', 0, 0, 4, True, 'user'),
+    ParamDef(ParamId.COMPARE_ACTION, 'compare_action', 'unsigned', '', 0, 0, 1, True, 'user'),
+    ParamDef(ParamId.COMPARE_DIRECTION, 'compare_direction', 'unsigned', '', 0, 0, 2, True, 'user'),
+    ParamDef(ParamId.COMPARE_PULSE_US, 'compare_pulse_us', 'unsigned', 'us', 1000, 0, 1000000, True, 'user'),
+    ParamDef(ParamId.GEAR_NUMERATOR, 'gear_numerator', 'signed', '', 1, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.GEAR_DENOMINATOR, 'gear_denominator', 'unsigned', '', 1, 1, 2147483647, True, 'user'),
+    ParamDef(ParamId.GEAR_OFFSET, 'gear_offset', 'signed', '', 0, 0, 4294967295, True, 'user'),
+    ParamDef(ParamId.FOLLOW_MAX_SPEED, 'follow_max_speed', 'unsigned', 'cnt/s', 0, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.FOLLOW_TIMEOUT_MS, 'follow_timeout_ms', 'unsigned', 'ms', 100, 0, 60000, False, 'user'),
+    ParamDef(ParamId.ODOMETRY_RATE_HZ, 'odometry_rate_hz', 'unsigned', 'Hz', 0, 0, 2000, True, 'user'),
+    ParamDef(ParamId.SPEED_WINDOW, 'speed_window', 'unsigned', 'ticks', 8, 1, 32, True, 'user'),
+    ParamDef(ParamId.SPEED_FILTER_ALPHA, 'speed_filter_alpha', 'unsigned', '', 0, 0, 255, True, 'user'),
+    ParamDef(ParamId.SPEED_UNIT, 'speed_unit', 'unsigned', '', 0, 0, 3, True, 'user'),
+    ParamDef(ParamId.COLLISION_THRESHOLD_MA, 'collision_threshold_ma', 'unsigned', 'mA', 0, 0, 5000, True, 'user'),
+    ParamDef(ParamId.COLLISION_DEBOUNCE_MS, 'collision_debounce_ms', 'unsigned', 'ms', 5, 0, 1000, True, 'user'),
+    ParamDef(ParamId.COLLISION_REACTION, 'collision_reaction', 'unsigned', '', 0, 0, 2, True, 'user'),
+    ParamDef(ParamId.COLLISION_REVERSE, 'collision_reverse', 'unsigned', '', 8192, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.COLLISION_TORQUE_MA, 'collision_torque_ma', 'unsigned', 'mA', 200, 0, 5000, True, 'user'),
+    ParamDef(ParamId.JOG_SPEED, 'jog_speed', 'unsigned', 'counts/s', 16384, 0, 655360, True, 'user'),
+    ParamDef(ParamId.JOG_TORQUE_MA, 'jog_torque_ma', 'unsigned', 'mA', 300, 0, 5000, True, 'advanced'),
+    ParamDef(ParamId.JOG_TIMEOUT_MS, 'jog_timeout_ms', 'unsigned', 'ms', 200, 20, 2000, True, 'user'),
+    ParamDef(ParamId.STEP_CURRENT_MA, 'step_current_ma', 'unsigned', 'mA', 100, 1, 1000, True, 'user'),
+    ParamDef(ParamId.STEP_SPEED, 'step_speed', 'unsigned', 'counts/s', 16384, 1, 65536, True, 'user'),
+    ParamDef(ParamId.STEP_DURATION_MS, 'step_duration_ms', 'unsigned', 'ms', 200, 10, 2000, True, 'user'),
+    ParamDef(ParamId.STEP_TRAVEL, 'step_travel', 'unsigned', '', 65536, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.ENCODER_INVERT, 'encoder_invert', 'bool', '', 0, 0, 1, False, 'advanced'),
+    ParamDef(ParamId.ENCODER_OFFSET, 'encoder_offset', 'signed', '', 0, 0, 4294967295, False, 'advanced'),
+    ParamDef(ParamId.FILTER_ALPHA_SLOW, 'filter_alpha_slow', 'unsigned', '', 128, 0, 255, True, 'user'),
+    ParamDef(ParamId.FILTER_ALPHA_FAST, 'filter_alpha_fast', 'unsigned', '', 0, 0, 255, True, 'user'),
+    ParamDef(ParamId.FILTER_SPEED_SLOW, 'filter_speed_slow', 'unsigned', 'counts/s', 6554, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.FILTER_SPEED_FAST, 'filter_speed_fast', 'unsigned', 'counts/s', 0, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.FILTER_ORDER, 'filter_order', 'unsigned', '', 1, 1, 2, True, 'user'),
+    ParamDef(ParamId.FAULT_REACTION_SENSOR, 'fault_reaction_sensor', 'unsigned', '', 0, 0, 3, True, 'advanced'),
+    ParamDef(ParamId.FAULT_REACTION_SUPPLY, 'fault_reaction_supply', 'unsigned', '', 0, 0, 3, True, 'advanced'),
+    ParamDef(ParamId.FAULT_REACTION_MOTION, 'fault_reaction_motion', 'unsigned', '', 0, 0, 3, True, 'advanced'),
+    ParamDef(ParamId.FAULT_RAMP_TIME, 'fault_ramp_time', 'unsigned', 'ms', 200, 1, 10000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_ENABLE_VOLTAGE, 'supply_enable_voltage', 'unsigned', 'mV', 8000, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_MAX_SLOPE, 'supply_max_slope', 'unsigned', 'mV/s', 5000, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_SETTLE_TIME, 'supply_settle_time', 'unsigned', 'ms', 100, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SCOPE_ANGLE_MASK, 'scope_angle_mask', 'mask', '', 0, 0, 7, True, 'user'),
+    ParamDef(ParamId.PRODUCTION_SUPPLY_MIN, 'production_supply_min', 'unsigned', 'mV', 10000, 0, 100000, True, 'factory'),
+    ParamDef(ParamId.PRODUCTION_SUPPLY_MAX, 'production_supply_max', 'unsigned', 'mV', 50000, 0, 100000, True, 'factory'),
+    ParamDef(ParamId.PRODUCTION_TEMP_MIN, 'production_temp_min', 'unsigned', '', 0, 0, 65535, True, 'factory'),
+    ParamDef(ParamId.PRODUCTION_TEMP_MAX, 'production_temp_max', 'unsigned', '', 65535, 0, 65535, True, 'factory'),
+    ParamDef(ParamId.PRODUCTION_DEVIATION_MAX, 'production_deviation_max', 'unsigned', '', 1024, 0, 65535, True, 'factory'),
+    ParamDef(ParamId.PRODUCTION_SPREAD_MAX, 'production_spread_max', 'unsigned', '', 2048, 0, 65535, True, 'factory'),
+    ParamDef(ParamId.ENCODER_GLITCH_THRESHOLD, 'encoder_glitch_threshold', 'unsigned', '', 0, 0, 32767, True, 'advanced'),
+    ParamDef(ParamId.ENCODER_EMC_MODE, 'encoder_emc_mode', 'bool', '', 0, 0, 1, True, 'advanced'),
+    ParamDef(ParamId.RIPPLE_TEST, 'ripple_test', 'bool', '', 0, 0, 1, False, 'user'),
+    ParamDef(ParamId.SUPPLY_UNDERVOLTAGE, 'supply_undervoltage', 'unsigned', 'mV', 7000, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_OVERVOLTAGE, 'supply_overvoltage', 'unsigned', 'mV', 55000, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_MISS_TIME, 'supply_miss_time', 'unsigned', 'ms', 5, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.SUPPLY_GRACE_TIME, 'supply_grace_time', 'unsigned', 'ms', 100, 0, 100000, True, 'advanced'),
+    ParamDef(ParamId.STATUS_RATE_HZ, 'status_rate_hz', 'unsigned', 'Hz', 0, 0, 10, True, 'user'),
+    ParamDef(ParamId.DEAD_TIME_COMPENSATION, 'dead_time_compensation', 'unsigned', '', 0, 0, 4096, True, 'advanced'),
+    ParamDef(ParamId.CURRENT_BLANKING_NS, 'current_blanking_ns', 'unsigned', 'ns', 500, 0, 5000, True, 'advanced'),
+    ParamDef(ParamId.MOTOR_PRESET, 'motor_preset', 'unsigned', '', 0, 0, 5, False, 'user'),
+    ParamDef(ParamId.CURRENT_LOOP_KP, 'current_loop_kp', 'unsigned', '%', 100, 0, 10000, True, 'advanced'),
+    ParamDef(ParamId.CURRENT_LOOP_KI, 'current_loop_ki', 'unsigned', '%', 10, 0, 10000, True, 'advanced'),
+    ParamDef(ParamId.ANGLE_SOURCE, 'angle_source', 'unsigned', '', 0, 0, 1, False, 'advanced'),
+    ParamDef(ParamId.OBSERVER_BANDWIDTH, 'observer_bandwidth', 'unsigned', 'rad/s', 2000, 100, 10000, False, 'advanced'),
+    ParamDef(ParamId.SENSORLESS_MIN_EMF, 'sensorless_min_emf', 'unsigned', 'mV', 500, 10, 20000, False, 'advanced'),
+    ParamDef(ParamId.SENSORLESS_START_SPEED, 'sensorless_start_speed', 'unsigned', 'Hz', 5, 1, 100, False, 'advanced'),
+    ParamDef(ParamId.SCOPE_ENVELOPE, 'scope_envelope', 'mask', '', 0, 0, 3, True, 'user'),
+    ParamDef(ParamId.STEP_INPUT, 'step_input', 'unsigned', '', 0, 0, 2, False, 'user'),
+    ParamDef(ParamId.CAN_NODE, 'can_node', 'unsigned', '', 1, 1, 127, False, 'user'),
+    ParamDef(ParamId.CAN_BITRATE, 'can_bitrate', 'unsigned', 'kbit/s', 500, 10, 1000, False, 'user'),
+    ParamDef(ParamId.CAN_PROTOCOL, 'can_protocol', 'unsigned', '', 0, 0, 1, False, 'user'),
+    ParamDef(ParamId.FOLLOWING_ERROR_WINDOW, 'following_error_window', 'unsigned', '', 0, 0, 2147483647, True, 'user'),
+    ParamDef(ParamId.FOLLOWING_ERROR_TIMEOUT_MS, 'following_error_timeout_ms', 'unsigned', 'ms', 50, 0, 10000, True, 'user'),
+    ParamDef(ParamId.VELOCITY_WINDOW, 'velocity_window', 'unsigned', 'counts/s', 6554, 0, 655360, True, 'user'),
+    ParamDef(ParamId.VELOCITY_WINDOW_MS, 'velocity_window_ms', 'unsigned', 'ms', 20, 0, 10000, True, 'user'),
+    ParamDef(ParamId.ZERO_SPEED_THRESHOLD, 'zero_speed_threshold', 'unsigned', 'counts/s', 3277, 0, 655360, True, 'user'),
+    ParamDef(ParamId.ZERO_SPEED_MS, 'zero_speed_ms', 'unsigned', 'ms', 20, 0, 10000, True, 'user'),
+    ParamDef(ParamId.STEP_LOSS_THRESHOLD, 'step_loss_threshold', 'unsigned', '%', 0, 0, 100, True, 'user'),
+    ParamDef(ParamId.STEP_LOSS_MIN_SPEED_HZ, 'step_loss_min_speed_hz', 'unsigned', 'Hz', 5, 0, 1000, True, 'user'),
+    ParamDef(ParamId.STEP_LOSS_DEBOUNCE_MS, 'step_loss_debounce_ms', 'unsigned', 'ms', 5, 0, 1000, True, 'user'),
+)
+
+PARAM_COUNT = 107
@@ -0,0 +1,682 @@
+"""Frames of the TunePulse protocol, mirrors `tunepulse_algo::protocol`.
+
+Every frame is 8 bytes: a frame type byte followed by type specific payload (little endian).
+On byte stream links telemetry points are interleaved with frames, each point prefixed by
+`TELEMETRY_SYNC`.
+"""
+
+import struct
+import zlib
+from dataclasses import dataclass
+from enum import IntEnum
+from typing import Optional
+
+from . import codes
+
+PROTOCOL_VERSION = 1
+FRAME_SIZE = 8
+POINT_SIZE = 9
+TELEMETRY_SYNC = 0xA5
+TIMEBASE_ID = 0xFF
+# Signal id flags of the window minimum and maximum of an enveloped scope channel
+ENVELOPE_MIN = 0x40
+ENVELOPE_MAX = 0x80
+
+
+class FrameType(IntEnum):
+    PARAM_READ = 0x10
+    PARAM_WRITE = 0x11
+    PARAM_VALUE = 0x12
+    PARAM_STAGE = 0x13
+    COMMAND = 0x20
+    COMMAND_RESULT = 0x21
+    SETPOINT = 0x22
+    STATUS_READ = 0x30
+    STATUS = 0x31
+    DEVICE_INFO_READ = 0x40
+    DEVICE_INFO = 0x41
+    RESPONSE_READ = 0x50
+    RESPONSE = 0x51
+    CAPTURE_READ = 0x60
+    CAPTURE = 0x61
+    MASTER_POSITION = 0x70
+    STEP_RESULT_READ = 0x80
+    STEP_RESULT = 0x81
+    TABLE_READ = 0x90
+    TABLE_WRITE = 0x91
+    TABLE_DATA = 0x92
+    PRODUCTION_RESULT_READ = 0xA0
+    PRODUCTION_RESULT = 0xA1
+    FACTORY_READ = 0xB0
+    FACTORY_WRITE = 0xB1
+    FACTORY_DATA = 0xB2
+    CALIBRATION_REPORT_READ = 0xC0
+    CALIBRATION_REPORT = 0xC1
+    WIZARD_RESULT_READ = 0xC2
+    WIZARD_RESULT = 0xC3
+    PEAK_READ = 0xC4
+    PEAK = 0xC5
+    STATE_READ = 0xD0
+    STATE = 0xD1
+    EVENT = 0xE0
+    ODOMETRY = 0xE1
+    STATUS_REPORT = 0xE2
+    ELECTRICAL = 0xE3
+    TIME_BEACON = 0xF0
+    TIME_SYNC = 0xF1
+
+
+class Command(IntEnum):
+    CALIBRATE = 1
+    QUICK_RECALIBRATE = 2
+    START_SEQUENCE = 3
+    STOP_SEQUENCE = 4
+    ENABLE = 5
+    DISABLE = 6
+    APPLY_STAGED = 7
+    DISCARD_STAGED = 8
+    START_EXCITATION = 9
+    STOP_EXCITATION = 10
+    START_FREQUENCY_RESPONSE = 11
+    SAVE_PARAMS = 12
+    ARM_CAPTURE = 13
+    START_FOLLOW = 14
+    STOP_FOLLOW = 15
+    CLEAR_COLLISION = 16
+    JOG_FORWARD = 17
+    JOG_BACKWARD = 18
+    JOG_STOP = 19
+    START_STEP_TEST = 20
+    EXPORT_TABLE = 21
+    IMPORT_TABLE = 22
+    IMPORT_GOLDEN_TABLE = 23
+    START_PRODUCTION_TEST = 24
+    WRITE_FACTORY_DATA = 25
+    UNLOCK = 26
+    LOCK = 27
+    RECONFIGURE_MOTOR = 28
+    START_WIZARD = 29
+    ABORT_WIZARD = 30
+    CLEAR_PEAKS = 31
+
+
+class SetpointMode(IntEnum):
+    CURRENT = 0  # mA
+    VELOCITY = 1  # counts/s
+    POSITION = 2  # i16 rotations + u16 angle
+    VOLTAGE_ANGLE = 3  # angle (low half) and amplitude (high half)
+
+
+class CanFunction(IntEnum):
+    """Function code of a CAN identifier, see `tunepulse_algo::protocol::can`"""
+
+    EVENT = 1
+    REQUEST = 2
+    REPLY = 3
+    TELEMETRY = 4
+    REPORT = 5
+
+
+def can_id(function, node):
+    """11 bit CAN identifier of a function of a node (1..127)"""
+    return int(function) << 7 | node & 0x7F
+
+
+class MotorType(IntEnum):
+    DC = 0xFFFF
+    BLDC = 3
+    STEP = 4
+
+
+class PhasePattern(IntEnum):
+    ABCD = 0b11100100
+    ACDB = 0b01111000
+    ADBC = 0b10011100
+    DCAB = 0b01001011
+
+
+class ScopeSignal(IntEnum):
+    NONE = 0
+    POSITION = 1
+    SPEED = 2
+    TARGET = 3
+    POSITION_ERROR = 4
+    ANGLE_EL = 5
+    CURRENT_COMMAND = 6
+    FRICTION_CURRENT = 7
+    DAMPING_CURRENT = 8
+    LOAD_TORQUE = 9
+    LOAD_ANGLE = 10
+    SUPPLY_VOLTAGE = 11
+    DUTY_A = 12
+    DUTY_B = 13
+    DUTY_C = 14
+    DUTY_D = 15
+    EXCITATION = 16
+    OVERFLOWS = 17
+    SAMPLE_OFFSET = 18
+    ANGLE_RAW = 19
+    ANGLE_FILTERED = 20
+    ANGLE_EL_CORRECTED = 21
+    ENCODER_GLITCHES = 22
+    ENCODER_ERRORS = 23
+    CURRENT_D = 24
+    CURRENT_Q = 25
+
+
+class ReplyError(Exception):
+    """Request rejected by the drive"""
+
+
+_RESULTS = {c.code: c.description for c in codes.REPLY_RESULTS}
+
+# Keys unlocking the access levels, see `tunepulse_params::AccessLevel`
+ACCESS_KEYS = {"advanced": 0x41445643, "factory": 0x46414354}
+
+
+def check_result(code):
+    if code != 0:
+        raise ReplyError(_RESULTS.get(code, f"error code {code}"))
+
+
+def _frame(kind, *payload):
+    frame = bytes([kind, *payload])
+    return frame + bytes(FRAME_SIZE - len(frame))
+
+
+def param_read(param_id):
+    return _frame(FrameType.PARAM_READ, 0, *struct.pack("<H", param_id))
+
+
+def param_write(param_id, value):
+    return _frame(FrameType.PARAM_WRITE, 0, *struct.pack("<HI", param_id, value & 0xFFFFFFFF))
+
+
+def param_stage(param_id, value):
+    return _frame(FrameType.PARAM_STAGE, 0, *struct.pack("<HI", param_id, value & 0xFFFFFFFF))
+
+
+def command(cmd, arg=0):
+    return _frame(FrameType.COMMAND, cmd, 0, 0, *struct.pack("<I", arg))
+
+
+def status_read():
+    return _frame(FrameType.STATUS_READ)
+
+
+def device_info_read(page):
+    return _frame(FrameType.DEVICE_INFO_READ, page)
+
+
+def response_read(index, page):
+    return _frame(FrameType.RESPONSE_READ, index, page)
+
+
+def capture_read():
+    return _frame(FrameType.CAPTURE_READ)
+
+
+def master_position(position):
+    return _frame(FrameType.MASTER_POSITION, 0, 0, 0, *struct.pack("<i", position))
+
+
+def setpoint(mode, value):
+    return _frame(FrameType.SETPOINT, mode, 0, 0, *struct.pack("<I", value & 0xFFFFFFFF))
+
+
+def step_result_read(test):
+    return _frame(FrameType.STEP_RESULT_READ, test)
+
+
+def production_result_read(metric):
+    return _frame(FrameType.PRODUCTION_RESULT_READ, metric)
+
+
+def calibration_report_read(metric):
+    return _frame(FrameType.CALIBRATION_REPORT_READ, metric)
+
+
+def wizard_result_read(result):
+    return _frame(FrameType.WIZARD_RESULT_READ, result)
+
+
+def peak_read(value, clear=False):
+    return _frame(FrameType.PEAK_READ, value, 1 if clear else 0)
+
+
+def state_read():
+    return _frame(FrameType.STATE_READ)
+
+
+def time_beacon(seq, host_ms):
+    return _frame(FrameType.TIME_BEACON, seq & 0xFF, 0, 0, *struct.pack("<I", host_ms & 0xFFFFFFFF))
+
+
+def time_sync(frame):
+    """Sequence number and tick counter of a TIME_SYNC reply"""
+    return frame[1], struct.unpack_from("<I", frame, 4)[0]
+
+
+def factory_read(page):
+    return _frame(FrameType.FACTORY_READ, page)
+
+
+def factory_write(page, data):
+    return _frame(FrameType.FACTORY_WRITE, page, *bytes(data))
+
+
+def table_read(offset):
+    return _frame(FrameType.TABLE_READ, 0, *struct.pack("<H", offset))
+
+
+def table_write(offset, data):
+    return _frame(FrameType.TABLE_WRITE, 0, *struct.pack("<H4s", offset, bytes(data)))
+
+
+def param_value(frame):
+    """Value of a PARAM_VALUE reply"""
+    check_result(frame[1])
+    return struct.unpack_from("<I", frame, 4)[0]
+
+
+TABLE_CHUNK_SIZE = 4  # Bytes carried by a table frame
+TABLE_HEADER_SIZE = 12  # See `tunepulse_algo::protocol::table_transfer`
+TABLE_IMAGE_MAGIC = 0x4354  # "TC"
+
+
+def table_data(frame):
+    """Chunk carried by a TABLE_DATA reply"""
+    check_result(frame[1])
+    return bytes(frame[4:8])
+
+
+def table_payload_len(header):
+    """Payload length stored in the header of a table image"""
+    return struct.unpack_from("<I", header, 4)[0]
+
+
+def check_table_image(image):
+    """Raises ValueError if magic, length or CRC of a table image are wrong"""
+    if len(image) < TABLE_HEADER_SIZE or struct.unpack_from("<H", image)[0] != TABLE_IMAGE_MAGIC:
+        raise ValueError("not a table image")
+    payload = image[TABLE_HEADER_SIZE:]
+    if len(payload) != table_payload_len(image):
+        raise ValueError("table image truncated")
+    if zlib.crc32(payload) != struct.unpack_from("<I", image, 8)[0]:
+        raise ValueError("table image CRC mismatch")
+
+
+STATUS_NAMES = {c.code: c.name for c in codes.DRIVER_STATUS}
+STATUS_UNSAVED = 1 << 7  # Bit of the status byte, parameters changed since they were saved
+STATUS_EXCITATION = 1 << 4
+STATUS_MEASURING = 1 << 5
+STATUS_FOLLOWING = 1 << 6
+STATUS_COLLISION = 1 << 7
+
+
+@dataclass(frozen=True)
+class Status:
+    status: int
+    fault: int
+    flags: int
+    position: int  # i16 rotations + u16 angle
+    unsaved: bool = False  # Parameters changed since they were saved, lost on reset
+
+    @classmethod
+    def decode(cls, frame):
+        position = struct.unpack_from("<i", frame, 4)[0]
+        status = frame[1] & ~STATUS_UNSAVED
+        return cls(status, frame[2], frame[3], position, bool(frame[1] & STATUS_UNSAVED))
+
+    @property
+    def name(self):
+        return STATUS_NAMES.get(self.status, "unknown")
+
+    @property
+    def fault_name(self):
+        return codes.name(codes.FAULT_CODES, self.fault)
+
+
+@dataclass(frozen=True)
+class StatusReport:
+    """Report of the slow status channel, enabled by the status_rate_hz parameter"""
+
+    status: int
+    fault: int
+    supply_mv: int
+    temperature: int  # Temperature sensor reading (raw ADC)
+    encoder_rejected: int  # Counters since power-up, saturated at 65535
+    encoder_retries: int
+    overflows: int
+    unsaved: bool = False  # Parameters changed since they were saved, lost on reset
+
+    @classmethod
+    def decode(cls, health, statistics):
+        """Decodes the health (page 0) and statistics (page 1) frames of one report"""
+        supply, temperature = struct.unpack_from("<HH", health, 4)
+        status = health[2] & ~STATUS_UNSAVED
+        return cls(
+            status,
+            health[3],
+            supply,
+            temperature,
+            *struct.unpack_from("<HHH", statistics, 2),
+            unsaved=bool(health[2] & STATUS_UNSAVED),
+        )
+
+    @property
+    def name(self):
+        return STATUS_NAMES.get(self.status, "unknown")
+
+    @property
+    def fault_name(self):
+        return codes.name(codes.FAULT_CODES, self.fault)
+
+
+STATE_ENERGIZED = 1 << 0  # State flag, the bridge drives current through the windings
+STATE_AT_VELOCITY = 1 << 1  # State flag, the speed settled within the velocity window
+STATE_ZERO_SPEED = 1 << 2  # State flag, the speed settled below the zero speed threshold
+
+# Calibration sub-stages by number, see `tunepulse_algo::motor_driver::CalibrationStage`
+CALIBRATION_STAGES = (
+    "startup",
+    "settling",
+    "motion_test",
+    "forward_pass",
+    "backward_pass",
+    "check",
+    "torque_ripple",
+    "trim",
+)
+
+
+@dataclass(frozen=True)
+class State:
+    stage: Optional[str]  # Calibration sub-stage, None unless calibrating
+    progress: int  # Calibration progress (percent)
+    energized: bool  # Bridge drives current through the windings
+    at_velocity: bool  # Speed settled within the velocity window of the velocity setpoint
+    zero_speed: bool  # Speed settled below the zero speed threshold
+
+    @classmethod
+    def decode(cls, frame):
+        stage = None
+        if frame[2] != 0xFF:
+            stage = CALIBRATION_STAGES[frame[2]] if frame[2] < len(CALIBRATION_STAGES) else "unknown"
+        return cls(
+            stage,
+            frame[3],
+            bool(frame[4] & STATE_ENERGIZED),
+            bool(frame[4] & STATE_AT_VELOCITY),
+            bool(frame[4] & STATE_ZERO_SPEED),
+        )
+
+
+@dataclass(frozen=True)
+class Capture:
+    position: Optional[int]  # None until an edge was captured
+    armed: bool
+    count: int
+
+    @classmethod
+    def decode(cls, frame):
+        count, position = struct.unpack_from("<Hi", frame, 2)
+        return cls(position if frame[1] & 1 else None, bool(frame[1] & 2), count)
+
+
+STEP_TESTS = ("current", "velocity")  # Step response tests in the order they are run
+
+
+@dataclass(frozen=True)
+class StepResult:
+    """Result of a step response test"""
+
+    rise_ms: Optional[float]  # 10 % to 90 % of the step, None if 90 % was never reached
+    overshoot_pct: float  # Peak above the step
+    error_pct: float  # Step minus steady-state level
+
+    @classmethod
+    def decode(cls, frame):
+        """Decodes the reply, None if the test didn't finish"""
+        if frame[1] == 0xFF:
+            return None
+        rise, overshoot, error = struct.unpack_from("<HHh", frame, 2)
+        return cls(None if rise == 0xFFFF else rise / 10, overshoot / 10, error / 10)
+
+
+# Production test metrics in report order
+PRODUCTION_METRICS = tuple(c.name for c in codes.PRODUCTION_METRICS)
+
+
+@dataclass(frozen=True)
+class ProductionResult:
+    """Result of a production test metric"""
+
+    value: Optional[int]  # None if the metric wasn't measured
+    passed: bool  # Value within the limits
+    unit_passed: bool  # All metrics of the unit passed
+
+    @classmethod
+    def decode(cls, frame):
+        """Decodes the reply, None if there's no report"""
+        if frame[1] == 0xFF:
+            return None
+        value = struct.unpack_from("<i", frame, 4)[0]
+        return cls(value if frame[2] & 2 else None, bool(frame[2] & 1), bool(frame[2] & 4))
+
+
+# Calibration report metrics in report order, torque ripple in per mille of the speed
+CALIBRATION_METRICS = tuple(c.name for c in codes.CALIBRATION_METRICS)
+
+# Flags of the "load_flags" calibration metric
+LOAD_HYSTERESIS = 1 << 0  # Friction, mean hysteresis between the passes too large
+LOAD_ASYMMETRY = 1 << 1  # Position dependent load, hysteresis varied along the turn
+LOAD_SETTLING = 1 << 2  # Yielding load, rotor kept moving while sampled
+
+
+def calibration_metric(frame):
+    """Decodes a calibration report reply, None if the metric wasn't measured"""
+    if frame[1] == 0xFF or not frame[2] & 1:
+        return None
+    return struct.unpack_from("<i", frame, 4)[0]
+
+
+class StepOutcome(IntEnum):
+    """Outcome of a setup wizard step, see codes.STEP_OUTCOMES"""
+
+    PENDING = 0
+    RUNNING = 1
+    PASSED = 2
+    FAILED = 3
+    SKIPPED = 4
+    ABORTED = 5
+
+
+# Setup wizard steps in run order
+WIZARD_STEPS = tuple(c.name for c in codes.WIZARD_STEPS)
+
+# Setup wizard results in report order, units in codes.WIZARD_RESULTS
+WIZARD_RESULTS = tuple(c.name for c in codes.WIZARD_RESULTS)
+
+
+@dataclass(frozen=True)
+class WizardResult:
+    """Result of the setup wizard and the outcome of the step measuring it"""
+
+    outcome: StepOutcome
+    value: Optional[int]  # None if the result wasn't measured
+
+    @classmethod
+    def decode(cls, frame):
+        """Decodes the reply, None if the result doesn't exist"""
+        if frame[1] == 0xFF:
+            return None
+        value = struct.unpack_from("<i", frame, 4)[0]
+        return cls(StepOutcome(frame[2]), value if frame[3] & 1 else None)
+
+
+# Peak-hold registers in register order, units in codes.PEAK_VALUES
+PEAK_VALUES = tuple(c.name for c in codes.PEAK_VALUES)
+
+
+def peak_value(frame):
+    """Decodes a peak reply, None if the register wasn't sampled since it was cleared"""
+    if frame[1] == 0xFF or not frame[2] & 1:
+        return None
+    return struct.unpack_from("<I", frame, 4)[0]
+
+
+@dataclass(frozen=True)
+class Odometry:
+    """Periodic position and velocity sample"""
+
+    seq: int  # 8-bit sequence number, sample time is seq / odometry rate
+    position: int  # i16 rotations + u16 angle
+    velocity: int  # 1/256 rev/s
+
+    @classmethod
+    def decode(cls, frame):
+        return cls(frame[1], *struct.unpack_from("<ih", frame, 2))
+
+    @property
+    def turns(self):
+        return self.position / 65536
+
+    @property
+    def turns_per_second(self):
+        return self.velocity / 256
+
+
+@dataclass(frozen=True)
+class Electrical:
+    """Periodic current and supply sample, taken with the odometry sample of the same seq"""
+
+    seq: int
+    current_q: int  # mA
+    current_d: int  # mA
+    supply_mv: int
+
+    @classmethod
+    def decode(cls, frame):
+        return cls(frame[1], *struct.unpack_from("<hhH", frame, 2))
+
+
+DEVICE_INFO_PAGES = 4
+BOARD_NAMES = {1: "CLN17"}
+
+
+@dataclass(frozen=True)
+class DeviceInfo:
+    version: tuple
+    board: int
+    calibrated: bool
+    git_dirty: bool
+    protocol: int
+    git_hash: int
+    param_count: int
+    uid: bytes
+
+    @classmethod
+    def decode(cls, pages):
+        """Decodes the pages read in order"""
+        if len(pages) < DEVICE_INFO_PAGES or any(p[1] != n for n, p in enumerate(pages)):
+            raise ReplyError("incomplete device information")
+        return cls(
+            version=tuple(pages[0][2:5]),
+            board=pages[0][5],
+            calibrated=bool(pages[0][6] & 1),
+            git_dirty=bool(pages[0][6] & 2),
+            protocol=pages[0][7],
+            git_hash=struct.unpack_from("<I", pages[1], 2)[0],
+            param_count=struct.unpack_from("<H", pages[1], 6)[0],
+            uid=bytes(pages[2][2:]) + bytes(pages[3][2:]),
+        )
+
+    @property
+    def board_name(self):
+        return BOARD_NAMES.get(self.board, "unknown")
+
+
+FACTORY_PAGES = 3
+FACTORY_INVALID_PAGE = 0xFF  # Page reported if the unit has no factory data or staging failed
+
+
+@dataclass(frozen=True)
+class FactoryData:
+    """Per-unit data written once during production"""
+
+    serial: int
+    hardware_rev: int
+    date: int  # Calibration date (unix time)
+    supply_trim: int = 0  # Supply voltage measurement gain correction (0.01 %)
+    temp_offset: int = 0  # Temperature sensor offset (raw ADC)
+
+    @classmethod
+    def decode(cls, pages):
+        """Decodes the pages read in order, None if the unit has no factory data"""
+        if len(pages) < FACTORY_PAGES or any(p[1] != n for n, p in enumerate(pages)):
+            return None
+        serial, hardware_rev = struct.unpack_from("<IH", pages[0], 2)
+        date, supply_trim = struct.unpack_from("<Ih", pages[1], 2)
+        temp_offset = struct.unpack_from("<h", pages[2], 2)[0]
+        return cls(serial, hardware_rev, date, supply_trim, temp_offset)
+
+    def pages(self):
+        """Payloads of all pages in order"""
+        return (
+            struct.pack("<IH", self.serial, self.hardware_rev),
+            struct.pack("<Ih", self.date, self.supply_trim),
+            struct.pack("<h4x", self.temp_offset),
+        )
+
+
+@dataclass(frozen=True)
+class Point:
+    """Telemetry point"""
+
+    id: int
+    tick: int
+    value: float
+
+    @classmethod
+    def decode(cls, data):
+        return cls(*struct.unpack("<BIf", data))
+
+    @property
+    def signal(self):
+        """Signal id without the envelope flags"""
+        if self.id == TIMEBASE_ID:
+            return self.id
+        return self.id & ~(ENVELOPE_MIN | ENVELOPE_MAX)
+
+
+class Deframer:
+    """Splits a byte stream into protocol frames and telemetry points"""
+
+    def __init__(self):
+        self._raw = bytearray()
+
+    def feed(self, data):
+        """Returns the frames and points completed by `data`"""
+        self._raw += data
+        frames, points = [], []
+        read = 0
+        raw = self._raw
+        while read < len(raw):
+            if raw[read] == TELEMETRY_SYNC:
+                if len(raw) - read < 1 + POINT_SIZE:
+                    break
+                points.append(Point.decode(bytes(raw[read + 1 : read + 1 + POINT_SIZE])))
+                read += 1 + POINT_SIZE
+            elif raw[read] in _FRAME_TYPES:
+                if len(raw) - read < FRAME_SIZE:
+                    break
+                frames.append(bytes(raw[read : read + FRAME_SIZE]))
+                read += FRAME_SIZE
+            else:
+                read += 1  # Out of sync
+        del raw[:read]
+        return frames, points
+
+
+_FRAME_TYPES = frozenset(int(t) for t in FrameType)
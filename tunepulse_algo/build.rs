@@ -0,0 +1,29 @@
+// Embeds the git revision of the source tree into the firmware build information.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use std::process::Command;
+
+fn main() {
+    // Short hash of the checked out commit, zero if built outside of a git tree
+    let hash = git(&["rev-parse", "--short=8", "HEAD"]).unwrap_or_else(|| "00000000".into());
+    // Uncommitted changes make the hash ambiguous, so they are reported separately
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .map(|status| !status.is_empty())
+        .unwrap_or(false);
+
+    println!("cargo:rustc-env=TUNEPULSE_GIT_HASH={hash}");
+    println!("cargo:rustc-env=TUNEPULSE_GIT_DIRTY={}", dirty as u8);
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    println!("cargo:rerun-if-changed=../.git/index");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().into())
+}
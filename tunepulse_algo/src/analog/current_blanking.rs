@@ -0,0 +1,92 @@
+// Implements the blanking of current samples taken close to a switching edge of the bridge.
+
+// Key Features:
+// - Blanking window around the sample instant, configured in nanoseconds.
+// - Switching edges derived from the phase duties of the center aligned PWM.
+// - Blanked samples are replaced by the last clean one by the board and counted here.
+
+// Detailed Operation:
+// A half bridge switching next to the current sample couples its ringing into the shunt
+// amplifiers, the sample then shows a spike instead of the coil current. With center aligned
+// PWM (output active around the counter valley) a phase with duty d switches (1 - d) / 2 of the
+// period before and after the counter peak, only phases at zero duty don't switch at all.
+// The board samples the currents around the peak and knows the instant relative to it as well
+// as the duties active during the sample, so it runs `is_clear()` on every sample and holds the
+// last clean one if any edge lies within the window. The controller owns the configuration:
+// the window is converted once from nanoseconds to i1.15 of the PWM period and handed to the
+// board every tick, the board reports back how many samples it blanked.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Full PWM period in the units of the window and the sample instant (i1.15)
+const PERIOD: i32 = 1 << 15;
+
+/// Blanking configuration and statistics.
+pub struct CurrentBlanking {
+    frequency: u32, // PWM periods per second
+    window: i16,    // Distance of an edge to the sample blanking it (i1.15 of the period)
+    blanked: u32,   // Samples blanked since power-up (wrapping)
+}
+
+impl CurrentBlanking {
+    /// Creates the blanking configuration
+    ///
+    /// # Arguments
+    /// * `frequency` - PWM periods per second
+    /// * `window_ns` - Samples within this time of a switching edge are blanked (0 - off)
+    pub fn new(frequency: u16, window_ns: u32) -> Self {
+        let mut blanking = Self {
+            frequency: frequency as u32,
+            window: 0,
+            blanked: 0,
+        };
+        blanking.configure(window_ns);
+        blanking
+    }
+
+    /// Changes the blanking window
+    pub fn configure(&mut self, window_ns: u32) {
+        let window = window_ns as u64 * self.frequency as u64 * PERIOD as u64 / 1_000_000_000;
+        self.window = window.min(PERIOD as u64 / 2) as i16;
+    }
+
+    /// Blanking window handed to the board (i1.15 of the PWM period, 0 - off)
+    #[inline(always)]
+    pub fn window(&self) -> i16 {
+        self.window
+    }
+
+    /// Counts the samples blanked by the board since the last call
+    pub fn count(&mut self, blanked: u32) {
+        self.blanked = self.blanked.wrapping_add(blanked);
+    }
+
+    /// Samples blanked since power-up (wrapping)
+    pub fn blanked(&self) -> u32 {
+        self.blanked
+    }
+}
+
+/// Returns true if no phase switched within the window of the sample
+///
+/// # Arguments
+/// * `window` - Blanking window (i1.15 of the PWM period, see `CurrentBlanking::window()`)
+/// * `pwm` - Phase duties active during the sample (i1.15, negative values as 0)
+/// * `sample_phase` - Sample instant relative to the counter peak (i1.15 of the period)
+#[inline(always)]
+pub fn is_clear(window: i16, pwm: &[i16; 4], sample_phase: i16) -> bool {
+    if window <= 0 {
+        return true;
+    }
+    let (window, phase) = (window as i32, sample_phase as i32);
+    pwm.iter().all(|&duty| {
+        let duty = duty.max(0) as i32;
+        if duty == 0 {
+            return true; // Zero duty doesn't switch, full scale still leaves a notch
+        }
+        // Edges lie symmetric to the counter peak
+        let edge = (PERIOD - duty) / 2;
+        (edge - phase).abs() >= window && (-edge - phase).abs() >= window
+    })
+}
@@ -0,0 +1,101 @@
+// Implements the conversion of the current ADC readings to probe currents in milliamps.
+
+// Key Features:
+// - Zero offset of every probe averaged while the bridge is still off at power-up.
+// - Board specific scale in microamps per ADC count, unknown scales keep the sense unused.
+// - Converted currents only once the offsets are known, so the current loop never starts
+//   on raw readings.
+
+// Detailed Operation:
+// The shunt amplifiers are biased to the middle of the ADC range, a reading equals the probe
+// current times the scale plus this bias. The bias of every probe differs slightly, so it is
+// measured: during the supply pre-charge the bridge is off and no current flows, the owner
+// passes the readings of these ticks until `ZERO_SAMPLES` were averaged. Afterwards every
+// reading is converted as (reading - zero) * scale. The board sets the scale, boards without
+// a known scale (0) never report currents and the driver keeps regulating the voltage only.
+// Probes beyond `PROBES` read zero.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of probes read by the board
+pub const PROBES: usize = 2;
+
+/// Readings averaged for the zero offset of every probe
+const ZERO_SAMPLES: u32 = 256;
+
+/// Current sense calibration of the board.
+pub struct CurrentSense {
+    scale: u32,              // Probe current per ADC count (uA), 0 - unknown
+    zero_sum: [u32; PROBES], // Sum of the readings without current
+    zero_count: u32,         // Readings summed so far
+    zero: [i32; PROBES],     // Reading without current (ADC counts)
+}
+
+impl CurrentSense {
+    /// Creates a sense without scale and offsets
+    pub const fn new() -> Self {
+        Self {
+            scale: 0,
+            zero_sum: [0; PROBES],
+            zero_count: 0,
+            zero: [0; PROBES],
+        }
+    }
+
+    /// Sets the board scale.
+    ///
+    /// # Arguments
+    /// * `ua_per_count` - Probe current per current ADC count (uA), 0 - unknown
+    #[inline(always)]
+    pub fn set_scale(&mut self, ua_per_count: u32) {
+        self.scale = ua_per_count;
+    }
+
+    /// Board scale (uA per ADC count), 0 if unknown
+    #[inline(always)]
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Averages a reading taken without current, ignored once the offsets are known
+    pub fn sample_zero(&mut self, adc: &[u16; 4]) {
+        if self.zero_count >= ZERO_SAMPLES {
+            return;
+        }
+        for (sum, &reading) in self.zero_sum.iter_mut().zip(adc.iter()) {
+            *sum += reading as u32;
+        }
+        self.zero_count += 1;
+        if self.zero_count == ZERO_SAMPLES {
+            for (zero, &sum) in self.zero.iter_mut().zip(self.zero_sum.iter()) {
+                *zero = (sum / ZERO_SAMPLES) as i32;
+            }
+        }
+    }
+
+    /// Returns true once scale and offsets are known
+    #[inline(always)]
+    pub fn is_ready(&self) -> bool {
+        self.scale != 0 && self.zero_count >= ZERO_SAMPLES
+    }
+
+    /// Probe currents (mA) of the readings, `None` until the sense is ready
+    pub fn currents(&self, adc: &[u16; 4]) -> Option<[i16; 4]> {
+        if !self.is_ready() {
+            return None;
+        }
+        let mut currents = [0; 4];
+        for ((current, &reading), &zero) in currents.iter_mut().zip(adc.iter()).zip(&self.zero) {
+            let ua = (reading as i32 - zero) as i64 * self.scale as i64;
+            *current = (ua / 1000).clamp(i16::MIN as i64, i16::MAX as i64) as i16;
+        }
+        Some(currents)
+    }
+}
+
+impl Default for CurrentSense {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,4 +1,8 @@
 pub mod adc_correction;
 pub mod supply_voltage;
+pub mod supply_startup;
+pub mod supply_monitor;
 use crate::math_integer::normalization::*;
 use crate::math_integer::filters::lpf;
+pub mod current_blanking;
+pub mod current_sense;
@@ -0,0 +1,105 @@
+// Implements the continuous supply monitoring (UVLO/OVLO) once the bridge is enabled.
+
+// Key Features:
+// - Undervoltage and overvoltage limits checked on every tick.
+// - Consecutive out-of-range samples ("misses") debounce the decision against ADC noise.
+// - Configurable grace period after the power-up, the supply settles under the first load.
+
+// Detailed Operation:
+// The staged power-up (`SupplyStartup`) only enables the bridge once the supply is stable, from
+// then on this monitor takes over. After the grace period every filtered supply sample is
+// compared against both limits, a sample out of range counts as a miss, a sample in range
+// resets the count. The supply is reported out of range once the misses lasted for the miss
+// time without interruption, a single spike (switching noise, a load step) never trips it.
+// The result isn't latched, the caller decides on the reaction. A limit of 0 disables its check.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Outcome of a supply check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupplyCheck {
+    /// Supply within the limits or still in the grace period
+    Ok,
+    /// Supply stayed below the undervoltage limit for the miss time
+    Undervoltage,
+    /// Supply stayed above the overvoltage limit for the miss time
+    Overvoltage,
+}
+
+/// Watches the supply voltage against the operating limits.
+pub struct SupplyMonitor {
+    frequency: u32,       // Number of ticks per second
+    undervoltage_mv: i32, // Lowest allowed supply voltage (0 - off)
+    overvoltage_mv: i32,  // Highest allowed supply voltage (0 - off)
+    miss_limit: u32,      // Consecutive misses tripping the check (ticks)
+    grace: u32,           // Ticks left until the monitoring starts
+    misses: u32,          // Consecutive samples out of range
+}
+
+impl SupplyMonitor {
+    /// Creates a monitor waiting for `start`.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `undervoltage_mv` - Lowest allowed supply voltage (0 - off)
+    /// * `overvoltage_mv` - Highest allowed supply voltage (0 - off)
+    /// * `miss_ms` - Time the supply has to stay out of range
+    pub fn new(frequency: u16, undervoltage_mv: u32, overvoltage_mv: u32, miss_ms: u32) -> Self {
+        let mut monitor = Self {
+            frequency: frequency as u32,
+            undervoltage_mv: 0,
+            overvoltage_mv: 0,
+            miss_limit: 1,
+            grace: 0,
+            misses: 0,
+        };
+        monitor.configure(undervoltage_mv, overvoltage_mv, miss_ms);
+        monitor
+    }
+
+    /// Changes the limits, the miss count restarts
+    pub fn configure(&mut self, undervoltage_mv: u32, overvoltage_mv: u32, miss_ms: u32) {
+        self.undervoltage_mv = undervoltage_mv.min(i32::MAX as u32) as i32;
+        self.overvoltage_mv = overvoltage_mv.min(i32::MAX as u32) as i32;
+        self.miss_limit = self.ms_to_ticks(miss_ms).max(1);
+        self.misses = 0;
+    }
+
+    /// Starts the monitoring after the grace period, call once the bridge is enabled
+    pub fn start(&mut self, grace_ms: u32) {
+        self.grace = self.ms_to_ticks(grace_ms);
+        self.misses = 0;
+    }
+
+    /// Math call, checks one supply sample
+    ///
+    /// # Arguments
+    /// * `voltage_mv` - Filtered supply voltage
+    pub fn tick(&mut self, voltage_mv: i32) -> SupplyCheck {
+        if self.grace > 0 {
+            self.grace -= 1;
+            if self.grace == 0 {
+                defmt::info!("SUPPLY: Monitoring started at {}mV", voltage_mv);
+            }
+            return SupplyCheck::Ok;
+        }
+        let check = if self.undervoltage_mv > 0 && voltage_mv < self.undervoltage_mv {
+            SupplyCheck::Undervoltage
+        } else if self.overvoltage_mv > 0 && voltage_mv > self.overvoltage_mv {
+            SupplyCheck::Overvoltage
+        } else {
+            self.misses = 0;
+            return SupplyCheck::Ok;
+        };
+        self.misses = (self.misses + 1).min(self.miss_limit);
+        if self.misses < self.miss_limit {
+            return SupplyCheck::Ok;
+        }
+        check
+    }
+
+    fn ms_to_ticks(&self, ms: u32) -> u32 {
+        (self.frequency as u64 * ms as u64 / 1000) as u32
+    }
+}
@@ -0,0 +1,94 @@
+// Implements the staged power-up, the bridge is only enabled once the supply finished charging.
+
+// Key Features:
+// - Supply has to stay above a threshold for a configurable time.
+// - Voltage slope (dV/dt) has to be low, a charging bus capacitor restarts the wait.
+// - Latches once ready, later supply faults are handled by the supply monitoring.
+
+// Detailed Operation:
+// On power-up the bus capacitors charge through the pre-charge path and the voltage ramps for
+// tens of milliseconds. Switching the bridge during this transient draws inrush current
+// through the still charging supply and calibrates against a voltage that is still moving.
+// Every slope window (10 ms) the filtered voltage is compared to the one of the previous
+// window. A window below the threshold or with a slope above the limit restarts the settle
+// time, the supply is ready once the settle time passed without restart.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Length of the window the slope is measured over (ms)
+const SLOPE_WINDOW_MS: u32 = 10;
+
+/// Waits for a stable supply before the bridge is enabled.
+pub struct SupplyStartup {
+    window: u32,       // Length of the slope window (ticks)
+    threshold_mv: i32, // Minimum supply voltage
+    max_slope: i32,    // Maximum voltage change per window (mV)
+    settle_ms: u32,    // Time the supply has to stay stable
+    ticks: u32,        // Ticks spent in the current window
+    stable_ms: u32,    // Time the supply stayed stable so far
+    window_mv: i32,    // Voltage at the start of the window
+    ready: bool,       // Supply stable, bridge may be enabled
+}
+
+impl SupplyStartup {
+    /// Creates a waiting power-up stage.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `threshold_mv` - Minimum supply voltage
+    /// * `max_slope` - Maximum supply voltage slope (mV/s)
+    /// * `settle_ms` - Time the supply has to stay stable
+    pub fn new(frequency: u16, threshold_mv: u32, max_slope: u32, settle_ms: u32) -> Self {
+        let mut startup = Self {
+            window: (frequency as u32 * SLOPE_WINDOW_MS / 1000).max(1),
+            threshold_mv: 0,
+            max_slope: 0,
+            settle_ms: 0,
+            ticks: 0,
+            stable_ms: 0,
+            window_mv: 0,
+            ready: false,
+        };
+        startup.configure(threshold_mv, max_slope, settle_ms);
+        startup
+    }
+
+    /// Changes the conditions, ignored once the supply is ready
+    pub fn configure(&mut self, threshold_mv: u32, max_slope: u32, settle_ms: u32) {
+        self.threshold_mv = threshold_mv.min(i32::MAX as u32) as i32;
+        self.max_slope = (max_slope as u64 * SLOPE_WINDOW_MS as u64 / 1000) as i32;
+        self.settle_ms = settle_ms;
+    }
+
+    /// Math call, returns true once the supply is ready
+    ///
+    /// # Arguments
+    /// * `voltage_mv` - Filtered supply voltage
+    pub fn tick(&mut self, voltage_mv: i32) -> bool {
+        if self.ready {
+            return true;
+        }
+        self.ticks += 1;
+        if self.ticks < self.window {
+            return false;
+        }
+        self.ticks = 0;
+
+        let slope = (voltage_mv - self.window_mv).abs();
+        self.window_mv = voltage_mv;
+        if voltage_mv < self.threshold_mv || slope > self.max_slope {
+            self.stable_ms = 0; // Still charging, wait for the full settle time again
+            return false;
+        }
+        self.stable_ms += SLOPE_WINDOW_MS;
+        self.ready = self.stable_ms >= self.settle_ms;
+        self.ready
+    }
+
+    /// Returns true once the supply is stable
+    #[inline(always)]
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+}
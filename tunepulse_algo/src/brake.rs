@@ -0,0 +1,124 @@
+// Implements holding brake control with timing interlocks.
+
+// Key Features:
+// - Release delay: motion is blocked until the brake has physically opened.
+// - Engage delay: torque is held after disable until the brake has physically closed.
+// - Fault interlock: the brake engages immediately on any fault.
+
+// Detailed Operation:
+// Electromechanical brakes need tens of milliseconds to open or close. On enable the drive
+// produces holding torque and releases the brake, but motion is only allowed once the release
+// delay has passed. On disable the brake is engaged first while the drive keeps holding torque
+// for the engage delay, so a vertical load never drops. A fault bypasses both delays and engages
+// the brake at once, the fault handling is responsible for removing torque.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrakeState {
+    /// Brake closed, no torque
+    Engaged,
+    /// Brake opening, torque held, motion blocked
+    Releasing,
+    /// Brake open, motion allowed
+    Released,
+    /// Brake closing, torque held, motion blocked
+    Engaging,
+}
+
+/// Holding brake controller.
+pub struct BrakeControl {
+    frequency: u16,     // Update frequency (ticks per second)
+    release_delay: u32, // Ticks between brake release and motion
+    engage_delay: u32,  // Ticks between brake engage and torque removal
+    counter: u32,       // Ticks spent in current state
+    state: BrakeState,  // Current brake state
+}
+
+impl BrakeControl {
+    /// Creates a new brake controller with the brake engaged.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `release_ms` - Delay between brake release and motion
+    /// * `engage_ms` - Delay between brake engage and torque removal
+    pub fn new(frequency: u16, release_ms: u16, engage_ms: u16) -> Self {
+        let mut brake = Self {
+            frequency,
+            release_delay: 0,
+            engage_delay: 0,
+            counter: 0,
+            state: BrakeState::Engaged,
+        };
+        brake.set_delays(release_ms, engage_ms);
+        brake
+    }
+
+    /// Math call, returns current brake state.
+    ///
+    /// # Arguments
+    /// * `enable` - Drive enable request
+    /// * `fault` - Fault present, engages the brake immediately
+    pub fn tick(&mut self, enable: bool, fault: bool) -> BrakeState {
+        if fault {
+            self.state = BrakeState::Engaged;
+            self.counter = 0;
+            return self.state;
+        }
+
+        self.counter = self.counter.saturating_add(1);
+        let next = match self.state {
+            BrakeState::Engaged if enable => Some(BrakeState::Releasing),
+            BrakeState::Releasing if !enable => Some(BrakeState::Engaged),
+            BrakeState::Releasing if self.counter >= self.release_delay => {
+                Some(BrakeState::Released)
+            }
+            BrakeState::Released if !enable => Some(BrakeState::Engaging),
+            BrakeState::Engaging if enable => Some(BrakeState::Releasing),
+            BrakeState::Engaging if self.counter >= self.engage_delay => Some(BrakeState::Engaged),
+            _ => None,
+        };
+        if let Some(next) = next {
+            self.state = next;
+            self.counter = 0;
+            self.tick_delays(); // Zero delays pass through the transition states at once
+        }
+        self.state
+    }
+
+    /// Skips transition states with zero delay
+    fn tick_delays(&mut self) {
+        if self.state == BrakeState::Releasing && self.release_delay == 0 {
+            self.state = BrakeState::Released;
+        } else if self.state == BrakeState::Engaging && self.engage_delay == 0 {
+            self.state = BrakeState::Engaged;
+        }
+    }
+
+    /// Changes delays in milliseconds
+    pub fn set_delays(&mut self, release_ms: u16, engage_ms: u16) {
+        self.release_delay = release_ms as u32 * self.frequency as u32 / 1000;
+        self.engage_delay = engage_ms as u32 * self.frequency as u32 / 1000;
+    }
+
+    /// Getter for current brake state
+    pub fn state(&self) -> BrakeState {
+        self.state
+    }
+
+    /// Brake has to be released (output driven)
+    pub fn is_released(&self) -> bool {
+        matches!(self.state, BrakeState::Releasing | BrakeState::Released)
+    }
+
+    /// Drive has to produce torque
+    pub fn torque_enabled(&self) -> bool {
+        self.state != BrakeState::Engaged
+    }
+
+    /// Motion is allowed
+    pub fn motion_allowed(&self) -> bool {
+        self.state == BrakeState::Released
+    }
+}
@@ -0,0 +1,124 @@
+// Implements latching of the axis position on an external edge (touch probe, registration mark).
+
+// Key Features:
+// - Latches the multi-turn position in the control tick the edge is seen.
+// - Sub-tick accuracy when the platform timestamps the edge (timer input capture).
+// - Single shot capture re-armed on request, or continuous capture of every edge.
+// - Counter of captured edges so the host can detect missed readouts.
+
+// Detailed Operation:
+// The capture input is a digital pin function decoded once per control tick, an active edge
+// latches the position of that tick, so the error stays below the travel of one tick.
+// Platforms routing the pin to a timer capture channel call `capture_timed()` instead, with the
+// delay between the edge and the position sample; the position is extrapolated back to the
+// edge using the speed estimate.
+// In single shot mode the capture disarms after the first edge, so bounces or a second mark
+// can't overwrite the result before the host read it, `arm()` starts the next capture. In
+// continuous mode every edge overwrites the latched position.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Behaviour after a captured edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CaptureMode {
+    /// Disarm after the first edge until re-armed
+    Single = 0,
+    /// Capture every edge
+    Continuous = 1,
+}
+
+impl CaptureMode {
+    /// Converts raw mode, unknown values select single shot
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => CaptureMode::Continuous,
+            _ => CaptureMode::Single,
+        }
+    }
+}
+
+/// Position latch triggered by an external edge.
+pub struct PositionCapture {
+    frequency: u16,        // Update frequency (ticks per second)
+    mode: CaptureMode,     // Single shot or continuous
+    armed: bool,           // Next edge is captured
+    position: Option<i32>, // Last captured position
+    count: u16,            // Number of captured edges, wraps around
+}
+
+impl PositionCapture {
+    /// Creates an armed single shot capture.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub const fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            mode: CaptureMode::Single,
+            armed: true,
+            position: None,
+            count: 0,
+        }
+    }
+
+    /// Changes the mode and arms the capture
+    pub fn set_mode(&mut self, mode: CaptureMode) {
+        self.mode = mode;
+        self.armed = true;
+    }
+
+    /// Arms the capture for the next edge, the last result stays readable until then
+    pub fn arm(&mut self) {
+        self.armed = true;
+    }
+
+    /// Latches `position` on an edge detected in this tick, returns the captured position
+    pub fn tick(&mut self, edge: bool, position: i32) -> Option<i32> {
+        if edge {
+            self.latch(position)
+        } else {
+            None
+        }
+    }
+
+    /// Latches the position at a timestamped edge, returns the captured position
+    ///
+    /// # Arguments
+    /// * `position` - Position sampled after the edge
+    /// * `speed` - Speed estimate (position units per second)
+    /// * `delay` - Time from the edge to the position sample (1/65536 tick)
+    pub fn capture_timed(&mut self, position: i32, speed: i32, delay: u16) -> Option<i32> {
+        let travel = speed as i64 * delay as i64 / (self.frequency as i64 * 65536);
+        self.latch(position.wrapping_sub(travel as i32))
+    }
+
+    fn latch(&mut self, position: i32) -> Option<i32> {
+        if !self.armed {
+            return None;
+        }
+        self.armed = self.mode == CaptureMode::Continuous;
+        self.position = Some(position);
+        self.count = self.count.wrapping_add(1);
+        self.position
+    }
+
+    /// Last captured position, `None` if nothing was captured yet
+    #[inline(always)]
+    pub fn position(&self) -> Option<i32> {
+        self.position
+    }
+
+    /// Number of captured edges
+    #[inline(always)]
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// Returns true if the next edge is captured
+    #[inline(always)]
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+}
@@ -0,0 +1,163 @@
+// Implements position compare outputs switching a digital pin as the axis passes programmed
+// positions (camera triggering, synchronization of external equipment).
+
+// Key Features:
+// - Up to `N` compare positions checked every control tick.
+// - Direction filter: trigger when passing in both directions, forward or backward only.
+// - Pulse of configurable width or toggle of the output level per passed position.
+// - Deterministic timing: evaluated in the control tick right before the pins are written.
+
+// Detailed Operation:
+// Every tick the position of the previous tick and the current one span the travelled interval.
+// A compare position is passed if it lies in that interval, exclusive of the previous position
+// so a position sitting exactly on a compare point triggers once. Differences are taken with
+// wrapping arithmetic, so passing the multi-turn wrap around works as well. The output is
+// computed in the same tick the position is passed and written with the other pin outputs,
+// so the jitter is bounded by one control tick plus the GPIO write. Several positions passed in
+// one tick produce a single pulse (or toggle once per passed position).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Output action on a passed compare position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CompareAction {
+    /// Output active for the configured pulse width
+    Pulse = 0,
+    /// Output level toggles
+    Toggle = 1,
+}
+
+impl CompareAction {
+    /// Converts raw action, unknown values select pulse
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => CompareAction::Toggle,
+            _ => CompareAction::Pulse,
+        }
+    }
+}
+
+/// Direction of travel triggering the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CompareDirection {
+    Both = 0,
+    Forward = 1,
+    Backward = 2,
+}
+
+impl CompareDirection {
+    /// Converts raw direction, unknown values select both directions
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => CompareDirection::Forward,
+            2 => CompareDirection::Backward,
+            _ => CompareDirection::Both,
+        }
+    }
+}
+
+/// Compare output over `N` programmed positions.
+pub struct PositionCompare<const N: usize> {
+    frequency: u16,              // Update frequency (ticks per second)
+    positions: [i32; N],         // Compare positions
+    count: usize,                // Number of active compare positions
+    action: CompareAction,       // Pulse or toggle
+    direction: CompareDirection, // Direction filter
+    pulse_ticks: u32,            // Pulse width in ticks
+    remaining: u32,              // Ticks left of the running pulse
+    level: bool,                 // Output level in toggle mode
+    previous: Option<i32>,       // Position of the previous tick
+}
+
+impl<const N: usize> PositionCompare<N> {
+    /// Creates a disabled compare output.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `pulse_us` - Pulse width in microseconds
+    pub fn new(frequency: u16, pulse_us: u32) -> Self {
+        let mut compare = Self {
+            frequency,
+            positions: [0; N],
+            count: 0,
+            action: CompareAction::Pulse,
+            direction: CompareDirection::Both,
+            pulse_ticks: 1,
+            remaining: 0,
+            level: false,
+            previous: None,
+        };
+        compare.set_pulse_width(pulse_us);
+        compare
+    }
+
+    /// Sets compare position `idx`, ignored if out of range
+    pub fn set_position(&mut self, idx: usize, position: i32) {
+        if let Some(slot) = self.positions.get_mut(idx) {
+            *slot = position;
+        }
+    }
+
+    /// Sets the number of active compare positions (0 disables the output)
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count.min(N);
+    }
+
+    /// Sets the output action, the output returns to inactive
+    pub fn set_action(&mut self, action: CompareAction) {
+        self.action = action;
+        self.level = false;
+        self.remaining = 0;
+    }
+
+    /// Sets the direction filter
+    pub fn set_direction(&mut self, direction: CompareDirection) {
+        self.direction = direction;
+    }
+
+    /// Sets the pulse width, at least one tick
+    pub fn set_pulse_width(&mut self, pulse_us: u32) {
+        let ticks = pulse_us as u64 * self.frequency as u64 / 1_000_000;
+        self.pulse_ticks = (ticks as u32).max(1);
+    }
+
+    /// Checks the compare positions against the travelled interval, returns the output level
+    pub fn tick(&mut self, position: i32) -> bool {
+        let previous = self.previous.replace(position).unwrap_or(position);
+        let passed = self.positions[..self.count]
+            .iter()
+            .filter(|&&target| self.passed(previous, position, target))
+            .count();
+
+        match self.action {
+            CompareAction::Pulse => {
+                if passed > 0 {
+                    self.remaining = self.pulse_ticks;
+                }
+                let active = self.remaining > 0;
+                self.remaining = self.remaining.saturating_sub(1);
+                active
+            }
+            CompareAction::Toggle => {
+                self.level ^= passed % 2 == 1;
+                self.level
+            }
+        }
+    }
+
+    /// True if `target` lies in (previous, position] in an allowed direction
+    fn passed(&self, previous: i32, position: i32, target: i32) -> bool {
+        let travel = position.wrapping_sub(previous); // Travel during this tick
+        let distance = target.wrapping_sub(previous); // Distance to the target at the last tick
+        let forward = travel > 0 && distance > 0 && distance <= travel;
+        let backward = travel < 0 && distance < 0 && distance >= travel;
+        match self.direction {
+            CompareDirection::Both => forward || backward,
+            CompareDirection::Forward => forward,
+            CompareDirection::Backward => backward,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Implements the device information block identifying firmware build and hardware.
+
+// Key Features:
+// - Firmware semantic version and git revision embedded at build time.
+// - Board variant and unique MCU identifier supplied by the hardware layer.
+// - Calibration status, protocol version and parameter count for host compatibility checks.
+// - Paged encoding fitting the 8 byte protocol frames and boot log output via defmt.
+
+// Detailed Operation:
+// The version is taken from the crate manifest and the git hash is injected by the build
+// script (`TUNEPULSE_GIT_HASH`, zero when built outside of a git tree). The hardware layer
+// provides the board variant and the 96 bit MCU UID once at startup. Host tools read the block
+// before writing parameters and refuse to continue if the protocol version or parameter table
+// doesn't match their own build.
+// Page layouts (6 byte payload following the frame type and page number):
+// - Page 0: [major, minor, patch, board, flags, protocol version]
+// - Page 1: [git hash (u32 LE), parameter count (u16 LE)]
+// - Page 2: [UID bytes 0..6]
+// - Page 3: [UID bytes 6..12]
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::params::PARAM_COUNT;
+use crate::protocol::PROTOCOL_VERSION;
+
+/// Number of pages of the encoded device information
+pub const DEVICE_INFO_PAGES: u8 = 4;
+
+/// Size of the payload of a single device information page
+pub const DEVICE_INFO_PAGE_SIZE: usize = 6;
+
+/// Flags reported in page 0
+pub const INFO_CALIBRATED: u8 = 1 << 0;
+pub const INFO_GIT_DIRTY: u8 = 1 << 1;
+
+/// Hardware the firmware is running on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+#[repr(u8)]
+pub enum BoardVariant {
+    /// Board not identified
+    Unknown = 0,
+    /// CLN17 closed loop NEMA17 driver (STM32G431)
+    Cln17 = 1,
+}
+
+/// Identification of the firmware build and the hardware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub version: [u8; 3],    // Firmware version (major, minor, patch)
+    pub git_hash: u32,       // Short git hash of the firmware sources
+    pub git_dirty: bool,     // Firmware was built from a modified tree
+    pub board: BoardVariant, // Hardware variant
+    pub uid: [u8; 12],       // Unique MCU identifier
+    pub calibrated: bool,    // Encoder calibration is valid
+}
+
+impl DeviceInfo {
+    /// Creates the information block of the running firmware build
+    ///
+    /// # Arguments
+    /// * `board` - Hardware variant
+    /// * `uid` - Unique MCU identifier as stored by the MCU
+    pub const fn new(board: BoardVariant, uid: [u8; 12]) -> Self {
+        Self {
+            version: [
+                parse_dec(env!("CARGO_PKG_VERSION_MAJOR")),
+                parse_dec(env!("CARGO_PKG_VERSION_MINOR")),
+                parse_dec(env!("CARGO_PKG_VERSION_PATCH")),
+            ],
+            git_hash: parse_hex(env!("TUNEPULSE_GIT_HASH")),
+            git_dirty: parse_dec(env!("TUNEPULSE_GIT_DIRTY")) != 0,
+            board,
+            uid,
+            calibrated: false,
+        }
+    }
+
+    /// Returns the payload of one page, `None` for pages past the end
+    pub fn page(&self, page: u8) -> Option<[u8; DEVICE_INFO_PAGE_SIZE]> {
+        match page {
+            0 => {
+                let mut flags = 0;
+                if self.calibrated {
+                    flags |= INFO_CALIBRATED;
+                }
+                if self.git_dirty {
+                    flags |= INFO_GIT_DIRTY;
+                }
+                let [major, minor, patch] = self.version;
+                Some([
+                    major,
+                    minor,
+                    patch,
+                    self.board as u8,
+                    flags,
+                    PROTOCOL_VERSION,
+                ])
+            }
+            1 => {
+                let hash = self.git_hash.to_le_bytes();
+                let count = (PARAM_COUNT as u16).to_le_bytes();
+                Some([hash[0], hash[1], hash[2], hash[3], count[0], count[1]])
+            }
+            2 | 3 => {
+                let start = (page as usize - 2) * DEVICE_INFO_PAGE_SIZE;
+                let mut payload = [0; DEVICE_INFO_PAGE_SIZE];
+                payload.copy_from_slice(&self.uid[start..start + DEVICE_INFO_PAGE_SIZE]);
+                Some(payload)
+            }
+            _ => None,
+        }
+    }
+
+    /// Prints the information block to the log
+    pub fn log(&self) {
+        let [major, minor, patch] = self.version;
+        defmt::info!(
+            "SYSTEM: TunePulse v{}.{}.{} ({:08x}{})",
+            major,
+            minor,
+            patch,
+            self.git_hash,
+            if self.git_dirty { "-dirty" } else { "" }
+        );
+        defmt::info!(
+            "SYSTEM: Board {}, UID {:02x}, protocol v{}",
+            self.board,
+            self.uid,
+            PROTOCOL_VERSION
+        );
+        defmt::info!("SYSTEM: Calibrated: {}", self.calibrated);
+    }
+}
+
+/// Parses a decimal number at compile time, stops at the first non digit
+const fn parse_dec(text: &str) -> u8 {
+    let bytes = text.as_bytes();
+    let mut value: u8 = 0;
+    let mut i = 0;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        value = value.wrapping_mul(10).wrapping_add(bytes[i] - b'0');
+        i += 1;
+    }
+    value
+}
+
+/// Parses a hexadecimal number at compile time, stops at the first non hex digit
+const fn parse_hex(text: &str) -> u32 {
+    let bytes = text.as_bytes();
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let digit = match bytes[i] {
+            b'0'..=b'9' => bytes[i] - b'0',
+            b'a'..=b'f' => bytes[i] - b'a' + 10,
+            b'A'..=b'F' => bytes[i] - b'A' + 10,
+            _ => break,
+        };
+        value = (value << 4) | digit as u32;
+        i += 1;
+    }
+    value
+}
@@ -0,0 +1,141 @@
+// Implements collision and obstruction detection from sudden changes of the estimated load
+// torque, with a configurable reaction for human-interactive mechanisms.
+
+// Key Features:
+// - Detects load torque spikes against a slowly tracking baseline, steady loads like gravity
+//   or friction don't trigger.
+// - Debounce time rejecting single noisy samples.
+// - Reaction selectable per application: stop, back off a distance or limit the torque.
+// - Latched until cleared, so the mechanism doesn't push into the obstacle again by itself.
+
+// Detailed Operation:
+// The disturbance observer estimates the load torque as equivalent current from the mismatch
+// between commanded current and measured acceleration. A collision shows up as a step of that
+// estimate while the expected load changes slowly. The detector low-pass filters the estimate
+// with a time constant of about 200 ms into a baseline and compares the estimate against it.
+// If the deviation exceeds the threshold for the debounce time a collision is latched and the
+// baseline is frozen, so a persisting obstruction stays detected. The owner applies the
+// reaction:
+// - Stop: motion programs stop and the axis holds the position of the collision.
+// - Reverse: the axis backs off the configured distance against the load direction.
+// - ReduceTorque: motion continues with the torque command limited to a safe level.
+// Clearing re-arms detection and releases the torque limit, the baseline restarts from the
+// current estimate.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Reaction on a detected collision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CollisionReaction {
+    /// Stop motion and hold the position
+    Stop = 0,
+    /// Back off a distance against the load direction
+    Reverse = 1,
+    /// Keep moving with the torque limited to a safe level
+    ReduceTorque = 2,
+}
+
+impl CollisionReaction {
+    /// Converts raw reaction, unknown values select stop
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => CollisionReaction::Reverse,
+            2 => CollisionReaction::ReduceTorque,
+            _ => CollisionReaction::Stop,
+        }
+    }
+}
+
+/// Detects load torque spikes.
+pub struct CollisionDetector {
+    frequency: u16,       // Update frequency (ticks per second)
+    threshold: i32,       // Deviation from the baseline treated as collision (mA, 0 - disabled)
+    debounce: u32,        // Ticks the deviation has to persist
+    shift: u32,           // Baseline filter time constant as power of two ticks
+    baseline: i64,        // Slowly tracking load estimate (mA as i48.16)
+    exceeded: u32,        // Ticks the deviation currently persists
+    deviation: i32,       // Deviation of the last tick (mA)
+    latched: Option<i32>, // Deviation at the detected collision
+}
+
+impl CollisionDetector {
+    /// Creates a disabled detector.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        // Time constant of about 200 ms: largest power of two ticks up to frequency / 5
+        let shift = (frequency as u32 / 5).max(1).ilog2();
+        Self {
+            frequency,
+            threshold: 0,
+            debounce: 0,
+            shift,
+            baseline: 0,
+            exceeded: 0,
+            deviation: 0,
+            latched: None,
+        }
+    }
+
+    /// Configures the detection.
+    ///
+    /// # Arguments
+    /// * `threshold` - Load torque step treated as collision (mA, 0 - disabled)
+    /// * `debounce_ms` - Time the step has to persist
+    pub fn configure(&mut self, threshold: i32, debounce_ms: u32) {
+        self.threshold = threshold.max(0);
+        self.debounce = (debounce_ms as u64 * self.frequency as u64 / 1000) as u32;
+        self.exceeded = 0;
+    }
+
+    /// Updates the detector, returns true in the tick a collision is detected
+    ///
+    /// # Arguments
+    /// * `load` - Estimated load torque (mA)
+    pub fn tick(&mut self, load: i32) -> bool {
+        self.deviation = load.saturating_sub((self.baseline >> 16) as i32);
+        if self.latched.is_some() {
+            return false;
+        }
+        self.baseline += (((load as i64) << 16) - self.baseline) >> self.shift;
+        if self.threshold == 0 || self.deviation.saturating_abs() <= self.threshold {
+            self.exceeded = 0;
+            return false;
+        }
+        self.exceeded += 1;
+        if self.exceeded <= self.debounce {
+            return false;
+        }
+        self.latched = Some(self.deviation);
+        true
+    }
+
+    /// Re-arms the detection, the baseline restarts from `load`
+    pub fn clear(&mut self, load: i32) {
+        self.latched = None;
+        self.exceeded = 0;
+        self.baseline = (load as i64) << 16;
+    }
+
+    /// Returns true while a detected collision is latched
+    #[inline(always)]
+    pub fn is_latched(&self) -> bool {
+        self.latched.is_some()
+    }
+
+    /// Deviation from the baseline at the detected collision (mA), positive loads oppose
+    /// positive motion
+    #[inline(always)]
+    pub fn collision_load(&self) -> Option<i32> {
+        self.latched
+    }
+
+    /// Deviation of the load estimate from the baseline (mA)
+    #[inline(always)]
+    pub fn deviation(&self) -> i32 {
+        self.deviation
+    }
+}
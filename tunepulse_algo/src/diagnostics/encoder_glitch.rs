@@ -0,0 +1,227 @@
+// Implements the rejection of glitched encoder samples by comparing two back-to-back reads,
+// extended by the EMC-robust profile for electrically noisy installations.
+
+// Key Features:
+// - Optional second encoder read per control period, enabled by a non-zero threshold.
+// - Sample rejected when the two reads disagree beyond the threshold, the last accepted angle
+//   is used instead.
+// - EMC profile: read pair repeated by the board on disagreement, plausibility check against
+//   the angle predicted from the last step, rejection bursts bridged by extrapolation.
+// - Degraded operation reported while rejections keep occurring, error statistics kept.
+
+// Detailed Operation:
+// A bit flipped on the SPI bus (switching noise coupling into the encoder cable) turns into an
+// angle jump the position and speed estimation take as real motion. With a threshold set the
+// board reads the encoder twice back to back and passes both angles with the inputs. Within
+// the few microseconds between the reads the shaft moves far less than the threshold, so a
+// larger difference means one of the reads was corrupted. It is unknown which, so the whole
+// sample is rejected and the last accepted angle repeated, the position holds for one tick.
+// The difference is taken modulo one turn, a read pair across the zero crossing agrees.
+// A disagreement persisting over `MAX_REJECTED` samples points to a broken bus rather than a
+// glitch, the first read is accepted then so the position keeps following the shaft, the
+// counter shows the problem. Without a threshold both angles are the same single read.
+// The encoder answer carries no CRC, the read pair is the transfer validation. The EMC
+// profile builds on it:
+// - The board repeats a disagreeing pair once within the period (`retry()`), the repeated
+//   pair is sampled a few microseconds late but saves the tick. Repeats are counted.
+// - Corruption hitting both reads alike passes the pair check. The plausibility model
+//   predicts the angle from the last accepted step (constant speed over one tick) and rejects
+//   samples deviating from it by more than the threshold. After a resynchronization there is
+//   no step to predict from, the next sample is checked by the pair only.
+// - Rejected samples are replaced by the predicted angle, the drive keeps running through
+//   bursts of up to `EMC_MAX_REJECTED` samples.
+// - The first rejection enters degraded operation, `RECOVERY_TIME_MS` without rejection ends
+//   it. The owner reports the transitions, the statistics are kept for the host.
+// Without configured threshold the profile uses `EMC_DEFAULT_THRESHOLD`.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Consecutive rejected samples after which the first read is accepted anyway
+const MAX_REJECTED: u8 = 3;
+
+/// Consecutive rejected samples bridged by extrapolation in the EMC profile
+const EMC_MAX_REJECTED: u8 = 16;
+
+/// Check threshold of the EMC profile without configured threshold (counts, ~1.4°)
+pub const EMC_DEFAULT_THRESHOLD: u16 = 256;
+
+/// Time without rejected samples ending the degraded operation
+const RECOVERY_TIME_MS: u32 = 100;
+
+/// Encoder error counters since power-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EncoderErrorStats {
+    pub glitches: u32,    // Samples rejected for disagreeing reads
+    pub implausible: u32, // Samples rejected for deviating from the predicted angle
+    pub retries: u32,     // Read pairs repeated by the board
+    pub resyncs: u32,     // Rejection bursts ended by accepting the read anyway
+    pub episodes: u32,    // Entries into degraded operation
+}
+
+impl EncoderErrorStats {
+    /// Rejected samples of any reason
+    #[inline(always)]
+    pub fn rejected(&self) -> u32 {
+        self.glitches.wrapping_add(self.implausible)
+    }
+}
+
+/// Encoder sample check against a second read.
+pub struct EncoderGlitchFilter {
+    threshold: u16,           // Largest accepted read difference (counts), 0 - disabled
+    emc: bool,                // EMC-robust profile enabled
+    angle: Option<u16>,       // Last accepted angle, `None` before the first sample
+    step: Option<i16>,        // Last accepted step per tick, `None` without prediction
+    rejected: u8,             // Consecutive rejected samples
+    degraded: bool,           // Rejections occurred within the recovery time
+    clean_ticks: u32,         // Ticks since the last rejected sample
+    recovery_ticks: u32,      // Ticks without rejection ending the degraded operation
+    stats: EncoderErrorStats, // Error counters since power-up
+}
+
+impl EncoderGlitchFilter {
+    /// Creates a disabled check, every sample is accepted
+    ///
+    /// # Arguments
+    /// * `frequency` - Control loop frequency (Hz)
+    pub const fn new(frequency: u16) -> Self {
+        Self {
+            threshold: 0,
+            emc: false,
+            angle: None,
+            step: None,
+            rejected: 0,
+            degraded: false,
+            clean_ticks: 0,
+            recovery_ticks: frequency as u32 * RECOVERY_TIME_MS / 1000,
+            stats: EncoderErrorStats {
+                glitches: 0,
+                implausible: 0,
+                retries: 0,
+                resyncs: 0,
+                episodes: 0,
+            },
+        }
+    }
+
+    /// Sets the largest accepted difference of the two reads (counts, 0 - single read)
+    pub fn set_threshold(&mut self, threshold: u16) {
+        self.threshold = threshold;
+    }
+
+    /// Enables the EMC-robust profile
+    pub fn set_emc(&mut self, enabled: bool) {
+        self.emc = enabled;
+        if !enabled {
+            self.degraded = false;
+        }
+    }
+
+    /// Threshold in effect, 0 if the board reads the encoder once per period
+    pub fn threshold(&self) -> u16 {
+        match self.threshold {
+            0 if self.emc => EMC_DEFAULT_THRESHOLD,
+            threshold => threshold,
+        }
+    }
+
+    /// Returns true if the board has to read the encoder twice per period
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.threshold() != 0
+    }
+
+    /// Returns true if the board repeats a disagreeing read pair
+    #[inline(always)]
+    pub fn retry(&self) -> bool {
+        self.emc
+    }
+
+    /// Math call, returns the angle to use for this tick
+    ///
+    /// # Arguments
+    /// * `angle` - First read, taken at the sample instant
+    /// * `check` - Second read right after it
+    pub fn tick(&mut self, angle: u16, check: u16) -> u16 {
+        let Some(last) = self.angle else {
+            return self.accept(angle);
+        };
+        let threshold = self.threshold();
+        let predicted = match self.step {
+            Some(step) if self.emc => last.wrapping_add(step as u16),
+            _ => last,
+        };
+        let glitch = threshold != 0 && difference(angle, check) > threshold;
+        let implausible =
+            self.emc && self.step.is_some() && difference(angle, predicted) > threshold;
+        let max_rejected = if self.emc {
+            EMC_MAX_REJECTED
+        } else {
+            MAX_REJECTED
+        };
+
+        if (glitch || implausible) && self.rejected < max_rejected {
+            self.rejected += 1;
+            if glitch {
+                self.stats.glitches = self.stats.glitches.wrapping_add(1);
+            } else {
+                self.stats.implausible = self.stats.implausible.wrapping_add(1);
+            }
+            self.clean_ticks = 0;
+            if self.emc && !self.degraded {
+                self.degraded = true;
+                self.stats.episodes = self.stats.episodes.wrapping_add(1);
+            }
+            self.angle = Some(predicted); // Coast on the prediction, hold without
+            return predicted;
+        }
+
+        if self.rejected >= max_rejected {
+            self.stats.resyncs = self.stats.resyncs.wrapping_add(1);
+            self.step = None; // The jump since the last accepted angle isn't a step
+        } else {
+            self.step = Some(angle.wrapping_sub(last) as i16);
+        }
+        if self.degraded {
+            self.clean_ticks += 1;
+            self.degraded = self.clean_ticks < self.recovery_ticks;
+        }
+        self.accept(angle)
+    }
+
+    fn accept(&mut self, angle: u16) -> u16 {
+        self.rejected = 0;
+        self.angle = Some(angle);
+        angle
+    }
+
+    /// Adds the read pairs repeated by the board since the last call
+    pub fn count_retries(&mut self, retries: u32) {
+        self.stats.retries = self.stats.retries.wrapping_add(retries);
+    }
+
+    /// Returns true while the drive runs on bridged encoder samples (EMC profile)
+    #[inline(always)]
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Samples rejected for disagreeing reads since power-up
+    #[inline(always)]
+    pub fn count(&self) -> u32 {
+        self.stats.glitches
+    }
+
+    /// Error counters since power-up
+    #[inline(always)]
+    pub fn stats(&self) -> &EncoderErrorStats {
+        &self.stats
+    }
+}
+
+/// Distance of two angles modulo one turn (counts)
+#[inline(always)]
+fn difference(a: u16, b: u16) -> u16 {
+    (b.wrapping_sub(a) as i16).unsigned_abs()
+}
@@ -0,0 +1,75 @@
+// Implements following error monitoring of the position loop: an axis lagging its reference
+// position by more than a window for longer than a timeout is reported once, the owner faults.
+
+// Key Features:
+// - Configurable window (encoder counts) and timeout, a window of 0 disables the monitor.
+// - Short excursions during acceleration or load steps within the timeout don't trip.
+// - Only counts while the position loop drives the axis with torque enabled.
+
+// Detailed Operation:
+// Each tick the owner passes the raw difference between the reference position and the
+// measured position (before the in-position deadband) and whether the position loop is in
+// control. While the magnitude of the error exceeds the window the monitor counts ticks and
+// reports a trip in the tick the count reaches the timeout; an error back within the window
+// restarts the count. A disabled drive, a torque or open-loop setpoint or a drive without
+// torque restarts the count too, the axis can be moved by hand there without tripping. After
+// a trip the monitor stays quiet until the error returns within the window or the loop stops,
+// the fault state of the owner takes over.
+// A jammed axis or a loop tuned too softly for the commanded motion both show up as a
+// persisting following error, the fault stops the axis before the motor overheats.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Detects a position error persisting outside the following error window.
+pub struct FollowingErrorMonitor {
+    frequency: u16, // Update frequency (ticks per second)
+    window: u32,    // Largest error allowed (counts, 0 - disabled)
+    timeout: u32,   // Ticks the error may stay outside the window
+    exceeded: u32,  // Ticks the error currently stays outside the window
+}
+
+impl FollowingErrorMonitor {
+    /// Creates a disabled monitor.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            window: 0,
+            timeout: 0,
+            exceeded: 0,
+        }
+    }
+
+    /// Configures the monitoring.
+    ///
+    /// # Arguments
+    /// * `window` - Largest following error allowed (i16 rotations + u16 angle, 0 - disabled)
+    /// * `timeout_ms` - Time the error may stay outside the window
+    pub fn configure(&mut self, window: u32, timeout_ms: u32) {
+        self.window = window;
+        self.timeout = (timeout_ms as u64 * self.frequency as u64 / 1000) as u32;
+        self.exceeded = 0;
+    }
+
+    /// Updates the monitor, returns true in the tick the following error times out.
+    ///
+    /// # Arguments
+    /// * `error` - Reference minus measured position (i16 rotations + u16 angle)
+    /// * `active` - Position loop drives the axis with torque enabled
+    pub fn tick(&mut self, error: i32, active: bool) -> bool {
+        if !active || self.window == 0 || error.unsigned_abs() <= self.window {
+            self.exceeded = 0;
+            return false;
+        }
+        self.exceeded = self.exceeded.saturating_add(1);
+        self.exceeded == self.timeout.max(1)
+    }
+
+    /// Restarts the count, e.g. once the fault was cleared
+    pub fn reset(&mut self) {
+        self.exceeded = 0;
+    }
+}
@@ -0,0 +1,124 @@
+// Implements the load angle monitor, tracking the difference between the commanded electrical
+// angle and the electrical angle derived from the encoder through the calibration table.
+
+// Key Features:
+// - Calculates the instantaneous and filtered load (torque) angle.
+// - Predicts stalls when the load angle approaches the pull-out angle (90° el), reported once
+//   per approach.
+// - Tracks load angle ripple to verify calibration quality under load.
+
+// Detailed Operation:
+// For a synchronous motor the produced torque follows sin(load angle), so the load angle grows
+// with the load until it reaches 90° el where the motor pulls out of synchronism. The monitor
+// filters the load angle and reports a stall risk once it exceeds the configured threshold,
+// the owner is warned once when the risk starts and again only after it cleared.
+// At constant load the load angle should stay constant over a revolution, so its peak-to-peak
+// ripple directly reflects the remaining error of the calibration table.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::filters::lpf::FilterLPF;
+
+/// Monitors the load angle between commanded and measured electrical angle.
+pub struct LoadAngleMonitor {
+    /// Filter smoothing the load angle for stall prediction
+    filter: FilterLPF,
+
+    /// Instantaneous load angle (i16 range = ±180° el)
+    load_angle: i16,
+
+    /// Filtered load angle
+    load_angle_filt: i16,
+
+    /// Minimum and maximum filtered load angle since last ripple reset
+    min: i16,
+    max: i16,
+
+    /// Absolute filtered load angle that indicates a stall risk
+    stall_threshold: i16,
+
+    /// Stall risk already reported to the owner
+    warned: bool,
+}
+
+impl LoadAngleMonitor {
+    /// Default stall threshold: 75° el, leaving 15° el margin before pull-out
+    pub const DEFAULT_STALL_THRESHOLD: i16 = (75 * 65536 / 360) as i16;
+
+    /// Creates a new load angle monitor.
+    ///
+    /// # Arguments
+    /// * `alpha` - LPF coefficient (0..255) used for the filtered load angle
+    /// * `stall_threshold` - Absolute load angle treated as a stall risk
+    pub fn new(alpha: u8, stall_threshold: i16) -> Self {
+        Self {
+            filter: FilterLPF::new(0, alpha),
+            load_angle: 0,
+            load_angle_filt: 0,
+            min: i16::MAX,
+            max: i16::MIN,
+            stall_threshold,
+            warned: false,
+        }
+    }
+
+    /// Updates the monitor with the commanded and measured electrical angles.
+    pub fn tick(&mut self, commanded_el: u16, measured_el: u16) -> i16 {
+        // Wrapping difference keeps the result within ±180° el
+        self.load_angle = commanded_el.wrapping_sub(measured_el) as i16;
+
+        // The filter handles zero-crossing transitions, so signed angles can be passed as u16
+        self.load_angle_filt = self.filter.tick(self.load_angle as u16) as i16;
+
+        self.min = self.min.min(self.load_angle_filt);
+        self.max = self.max.max(self.load_angle_filt);
+        self.load_angle
+    }
+
+    /// Getter for instantaneous load angle
+    pub fn load_angle(&self) -> i16 {
+        self.load_angle
+    }
+
+    /// Getter for filtered load angle
+    pub fn load_angle_filtered(&self) -> i16 {
+        self.load_angle_filt
+    }
+
+    /// Returns true if the filtered load angle is close to the pull-out angle
+    pub fn is_stall_risk(&self) -> bool {
+        self.load_angle_filt.unsigned_abs() >= self.stall_threshold as u16
+    }
+
+    /// Returns true once when the stall risk starts, `armed` false suppresses and clears it.
+    ///
+    /// # Arguments
+    /// * `armed` - The motor produces torque and a stall matters
+    pub fn take_stall_warning(&mut self, armed: bool) -> bool {
+        let risk = armed && self.is_stall_risk();
+        let warning = risk && !self.warned;
+        self.warned = risk;
+        warning
+    }
+
+    /// Peak-to-peak filtered load angle since last reset, reflects calibration error under
+    /// constant load, `None` until a sample was collected
+    pub fn ripple(&self) -> Option<u16> {
+        if self.max < self.min {
+            return None;
+        }
+        Some((self.max as i32 - self.min as i32) as u16)
+    }
+
+    /// Restarts ripple measurement
+    pub fn reset_ripple(&mut self) {
+        self.min = i16::MAX;
+        self.max = i16::MIN;
+    }
+
+    /// Changes the stall threshold
+    pub fn set_stall_threshold(&mut self, stall_threshold: i16) {
+        self.stall_threshold = stall_threshold;
+    }
+}
@@ -0,0 +1,11 @@
+pub mod collision;
+pub mod encoder_glitch;
+pub mod following_error;
+pub mod load_angle;
+pub mod overflow;
+pub mod peak_hold;
+#[cfg(feature = "production-test")]
+pub mod production_test;
+pub mod resonance;
+pub mod sample_alignment;
+pub mod step_loss;
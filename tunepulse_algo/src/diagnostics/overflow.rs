@@ -0,0 +1,144 @@
+// Implements optional instrumentation of critical fixed point operations counting overflows and
+// saturations, so gains and scales pushing values past the i16/i32 ranges become visible.
+
+// Key Features:
+// - Enabled by the `overflow-check` feature, otherwise the helpers compile to the plain
+//   operations and the counters stay zero.
+// - Counter per instrumented site: duty scaling, current command, PID sums and calibration
+//   interpolation.
+// - Total count routed to the scope as `ScopeSignal::Overflows` for live telemetry.
+
+// Detailed Operation:
+// Instrumented code calls the helpers below instead of the raw arithmetic. With the feature
+// enabled, every helper checks its operation, clamps the result to the representable range and
+// increments the counter of the site when it had to. Without the feature the helpers perform
+// exactly the operation they replace (including its truncation), so release builds keep their
+// timing and results. The counters are atomics so sites in any module and interrupt priority
+// can record without passing state around; they only ever increase (wrapping), the host looks
+// at the increase between two readings. A steadily rising count means a gain or scale leaves
+// no headroom, a single step usually points to a transient such as a position jump.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Instrumented operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OverflowSite {
+    /// Current to voltage scale and summing of the duty components
+    DutyScale = 0,
+    /// Torque current command after compensations
+    CurrentCommand = 1,
+    /// Gain products and output sum of the PID controller
+    PidSum = 2,
+    /// Interpolation of the encoder calibration table
+    CalibrationInterpolation = 3,
+}
+
+/// Number of instrumented sites
+pub const OVERFLOW_SITES: usize = 4;
+
+static COUNTERS: [AtomicU32; OVERFLOW_SITES] = [
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+];
+
+/// Returns true if the `overflow-check` feature is enabled
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "overflow-check")
+}
+
+/// Records an overflow at `site`
+#[inline(always)]
+pub fn record(site: OverflowSite) {
+    if is_enabled() {
+        COUNTERS[site as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Number of overflows recorded at `site`
+pub fn count(site: OverflowSite) -> u32 {
+    COUNTERS[site as usize].load(Ordering::Relaxed)
+}
+
+/// Number of overflows recorded at all sites
+pub fn total() -> u32 {
+    COUNTERS.iter().fold(0, |sum, counter| {
+        sum.wrapping_add(counter.load(Ordering::Relaxed))
+    })
+}
+
+/// Converts to i16, values outside of `[-limit, limit]` are clamped and recorded
+#[inline(always)]
+pub fn clamp_i16(value: i32, limit: i16, site: OverflowSite) -> i16 {
+    let limit = limit as i32;
+    if is_enabled() && (value > limit || value < -limit) {
+        record(site);
+    }
+    value.clamp(-limit, limit) as i16
+}
+
+/// Saturating i16 sum, saturation is recorded
+#[inline(always)]
+pub fn add_i16(a: i16, b: i16, site: OverflowSite) -> i16 {
+    if is_enabled() && a.checked_add(b).is_none() {
+        record(site);
+    }
+    a.saturating_add(b)
+}
+
+/// Truncating conversion to i16 (`as i16`), values outside of the range are recorded and
+/// clamped when checks are enabled
+#[inline(always)]
+pub fn to_i16(value: i32, site: OverflowSite) -> i16 {
+    if is_enabled() {
+        if let Ok(value) = i16::try_from(value) {
+            return value;
+        }
+        record(site);
+        return value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    }
+    value as i16
+}
+
+/// Truncating conversion to u16 (`as u16`), values outside of the range are recorded and
+/// clamped when checks are enabled
+#[inline(always)]
+pub fn to_u16(value: u32, site: OverflowSite) -> u16 {
+    if is_enabled() {
+        if let Ok(value) = u16::try_from(value) {
+            return value;
+        }
+        record(site);
+        return u16::MAX;
+    }
+    value as u16
+}
+
+/// i32 product, overflows are recorded and saturated when checks are enabled
+#[inline(always)]
+pub fn mul_i32(a: i32, b: i32, site: OverflowSite) -> i32 {
+    if is_enabled() {
+        return a.checked_mul(b).unwrap_or_else(|| {
+            record(site);
+            a.saturating_mul(b)
+        });
+    }
+    a * b
+}
+
+/// i32 sum, overflows are recorded and saturated when checks are enabled
+#[inline(always)]
+pub fn add_i32(a: i32, b: i32, site: OverflowSite) -> i32 {
+    if is_enabled() {
+        return a.checked_add(b).unwrap_or_else(|| {
+            record(site);
+            a.saturating_add(b)
+        });
+    }
+    a + b
+}
@@ -0,0 +1,106 @@
+// Implements peak-hold registers of the drive load: the largest current, temperature sensor
+// reading, speed and following error seen since they were last cleared.
+
+// Key Features:
+// - One register per value, updated every control tick with a compare and a store.
+// - Values read one by one, each optionally cleared by the read that returned it.
+// - All registers cleared together on request or at reset.
+
+// Detailed Operation:
+// The controller feeds the measured current vector, the raw temperature sensor reading, the
+// estimated speed and the position error of every tick. Signed values enter by magnitude. The
+// current is compared as the squared length of the d/q vector, so the tick doesn't take a
+// square root; the root is taken once the register is read. A register that hasn't seen a
+// sample since it was cleared reads as `None`.
+// The temperature is the raw sensor ADC value as in the status report, the largest reading is
+// held whichever direction the sensor of the board moves with the temperature.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of peak-hold registers
+pub const PEAK_VALUES: usize = 4;
+
+/// Value held by a peak-hold register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PeakValue {
+    /// Magnitude of the measured d/q current vector (mA)
+    Current = 0,
+    /// Temperature sensor reading (raw ADC)
+    Temperature = 1,
+    /// Magnitude of the estimated speed (counts/s)
+    Speed = 2,
+    /// Magnitude of the position error beyond the deadband (counts)
+    FollowingError = 3,
+}
+
+impl PeakValue {
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(PeakValue::Current),
+            1 => Some(PeakValue::Temperature),
+            2 => Some(PeakValue::Speed),
+            3 => Some(PeakValue::FollowingError),
+            _ => None,
+        }
+    }
+}
+
+/// Largest values seen since the registers were cleared.
+pub struct PeakHold {
+    peaks: [Option<u32>; PEAK_VALUES], // Held values indexed by `PeakValue`, current squared
+}
+
+impl PeakHold {
+    pub fn new() -> Self {
+        Self {
+            peaks: [None; PEAK_VALUES],
+        }
+    }
+
+    /// Updates the registers with the values of one control tick.
+    ///
+    /// # Arguments
+    /// * `current` - Measured d/q current (mA)
+    /// * `temperature` - Temperature sensor reading (raw ADC)
+    /// * `speed` - Estimated speed (counts/s)
+    /// * `error` - Position error beyond the deadband (counts)
+    pub fn tick(&mut self, current: (i16, i16), temperature: u16, speed: i32, error: i32) {
+        let (d, q) = (current.0 as i32, current.1 as i32);
+        let values = [
+            (d * d) as u32 + (q * q) as u32,
+            temperature as u32,
+            speed.unsigned_abs(),
+            error.unsigned_abs(),
+        ];
+        for (peak, value) in self.peaks.iter_mut().zip(values) {
+            *peak = Some(peak.map_or(value, |peak| peak.max(value)));
+        }
+    }
+
+    /// Held value, `None` if no tick was seen since the register was cleared
+    pub fn get(&self, value: PeakValue) -> Option<u32> {
+        let peak = self.peaks[value as usize]?;
+        match value {
+            PeakValue::Current => Some(peak.isqrt()),
+            _ => Some(peak),
+        }
+    }
+
+    /// Clears one register, the next tick starts it over
+    pub fn clear(&mut self, value: PeakValue) {
+        self.peaks[value as usize] = None;
+    }
+
+    /// Clears all registers
+    pub fn clear_all(&mut self) {
+        self.peaks = [None; PEAK_VALUES];
+    }
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        Self::new()
+    }
+}
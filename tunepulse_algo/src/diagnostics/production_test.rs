@@ -0,0 +1,188 @@
+// Implements the end-of-line production test, a single command checking a unit against
+// configurable pass/fail limits.
+
+// Key Features:
+// - Self-test of the calibration table and the arithmetic, abbreviated calibration by offset trim.
+// - Supply voltage, temperature sensor, table deviation and trim spread measured on-device.
+// - Every metric compared to its limits, report kept for readout with a failed metric mask.
+// - Verdict shown on the status LED while the drive is disabled.
+// - Compiled out without the `production-test` feature.
+
+// Detailed Operation:
+// The owner runs the self-test checks when the test is started and passes the failed checks
+// to `start()`. A unit failing the self-test finishes at once, otherwise the owner starts the
+// quick offset trim (the abbreviated calibration, the boot calibration already measured the
+// table) and feeds the temperature sensor reading every tick, it is averaged over the whole
+// test. Once the trim finished the owner measures the remaining metrics and hands them to
+// `finish()` together with the limits. A metric passes if it was measured and lies within its
+// limits, the test passes if all metrics pass. Metrics which weren't measured (e.g. the trim
+// spread after a failed self-test) fail.
+// The temperature is reported as raw sensor ADC value, the limits use the same scale.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of metrics in the report
+pub const PRODUCTION_METRICS: usize = 5;
+
+/// Self-test check: calibration table missing or drifted
+pub const SELF_TEST_TABLE: u32 = 1 << 0;
+/// Self-test check: saturated arithmetic since power-up
+pub const SELF_TEST_OVERFLOW: u32 = 1 << 1;
+
+/// Metrics of the production test, the value is the index in the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProductionMetric {
+    /// Failed self-test checks (`SELF_TEST_*` bits), passes at 0
+    SelfTest = 0,
+    /// Supply voltage (mV)
+    SupplyVoltage = 1,
+    /// Temperature sensor reading averaged over the test (raw ADC)
+    Temperature = 2,
+    /// Largest deviation of the calibration table from an ideal encoder (counts)
+    TableDeviation = 3,
+    /// Spread of the electrical offset between the trim points (65536 per 360° el)
+    TrimSpread = 4,
+}
+
+/// Result of one metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetricResult {
+    pub value: Option<i32>, // Measured value, `None` if the metric wasn't measured
+    pub passed: bool,       // Value within the limits
+}
+
+/// Report of a finished production test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProductionReport {
+    pub metrics: [MetricResult; PRODUCTION_METRICS], // Results indexed by `ProductionMetric`
+}
+
+impl ProductionReport {
+    /// Returns true if every metric passed
+    pub fn passed(&self) -> bool {
+        self.metrics.iter().all(|metric| metric.passed)
+    }
+
+    /// Bit per failed metric, 0 if the unit passed
+    pub fn failed_mask(&self) -> u32 {
+        self.metrics
+            .iter()
+            .enumerate()
+            .filter(|(_, metric)| !metric.passed)
+            .fold(0, |mask, (index, _)| mask | 1 << index)
+    }
+}
+
+/// Runs the production test and keeps its report.
+pub struct ProductionTest {
+    running: bool,                    // Test in progress
+    self_test: u32,                   // Failed self-test checks of the running test
+    temp_sum: u64,                    // Temperature readings summed over the test
+    temp_count: u32,                  // Number of summed temperature readings
+    report: Option<ProductionReport>, // Report of the last finished test
+}
+
+impl ProductionTest {
+    /// Creates an idle test without report
+    pub const fn new() -> Self {
+        Self {
+            running: false,
+            self_test: 0,
+            temp_sum: 0,
+            temp_count: 0,
+            report: None,
+        }
+    }
+
+    /// Starts the test, the report of the previous run is cleared.
+    ///
+    /// # Arguments
+    /// * `self_test` - Failed self-test checks (`SELF_TEST_*` bits)
+    pub fn start(&mut self, self_test: u32) {
+        self.running = true;
+        self.self_test = self_test;
+        self.temp_sum = 0;
+        self.temp_count = 0;
+        self.report = None;
+    }
+
+    /// Returns true if the self-test of the running test passed
+    #[inline(always)]
+    pub fn self_test_passed(&self) -> bool {
+        self.self_test == 0
+    }
+
+    /// Accumulates the temperature sensor reading while running
+    pub fn sample(&mut self, temper_adc: u16) {
+        if self.running {
+            self.temp_sum += temper_adc as u64;
+            self.temp_count += 1;
+        }
+    }
+
+    /// Evaluates the metrics and stores the report, returns it.
+    ///
+    /// # Arguments
+    /// * `supply_mv` - Supply voltage
+    /// * `deviation` - Largest calibration table deviation, `None` without table
+    /// * `spread` - Spread of the trim points, `None` if the trim didn't run
+    /// * `limits` - Inclusive (min, max) limits indexed by `ProductionMetric`
+    pub fn finish(
+        &mut self,
+        supply_mv: i32,
+        deviation: Option<i32>,
+        spread: Option<i32>,
+        limits: [(i32, i32); PRODUCTION_METRICS],
+    ) -> ProductionReport {
+        let temperature =
+            (self.temp_count > 0).then(|| (self.temp_sum / self.temp_count as u64) as i32);
+        let values = [
+            Some(self.self_test as i32),
+            Some(supply_mv),
+            temperature,
+            deviation,
+            spread,
+        ];
+        let mut metrics = [MetricResult::default(); PRODUCTION_METRICS];
+        for ((metric, value), (min, max)) in metrics.iter_mut().zip(values).zip(limits) {
+            *metric = MetricResult {
+                value,
+                passed: value.is_some_and(|value| (min..=max).contains(&value)),
+            };
+        }
+        let report = ProductionReport { metrics };
+        self.running = false;
+        self.report = Some(report);
+        report
+    }
+
+    /// Aborts the test without report
+    pub fn abort(&mut self) {
+        self.running = false;
+    }
+
+    /// Returns true while the test is running
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Report of the last finished test
+    #[inline(always)]
+    pub fn report(&self) -> Option<&ProductionReport> {
+        self.report.as_ref()
+    }
+
+    /// Clears the report of the last test
+    pub fn clear(&mut self) {
+        self.report = None;
+    }
+}
+
+impl Default for ProductionTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
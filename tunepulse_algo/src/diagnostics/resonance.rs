@@ -0,0 +1,161 @@
+// Implements the resonance detector for stepper motors, monitoring speed ripple around the
+// commanded velocity and managing a list of resonant speed bands that should be skipped.
+
+// Key Features:
+// - Tracks the speed ripple envelope relative to the commanded speed.
+// - Detects resonance with a debounce time to ignore short transients.
+// - Stores up to `BANDS` resonant speed bands and moves speed setpoints out of them.
+// - Reports when extra damping should be engaged.
+
+// Detailed Operation:
+// Every tick the absolute difference between measured and commanded speed is passed through a
+// leaky average to get the ripple envelope. When the envelope exceeds the configured share of the
+// commanded speed for longer than the debounce time, the current commanded speed is stored as the
+// center of a resonant band. `skip_bands()` then shifts any requested speed that lies inside a
+// stored band to the nearest band edge, so the motor accelerates through resonant speeds instead
+// of dwelling in them, while `needs_damping()` tells the control loop to increase damping.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Detects speed resonance and keeps a table of speed bands to avoid.
+pub struct ResonanceDetector<const BANDS: usize> {
+    /// Leaky average of absolute speed error (speed ripple envelope)
+    envelope: i32,
+
+    /// Allowed ripple as share of commanded speed (0..255 = 0..100%)
+    threshold: i32,
+
+    /// Commanded speed below which detection is disabled
+    min_speed: i32,
+
+    /// Full width of a skipped speed band
+    band_width: i32,
+
+    /// Ticks ripple has to stay above threshold before a band is stored
+    debounce: u16,
+
+    /// Ticks ripple has been above threshold
+    over_time: u16,
+
+    /// Centers of detected resonant bands
+    bands: [i32; BANDS],
+
+    /// Amount of valid entries in `bands`
+    count: usize,
+
+    /// Ripple is currently above threshold
+    resonant: bool,
+}
+
+impl<const BANDS: usize> ResonanceDetector<BANDS> {
+    /// Bit shift of the envelope leaky average (time constant of 2^6 ticks)
+    const ENVELOPE_SHIFT: u32 = 6;
+
+    /// Creates a new resonance detector.
+    ///
+    /// # Arguments
+    /// * `threshold` - Allowed ripple as share of commanded speed (0..255 = 0..100%)
+    /// * `min_speed` - Commanded speed below which detection is disabled
+    /// * `band_width` - Full width of a skipped speed band
+    /// * `debounce` - Ticks ripple has to stay above threshold before a band is stored
+    pub fn new(threshold: u8, min_speed: i32, band_width: i32, debounce: u16) -> Self {
+        Self {
+            envelope: 0,
+            threshold: threshold as i32,
+            min_speed: min_speed.abs(),
+            band_width: band_width.abs(),
+            debounce,
+            over_time: 0,
+            bands: [0; BANDS],
+            count: 0,
+            resonant: false,
+        }
+    }
+
+    /// Updates the detector with commanded and measured speed, returns true while resonance is present.
+    pub fn tick(&mut self, speed_cmd: i32, speed_meas: i32) -> bool {
+        // Update ripple envelope as leaky average of absolute speed error
+        let error = speed_meas.saturating_sub(speed_cmd).saturating_abs();
+        self.envelope += (error - self.envelope) >> Self::ENVELOPE_SHIFT;
+
+        let speed_abs = speed_cmd.saturating_abs();
+        if speed_abs < self.min_speed {
+            // Ripple at low speed is dominated by encoder noise and step quantization
+            self.over_time = 0;
+            self.resonant = false;
+            return false;
+        }
+
+        // Compare ripple with the allowed share of commanded speed (i64 to avoid overflow)
+        self.resonant = (self.envelope as i64) << 8 > speed_abs as i64 * self.threshold as i64;
+        if !self.resonant {
+            self.over_time = 0;
+            return false;
+        }
+
+        self.over_time = self.over_time.saturating_add(1);
+        if self.over_time == self.debounce {
+            self.add_band(speed_cmd);
+        }
+        true
+    }
+
+    /// Stores a new resonant band centered on `speed` if it isn't covered by an existing one.
+    pub fn add_band(&mut self, speed: i32) -> bool {
+        let speed = speed.saturating_abs();
+        if self.find_band(speed).is_some() || self.count == BANDS {
+            return false;
+        }
+        self.bands[self.count] = speed;
+        self.count += 1;
+        defmt::warn!("RESONANCE: Speed band around {} will be skipped", speed);
+        true
+    }
+
+    /// Moves a speed setpoint lying inside a resonant band to the nearest band edge.
+    pub fn skip_bands(&self, speed: i32) -> i32 {
+        let speed_abs = speed.saturating_abs();
+        match self.find_band(speed_abs) {
+            Some(center) => {
+                let half = self.band_width >> 1;
+                let edge = if speed_abs < center {
+                    center - half
+                } else {
+                    center + half
+                };
+                edge * speed.signum()
+            }
+            None => speed,
+        }
+    }
+
+    /// Returns the center of the band covering the absolute `speed` if there is any.
+    fn find_band(&self, speed: i32) -> Option<i32> {
+        let half = self.band_width >> 1;
+        self.bands[..self.count]
+            .iter()
+            .copied()
+            .find(|center| (speed - center).abs() < half)
+    }
+
+    /// Returns true while ripple exceeds the threshold and extra damping should be engaged.
+    pub fn needs_damping(&self) -> bool {
+        self.resonant
+    }
+
+    /// Getter for speed ripple envelope
+    pub fn envelope(&self) -> i32 {
+        self.envelope
+    }
+
+    /// Getter for detected resonant band centers
+    pub fn bands(&self) -> &[i32] {
+        &self.bands[..self.count]
+    }
+
+    /// Forgets all detected bands
+    pub fn clear_bands(&mut self) {
+        self.count = 0;
+    }
+}
@@ -0,0 +1,105 @@
+// Implements the encoder to PWM alignment diagnostic, reporting when the encoder is sampled
+// relative to the center of the PWM period.
+
+// Key Features:
+// - Offset of every sample in nanoseconds, negative before the period center.
+// - Mean, minimum and maximum offset over windows of one second.
+// - Independent of the timer clock, the board reports the phase in fractions of the period.
+
+// Detailed Operation:
+// The sampling is meant to happen at a fixed point of the modulation, the center of the PWM
+// period where the switching noise is lowest and the measured angle matches the applied
+// voltage. Interrupt latency, task priorities or a changed timer configuration silently move
+// this point. The board timestamps the encoder chip select with the PWM counter and passes the
+// phase (i1.15 of the period) with the inputs. `tick()` converts it to nanoseconds and keeps
+// statistics; at the end of a window the mean and the spread (max - min, the jitter) are
+// published and the window restarts. A stable mean close to the intended offset with a small
+// spread confirms the sampling configuration.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Statistics of one finished window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AlignmentStats {
+    pub mean_ns: i32, // Average offset from the period center
+    pub min_ns: i32,  // Earliest sample
+    pub max_ns: i32,  // Latest sample
+}
+
+impl AlignmentStats {
+    /// Spread of the sample instant (ns)
+    #[inline(always)]
+    pub fn jitter_ns(&self) -> i32 {
+        self.max_ns - self.min_ns
+    }
+}
+
+/// Encoder sample instant relative to the PWM period center.
+pub struct SampleAlignment {
+    period_ns: i64,                // Length of the PWM period
+    window: u32,                   // Samples per statistics window
+    offset_ns: i32,                // Offset of the last sample
+    count: u32,                    // Samples in the current window
+    sum: i64,                      // Offset sum of the current window
+    min: i32,                      // Earliest sample of the current window
+    max: i32,                      // Latest sample of the current window
+    stats: Option<AlignmentStats>, // Statistics of the last finished window
+}
+
+impl SampleAlignment {
+    /// Creates the diagnostic without statistics.
+    ///
+    /// # Arguments
+    /// * `frequency` - PWM frequency, one sample per period
+    pub fn new(frequency: u16) -> Self {
+        let frequency = frequency.max(1);
+        Self {
+            period_ns: 1_000_000_000 / frequency as i64,
+            window: frequency as u32,
+            offset_ns: 0,
+            count: 0,
+            sum: 0,
+            min: i32::MAX,
+            max: i32::MIN,
+            stats: None,
+        }
+    }
+
+    /// Math call, returns true when a window finished
+    ///
+    /// # Arguments
+    /// * `phase` - Sample instant relative to the period center (i1.15 of the period)
+    pub fn tick(&mut self, phase: i16) -> bool {
+        self.offset_ns = ((phase as i64 * self.period_ns) >> 15) as i32;
+        self.sum += self.offset_ns as i64;
+        self.min = self.min.min(self.offset_ns);
+        self.max = self.max.max(self.offset_ns);
+        self.count += 1;
+        if self.count < self.window {
+            return false;
+        }
+        self.stats = Some(AlignmentStats {
+            mean_ns: (self.sum / self.count as i64) as i32,
+            min_ns: self.min,
+            max_ns: self.max,
+        });
+        self.count = 0;
+        self.sum = 0;
+        self.min = i32::MAX;
+        self.max = i32::MIN;
+        true
+    }
+
+    /// Offset of the last sample from the period center (ns)
+    #[inline(always)]
+    pub fn offset_ns(&self) -> i32 {
+        self.offset_ns
+    }
+
+    /// Statistics of the last finished window, `None` during the first second
+    #[inline(always)]
+    pub fn stats(&self) -> Option<AlignmentStats> {
+        self.stats
+    }
+}
@@ -0,0 +1,266 @@
+// Implements a step-loss detector for open-loop stepper operation based on the phase current
+// waveform, providing stall detection without any position feedback.
+
+// Key Features:
+// - Works on measured alpha-beta phase currents only, no encoder needed.
+// - Compares the current vector against the commanded one and tracks the deviation.
+// - Flags probable step loss on abrupt deviation changes and counts the events.
+
+// Detailed Operation:
+// In open-loop the driver forces a current vector at the commanded electrical angle. The rotor
+// back-EMF, which depends on the load angle, distorts the phase currents so the measured vector
+// gets a quadrature component relative to the commanded one. While the motor follows the
+// command this deviation changes slowly with load. When the rotor slips a pole the back-EMF
+// phase jumps and the deviation departs from its running baseline. The detector splits the
+// measured current into in-phase and quadrature parts, computes their ratio, and compares a fast
+// filtered ratio with a slow baseline. A difference exceeding the threshold for the debounce
+// time is reported as step loss. Below the minimum speed the back-EMF is too small to be
+// observed, so detection is suspended and the baseline is kept tracking. The owner ticks the
+// detector only while the open-loop vector drives the motor and resets it otherwise, so the
+// baseline never starts from a stale waveform.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::trigonometry as math;
+
+/// Detects probable step loss from phase current waveform deviations.
+pub struct StepLossDetector {
+    frequency: u16, // Update frequency (ticks per second)
+
+    /// Quadrature to in-phase current ratio filtered fast and slow (i1.15 as i32 for headroom)
+    ratio_fast: i32,
+    ratio_slow: i32,
+
+    /// Ratio deviation from baseline treated as step loss (i1.15, 0 - disabled)
+    threshold: i32,
+    /// Minimum commanded electrical speed (angle change per tick) for detection
+    min_speed: u16,
+    /// Ticks the deviation has to persist before step loss is reported
+    debounce: u32,
+
+    /// Commanded electrical angle of previous tick, `None` after a reset
+    prev_angle: Option<u16>,
+    /// Ticks the deviation has currently persisted
+    hits: u32,
+    /// Step loss currently detected
+    detected: bool,
+    /// Number of step loss events since last reset
+    events: u32,
+}
+
+impl StepLossDetector {
+    /// Fast filter shift (time constant of 2^n ticks)
+    const FAST_SHIFT: u32 = 3;
+    /// Baseline filter shift (time constant of 2^n ticks)
+    const SLOW_SHIFT: u32 = 9;
+
+    /// Creates a disabled detector.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            ratio_fast: 0,
+            ratio_slow: 0,
+            threshold: 0,
+            min_speed: 0,
+            debounce: 0,
+            prev_angle: None,
+            hits: 0,
+            detected: false,
+            events: 0,
+        }
+    }
+
+    /// Configures the detection.
+    ///
+    /// # Arguments
+    /// * `threshold_pct` - Ratio deviation from baseline treated as step loss (percent of the
+    ///   in-phase current, 0 - disabled)
+    /// * `min_speed_hz` - Minimum commanded electrical frequency for detection (Hz)
+    /// * `debounce_ms` - Time the deviation has to persist before step loss is reported
+    pub fn configure(&mut self, threshold_pct: u32, min_speed_hz: u32, debounce_ms: u32) {
+        self.threshold = (threshold_pct.min(100) * 32768 / 100) as i32;
+        let min_speed = min_speed_hz as u64 * 65536 / self.frequency as u64;
+        self.min_speed = min_speed.min(u16::MAX as u64) as u16;
+        self.debounce = (debounce_ms as u64 * self.frequency as u64 / 1000).max(1) as u32;
+        self.reset();
+    }
+
+    /// Restarts the waveform tracking, e.g. when the open-loop vector takes over the motor
+    pub fn reset(&mut self) {
+        self.ratio_fast = 0;
+        self.ratio_slow = 0;
+        self.prev_angle = None;
+        self.hits = 0;
+        self.detected = false;
+    }
+
+    /// Updates the detector, returns true in the tick step loss is detected.
+    ///
+    /// # Arguments
+    /// * `angle_el` - Commanded electrical angle
+    /// * `current_ab` - Measured alpha-beta phase currents
+    pub fn tick(&mut self, angle_el: u16, current_ab: (i16, i16)) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        // The first tick after a reset only seeds the speed and the filters
+        let first = self.prev_angle.is_none();
+        let prev_angle = self.prev_angle.replace(angle_el).unwrap_or(angle_el);
+        let speed = (angle_el.wrapping_sub(prev_angle) as i16).unsigned_abs();
+
+        // Split measured current into parts in-phase and in quadrature to the commanded vector
+        let (sin, cos) = math::angle2sincos(angle_el as i16);
+        let (ia, ib) = (current_ab.0 as i32, current_ab.1 as i32);
+        let i_d = (ia * sin as i32 + ib * cos as i32) >> 15;
+        let i_q = (ia * cos as i32 - ib * sin as i32) >> 15;
+
+        // Ratio is independent from current scaling, clamped to ±1.0
+        let ratio = if i_d > 0 {
+            ((i_q << 15) / i_d).clamp(-(i16::MAX as i32), i16::MAX as i32)
+        } else {
+            i16::MAX as i32 * i_q.signum() // Current vector turned by 90° or more
+        };
+
+        if first {
+            self.ratio_fast = ratio;
+            self.ratio_slow = ratio;
+            return false;
+        }
+        self.ratio_fast += (ratio - self.ratio_fast) >> Self::FAST_SHIFT;
+        self.ratio_slow += (ratio - self.ratio_slow) >> Self::SLOW_SHIFT;
+
+        if speed < self.min_speed {
+            self.hits = 0;
+            self.detected = false;
+            return false;
+        }
+
+        let deviation = (self.ratio_fast - self.ratio_slow).abs();
+        if deviation > self.threshold {
+            self.hits = self.hits.saturating_add(1);
+        } else {
+            self.hits = 0;
+            self.detected = false;
+        }
+
+        if self.hits >= self.debounce && !self.detected {
+            self.detected = true;
+            self.events = self.events.wrapping_add(1);
+            defmt::warn!("STEP LOSS: Current waveform deviation {}", deviation);
+            return true;
+        }
+        false
+    }
+
+    /// Returns true while step loss is detected
+    pub fn is_detected(&self) -> bool {
+        self.detected
+    }
+
+    /// Number of step loss events since last reset
+    pub fn events(&self) -> u32 {
+        self.events
+    }
+
+    /// Clears event counter
+    pub fn reset_events(&mut self) {
+        self.events = 0;
+    }
+
+    /// Getter for current baseline ratio (quadrature to in-phase current, i1.15)
+    pub fn baseline(&self) -> i16 {
+        self.ratio_slow as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FREQUENCY: u16 = 20_000;
+    /// Commanded electrical speed, 20 Hz at 20 kHz
+    const SPEED: u16 = 65;
+    /// Amplitude of the phase current (mA)
+    const CURRENT: i32 = 1000;
+
+    /// Current vector lagging the commanded angle by `lag` (65536 per electrical turn)
+    fn current(angle: u16, lag: u16) -> (i16, i16) {
+        let (sin, cos) = math::angle2sincos(angle.wrapping_sub(lag) as i16);
+        (
+            ((sin as i32 * CURRENT) >> 15) as i16,
+            ((cos as i32 * CURRENT) >> 15) as i16,
+        )
+    }
+
+    fn detector() -> StepLossDetector {
+        let mut detector = StepLossDetector::new(FREQUENCY);
+        detector.configure(20, 5, 2);
+        detector
+    }
+
+    /// Runs `ticks` ticks at `speed` with the current lagging by `lag`, returns the detections
+    fn run(
+        detector: &mut StepLossDetector,
+        angle: &mut u16,
+        speed: u16,
+        lag: u16,
+        ticks: u32,
+    ) -> u32 {
+        let mut detections = 0;
+        for _ in 0..ticks {
+            *angle = angle.wrapping_add(speed);
+            detections += detector.tick(*angle, current(*angle, lag)) as u32;
+        }
+        detections
+    }
+
+    /// Load angle of the synchronous motor, 10° el
+    const LAG: u16 = 1820;
+    /// Back-EMF phase jump of a pole slip, the current turns by 60° el
+    const SLIP: u16 = 10923;
+
+    #[test]
+    fn steady_load_is_not_step_loss() {
+        let mut detector = detector();
+        let mut angle = 0;
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG, 20_000), 0);
+        // A slow load change stays with the baseline
+        for lag in (LAG..LAG + 2000).step_by(100) {
+            assert_eq!(run(&mut detector, &mut angle, SPEED, lag, 2_000), 0);
+        }
+        assert_eq!(detector.events(), 0);
+    }
+
+    #[test]
+    fn pole_slip_is_detected() {
+        let mut detector = detector();
+        let mut angle = 0;
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG, 20_000), 0);
+        // Pole slip: the current waveform jumps away from its baseline
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG + SLIP, 200), 1);
+        assert!(detector.is_detected());
+        assert_eq!(detector.events(), 1);
+        // Settled at the new waveform the detection clears and the next slip counts again
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG + SLIP, 20_000), 0);
+        assert!(!detector.is_detected());
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG, 200), 1);
+        assert_eq!(detector.events(), 2);
+    }
+
+    #[test]
+    fn slip_below_min_speed_or_disabled_is_ignored() {
+        let mut detector = detector();
+        let mut angle = 0;
+        // 1 Hz is below the 5 Hz minimum speed
+        assert_eq!(run(&mut detector, &mut angle, 3, LAG, 20_000), 0);
+        assert_eq!(run(&mut detector, &mut angle, 3, LAG + SLIP, 200), 0);
+
+        let mut detector = StepLossDetector::new(FREQUENCY);
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG, 20_000), 0);
+        assert_eq!(run(&mut detector, &mut angle, SPEED, LAG + SLIP, 200), 0);
+    }
+}
@@ -0,0 +1,278 @@
+// Implements the per-unit factory data block: serial number, hardware revision, analog trim
+// constants and calibration date, written once during production.
+
+// Key Features:
+// - Own flash area, separate from the parameters, so it survives parameter resets and restores.
+// - Write-once: a valid block is never overwritten, only an interrupted write is repeated.
+// - Paged encoding fitting the 8 byte protocol frames, staged by the host before the write.
+// - Programmed as background work admitted by the scheduler, like the parameter image.
+
+// Detailed Operation:
+// The production station writes the pages into the staging block of the controller and issues
+// the write command. The controller rejects it if the unit already holds factory data,
+// otherwise the owner of the flash hands the block to `FactoryWriter`. The writer checks the
+// area first: a valid block ends the write with `FlashError::WriteProtected`, a partially
+// written block (power loss during production) is erased, an erased area is programmed
+// directly. The block is read back and compared before the write reports success.
+// Image layout (little endian, 32 bytes, 4 double words):
+// - 0:  [magic (u32), layout version (u16), payload length (u16)]
+// - 8:  [serial number (u32), calibration date (u32, unix time)]
+// - 16: [hardware revision (u16), supply trim (i16), temperature offset (i16), 0 (u16)]
+// - 24: [CRC-32 of bytes 0..24 (u32), 0 (u32)]
+// Page layouts (6 byte payload following the frame type and page number):
+// - Page 0: [serial number (u32 LE), hardware revision (u16 LE)]
+// - Page 1: [calibration date (u32 LE), supply trim (i16 LE)]
+// - Page 2: [temperature offset (i16 LE), 0, 0, 0, 0]
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::device_info::DEVICE_INFO_PAGE_SIZE;
+use crate::params::writer::{crc32, FlashError, FlashOps, DOUBLE_WORD};
+use crate::scheduler::{Budget, Poll};
+
+/// Number of pages of the encoded factory data
+pub const FACTORY_PAGES: u8 = 3;
+
+/// Size of the stored factory data image
+pub const FACTORY_IMAGE_SIZE: usize = 32;
+
+/// Marks a factory data image ("TPFD")
+const FACTORY_MAGIC: u32 = 0x4446_5054;
+
+/// Revision of the image layout
+const FACTORY_VERSION: u16 = 1;
+
+/// Size of the payload following the image header
+const FACTORY_PAYLOAD_SIZE: u16 = 16;
+
+/// Offset of the CRC in the image
+const CRC_OFFSET: usize = 24;
+
+/// Per-unit data written during production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FactoryData {
+    pub serial: u32,       // Serial number
+    pub date: u32,         // Calibration date (unix time)
+    pub hardware_rev: u16, // Hardware revision of the board
+    pub supply_trim: i16,  // Supply voltage measurement gain correction (0.01 %)
+    pub temp_offset: i16,  // Temperature sensor offset (raw ADC)
+}
+
+impl FactoryData {
+    /// Encodes the block into its flash image
+    pub fn encode(&self) -> [u8; FACTORY_IMAGE_SIZE] {
+        let mut image = [0; FACTORY_IMAGE_SIZE];
+        image[0..4].copy_from_slice(&FACTORY_MAGIC.to_le_bytes());
+        image[4..6].copy_from_slice(&FACTORY_VERSION.to_le_bytes());
+        image[6..8].copy_from_slice(&FACTORY_PAYLOAD_SIZE.to_le_bytes());
+        image[8..12].copy_from_slice(&self.serial.to_le_bytes());
+        image[12..16].copy_from_slice(&self.date.to_le_bytes());
+        image[16..18].copy_from_slice(&self.hardware_rev.to_le_bytes());
+        image[18..20].copy_from_slice(&self.supply_trim.to_le_bytes());
+        image[20..22].copy_from_slice(&self.temp_offset.to_le_bytes());
+        let crc = crc32(&image[..CRC_OFFSET]);
+        image[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        image
+    }
+
+    /// Decodes a flash image, `None` if the area is blank, foreign or corrupted
+    pub fn decode(image: &[u8]) -> Option<Self> {
+        let image = image.get(..FACTORY_IMAGE_SIZE)?;
+        let u16_at = |offset: usize| u16::from_le_bytes([image[offset], image[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                image[offset],
+                image[offset + 1],
+                image[offset + 2],
+                image[offset + 3],
+            ])
+        };
+        if u32_at(0) != FACTORY_MAGIC
+            || u16_at(4) != FACTORY_VERSION
+            || u16_at(6) != FACTORY_PAYLOAD_SIZE
+            || crc32(&image[..CRC_OFFSET]) != u32_at(CRC_OFFSET)
+        {
+            return None;
+        }
+        Some(Self {
+            serial: u32_at(8),
+            date: u32_at(12),
+            hardware_rev: u16_at(16),
+            supply_trim: u16_at(18) as i16,
+            temp_offset: u16_at(20) as i16,
+        })
+    }
+
+    /// Returns the payload of one page, `None` for pages past the end
+    pub fn page(&self, page: u8) -> Option<[u8; DEVICE_INFO_PAGE_SIZE]> {
+        let mut payload = [0; DEVICE_INFO_PAGE_SIZE];
+        match page {
+            0 => {
+                payload[0..4].copy_from_slice(&self.serial.to_le_bytes());
+                payload[4..6].copy_from_slice(&self.hardware_rev.to_le_bytes());
+            }
+            1 => {
+                payload[0..4].copy_from_slice(&self.date.to_le_bytes());
+                payload[4..6].copy_from_slice(&self.supply_trim.to_le_bytes());
+            }
+            2 => payload[0..2].copy_from_slice(&self.temp_offset.to_le_bytes()),
+            _ => return None,
+        }
+        Some(payload)
+    }
+
+    /// Updates the fields of one page, returns false for pages past the end
+    pub fn set_page(&mut self, page: u8, payload: [u8; DEVICE_INFO_PAGE_SIZE]) -> bool {
+        let [b0, b1, b2, b3, b4, b5] = payload;
+        match page {
+            0 => {
+                self.serial = u32::from_le_bytes([b0, b1, b2, b3]);
+                self.hardware_rev = u16::from_le_bytes([b4, b5]);
+            }
+            1 => {
+                self.date = u32::from_le_bytes([b0, b1, b2, b3]);
+                self.supply_trim = i16::from_le_bytes([b4, b5]);
+            }
+            2 => self.temp_offset = i16::from_le_bytes([b0, b1]),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Prints the block to the log
+    pub fn log(&self) {
+        defmt::info!(
+            "FACTORY: Serial {}, hardware rev {}, calibrated {} (unix)",
+            self.serial,
+            self.hardware_rev,
+            self.date
+        );
+    }
+}
+
+/// Progress of a factory data write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteState {
+    Idle,
+    Check,
+    Erase,
+    Program { offset: usize },
+    Verify,
+}
+
+/// Programs the factory data block into its erased flash area, once.
+pub struct FactoryWriter {
+    image: [u8; FACTORY_IMAGE_SIZE],        // Image being written
+    state: WriteState,                      // Current step
+    result: Option<Result<(), FlashError>>, // Outcome of the last finished write
+}
+
+impl FactoryWriter {
+    pub const fn new() -> Self {
+        Self {
+            image: [0; FACTORY_IMAGE_SIZE],
+            state: WriteState::Idle,
+            result: None,
+        }
+    }
+
+    /// Starts writing `data`, returns false if a write is running
+    pub fn start(&mut self, data: &FactoryData) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        self.image = data.encode();
+        self.result = None;
+        self.state = WriteState::Check;
+        true
+    }
+
+    /// Returns true while a write is in progress
+    pub fn is_busy(&self) -> bool {
+        self.state != WriteState::Idle
+    }
+
+    /// Returns the outcome of the last finished write, once
+    pub fn take_result(&mut self) -> Option<Result<(), FlashError>> {
+        self.result.take()
+    }
+
+    /// Runs the write steps admitted by the budget.
+    ///
+    /// # Arguments
+    /// * `flash` - Factory data area, offsets relative to its start
+    /// * `budget` - Time and stall budget of this poll
+    pub fn poll<F: FlashOps>(&mut self, flash: &mut F, budget: &Budget) -> Poll {
+        while !budget.expired() {
+            match self.state {
+                WriteState::Idle => return Poll::Idle,
+                WriteState::Check => {
+                    let mut stored = [0; FACTORY_IMAGE_SIZE];
+                    if let Err(error) = flash.read(0, &mut stored) {
+                        self.finish(Err(error));
+                    } else if FactoryData::decode(&stored).is_some() {
+                        self.finish(Err(FlashError::WriteProtected)); // Written once only
+                    } else if stored.iter().all(|byte| *byte == 0xFF) {
+                        self.state = WriteState::Program { offset: 0 };
+                    } else {
+                        self.state = WriteState::Erase; // Interrupted write, no valid data
+                    }
+                }
+                WriteState::Erase => {
+                    if !budget.allow_stall(F::ERASE_CYCLES) {
+                        return Poll::Pending;
+                    }
+                    match flash.erase_page(0) {
+                        Ok(()) => self.state = WriteState::Program { offset: 0 },
+                        Err(error) => self.finish(Err(error)),
+                    }
+                }
+                WriteState::Program { offset } if offset >= FACTORY_IMAGE_SIZE => {
+                    self.state = WriteState::Verify;
+                }
+                WriteState::Program { offset } => {
+                    if !budget.allow_stall(F::PROGRAM_CYCLES) {
+                        return Poll::Pending;
+                    }
+                    let mut word = [0; DOUBLE_WORD];
+                    word.copy_from_slice(&self.image[offset..offset + DOUBLE_WORD]);
+                    match flash.program(offset, word) {
+                        Ok(()) => {
+                            self.state = WriteState::Program {
+                                offset: offset + DOUBLE_WORD,
+                            }
+                        }
+                        Err(error) => self.finish(Err(error)),
+                    }
+                }
+                WriteState::Verify => {
+                    let mut stored = [0; FACTORY_IMAGE_SIZE];
+                    let result = flash.read(0, &mut stored).and_then(|_| {
+                        if stored == self.image {
+                            Ok(())
+                        } else {
+                            Err(FlashError::Verify)
+                        }
+                    });
+                    self.finish(result);
+                }
+            }
+        }
+        Poll::Pending
+    }
+
+    fn finish(&mut self, result: Result<(), FlashError>) {
+        if let Err(error) = result {
+            defmt::warn!("FACTORY: Write failed ({})", error);
+        }
+        self.result = Some(result);
+        self.state = WriteState::Idle;
+    }
+}
+
+impl Default for FactoryWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,69 @@
+// Implements fault codes reported by the controller.
+
+// Key Features:
+// - Stable numeric codes shared by LED indication, events and host tools.
+// - Codes grouped into classes, every class has its own configurable stop reaction.
+
+// Detailed Operation:
+// Whenever the controller enters the error state it records the reason as a `FaultCode`. The
+// numeric value is reported in fault events and blinked by the status LED, so the codes must
+// stay stable once released. Code 0 means no fault.
+// Faults of one class call for the same reaction: a sensor fault leaves no reliable rotor
+// angle, a supply fault limits what the bridge can still do, a motion fault happens with a
+// healthy drive that only has to stop the axis. `FaultReaction` is selected per class.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Reason of the controller error state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FaultCode {
+    /// No fault present
+    None = 0,
+    /// Encoder calibration failed
+    CalibrationFailed = 1,
+    /// Supply voltage too low for operation
+    SupplyUndervoltage = 2,
+    /// Supply voltage exceeds the allowed maximum
+    SupplyOvervoltage = 3,
+    /// Motor stalled or lost synchronism
+    Stall = 4,
+    /// Master position of the electronic gearing stopped updating
+    MasterLost = 5,
+    /// Encoder calibration aborted, the axis load skewed the table
+    CalibrationLoaded = 6,
+    /// Encoder calibration stage timed out, the motor doesn't follow (e.g. open phase)
+    CalibrationTimeout = 7,
+    /// Position error stayed outside the following error window for the timeout
+    FollowingError = 8,
+}
+
+/// Group of faults sharing a stop reaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultClass {
+    /// Rotor angle measurement is unreliable
+    Sensor,
+    /// Supply voltage out of the operating range
+    Supply,
+    /// Axis motion failed with a healthy drive
+    Motion,
+}
+
+impl FaultCode {
+    /// Class of the fault, `None` for `FaultCode::None`
+    pub fn class(self) -> Option<FaultClass> {
+        match self {
+            FaultCode::None => None,
+            FaultCode::CalibrationFailed
+            | FaultCode::CalibrationLoaded
+            | FaultCode::CalibrationTimeout => Some(FaultClass::Sensor),
+            FaultCode::SupplyUndervoltage | FaultCode::SupplyOvervoltage => {
+                Some(FaultClass::Supply)
+            }
+            FaultCode::Stall | FaultCode::MasterLost | FaultCode::FollowingError => {
+                Some(FaultClass::Motion)
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// Implements the stop behaviour of the drive after a fault was raised.
+
+// Key Features:
+// - Four reactions: short brake, freewheel, torque ramp-down and open loop deceleration.
+// - Reaction selected per fault class by the owner.
+// - Tracks the electrical speed while running, open loop deceleration starts from it.
+
+// Detailed Operation:
+// Different faults warrant different stops. A shorted winding or a bridge fault should float
+// the outputs at once (freewheel). An overheated or overloaded drive still commutates
+// correctly and brings the torque down along a ramp, so a suspended load is not released in a
+// single tick. With a lost encoder the rotor angle is unknown: the commutation continues open
+// loop from the last electrical speed, which decays linearly to zero over the ramp time, and
+// the rotor follows the rotating current vector like a stepper in open loop.
+// `track()` is called every tick of normal operation with the commutation angle and keeps a
+// smoothed electrical speed. `start()` latches the reaction, `tick()` then returns the angle
+// and torque command to apply. Once a ramp finished the windings stay shorted (zero voltage),
+// which also is the immediate short brake reaction. `bridge_enabled()` tells the board
+// whether the bridge outputs have to be switched off.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Stop behaviour applied when a fault is raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FaultReaction {
+    /// Zero voltage, the windings are shorted and brake the motor
+    ShortBrake = 0,
+    /// Bridge outputs disabled, the motor coasts
+    Freewheel = 1,
+    /// Torque command ramps down to zero with closed loop commutation, then short brake
+    RampDown = 2,
+    /// Open loop commutation decelerating to standstill, then short brake
+    OpenLoopDecel = 3,
+}
+
+impl FaultReaction {
+    /// Converts raw reaction, unknown values select short brake
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => FaultReaction::Freewheel,
+            2 => FaultReaction::RampDown,
+            3 => FaultReaction::OpenLoopDecel,
+            _ => FaultReaction::ShortBrake,
+        }
+    }
+}
+
+/// Drives the motor to rest after a fault.
+pub struct FaultStop {
+    frequency: u16,                  // Update frequency (ticks per second)
+    ramp: u32,                       // Length of the ramps (ticks, at least 1)
+    reaction: Option<FaultReaction>, // Active reaction, `None` without fault
+    remaining: u32,                  // Ticks left of the ramp
+    current: i16,                    // Torque command at the fault (mA)
+    angle: u16,                      // Commutation angle of the previous tick
+    el_speed: i32,                   // Smoothed electrical speed (angle counts / 256 per tick)
+    open_angle: u32,                 // Open loop angle (u16.16)
+}
+
+impl FaultStop {
+    /// Creates an inactive fault stop.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `ramp_ms` - Length of the torque and speed ramps
+    pub fn new(frequency: u16, ramp_ms: u32) -> Self {
+        let mut stop = Self {
+            frequency,
+            ramp: 1,
+            reaction: None,
+            remaining: 0,
+            current: 0,
+            angle: 0,
+            el_speed: 0,
+            open_angle: 0,
+        };
+        stop.set_ramp(ramp_ms);
+        stop
+    }
+
+    /// Sets the length of the ramps, a running ramp keeps its length
+    pub fn set_ramp(&mut self, ramp_ms: u32) {
+        let ticks = ramp_ms as u64 * self.frequency as u64 / 1000;
+        self.ramp = (ticks.min(u32::MAX as u64) as u32).max(1);
+    }
+
+    /// Follows the commutation angle of normal operation, called every tick
+    pub fn track(&mut self, angle: u16) {
+        let step = (angle.wrapping_sub(self.angle) as i16 as i32) << 8;
+        self.el_speed += (step - self.el_speed) >> 4;
+        self.angle = angle;
+    }
+
+    /// Latches the reaction to a fault.
+    ///
+    /// # Arguments
+    /// * `reaction` - Stop behaviour
+    /// * `current` - Torque command at the fault (mA)
+    pub fn start(&mut self, reaction: FaultReaction, current: i16) {
+        self.reaction = Some(reaction);
+        self.remaining = self.ramp;
+        self.current = current;
+        self.open_angle = (self.angle as u32) << 16;
+    }
+
+    /// Releases the reaction once the fault was cleared
+    pub fn stop(&mut self) {
+        self.reaction = None;
+        self.el_speed = 0;
+    }
+
+    /// Active reaction, `None` without fault
+    #[inline(always)]
+    pub fn reaction(&self) -> Option<FaultReaction> {
+        self.reaction
+    }
+
+    /// Returns false while the bridge outputs have to be switched off
+    #[inline(always)]
+    pub fn bridge_enabled(&self) -> bool {
+        self.reaction != Some(FaultReaction::Freewheel)
+    }
+
+    /// Returns the commutation angle and torque command (mA) of this tick.
+    ///
+    /// # Arguments
+    /// * `angle` - Commutation angle from the encoder, used by the torque ramp
+    pub fn tick(&mut self, angle: u16) -> (u16, i16) {
+        let remaining = self.remaining;
+        self.remaining = self.remaining.saturating_sub(1);
+        match self.reaction {
+            Some(FaultReaction::RampDown) if remaining > 0 => {
+                let current = self.current as i64 * remaining as i64 / self.ramp as i64;
+                (angle, current as i16)
+            }
+            Some(FaultReaction::OpenLoopDecel) if remaining > 0 => {
+                let speed = (self.el_speed as i64 * remaining as i64 / self.ramp as i64) << 8;
+                self.open_angle = self.open_angle.wrapping_add(speed as i32 as u32);
+                (
+                    (self.open_angle >> 16) as u16,
+                    self.current.saturating_abs(),
+                )
+            }
+            // Short brake, freewheel and finished ramps apply no voltage
+            _ => (angle, 0),
+        }
+    }
+}
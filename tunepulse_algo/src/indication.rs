@@ -0,0 +1,141 @@
+// Implements status indication through the RGB LED.
+
+// Key Features:
+// - Maps driver status, fault code and enable state to colors and blink patterns.
+// - Fault codes are blinked as a countable number of red flashes.
+// - Production test verdict shown until the drive is enabled.
+// - Hardware independent, produces the LED color for the LED driver.
+
+// Detailed Operation:
+// The indicator is updated from a low priority task with the time elapsed since the previous
+// update. It keeps a phase counter and evaluates the pattern of the current state:
+// - Calibrating: blue pulse (on 250ms, off 250ms)
+// - Ready and enabled: solid green
+// - Ready and disabled: short green flash every 2s
+// - Error: red flashes, one per fault code unit (200ms on, 300ms off), followed by 1.5s pause
+// - Production test passed: green blink (on 500ms, off 500ms), failed: fast red blink (on 100ms,
+//   off 100ms), only while ready and disabled
+// The pattern restarts whenever the displayed state changes, so fault codes are always blinked
+// from the first flash.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::fault::FaultCode;
+use crate::motor_driver::DriverStatus;
+
+/// Color of the RGB LED.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LedColor {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+}
+
+impl LedColor {
+    pub const OFF: LedColor = LedColor::rgb(false, false, false);
+    pub const RED: LedColor = LedColor::rgb(true, false, false);
+    pub const GREEN: LedColor = LedColor::rgb(false, true, false);
+    pub const BLUE: LedColor = LedColor::rgb(false, false, true);
+
+    const fn rgb(red: bool, green: bool, blue: bool) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+/// State displayed by the status LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicationState {
+    pub status: DriverStatus,
+    pub fault: FaultCode,
+    pub enabled: bool,
+    pub production: Option<bool>, // Verdict of the last production test, `None` if not run
+}
+
+/// Status LED pattern generator.
+pub struct StatusIndicator {
+    state: Option<IndicationState>, // Displayed state
+    phase_ms: u32,                  // Time since start of the pattern
+}
+
+impl StatusIndicator {
+    const PULSE_MS: u32 = 250;
+    const IDLE_PERIOD_MS: u32 = 2000;
+    const IDLE_FLASH_MS: u32 = 100;
+    const PASS_BLINK_MS: u32 = 500;
+    const FAIL_BLINK_MS: u32 = 100;
+    const CODE_ON_MS: u32 = 200;
+    const CODE_OFF_MS: u32 = 300;
+    const CODE_PAUSE_MS: u32 = 1500;
+
+    pub const fn new() -> Self {
+        Self {
+            state: None,
+            phase_ms: 0,
+        }
+    }
+
+    /// Updates the pattern, returns the LED color.
+    ///
+    /// # Arguments
+    /// * `state` - Current controller state
+    /// * `elapsed_ms` - Time since previous update
+    pub fn tick(&mut self, state: IndicationState, elapsed_ms: u32) -> LedColor {
+        if self.state != Some(state) {
+            self.state = Some(state);
+            self.phase_ms = 0;
+        } else {
+            self.phase_ms = self.phase_ms.wrapping_add(elapsed_ms);
+        }
+
+        match state.status {
+            DriverStatus::Calibrating => {
+                let on = (self.phase_ms / Self::PULSE_MS) & 1 == 0;
+                if on {
+                    LedColor::BLUE
+                } else {
+                    LedColor::OFF
+                }
+            }
+            DriverStatus::Ready if state.enabled => LedColor::GREEN,
+            DriverStatus::Ready if state.production.is_some() => {
+                let (color, blink) = match state.production {
+                    Some(true) => (LedColor::GREEN, Self::PASS_BLINK_MS),
+                    _ => (LedColor::RED, Self::FAIL_BLINK_MS),
+                };
+                if (self.phase_ms / blink) & 1 == 0 {
+                    color
+                } else {
+                    LedColor::OFF
+                }
+            }
+            DriverStatus::Ready => {
+                if self.phase_ms % Self::IDLE_PERIOD_MS < Self::IDLE_FLASH_MS {
+                    LedColor::GREEN
+                } else {
+                    LedColor::OFF
+                }
+            }
+            DriverStatus::Error => Self::code_flash(state.fault as u32, self.phase_ms),
+        }
+    }
+
+    /// Red flash pattern counting the fault code
+    fn code_flash(code: u32, phase_ms: u32) -> LedColor {
+        let code = code.max(1); // Unknown fault reason still blinks once
+        let flash = Self::CODE_ON_MS + Self::CODE_OFF_MS;
+        let period = code * flash + Self::CODE_PAUSE_MS;
+        let phase = phase_ms % period;
+        if phase < code * flash && phase % flash < Self::CODE_ON_MS {
+            LedColor::RED
+        } else {
+            LedColor::OFF
+        }
+    }
+}
+
+impl Default for StatusIndicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
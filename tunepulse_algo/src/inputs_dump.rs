@@ -39,6 +39,12 @@ pub struct DataInputs {
 
     /// Raw angle measurement.
     pub angle_raw: u16,
+
+    /// Encoder sample instant relative to the PWM period center (i1.15 of the period).
+    pub sample_phase: i16,
+
+    /// Second angle read of the same sample, equal to `angle_raw` with single reads.
+    pub angle_check: u16,
 }
 
 impl DataInputs {
@@ -48,6 +54,8 @@ impl DataInputs {
             temper_adc: 0,
             currnt_adc: [0; 4],
             angle_raw: 0,
+            sample_phase: 0,
+            angle_check: 0,
         }
     }
 }
@@ -69,6 +77,12 @@ pub enum DataInputsBit {
     /// Mask for the angle field bit.
     ANGLE = 1 << 3,
 
+    /// Mask for the sample phase field bit.
+    PHASE = 1 << 4,
+
+    /// Mask for the angle check field bit.
+    CHECK = 1 << 5,
+
     /// Mask for the lock bit at the most significant bit.
     LOCK = 1 << 31,
 }
@@ -168,6 +182,22 @@ impl<const MANDATORY_FIELDS: u32> InputsDump<MANDATORY_FIELDS> {
         self.check_fill(idx); // Check if buffer filling is complete or if we need to switch
     }
 
+    /// Sets the `sample_phase` field in the currently updating buffer.
+    pub fn set_sample_phase(&mut self, value: i16) {
+        let idx = self.idx2update; // Get the currently updating buffer index
+        self.buffers[idx].sample_phase = value; // Store the sample instant
+        self.clear_field_bit(idx, DataInputsBit::PHASE); // Mark the phase field as filled
+        self.check_fill(idx); // Check if buffer filling is complete or if we need to switch
+    }
+
+    /// Sets the `angle_check` field in the currently updating buffer.
+    pub fn set_angle_check(&mut self, value: u16) {
+        let idx = self.idx2update; // Get the currently updating buffer index
+        self.buffers[idx].angle_check = value; // Store the second angle read
+        self.clear_field_bit(idx, DataInputsBit::CHECK); // Mark the check field as filled
+        self.check_fill(idx); // Check if buffer filling is complete or if we need to switch
+    }
+
     /// Checks if the data has been updated since the last read.
     #[inline(always)]
     pub fn is_updated(&self) -> bool {
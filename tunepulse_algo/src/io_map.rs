@@ -0,0 +1,185 @@
+// Implements runtime mapping of spare digital pins to controller functions.
+
+// Key Features:
+// - Each pin can be assigned an input or output function at runtime.
+// - Optional per pin level inversion.
+// - Hardware independent: works on pin level bit masks provided by the GPIO driver.
+
+// Detailed Operation:
+// Pin functions are configured through the parameter registry, one parameter per pin holding
+// the function code in the low byte and the inversion flag in bit 8. Each tick the GPIO driver
+// reads raw pin levels, `decode()` converts them into active input functions and `encode()`
+// converts the controller outputs into raw levels to be written back. Pins mapped to an input
+// function are reported by `input_mask()` so the driver can configure them as inputs.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+pub use tunepulse_params::IO_INVERT;
+
+/// Functions which can be assigned to a digital pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum IoFunction {
+    /// Pin is not used
+    None = 0,
+    /// Input: drive is enabled while active
+    EnableInput = 1,
+    /// Input: rising edge starts the stored motion sequence
+    SequenceTrigger = 2,
+    /// Output: active while a fault is present
+    FaultOutput = 3,
+    /// Output: active while the axis is in position
+    InPositionOutput = 4,
+    /// Output: active while the holding brake has to be released
+    BrakeReleaseOutput = 5,
+    /// Input: active edge latches the position (touch probe, registration mark)
+    CaptureInput = 6,
+    /// Output: driven by the position compare (pulse or toggle at programmed positions)
+    CompareOutput = 7,
+    /// Output: active while the speed is within the velocity window of the velocity setpoint
+    AtVelocityOutput = 8,
+    /// Output: active while the axis is at zero speed
+    ZeroSpeedOutput = 9,
+}
+
+impl IoFunction {
+    /// Converts raw function code, `None` for unknown codes
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(IoFunction::None),
+            1 => Some(IoFunction::EnableInput),
+            2 => Some(IoFunction::SequenceTrigger),
+            3 => Some(IoFunction::FaultOutput),
+            4 => Some(IoFunction::InPositionOutput),
+            5 => Some(IoFunction::BrakeReleaseOutput),
+            6 => Some(IoFunction::CaptureInput),
+            7 => Some(IoFunction::CompareOutput),
+            8 => Some(IoFunction::AtVelocityOutput),
+            9 => Some(IoFunction::ZeroSpeedOutput),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the function reads the pin
+    pub fn is_input(self) -> bool {
+        matches!(
+            self,
+            IoFunction::EnableInput | IoFunction::SequenceTrigger | IoFunction::CaptureInput
+        )
+    }
+}
+
+/// Active input functions decoded from pin levels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoInputs {
+    /// Enable input active, `None` if no pin is mapped to it
+    pub enable: Option<bool>,
+    /// Sequence trigger rising edge detected
+    pub trigger: bool,
+    /// Capture input active edge detected
+    pub capture: bool,
+}
+
+/// Controller outputs to be written to pins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoOutputs {
+    pub fault: bool,
+    pub in_position: bool,
+    pub at_velocity: bool,
+    pub zero_speed: bool,
+    pub brake_release: bool,
+    pub compare: bool,
+}
+
+/// Mapping of `PINS` digital pins to functions.
+pub struct IoMap<const PINS: usize> {
+    functions: [IoFunction; PINS], // Function of each pin
+    inverted: u32,                 // Inverted pins, bit per pin
+    prev_levels: u32,              // Active levels of previous decode for edge detection
+}
+
+impl<const PINS: usize> IoMap<PINS> {
+    /// Creates mapping with all pins unused.
+    pub const fn new() -> Self {
+        Self {
+            functions: [IoFunction::None; PINS],
+            inverted: 0,
+            prev_levels: 0,
+        }
+    }
+
+    /// Configures pin from a configuration word (function code | `IO_INVERT`).
+    ///
+    /// Returns false if the pin or function doesn't exist.
+    pub fn configure(&mut self, pin: usize, config: u32) -> bool {
+        let function = match IoFunction::from_raw(config as u8) {
+            Some(function) if pin < PINS => function,
+            _ => return false,
+        };
+        self.functions[pin] = function;
+        if config & IO_INVERT != 0 {
+            self.inverted |= 1 << pin;
+        } else {
+            self.inverted &= !(1 << pin);
+        }
+        true
+    }
+
+    /// Decodes raw pin levels into active input functions
+    pub fn decode(&mut self, levels: u32) -> IoInputs {
+        let levels = levels ^ self.inverted;
+        let mut inputs = IoInputs::default();
+        for (pin, function) in self.functions.iter().enumerate() {
+            let active = levels & (1 << pin) != 0;
+            match function {
+                IoFunction::EnableInput => {
+                    // Several enable pins have to be active all together
+                    inputs.enable = Some(inputs.enable.unwrap_or(true) && active);
+                }
+                IoFunction::SequenceTrigger => {
+                    inputs.trigger |= active && self.prev_levels & (1 << pin) == 0;
+                }
+                IoFunction::CaptureInput => {
+                    inputs.capture |= active && self.prev_levels & (1 << pin) == 0;
+                }
+                _ => {}
+            }
+        }
+        self.prev_levels = levels;
+        inputs
+    }
+
+    /// Encodes controller outputs into raw pin levels
+    pub fn encode(&self, outputs: IoOutputs) -> u32 {
+        let mut levels = 0;
+        for (pin, function) in self.functions.iter().enumerate() {
+            let active = match function {
+                IoFunction::FaultOutput => outputs.fault,
+                IoFunction::InPositionOutput => outputs.in_position,
+                IoFunction::AtVelocityOutput => outputs.at_velocity,
+                IoFunction::ZeroSpeedOutput => outputs.zero_speed,
+                IoFunction::BrakeReleaseOutput => outputs.brake_release,
+                IoFunction::CompareOutput => outputs.compare,
+                _ => false,
+            };
+            levels |= (active as u32) << pin;
+        }
+        levels ^ (self.inverted & !self.input_mask())
+    }
+
+    /// Pins mapped to input functions, bit per pin
+    pub fn input_mask(&self) -> u32 {
+        let mut mask = 0;
+        for (pin, function) in self.functions.iter().enumerate() {
+            mask |= (function.is_input() as u32) << pin;
+        }
+        mask
+    }
+}
+
+impl<const PINS: usize> Default for IoMap<PINS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,51 +3,286 @@
 pub mod inputs_dump;
 use inputs_dump::DataInputs;
 
+#[cfg(any(test, feature = "math-float"))]
+extern crate std;
+#[cfg(any(test, feature = "math-float"))]
+pub mod math_float;
 pub mod math_integer;
 pub mod motor_driver;
 
 pub mod analog;
+pub mod brake;
+pub mod capture;
+pub mod compare;
+pub mod device_info;
+pub mod diagnostics;
+pub mod factory_data;
+pub mod fault;
+pub mod fault_reaction;
+pub mod indication;
+pub mod io_map;
+pub mod params;
+pub mod protocol;
+pub mod scheduler;
+pub mod scope;
+pub mod sensorless;
+#[cfg(feature = "sequencer")]
+pub mod sequence;
+pub mod setpoint;
+#[cfg(feature = "wizard")]
+pub mod wizard;
 
 use defmt_rtt as _; // Use the defmt_rtt crate for logging via RTT (Real-Time Transfer)
 
+use motor_driver::calibration::inertia::InertiaIdentifier;
+use motor_driver::calibration::load_check::LoadVerdict;
+#[cfg(feature = "ripple-test")]
+use motor_driver::calibration::torque_ripple::TorqueRipple;
+use motor_driver::calibration::{CalibrationError, CalibrationMetric, CAL_TABLE_SIZE};
+use motor_driver::driver_pwm::beeper::Melody;
+use motor_driver::driver_pwm::linearization::{DutyLinearizer, LINEARIZATION_POINTS};
+use motor_driver::presets::MotorPreset;
 use motor_driver::{
-    AngleCalibrator, ControlMode, DriverPWM, DriverStatus, Motor, MotorDriver, MotorType,
-    PhasePattern,
+    AngleCalibrator, CalibrationStage, ControlMode, DriverPWM, DriverState, DriverStatus, Motor,
+    MotorDriver, MotorType, PhasePattern, ReconfigError,
 };
 
-use crate::math_integer::filters::lpf::FilterLPF;
+use crate::math_integer::controllers::damping::ActiveDamping;
+use crate::math_integer::controllers::disturbance::DisturbanceObserver;
+use crate::math_integer::controllers::friction::{FrictionFeedforward, FrictionParams};
+use crate::math_integer::filters::alpha_schedule::AlphaSchedule;
+use crate::math_integer::filters::lpf::{FilterLPF, FilterOrder};
+use crate::math_integer::motion::in_position::InPosition;
 use crate::math_integer::motion::position_integrator::Position;
+use crate::math_integer::motion::speed_estimator::{SpeedEstimator, SpeedUnit};
+use crate::math_integer::motion::encoder_seed::EncoderSeed;
+use crate::math_integer::motion::gearing::{ElectronicGear, StepInput};
+use crate::math_integer::motion::jog::{Jog, JogDirection};
+use crate::math_integer::motion::standstill::Standstill;
+use crate::math_integer::motion::speed_window::SpeedWindow;
+#[cfg(feature = "tuning")]
+use crate::math_integer::signals::frequency_response::{AnalyzerStep, FrequencyResponse};
+#[cfg(feature = "tuning")]
+use crate::math_integer::signals::generator::{InjectionPoint, SignalGenerator, Waveform};
+#[cfg(feature = "tuning")]
+use crate::math_integer::signals::step_response::StepResponse;
+use crate::math_integer::tick_rate::{self, RUNTIME};
 
+use analog::current_blanking::CurrentBlanking;
+use analog::current_sense::CurrentSense;
+use analog::supply_monitor::{SupplyCheck, SupplyMonitor};
+use analog::supply_startup::SupplyStartup;
 use analog::supply_voltage::SupplyVoltage;
+use brake::BrakeControl;
+use capture::{CaptureMode, PositionCapture};
+use compare::{CompareAction, CompareDirection, PositionCompare};
+use device_info::{BoardVariant, DeviceInfo};
+use factory_data::FactoryData;
+use fault::{FaultClass, FaultCode};
+use fault_reaction::{FaultReaction, FaultStop};
+use indication::IndicationState;
+use diagnostics::collision::{CollisionDetector, CollisionReaction};
+use diagnostics::following_error::FollowingErrorMonitor;
+use diagnostics::encoder_glitch::{EncoderErrorStats, EncoderGlitchFilter};
+use diagnostics::load_angle::LoadAngleMonitor;
+use diagnostics::overflow::{self, OverflowSite};
+use diagnostics::peak_hold::{PeakHold, PeakValue};
+#[cfg(feature = "production-test")]
+use diagnostics::production_test::{
+    ProductionTest, PRODUCTION_METRICS, SELF_TEST_OVERFLOW, SELF_TEST_TABLE,
+};
+use diagnostics::resonance::ResonanceDetector;
+use diagnostics::sample_alignment::SampleAlignment;
+use diagnostics::step_loss::StepLossDetector;
+use io_map::{IoFunction, IoMap, IoOutputs, IO_INVERT};
+use params::staging::ParamStage;
+use params::storage::{self, MigrationReport, StorageError};
+use params::writer::FlashError;
+use params::{AccessLevel, ParamError, ParamId, ParamRegistry};
+#[cfg(feature = "can")]
+use protocol::can::CanProtocol;
+#[cfg(feature = "can")]
+use protocol::canopen::cia402::DriveAction;
+#[cfg(feature = "can")]
+use protocol::canopen::{CanOpenNode, CanTransport, DriveCommand, DriveFeedback};
+use protocol::events::{EventQueue, MotionEvent};
+use protocol::odometry::OdometryPublisher;
+use protocol::status_report::{StatusPublisher, StatusReport};
+use protocol::table_transfer::{TableKind, TableTransfer};
+use protocol::time_sync::{self, HostClock};
+use protocol::commands::{self, Command, ReplyResult, Request};
+#[cfg(feature = "console")]
+use protocol::console::{self, ConsoleCommand, ConsoleReply, ConsoleStatus, PidGains};
+use protocol::{Frame, Transport};
+use scope::{ScopeSignal, SignalScope, TelemetryLink};
+use sensorless::{AngleSource, SensorlessAngle};
+#[cfg(feature = "sequencer")]
+use sequence::SequenceEngine;
+use setpoint::{PositionLoop, Setpoint};
+#[cfg(feature = "wizard")]
+use wizard::{SetupWizard, WizardInputs, WizardOutput, WizardResult};
+
+#[cfg(feature = "console")]
+use core::fmt::{self, Write};
+#[cfg(feature = "console")]
+use tunepulse_params::codes;
+
+/// Number of motion events buffered until flushed to the host
+const EVENT_QUEUE_SIZE: usize = 16;
+
+/// Maximum number of parameter values staged for atomic application
+const PARAM_STAGE_SIZE: usize = 8;
+
+/// Number of scope samples buffered for the telemetry link
+pub const SCOPE_BUFFER_SIZE: usize = 64;
+
+/// Maximum number of frequency response points
+pub const RESPONSE_POINTS: usize = 64;
+
+/// Amplitude of the current vector during the calibration (mA)
+const CALIBRATION_CURRENT: i16 = 400;
+
+/// Number of resonant speed bands skipped by velocity setpoints
+const RESONANCE_BANDS: usize = 4;
+
+/// Maximum number of steps in a stored motion sequence
+pub const SEQUENCE_STEPS: usize = 32;
+
+/// Number of spare digital pins with configurable function
+pub const IO_PINS: usize = 4;
+
+/// Number of position compare points
+pub const COMPARE_POSITIONS: usize = 4;
+
+/// Flash areas reported by the `FlashWriteFailed` event
+const FLASH_AREA_PARAMS: u32 = 0;
+const FLASH_AREA_FACTORY: u32 = 1;
 
 /// The main driver struct for the motor, holding all the state required for operation and calibration.
-pub struct MotorController {
-    motor: DriverPWM,   // Motor interface using PWM signals for control
-    frequency: u16,     // Update frequency (ticks per second)
-    position: Position, // Current encoder position reading
+///
+/// `FREQ` fixes the control-loop frequency at compile time, per-tick conversions of times and
+/// rates then fold into constants. `RUNTIME` (default) uses the frequency passed to `new`.
+pub struct MotorController<const FREQ: u16 = RUNTIME> {
+    motor: DriverPWM,                // Motor interface using PWM signals for control
+    frequency: u16,                  // Update frequency (ticks per second)
+    position: Position,              // Current encoder position reading
+    speed_est: SpeedEstimator<FREQ>, // Encoder speed estimation
+    speed_unit: SpeedUnit,           // Unit of the reported speed
+    motor_type: MotorType,           // Motor type currently driven
 
     driver_status: DriverStatus, // Current motor status (Calibrating, Ready, or Error)
+    fault: FaultCode,            // Reason of the Error status
+    fault_stop: FaultStop,       // Stop behaviour after a fault
 
-    angle_el: u16,  // Electrical angle of the motor (0..65535), used to control phase
-    amplitude: i16, // Amplitude (voltage magnitude) used during calibration
-    direction: i16, // Current rotation direction (1 for forward, -1 for backward)
-    speed: i16,     // Speed (steps per tick) during calibration
+    angle_el: u16,     // Electrical angle of the motor (0..65535), used to control phase
+    angle_el_enc: u16, // Electrical angle of the unfiltered encoder angle from the table
+    amplitude: i16,    // Amplitude (voltage magnitude) used during calibration
+    direction: i16,    // Current rotation direction (1 for forward, -1 for backward)
+    speed: i16,        // Speed (steps per tick) during calibration
 
-    angle_calibrator: AngleCalibrator,
+    angle_calibrator: AngleCalibrator<CAL_TABLE_SIZE, FREQ>,
+    angle_source: AngleSource,   // Rotor angle from the encoder or the back-EMF observer
+    sensorless: SensorlessAngle, // Commutation and encoder angle without encoder
     filter: FilterLPF,
+    filter_schedule: AlphaSchedule, // Position filter alpha over the speed
     supply: SupplyVoltage,
+    supply_startup: SupplyStartup, // Bridge stays off while the supply charges
+    supply_monitor: SupplyMonitor, // Undervoltage/overvoltage once the bridge is enabled
+    blanking: CurrentBlanking,     // Current samples blanked around switching edges
+    current_sense: CurrentSense,   // Probe currents of the current ADC readings
     ticker: i32,
-    sup_check: usize,
+    seed: EncoderSeed<ENCODER_SEED_SAMPLES>, // Averaged boot reading of the encoder
+
+    load_angle: LoadAngleMonitor,  // Commanded vs encoder-derived electrical angle
+    alignment: SampleAlignment,    // Encoder sample instant vs PWM period center
+    glitch: EncoderGlitchFilter,   // Encoder samples checked against a second read
+    damping: ActiveDamping,        // Mid-band resonance damping for steppers
+    friction: FrictionFeedforward, // Friction and gravity compensation of the torque command
+    inertia: InertiaIdentifier,    // Inertia test move and identified inertia
+    #[cfg(feature = "ripple-test")]
+    ripple: TorqueRipple,          // Torque ripple measurement after the calibration
+    observer: DisturbanceObserver, // Load torque estimation
+    collision: CollisionDetector,  // Load torque spikes from obstructions
+    step_loss: StepLossDetector,   // Pole slips of the open-loop vector from the phase currents
+    following: FollowingErrorMonitor, // Position error persisting outside its window
+    standstill: Standstill,        // Position hold suppressing idle dither
+    target: i32,                   // Target position (i16 rotations + u16 angle)
+    setpoint: Setpoint,            // Command followed in normal operation
+    position_loop: PositionLoop,   // Torque command of the velocity and position setpoints
+    in_position: InPosition,       // Position deadband and in-position window
+    speed_window: SpeedWindow,     // At target velocity and zero speed flags
+
+    params: ParamRegistry,                // Runtime configuration
+    staged: ParamStage<PARAM_STAGE_SIZE>, // Hot-tunable values waiting for the next tick
+    events: EventQueue<EVENT_QUEUE_SIZE>, // Motion events pending for the host
+
+    #[cfg(feature = "sequencer")]
+    sequence: SequenceEngine<SEQUENCE_STEPS>, // Standalone motion program
+    #[cfg(feature = "sequencer")]
+    inputs: u32,                              // Digital input levels, bit per input
+    io: IoMap<IO_PINS>,                       // Functions of spare digital pins
+    enabled: bool,                            // Drive output enable request
+    brake: BrakeControl,                      // Holding brake with timing interlocks
+    device_info: DeviceInfo,                  // Firmware build and hardware identification
+    scope: SignalScope<SCOPE_BUFFER_SIZE>,    // Capture of internal signals for telemetry
+    #[cfg(feature = "tuning")]
+    generator: SignalGenerator,               // Test signal excitation
+    #[cfg(feature = "tuning")]
+    injection: InjectionPoint,                // Loop node excited by the generator
+    #[cfg(feature = "tuning")]
+    reference_offset: i32,                    // Excitation added to the position reference
+    #[cfg(feature = "tuning")]
+    velocity_acc: i32,                        // Sub-count remainder of velocity excitation
+    #[cfg(feature = "tuning")]
+    analyzer: FrequencyResponse<RESPONSE_POINTS>, // Frequency response measurement
+    #[cfg(feature = "tuning")]
+    response: ScopeSignal,                    // Response signal of the measurement
+    #[cfg(feature = "tuning")]
+    step_test: StepResponse,                  // On-device step response health check
+    save_requested: bool,                     // Parameter image has to be written to flash
+    saved_crc: u32,                           // CRC of the parameters as loaded or last saved
+    saving_crc: Option<u32>,                  // CRC of the image being written to flash
+    factory: Option<FactoryData>,             // Factory data of the unit, `None` until written
+    factory_staged: FactoryData,              // Factory data written by the host, not stored yet
+    factory_requested: bool,                  // Staged factory data has to be written to flash
+    factory_writing: bool,                    // Staged factory data handed to the flash writer
+    access: AccessLevel,                      // Access level unlocked by the host
+    capture: PositionCapture,                 // Position latched by the capture input
+    compare: PositionCompare<COMPARE_POSITIONS>, // Output pulses at programmed positions
+    gear: ElectronicGear,                     // Target following a master position
+    step_follow: bool,                        // Gear engages on the STEP/DIR input once ready
+    jog: Jog,                                 // Commissioning jog with torque cap
+    resonance: ResonanceDetector<RESONANCE_BANDS>, // Speed bands skipped by velocity setpoints
+    odometry: OdometryPublisher,              // Virtual encoder output for robotics stacks
+    status_report: StatusPublisher,           // Slow health and statistics channel
+    peaks: PeakHold,                          // Largest current, temperature, speed and error
+    host_clock: HostClock,                    // Host wall clock of the last time beacon
+    #[cfg(feature = "can")]
+    canopen: CanOpenNode,                     // CiA 402 slave of a CAN link running CANopen
+    table: TableTransfer,                     // Table image exchanged with the host
+    #[cfg(feature = "wizard")]
+    wizard: SetupWizard,                      // First-run guided setup
+    #[cfg(feature = "production-test")]
+    production: ProductionTest,               // End-of-line test and its report
 }
 
+/// Position filter alpha during normal operation
+const FILTER_ALPHA_RUN: u8 = 0;
+
+/// Encoder samples averaged at boot before the position is initialized
+const ENCODER_SEED_SAMPLES: usize = 16;
+
+/// Maximum deviation of a boot sample from the median (~1°)
+const ENCODER_SEED_LIMIT: i16 = 182;
+
 // Constants used during calibration
-impl MotorController {
+impl<const FREQ: u16> MotorController<FREQ> {
     /// Create a new MotorDriver instance.
     ///
     /// # Arguments
     /// * `motor` - Motor type configuration
     /// * `connection` - Phase pattern configuration
-    /// * `frequency` - Number of ticks per second
+    /// * `frequency` - Number of ticks per second, ignored with a fixed `FREQ`
     pub fn new(
         motor_type: MotorType,
         connection: PhasePattern,
@@ -55,19 +290,47 @@ impl MotorController {
         max_sup_voltage: i32,
         resistance: i32,
     ) -> Self {
+        let frequency = tick_rate::resolve(FREQ, frequency);
         let mut motor = Motor::new(resistance);
         motor.pole_type = motor_type;
         motor.connection = connection;
         let control_mode = ControlMode::CurrentAB;
+        let params = ParamRegistry::new();
+        let saved_crc = params.crc(); // Defaults count as saved until an image is loaded
+        let mut driver = DriverPWM::new(motor, control_mode);
+        driver.set_beep_current(params.get(ParamId::BeepCurrent) as i16);
+        let dead_time = params.get(ParamId::DeadTimeCompensation) as i16;
+        driver.set_linearization(DutyLinearizer::from_dead_time(dead_time));
+        driver.set_current_loop(
+            params.get(ParamId::CurrentLoopKp) as i32,
+            params.get(ParamId::CurrentLoopKi) as i32,
+        );
+        if params.get(ParamId::BeepEnable) != 0 {
+            driver.beep(Melody::Startup, frequency); // Calibration starts after the melody
+        }
+        let mut speed_window = SpeedWindow::new(frequency);
+        speed_window.configure(
+            params.get(ParamId::VelocityWindow),
+            params.get(ParamId::VelocityWindowTime),
+            params.get(ParamId::ZeroSpeedThreshold),
+            params.get(ParamId::ZeroSpeedTime),
+        );
 
         Self {
-            motor: DriverPWM::new(motor, control_mode), // Initialize MotorPWM with given type and phase connection
+            motor: driver,                              // MotorPWM with given type and phase connection
             frequency,                                  // Store the update frequency
             position: Position::new(),                  // Initialize encoder position to 0
+            seed: EncoderSeed::new(ENCODER_SEED_LIMIT),
+            speed_est: SpeedEstimator::new(0, frequency),
+            speed_unit: SpeedUnit::CountsPerSecond,
+            motor_type,
 
             driver_status: DriverStatus::Calibrating, // Start in Calibrating mode
+            fault: FaultCode::None,
+            fault_stop: FaultStop::new(frequency, params.get(ParamId::FaultRampTime)),
 
             angle_el: 0, // Initial electrical angle is 0
+            angle_el_enc: 0,
 
             amplitude: 0,
 
@@ -75,80 +338,2783 @@ impl MotorController {
             speed: 0,     // Use the predefined calibration speed
 
             angle_calibrator: AngleCalibrator::new(frequency),
-            filter: FilterLPF::new(0, 0),
+            angle_source: AngleSource::Encoder,
+            sensorless: SensorlessAngle::new(frequency),
+            filter: FilterLPF::new(0, FILTER_ALPHA_RUN),
+            filter_schedule: AlphaSchedule::new(FILTER_ALPHA_RUN),
 
             supply: SupplyVoltage::new(200, max_sup_voltage),
+            supply_startup: SupplyStartup::new(
+                frequency,
+                params.get(ParamId::SupplyEnableVoltage),
+                params.get(ParamId::SupplyMaxSlope),
+                params.get(ParamId::SupplySettleTime),
+            ),
+            supply_monitor: SupplyMonitor::new(
+                frequency,
+                params.get(ParamId::SupplyUndervoltage),
+                params.get(ParamId::SupplyOvervoltage),
+                params.get(ParamId::SupplyMissTime),
+            ),
+            blanking: CurrentBlanking::new(frequency, params.get(ParamId::CurrentBlanking)),
+            current_sense: CurrentSense::new(),
             ticker: 0,
-            sup_check: 100,
+
+            load_angle: LoadAngleMonitor::new(250, LoadAngleMonitor::DEFAULT_STALL_THRESHOLD),
+            alignment: SampleAlignment::new(frequency),
+            glitch: EncoderGlitchFilter::new(frequency), // Single read until configured
+            damping: ActiveDamping::new(0, 0), // Disabled until configured
+            friction: FrictionFeedforward::new(FrictionParams::default(), 0), // Disabled until configured
+            inertia: InertiaIdentifier::new(frequency),
+            #[cfg(feature = "ripple-test")]
+            ripple: TorqueRipple::new(frequency),
+            observer: DisturbanceObserver::new(frequency, 50),
+            collision: CollisionDetector::new(frequency),
+            step_loss: StepLossDetector::new(frequency),
+            following: FollowingErrorMonitor::new(frequency),
+            // ~0.08 rev/s, ~0.04° deadband, 10ms settle time at 20kHz
+            standstill: Standstill::new(5000, 8, 200),
+            target: 0,
+            setpoint: Setpoint::default(),
+            position_loop: PositionLoop::new(frequency),
+            // ~10% ripple above ~0.5 rev/s, ~0.2 rev/s wide bands, 50ms debounce at 20kHz
+            resonance: ResonanceDetector::new(26, 32768, 13107, 1000),
+            // ~0.04° deadband, ~0.5° window with ~0.1° hysteresis, 10ms settle time at 20kHz
+            in_position: InPosition::new(8, 91, 18, 200),
+            speed_window,
+
+            events: EventQueue::new(params.get(ParamId::EventMask)),
+            compare: PositionCompare::new(frequency, params.get(ParamId::ComparePulseWidth)),
+            gear: ElectronicGear::new(frequency, params.get(ParamId::FollowTimeout)),
+            step_follow: false,
+            jog: Jog::new(
+                frequency,
+                params.get(ParamId::JogSpeed),
+                params.get(ParamId::JogTimeout),
+            ),
+            odometry: OdometryPublisher::new(frequency, params.get(ParamId::OdometryRate)),
+            status_report: StatusPublisher::new(frequency, params.get(ParamId::StatusRate)),
+            peaks: PeakHold::new(),
+            host_clock: HostClock::new(),
+            #[cfg(feature = "can")]
+            canopen: CanOpenNode::new(params.get(ParamId::CanNode) as u8),
+            params,
+            staged: ParamStage::new(),
+
+            #[cfg(feature = "sequencer")]
+            sequence: SequenceEngine::new(frequency),
+            #[cfg(feature = "sequencer")]
+            inputs: 0,
+            io: IoMap::new(),
+            enabled: true,
+            brake: BrakeControl::new(frequency, 0, 0),
+            device_info: DeviceInfo::new(BoardVariant::Unknown, [0; 12]),
+            scope: SignalScope::new(),
+            #[cfg(feature = "tuning")]
+            generator: SignalGenerator::new(frequency),
+            #[cfg(feature = "tuning")]
+            injection: InjectionPoint::Current,
+            #[cfg(feature = "tuning")]
+            reference_offset: 0,
+            #[cfg(feature = "tuning")]
+            velocity_acc: 0,
+            #[cfg(feature = "tuning")]
+            analyzer: FrequencyResponse::new(frequency),
+            #[cfg(feature = "tuning")]
+            response: ScopeSignal::Position,
+            #[cfg(feature = "tuning")]
+            step_test: StepResponse::new(frequency),
+            save_requested: false,
+            saving_crc: None,
+            saved_crc,
+            factory: None,
+            factory_staged: FactoryData::default(),
+            factory_requested: false,
+            factory_writing: false,
+            access: AccessLevel::User,
+            capture: PositionCapture::new(frequency),
+            table: TableTransfer::new(),
+            #[cfg(feature = "wizard")]
+            wizard: SetupWizard::new(frequency),
+            #[cfg(feature = "production-test")]
+            production: ProductionTest::new(),
         }
     }
 
     /// Main update method.
     ///
     /// # Arguments
-    /// * `voltg_angle` - (angle, amplitude) tuple for normal operation
-    /// * `encoder_pos` - current encoder position from the sensor
+    /// * `input` - Inputs sampled for this tick
     ///
     /// This method decides whether to run normal operation or calibration logic based on the motor status.
-    pub fn tick(&mut self, current: i32, input: DataInputs) -> [i16; 4] {
-        self.position.tick(input.angle_raw); // Update the internal position from the sensor
+    /// Normal operation follows the setpoint changed with `set_setpoint` between the ticks.
+    pub fn tick(&mut self, input: DataInputs) -> [i16; 4] {
+        // Apply committed hot-tunable values together, before any controller runs
+        if let Some(staged) = self.staged.take_committed() {
+            for &(id, value) in staged.entries() {
+                self.set_param(id as u16, value).ok(); // Validated while staging
+            }
+        }
+
         let sup_adc = self.supply.tick(input.supply_adc).voltage_norm();
-        self.amplitude = current as i16; // ma
-                                         // let sup_adc = self.supply.voltage_norm();
+
+        if self.alignment.tick(input.sample_phase) {
+            if let Some(stats) = self.alignment.stats() {
+                defmt::debug!(
+                    "ENCODER: Sampled {}ns from PWM center (jitter {}ns)",
+                    stats.mean_ns,
+                    stats.jitter_ns()
+                );
+            }
+        }
+
+        // Sample disagreeing with its second read is replaced by the last accepted one
+        let degraded = self.glitch.is_degraded();
+        let angle_raw = self.glitch.tick(input.angle_raw, input.angle_check);
+        let sensorless = self.angle_source == AngleSource::Sensorless;
+        if self.glitch.is_degraded() != degraded && !sensorless {
+            self.report_encoder_state();
+        }
+        // Sensorless drive runs on the encoder angle synthesized by its commutation
+        let angle_raw = if sensorless {
+            self.sensorless.angle_raw()
+        } else {
+            angle_raw
+        };
+
+        // Nothing runs until the position is seeded from averaged samples, a glitched first
+        // reading would start the position, filter and speed with a jump
+        if !self.seed.is_done() {
+            if let Some(angle) = self.seed.push(angle_raw) {
+                self.seed_position(angle);
+            }
+            return self.motor.tick_control((self.angle_el as i16, 0), sup_adc);
+        }
+
+        self.position.tick(angle_raw); // Update the internal position from the sensor
+        // Speed stays in the sensor frame like the current, loops combining both don't care
+        // about the user frame
+        let speed = self.speed_est.tick(self.position.raw_position()).get_speed();
+
+        // Bridge stays off during the supply pre-charge, calibration starts once it's stable
+        if !self.supply_startup.is_ready() {
+            if self.supply_startup.tick(self.supply.voltage_mv()) {
+                defmt::info!("SUPPLY: Stable at {}mV, bridge enabled", self.supply.voltage_mv());
+                self.supply_monitor
+                    .start(self.params.get(ParamId::SupplyGraceTime));
+            }
+            self.tick_wizard(&input, false); // Only waits for the supply
+            self.current_sense.sample_zero(&input.currnt_adc); // No current with the bridge off
+            return self.motor.tick_control((self.angle_el as i16, 0), sup_adc);
+        }
+        let fault = match self.supply_monitor.tick(self.supply.voltage_mv()) {
+            SupplyCheck::Ok => FaultCode::None,
+            SupplyCheck::Undervoltage => FaultCode::SupplyUndervoltage,
+            SupplyCheck::Overvoltage => FaultCode::SupplyOvervoltage,
+        };
+        if fault != FaultCode::None && self.driver_status != DriverStatus::Error {
+            defmt::error!("SUPPLY: Out of range at {}mV", self.supply.voltage_mv());
+            self.raise_fault(fault);
+        }
+
+        // Brake always engages on fault, otherwise follows the enable request with delays
+        let fault = self.driver_status == DriverStatus::Error;
+        self.brake.tick(self.enabled && !fault, fault);
+
+        // Open-loop steps of the setup wizard take over the bridge
+        if let Some((angle, current)) = self.tick_wizard(&input, true) {
+            (self.angle_el, self.amplitude) = (angle, current);
+            self.motor.set_current_q(0);
+            return self
+                .motor
+                .tick_control((self.angle_el as i16, self.amplitude), sup_adc);
+        }
+
+        // Current loop closes on the measured currents, the wizard measures its steps open-loop
+        let mut current_ab = None;
+        if let Some(currents) = self.current_sense.currents(&input.currnt_adc) {
+            let current = self.motor.tick_current(currents);
+            current_ab = Some(current);
+            // Observer needs the voltage applied while the current was measured
+            if sensorless {
+                let supply_mv = self.supply.voltage_mv();
+                let (alpha, beta) = self.motor.voltage_ab();
+                let voltage = (
+                    (alpha as i32 * supply_mv) >> 15,
+                    (beta as i32 * supply_mv) >> 15,
+                );
+                self.sensorless.tick(voltage, current);
+            }
+        }
+
         match self.driver_status {
             DriverStatus::Ready => {
                 self.ticker += 1;
 
+                // At standstill freeze the position and narrow the filter bandwidth, while
+                // moving the bandwidth follows the speed
+                let held = self.standstill.tick(self.position.raw_position(), speed);
+                let position = self.position.to_user(held); // Targets use the user frame
+                self.filter.set_alpha(if self.standstill.is_active() {
+                    self.params.get(ParamId::HoldFilterAlpha) as u8
+                } else {
+                    self.filter_schedule.alpha(speed)
+                });
+                let speed = if self.standstill.is_active() { 0 } else { speed };
+
+                // Test signals excite the reference position or the torque command
+                let (reference_offset, current_excitation) = self.tick_excitation();
+                // Velocity setpoint moves the target, resonant speeds are passed quickly
+                if let Setpoint::Velocity(velocity) = self.setpoint {
+                    let velocity = self.resonance.skip_bands(velocity);
+                    self.resonance
+                        .tick(velocity, speed * self.position.direction());
+                    if self.brake.motion_allowed() {
+                        let step = self.position_loop.integrate(velocity);
+                        self.target = self.target.wrapping_add(step);
+                    }
+                }
+                let reference = self.target.wrapping_add(reference_offset);
+
+                let was_in_position = self.in_position.is_in_position();
+                // Errors within the deadband are not corrected, the loop doesn't chase noise
+                let error = self.in_position.tick(reference, position);
+                if self.in_position.is_in_position() && !was_in_position {
+                    self.events.push(MotionEvent::TargetReached, position as u32);
+                }
+                let target_velocity = match self.setpoint {
+                    Setpoint::Velocity(velocity) => Some(velocity),
+                    _ => None,
+                };
+                self.speed_window
+                    .tick(target_velocity, speed * self.position.direction());
+                // A jam or a loop too soft for the motion lets the error persist, stop the axis
+                let following = self.setpoint.uses_position_loop() && self.brake.torque_enabled();
+                if self.following.tick(reference.wrapping_sub(position), following) {
+                    self.raise_fault(FaultCode::FollowingError);
+                }
+                if self.brake.motion_allowed() {
+                    // STEP/DIR input takes over as soon as its first count arrived
+                    if self.step_follow && self.gear.engage(self.target) {
+                        self.step_follow = false;
+                    }
+                    #[cfg(feature = "sequencer")]
+                    {
+                        let in_position = self.in_position.is_in_position();
+                        if let Some(target) = self.sequence.tick(in_position, self.inputs) {
+                            self.target = target;
+                        }
+                    }
+                    if let Some(target) = self.gear.tick() {
+                        self.target = target;
+                    }
+                    if self.gear.take_lost() {
+                        self.raise_fault(FaultCode::MasterLost);
+                    }
+                    if let Some(step) = self.jog.tick() {
+                        self.target = self.target.wrapping_add(step);
+                    }
+                } else {
+                    self.jog.abort(); // No jog while the brake holds the axis
+                }
+
                 // If calibration is complete, run normal operation logic
-                let filtered_pos = self.filter.tick(self.position.angle());
+                let filtered_pos = self.filter.tick(held as u16);
 
                 self.angle_el = self.angle_calibrator.get_correction(filtered_pos).1;
+
+                // Inertia test move overrides the torque command while running
+                if let Some(test_current) = self.inertia.tick(speed) {
+                    self.amplitude = test_current;
+                } else {
+                    // Loop works in the user frame, the torque command in the sensor frame
+                    let current = match self.setpoint {
+                        Setpoint::Current(current) => current,
+                        Setpoint::Velocity(_) | Setpoint::Position(_) => {
+                            let current = self.position_loop.tick_error(error);
+                            current * self.position.direction()
+                        }
+                        Setpoint::VoltageAngle { .. } => 0,
+                    };
+                    // Add friction, gravity and load disturbance compensation to the torque command
+                    let current = self.friction.tick(current, speed);
+                    let current = current.saturating_add(self.observer.compensation());
+                    let current = current.saturating_add(current_excitation);
+                    self.amplitude =
+                        overflow::clamp_i16(current, i16::MAX, OverflowSite::CurrentCommand);
+                }
+
+                // Load torque can only be observed once the inertia is known
+                if let Some(inertia) = self.inertia.inertia() {
+                    self.observer.set_inertia(inertia);
+                    let load = self.observer.tick(self.amplitude as i32, speed);
+                    if self.collision.tick(load) {
+                        self.react_collision(position);
+                    }
+                }
+
+                // Reduce torque reaction keeps the torque command at a safe level
+                if self.collision.is_latched()
+                    && self.collision_reaction() == CollisionReaction::ReduceTorque
+                {
+                    let limit = self.params.get(ParamId::CollisionTorque) as i16;
+                    self.amplitude = self.amplitude.clamp(-limit, limit);
+                }
+
+                // Jog never exceeds its torque cap and stops once the cap is reached
+                if self.jog.is_active() {
+                    let limit = self.params.get(ParamId::JogTorque) as i16;
+                    if self.amplitude.saturating_abs() >= limit {
+                        self.jog.abort();
+                        self.target = position;
+                        self.events
+                            .push(MotionEvent::JogAborted, self.amplitude as i32 as u32);
+                    }
+                    self.amplitude = self.amplitude.clamp(-limit, limit);
+                }
+
+                // Active damping only makes sense for steppers in closed loop
+                if self.motor_type == MotorType::STEP {
+                    let resonant =
+                        self.setpoint.uses_position_loop() && self.resonance.needs_damping();
+                    self.damping.set_boost(resonant);
+                    let damping = self.damping.tick(speed);
+                    self.motor.set_current_q(damping);
+                }
+
+                // Back-EMF observer or its open-loop start replaces the encoder commutation
+                if sensorless {
+                    (self.angle_el, self.amplitude) = self.sensorless.commutate(self.amplitude);
+                }
+
+                // Open-loop vector replaces the commutation
+                if let Setpoint::VoltageAngle { angle, amplitude } = self.setpoint {
+                    self.angle_el = angle;
+                    self.amplitude = amplitude;
+                    self.motor.set_current_q(0);
+                }
+
+                // Without feedback the phase currents are the only sign of a pole slip
+                let open_loop = matches!(self.setpoint, Setpoint::VoltageAngle { .. });
+                match current_ab {
+                    Some(current) if open_loop && self.brake.torque_enabled() => {
+                        if self.step_loss.tick(self.angle_el, current) {
+                            self.raise_fault(FaultCode::Stall);
+                        }
+                    }
+                    _ => self.step_loss.reset(),
+                }
+
+                // Disabled drive keeps tracking the position but produces no torque
+                if !self.brake.torque_enabled() {
+                    self.amplitude = 0;
+                    self.motor.set_current_q(0);
+                }
             }
             DriverStatus::Error => {
-                // If in error state, stop driving the motor by setting amplitude to 0
+                // The fault reaction brings the motor to rest, then no voltage is applied
+                #[cfg(feature = "tuning")]
+                self.generator.stop();
+                self.motor.set_current_q(0);
+                let rotor = if sensorless {
+                    self.sensorless.angle_el()
+                } else if self.angle_calibrator.has_table() {
+                    let filtered_pos = self.filter.tick(self.position.raw_position() as u16);
+                    self.angle_calibrator.get_correction(filtered_pos).1
+                } else {
+                    self.angle_el
+                };
+                (self.angle_el, self.amplitude) = self.fault_stop.tick(rotor);
+            }
+            DriverStatus::Calibrating if sensorless => {
+                // Nothing to calibrate without encoder, the commutation starts open loop
                 self.amplitude = 0;
+                self.driver_status = DriverStatus::Ready;
+                self.events.push(MotionEvent::CalibrationDone, 0);
             }
             DriverStatus::Calibrating => {
-                if self.sup_check > 0 {
-                    self.sup_check -= 1;
-                    if self.sup_check == 0 {
-                        if self.supply.voltage_mv() < 8000 {
-                            defmt::warn!(
-                                "SUPPLY is not enough: {}mV while at least 8000mV is needed",
-                                self.supply.voltage_mv());
-                        } else {
-                            defmt::info!("SUPPLY is OK: {}mV", self.supply.voltage_mv());
-                        };
+                self.motor.set_current_q(0); // Calibration requires a pure current vector
+                self.amplitude = CALIBRATION_CURRENT;
+                // Winding beeps would disturb the calibration, wait until they finish
+                if self.motor.is_beeping() {
+                    self.amplitude = 0;
+                } else if !self.tick_ripple() {
+                    // If still calibrating, run the calibration logic
+                    self.angle_el = self.angle_calibrator.tick(self.position.raw_position());
+                }
+                if self.angle_calibrator.is_ready() && self.ripple_progress().is_none() {
+                    if !self.start_ripple() {
+                        self.driver_status = DriverStatus::Ready;
+                        let verdict = self.angle_calibrator.load_verdict();
+                        if let Some(LoadVerdict::Warning(flags)) = verdict {
+                            self.events
+                                .push(MotionEvent::CalibrationLoadWarning, flags as u32);
+                        }
+                        self.events.push(MotionEvent::CalibrationDone, 0);
+                        self.load_angle.reset_ripple();
+                        self.beep(Melody::CalibrationDone);
+                    }
+                } else if self.angle_calibrator.is_failed() {
+                    let fault = match self.angle_calibrator.error() {
+                        Some(CalibrationError::Loaded) => FaultCode::CalibrationLoaded,
+                        Some(CalibrationError::Timeout) => FaultCode::CalibrationTimeout,
+                        _ => FaultCode::CalibrationFailed,
                     };
-                };
-                // If still calibrating, run the calibration logic
-                self.angle_el = self.angle_calibrator.tick(self.position.position());
-                if self.angle_calibrator.is_ready() {
-                    self.driver_status = DriverStatus::Ready
+                    self.raise_fault(fault);
                 }
             }
         }
 
+        // Fault reaction continues from the commutation of normal operation
+        if self.driver_status != DriverStatus::Error {
+            self.fault_stop.track(self.angle_el);
+        }
+
+        // Load angle is only meaningful once a valid calibration table exists
+        if self.angle_calibrator.has_table() && !sensorless {
+            self.angle_el_enc = self.angle_calibrator.get_correction(self.position.angle()).1;
+            self.load_angle.tick(self.angle_el, self.angle_el_enc);
+            // Warn the host before the motor pulls out, only while it produces torque
+            let armed = self.driver_status == DriverStatus::Ready && self.brake.torque_enabled();
+            if self.load_angle.take_stall_warning(armed) {
+                let load_angle = self.load_angle.load_angle_filtered();
+                self.events
+                    .push(MotionEvent::StallWarning, load_angle as i32 as u32);
+            }
+        }
+
         // Compute the PWM signals based on the current angle_el and amplitude
-        self.motor
-            .tick_control((self.angle_el as i16, self.amplitude), sup_adc)
+        let pwm = self
+            .motor
+            .tick_control((self.angle_el as i16, self.amplitude), sup_adc);
+
+        let [signal0, signal1] = self.scope.signals();
+        let values = [self.signal(signal0, &pwm), self.signal(signal1, &pwm)];
+        self.scope.capture(values);
+        let user_speed = speed * self.position.direction();
+        self.odometry.tick(
+            self.position.position(),
+            user_speed,
+            self.motor.current_dq(),
+            self.supply.voltage_mv(),
+        );
+        self.peaks.tick(
+            self.motor.current_dq(),
+            input.temper_adc,
+            user_speed,
+            self.in_position.error(),
+        );
+        if self.status_report.tick() {
+            let report = self.status_report(input.temper_adc);
+            self.status_report.publish(&report);
+        }
+
+        #[cfg(feature = "tuning")]
+        self.tick_measurements(&pwm, user_speed);
+
+        // Production test measures once its offset trim finished, a fault fails the unit
+        #[cfg(feature = "production-test")]
+        if self.production.is_running() {
+            self.production.sample(input.temper_adc);
+            match self.driver_status {
+                DriverStatus::Calibrating => {}
+                DriverStatus::Ready => {
+                    self.finish_production_test(self.production.self_test_passed())
+                }
+                DriverStatus::Error => self.finish_production_test(false),
+            }
+        }
+        pwm
+    }
+
+    /// Run the setup wizard, returns the open-loop vector taking over the bridge.
+    #[cfg(feature = "wizard")]
+    fn tick_wizard(&mut self, input: &DataInputs, supply_ready: bool) -> Option<(u16, i16)> {
+        if !self.wizard.is_running() {
+            return None;
+        }
+        if self.enabled {
+            self.wizard.abort(); // Enabling the drive ends the setup
+        }
+        let inputs = WizardInputs {
+            supply_ready,
+            supply_mv: self.supply.voltage_mv(),
+            currents: [input.currnt_adc[0] as i32, input.currnt_adc[1] as i32],
+            position: self.position.raw_position(),
+            calibrated: self.driver_status == DriverStatus::Ready,
+            fault: self.driver_status == DriverStatus::Error,
+        };
+        let output = self.wizard.tick(&inputs);
+        while let Some(progress) = self.wizard.take_progress() {
+            self.events.push(MotionEvent::WizardProgress, progress.arg());
+        }
+        match output {
+            WizardOutput::Released => None,
+            WizardOutput::Idle => Some((self.angle_el, 0)),
+            WizardOutput::Vector { angle, current } => Some((angle, current)),
+            WizardOutput::Calibrate => {
+                self.recalibrate();
+                None
+            }
+        }
     }
 
-    /// Change the motor type mode.
+    /// Setup wizard compiled out, the bridge is never taken over.
+    #[cfg(not(feature = "wizard"))]
     #[inline(always)]
-    pub fn change_motor_mode(&mut self, motor: MotorType) {
-        self.motor.change_motor_mode(motor); // Delegate to motor instance
+    fn tick_wizard(&mut self, _input: &DataInputs, _supply_ready: bool) -> Option<(u16, i16)> {
+        None
     }
 
-    /// Change the phase pattern mode.
+    /// Run the test signal excitation, returns the offset of the reference position and the
+    /// excitation added to the torque command.
+    #[cfg(feature = "tuning")]
+    fn tick_excitation(&mut self) -> (i32, i32) {
+        // Step test injects at its own point, the generator is idle meanwhile
+        let (excitation, injection) = match self.step_test.output() {
+            Some(step) => (step, self.step_test.point()),
+            None => (self.generator.tick(), self.injection),
+        };
+
+        // Excitation of the velocity or position reference moves the reference position
+        match injection {
+            InjectionPoint::Current => {}
+            InjectionPoint::Velocity => {
+                self.velocity_acc += excitation;
+                let step = self.velocity_acc / self.frequency() as i32;
+                self.velocity_acc -= step * self.frequency() as i32;
+                self.reference_offset = self.reference_offset.wrapping_add(step);
+            }
+            InjectionPoint::Position => self.reference_offset = excitation,
+        }
+        if !self.generator.is_running() && !self.step_test.is_running() {
+            self.reference_offset = 0;
+            self.velocity_acc = 0;
+        }
+        match injection {
+            InjectionPoint::Current => (self.reference_offset, excitation),
+            _ => (self.reference_offset, 0),
+        }
+    }
+
+    /// Test signals compiled out, nothing is excited.
+    #[cfg(not(feature = "tuning"))]
     #[inline(always)]
-    pub fn change_phase_mode(&mut self, connection: PhasePattern) {
-        self.motor.change_phase_mode(connection); // Delegate to motor instance
+    fn tick_excitation(&mut self) -> (i32, i32) {
+        (0, 0)
     }
 
-    /// Get current PWM signals.
+    /// Evaluate the frequency response and the step test on the output of the tick.
+    #[cfg(feature = "tuning")]
+    fn tick_measurements(&mut self, pwm: &[i16; 4], user_speed: i32) {
+        // Frequency response correlates the injected excitation with the response signal
+        if self.analyzer.is_running() {
+            if !self.generator.is_running() {
+                self.analyzer.stop(); // Excitation aborted
+            }
+            let response = self.signal(self.response, pwm);
+            let phase = self.generator.phase();
+            match self.analyzer.tick(phase, self.generator.output(), response) {
+                AnalyzerStep::Continue => {}
+                AnalyzerStep::Frequency(frequency) => self.generator.set_frequency(frequency),
+                AnalyzerStep::Done => self.generator.stop(),
+            }
+        }
+
+        // Step test measures the response to its own injection, aborted once the axis moves
+        // too far or can't follow anymore
+        if self.step_test.is_running() {
+            let response = match self.step_test.point() {
+                InjectionPoint::Current => self.amplitude as i32,
+                _ => user_speed,
+            };
+            let travel = self.step_test.travel(self.position.position());
+            if self.driver_status != DriverStatus::Ready
+                || !self.brake.motion_allowed()
+                || travel > self.params.get(ParamId::StepTravel)
+            {
+                self.step_test.stop();
+                self.events.push(MotionEvent::StepTestDone, 1);
+            } else if self.step_test.tick(response) {
+                self.events.push(MotionEvent::StepTestDone, 0);
+            }
+        }
+    }
+
+    /// Run the torque ripple measurement, returns `false` if it isn't running.
+    #[cfg(feature = "ripple-test")]
+    fn tick_ripple(&mut self) -> bool {
+        if !self.ripple.is_running() {
+            return false;
+        }
+        let raw = self.position.angle();
+        let corrected = self.angle_calibrator.get_correction(raw).0;
+        if let Some(angle) = self.ripple.tick(raw, corrected) {
+            self.angle_el = angle;
+        }
+        true
+    }
+
+    /// Start the torque ripple measurement once the calibration table is ready, returns
+    /// `false` if it just finished or is disabled by the parameters.
+    #[cfg(feature = "ripple-test")]
+    fn start_ripple(&mut self) -> bool {
+        if self.ripple.take_done() || self.params.get(ParamId::RippleTest) == 0 {
+            return false;
+        }
+        // Torque ripple is measured once the table corrects the encoder
+        self.ripple.start(self.angle_el);
+        true
+    }
+
+    /// Progress of the torque ripple measurement (percent), `None` unless it is running.
+    #[cfg(feature = "ripple-test")]
+    fn ripple_progress(&self) -> Option<u8> {
+        self.ripple.is_running().then(|| self.ripple.progress())
+    }
+
+    /// Torque ripple measurement compiled out, the calibration finishes without it.
+    #[cfg(not(feature = "ripple-test"))]
     #[inline(always)]
-    pub fn get_pwm(&mut self) -> [i16; 4] {
-        self.motor.get_control()
+    fn tick_ripple(&mut self) -> bool {
+        false
+    }
+
+    #[cfg(not(feature = "ripple-test"))]
+    #[inline(always)]
+    fn start_ripple(&mut self) -> bool {
+        false
+    }
+
+    #[cfg(not(feature = "ripple-test"))]
+    #[inline(always)]
+    fn ripple_progress(&self) -> Option<u8> {
+        None
+    }
+
+    /// Returns true while the signal generator or the step test excites the loop.
+    #[cfg(feature = "tuning")]
+    #[inline(always)]
+    fn test_signal_running(&self) -> bool {
+        self.generator.is_running() || self.step_test.is_running()
+    }
+
+    #[cfg(not(feature = "tuning"))]
+    #[inline(always)]
+    fn test_signal_running(&self) -> bool {
+        false
+    }
+
+    /// Returns true while the production test runs.
+    #[cfg(feature = "production-test")]
+    #[inline(always)]
+    fn production_running(&self) -> bool {
+        self.production.is_running()
+    }
+
+    #[cfg(not(feature = "production-test"))]
+    #[inline(always)]
+    fn production_running(&self) -> bool {
+        false
+    }
+
+    /// Returns true while the setup wizard runs.
+    #[cfg(feature = "wizard")]
+    #[inline(always)]
+    fn wizard_running(&self) -> bool {
+        self.wizard.is_running()
+    }
+
+    #[cfg(not(feature = "wizard"))]
+    #[inline(always)]
+    fn wizard_running(&self) -> bool {
+        false
+    }
+
+    /// Initialize the position, its filter and the speed estimation at the boot angle.
+    fn seed_position(&mut self, angle: u16) {
+        self.position.seed(angle);
+        self.speed_est.reset(self.position.raw_position());
+        self.filter.reset(angle);
+        defmt::info!(
+            "ENCODER: Seeded at {} ({} of {} samples rejected)",
+            angle,
+            self.seed.rejected(),
+            ENCODER_SEED_SAMPLES
+        );
+    }
+
+    /// Route the scope channels, a non-empty angle mask overrides the channel parameters.
+    fn route_scope(&mut self) {
+        let mask = self.params.get(ParamId::ScopeAngleMask);
+        let signals = ScopeSignal::angle_selection(mask).unwrap_or_else(|| {
+            [ParamId::ScopeChannel0, ParamId::ScopeChannel1].map(|id| {
+                ScopeSignal::from_raw(self.params.get(id) as u8).unwrap_or(ScopeSignal::None)
+            })
+        });
+        for (channel, signal) in signals.into_iter().enumerate() {
+            self.scope.select(channel, signal);
+        }
+    }
+
+    /// Read an internal signal for the scope.
+    fn signal(&self, signal: ScopeSignal, pwm: &[i16; 4]) -> i32 {
+        match signal {
+            ScopeSignal::None => 0,
+            ScopeSignal::Position => self.position.position(),
+            ScopeSignal::Speed => self.reported_speed(),
+            ScopeSignal::Target => self.target,
+            ScopeSignal::PositionError => self.in_position.error(),
+            ScopeSignal::AngleEl => self.angle_el as i32,
+            ScopeSignal::CurrentCommand => self.amplitude as i32,
+            ScopeSignal::FrictionCurrent => self.friction.output(),
+            ScopeSignal::DampingCurrent => self.damping.output() as i32,
+            ScopeSignal::LoadTorque => self.observer.estimate(),
+            ScopeSignal::LoadAngle => self.load_angle.load_angle() as i32,
+            ScopeSignal::SupplyVoltage => self.supply.voltage_mv(),
+            ScopeSignal::DutyA => pwm[0] as i32,
+            ScopeSignal::DutyB => pwm[1] as i32,
+            ScopeSignal::DutyC => pwm[2] as i32,
+            ScopeSignal::DutyD => pwm[3] as i32,
+            #[cfg(feature = "tuning")]
+            ScopeSignal::Excitation => self.generator.output(),
+            #[cfg(not(feature = "tuning"))]
+            ScopeSignal::Excitation => 0,
+            ScopeSignal::Overflows => overflow::total() as i32,
+            ScopeSignal::SampleOffset => self.alignment.offset_ns(),
+            ScopeSignal::AngleRaw => self.position.angle() as i32,
+            ScopeSignal::AngleFiltered => self.filter.get_output() as i32,
+            ScopeSignal::AngleElCorrected => self.angle_el_enc as i32,
+            ScopeSignal::EncoderGlitches => self.glitch.count() as i32,
+            ScopeSignal::EncoderErrors => self.glitch.stats().rejected() as i32,
+            ScopeSignal::CurrentD => self.motor.current_dq().0 as i32,
+            ScopeSignal::CurrentQ => self.motor.current_dq().1 as i32,
+        }
+    }
+
+    /// Start the test signal configured by the excitation parameters.
+    ///
+    /// Returns `false` if the motor isn't ready, produces no torque, the step test is running
+    /// or the configuration is invalid. The amplitude is limited depending on the excited loop
+    /// node.
+    #[cfg(feature = "tuning")]
+    pub fn start_excitation(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready
+            || !self.brake.torque_enabled()
+            || self.step_test.is_running()
+        {
+            return false;
+        }
+        let point = InjectionPoint::from_raw(self.params.get(ParamId::ExcitationPoint) as u8);
+        let waveform = Waveform::from_raw(self.params.get(ParamId::ExcitationWaveform) as u8);
+        let (Some(point), Some(waveform)) = (point, waveform) else {
+            return false;
+        };
+        let amplitude = (self.params.get(ParamId::ExcitationAmplitude) as i32).min(point.limit());
+        self.injection = point;
+        self.generator.start(
+            waveform,
+            amplitude,
+            self.params.get(ParamId::ExcitationFrequency),
+            self.params.get(ParamId::ExcitationFrequencyEnd),
+            self.params.get(ParamId::ExcitationDuration),
+        );
+        true
+    }
+
+    /// Stop the test signal, a running frequency response measurement and the step test.
+    #[cfg(feature = "tuning")]
+    #[inline(always)]
+    pub fn stop_excitation(&mut self) {
+        self.generator.stop();
+        self.analyzer.stop();
+        self.step_test.stop();
+    }
+
+    /// Start the frequency response measurement configured by the excitation parameters.
+    ///
+    /// A sine is injected at logarithmically spaced frequencies between the start and the end
+    /// frequency, returns `false` if the excitation can't be started.
+    #[cfg(feature = "tuning")]
+    pub fn start_frequency_response(&mut self) -> bool {
+        let response = ScopeSignal::from_raw(self.params.get(ParamId::BodeResponse) as u8);
+        let point = InjectionPoint::from_raw(self.params.get(ParamId::ExcitationPoint) as u8);
+        let (Some(response), Some(point)) = (response, point) else {
+            return false;
+        };
+        if self.driver_status != DriverStatus::Ready
+            || !self.brake.torque_enabled()
+            || self.step_test.is_running()
+        {
+            return false;
+        }
+        let amplitude = (self.params.get(ParamId::ExcitationAmplitude) as i32).min(point.limit());
+        let frequency = self.analyzer.start(
+            self.params.get(ParamId::ExcitationFrequency),
+            self.params.get(ParamId::ExcitationFrequencyEnd),
+            self.params.get(ParamId::BodePoints) as usize,
+            self.params.get(ParamId::BodePeriods) as u16,
+        );
+        self.injection = point;
+        self.response = response;
+        self.generator
+            .start(Waveform::Sweep, amplitude, frequency, frequency, 0);
+        true
+    }
+
+    /// Get the frequency response measurement and its results.
+    #[cfg(feature = "tuning")]
+    #[inline(always)]
+    pub fn frequency_response(&self) -> &FrequencyResponse<RESPONSE_POINTS> {
+        &self.analyzer
+    }
+
+    /// Start the current and velocity step response tests configured by the step parameters.
+    ///
+    /// Returns `false` if the motor isn't ready, the axis is held or the signal generator is
+    /// running. Results are read with `step_test()` once the measuring status flag clears.
+    #[cfg(feature = "tuning")]
+    pub fn start_step_test(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready
+            || !self.brake.motion_allowed()
+            || self.generator.is_running()
+        {
+            return false;
+        }
+        let current = self.params.get(ParamId::StepCurrent) as i32;
+        let speed = self.params.get(ParamId::StepSpeed) as i32;
+        self.step_test.configure(
+            current.min(InjectionPoint::Current.limit()),
+            speed.min(InjectionPoint::Velocity.limit()),
+            self.params.get(ParamId::StepDuration),
+        );
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.jog.stop();
+        self.step_test.start(self.position.position());
+        true
+    }
+
+    /// Start the production test: self-test, quick offset trim and measurement of the metrics.
+    ///
+    /// Returns `false` if the motor isn't ready, produces torque or a test signal is running.
+    /// The unit passed once `production_test()` reports all metrics passed, the verdict is also
+    /// shown by the status LED while the drive stays disabled.
+    #[cfg(feature = "production-test")]
+    pub fn start_production_test(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready
+            || self.brake.torque_enabled()
+            || self.test_signal_running()
+        {
+            return false;
+        }
+        let mut self_test = 0;
+        if !self.angle_calibrator.has_table() || self.angle_calibrator.needs_full_recal() {
+            self_test |= SELF_TEST_TABLE;
+        }
+        if overflow::total() != 0 {
+            self_test |= SELF_TEST_OVERFLOW;
+        }
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.jog.stop();
+        self.production.start(self_test);
+        // A failed self-test finishes without trim on the next tick
+        if self.production.self_test_passed() {
+            self.quick_recalibrate();
+        }
+        true
+    }
+
+    /// Measure the metrics, compare them to the limits and report the result.
+    ///
+    /// # Arguments
+    /// * `trimmed` - The offset trim of this test finished
+    #[cfg(feature = "production-test")]
+    fn finish_production_test(&mut self, trimmed: bool) {
+        let limit = |id| self.params.get(id) as i32;
+        let limits: [(i32, i32); PRODUCTION_METRICS] = [
+            (0, 0),
+            (limit(ParamId::ProductionSupplyMin), limit(ParamId::ProductionSupplyMax)),
+            (limit(ParamId::ProductionTempMin), limit(ParamId::ProductionTempMax)),
+            (0, limit(ParamId::ProductionDeviationMax)),
+            (0, limit(ParamId::ProductionSpreadMax)),
+        ];
+        let deviation = self.angle_calibrator.table_deviation().map(i32::from);
+        let spread = if trimmed {
+            self.angle_calibrator.trim_spread()
+        } else {
+            None
+        };
+        let report = self
+            .production
+            .finish(self.supply.voltage_mv(), deviation, spread, limits);
+        if report.passed() {
+            defmt::info!("PRODUCTION: Unit passed");
+        } else {
+            defmt::warn!("PRODUCTION: Unit failed (metrics {:#b})", report.failed_mask());
+        }
+        self.events
+            .push(MotionEvent::ProductionTestDone, report.failed_mask());
+    }
+
+    /// Get a metric of the calibration report, `None` if it wasn't measured.
+    pub fn calibration_metric(&self, metric: CalibrationMetric) -> Option<i32> {
+        match metric {
+            CalibrationMetric::TableDeviation => {
+                self.angle_calibrator.table_deviation().map(i32::from)
+            }
+            CalibrationMetric::TrimSpread => self.angle_calibrator.trim_spread(),
+            #[cfg(feature = "ripple-test")]
+            CalibrationMetric::TorqueRipple => self.ripple.result().map(|result| result.corrected),
+            #[cfg(feature = "ripple-test")]
+            CalibrationMetric::TorqueRippleRaw => self.ripple.result().map(|result| result.raw),
+            #[cfg(not(feature = "ripple-test"))]
+            CalibrationMetric::TorqueRipple | CalibrationMetric::TorqueRippleRaw => None,
+            CalibrationMetric::PassHysteresis => self.angle_calibrator.pass_hysteresis(),
+            CalibrationMetric::LoadFlags => match self.angle_calibrator.load_verdict()? {
+                LoadVerdict::Unloaded => Some(0),
+                LoadVerdict::Warning(flags) | LoadVerdict::Loaded(flags) => Some(flags as i32),
+            },
+            CalibrationMetric::LoadAngleRipple => self.load_angle.ripple().map(i32::from),
+        }
+    }
+
+    /// Get the production test and its report.
+    #[cfg(feature = "production-test")]
+    #[inline(always)]
+    pub fn production_test(&self) -> &ProductionTest {
+        &self.production
+    }
+
+    /// Get the step response test and its results.
+    #[cfg(feature = "tuning")]
+    #[inline(always)]
+    pub fn step_test(&self) -> &StepResponse {
+        &self.step_test
+    }
+
+    /// Get the test signal generator.
+    #[cfg(feature = "tuning")]
+    #[inline(always)]
+    pub fn generator(&self) -> &SignalGenerator {
+        &self.generator
+    }
+
+    /// Get the scope capturing internal signals, drain it from a lower priority task.
+    #[inline(always)]
+    pub fn scope(&mut self) -> &mut SignalScope<SCOPE_BUFFER_SIZE> {
+        &mut self.scope
+    }
+
+    /// Get the telemetry time base point, send it before the first scope sample.
+    pub fn scope_timebase(&self) -> [u8; scope::POINT_SIZE] {
+        self.scope.timebase(self.frequency())
+    }
+
+    /// Stream captured scope samples over a telemetry link, time base points included, call
+    /// from a lower priority task. Returns the number of samples sent.
+    #[inline(always)]
+    pub fn flush_scope<L: TelemetryLink>(&mut self, link: &mut L) -> usize {
+        let frequency = self.frequency();
+        self.scope.flush(link, frequency)
+    }
+
+    /// Estimated host wall clock (ms, wrapping), `None` until the host sent a time beacon.
+    pub fn host_time_ms(&self) -> Option<u32> {
+        self.host_clock
+            .host_time_ms(self.scope.tick(), self.frequency())
+    }
+
+    /// Enter the error state and notify the host.
+    fn raise_fault(&mut self, fault: FaultCode) {
+        self.driver_status = DriverStatus::Error;
+        self.fault = fault;
+        let reaction = match fault.class() {
+            Some(FaultClass::Sensor) => ParamId::FaultReactionSensor,
+            Some(FaultClass::Supply) => ParamId::FaultReactionSupply,
+            Some(FaultClass::Motion) | None => ParamId::FaultReactionMotion,
+        };
+        let reaction = FaultReaction::from_raw(self.params.get(reaction) as u8);
+        self.fault_stop.start(reaction, self.amplitude);
+        self.gear.disengage();
+        self.jog.abort();
+        self.events.push(MotionEvent::FaultRaised, fault as u32);
+        if fault == FaultCode::SupplyUndervoltage {
+            self.warn_unsaved(); // Likely powering down
+        }
+        self.beep(Melody::Fault);
+    }
+
+    /// Update frequency (ticks per second), a constant with a fixed `FREQ`.
+    #[inline(always)]
+    fn frequency(&self) -> u16 {
+        tick_rate::resolve(FREQ, self.frequency)
+    }
+
+    /// Play a status melody through the windings if beeps are enabled.
+    fn beep(&mut self, melody: Melody) {
+        if self.params.get(ParamId::BeepEnable) != 0 {
+            self.motor.beep(melody, self.frequency());
+        }
+    }
+
+    /// Get the operating state of the driver.
+    #[inline(always)]
+    pub fn status(&self) -> DriverStatus {
+        self.driver_status
+    }
+
+    /// Get the detailed operating state: calibration sub-stage and progress, energized windings,
+    /// at target velocity and zero speed.
+    pub fn state(&self) -> DriverState {
+        let calibrator = &self.angle_calibrator;
+        let (stage, progress) = if self.driver_status != DriverStatus::Calibrating {
+            (None, calibrator.progress())
+        } else if !self.supply_startup.is_ready() || self.motor.is_beeping() {
+            (Some(CalibrationStage::Startup), 0)
+        } else if let Some(progress) = self.ripple_progress() {
+            (Some(CalibrationStage::TorqueRipple), progress)
+        } else {
+            (calibrator.stage(), calibrator.progress())
+        };
+        DriverState {
+            status: self.driver_status,
+            stage,
+            progress,
+            energized: self.is_energized(),
+            at_velocity: self.speed_window.is_at_velocity(),
+            zero_speed: self.speed_window.is_zero_speed(),
+        }
+    }
+
+    /// Returns true while the bridge drives current through the windings: torque enabled in
+    /// normal operation, a current vector or beep applied otherwise.
+    pub fn is_energized(&self) -> bool {
+        if !self.bridge_enabled() {
+            return false;
+        }
+        match self.driver_status {
+            DriverStatus::Ready => self.brake.torque_enabled() || self.motor.is_beeping(),
+            _ => self.amplitude != 0 || self.motor.is_beeping(),
+        }
+    }
+
+    /// Returns false while the supply charges or the fault reaction requires the bridge
+    /// outputs switched off.
+    #[inline(always)]
+    pub fn bridge_enabled(&self) -> bool {
+        self.supply_startup.is_ready() && self.fault_stop.bridge_enabled()
+    }
+
+    /// Returns the threshold of the encoder read pair check (0 - single read) and whether the
+    /// board repeats a disagreeing pair.
+    #[inline(always)]
+    pub fn encoder_check(&self) -> (u16, bool) {
+        (self.glitch.threshold(), self.glitch.retry())
+    }
+
+    /// Count the encoder read pairs repeated by the board since the last call.
+    pub fn count_encoder_retries(&mut self, retries: u32) {
+        self.glitch.count_retries(retries);
+    }
+
+    /// Get the current sample blanking window for the board (i1.15 of the PWM period, 0 - off).
+    /// See `analog::current_blanking::is_clear()`.
+    #[inline(always)]
+    pub fn current_blanking(&self) -> i16 {
+        self.blanking.window()
+    }
+
+    /// Count the current samples blanked by the board since the last call.
+    pub fn count_blanked_samples(&mut self, blanked: u32) {
+        self.blanking.count(blanked);
+    }
+
+    /// Get the number of blanked current samples since power-up (wrapping).
+    #[inline(always)]
+    pub fn blanked_samples(&self) -> u32 {
+        self.blanking.blanked()
+    }
+
+    /// Get the encoder error counters since power-up.
+    #[inline(always)]
+    pub fn encoder_errors(&self) -> &EncoderErrorStats {
+        self.glitch.stats()
+    }
+
+    /// Notify the host about the EMC profile entering or leaving degraded operation.
+    fn report_encoder_state(&mut self) {
+        let stats = *self.glitch.stats();
+        if self.glitch.is_degraded() {
+            defmt::warn!("ENCODER: Degraded, bridging rejected samples");
+            self.events
+                .push(MotionEvent::EncoderDegraded, stats.rejected());
+        } else {
+            defmt::info!(
+                "ENCODER: Recovered ({} glitches, {} implausible, {} retries, {} resyncs)",
+                stats.glitches,
+                stats.implausible,
+                stats.retries,
+                stats.resyncs
+            );
+            self.events
+                .push(MotionEvent::EncoderRecovered, stats.rejected());
+        }
+    }
+
+    /// Get the reason of the error state (`FaultCode::None` if no fault).
+    #[inline(always)]
+    pub fn fault(&self) -> FaultCode {
+        self.fault
+    }
+
+    /// Get the state displayed by the status LED.
+    #[inline(always)]
+    pub fn indication_state(&self) -> IndicationState {
+        IndicationState {
+            status: self.driver_status,
+            fault: self.fault,
+            enabled: self.enabled,
+            #[cfg(feature = "production-test")]
+            production: self.production.report().map(|report| report.passed()),
+            #[cfg(not(feature = "production-test"))]
+            production: None,
+        }
+    }
+
+    /// Set the hardware identification reported in the device information.
+    ///
+    /// # Arguments
+    /// * `board` - Hardware variant
+    /// * `uid` - Unique MCU identifier
+    pub fn set_hardware_id(&mut self, board: BoardVariant, uid: [u8; 12]) {
+        self.device_info.board = board;
+        self.device_info.uid = uid;
+    }
+
+    /// Set the factory data loaded from its flash area at startup.
+    pub fn set_factory_data(&mut self, data: FactoryData) {
+        self.factory = Some(data);
+    }
+
+    /// Get the factory data, `None` if the unit left production without it.
+    #[inline(always)]
+    pub fn factory_data(&self) -> Option<FactoryData> {
+        self.factory
+    }
+
+    /// Request writing the staged factory data.
+    ///
+    /// Returns `false` if the unit already holds factory data, it is written only once.
+    pub fn write_factory_data(&mut self) -> bool {
+        if self.factory.is_some() {
+            return false;
+        }
+        self.factory_requested = true;
+        true
+    }
+
+    /// Returns the staged factory data while the host's write request waits for the owner,
+    /// who programs it into the factory data area and calls `factory_write_started()`.
+    pub fn factory_request(&self) -> Option<FactoryData> {
+        (self.factory_requested && !self.factory_writing).then_some(self.factory_staged)
+    }
+
+    /// Record that the staged factory data was handed to the flash writer.
+    #[inline(always)]
+    pub fn factory_write_started(&mut self) {
+        self.factory_writing = true;
+    }
+
+    /// Record the outcome of the factory data write, the data is reported as stored only once
+    /// the write succeeded. A failure is pushed as event, the host can stage and write again.
+    pub fn factory_write_done(&mut self, result: Result<(), FlashError>) {
+        if !core::mem::take(&mut self.factory_writing) {
+            return;
+        }
+        self.factory_requested = false;
+        match result {
+            Ok(()) => self.factory = Some(self.factory_staged),
+            Err(error) => self.flash_write_failed(FLASH_AREA_FACTORY, error),
+        }
+    }
+
+    /// Push the failure of a flash write to the host.
+    fn flash_write_failed(&mut self, area: u32, error: FlashError) {
+        self.events.push(
+            MotionEvent::FlashWriteFailed,
+            area | (error.code() as u32) << 8,
+        );
+    }
+
+    /// Unlock the access level of `key` for host writes, returns `false` if the key is unknown.
+    pub fn unlock(&mut self, key: u32) -> bool {
+        let Some(level) = AccessLevel::from_key(key) else {
+            return false;
+        };
+        self.access = level;
+        defmt::info!("ACCESS: {} level unlocked", level.name());
+        true
+    }
+
+    /// Return to the user access level, writes above it are refused again.
+    #[inline(always)]
+    pub fn lock(&mut self) {
+        self.access = AccessLevel::User;
+    }
+
+    /// Get the access level unlocked by the host.
+    #[inline(always)]
+    pub fn access_level(&self) -> AccessLevel {
+        self.access
+    }
+
+    /// Get the firmware build and hardware identification.
+    pub fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            calibrated: self.angle_calibrator.is_ready(),
+            ..self.device_info
+        }
+    }
+
+    /// Restart full encoder calibration.
+    pub fn recalibrate(&mut self) {
+        self.angle_calibrator = AngleCalibrator::new(self.frequency());
+        self.driver_status = DriverStatus::Calibrating;
+        self.step_follow = self.step_input() != StepInput::Off; // Follows again once ready
+        self.fault = FaultCode::None;
+        self.fault_stop.stop();
+        self.following.reset();
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.jog.stop();
+        #[cfg(feature = "production-test")]
+        self.production.abort();
+    }
+
+    /// Switch the rotor angle between the encoder and the back-EMF observer.
+    ///
+    /// The sensorless drive continues from the encoder angle and starts open loop. Back on the
+    /// encoder its position is seeded again and the calibration restarts, the synthesized angle
+    /// has drifted from the shaft.
+    fn set_angle_source(&mut self, source: AngleSource) {
+        if source == self.angle_source {
+            return;
+        }
+        self.angle_source = source;
+        match source {
+            AngleSource::Sensorless => {
+                self.configure_sensorless();
+                self.sensorless.set_angle_raw(self.position.angle());
+            }
+            AngleSource::Encoder => {
+                self.seed = EncoderSeed::new(ENCODER_SEED_LIMIT);
+                self.recalibrate();
+            }
+        }
+        defmt::info!("MOTOR: Angle source {}", source);
+    }
+
+    /// Apply the motor data and the sensorless parameters to the back-EMF observer.
+    fn configure_sensorless(&mut self) {
+        let motor = self.motor.motor();
+        let pole_pairs = (motor.pole_count / 2).max(1) as u16;
+        self.sensorless.configure(
+            motor.resistance,
+            motor.inductance,
+            pole_pairs,
+            self.params.get(ParamId::ObserverBandwidth),
+            self.params.get(ParamId::SensorlessMinEmf),
+            self.params.get(ParamId::SensorlessStartSpeed),
+        );
+    }
+
+    /// Get the source of the rotor angle.
+    #[inline(always)]
+    pub fn angle_source(&self) -> AngleSource {
+        self.angle_source
+    }
+
+    /// Returns true while the sensorless commutation follows the back-EMF observer, false
+    /// during its open-loop start or with the encoder.
+    #[inline(always)]
+    pub fn is_sensorless_locked(&self) -> bool {
+        self.angle_source == AngleSource::Sensorless && self.sensorless.is_locked()
+    }
+
+    /// Encode the calibration table into the transfer buffer read by the host.
+    ///
+    /// Returns `false` without a valid calibration table.
+    pub fn export_table(&mut self) -> bool {
+        let calibrator = &self.angle_calibrator;
+        self.table
+            .export(TableKind::Calibration, |buf| calibrator.export_table(buf))
+    }
+
+    /// Replace the calibration table by the image the host wrote into the transfer buffer.
+    ///
+    /// Returns `false` while the motor produces torque in closed loop or if the image is
+    /// invalid. A running calibration is finished and a fault cleared as by a calibration.
+    pub fn import_table(&mut self) -> bool {
+        if !self.load_table() {
+            return false;
+        }
+        self.driver_status = DriverStatus::Ready;
+        self.events.push(MotionEvent::CalibrationDone, 0);
+        self.load_angle.reset_ripple();
+        true
+    }
+
+    /// Replace the calibration table by a golden unit table and align it to this unit.
+    ///
+    /// Instead of a full calibration sweep only a single point offset trim is measured, the
+    /// motor reports calibration done once it finished. Returns `false` as `import_table()`.
+    pub fn import_golden_table(&mut self) -> bool {
+        if !self.load_table() {
+            return false;
+        }
+        self.angle_calibrator.start_unit_trim(self.angle_el);
+        self.driver_status = DriverStatus::Calibrating;
+        true
+    }
+
+    /// Load the table image from the transfer buffer into the calibrator.
+    fn load_table(&mut self) -> bool {
+        if self.driver_status == DriverStatus::Ready && self.brake.torque_enabled()
+            || self.production_running()
+        {
+            return false;
+        }
+        let payload = match self.table.payload() {
+            Ok((TableKind::Calibration, payload)) => payload,
+            Err(error) => {
+                defmt::warn!("CALIBRATION: Table image rejected ({})", error);
+                return false;
+            }
+        };
+        if !self.angle_calibrator.import_table(payload) {
+            return false;
+        }
+        self.fault = FaultCode::None;
+        self.fault_stop.stop();
+        self.following.reset();
+        true
+    }
+
+    /// Start quick recalibration refining only the zero electrical angle against the stored table.
+    ///
+    /// Returns `false` if the motor isn't calibrated yet.
+    pub fn quick_recalibrate(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready {
+            return false;
+        }
+        if !self.angle_calibrator.start_trim(self.angle_el) {
+            return false;
+        }
+        self.driver_status = DriverStatus::Calibrating;
+        true
+    }
+
+    /// Returns true if the stored calibration table drifted and a full recalibration is needed.
+    #[inline(always)]
+    pub fn needs_full_recal(&self) -> bool {
+        self.angle_calibrator.needs_full_recal()
+    }
+
+    /// Get load angle between commanded and encoder-derived electrical angle (i16 range = ±180° el).
+    #[inline(always)]
+    pub fn load_angle(&self) -> i16 {
+        self.load_angle.load_angle()
+    }
+
+    /// Get the encoder sampling instant relative to the PWM period center.
+    #[inline(always)]
+    pub fn sample_alignment(&self) -> &SampleAlignment {
+        &self.alignment
+    }
+
+    /// Get the load angle monitor for stall prediction and calibration quality checks.
+    #[inline(always)]
+    pub fn load_angle_monitor(&mut self) -> &mut LoadAngleMonitor {
+        &mut self.load_angle
+    }
+
+    /// Swap the motor type and phase connection of a disabled drive.
+    ///
+    /// Refused while the output is enabled, the windings are energized or a test runs. The
+    /// calibration table and everything identified on the previous motor are dropped and the
+    /// calibration restarts as after power-up, the torque ripple test follows it if enabled.
+    /// Inertia and friction have to be identified again by the host.
+    ///
+    /// # Arguments
+    /// * `motor` - Motor type of the new motor
+    /// * `connection` - Phase connection pattern of the new motor
+    pub fn reconfigure_motor(
+        &mut self,
+        motor: MotorType,
+        connection: PhasePattern,
+    ) -> Result<(), ReconfigError> {
+        if self.enabled {
+            return Err(ReconfigError::Enabled);
+        }
+        if self.is_energized() {
+            return Err(ReconfigError::Energized);
+        }
+        if self.production_running()
+            || self.test_signal_running()
+            || self.inertia.is_running()
+            || self.wizard_running()
+        {
+            return Err(ReconfigError::Busy);
+        }
+        self.motor_type = motor;
+        self.motor.change_motor_mode(motor);
+        self.motor.change_phase_mode(connection);
+        // Only BLDC motors run sensorless
+        if motor != MotorType::BLDC {
+            self.params.set(ParamId::AngleSource, 0).ok();
+            self.set_angle_source(AngleSource::Encoder);
+        }
+
+        self.inertia = InertiaIdentifier::new(self.frequency());
+        #[cfg(feature = "ripple-test")]
+        {
+            self.ripple = TorqueRipple::new(self.frequency());
+        }
+        self.observer = DisturbanceObserver::new(self.frequency(), 50);
+        self.friction = FrictionFeedforward::new(FrictionParams::default(), 0);
+        self.recalibrate();
+        defmt::info!("MOTOR: Reconfigured, calibration restarted");
+        Ok(())
+    }
+
+    /// Apply the data of a motor preset, `Custom` keeps the current data.
+    ///
+    /// A preset of another motor type swaps the motor as `reconfigure_motor()`, keeping the
+    /// phase connection, and is refused for the same reasons.
+    pub fn apply_preset(&mut self, preset: MotorPreset) -> Result<(), ReconfigError> {
+        let Some(data) = preset.data() else {
+            return Ok(());
+        };
+        if data.motor_type != self.motor_type {
+            let connection = self.motor.motor().connection;
+            self.reconfigure_motor(data.motor_type, connection)?;
+        }
+        let limit = data.current_limit as i32;
+        self.motor
+            .set_motor_data(data.pole_pairs, data.resistance, data.inductance, limit);
+        let (kp, ki, kd) = data.gains;
+        self.position_loop.configure(kp, ki, kd, data.current_limit);
+        if self.angle_source == AngleSource::Sensorless {
+            self.configure_sensorless();
+        }
+        defmt::info!("MOTOR: Preset {} applied", preset as u8);
+        Ok(())
+    }
+
+    /// Set the current sense scale of the board, used by the current loop and the impedance
+    /// step of the wizard. The current loop stays open without a scale.
+    ///
+    /// # Arguments
+    /// * `ua_per_count` - Coil current per current ADC count (uA), 0 - unknown
+    #[inline(always)]
+    pub fn set_current_sense(&mut self, ua_per_count: u32) {
+        self.current_sense.set_scale(ua_per_count);
+    }
+
+    /// Start the first-run setup wizard on the disabled drive.
+    ///
+    /// Returns `false` while the output is enabled or a test runs.
+    /// The calibration restarts, the open-loop steps pause it until the wizard starts it again.
+    /// Progress is reported by motion events, results are read with `wizard()` once the
+    /// measuring status flag clears.
+    #[cfg(feature = "wizard")]
+    pub fn start_wizard(&mut self) -> bool {
+        if self.enabled || self.production_running() || self.test_signal_running() {
+            return false;
+        }
+        self.recalibrate();
+        let resistance = self.motor.motor().resistance;
+        self.wizard.start(
+            CALIBRATION_CURRENT,
+            resistance,
+            self.current_sense.scale(),
+            self.motor_type,
+        );
+        defmt::info!("WIZARD: Started");
+        true
+    }
+
+    /// Abort the setup wizard, a paused calibration continues.
+    #[cfg(feature = "wizard")]
+    #[inline(always)]
+    pub fn abort_wizard(&mut self) {
+        self.wizard.abort();
+    }
+
+    /// Get the setup wizard and the results of its last run.
+    #[cfg(feature = "wizard")]
+    #[inline(always)]
+    pub fn wizard(&self) -> &SetupWizard {
+        &self.wizard
+    }
+
+    /// Configure active damping for steppers in closed loop.
+    ///
+    /// # Arguments
+    /// * `gain` - Damping gain (mA per speed unit as i24.8), zero disables damping
+    /// * `limit` - Maximum injected quadrature current (mA)
+    #[inline(always)]
+    pub fn set_damping(&mut self, gain: i32, limit: i16) {
+        self.damping.configure(gain, limit);
+        if gain == 0 {
+            self.motor.set_current_q(0);
+        }
+    }
+
+    /// Set a measured duty linearization table, replaced by the dead time model on the next
+    /// write of `DeadTimeCompensation`. Returns `false` if the table is rejected.
+    ///
+    /// # Arguments
+    /// * `table` - Commanded duty at the requested table points (see `DutyLinearizer`)
+    pub fn set_duty_linearization(&mut self, table: [i16; LINEARIZATION_POINTS]) -> bool {
+        match DutyLinearizer::from_table(table) {
+            Some(linearizer) => {
+                self.motor.set_linearization(linearizer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Configure friction and gravity feedforward added to the torque command.
+    ///
+    /// # Arguments
+    /// * `params` - Compensation terms (e.g. from `FrictionIdentifier::solve()`)
+    /// * `breakaway_speed` - Speed below which the axis is treated as standing still
+    pub fn set_friction(&mut self, params: FrictionParams, breakaway_speed: i32) {
+        self.friction = FrictionFeedforward::new(params, breakaway_speed);
+    }
+
+    /// Start the inertia identification test move.
+    ///
+    /// Returns `false` if the motor isn't calibrated yet or a test is already running.
+    ///
+    /// # Arguments
+    /// * `current` - Test current (mA), sign selects the direction of motion
+    /// * `phase_ms` - Duration of acceleration and deceleration phase in milliseconds
+    pub fn start_inertia_test(&mut self, current: i16, phase_ms: u16) -> bool {
+        if self.driver_status != DriverStatus::Ready || self.inertia.is_running() {
+            return false;
+        }
+        self.inertia.start(current, phase_ms);
+        true
+    }
+
+    /// Get identified inertia (mA per rev/s^2 as i24.8), `None` until the test move completed.
+    #[inline(always)]
+    pub fn inertia(&self) -> Option<i32> {
+        self.inertia.inertia()
+    }
+
+    /// Get the inertia identifier for auto-tuning and acceleration feedforward.
+    #[inline(always)]
+    pub fn inertia_identifier(&mut self) -> &mut InertiaIdentifier {
+        &mut self.inertia
+    }
+
+    /// Get estimated external load torque as equivalent current (mA).
+    #[inline(always)]
+    pub fn load_torque(&self) -> i32 {
+        self.observer.estimate()
+    }
+
+    /// Configure feedback of the estimated load torque into the torque command.
+    ///
+    /// # Arguments
+    /// * `feedback` - Feedback gain (0 - disabled, 255 - full compensation)
+    #[inline(always)]
+    pub fn set_disturbance_feedback(&mut self, feedback: u8) {
+        self.observer.set_feedback(feedback);
+    }
+
+    /// Re-arm collision detection and release the collision reaction.
+    pub fn clear_collision(&mut self) {
+        self.collision.clear(self.observer.estimate());
+    }
+
+    /// Returns true while a detected collision is latched.
+    #[inline(always)]
+    pub fn is_collision(&self) -> bool {
+        self.collision.is_latched()
+    }
+
+    fn collision_reaction(&self) -> CollisionReaction {
+        CollisionReaction::from_raw(self.params.get(ParamId::CollisionReaction) as u8)
+    }
+
+    /// Apply the configured collision reaction and notify the host.
+    fn react_collision(&mut self, position: i32) {
+        let load = self.collision.collision_load().unwrap_or(0);
+        self.events.push(MotionEvent::CollisionDetected, load as u32);
+        let reaction = self.collision_reaction();
+        if reaction == CollisionReaction::ReduceTorque {
+            return; // Motion continues with limited torque
+        }
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.jog.abort();
+        self.target = if reaction == CollisionReaction::Reverse {
+            // Positive load opposes positive sensor motion, back off against it
+            let distance = self.params.get(ParamId::CollisionReverse) as i32;
+            let direction = load.signum() * self.position.direction();
+            position.wrapping_sub(distance.wrapping_mul(direction))
+        } else {
+            position
+        };
+    }
+
+    /// Change the user frame of the position without recalibration, e.g. after reassembly.
+    ///
+    /// The target moves along so the axis holds still, running motions are stopped because
+    /// they were planned in the old frame.
+    ///
+    /// # Arguments
+    /// * `inverted` - User position counts against the encoder
+    /// * `offset` - User position of the encoder zero (i16 rotations + u16 angle)
+    pub fn set_encoder_frame(&mut self, inverted: bool, offset: i32) {
+        let held = self.target.wrapping_sub(self.position.to_user(0));
+        let raw_target = held.wrapping_mul(self.position.direction());
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.jog.stop();
+        #[cfg(feature = "tuning")]
+        self.step_test.stop();
+        self.position.set_frame(inverted, offset);
+        self.target = self.position.to_user(raw_target);
+    }
+
+    /// Configure standstill detection suppressing idle dither.
+    ///
+    /// # Arguments
+    /// * `speed_threshold` - Absolute speed below which the motor is considered stopped, 0 disables
+    /// * `deadband` - Position deadband (encoder counts) around the held position
+    /// * `settle_ms` - Time below speed threshold before entering standstill
+    pub fn set_standstill(&mut self, speed_threshold: i32, deadband: i32, settle_ms: u16) {
+        let settle_ticks = (settle_ms as u32 * self.frequency() as u32 / 1000).min(u16::MAX as u32);
+        self.standstill.configure(speed_threshold, deadband, settle_ticks as u16);
+        self.filter.set_alpha(FILTER_ALPHA_RUN);
+    }
+
+    /// Returns true while the motor is at standstill and its position is held.
+    #[inline(always)]
+    pub fn is_standstill(&self) -> bool {
+        self.standstill.is_active()
+    }
+
+    /// Change the command followed in normal operation, applied on the next tick.
+    ///
+    /// A position setpoint replaces the target position, a velocity setpoint moves it from
+    /// where it is. Taking over from a current or voltage angle setpoint the target starts at
+    /// the actual position, so the axis doesn't jump to a stale target.
+    pub fn set_setpoint(&mut self, setpoint: Setpoint) {
+        if setpoint.uses_position_loop() && !self.setpoint.uses_position_loop() {
+            self.target = self.position.position();
+            self.position_loop.reset();
+        }
+        if let Setpoint::Position(target) = setpoint {
+            self.target = target;
+        }
+        self.setpoint = setpoint;
+    }
+
+    /// Get the command followed in normal operation.
+    #[inline(always)]
+    pub fn setpoint(&self) -> Setpoint {
+        self.setpoint
+    }
+
+    /// Configure the position loop of the velocity and position setpoints.
+    ///
+    /// # Arguments
+    /// * `kp` - Proportional gain (percent of the error in counts as mA)
+    /// * `ki` - Integral gain (percent)
+    /// * `kd` - Derivative gain (percent)
+    /// * `limit` - Maximum torque command (mA)
+    pub fn set_position_loop(&mut self, kp: i32, ki: i32, kd: i32, limit: i16) {
+        self.position_loop.configure(kp, ki, kd, limit);
+    }
+
+    /// Set target position (i16 rotations + u16 angle) monitored by the in-position window.
+    #[inline(always)]
+    pub fn set_target_position(&mut self, target: i32) {
+        self.target = target;
+    }
+
+    /// Configure position deadband and in-position window (all in encoder counts).
+    ///
+    /// # Arguments
+    /// * `deadband` - Errors within the deadband are treated as zero
+    /// * `window` - Absolute error considered in position
+    /// * `hysteresis` - Extra error allowed before leaving in-position state
+    /// * `settle_ms` - Time the error has to stay within window before flag is raised
+    pub fn set_in_position(&mut self, deadband: u32, window: u32, hysteresis: u32, settle_ms: u16) {
+        let settle_ticks = (settle_ms as u32 * self.frequency() as u32 / 1000).min(u16::MAX as u32);
+        self.in_position
+            .configure(deadband, window, hysteresis, settle_ticks as u16);
+    }
+
+    /// Returns true once the position settled within the in-position window.
+    #[inline(always)]
+    pub fn is_in_position(&self) -> bool {
+        self.in_position.is_in_position()
+    }
+
+    /// Returns true once the speed settled within the velocity window around the velocity
+    /// setpoint, false with any other setpoint.
+    #[inline(always)]
+    pub fn is_at_velocity(&self) -> bool {
+        self.speed_window.is_at_velocity()
+    }
+
+    /// Returns true once the speed stayed below the zero speed threshold for its time.
+    #[inline(always)]
+    pub fn is_zero_speed(&self) -> bool {
+        self.speed_window.is_zero_speed()
+    }
+
+    /// Get position error to target with deadband applied.
+    #[inline(always)]
+    pub fn position_error(&self) -> i32 {
+        self.in_position.error()
+    }
+
+    /// Write a parameter and apply it, `raw_id` is the identifier received over protocol.
+    pub fn set_param(&mut self, raw_id: u16, value: u32) -> Result<(), ParamError> {
+        let id = ParamId::from_raw(raw_id).ok_or(ParamError::UnknownId)?;
+        let is_io_pin = matches!(
+            id,
+            ParamId::IoPin0 | ParamId::IoPin1 | ParamId::IoPin2 | ParamId::IoPin3
+        );
+
+        // Pin functions are validated by the mapping before being stored
+        let valid_function = IoFunction::from_raw(value as u8).is_some();
+        if is_io_pin && (value & !(IO_INVERT | 0xFF) != 0 || !valid_function) {
+            return Err(ParamError::OutOfRange);
+        }
+
+        // Preset of another motor type swaps the motor, refused while that isn't safe
+        if id == ParamId::MotorPreset {
+            let preset = MotorPreset::from_raw(value).ok_or(ParamError::OutOfRange)?;
+            self.apply_preset(preset).map_err(|_| ParamError::NotTunable)?;
+        }
+
+        // Sensorless commutation needs a BLDC motor, its observer is only swapped at rest
+        let sensorless = matches!(
+            id,
+            ParamId::AngleSource
+                | ParamId::ObserverBandwidth
+                | ParamId::SensorlessMinEmf
+                | ParamId::SensorlessStartSpeed
+        );
+        if id == ParamId::AngleSource {
+            let source = AngleSource::from_raw(value as u8).ok_or(ParamError::OutOfRange)?;
+            if source == AngleSource::Sensorless && self.motor_type != MotorType::BLDC {
+                return Err(ParamError::OutOfRange);
+            }
+        }
+        if sensorless && self.is_energized() {
+            return Err(ParamError::NotTunable);
+        }
+
+        self.params.set(id, value)?;
+        match id {
+            ParamId::EventMask => self.events.set_mask(value),
+            ParamId::IoPin0 | ParamId::IoPin1 | ParamId::IoPin2 | ParamId::IoPin3 => {
+                let pin = id as usize - ParamId::IoPin0 as usize;
+                self.io.configure(pin, value);
+            }
+            ParamId::BeepEnable => {}
+            ParamId::BeepCurrent => self.motor.set_beep_current(value as i16),
+            ParamId::BrakeReleaseDelay | ParamId::BrakeEngageDelay => {
+                let release_ms = self.params.get(ParamId::BrakeReleaseDelay) as u16;
+                let engage_ms = self.params.get(ParamId::BrakeEngageDelay) as u16;
+                self.brake.set_delays(release_ms, engage_ms);
+            }
+            ParamId::DampingGain | ParamId::DampingLimit => {
+                let gain = self.params.get(ParamId::DampingGain) as i32;
+                let limit = self.params.get(ParamId::DampingLimit) as i16;
+                self.set_damping(gain, limit);
+            }
+            ParamId::DisturbanceFeedback => self.observer.set_feedback(value as u8),
+            ParamId::ScopeChannel0 | ParamId::ScopeChannel1 | ParamId::ScopeAngleMask => {
+                self.route_scope();
+            }
+            ParamId::ScopeDecimation => self.scope.set_decimation(value as u16),
+            ParamId::ScopeEnvelope => self.scope.set_envelope(value as u8),
+            ParamId::CaptureMode => self.capture.set_mode(CaptureMode::from_raw(value as u8)),
+            ParamId::ComparePosition0
+            | ParamId::ComparePosition1
+            | ParamId::ComparePosition2
+            | ParamId::ComparePosition3 => {
+                let idx = id as usize - ParamId::ComparePosition0 as usize;
+                self.compare.set_position(idx, value as i32);
+            }
+            ParamId::CompareCount => self.compare.set_count(value as usize),
+            ParamId::CompareAction => self
+                .compare
+                .set_action(CompareAction::from_raw(value as u8)),
+            ParamId::CompareDirection => self
+                .compare
+                .set_direction(CompareDirection::from_raw(value as u8)),
+            ParamId::ComparePulseWidth => self.compare.set_pulse_width(value),
+            ParamId::GearNumerator | ParamId::GearDenominator => {
+                let numerator = self.params.get(ParamId::GearNumerator) as i32;
+                let denominator = self.params.get(ParamId::GearDenominator);
+                self.gear.set_ratio(numerator, denominator);
+            }
+            ParamId::GearOffset => self.gear.set_offset(value as i32),
+            ParamId::FollowMaxSpeed => self.gear.set_max_speed(value),
+            ParamId::FollowTimeout => self.gear.set_timeout(value),
+            ParamId::StepInput => {
+                self.step_follow = StepInput::from_raw(value as u8) != StepInput::Off;
+            }
+            ParamId::JogSpeed => self.jog.set_speed(value),
+            ParamId::JogTimeout => self.jog.set_timeout(value),
+            ParamId::JogTorque => {} // Read every tick while jogging
+            ParamId::StepCurrent
+            | ParamId::StepSpeed
+            | ParamId::StepDuration
+            | ParamId::StepTravel => {} // Read when the step test is started
+            ParamId::ProductionSupplyMin
+            | ParamId::ProductionSupplyMax
+            | ParamId::ProductionTempMin
+            | ParamId::ProductionTempMax
+            | ParamId::ProductionDeviationMax
+            | ParamId::ProductionSpreadMax => {} // Read when the production test finishes
+            ParamId::OdometryRate => self.odometry.set_rate(value),
+            ParamId::StatusRate => self.status_report.set_rate(value),
+            // Read by the platform CAN link
+            ParamId::CanNode | ParamId::CanBitrate | ParamId::CanProtocol => {}
+            ParamId::CurrentBlanking => self.blanking.configure(value),
+            ParamId::CurrentLoopKp | ParamId::CurrentLoopKi => self.motor.set_current_loop(
+                self.params.get(ParamId::CurrentLoopKp) as i32,
+                self.params.get(ParamId::CurrentLoopKi) as i32,
+            ),
+            ParamId::DeadTimeCompensation => self
+                .motor
+                .set_linearization(DutyLinearizer::from_dead_time(value as i16)),
+            ParamId::RippleTest => {} // Read when the calibration finishes
+            ParamId::MotorPreset => {} // Applied before storing
+            ParamId::AngleSource => {
+                let source = AngleSource::from_raw(value as u8).unwrap_or(AngleSource::Encoder);
+                self.set_angle_source(source);
+            }
+            ParamId::ObserverBandwidth
+            | ParamId::SensorlessMinEmf
+            | ParamId::SensorlessStartSpeed => {
+                if self.angle_source == AngleSource::Sensorless {
+                    self.configure_sensorless();
+                }
+            }
+            ParamId::EncoderInvert | ParamId::EncoderOffset => {
+                let inverted = self.params.get(ParamId::EncoderInvert) != 0;
+                let offset = self.params.get(ParamId::EncoderOffset) as i32;
+                self.set_encoder_frame(inverted, offset);
+            }
+            ParamId::EncoderGlitchThreshold => self.glitch.set_threshold(value as u16),
+            ParamId::EncoderEmcMode => self.glitch.set_emc(value != 0),
+            ParamId::SpeedWindow => self.speed_est.set_window(value as usize),
+            ParamId::SpeedFilterAlpha => self.speed_est.set_filter(value as u8),
+            ParamId::SpeedUnit => self.speed_unit = SpeedUnit::from_raw(value as u8),
+            ParamId::CollisionThreshold | ParamId::CollisionDebounce => {
+                let threshold = self.params.get(ParamId::CollisionThreshold) as i32;
+                let debounce_ms = self.params.get(ParamId::CollisionDebounce);
+                self.collision.configure(threshold, debounce_ms);
+            }
+            ParamId::StepLossThreshold | ParamId::StepLossMinSpeed | ParamId::StepLossDebounce => {
+                self.step_loss.configure(
+                    self.params.get(ParamId::StepLossThreshold),
+                    self.params.get(ParamId::StepLossMinSpeed),
+                    self.params.get(ParamId::StepLossDebounce),
+                );
+            }
+            ParamId::FollowingErrorWindow | ParamId::FollowingErrorTimeout => {
+                let window = self.params.get(ParamId::FollowingErrorWindow);
+                let timeout_ms = self.params.get(ParamId::FollowingErrorTimeout);
+                self.following.configure(window, timeout_ms);
+            }
+            ParamId::VelocityWindow
+            | ParamId::VelocityWindowTime
+            | ParamId::ZeroSpeedThreshold
+            | ParamId::ZeroSpeedTime => self.speed_window.configure(
+                self.params.get(ParamId::VelocityWindow),
+                self.params.get(ParamId::VelocityWindowTime),
+                self.params.get(ParamId::ZeroSpeedThreshold),
+                self.params.get(ParamId::ZeroSpeedTime),
+            ),
+            ParamId::CollisionReaction
+            | ParamId::CollisionReverse
+            | ParamId::CollisionTorque => {} // Read on collision
+            ParamId::FaultReactionSensor
+            | ParamId::FaultReactionSupply
+            | ParamId::FaultReactionMotion => {} // Read on fault
+            ParamId::FaultRampTime => self.fault_stop.set_ramp(value),
+            ParamId::SupplyEnableVoltage | ParamId::SupplyMaxSlope | ParamId::SupplySettleTime => {
+                self.supply_startup.configure(
+                    self.params.get(ParamId::SupplyEnableVoltage),
+                    self.params.get(ParamId::SupplyMaxSlope),
+                    self.params.get(ParamId::SupplySettleTime),
+                )
+            }
+            ParamId::SupplyUndervoltage | ParamId::SupplyOvervoltage | ParamId::SupplyMissTime => {
+                self.supply_monitor.configure(
+                    self.params.get(ParamId::SupplyUndervoltage),
+                    self.params.get(ParamId::SupplyOvervoltage),
+                    self.params.get(ParamId::SupplyMissTime),
+                )
+            }
+            ParamId::SupplyGraceTime => {} // Read once the bridge is enabled
+            ParamId::ExcitationPoint
+            | ParamId::ExcitationWaveform
+            | ParamId::ExcitationAmplitude
+            | ParamId::ExcitationFrequency
+            | ParamId::ExcitationFrequencyEnd
+            | ParamId::ExcitationDuration
+            | ParamId::BodePoints
+            | ParamId::BodePeriods
+            | ParamId::BodeResponse => {} // Read on excitation start
+            ParamId::HoldFilterAlpha => {} // Applied every tick at standstill
+            ParamId::FilterAlphaSlow
+            | ParamId::FilterAlphaFast
+            | ParamId::FilterSpeedSlow
+            | ParamId::FilterSpeedFast => self.filter_schedule.configure(
+                self.params.get(ParamId::FilterSpeedSlow),
+                self.params.get(ParamId::FilterSpeedFast),
+                self.params.get(ParamId::FilterAlphaSlow) as u8,
+                self.params.get(ParamId::FilterAlphaFast) as u8,
+            ),
+            ParamId::FilterOrder => {
+                self.filter
+                    .set_order(FilterOrder::from_u8(value as u8).unwrap_or(FilterOrder::First));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stage a hot-tunable parameter, applied with the other staged values after commit.
+    #[inline(always)]
+    pub fn stage_param(&mut self, raw_id: u16, value: u32) -> Result<(), ParamError> {
+        self.staged.stage(raw_id, value).map(|_| ())
+    }
+
+    /// Apply all staged values at the start of the next tick, returns `false` if none staged.
+    #[inline(always)]
+    pub fn commit_staged(&mut self) -> bool {
+        self.staged.commit() > 0
+    }
+
+    /// Read a parameter, `raw_id` is the identifier received over protocol.
+    #[inline(always)]
+    pub fn get_param(&self, raw_id: u16) -> Result<u32, ParamError> {
+        self.params.get_raw(raw_id)
+    }
+
+    /// Restore parameters from a stored image, migrating images of older firmware.
+    ///
+    /// Parameters missing in the image or with invalid values keep their defaults.
+    pub fn load_params(&mut self, image: &[u8]) -> Result<MigrationReport, StorageError> {
+        // Nothing is energized before the first tick, a stored preset may swap the motor
+        let enabled = core::mem::replace(&mut self.enabled, false);
+        let result = storage::load(image, |id, value| self.set_param(id, value));
+        self.enabled = enabled;
+        match result {
+            Ok(report) => report.log(),
+            Err(error) => defmt::warn!("PARAMS: No valid image ({}), using defaults", error),
+        }
+        // The loaded set (or the defaults) is what the next boot starts with again
+        self.saved_crc = self.params.crc();
+        result
+    }
+
+    /// Record that the image from `store_params()` was handed to the flash writer.
+    #[inline(always)]
+    pub fn params_queued(&mut self) {
+        self.saving_crc = Some(self.params.crc());
+    }
+
+    /// Record the outcome of the parameter save, the queued image counts as saved only once
+    /// the write succeeded. A failure is pushed as event, the parameters stay unsaved.
+    pub fn params_saved(&mut self, result: Result<(), FlashError>) {
+        let Some(crc) = self.saving_crc.take() else {
+            return;
+        };
+        match result {
+            Ok(()) => self.saved_crc = crc,
+            Err(error) => self.flash_write_failed(FLASH_AREA_PARAMS, error),
+        }
+    }
+
+    /// Returns true if the parameters changed since they were loaded or last saved.
+    #[inline(always)]
+    pub fn params_dirty(&self) -> bool {
+        self.params.crc() != self.saved_crc
+    }
+
+    /// Warn the host about unsaved parameter changes, called when the drive is disabled or
+    /// loses its supply.
+    fn warn_unsaved(&mut self) {
+        let crc = self.params.crc();
+        if crc != self.saved_crc {
+            defmt::warn!("PARAMS: Changed but not saved");
+            self.events.push(MotionEvent::UnsavedChanges, crc);
+        }
+    }
+
+    /// Switch the drive output, disabling it warns about unsaved parameter changes.
+    fn switch_output(&mut self, enabled: bool) {
+        if self.enabled && !enabled {
+            self.warn_unsaved();
+        }
+        self.enabled = enabled;
+    }
+
+    /// Write the current parameters as image into `buf`, returns the image size.
+    #[inline(always)]
+    pub fn store_params(&self, buf: &mut [u8]) -> Result<usize, StorageError> {
+        storage::store(&self.params, buf)
+    }
+
+    /// Returns true once after the host requested to save the parameters, the owner then
+    /// writes the image from `store_params()` to non-volatile memory. A request waits while
+    /// the previous image is still written.
+    #[inline(always)]
+    pub fn take_save_request(&mut self) -> bool {
+        self.saving_crc.is_none() && core::mem::take(&mut self.save_requested)
+    }
+
+    /// Returns true if the motor tolerates missed control ticks (e.g. flash stalls): output
+    /// torque off or faulted, never while calibrating.
+    pub fn tolerates_stall(&self) -> bool {
+        match self.driver_status {
+            DriverStatus::Error => true,
+            DriverStatus::Ready => !self.brake.torque_enabled(),
+            _ => false,
+        }
+    }
+
+    /// Execute a line of the text console, the answer is written to `out`.
+    ///
+    /// # Arguments
+    /// * `line` - Command line without its line ending
+    /// * `out` - Sink of the answer, e.g. the transmit buffer of the virtual serial port
+    #[cfg(feature = "console")]
+    pub fn console_command<W: Write>(&mut self, line: &str, out: &mut W) -> fmt::Result {
+        match ConsoleCommand::parse(line) {
+            Ok(command) => console::write_reply(out, &self.apply_console(command)),
+            Err(error) => console::write_result(out, Err(error.description())),
+        }
+    }
+
+    /// Execute a command parsed by `ConsoleCommand::parse()`, returns the answer for
+    /// `console::write_reply()`. Links parsing outside of the control context hand the
+    /// commands over and apply them here.
+    #[cfg(feature = "console")]
+    pub fn apply_console(&mut self, command: ConsoleCommand) -> ConsoleReply {
+        let reply = |result: ReplyResult| match result {
+            ReplyResult::Ok => Ok(()),
+            result => Err(codes::REPLY_RESULTS[result as usize].description),
+        };
+        match command {
+            ConsoleCommand::Help => ConsoleReply::Help,
+            ConsoleCommand::Status => ConsoleReply::Status(ConsoleStatus {
+                status: self.driver_status,
+                fault: self.fault,
+                enabled: self.enabled,
+                position: self.position.position(),
+                speed: self.reported_speed(),
+                at_velocity: self.speed_window.is_at_velocity(),
+                zero_speed: self.speed_window.is_zero_speed(),
+                supply_mv: self.supply.voltage_mv(),
+            }),
+            ConsoleCommand::Get(id) => ConsoleReply::Param(id, self.params.get(id)),
+            ConsoleCommand::Set(id, value) => {
+                let result = params::check_access(id as u16, self.access)
+                    .and_then(|_| self.set_param(id as u16, value))
+                    .map_or_else(ReplyResult::from, |_| ReplyResult::Ok);
+                ConsoleReply::Done(reply(result))
+            }
+            ConsoleCommand::Pid(None) => {
+                let (kp, ki, kd) = self.position_loop.gains();
+                let limit = Some(self.position_loop.limit());
+                ConsoleReply::Pid(PidGains { kp, ki, kd, limit })
+            }
+            ConsoleCommand::Pid(Some(gains)) => {
+                // The torque limit is a current limit: locked as the current limit parameters
+                // and kept within the limit of the motor
+                let limit = match gains.limit {
+                    Some(_) if self.access < AccessLevel::Advanced => {
+                        return ConsoleReply::Done(reply(ParamError::Locked.into()));
+                    }
+                    Some(limit) => {
+                        let max = self.motor.motor().max_current.clamp(0, i16::MAX as i32);
+                        limit.clamp(0, max as i16)
+                    }
+                    None => self.position_loop.limit(),
+                };
+                self.set_position_loop(gains.kp, gains.ki, gains.kd, limit);
+                ConsoleReply::Done(Ok(()))
+            }
+            ConsoleCommand::Motor(None) => ConsoleReply::Motor(self.motor_type),
+            ConsoleCommand::Motor(Some(motor)) => {
+                let connection = self.motor.motor().connection;
+                let result = self.reconfigure_motor(motor, connection);
+                ConsoleReply::Done(result.map_err(console::reconfig_reason))
+            }
+            ConsoleCommand::Enable => ConsoleReply::Done(reply(self.execute(Command::Enable, 0))),
+            ConsoleCommand::Disable => ConsoleReply::Done(reply(self.execute(Command::Disable, 0))),
+            ConsoleCommand::Calibrate => {
+                ConsoleReply::Done(reply(self.execute(Command::Calibrate, 0)))
+            }
+            ConsoleCommand::Save => ConsoleReply::Done(reply(self.execute(Command::SaveParams, 0))),
+        }
+    }
+
+    /// Handle a request frame received from the host, returns the reply frame.
+    pub fn handle_request(&mut self, frame: &Frame) -> Frame {
+        match Request::decode(frame) {
+            Some(request) => self.apply_request(request),
+            None => commands::command_reply(frame[1], ReplyResult::InvalidFrame),
+        }
+    }
+
+    /// Apply a request decoded by `Request::decode()`, returns the reply frame. Links decoding
+    /// outside of the control context hand the requests over and apply them here.
+    pub fn apply_request(&mut self, request: Request) -> Frame {
+        match request {
+            Request::ParamRead { id } => commands::param_reply(id, self.get_param(id)),
+            Request::ParamWrite { id, value } => {
+                let result = params::check_access(id, self.access)
+                    .and_then(|_| self.set_param(id, value))
+                    .map(|_| value);
+                commands::param_reply(id, result)
+            }
+            Request::ParamStage { id, value } => {
+                let result = params::check_access(id, self.access)
+                    .and_then(|_| self.stage_param(id, value))
+                    .map(|_| value);
+                commands::param_reply(id, result)
+            }
+            Request::Command { command, arg } => {
+                let result = match Command::from_raw(command) {
+                    Some(command) => self.execute(command, arg),
+                    None => ReplyResult::UnknownCommand,
+                };
+                commands::command_reply(command, result)
+            }
+            Request::Setpoint { mode, value } => match Setpoint::from_raw(mode, value) {
+                Some(setpoint) => {
+                    self.set_setpoint(setpoint);
+                    self.status_frame()
+                }
+                None => commands::command_reply(mode, ReplyResult::OutOfRange),
+            },
+            Request::StatusRead => self.status_frame(),
+            Request::StateRead => commands::state_reply(&self.state()),
+            Request::DeviceInfoRead { page } => {
+                commands::device_info_reply(page, self.device_info().page(page))
+            }
+            #[cfg(feature = "tuning")]
+            Request::ResponseRead { index, page } => {
+                let point = self.analyzer.point(index as usize);
+                commands::response_reply(index, page, point)
+            }
+            Request::CaptureRead => commands::capture_reply(
+                self.capture.position(),
+                self.capture.is_armed(),
+                self.capture.count(),
+            ),
+            Request::MasterPosition { position } => {
+                self.gear.set_master(position);
+                self.status_frame()
+            }
+            #[cfg(feature = "tuning")]
+            Request::StepResultRead { test } => {
+                commands::step_reply(test, self.step_test.result(test as usize))
+            }
+            Request::TableRead { offset } => commands::table_reply(offset, self.table.read(offset)),
+            Request::TableWrite { offset, data } => {
+                let data = data.to_le_bytes();
+                let written = self.table.write(offset, data);
+                commands::table_reply(offset, written.then_some(data))
+            }
+            #[cfg(feature = "production-test")]
+            Request::ProductionResultRead { metric } => {
+                commands::production_reply(metric, self.production.report())
+            }
+            Request::FactoryRead { page } => {
+                commands::factory_reply(page, self.factory.and_then(|data| data.page(page)))
+            }
+            Request::FactoryWrite { page, data } => {
+                // Staging is refused once the unit holds factory data or while it is written
+                let staged = self.access == AccessLevel::Factory
+                    && self.factory.is_none()
+                    && !self.factory_writing
+                    && self.factory_staged.set_page(page, data);
+                commands::factory_reply(page, staged.then_some(data))
+            }
+            Request::CalibrationReportRead { metric } => commands::calibration_reply(
+                metric,
+                CalibrationMetric::from_raw(metric).map(|metric| self.calibration_metric(metric)),
+            ),
+            #[cfg(feature = "wizard")]
+            Request::WizardResultRead { result } => commands::wizard_reply(
+                result,
+                WizardResult::from_raw(result).map(|result| self.wizard.result(result)),
+            ),
+            Request::PeakRead { value, clear } => {
+                let Some(peak) = PeakValue::from_raw(value) else {
+                    return commands::peak_reply(value, None);
+                };
+                let held = self.peaks.get(peak);
+                if clear {
+                    self.peaks.clear(peak);
+                }
+                commands::peak_reply(value, Some(held))
+            }
+            Request::TimeBeacon { seq, host_ms } => {
+                let tick = self.scope.tick();
+                self.host_clock.beacon(host_ms, tick);
+                time_sync::time_sync_reply(seq, tick)
+            }
+        }
+    }
+
+    /// Encode the status reply.
+    fn status_frame(&self) -> Frame {
+        let mut flags = 0;
+        if self.enabled {
+            flags |= commands::STATUS_ENABLED;
+        }
+        if self.in_position.is_in_position() {
+            flags |= commands::STATUS_IN_POSITION;
+        }
+        if self.standstill.is_active() {
+            flags |= commands::STATUS_STANDSTILL;
+        }
+        #[cfg(feature = "sequencer")]
+        if self.sequence.is_running() {
+            flags |= commands::STATUS_SEQUENCE;
+        }
+        #[cfg(feature = "tuning")]
+        if self.generator.is_running() {
+            flags |= commands::STATUS_EXCITATION;
+        }
+        #[cfg(feature = "tuning")]
+        if self.analyzer.is_running() || self.step_test.is_running() {
+            flags |= commands::STATUS_MEASURING;
+        }
+        if self.production_running() || self.wizard_running() {
+            flags |= commands::STATUS_MEASURING;
+        }
+        if self.gear.is_engaged() {
+            flags |= commands::STATUS_FOLLOWING;
+        }
+        if self.collision.is_latched() {
+            flags |= commands::STATUS_COLLISION;
+        }
+        let position = self.position.position();
+        commands::status_reply(self.status_byte(), self.fault as u8, flags, position)
+    }
+
+    /// Status byte of the status reply: driver status and the unsaved parameters bit.
+    fn status_byte(&self) -> u8 {
+        let mut status = self.driver_status as u8;
+        if self.params_dirty() {
+            status |= commands::DRIVER_STATUS_UNSAVED;
+        }
+        status
+    }
+
+    /// Gather the values of the slow status channel.
+    fn status_report(&self, temperature: u16) -> StatusReport {
+        let encoder = self.glitch.stats();
+        StatusReport {
+            status: self.status_byte(),
+            fault: self.fault as u8,
+            supply_mv: self.supply.voltage_mv(),
+            temperature,
+            encoder_rejected: encoder.rejected(),
+            encoder_retries: encoder.retries,
+            overflows: overflow::total(),
+        }
+    }
+
+    /// Execute a command requested by the host, `arg` is the argument of the command frame.
+    fn execute(&mut self, command: Command, arg: u32) -> ReplyResult {
+        let accepted = match command {
+            Command::WriteFactoryData if self.access != AccessLevel::Factory => {
+                return ReplyResult::AccessDenied;
+            }
+            Command::Calibrate => {
+                self.recalibrate();
+                true
+            }
+            Command::QuickRecalibrate => self.quick_recalibrate(),
+            #[cfg(feature = "sequencer")]
+            Command::StartSequence => self.start_sequence(),
+            #[cfg(feature = "sequencer")]
+            Command::StopSequence => {
+                self.stop_sequence();
+                true
+            }
+            Command::Enable => {
+                self.switch_output(true);
+                true
+            }
+            Command::Disable => {
+                self.switch_output(false);
+                true
+            }
+            Command::ApplyStaged => self.commit_staged(),
+            Command::DiscardStaged => {
+                self.staged.discard();
+                true
+            }
+            #[cfg(feature = "tuning")]
+            Command::StartExcitation => self.start_excitation(),
+            #[cfg(feature = "tuning")]
+            Command::StartFrequencyResponse => self.start_frequency_response(),
+            #[cfg(feature = "tuning")]
+            Command::StopExcitation => {
+                self.stop_excitation();
+                true
+            }
+            Command::SaveParams => {
+                self.save_requested = true;
+                true
+            }
+            Command::ArmCapture => {
+                self.capture.arm();
+                true
+            }
+            Command::ClearCollision => {
+                self.clear_collision();
+                true
+            }
+            Command::StartFollow => self.start_follow(),
+            Command::StopFollow => {
+                self.stop_follow();
+                true
+            }
+            Command::JogForward => self.jog(JogDirection::Forward),
+            Command::JogBackward => self.jog(JogDirection::Backward),
+            Command::JogStop => {
+                self.stop_jog();
+                true
+            }
+            #[cfg(feature = "tuning")]
+            Command::StartStepTest => self.start_step_test(),
+            Command::ExportTable => self.export_table(),
+            Command::ImportTable => self.import_table(),
+            Command::ImportGoldenTable => self.import_golden_table(),
+            #[cfg(feature = "production-test")]
+            Command::StartProductionTest => self.start_production_test(),
+            Command::WriteFactoryData => self.write_factory_data(),
+            Command::Unlock => self.unlock(arg),
+            Command::Lock => {
+                self.lock();
+                true
+            }
+            Command::ReconfigureMotor => {
+                let motor = MotorType::from_raw(arg & 0xFFFF);
+                let connection = PhasePattern::from_raw((arg >> 16) as u8);
+                let (Some(motor), Some(connection)) = (motor, connection) else {
+                    return ReplyResult::OutOfRange;
+                };
+                self.reconfigure_motor(motor, connection).is_ok()
+            }
+            #[cfg(feature = "wizard")]
+            Command::StartWizard => self.start_wizard(),
+            #[cfg(feature = "wizard")]
+            Command::AbortWizard => {
+                self.abort_wizard();
+                true
+            }
+            Command::ClearPeaks => {
+                self.peaks.clear_all();
+                true
+            }
+        };
+        if accepted {
+            ReplyResult::Ok
+        } else {
+            ReplyResult::Rejected
+        }
+    }
+
+    /// Raise a motion event from outside of the controller (e.g. homing or limit switches).
+    #[inline(always)]
+    pub fn push_event(&mut self, event: MotionEvent, arg: u32) {
+        self.events.push(event, arg);
+    }
+
+    /// Send pending motion events to the host, call from a lower priority task.
+    #[inline(always)]
+    pub fn flush_events<T: Transport>(&mut self, transport: &mut T) -> usize {
+        self.events.flush(transport)
+    }
+
+    /// Send the pending odometry and electrical frames to the host, call from a lower priority
+    /// task.
+    #[inline(always)]
+    pub fn flush_odometry<T: Transport>(&mut self, transport: &mut T) -> bool {
+        self.odometry.flush(transport)
+    }
+
+    /// Send the pending status report to the host, call from a lower priority task. On RTT
+    /// links use a dedicated `status` channel, the reports shouldn't queue behind telemetry.
+    #[inline(always)]
+    pub fn flush_status<T: Transport>(&mut self, transport: &mut T) -> usize {
+        self.status_report.flush(transport)
+    }
+
+    /// Start the stored motion sequence, returns `false` if the motor isn't ready.
+    #[cfg(feature = "sequencer")]
+    pub fn start_sequence(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready {
+            return false;
+        }
+        self.gear.disengage();
+        self.jog.stop();
+        self.sequence.start(self.target);
+        true
+    }
+
+    /// Stop the running motion sequence, the axis holds the last target.
+    #[cfg(feature = "sequencer")]
+    #[inline(always)]
+    pub fn stop_sequence(&mut self) {
+        self.sequence.stop();
+    }
+
+    /// Start following the master position through the electronic gearing, returns `false`
+    /// if the motor isn't ready or no master position was received yet.
+    pub fn start_follow(&mut self) -> bool {
+        if self.driver_status != DriverStatus::Ready {
+            return false;
+        }
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.jog.stop();
+        self.gear.engage(self.target)
+    }
+
+    /// Stop following the master, the axis holds the last target.
+    #[inline(always)]
+    pub fn stop_follow(&mut self) {
+        self.gear.disengage();
+    }
+
+    /// Start jogging or keep the jog running, returns `false` if the motor isn't ready, the
+    /// axis is held or the jog was aborted and not stopped since. The command has to be
+    /// repeated within the jog timeout, the torque is capped and the jog aborts at the cap.
+    /// The jog moves the target of the position setpoint, any other setpoint is replaced by a
+    /// position hold where the motor is.
+    pub fn jog(&mut self, direction: JogDirection) -> bool {
+        if self.driver_status != DriverStatus::Ready || !self.brake.motion_allowed() {
+            return false;
+        }
+        if !self.jog.start(direction) {
+            return false; // Aborted, the host has to release the jog first
+        }
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        if !matches!(self.setpoint, Setpoint::Position(_)) {
+            self.set_setpoint(Setpoint::Position(self.position.position()));
+        }
+        true
+    }
+
+    /// Stop jogging immediately, the axis holds the last target.
+    #[inline(always)]
+    pub fn stop_jog(&mut self) {
+        self.jog.stop();
+    }
+
+    /// Returns true while jogging.
+    #[inline(always)]
+    pub fn is_jogging(&self) -> bool {
+        self.jog.is_active()
+    }
+
+    /// Feed the master position (i16 rotations + u16 angle) followed by the electronic
+    /// gearing, e.g. from an ABZ input decoded by the platform. Call at a steady rate faster
+    /// than the follow timeout.
+    #[inline(always)]
+    pub fn set_master_position(&mut self, position: i32) {
+        self.gear.set_master(position);
+    }
+
+    /// Get the source counted by the STEP/DIR input, the platform feeds its count with
+    /// `set_master_position()` unless it is off.
+    #[inline(always)]
+    pub fn step_input(&self) -> StepInput {
+        StepInput::from_raw(self.params.get(ParamId::StepInput) as u8)
+    }
+
+    /// Get the node number, the bit rate (kbit/s) and the protocol of the CAN link, the platform
+    /// joins the bus with them and follows a new node number.
+    #[cfg(feature = "can")]
+    #[inline(always)]
+    pub fn can_config(&self) -> (u8, u32, CanProtocol) {
+        (
+            self.params.get(ParamId::CanNode) as u8,
+            self.params.get(ParamId::CanBitrate),
+            CanProtocol::from_raw(self.params.get(ParamId::CanProtocol) as u8),
+        )
+    }
+
+    /// Pass a frame received by a CAN link running CANopen to the CiA 402 slave.
+    #[cfg(feature = "can")]
+    pub fn canopen_receive(&mut self, id: u16, data: &[u8]) {
+        let drive = self.canopen_feedback();
+        let command = self.canopen.receive(id, data, &drive);
+        self.apply_canopen(command);
+    }
+
+    /// Follow the drive state with the CiA 402 slave and send its pending frames, call from the
+    /// task of the CAN link after the control tick.
+    #[cfg(feature = "can")]
+    pub fn flush_canopen<T: CanTransport>(&mut self, transport: &mut T) {
+        self.canopen.set_node(self.params.get(ParamId::CanNode) as u8);
+        let drive = self.canopen_feedback();
+        let command = self.canopen.update(&drive);
+        self.apply_canopen(command);
+        let drive = self.canopen_feedback();
+        let tick = self.scope.tick();
+        self.canopen.flush(transport, &drive, tick, self.frequency());
+    }
+
+    /// Drive values read by the objects of the CiA 402 slave.
+    #[cfg(feature = "can")]
+    fn canopen_feedback(&self) -> DriveFeedback {
+        let info = self.device_info;
+        let [major, minor, patch] = info.version;
+        DriveFeedback {
+            status: self.driver_status,
+            fault: self.fault,
+            enabled: self.enabled,
+            powered: self.supply_startup.is_ready(),
+            position: self.position.position(),
+            velocity: self.speed(),
+            in_position: self.in_position.is_in_position(),
+            at_velocity: self.speed_window.is_at_velocity(),
+            identity: [
+                info.board as u32,
+                (major as u32) << 16 | (minor as u32) << 8 | patch as u32,
+                self.factory.map_or(0, |data| data.serial),
+            ],
+        }
+    }
+
+    /// Apply the changes requested by the CiA 402 slave, the setpoint before the output enable.
+    #[cfg(feature = "can")]
+    fn apply_canopen(&mut self, command: DriveCommand) {
+        if let Some(setpoint) = command.setpoint {
+            self.set_setpoint(setpoint);
+        }
+        match command.action {
+            DriveAction::None => {}
+            DriveAction::Enable => self.switch_output(true),
+            DriveAction::QuickStop => self.quick_stop(),
+            DriveAction::Disable => self.switch_output(false),
+            DriveAction::FaultReset => self.reset_fault(),
+        }
+    }
+
+    /// Stop the axis with the output enabled: the motion sources stop and the position loop
+    /// brakes the axis to its actual position and holds it there.
+    #[cfg(feature = "can")]
+    fn quick_stop(&mut self) {
+        #[cfg(feature = "sequencer")]
+        self.sequence.stop();
+        self.gear.disengage();
+        self.step_follow = false;
+        self.jog.stop();
+        self.set_setpoint(Setpoint::Position(self.position.position()));
+    }
+
+    /// Clear the fault without moving the motor, the output stays disabled and the target
+    /// holds the actual position. Without an encoder table the fault stays, only a
+    /// recalibration clears it then.
+    #[cfg(feature = "can")]
+    fn reset_fault(&mut self) {
+        let sensorless = self.angle_source == AngleSource::Sensorless;
+        if self.driver_status != DriverStatus::Error
+            || !sensorless && !self.angle_calibrator.has_table()
+        {
+            return;
+        }
+        self.switch_output(false);
+        self.fault = FaultCode::None;
+        self.fault_stop.stop();
+        self.following.reset();
+        self.set_setpoint(Setpoint::Position(self.position.position()));
+        self.driver_status = DriverStatus::Ready;
+    }
+
+    /// Load motion sequence from storage (e.g. flash), returns `false` if it is invalid.
+    #[cfg(feature = "sequencer")]
+    #[inline(always)]
+    pub fn load_sequence(&mut self, raw: &[u8]) -> bool {
+        self.sequence.load(raw)
+    }
+
+    /// Get the sequence engine for editing and storing the program.
+    #[cfg(feature = "sequencer")]
+    #[inline(always)]
+    pub fn sequence(&mut self) -> &mut SequenceEngine<SEQUENCE_STEPS> {
+        &mut self.sequence
+    }
+
+    /// Update digital input levels used by sequence input waits (bit per input).
+    #[cfg(feature = "sequencer")]
+    #[inline(always)]
+    pub fn set_inputs(&mut self, inputs: u32) {
+        self.inputs = inputs;
+    }
+
+    /// Process spare digital pins, call periodically from the GPIO driver.
+    ///
+    /// # Arguments
+    /// * `levels` - Raw pin levels read from the pins, bit per pin
+    ///
+    /// Returns raw levels to be written to the pins configured as outputs.
+    pub fn tick_io(&mut self, levels: u32) -> u32 {
+        #[cfg(feature = "sequencer")]
+        {
+            self.inputs = levels;
+        }
+        let inputs = self.io.decode(levels);
+        if let Some(enable) = inputs.enable {
+            self.switch_output(enable);
+        }
+        #[cfg(feature = "sequencer")]
+        if inputs.trigger {
+            self.start_sequence();
+        }
+        if let Some(position) = self.capture.tick(inputs.capture, self.position.position()) {
+            self.events
+                .push(MotionEvent::PositionCaptured, position as u32);
+        }
+        self.io.encode(IoOutputs {
+            fault: self.driver_status == DriverStatus::Error,
+            in_position: self.in_position.is_in_position(),
+            at_velocity: self.speed_window.is_at_velocity(),
+            zero_speed: self.speed_window.is_zero_speed(),
+            brake_release: self.brake.is_released(),
+            compare: self.compare.tick(self.position.position()),
+        })
+    }
+
+    /// Latch the position at a capture edge timestamped by a timer capture channel, use
+    /// instead of a pin mapped to `IoFunction::CaptureInput` for sub-tick accuracy.
+    ///
+    /// # Arguments
+    /// * `delay` - Time from the edge to the last position sample (1/65536 tick)
+    pub fn capture_timed(&mut self, delay: u16) {
+        let speed = self.speed();
+        if let Some(position) = self.capture.capture_timed(self.position.position(), speed, delay) {
+            self.events.push(MotionEvent::PositionCaptured, position as u32);
+        }
+    }
+
+    /// Get spare pins which have to be configured as inputs, bit per pin.
+    #[inline(always)]
+    pub fn io_input_mask(&self) -> u32 {
+        self.io.input_mask()
+    }
+
+    /// Enable or disable the drive output.
+    #[inline(always)]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.switch_output(enabled);
+    }
+
+    /// Get holding brake state.
+    #[inline(always)]
+    pub fn brake_state(&self) -> brake::BrakeState {
+        self.brake.state()
+    }
+
+    /// Returns true if the drive output is enabled.
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Get current speed estimate (counts per second, user frame).
+    #[inline(always)]
+    pub fn speed(&self) -> i32 {
+        self.speed_est.get_speed() * self.position.direction()
+    }
+
+    /// Get current speed estimate in the unit selected by the speed unit parameter.
+    #[inline(always)]
+    pub fn reported_speed(&self) -> i32 {
+        self.speed_est.get_speed_in(self.speed_unit) * self.position.direction()
+    }
+
+    /// Get current PWM signals.
+    #[inline(always)]
+    pub fn get_pwm(&mut self) -> [i16; 4] {
+        self.motor.get_control()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready_controller() -> MotorController {
+        let mut motor =
+            MotorController::new(MotorType::STEP, PhasePattern::ABCD, 20_000, 24_000, 1_000);
+        motor.driver_status = DriverStatus::Ready;
+        motor.brake.tick(true, false); // No brake delays, released at once
+        motor
+    }
+
+    #[test]
+    fn jog_switches_to_position_setpoint() {
+        // Default current setpoint would ignore the jogged target
+        let mut motor = ready_controller();
+        assert_eq!(motor.setpoint(), Setpoint::Current(0));
+        assert!(motor.jog(JogDirection::Forward));
+        let hold = motor.position.position();
+        assert_eq!(motor.setpoint(), Setpoint::Position(hold));
+        assert_eq!(motor.target, hold);
+
+        // Velocity setpoint would keep integrating the target on top of the jog
+        let mut motor = ready_controller();
+        motor.set_setpoint(Setpoint::Velocity(65536));
+        assert!(motor.jog(JogDirection::Backward));
+        assert!(matches!(motor.setpoint(), Setpoint::Position(_)));
+        assert!(motor.is_jogging());
+    }
+
+    #[test]
+    fn jog_rejected_while_not_ready() {
+        let mut motor = ready_controller();
+        motor.driver_status = DriverStatus::Error;
+        assert!(!motor.jog(JogDirection::Forward));
+        assert_eq!(motor.setpoint(), Setpoint::Current(0));
     }
 }
@@ -55,8 +55,11 @@ impl PID {
         // Update previous error for the next calculation
         self.previous_error = error; // Updates the previous error.
 
+        // Calculate feedforward term
+        let ff = self.kff * feedfwd; // Computes the feedforward component.
+
         // Calculate the total output with clamping
-        self.output = (p + i + d).clamp(-limit, limit); // Calculates and clamps the total PID output.
+        self.output = (p + i + d + ff).clamp(-limit, limit); // Calculates and clamps the total PID output.
     }
 
     /// Retrieves the current output of the PID controller.
@@ -0,0 +1,87 @@
+// Implements the floating point mirror of the integer low-pass filter
+// (`math_integer::filters::lpf`), a reference for the error of the fixed point version.
+
+// Key Features:
+// - Same first order or second order (two cascaded stages) response.
+// - Alpha as a fraction, integer alpha / 256 gives the same corner.
+// - Input wraps at 65536 counts like the integer filter, both stages follow the shortest way.
+// - No rounding, the difference to the integer output is the fixed point error.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+pub use crate::math_integer::filters::lpf::FilterOrder;
+
+/// Range the input wraps at (counts)
+const TURN: f32 = 65536.0;
+
+pub struct FilterLPF {
+    alpha: f32,         // Weight of the previous value (0.0..1.0)
+    temp: f32,          // State of the first stage (counts)
+    temp2: f32,         // State of the second stage (counts)
+    output: f32,        // Filtered value (counts, 0..65536)
+    order: FilterOrder, // Number of stages
+}
+
+impl FilterLPF {
+    /// Constructor to initialize the filter with the input and alpha
+    pub fn new(input_default: f32, alpha: f32) -> Self {
+        Self {
+            alpha,
+            temp: input_default,
+            temp2: input_default,
+            output: input_default,
+            order: FilterOrder::First,
+        }
+    }
+
+    /// Math call
+    pub fn tick(&mut self, input: f32) -> f32 {
+        self.temp = Self::stage(self.temp, input, self.alpha);
+        let filtered = match self.order {
+            FilterOrder::First => self.temp,
+            FilterOrder::Second => {
+                self.temp2 = Self::stage(self.temp2, self.temp, self.alpha);
+                self.temp2
+            }
+        };
+        self.output = filtered;
+        self.output
+    }
+
+    /// Function to retrieve the output value
+    pub fn get_output(&self) -> f32 {
+        self.output
+    }
+
+    /// Changes the weight of the previous value
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    /// Changes the number of stages, the second stage starts at the current output
+    pub fn set_order(&mut self, order: FilterOrder) {
+        if order != self.order {
+            self.temp2 = self.temp;
+            self.order = order;
+        }
+    }
+
+    /// Getter for the filter order
+    pub fn order(&self) -> FilterOrder {
+        self.order
+    }
+
+    /// Settles every stage at `value`
+    pub fn reset(&mut self, value: f32) {
+        self.temp = value;
+        self.temp2 = value;
+        self.output = value;
+    }
+
+    /// One first order stage, moves by (1 - alpha) of the shortest way to the input
+    fn stage(prev: f32, current: f32, alpha: f32) -> f32 {
+        let diff = (current - prev + TURN / 2.0).rem_euclid(TURN) - TURN / 2.0;
+        (prev + diff * (1.0 - alpha)).rem_euclid(TURN)
+    }
+}
@@ -1 +1,11 @@
-pub mod controllers;
\ No newline at end of file
+// Floating point mirror of `math_integer` (feature `math-float`, needs std for the float
+// functions). Same algorithms without fixed point scaling and rounding, used in simulation
+// and as reference for the error of the integer implementations, which stay the ones running
+// on the device. Always built for the tests, `parity` checks the integer math against it.
+pub mod controllers;
+pub mod filters;
+pub mod motor;
+pub mod trigonometry;
+
+#[cfg(test)]
+mod parity;
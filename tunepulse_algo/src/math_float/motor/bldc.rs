@@ -0,0 +1,93 @@
+// Implements the floating point mirror of the integer BLDC voltage and current transforms
+// (`math_integer::motor::bldc`), a reference for the error of the fixed point version.
+
+// Key Features:
+// - Same inverse Clarke transform with centering and scaling of the phase duties.
+// - Same direct Clarke transform of dual and triple current measurements.
+// - Same Park and inverse Park transforms, without the saturation to i16.
+// - Voltages as a fraction of the supply (1.0 = full duty) instead of i16.
+
+// Detailed Operation:
+// The functions repeat the integer math step by step without shifts and truncation, so the
+// scaling conventions are identical: phase duties are centered in the available range or,
+// if the vector doesn't fit, scaled down to it and clamped to the bottom; beta of the
+// direct transform is (B - C) * sqrt(3) / 2 like the integer transform.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Precalculated sqrt(3)/2
+const SQRT3DIV2: f32 = 0.866_025_4;
+
+pub mod duty {
+    /// Calculates SVPWM duties (0.0..1.0) from the voltage vector (fraction of the supply)
+    pub fn ab2abc(voltg_sin: f32, voltg_cos: f32) -> (f32, f32, f32) {
+        let (mut voltg_a, mut voltg_b, mut voltg_c) =
+            super::inverse_clarke_tf(voltg_sin, voltg_cos);
+
+        let voltg_min = voltg_a.min(voltg_b).min(voltg_c);
+        let voltg_max = voltg_a.max(voltg_b).max(voltg_c);
+        let voltg_full_scale = voltg_max - voltg_min;
+
+        // Scale down and clamp to the bottom if the supply isn't enough, center otherwise
+        let voltg_offset = if voltg_full_scale > 1.0 {
+            let voltg_scale = 1.0 / voltg_full_scale;
+            voltg_a *= voltg_scale;
+            voltg_b *= voltg_scale;
+            voltg_c *= voltg_scale;
+            -voltg_min * voltg_scale
+        } else {
+            (1.0 - voltg_max - voltg_min) / 2.0
+        };
+
+        // Zero voltage keeps all phases low (maximum brake)
+        if voltg_full_scale != 0.0 {
+            voltg_a += voltg_offset;
+            voltg_b += voltg_offset;
+            voltg_c += voltg_offset;
+        }
+        (voltg_a, voltg_b, voltg_c)
+    }
+}
+
+pub mod current {
+    /// Converts dual current measurements from ABC to AB system (Ia + Ib + Ic = 0)
+    pub fn dual(curnt_a: f32, curnt_b: f32) -> (f32, f32) {
+        super::direct_clarke_tf(curnt_a, curnt_b, -(curnt_a + curnt_b))
+    }
+
+    /// Converts triple current measurements from ABC to AB system
+    pub fn triple(curnt_a: f32, curnt_b: f32, curnt_c: f32) -> (f32, f32) {
+        super::direct_clarke_tf(curnt_a, curnt_b, curnt_c)
+    }
+}
+
+/// Inverse Clarke transform, phase values (A, B, C) from the `sin` (alpha) and `cos` (beta)
+fn inverse_clarke_tf(sin: f32, cos: f32) -> (f32, f32, f32) {
+    let beta_sqrt3_div2 = SQRT3DIV2 * cos;
+    (
+        sin,
+        -sin / 2.0 + beta_sqrt3_div2,
+        -sin / 2.0 - beta_sqrt3_div2,
+    )
+}
+
+/// Direct Clarke transform, `alpha` and `beta` from the phase values
+fn direct_clarke_tf(a: f32, b: f32, c: f32) -> (f32, f32) {
+    (a, (b - c) * SQRT3DIV2)
+}
+
+/// Park transform, direct and quadrature components of the `alpha`/`beta` vector in the frame
+/// rotating with the angle given by `sincos`
+pub fn park_transform(alpha_beta: (f32, f32), sincos: (f32, f32)) -> (f32, f32) {
+    let (alpha, beta) = alpha_beta;
+    let (sin, cos) = sincos;
+    (alpha * sin + beta * cos, alpha * cos - beta * sin)
+}
+
+/// Inverse Park transform, `alpha` and `beta` components of the direct/quadrature vector
+pub fn inverse_park_transform(d_q: (f32, f32), sincos: (f32, f32)) -> (f32, f32) {
+    let (d, q) = d_q;
+    let (sin, cos) = sincos;
+    (d * sin + q * cos, d * cos - q * sin)
+}
@@ -0,0 +1,249 @@
+// Implements the parity tests of the integer math against its floating point mirror.
+
+// Detailed Operation:
+// Every integer function is run over an exhaustive or pseudo-random input set next to the
+// `math_float` version with the same inputs, the largest difference is compared with the bound
+// the fixed point implementation is expected to hold (in units of the integer output).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use std::f64::consts::TAU;
+use std::vec::Vec;
+
+use crate::math_float;
+use crate::math_integer;
+use crate::math_integer::filters::lpf::FilterOrder;
+
+/// Full scale of i1.15 values
+const Q15: f64 = 32767.0;
+/// Random inputs per check
+const SAMPLES: usize = 100_000;
+
+/// Deterministic xorshift generator, the same inputs on every run
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn i16(&mut self) -> i16 {
+        self.next() as i16
+    }
+
+    /// Uniform in `-range..range`
+    fn signed(&mut self, range: i32) -> i32 {
+        (self.next() % (2 * range as u64)) as i32 - range
+    }
+}
+
+/// Largest error of one check
+struct Check {
+    name: &'static str,
+    bound: f64, // Largest allowed difference
+    error: f64, // Largest difference seen
+}
+
+impl Check {
+    fn new(name: &'static str, bound: f64) -> Self {
+        Self {
+            name,
+            bound,
+            error: 0.0,
+        }
+    }
+
+    fn compare(&mut self, integer: f64, float: f64) {
+        self.error = self.error.max((integer - float).abs());
+    }
+
+    /// Fails the test if the largest difference exceeds the bound
+    fn assert(&self) {
+        assert!(
+            self.error <= self.bound,
+            "{}: error {:.4} exceeds the bound {}",
+            self.name,
+            self.error,
+            self.bound
+        );
+    }
+}
+
+/// Difference of two positions on a circle of 65536 counts, the short way
+fn wrapped(a: f64, b: f64) -> f64 {
+    (a - b + 32768.0).rem_euclid(65536.0) - 32768.0
+}
+
+fn trigonometry(random: &mut Random) -> Vec<Check> {
+    // 1024 table points per turn without interpolation: up to 2π/1024 of full scale
+    let mut sincos = Check::new("angle2sincos (i1.15)", 202.0);
+    for angle in i16::MIN..=i16::MAX {
+        let (sin, cos) = math_integer::trigonometry::angle2sincos(angle);
+        let radians = (angle as u16) as f64 * TAU / 65536.0;
+        let (sin_f, cos_f) = math_float::trigonometry::angle2sincos(radians as f32);
+        sincos.compare(sin as f64, sin_f as f64 * Q15);
+        sincos.compare(cos as f64, cos_f as f64 * Q15);
+    }
+
+    let mut scale = Check::new("scale_sincos (i1.15)", 2.0);
+    let mut rotate = Check::new("rotate_sincos (i1.15)", 3.0);
+    for _ in 0..SAMPLES {
+        let angle = random.next() as f64 * TAU / u64::MAX as f64;
+        let (sin, cos) = ((angle.sin() * Q15) as i16, (angle.cos() * Q15) as i16);
+        let float = |value: i16| value as f32 / Q15 as f32;
+
+        let factor = random.i16();
+        let (a, b) = math_integer::trigonometry::scale_sincos((sin, cos), factor);
+        let (a_f, b_f) =
+            math_float::trigonometry::scale_sincos((float(sin), float(cos)), float(factor));
+        scale.compare(a as f64, a_f as f64 * Q15);
+        scale.compare(b as f64, b_f as f64 * Q15);
+
+        let offset = random.next() as f64 * TAU / u64::MAX as f64;
+        let (offset_sin, offset_cos) = ((offset.sin() * Q15) as i16, (offset.cos() * Q15) as i16);
+        let (a, b) =
+            math_integer::trigonometry::rotate_sincos((sin, cos), (offset_sin, offset_cos));
+        let (a_f, b_f) = math_float::trigonometry::rotate_sincos(
+            (float(sin), float(cos)),
+            (float(offset_sin), float(offset_cos)),
+        );
+        rotate.compare(a as f64, a_f as f64 * Q15);
+        rotate.compare(b as f64, b_f as f64 * Q15);
+    }
+
+    // 16 CORDIC iterations: angle to a few counts, gain compensation to 0.01 %
+    let mut magnitude = Check::new("vector2mag_angle magnitude (%)", 0.01);
+    let mut angle = Check::new("vector2mag_angle angle (counts)", 4.0);
+    for _ in 0..SAMPLES {
+        let (x, y) = (random.signed(1 << 28), random.signed(1 << 28));
+        if x.unsigned_abs().max(y.unsigned_abs()) < 1 << 12 {
+            continue; // Too short for the angle resolution
+        }
+        let (mag, ang) = math_integer::trigonometry::vector2mag_angle(x, y);
+        let (mag_f, ang_f) = math_float::trigonometry::vector2mag_angle(x as f32, y as f32);
+        magnitude.compare(mag as f64 * 100.0 / mag_f as f64, 100.0);
+        angle.error = angle
+            .error
+            .max(wrapped(ang as f64, ang_f as f64 * 65536.0 / TAU).abs());
+    }
+    Vec::from([sincos, scale, rotate, magnitude, angle])
+}
+
+fn bldc(random: &mut Random) -> Vec<Check> {
+    let mut duty = Check::new("bldc duty ab2abc (i16)", 4.0);
+    let mut dual = Check::new("bldc current dual (i16)", 2.0);
+    let mut triple = Check::new("bldc current triple (i16)", 2.0);
+    for _ in 0..SAMPLES {
+        let (sin, cos) = (random.i16(), random.i16());
+        let (a, b, c) = math_integer::motor::bldc::duty::ab2abc(sin, cos);
+        let (a_f, b_f, c_f) =
+            math_float::motor::bldc::duty::ab2abc(sin as f32 / Q15 as f32, cos as f32 / Q15 as f32);
+        for (integer, float) in [(a, a_f), (b, b_f), (c, c_f)] {
+            duty.compare(integer as f64, float as f64 * Q15);
+        }
+
+        // Phase currents of a balanced measurement, kept inside i16 after the transform
+        let (ia, ib) = (random.i16() / 4, random.i16() / 4);
+        let ic = -(ia + ib);
+        let (alpha, beta) = math_integer::motor::bldc::current::dual(ia, ib);
+        let (alpha_f, beta_f) = math_float::motor::bldc::current::dual(ia as f32, ib as f32);
+        dual.compare(alpha as f64, alpha_f as f64);
+        dual.compare(beta as f64, beta_f as f64);
+        let (alpha, beta) = math_integer::motor::bldc::current::triple(ia, ib, ic);
+        let (alpha_f, beta_f) =
+            math_float::motor::bldc::current::triple(ia as f32, ib as f32, ic as f32);
+        triple.compare(alpha as f64, alpha_f as f64);
+        triple.compare(beta as f64, beta_f as f64);
+    }
+    Vec::from([duty, dual, triple])
+}
+
+fn lpf(random: &mut Random) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for (name, order) in [
+        ("lpf first order (counts)", FilterOrder::First),
+        ("lpf second order (counts)", FilterOrder::Second),
+    ] {
+        // Output rounded to nearest count, the state keeps 16 fractional bits
+        let mut check = Check::new(name, 1.0);
+        for alpha in [0u8, 64, 128, 200, 240, 250] {
+            let mut integer = math_integer::filters::lpf::FilterLPF::new(0, alpha);
+            let mut float = math_float::filters::lpf::FilterLPF::new(0.0, alpha as f32 / 256.0);
+            integer.set_order(order);
+            float.set_order(order);
+            // Steps, a ramp across the wrap and noise around a fixed position
+            let mut position = 0i32;
+            for tick in 0..20_000 {
+                position = match tick / 5_000 {
+                    0 => {
+                        if tick % 1_000 < 500 {
+                            1_000
+                        } else {
+                            60_000
+                        }
+                    }
+                    1 => position + 37,
+                    2 => 30_000 + random.signed(200),
+                    _ => position - 113,
+                };
+                let input = position.rem_euclid(65536);
+                let out = integer.tick(input as u16);
+                let out_f = float.tick(input as f32);
+                check.error = check.error.max(wrapped(out as f64, out_f as f64).abs());
+            }
+        }
+        checks.push(check);
+    }
+    checks
+}
+
+fn pid(random: &mut Random) -> Vec<Check> {
+    // The integer gains are stored in 1/128 steps, the float one gets the same quantized gain
+    // so only the truncation is left: a count per term, and the floor of the Tustin half sum
+    // that drifts the integral by up to half a count per tick until the clamp catches it
+    let gain = |percent: i32| ((percent << 7) / 100) as f32 / 128.0;
+    let mut check = Check::new("pid output (i16)", 32.0);
+    for (kp, ki, kd) in [(100, 0, 0), (150, 10, 0), (50, 50, 20), (300, 5, 100)] {
+        let mut integer = math_integer::controllers::pid::PID::new(kp, ki, kd, 0);
+        let mut float = math_float::controllers::pid::PID::new(gain(kp), gain(ki), gain(kd), 0.0);
+        let mut error = 0i32;
+        for _ in 0..SAMPLES / 10 {
+            // Random walk of the error, like a loop tracking a moving target
+            error = (error + random.signed(500)).clamp(-8_000, 8_000);
+            integer.tick(error as i16, 0, i16::MAX);
+            float.tick(error as f32, 0.0, i16::MAX as f32);
+            check.compare(integer.output() as f64, float.output() as f64);
+        }
+    }
+    Vec::from([check])
+}
+
+/// Runs a group of checks with the fixed seed, the same inputs on every run
+fn run(group: fn(&mut Random) -> Vec<Check>) {
+    let mut random = Random(0x2545_F491_4F6C_DD1D);
+    group(&mut random).iter().for_each(Check::assert);
+}
+
+#[test]
+fn trigonometry_parity() {
+    run(trigonometry);
+}
+
+#[test]
+fn bldc_parity() {
+    run(bldc);
+}
+
+#[test]
+fn lpf_parity() {
+    run(lpf);
+}
+
+#[test]
+fn pid_parity() {
+    run(pid);
+}
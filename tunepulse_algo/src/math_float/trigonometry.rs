@@ -0,0 +1,36 @@
+// Implements the floating point mirror of the integer trigonometry
+// (`math_integer::trigonometry`), a reference for the error of the lookup table and CORDIC.
+
+// Key Features:
+// - Angles in radians, sine and cosine in -1.0..1.0 instead of i1.15.
+// - Same functions: sine and cosine of an angle, scaling, rotation and vector magnitude/angle.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::f32::consts::TAU;
+
+/// Computes the sine and cosine of an angle (radians)
+pub fn angle2sincos(angle: f32) -> (f32, f32) {
+    angle.sin_cos()
+}
+
+/// Scales sine and cosine values by a given scale factor
+pub fn scale_sincos(input: (f32, f32), scale: f32) -> (f32, f32) {
+    (input.0 * scale, input.1 * scale)
+}
+
+/// Rotates a vector given by its sine and cosine components by the angle of another one
+pub fn rotate_sincos(source: (f32, f32), offset: (f32, f32)) -> (f32, f32) {
+    let (source_sin, source_cos) = source;
+    let (offset_sin, offset_cos) = offset;
+    (
+        source_sin * offset_cos + source_cos * offset_sin,
+        source_cos * offset_cos - source_sin * offset_sin,
+    )
+}
+
+/// Computes magnitude and angle (radians, 0..2π counter-clockwise from positive X) of a vector
+pub fn vector2mag_angle(x: f32, y: f32) -> (f32, f32) {
+    (x.hypot(y), y.atan2(x).rem_euclid(TAU))
+}
@@ -0,0 +1,106 @@
+// Implements the active damping controller, suppressing mid-band resonance of stepper motors
+// by injecting a current term proportional to the oscillating part of the rotor speed.
+
+// Key Features:
+// - Separates speed oscillation from the commanded motion with a slow running average.
+// - Produces a quadrature current counteracting the oscillation.
+// - Configurable gain and output limit, zero gain disables damping.
+
+// Detailed Operation:
+// Mid-band resonance shows up as speed oscillation around the mean speed. The controller keeps
+// a slow leaky average of the measured speed and treats the difference as the oscillating part.
+// The output current is proportional to this difference with opposite sign, so it works like
+// a viscous damper acting only on oscillations, without braking the intended motion.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Electronic damping controller producing a velocity-proportional quadrature current.
+pub struct ActiveDamping {
+    /// Damping gain (mA per speed unit as i24.8)
+    gain: i32,
+
+    /// Maximum absolute output current (mA)
+    limit: i32,
+
+    /// Slow average of the speed (i24.8)
+    mean: i32,
+
+    /// Output current (mA)
+    output: i16,
+
+    /// Gain doubled while a resonance is present
+    boost: bool,
+}
+
+impl ActiveDamping {
+    /// Bit shift of the speed average (time constant of 2^8 ticks)
+    const MEAN_SHIFT: u32 = 8;
+
+    /// Speed limit that keeps the scaled speed and its differences within i32
+    const SPEED_MAX: i32 = i32::MAX >> 10;
+
+    /// Creates a new damping controller.
+    ///
+    /// # Arguments
+    /// * `gain` - Damping gain (mA per speed unit as i24.8)
+    /// * `limit` - Maximum absolute output current (mA)
+    pub fn new(gain: i32, limit: i16) -> Self {
+        Self {
+            gain,
+            limit: limit.unsigned_abs() as i32,
+            mean: 0,
+            output: 0,
+            boost: false,
+        }
+    }
+
+    /// Math call, returns quadrature current in milliamps.
+    pub fn tick(&mut self, speed: i32) -> i16 {
+        // Use 8 extra bits to avoid losing small speed changes in the average
+        let speed_scaled = speed.clamp(-Self::SPEED_MAX, Self::SPEED_MAX) << 8;
+
+        if self.gain == 0 {
+            self.mean = speed_scaled; // Keep average tracking so enabling damping doesn't kick
+            self.output = 0;
+            return 0;
+        }
+
+        // Leaky average of the speed
+        self.mean += (speed_scaled - self.mean) >> Self::MEAN_SHIFT;
+
+        // Oscillating part of the speed
+        let oscillation = (speed_scaled - self.mean) >> 8;
+
+        // Counteract oscillation (i64 to avoid overflow with high gains)
+        let gain = if self.boost {
+            self.gain.saturating_mul(2)
+        } else {
+            self.gain
+        };
+        let output = -((oscillation as i64 * gain as i64) >> 8);
+        self.output = output.clamp(-self.limit as i64, self.limit as i64) as i16;
+        self.output
+    }
+
+    /// Changes damping gain and output limit, zero gain disables damping.
+    pub fn configure(&mut self, gain: i32, limit: i16) {
+        self.gain = gain;
+        self.limit = limit.unsigned_abs() as i32;
+    }
+
+    /// Doubles the gain while set, e.g. while a resonance is detected
+    pub fn set_boost(&mut self, boost: bool) {
+        self.boost = boost;
+    }
+
+    /// Getter for damping gain
+    pub fn gain(&self) -> i32 {
+        self.gain
+    }
+
+    /// Getter for output current
+    pub fn output(&self) -> i16 {
+        self.output
+    }
+}
@@ -0,0 +1,90 @@
+// Implements a fixed-point disturbance observer estimating the external load torque.
+
+// Key Features:
+// - Estimates load torque (as equivalent current) from commanded current and measured speed.
+// - Avoids explicit differentiation of the noisy speed signal.
+// - Optional feedback of the estimate for stiffer disturbance rejection.
+
+// Detailed Operation:
+// The mechanical model J * a = I - D relates commanded current I, inertia J and the load
+// disturbance D. A direct estimate D = I - J * a requires the acceleration, which is very noisy.
+// Instead the observer low-pass filters the sum I + L * v with L = J * wc and subtracts L * v
+// again, which equals the first-order filtered estimate of D with cutoff wc without any
+// derivative. The estimate scaled by the feedback gain may be added to the torque command to
+// cancel the load.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Encoder counts per mechanical revolution
+const COUNTS_PER_REV: i64 = 1 << 16;
+
+/// Disturbance observer for load torque estimation.
+pub struct DisturbanceObserver {
+    inertia: i32,  // Inertia (mA per rev/s^2 as i24.8)
+    omega: i32,    // Observer cutoff (rad/s)
+    gain: i32,     // Filter gain per tick as i16.16
+    state: i64,    // Filter state (mA as i48.16)
+    estimate: i32, // Estimated disturbance (mA)
+    feedback: u8,  // Feedback gain (0 - disabled, 255 - full compensation)
+}
+
+impl DisturbanceObserver {
+    /// Creates a new observer.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `cutoff_hz` - Observer bandwidth in Hz
+    pub fn new(frequency: u16, cutoff_hz: u16) -> Self {
+        let omega = (cutoff_hz as i32 * 6283) / 1000; // 2 * pi * f
+        let gain = ((omega as i64) << 16) / frequency.max(1) as i64;
+        Self {
+            inertia: 0,
+            omega,
+            gain: gain.min(1 << 16) as i32,
+            state: 0,
+            estimate: 0,
+            feedback: 0,
+        }
+    }
+
+    /// Math call, returns estimated disturbance (mA).
+    ///
+    /// # Arguments
+    /// * `current` - Current applied to the motor (mA)
+    /// * `speed` - Measured speed (counts/s)
+    pub fn tick(&mut self, current: i32, speed: i32) -> i32 {
+        // L * v = J * wc * v, J is per rev/s^2 so speed is converted to rev/s
+        let lv = ((speed as i64 * self.inertia as i64 * self.omega as i64) / COUNTS_PER_REV) >> 8;
+        let input = (current as i64 + lv) << 16;
+        self.state += ((input - self.state) * self.gain as i64) >> 16;
+        self.estimate = ((self.state >> 16) - lv).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.estimate
+    }
+
+    /// Sets inertia used by the model (mA per rev/s^2 as i24.8)
+    pub fn set_inertia(&mut self, inertia: i32) {
+        self.inertia = inertia;
+    }
+
+    /// Sets feedback gain (0 - disabled, 255 - full compensation)
+    pub fn set_feedback(&mut self, feedback: u8) {
+        self.feedback = feedback;
+    }
+
+    /// Getter for estimated disturbance (mA)
+    pub fn estimate(&self) -> i32 {
+        self.estimate
+    }
+
+    /// Current (mA) to add to the torque command for disturbance rejection
+    pub fn compensation(&self) -> i32 {
+        ((self.estimate as i64 * self.feedback as i64) >> 8) as i32
+    }
+
+    /// Clears observer state
+    pub fn reset(&mut self) {
+        self.state = 0;
+        self.estimate = 0;
+    }
+}
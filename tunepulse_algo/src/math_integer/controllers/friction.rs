@@ -0,0 +1,198 @@
+// Implements friction and gravity feedforward compensation together with an identification
+// routine estimating the compensation terms from a slow bidirectional sweep.
+
+// Key Features:
+// - Static (breakaway), Coulomb and viscous friction feedforward.
+// - Constant gravity torque offset for vertical axes.
+// - Least squares identification of all terms from current and speed samples.
+
+// Detailed Operation:
+// The feedforward adds a current to the torque command that cancels the expected friction and
+// gravity load. Below the breakaway speed the static friction is applied in the direction of the
+// command, above it Coulomb friction follows the direction of motion and viscous friction grows
+// linearly with speed. The identifier collects steady-state samples of current and speed while
+// the axis is swept slowly in both directions. A straight line I = a + b * v is fitted for each
+// direction: Coulomb friction is half the difference of the intercepts, gravity is their mean
+// and viscous friction is the mean slope. Static friction is taken from the highest current
+// observed before the axis started moving in each direction.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Friction and gravity compensation terms (currents in mA).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrictionParams {
+    /// Current needed to break away from standstill
+    pub static_ma: i32,
+    /// Constant current opposing motion
+    pub coulomb_ma: i32,
+    /// Current per speed unit as i24.8
+    pub viscous: i32,
+    /// Constant current holding the load against gravity (signed)
+    pub gravity_ma: i32,
+}
+
+/// Feedforward summing friction and gravity compensation into the torque command.
+pub struct FrictionFeedforward {
+    /// Compensation terms
+    params: FrictionParams,
+    /// Speed below which the axis is treated as standing still
+    breakaway_speed: i32,
+    /// Last feedforward output (mA)
+    output: i32,
+}
+
+impl FrictionFeedforward {
+    /// Creates a new feedforward block.
+    ///
+    /// # Arguments
+    /// * `params` - Compensation terms, `FrictionParams::default()` disables compensation
+    /// * `breakaway_speed` - Speed below which the axis is treated as standing still
+    pub fn new(params: FrictionParams, breakaway_speed: i32) -> Self {
+        Self {
+            params,
+            breakaway_speed: breakaway_speed.abs(),
+            output: 0,
+        }
+    }
+
+    /// Math call, returns torque command with compensation added.
+    ///
+    /// # Arguments
+    /// * `current` - Torque command (mA)
+    /// * `speed` - Measured speed
+    pub fn tick(&mut self, current: i32, speed: i32) -> i32 {
+        let friction = if speed.abs() < self.breakaway_speed {
+            // At standstill static friction opposes the commanded torque
+            self.params.static_ma * current.signum()
+        } else {
+            // In motion Coulomb and viscous friction oppose the movement
+            let viscous = (speed as i64 * self.params.viscous as i64) >> 8;
+            self.params.coulomb_ma * speed.signum() + viscous as i32
+        };
+        self.output = friction + self.params.gravity_ma;
+        current.saturating_add(self.output)
+    }
+
+    /// Changes compensation terms
+    pub fn set_params(&mut self, params: FrictionParams) {
+        self.params = params;
+    }
+
+    /// Getter for compensation terms
+    pub fn params(&self) -> FrictionParams {
+        self.params
+    }
+
+    /// Getter for last feedforward output (mA)
+    pub fn output(&self) -> i32 {
+        self.output
+    }
+}
+
+/// Sums for least squares fit of I = a + b * v in one direction of motion.
+#[derive(Clone, Copy, Default)]
+struct LineFit {
+    n: i64,
+    sum_v: i64,
+    sum_i: i64,
+    sum_vv: i64,
+    sum_vi: i64,
+}
+
+impl LineFit {
+    fn add(&mut self, speed: i32, current: i32) {
+        let (v, i) = (speed as i64, current as i64);
+        self.n += 1;
+        self.sum_v += v;
+        self.sum_i += i;
+        self.sum_vv += v * v;
+        self.sum_vi += v * i;
+    }
+
+    /// Returns (intercept, slope as i24.8) or `None` if speeds don't differ enough.
+    fn solve(&self) -> Option<(i32, i32)> {
+        let det = self.n * self.sum_vv - self.sum_v * self.sum_v;
+        if self.n < 2 || det == 0 {
+            return None;
+        }
+        let slope = ((self.n * self.sum_vi - self.sum_v * self.sum_i) << 8) / det;
+        let intercept = (self.sum_i - ((slope * self.sum_v) >> 8)) / self.n;
+        Some((intercept as i32, slope as i32))
+    }
+}
+
+/// Identifies friction and gravity terms from samples collected during a slow sweep.
+pub struct FrictionIdentifier {
+    /// Speed below which the axis is treated as standing still
+    breakaway_speed: i32,
+    /// Fits for positive and negative direction of motion
+    fit_pos: LineFit,
+    fit_neg: LineFit,
+    /// Highest standstill current in positive and negative direction
+    static_pos: i32,
+    static_neg: i32,
+}
+
+impl FrictionIdentifier {
+    /// Creates a new identifier.
+    ///
+    /// # Arguments
+    /// * `breakaway_speed` - Speed below which the axis is treated as standing still
+    pub fn new(breakaway_speed: i32) -> Self {
+        Self {
+            breakaway_speed: breakaway_speed.abs(),
+            fit_pos: LineFit::default(),
+            fit_neg: LineFit::default(),
+            static_pos: 0,
+            static_neg: 0,
+        }
+    }
+
+    /// Adds one steady-state sample of torque current (mA) and measured speed.
+    pub fn sample(&mut self, current: i32, speed: i32) {
+        if speed.abs() < self.breakaway_speed {
+            // Standstill: the highest current before motion starts is the breakaway current
+            self.static_pos = self.static_pos.max(current);
+            self.static_neg = self.static_neg.min(current);
+        } else if speed > 0 {
+            self.fit_pos.add(speed, current);
+        } else {
+            self.fit_neg.add(speed, current);
+        }
+    }
+
+    /// Estimates compensation terms, returns `None` until both directions were swept at
+    /// two or more different speeds.
+    pub fn solve(&self) -> Option<FrictionParams> {
+        let (a_pos, b_pos) = self.fit_pos.solve()?;
+        let (a_neg, b_neg) = self.fit_neg.solve()?;
+
+        let gravity_ma = (a_pos + a_neg) / 2;
+        let coulomb_ma = ((a_pos - a_neg) / 2).max(0);
+        let viscous = ((b_pos + b_neg) / 2).max(0);
+
+        // Static friction is breakaway current without gravity share, at least Coulomb friction
+        let static_ma = ((self.static_pos - self.static_neg) / 2).max(coulomb_ma);
+
+        defmt::info!(
+            "FRICTION: Static: {}mA; Coulomb: {}mA; Viscous: {}; Gravity: {}mA",
+            static_ma,
+            coulomb_ma,
+            viscous,
+            gravity_ma
+        );
+
+        Some(FrictionParams {
+            static_ma,
+            coulomb_ma,
+            viscous,
+            gravity_ma,
+        })
+    }
+
+    /// Drops all collected samples
+    pub fn reset(&mut self) {
+        *self = Self::new(self.breakaway_speed);
+    }
+}
@@ -1 +1,4 @@
-pub mod pid;
\ No newline at end of file
+pub mod pid;
+pub mod damping;
+pub mod friction;
+pub mod disturbance;
\ No newline at end of file
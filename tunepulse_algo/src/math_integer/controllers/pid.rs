@@ -1,3 +1,5 @@
+use crate::diagnostics::overflow::{self, OverflowSite};
+
 /// A Proportional-Integral-Derivative (PID) controller implementation
 /// to calculate corrective action for controlling dynamic systems.
 ///
@@ -103,7 +105,10 @@ impl PID {
 
         // ############################## OUTPUT ######################################
         // Calculate the total output by combining all components
-        let output = p + i + d + ff; // Maximum possible value: ±500 * 2^15
+        // Maximum possible value: ±500 * 2^15
+        let output = overflow::add_i32(p, i, OverflowSite::PidSum);
+        let output = overflow::add_i32(output, d, OverflowSite::PidSum);
+        let output = overflow::add_i32(output, ff, OverflowSite::PidSum);
 
         // Apply fixed-point math correction to the output
         let output = Self::fixed_point_correction(output);
@@ -133,10 +138,11 @@ impl PID {
     /// The scaled value after applying the gain coefficient.
     #[inline(always)]
     fn apply_coef(value: i32, coef: i32) -> i32 {
+        let product = overflow::mul_i32(value, coef, OverflowSite::PidSum);
         if !Self::FAST_MATH {
-            (value * coef) >> Self::SLOW_MATH_SCALE
+            product >> Self::SLOW_MATH_SCALE
         } else {
-            (value * coef) >> 7
+            product >> 7
         }
     }
 
@@ -0,0 +1,76 @@
+// Implements a speed-adaptive coefficient schedule for `FilterLPF`, strong filtering at low
+// speed where noise dominates and high bandwidth at speed where lag dominates.
+
+// Key Features:
+// - Two breakpoints with their alphas, linear interpolation in between.
+// - Symmetric in the direction of motion.
+// - Disabled by a zero high speed breakpoint, the high speed alpha applies at any speed.
+
+// Detailed Operation:
+// A fixed filter alpha trades noise at low speed against lag at speed: the lag of the
+// position filter grows with speed (error = speed * delay) while encoder noise is only
+// visible when the position barely moves. The schedule selects the alpha from the absolute
+// speed every tick:
+//   |speed| <= slow_speed:                slow_alpha
+//   slow_speed < |speed| < fast_speed:    linear between slow_alpha and fast_alpha
+//   |speed| >= fast_speed:                fast_alpha
+// Interpolating avoids a step of the filter output when the speed crosses a breakpoint.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Filter alpha scheduled over the speed.
+pub struct AlphaSchedule {
+    slow_speed: i32, // Speed up to which the slow alpha applies (counts per second)
+    fast_speed: i32, // Speed from which the fast alpha applies (counts per second, 0 - disabled)
+    slow_alpha: i32, // Alpha at low speed (higher - narrower bandwidth)
+    fast_alpha: i32, // Alpha at high speed
+}
+
+impl AlphaSchedule {
+    /// Creates a disabled schedule, `fast_alpha` applies at any speed
+    pub const fn new(fast_alpha: u8) -> Self {
+        Self {
+            slow_speed: 0,
+            fast_speed: 0,
+            slow_alpha: fast_alpha as i32,
+            fast_alpha: fast_alpha as i32,
+        }
+    }
+
+    /// Configures the breakpoints.
+    ///
+    /// # Arguments
+    /// * `slow_speed` - Speed up to which `slow_alpha` applies (counts per second)
+    /// * `fast_speed` - Speed from which `fast_alpha` applies (counts per second, 0 - disabled)
+    /// * `slow_alpha` - Alpha at low speed
+    /// * `fast_alpha` - Alpha at high speed
+    pub fn configure(&mut self, slow_speed: u32, fast_speed: u32, slow_alpha: u8, fast_alpha: u8) {
+        let fast_speed = fast_speed.min(i32::MAX as u32) as i32;
+        self.slow_speed = (slow_speed.min(i32::MAX as u32) as i32).min(fast_speed);
+        self.fast_speed = fast_speed;
+        self.slow_alpha = slow_alpha as i32;
+        self.fast_alpha = fast_alpha as i32;
+    }
+
+    /// Returns true if the alpha depends on the speed
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.fast_speed > 0
+    }
+
+    /// Alpha for the speed (counts per second)
+    pub fn alpha(&self, speed: i32) -> u8 {
+        let speed = speed.saturating_abs();
+        if !self.is_enabled() || speed >= self.fast_speed {
+            return self.fast_alpha as u8;
+        }
+        if speed <= self.slow_speed {
+            return self.slow_alpha as u8;
+        }
+        let span = (self.fast_speed - self.slow_speed) as i64;
+        let progress = (speed - self.slow_speed) as i64;
+        let delta = (self.fast_alpha - self.slow_alpha) as i64 * progress / span;
+        (self.slow_alpha + delta as i32) as u8
+    }
+}
@@ -3,9 +3,12 @@
 
 // Key Features:
 // - Implements a low-pass filter (LPF) for smoothing position data.
+// - First order or second order (two cascaded first order stages) response.
 // - Allows dynamic adjustment of the filter coefficient (`alpha`).
 // - Efficiently updates filtered output with minimal computational overhead.
 // - Provides additional 8bit for error storage improoving accuracy of result over time
+// - Rounds to nearest, a constant input is reproduced exactly (unity DC gain, no bias).
+// - Can be reset to a value, e.g. after a jump of the input.
 
 // Detailed Operation:
 // The `FilterLPF` struct implements a low-pass filter to smooth incoming position
@@ -16,16 +19,43 @@
 // operation, while `get_output` retrieves the current filtered value. The `set_alpha`
 // method allows dynamic adjustment of the filter coefficient to modify the filter's
 // responsiveness.
+// The state moves towards the input by the rounded step (256 - alpha) / 256 of the error and
+// the output is rounded to nearest. Truncation in both places used to round down, so the
+// settled output stayed up to one count below the input. The second order runs the output of
+// the first stage through an identical second stage, the response is critically damped (no
+// overshoot) and noise above the corner is attenuated twice as steeply at the cost of
+// additional lag. The input wraps at 65536, both stages follow the shortest way across the
+// wrap.
 
 // Licensed under the Apache License, Version 2.0
 // Copyright 2024 Anton Khrustalev, creapunk.com
 
+/// Order of the filter response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOrder {
+    First = 1,  // Single stage, -20 dB/decade
+    Second = 2, // Two cascaded stages, -40 dB/decade
+}
+
+impl FilterOrder {
+    /// Converts the order number, `None` for unsupported orders
+    pub fn from_u8(order: u8) -> Option<Self> {
+        match order {
+            1 => Some(Self::First),
+            2 => Some(Self::Second),
+            _ => None,
+        }
+    }
+}
+
 // Defining the PositionFilter struct that implements the position filtering logic.
 pub struct FilterLPF {
     // Filter coefficient (0u..255u = 0.0f..1.0f)
-    alpha: i32, 
+    alpha: i32,
+    temp: i32,  // Stores scaled filtered value
+    temp2: i32, // Stores scaled value of the second stage
     output: u16,
-    temp: i32, // Stores scaled filtered value
+    order: FilterOrder, // Number of stages
 }
 
 impl FilterLPF {
@@ -33,8 +63,10 @@ impl FilterLPF {
     pub fn new(input_default: u16, alpha: u8) -> FilterLPF {
         FilterLPF {
             alpha: alpha as i32,
-            output: input_default,
             temp: (input_default as i32) << 16,
+            temp2: (input_default as i32) << 16,
+            output: input_default,
+            order: FilterOrder::First,
         }
     }
 
@@ -43,20 +75,17 @@ impl FilterLPF {
         // Convert the input to a 32-bit integer and shift left by 16 bits to allow wrapping as i32
         let current: i32 = (input as i32) << 16;
 
-        // LPF filter math: filtered = alpha * (input - prev)
-        // For integer alpha u8: filtered = alpha * (input - prev) / 256 + current
-
-        // Get difference between previous (temp is i32 scaled) and current
-        let diff: i32 = self.temp.wrapping_sub(current) as i32;
+        self.temp = Self::stage(self.temp, current, self.alpha);
+        let filtered = match self.order {
+            FilterOrder::First => self.temp,
+            FilterOrder::Second => {
+                self.temp2 = Self::stage(self.temp2, self.temp, self.alpha);
+                self.temp2
+            }
+        };
 
-        // Downscale difference to allow alpha scale
-        let diff: i32 = diff >> 8;
-
-        // Calculate rest part of lpf, temp now will be as prev for next iter (i32 scaled)
-        self.temp = (diff * self.alpha).wrapping_add(current);
-
-        // Convert to u32 to allow correct bitshift and scale back to u16
-        self.output = (self.temp as u32 >> 16) as u16;
+        // Convert to u32 to allow correct bitshift, round and scale back to u16
+        self.output = ((filtered as u32).wrapping_add(1 << 15) >> 16) as u16;
 
         self.output
     }
@@ -70,4 +99,148 @@ impl FilterLPF {
     pub fn set_alpha(&mut self, alpha: u8) {
         self.alpha = alpha as i32;
     }
+
+    /// Changes the number of stages, the second stage starts at the current output
+    pub fn set_order(&mut self, order: FilterOrder) {
+        if order != self.order {
+            self.temp2 = self.temp;
+            self.order = order;
+        }
+    }
+
+    /// Getter for the filter order
+    pub fn order(&self) -> FilterOrder {
+        self.order
+    }
+
+    /// Settles every stage at `value`, the next output starts from it without transient
+    pub fn reset(&mut self, value: u16) {
+        self.temp = (value as i32) << 16;
+        self.temp2 = self.temp;
+        self.output = value;
+    }
+
+    /// One first order stage, `prev` and `current` scaled by 65536
+    #[inline(always)]
+    fn stage(prev: i32, current: i32, alpha: i32) -> i32 {
+        // LPF filter math: filtered = alpha * prev + (1 - alpha) * input
+        // For integer alpha u8: filtered = prev + (256 - alpha) * (input - prev) / 256
+
+        // Get difference between current and previous (temp is i32 scaled), wraps at a turn
+        let diff: i64 = current.wrapping_sub(prev) as i64;
+
+        // Scale by the input weight, rounded to nearest. The rounded step only vanishes once
+        // the state is within a fraction of a count of the input, so the output settles exactly
+        let step: i64 = (diff * (256 - alpha) as i64 + (1 << 7)) >> 8;
+
+        // Result now will be as prev for next iter (i32 scaled)
+        prev.wrapping_add(step as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [FilterOrder; 2] = [FilterOrder::First, FilterOrder::Second];
+    const ALPHAS: [u8; 5] = [0, 64, 128, 200, 250];
+
+    fn filter(input: u16, alpha: u8, order: FilterOrder) -> FilterLPF {
+        let mut filter = FilterLPF::new(input, alpha);
+        filter.set_order(order);
+        filter
+    }
+
+    /// Output after the filter had time to settle on a constant input
+    fn settle(filter: &mut FilterLPF, input: u16) -> u16 {
+        for _ in 0..20_000 {
+            filter.tick(input);
+        }
+        filter.get_output()
+    }
+
+    #[test]
+    fn unity_dc_gain() {
+        for order in ORDERS {
+            for alpha in ALPHAS {
+                for input in [0u16, 1, 255, 1_000, 32_767, 32_768, 65_000, u16::MAX] {
+                    for start in [0u16, 30_000] {
+                        let mut lpf = filter(start, alpha, order);
+                        assert_eq!(
+                            settle(&mut lpf, input),
+                            input,
+                            "{:?} alpha {} from {}",
+                            order,
+                            alpha,
+                            start
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn step_response_is_monotonic() {
+        for order in ORDERS {
+            for alpha in ALPHAS {
+                for (start, target) in [(0u16, 10_000u16), (10_000, 0), (1_000, 1_003)] {
+                    let mut lpf = filter(start, alpha, order);
+                    let mut previous = start;
+                    for _ in 0..10_000 {
+                        let output = lpf.tick(target);
+                        // Never moves backwards and never overshoots the target
+                        if target > start {
+                            assert!(output >= previous && output <= target, "{:?}", order);
+                        } else {
+                            assert!(output <= previous && output >= target, "{:?}", order);
+                        }
+                        previous = output;
+                    }
+                    assert_eq!(previous, target, "{:?} alpha {}", order, alpha);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn step_follows_the_short_way_across_the_wrap() {
+        for order in ORDERS {
+            let mut lpf = filter(65_500, 200, order);
+            for _ in 0..10_000 {
+                let output = lpf.tick(100);
+                assert!(
+                    output >= 65_500 || output <= 100,
+                    "{:?} went the long way",
+                    order
+                );
+            }
+            assert_eq!(lpf.get_output(), 100);
+        }
+    }
+
+    #[test]
+    fn second_order_lags_first_order() {
+        let mut first = filter(0, 200, FilterOrder::First);
+        let mut second = filter(0, 200, FilterOrder::Second);
+        for _ in 0..100 {
+            assert!(second.tick(10_000) <= first.tick(10_000));
+        }
+    }
+
+    #[test]
+    fn reset_settles_every_stage() {
+        for order in ORDERS {
+            let mut lpf = filter(0, 250, order);
+            for _ in 0..50 {
+                lpf.tick(20_000);
+            }
+            lpf.reset(5_000);
+            assert_eq!(lpf.get_output(), 5_000);
+            // No transient left from before the reset
+            for _ in 0..100 {
+                assert_eq!(lpf.tick(5_000), 5_000, "{:?}", order);
+            }
+        }
+    }
 }
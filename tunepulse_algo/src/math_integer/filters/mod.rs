@@ -1 +1,2 @@
-pub mod lpf;
\ No newline at end of file
+pub mod lpf;
+pub mod alpha_schedule;
@@ -5,4 +5,7 @@ pub mod filters;
 pub mod controllers;
 pub mod motion;
 pub mod fifo_buffer;
-pub mod motor;
\ No newline at end of file
+pub mod motor;
+pub mod signals;
+pub mod tick_rate;
+pub mod observers;
@@ -0,0 +1,91 @@
+// Implements boot-time oversampling of an absolute encoder, the averaged angle seeds the
+// position integrator and the filters instead of a single, possibly glitched, first reading.
+
+// Key Features:
+// - Averages `N` samples taken before the control starts.
+// - Rejects outliers deviating from the median by more than a limit.
+// - Handles the wrap at 65536, samples around zero average correctly.
+
+// Detailed Operation:
+// Samples are stored as offsets from the first sample (wrapping i16 differences), so a set of
+// readings around the wrap point forms a continuous range. Once `N` samples are collected the
+// offsets are sorted and the median is taken as reference, it is unaffected by a few glitched
+// readings. Samples further than the limit from the median are discarded and the rest are
+// averaged. The median itself always passes, so the result is defined even if every other
+// sample is rejected. The seed repeats across power cycles as long as the shaft is at the same
+// position, the first-sample noise no longer shows up as a position offset or a speed spike.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Averaged encoder angle from `N` boot samples.
+pub struct EncoderSeed<const N: usize> {
+    offsets: [i16; N], // Samples relative to the first one
+    first: u16,        // First sample, reference of the offsets
+    count: usize,      // Number of collected samples
+    limit: i16,        // Maximum deviation from the median (counts)
+    rejected: usize,   // Number of outliers of the finished seed
+    done: bool,        // Seed angle was delivered
+}
+
+impl<const N: usize> EncoderSeed<N> {
+    /// Creates an empty seed.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum deviation of a sample from the median (counts)
+    pub const fn new(limit: i16) -> Self {
+        Self {
+            offsets: [0; N],
+            first: 0,
+            count: 0,
+            limit,
+            rejected: 0,
+            done: false,
+        }
+    }
+
+    /// Adds a sample, returns the averaged angle once `N` samples were collected
+    pub fn push(&mut self, angle: u16) -> Option<u16> {
+        if self.done {
+            return None;
+        }
+        if self.count == 0 {
+            self.first = angle;
+        }
+        self.offsets[self.count] = angle.wrapping_sub(self.first) as i16;
+        self.count += 1;
+        if self.count < N {
+            return None;
+        }
+        self.done = true;
+        Some(self.average())
+    }
+
+    /// Returns true once the seed angle was delivered
+    #[inline(always)]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Number of samples rejected as outliers
+    #[inline(always)]
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    fn average(&mut self) -> u16 {
+        self.offsets.sort_unstable();
+        let median = self.offsets[N / 2] as i32;
+        let mut sum = 0;
+        let mut used = 0;
+        for &offset in self.offsets.iter() {
+            if (offset as i32 - median).abs() <= self.limit as i32 {
+                sum += offset as i32;
+                used += 1;
+            }
+        }
+        self.rejected = N - used as usize;
+        let mean = sum / used; // The median always passes
+        self.first.wrapping_add(mean as u16)
+    }
+}
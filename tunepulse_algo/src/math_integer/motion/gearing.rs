@@ -0,0 +1,193 @@
+// Implements electronic gearing: the axis target follows an external master position scaled by a
+// ratio, making this drive a slave axis of another drive or of a handwheel.
+
+// Key Features:
+// - Exact rational ratio (numerator / denominator), no drift over any travel.
+// - Phase offset between master and slave applied on top of the geared position.
+// - Bumpless engagement: the slave starts from its current target, not from the master origin.
+// - Speed limit of the slave target, excess travel is caught up once the master slows down.
+// - Master loss detection when no position arrives within a timeout.
+// - Master counted by the STEP/DIR or quadrature input makes the drive a stepper replacement.
+
+// Detailed Operation:
+// The master position is supplied once per sample by the platform, either decoded from an ABZ
+// input or received over the protocol. Increments between samples are accumulated in 64 bits,
+// so the ratio is applied to the total master travel since engagement and the remainder of the
+// division never accumulates as error:
+//   geared = start + travel * numerator / denominator + offset
+// Every tick the slave target moves towards the geared position by at most the speed limit.
+// The phase offset is rate limited the same way, so changing it while engaged shifts the slave
+// smoothly. If no master sample arrived for the timeout the gear disengages and reports the
+// loss, the owner then raises a fault; the target stays where it was.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Source of the master position counted by the STEP/DIR input of the platform.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepInput {
+    /// Input not used, the master comes over the protocol
+    Off = 0,
+    /// Step pulses with direction level
+    StepDir = 1,
+    /// Quadrature channels A and B (x4)
+    Quadrature = 2,
+}
+
+impl StepInput {
+    /// Converts raw value of the step input parameter
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => StepInput::StepDir,
+            2 => StepInput::Quadrature,
+            _ => StepInput::Off,
+        }
+    }
+}
+
+/// Slave axis following a master position.
+pub struct ElectronicGear {
+    frequency: u16,      // Update frequency (ticks per second)
+    numerator: i32,      // Ratio numerator, sign reverses the direction
+    denominator: u32,    // Ratio denominator
+    offset: i32,         // Phase offset added to the geared position
+    max_step: u32,       // Target change limit per tick (0 - unlimited)
+    timeout_ticks: u32,  // Ticks without master sample until lost (0 - never)
+    engaged: bool,       // Slave follows the master
+    master: Option<i32>, // Last master sample
+    fresh: bool,         // Master sample received since the last tick
+    age: u32,            // Ticks since the last master sample
+    travel: i64,         // Master travel since engagement
+    start: i32,          // Slave target at engagement
+    target: i32,         // Current slave target
+    lost: bool,          // Disengaged by master timeout
+}
+
+impl ElectronicGear {
+    /// Creates a disengaged 1:1 gear.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `timeout_ms` - Master loss timeout (ms, 0 - disabled)
+    pub fn new(frequency: u16, timeout_ms: u32) -> Self {
+        let mut gear = Self {
+            frequency,
+            numerator: 1,
+            denominator: 1,
+            offset: 0,
+            max_step: 0,
+            timeout_ticks: 0,
+            engaged: false,
+            master: None,
+            fresh: false,
+            age: 0,
+            travel: 0,
+            start: 0,
+            target: 0,
+            lost: false,
+        };
+        gear.set_timeout(timeout_ms);
+        gear
+    }
+
+    /// Sets the ratio, a zero denominator is treated as 1
+    pub fn set_ratio(&mut self, numerator: i32, denominator: u32) {
+        self.rebase();
+        self.numerator = numerator;
+        self.denominator = denominator.max(1);
+    }
+
+    /// Sets the phase offset between master and slave
+    pub fn set_offset(&mut self, offset: i32) {
+        self.offset = offset;
+    }
+
+    /// Sets the slave speed limit (position units per second, 0 - unlimited)
+    pub fn set_max_speed(&mut self, speed: u32) {
+        let step = speed as u64 / self.frequency as u64;
+        self.max_step = if speed == 0 { 0 } else { (step as u32).max(1) };
+    }
+
+    /// Sets the master loss timeout (ms, 0 - disabled)
+    pub fn set_timeout(&mut self, timeout_ms: u32) {
+        self.timeout_ticks = (timeout_ms as u64 * self.frequency as u64 / 1000) as u32;
+    }
+
+    /// Feeds a master position sample
+    pub fn set_master(&mut self, position: i32) {
+        if let Some(last) = self.master {
+            self.travel += position.wrapping_sub(last) as i64;
+        }
+        self.master = Some(position);
+        self.fresh = true;
+    }
+
+    /// Engages the gear at the current slave target, returns false without a master sample
+    pub fn engage(&mut self, target: i32) -> bool {
+        if self.master.is_none() {
+            return false;
+        }
+        self.engaged = true;
+        self.lost = false;
+        self.travel = 0;
+        self.start = target.wrapping_sub(self.offset);
+        self.target = target;
+        true
+    }
+
+    /// Disengages the gear, the slave holds its target
+    pub fn disengage(&mut self) {
+        self.engaged = false;
+    }
+
+    /// Returns true while the slave follows the master
+    #[inline(always)]
+    pub fn is_engaged(&self) -> bool {
+        self.engaged
+    }
+
+    /// Returns true once after the gear disengaged because the master was lost
+    pub fn take_lost(&mut self) -> bool {
+        core::mem::take(&mut self.lost)
+    }
+
+    /// Updates the slave target, returns it while engaged
+    pub fn tick(&mut self) -> Option<i32> {
+        self.age = if self.fresh {
+            0
+        } else {
+            self.age.saturating_add(1)
+        };
+        self.fresh = false;
+        if !self.engaged {
+            return None;
+        }
+        if self.timeout_ticks > 0 && self.age >= self.timeout_ticks {
+            self.engaged = false;
+            self.lost = true;
+            return None;
+        }
+
+        let geared = self.travel * self.numerator as i64 / self.denominator as i64;
+        let geared = self
+            .start
+            .wrapping_add(geared as i32)
+            .wrapping_add(self.offset);
+        let mut step = geared.wrapping_sub(self.target);
+        if self.max_step > 0 {
+            let limit = self.max_step.min(i32::MAX as u32) as i32;
+            step = step.clamp(-limit, limit);
+        }
+        self.target = self.target.wrapping_add(step);
+        Some(self.target)
+    }
+
+    /// Moves the ratio origin to the current master position, so a new ratio applies only to
+    /// the travel from now on instead of jumping the slave
+    fn rebase(&mut self) {
+        let geared = self.travel * self.numerator as i64 / self.denominator as i64;
+        self.start = self.start.wrapping_add(geared as i32);
+        self.travel = 0;
+    }
+}
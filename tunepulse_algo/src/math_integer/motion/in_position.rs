@@ -0,0 +1,102 @@
+// Implements the setpoint deadband and in-position window for position mode.
+
+// Key Features:
+// - Position error deadband suppressing corrections of negligible errors.
+// - In-position window with hysteresis avoiding flag chatter at the window edge.
+// - Settle time requirement before the in-position flag is raised.
+
+// Detailed Operation:
+// Each tick the position error between target and measured position is calculated. Errors
+// within the deadband are reported as zero so the control loop doesn't chase noise around the
+// target. The in-position flag is raised once the absolute error stays within the window for the
+// settle time and cleared only once the error exceeds the window plus the hysteresis. A new
+// target clears the flag immediately, so hosts waiting for it can sequence moves reliably.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Position deadband and in-position window.
+pub struct InPosition {
+    deadband: u32,     // Errors within the deadband are treated as zero
+    window: u32,       // Absolute error considered in position
+    hysteresis: u32,   // Extra error allowed before leaving in-position state
+    settle_ticks: u16, // Ticks the error has to stay within window
+    counter: u16,      // Ticks spent within window
+    target: i32,       // Last target position
+    error: i32,        // Position error after deadband
+    in_position: bool, // In-position flag
+}
+
+impl InPosition {
+    /// Creates a new in-position monitor.
+    ///
+    /// # Arguments
+    /// * `deadband` - Errors within the deadband are treated as zero (encoder counts)
+    /// * `window` - Absolute error considered in position (encoder counts)
+    /// * `hysteresis` - Extra error allowed before leaving in-position state (encoder counts)
+    /// * `settle_ticks` - Ticks the error has to stay within window
+    pub fn new(deadband: u32, window: u32, hysteresis: u32, settle_ticks: u16) -> Self {
+        Self {
+            deadband,
+            window,
+            hysteresis,
+            settle_ticks,
+            counter: 0,
+            target: 0,
+            error: 0,
+            in_position: false,
+        }
+    }
+
+    /// Math call, returns position error with deadband applied.
+    ///
+    /// # Arguments
+    /// * `target` - Target position (i16 rotations + u16 angle)
+    /// * `position` - Measured position (i16 rotations + u16 angle)
+    pub fn tick(&mut self, target: i32, position: i32) -> i32 {
+        if target != self.target {
+            // New setpoint: the move has to settle again
+            self.target = target;
+            self.counter = 0;
+            self.in_position = false;
+        }
+
+        let error = target.wrapping_sub(position);
+        let abs_error = error.unsigned_abs();
+
+        if self.in_position {
+            if abs_error > self.window.saturating_add(self.hysteresis) {
+                self.in_position = false;
+                self.counter = 0;
+            }
+        } else if abs_error <= self.window {
+            self.counter = self.counter.saturating_add(1);
+            self.in_position = self.counter >= self.settle_ticks;
+        } else {
+            self.counter = 0;
+        }
+
+        self.error = if abs_error <= self.deadband { 0 } else { error };
+        self.error
+    }
+
+    /// Returns true once the position settled within the window
+    pub fn is_in_position(&self) -> bool {
+        self.in_position
+    }
+
+    /// Getter for position error with deadband applied
+    pub fn error(&self) -> i32 {
+        self.error
+    }
+
+    /// Changes deadband and window parameters
+    pub fn configure(&mut self, deadband: u32, window: u32, hysteresis: u32, settle_ticks: u16) {
+        self.deadband = deadband;
+        self.window = window;
+        self.hysteresis = hysteresis;
+        self.settle_ticks = settle_ticks;
+        self.counter = 0;
+        self.in_position = false;
+    }
+}
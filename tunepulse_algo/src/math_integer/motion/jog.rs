@@ -0,0 +1,128 @@
+// Implements the jog mode for commissioning: the target moves at a fixed speed while the host
+// holds the jog command, with a strict torque cap enforced by the owner.
+
+// Key Features:
+// - Forward and backward jog at the configured speed, sub-count accurate at low speeds.
+// - Dead man behaviour: motion stops when the command is released or stops being repeated.
+// - Stops immediately, the target is not ramped down.
+// - Aborted jog stays stopped until the command is released, repetitions don't restart it.
+
+// Detailed Operation:
+// While active, every tick advances the target by speed / frequency counts, the remainder of
+// the division is accumulated so low speeds stay exact. The host starts the jog with a jog
+// command and has to repeat it faster than the timeout while the button is held, a stop
+// command (button released) or a missed repetition (link lost, host crashed) stops the
+// motion at the current target. The controller caps the torque command while jogging and
+// aborts the jog when the cap is reached, so an obstacle or a wrong direction can't build up
+// force. An aborted jog rejects further jog commands until the host sends the stop command,
+// otherwise the repetitions of a held button would push into the obstacle again.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Direction of a jog move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JogDirection {
+    Forward,
+    Backward,
+}
+
+/// Target generator for jog moves.
+pub struct Jog {
+    frequency: u16,     // Update frequency (ticks per second)
+    speed: i32,         // Jog speed (counts per second)
+    timeout_ticks: u32, // Ticks without repeated command until the jog stops
+    direction: i32,     // 1 forward, -1 backward, 0 stopped
+    age: u32,           // Ticks since the last jog command
+    acc: i32,           // Sub-count remainder of the travel
+    aborted: bool,      // Jog was aborted, rejected until stopped
+}
+
+impl Jog {
+    /// Creates a stopped jog.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `speed` - Jog speed (counts per second)
+    /// * `timeout_ms` - Time the jog command has to be repeated in
+    pub fn new(frequency: u16, speed: u32, timeout_ms: u32) -> Self {
+        let mut jog = Self {
+            frequency,
+            speed: 0,
+            timeout_ticks: 0,
+            direction: 0,
+            age: 0,
+            acc: 0,
+            aborted: false,
+        };
+        jog.set_speed(speed);
+        jog.set_timeout(timeout_ms);
+        jog
+    }
+
+    /// Sets the jog speed (counts per second)
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed.min(i32::MAX as u32) as i32;
+    }
+
+    /// Sets the time the jog command has to be repeated in, at least one tick
+    pub fn set_timeout(&mut self, timeout_ms: u32) {
+        let ticks = timeout_ms as u64 * self.frequency as u64 / 1000;
+        self.timeout_ticks = (ticks as u32).max(1);
+    }
+
+    /// Starts the jog or keeps it running, a direction change restarts it. Returns `false`
+    /// while an aborted jog wasn't stopped yet.
+    pub fn start(&mut self, direction: JogDirection) -> bool {
+        if self.aborted {
+            return false;
+        }
+        let direction = match direction {
+            JogDirection::Forward => 1,
+            JogDirection::Backward => -1,
+        };
+        if direction != self.direction {
+            self.acc = 0;
+        }
+        self.direction = direction;
+        self.age = 0;
+        true
+    }
+
+    /// Stops the jog immediately, the release of the jog command
+    pub fn stop(&mut self) {
+        self.direction = 0;
+        self.acc = 0;
+        self.aborted = false;
+    }
+
+    /// Stops a running jog and rejects jog commands until `stop()`
+    pub fn abort(&mut self) {
+        if self.is_active() {
+            self.stop();
+            self.aborted = true;
+        }
+    }
+
+    /// Returns true while jogging
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        self.direction != 0
+    }
+
+    /// Returns the target travel of this tick (counts), `None` while stopped
+    pub fn tick(&mut self) -> Option<i32> {
+        if self.direction == 0 {
+            return None;
+        }
+        self.age += 1;
+        if self.age > self.timeout_ticks {
+            self.stop(); // Command not repeated, treat as released
+            return None;
+        }
+        self.acc += self.speed * self.direction;
+        let step = self.acc / self.frequency as i32;
+        self.acc -= step * self.frequency as i32;
+        Some(step)
+    }
+}
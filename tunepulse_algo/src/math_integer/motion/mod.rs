@@ -1,2 +1,8 @@
 pub mod position_integrator;
-pub mod speed_estimator;
\ No newline at end of file
+pub mod speed_estimator;
+pub mod standstill;
+pub mod gearing;
+pub mod in_position;
+pub mod jog;
+pub mod encoder_seed;
+pub mod speed_window;
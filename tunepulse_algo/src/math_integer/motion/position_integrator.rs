@@ -1,13 +1,24 @@
 /// EncoderPosition manages and calculates the absolute position and speed of the encoder.
+///
+/// The position is integrated in the sensor frame, which the calibration table and the
+/// commutation use. Control and reporting use the user frame: the direction can be inverted
+/// and a zero offset added, so mechanical reassembly only needs new frame parameters instead of
+/// a new calibration table.
 pub struct Position {
-    position: i32, // Combined value (rotations + angle)
+    position: i32,  // Combined value (rotations + angle) in the sensor frame
+    inverted: bool, // User frame counts against the sensor
+    offset: i32,    // User frame position of the sensor zero
 }
 
 impl Position {
     /// Creates new encoder handler instance
     pub fn new() -> Self {
         // Init filter with input values as default
-        Self { position: 0 }
+        Self {
+            position: 0,
+            inverted: false,
+            offset: 0,
+        }
     }
 
     /// Updates the encoder state, including position filtering, zero-cross detection, and speed estimation.
@@ -24,21 +35,53 @@ impl Position {
         self
     }
 
-    /// Getter for angle
+    /// Sets the user frame: direction inversion and position of the sensor zero
+    pub fn set_frame(&mut self, inverted: bool, offset: i32) {
+        self.inverted = inverted;
+        self.offset = offset;
+    }
+
+    /// Direction of the user frame relative to the sensor (1 or -1)
+    #[inline(always)]
+    pub fn direction(&self) -> i32 {
+        if self.inverted {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// Converts a sensor frame position (or a held copy of it) to the user frame
+    #[inline(always)]
+    pub fn to_user(&self, raw: i32) -> i32 {
+        raw.wrapping_mul(self.direction()).wrapping_add(self.offset)
+    }
+
+    /// Getter for the sensor angle, commutation works in the sensor frame
     pub fn angle(&self) -> u16 {
         self.position as u16
     }
 
-    /// Getter for rotations
+    /// Getter for rotations in the user frame
     pub fn rotations(&self) -> i16 {
-        (self.position >> 16) as i16
+        (self.position() >> 16) as i16
     }
 
-    /// Getter for position, returns i32 (i16 rotations + u16 angle)
+    /// Getter for position in the user frame, returns i32 (i16 rotations + u16 angle)
     pub fn position(&self) -> i32 {
+        self.to_user(self.position)
+    }
+
+    /// Getter for position in the sensor frame, returns i32 (i16 rotations + u16 angle)
+    pub fn raw_position(&self) -> i32 {
         self.position
     }
 
+    /// Starts the integration at `angle`, e.g. an averaged boot reading of an absolute encoder
+    pub fn seed(&mut self, angle: u16) {
+        self.position = angle as i16 as i32; // Same turn as the first `tick()` would select
+    }
+
     // Call this if ABZ encoder is used at it hit zero very first time
     pub fn reset(&mut self) {
         self.position = 0;
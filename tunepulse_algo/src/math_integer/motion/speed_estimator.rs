@@ -1,44 +1,160 @@
-/// SpeedEstimator estimates the instantaneous speed of the encoder.
+// Implements estimation of the encoder speed from the multi-turn position.
 
-/// Size of circular buffer (min 2 max 32)
-const SIZE: usize = 8;
+// Key Features:
+// - Finite difference over a configurable window of 1 to `MAX_WINDOW` ticks.
+// - Optional low-pass filter of the estimate.
+// - Speed available in counts per second, counts per tick, RPM and mrad/s.
 
-pub struct SpeedEstimator {
-    freq: u16,            // Sampling frequency
-    speed: i32,           // Calculated speed
-    pos_buffer: [i32; SIZE], // Circular buffer for position samples
-    idx: usize,           // Current index in circular buffer
+// Detailed Operation:
+// The last `MAX_WINDOW` positions are kept in a circular buffer. Every tick the speed is the
+// position change over the window scaled by `freq / window`, so the resolution is
+// `freq / window` counts per second and the estimate lags by half the window. Changing the
+// window takes effect on the next tick, the history is always complete.
+// Accuracy versus latency:
+// - Long window: fine resolution and low noise, the estimate lags by window / 2 ticks.
+// - Short window with filter: the filter smooths the coarse steps while reacting faster to
+//   speed changes, the lag is set by the filter alpha instead of the window.
+// The filter uses the same alpha convention as `FilterLPF`: 0 passes the estimate unchanged,
+// larger values filter more (output moves by (256 - alpha) / 256 of the error per tick).
+// Controllers use counts per second (65536 counts per revolution), other units are conversions
+// of that value for reporting.
+// With a fixed `FREQ` the scaling by the frequency is a constant multiplication.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Maximum measurement window (ticks)
+pub const MAX_WINDOW: usize = 32;
+
+/// Default measurement window (ticks)
+pub const DEFAULT_WINDOW: usize = 8;
+
+/// Position counts per revolution
+const COUNTS_PER_REV: i64 = 65536;
+
+use crate::math_integer::tick_rate::{self, RUNTIME};
+
+/// Units of the reported speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SpeedUnit {
+    /// Position counts per second (65536 counts per revolution)
+    CountsPerSecond = 0,
+    /// Position counts per control tick, truncated
+    CountsPerTick = 1,
+    /// Revolutions per minute
+    Rpm = 2,
+    /// Milliradians per second
+    MradPerSecond = 3,
 }
 
-impl SpeedEstimator {
-    // Create new speed estimator
+impl SpeedUnit {
+    /// Converts raw unit, unknown values select counts per second
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => SpeedUnit::CountsPerTick,
+            2 => SpeedUnit::Rpm,
+            3 => SpeedUnit::MradPerSecond,
+            _ => SpeedUnit::CountsPerSecond,
+        }
+    }
+}
+
+/// Speed estimator, `FREQ` fixes the sampling frequency at compile time (`RUNTIME` - from `new`).
+pub struct SpeedEstimator<const FREQ: u16 = RUNTIME> {
+    freq: u16,                     // Sampling frequency
+    window: usize,                 // Measurement window (ticks)
+    alpha: i32,                    // Filter coefficient (0 - off, 255 - strongest)
+    raw: i32,                      // Unfiltered speed (counts per second)
+    filtered: i64,                 // Filtered speed scaled by 256
+    speed: i32,                    // Calculated speed (counts per second)
+    pos_buffer: [i32; MAX_WINDOW], // Circular buffer for position samples
+    idx: usize,                    // Index of the oldest sample in circular buffer
+}
+
+impl<const FREQ: u16> SpeedEstimator<FREQ> {
+    /// Creates an estimator with the default window and no filter.
+    ///
+    /// # Arguments
+    /// * `init_position` - Position the history is filled with
+    /// * `freq` - Number of ticks per second, ignored with a fixed `FREQ`
     pub fn new(init_position: i32, freq: u16) -> Self {
         Self {
-            freq,
+            freq: tick_rate::resolve(FREQ, freq),
+            window: DEFAULT_WINDOW,
+            alpha: 0,
+            raw: 0,
+            filtered: 0,
             speed: 0,
-            pos_buffer: [init_position; SIZE],
+            pos_buffer: [init_position; MAX_WINDOW],
             idx: 0,
         }
     }
 
-    // Math call
-    pub fn tick(&mut self, new_position: i32) -> &Self{
-        // Calculate position difference over N = SIZE samples
-        let difference = new_position - self.pos_buffer[self.idx];
+    /// Restarts the estimation at standstill at `position`
+    pub fn reset(&mut self, position: i32) {
+        self.pos_buffer = [position; MAX_WINDOW];
+        self.raw = 0;
+        self.filtered = 0;
+        self.speed = 0;
+    }
+
+    /// Sets the measurement window, limited to 1..=`MAX_WINDOW` ticks
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.clamp(1, MAX_WINDOW);
+    }
 
-        // Calculate speed based on sampling frequency (corrected to buffer size)
-        self.speed = difference.wrapping_mul(self.freq as i32) / SIZE as i32;
+    /// Sets the filter coefficient (0 - off)
+    pub fn set_filter(&mut self, alpha: u8) {
+        self.alpha = alpha as i32;
+    }
 
-        // Update buffer
-        self.pos_buffer[self.idx] = new_position;
+    /// Math call, updates the estimate with the position of this tick
+    pub fn tick(&mut self, new_position: i32) -> &Self {
+        // Sample `window` ticks ago, the oldest one for the maximum window
+        let past = (self.idx + MAX_WINDOW - self.window) % MAX_WINDOW;
+        let difference = new_position.wrapping_sub(self.pos_buffer[past]);
 
-        // Update index value
-        self.idx = (self.idx + 1) % SIZE;
+        // Calculate speed based on sampling frequency (corrected to window size)
+        self.raw = difference.wrapping_mul(self.freq() as i32) / self.window as i32;
+
+        // Low-pass filter in 1/256 counts per second to keep the remainder, tracks the raw
+        // speed exactly when off so enabling it doesn't cause a step
+        let error = ((self.raw as i64) << 8) - self.filtered;
+        self.filtered += (error * (256 - self.alpha) as i64) >> 8;
+        self.speed = (self.filtered >> 8) as i32;
+
+        // Replace the oldest sample
+        self.pos_buffer[self.idx] = new_position;
+        self.idx = (self.idx + 1) % MAX_WINDOW;
         self
     }
 
-    // Getter for instant speed
+    /// Sampling frequency (ticks per second)
+    #[inline(always)]
+    pub fn freq(&self) -> u16 {
+        tick_rate::resolve(FREQ, self.freq)
+    }
+
+    /// Getter for the speed (counts per second)
     pub fn get_speed(&self) -> i32 {
         self.speed
     }
+
+    /// Getter for the unfiltered speed (counts per second)
+    pub fn get_raw_speed(&self) -> i32 {
+        self.raw
+    }
+
+    /// Getter for the speed converted to `unit`
+    pub fn get_speed_in(&self, unit: SpeedUnit) -> i32 {
+        let speed = self.speed as i64;
+        let speed = match unit {
+            SpeedUnit::CountsPerSecond => speed,
+            SpeedUnit::CountsPerTick => speed / self.freq() as i64,
+            SpeedUnit::Rpm => speed * 60 / COUNTS_PER_REV,
+            SpeedUnit::MradPerSecond => speed * 6_283_185 / (COUNTS_PER_REV * 1000),
+        };
+        speed as i32
+    }
 }
@@ -0,0 +1,142 @@
+// Implements the at-velocity and zero-speed detection of the drive status.
+
+// Key Features:
+// - At target velocity: the speed stays within a window around the velocity setpoint for a
+//   hold time.
+// - Zero speed: the absolute speed stays below a threshold for a hold time, in every mode.
+// - Hysteresis of a quarter of the window or threshold avoiding flag chatter at the edge.
+// - A window or threshold of 0 disables its flag.
+
+// Detailed Operation:
+// Each tick the owner passes the measured speed and the target velocity, `None` unless a
+// velocity setpoint is followed. A flag is raised once its condition held for the hold time
+// and cleared as soon as the speed leaves the window or threshold plus the hysteresis. A new
+// target velocity clears the at-velocity flag immediately, hosts sequencing on it never see
+// the flag of the previous setpoint. Without a velocity setpoint the drive has no target
+// velocity and the flag stays cleared, the in-position flag serves the position setpoints.
+// The zero-speed flag doesn't depend on the setpoint: it tells a host or a brake that the
+// axis came to rest, also after a disable while coasting.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// At-velocity and zero-speed detector.
+pub struct SpeedWindow {
+    frequency: u16,      // Update frequency (ticks per second)
+    window: u32,         // Speed deviation considered at velocity (counts/s, 0 - disabled)
+    window_ticks: u32,   // Ticks the speed has to stay within the window
+    zero_threshold: u32, // Absolute speed considered zero (counts/s, 0 - disabled)
+    zero_ticks: u32,     // Ticks the speed has to stay below the threshold
+    target: Option<i32>, // Last target velocity
+    window_counter: u32, // Ticks spent within the window
+    zero_counter: u32,   // Ticks spent below the threshold
+    at_velocity: bool,   // At target velocity flag
+    zero_speed: bool,    // Zero speed flag
+}
+
+impl SpeedWindow {
+    /// Creates a disabled detector.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            window: 0,
+            window_ticks: 0,
+            zero_threshold: 0,
+            zero_ticks: 0,
+            target: None,
+            window_counter: 0,
+            zero_counter: 0,
+            at_velocity: false,
+            zero_speed: false,
+        }
+    }
+
+    /// Configures the detection, both flags have to settle again.
+    ///
+    /// # Arguments
+    /// * `window` - Speed deviation from the target considered at velocity (counts/s)
+    /// * `window_ms` - Time the speed has to stay within the window
+    /// * `zero_threshold` - Absolute speed considered zero (counts/s)
+    /// * `zero_ms` - Time the speed has to stay below the threshold
+    pub fn configure(&mut self, window: u32, window_ms: u32, zero_threshold: u32, zero_ms: u32) {
+        self.window = window;
+        self.window_ticks = self.ms_to_ticks(window_ms);
+        self.zero_threshold = zero_threshold;
+        self.zero_ticks = self.ms_to_ticks(zero_ms);
+        self.window_counter = 0;
+        self.zero_counter = 0;
+        self.at_velocity = false;
+        self.zero_speed = false;
+    }
+
+    /// Updates both flags.
+    ///
+    /// # Arguments
+    /// * `target` - Target velocity (counts/s), `None` without a velocity setpoint
+    /// * `speed` - Measured speed (counts/s)
+    pub fn tick(&mut self, target: Option<i32>, speed: i32) {
+        if target != self.target {
+            // New setpoint: the speed has to settle again
+            self.target = target;
+            self.window_counter = 0;
+            self.at_velocity = false;
+        }
+        let deviation = target.map(|target| target.abs_diff(speed));
+        (self.at_velocity, self.window_counter) = Self::settle(
+            self.at_velocity,
+            self.window_counter,
+            deviation.filter(|_| self.window != 0),
+            self.window,
+            self.window_ticks,
+        );
+        let magnitude = Some(speed.unsigned_abs()).filter(|_| self.zero_threshold != 0);
+        (self.zero_speed, self.zero_counter) = Self::settle(
+            self.zero_speed,
+            self.zero_counter,
+            magnitude,
+            self.zero_threshold,
+            self.zero_ticks,
+        );
+    }
+
+    /// Returns true once the speed settled within the window around the target velocity
+    #[inline(always)]
+    pub fn is_at_velocity(&self) -> bool {
+        self.at_velocity
+    }
+
+    /// Returns true once the axis came to rest
+    #[inline(always)]
+    pub fn is_zero_speed(&self) -> bool {
+        self.zero_speed
+    }
+
+    /// Next state and counter of a flag, `value` is `None` while the flag is disabled
+    fn settle(
+        active: bool,
+        counter: u32,
+        value: Option<u32>,
+        limit: u32,
+        ticks: u32,
+    ) -> (bool, u32) {
+        let Some(value) = value else {
+            return (false, 0);
+        };
+        if active {
+            let released = value > limit.saturating_add(limit / 4);
+            (!released, 0)
+        } else if value <= limit {
+            let counter = counter.saturating_add(1);
+            (counter >= ticks.max(1), counter)
+        } else {
+            (false, 0)
+        }
+    }
+
+    fn ms_to_ticks(&self, ms: u32) -> u32 {
+        (ms as u64 * self.frequency as u64 / 1000) as u32
+    }
+}
@@ -0,0 +1,87 @@
+// Implements standstill detection with position hold, suppressing dither caused by encoder noise
+// while the motor is not moving.
+
+// Key Features:
+// - Detects standstill once the speed stays below a threshold for a settle time.
+// - Freezes the position fed to the control loops while at standstill.
+// - Applies a position deadband, leaving standstill only on real motion.
+
+// Detailed Operation:
+// At standstill the encoder noise is the only thing changing the measured position, and the
+// control loops amplify it into audible buzzing. The detector counts ticks with the absolute
+// speed below the threshold. After the settle time it latches the current position and keeps
+// returning it instead of the measured one. As long as the measured position stays within the
+// deadband around the latched one the output doesn't change. Once the position leaves the
+// deadband (or the speed exceeds the threshold) the detector releases and returns the measured
+// position again. Users of the detector may also narrow their bandwidth while it is active.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Standstill detector holding the position while the motor is not moving.
+pub struct Standstill {
+    speed_threshold: i32, // Absolute speed below which the motor is considered stopped
+    deadband: i32,        // Position deadband around the held position
+    settle_ticks: u16,    // Ticks below speed threshold before entering standstill
+    counter: u16,         // Ticks spent below speed threshold
+    held: i32,            // Position latched on entering standstill
+    active: bool,         // Standstill state
+}
+
+impl Standstill {
+    /// Creates a new standstill detector.
+    ///
+    /// # Arguments
+    /// * `speed_threshold` - Absolute speed below which the motor is considered stopped, 0 disables
+    /// * `deadband` - Position deadband around the held position
+    /// * `settle_ticks` - Ticks below speed threshold before entering standstill
+    pub fn new(speed_threshold: i32, deadband: i32, settle_ticks: u16) -> Self {
+        Self {
+            speed_threshold: speed_threshold.abs(),
+            deadband: deadband.abs(),
+            settle_ticks,
+            counter: 0,
+            held: 0,
+            active: false,
+        }
+    }
+
+    /// Math call, returns the position to be used by the control loops.
+    ///
+    /// # Arguments
+    /// * `position` - Measured position (i16 rotations + u16 angle)
+    /// * `speed` - Measured speed
+    pub fn tick(&mut self, position: i32, speed: i32) -> i32 {
+        if speed.abs() >= self.speed_threshold {
+            self.counter = 0;
+            self.active = false;
+            return position;
+        }
+
+        if self.active {
+            if position.wrapping_sub(self.held).unsigned_abs() <= self.deadband as u32 {
+                return self.held; // Noise only, keep the position frozen
+            }
+            self.counter = 0;
+            self.active = false; // Real motion, release the hold
+            return position;
+        }
+
+        self.counter = self.counter.saturating_add(1);
+        if self.counter >= self.settle_ticks {
+            self.active = true;
+            self.held = position;
+        }
+        position
+    }
+
+    /// Returns true while the motor is at standstill and the position is held
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Changes detection parameters, 0 speed threshold disables detection
+    pub fn configure(&mut self, speed_threshold: i32, deadband: i32, settle_ticks: u16) {
+        *self = Self::new(speed_threshold, deadband, settle_ticks);
+    }
+}
@@ -5,7 +5,9 @@
 // - Performs inverse and direct Clarke transforms to convert between two-phase (alpha-beta) and three-phase (A-B-C) systems.
 // - Calculates SVPWM voltages based on sine and cosine references and available voltage.
 // - Supports dual and triple current conversion methods.
-// - Ensures voltage scaling and clamping to prevent overvoltage conditions.
+// - Park and inverse Park transforms between alpha-beta and the frame rotating with a vector.
+// - Ensures voltage scaling and clamping to prevent overvoltage conditions, phase duties saturate
+//   to the PWM range instead of wrapping around.
 
 // Detailed Operation:
 // This module provides functions to perform Clarke transforms, converting between two-phase (alpha-beta)
@@ -14,6 +16,9 @@
 // phase currents. The `voltage_ab2abc` function calculates SVPWM voltages, scaling them based on available voltage
 // and applying necessary offsets to ensure safe operation. Additionally, the module includes functions for
 // dual and triple current conversions, facilitating different motor control scenarios.
+// `park_transform` projects an alpha-beta vector on the direct axis `(sin, cos)` of the rotating
+// frame and on the quadrature axis 90° ahead `(cos, -sin)`, `inverse_park_transform` rotates it
+// back; a rotation by an i1.15 sine/cosine pair can exceed i16, so both saturate.
 
 // Licensed under the Apache License, Version 2.0
 // Copyright 2024 Anton Khrustalev, creapunk.com
@@ -21,6 +26,8 @@
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 pub mod duty {
+    use crate::diagnostics::overflow::{self, OverflowSite};
+
     /// Calculates SVPWM voltages based on sine and cosine references and available voltage.
     /// Additionally, SVPWM allows excluding zero duty PWM.
     ///
@@ -29,43 +36,57 @@ pub mod duty {
     pub fn ab2abc(voltg_sin: i16, voltg_cos: i16) -> (i16, i16, i16) {
         const MAX_OUTPUT: i32 = i16::MAX as i32;
         // Inverse Clarke transform
-        let (mut voltg_a, mut voltg_b, mut voltg_c) = super::inverse_clarke_tf(voltg_sin, voltg_cos); // Transforms sine and cosine voltages to three-phase voltages
-
-        // Find the minimum and maximum phase voltages
-        let voltg_min: i32 = voltg_a.min(voltg_b).min(voltg_c); // Determines the minimum voltage among phases
-        let voltg_max: i32 = voltg_a.max(voltg_b).max(voltg_c); // Determines the maximum voltage among phases
+        let (voltg_a, voltg_b, voltg_c) = super::inverse_clarke_tf(voltg_sin, voltg_cos); // Transforms sine and cosine voltages to three-phase voltages
 
-        let voltg_offset: i32; // Initializes voltage offset
+        // B and C are mirrored around -A/2, ordering them leaves two comparisons for min/max
+        let (voltg_upper, voltg_lower) = if voltg_b >= voltg_c {
+            (voltg_b, voltg_c)
+        } else {
+            (voltg_c, voltg_b)
+        };
+        let voltg_min: i32 = voltg_a.min(voltg_lower); // Determines the minimum voltage among phases
+        let voltg_max: i32 = voltg_a.max(voltg_upper); // Determines the maximum voltage among phases
 
         let voltg_full_scale: i32 = voltg_max - voltg_min; // Calculates the full scale voltage range
 
-        // Automatic constraining and bottom clamping if available voltage isn't enough
-        if voltg_full_scale > MAX_OUTPUT {
-            // Calculate scaling factor (fixed point based on i32, scale resolution: 15bit)
-            let voltg_scale = (MAX_OUTPUT << 15) / voltg_full_scale; // Determines scaling factor to fit available voltage
+        // If zero voltage is required - activate maximum brake
+        if voltg_full_scale == 0 {
+            return (0, 0, 0);
+        }
 
-            // Apply scale to all channels
-            voltg_a = (voltg_a * voltg_scale) >> 15; // Scales voltage A
-            voltg_b = (voltg_b * voltg_scale) >> 15; // Scales voltage B
-            voltg_c = (voltg_c * voltg_scale) >> 15; // Scales voltage C
+        let (voltg_a, voltg_b, voltg_c) = if voltg_full_scale > MAX_OUTPUT {
+            // Automatic constraining and bottom clamping if available voltage isn't enough.
+            // Scale resolution: 15bit, the only division per tick and only when over-modulating
+            // (hardware divider, a reciprocal table wouldn't be faster).
+            let voltg_scale = (MAX_OUTPUT << 15) / voltg_full_scale; // Determines scaling factor to fit available voltage
 
-            // Apply scale to voltg_min to allow correct clamping
-            let voltg_min_scaled: i32 = (voltg_min * voltg_scale) >> 15; // Scales minimum voltage
-            voltg_offset = -voltg_min_scaled; // Sets voltage offset based on scaled minimum voltage
+            // Scaling the distance to the lowest phase clamps it to zero without offset and
+            // keeps the highest within MAX_OUTPUT (full scale * scale <= MAX_OUTPUT << 15,
+            // rounding half a count up doesn't reach the next one)
+            let scale = |voltg: i32| ((voltg - voltg_min) * voltg_scale + (1 << 14)) >> 15;
+            (scale(voltg_a), scale(voltg_b), scale(voltg_c))
         } else {
             // Calculate reference voltage to shift all phase voltages
-            voltg_offset = (MAX_OUTPUT - voltg_max - voltg_min) >> 1; // Determines voltage offset for shifting
-        }
+            let voltg_offset = (MAX_OUTPUT - voltg_max - voltg_min) >> 1; // Determines voltage offset for shifting
+            (
+                voltg_a + voltg_offset,
+                voltg_b + voltg_offset,
+                voltg_c + voltg_offset,
+            )
+        };
 
-        // If zero voltage is required - activate maximum brake
-        if voltg_full_scale != 0 {
-            // Shift all phase voltages by reference voltage
-            voltg_a += voltg_offset; // Applies offset to voltage A
-            voltg_b += voltg_offset; // Applies offset to voltage B
-            voltg_c += voltg_offset; // Applies offset to voltage C
-        }
+        // Out of range only if the bounds above are broken, never wrap around
+        (saturate(voltg_a), saturate(voltg_b), saturate(voltg_c)) // Returns the final adjusted voltages
+    }
 
-        return (voltg_a as i16, voltg_b as i16, voltg_c as i16); // Returns the final adjusted voltages
+    /// Clamps a phase duty to `0..=i16::MAX`, clamping is recorded as a duty scale overflow
+    #[inline(always)]
+    fn saturate(duty: i32) -> i16 {
+        const MAX_OUTPUT: i32 = i16::MAX as i32;
+        if !(0..=MAX_OUTPUT).contains(&duty) {
+            overflow::record(OverflowSite::DutyScale);
+        }
+        duty.clamp(0, MAX_OUTPUT) as i16
     }
 }
 
@@ -94,6 +115,9 @@ const SQRT3DIV2: i32 = (SQRT3 / 2.0f64 * (1u32 << 16) as f64) as i32;
 
 /// Performs the inverse Clarke transform to calculate phase values (A, B, C)
 /// from the `sin` and `cos` values.
+///
+/// Intermediate values stay within i32 for every input: the largest product is
+/// `SQRT3DIV2 * 32768 < 2^31`, the phases stay within ±(2^15 * 1.37).
 fn inverse_clarke_tf(sin: i16, cos: i16) -> (i32, i32, i32) {
     let sin: i32 = sin as i32; // Convert sine input to i32
     let cos: i32 = cos as i32; // Convert cosine input to i32
@@ -128,3 +152,270 @@ fn direct_clarke_tf(a: i16, b: i16, c: i16) -> (i16, i16) {
 
     (alpha, beta) // Return the alpha and beta components
 }
+
+/// Performs the Park transform, direct and quadrature components of the `alpha`/`beta` vector in
+/// the frame rotating with the angle given by `sincos` (i1.15).
+///
+/// The direct axis points along `(sin, cos)`, the quadrature axis 90° ahead along `(cos, -sin)`.
+#[inline]
+pub fn park_transform(alpha_beta: (i16, i16), sincos: (i16, i16)) -> (i16, i16) {
+    let (alpha, beta) = (alpha_beta.0 as i64, alpha_beta.1 as i64);
+    let (sin, cos) = (sincos.0 as i64, sincos.1 as i64);
+
+    // d = alpha * sin + beta * cos, q = alpha * cos - beta * sin
+    // The sum reaches 2^31 at i16::MIN and doesn't fit i32
+    let d = (alpha * sin + beta * cos) >> 15;
+    let q = (alpha * cos - beta * sin) >> 15;
+
+    (saturate(d), saturate(q))
+}
+
+/// Performs the inverse Park transform, `alpha` and `beta` components of the direct/quadrature
+/// vector in the frame rotating with the angle given by `sincos` (i1.15).
+#[inline]
+pub fn inverse_park_transform(d_q: (i16, i16), sincos: (i16, i16)) -> (i16, i16) {
+    let (d, q) = (d_q.0 as i64, d_q.1 as i64);
+    let (sin, cos) = (sincos.0 as i64, sincos.1 as i64);
+
+    // alpha = d * sin + q * cos, beta = d * cos - q * sin
+    let alpha = (d * sin + q * cos) >> 15;
+    let beta = (d * cos - q * sin) >> 15;
+
+    (saturate(alpha), saturate(beta))
+}
+
+/// Clamps an i64 intermediate to the i16 range
+#[inline(always)]
+fn saturate(value: i64) -> i16 {
+    value.clamp(i16::MIN as i64, i16::MAX as i64) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+    use std::vec::Vec;
+
+    use super::duty::ab2abc;
+    use super::{inverse_park_transform, park_transform};
+    use crate::math_float;
+
+    const MAX_OUTPUT: f64 = i16::MAX as f64;
+    /// Tolerated line to line difference from the ideal transform (counts), truncation of the
+    /// 15-bit scale factor and of each scaled phase
+    const LINE_TOLERANCE: f64 = 4.0;
+    /// Tolerated phase duty difference from the floating point mirror (counts)
+    const GOLDEN_TOLERANCE: f64 = 4.0;
+
+    /// Tolerated difference of the Park transforms from the floating point mirror (counts),
+    /// truncation of the 15-bit shift
+    const PARK_TOLERANCE: f32 = 1.5;
+
+    /// Transform of a vector by the angle given as `(sin, cos)`
+    type Transform<T> = fn((T, T), (T, T)) -> (T, T);
+
+    /// Values at and next to the limits of i16
+    const EDGES: [i16; 7] = [i16::MIN, i16::MIN + 1, -1, 0, 1, i16::MAX - 1, i16::MAX];
+
+    /// Checks that every phase duty stays within `0..=i16::MAX` and that the line to line
+    /// voltages match the inverse Clarke transform, scaled down when the vector exceeds the
+    /// available voltage
+    fn check_range(sin: i16, cos: i16) {
+        let duty = ab2abc(sin, cos);
+        let phases = [duty.0, duty.1, duty.2];
+        assert!(
+            phases.iter().all(|&phase| phase >= 0),
+            "ab2abc({}, {}): negative duty {:?}",
+            sin,
+            cos,
+            phases
+        );
+
+        // Vectors below a count round to zero and brake
+        if phases == [0; 3] {
+            return;
+        }
+        let (alpha, beta) = (sin as f64, cos as f64 * 3f64.sqrt() / 2.0);
+        let ideal = [alpha, beta - alpha / 2.0, -beta - alpha / 2.0];
+        let max = ideal.iter().copied().fold(f64::MIN, f64::max);
+        let min = ideal.iter().copied().fold(f64::MAX, f64::min);
+        let scale = (MAX_OUTPUT / (max - min)).min(1.0);
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            let line = (phases[i] as f64 - phases[j] as f64) - (ideal[i] - ideal[j]) * scale;
+            assert!(
+                line.abs() <= LINE_TOLERANCE,
+                "ab2abc({}, {}): line voltage {}-{} off by {:.1}: {:?}",
+                sin,
+                cos,
+                i,
+                j,
+                line,
+                phases
+            );
+        }
+    }
+
+    /// Compares the phase duties with the floating point mirror as golden model, returns the
+    /// largest phase error
+    fn check_golden(sin: i16, cos: i16) -> f64 {
+        let duty = ab2abc(sin, cos);
+        let phases = [duty.0, duty.1, duty.2];
+        // Vectors below a count round to zero and brake, the float model centers them instead
+        if phases == [0; 3] {
+            return 0.0;
+        }
+        let (a, b, c) = math_float::motor::bldc::duty::ab2abc(
+            sin as f32 / MAX_OUTPUT as f32,
+            cos as f32 / MAX_OUTPUT as f32,
+        );
+        let golden = [a, b, c].map(|duty| duty as f64 * MAX_OUTPUT);
+        let error = (0..3)
+            .map(|i| (phases[i] as f64 - golden[i]).abs())
+            .fold(0.0, f64::max);
+        assert!(
+            error <= GOLDEN_TOLERANCE,
+            "ab2abc({}, {}): off golden model by {:.1}: {:?}",
+            sin,
+            cos,
+            error,
+            phases
+        );
+        error
+    }
+
+    /// Compares a Park transform with its floating point mirror, the expected outputs saturated
+    /// to the i16 range like the integer ones
+    fn check_park(
+        name: &str,
+        transform: Transform<i16>,
+        golden: Transform<f32>,
+        vector: (i16, i16),
+        sincos: (i16, i16),
+    ) {
+        const SCALE: f32 = 32768.0; // i1.15
+        let normalize = |pair: (i16, i16)| (pair.0 as f32 / SCALE, pair.1 as f32 / SCALE);
+        let limit = |value: f32| (value * SCALE).clamp(i16::MIN as f32, i16::MAX as f32);
+        let result = transform(vector, sincos);
+        let expected = golden(normalize(vector), normalize(sincos));
+        let expected = (limit(expected.0), limit(expected.1));
+        assert!(
+            (result.0 as f32 - expected.0).abs() <= PARK_TOLERANCE
+                && (result.1 as f32 - expected.1).abs() <= PARK_TOLERANCE,
+            "{}({:?}, {:?}) = {:?}, expected {:?}",
+            name,
+            vector,
+            sincos,
+            result,
+            expected
+        );
+    }
+
+    /// Runs both Park transforms against the floating point mirror
+    fn check_park_pair(vector: (i16, i16), sincos: (i16, i16)) {
+        use math_float::motor::bldc as float;
+        check_park(
+            "park_transform",
+            park_transform,
+            float::park_transform,
+            vector,
+            sincos,
+        );
+        check_park(
+            "inverse_park_transform",
+            inverse_park_transform,
+            float::inverse_park_transform,
+            vector,
+            sincos,
+        );
+    }
+
+    /// Input pairs with one component at or next to the i16 limits, on the full scale circle
+    /// and on a coarse grid of the rest
+    fn boundary_inputs() -> Vec<(i16, i16)> {
+        let mut pairs = Vec::new();
+        for edge in EDGES {
+            for value in i16::MIN..=i16::MAX {
+                pairs.push((edge, value));
+                pairs.push((value, edge));
+            }
+        }
+        for step in 0..65536 {
+            let angle = step as f64 * TAU / 65536.0;
+            for magnitude in [MAX_OUTPUT, MAX_OUTPUT * 2f64.sqrt()] {
+                let clamp = |value: f64| value.clamp(i16::MIN as f64, MAX_OUTPUT) as i16;
+                pairs.push((
+                    clamp(angle.sin() * magnitude),
+                    clamp(angle.cos() * magnitude),
+                ));
+            }
+        }
+        for sin in (i16::MIN..=i16::MAX).step_by(61) {
+            for cos in (i16::MIN..=i16::MAX).step_by(67) {
+                pairs.push((sin, cos));
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn svpwm_duty_range_at_boundaries() {
+        for (sin, cos) in boundary_inputs() {
+            check_range(sin, cos);
+        }
+    }
+
+    #[test]
+    fn svpwm_duty_matches_golden_model() {
+        for (sin, cos) in boundary_inputs() {
+            check_golden(sin, cos);
+        }
+    }
+
+    #[test]
+    fn park_saturates_at_the_corners() {
+        for vector in EDGES.iter().flat_map(|&a| EDGES.map(|b| (a, b))) {
+            for sincos in EDGES.iter().flat_map(|&a| EDGES.map(|b| (a, b))) {
+                check_park_pair(vector, sincos);
+            }
+        }
+        // Both products at i16::MIN add up to 2^31
+        assert_eq!(
+            park_transform((i16::MIN, i16::MIN), (i16::MIN, i16::MIN)),
+            (i16::MAX, 0)
+        );
+        assert_eq!(
+            inverse_park_transform((i16::MIN, i16::MIN), (i16::MIN, i16::MIN)),
+            (i16::MAX, 0)
+        );
+    }
+
+    #[test]
+    fn park_matches_golden_model() {
+        for step in 0..4096 {
+            let angle = step as f64 * TAU / 4096.0;
+            let sincos = (
+                (angle.sin() * MAX_OUTPUT).round() as i16,
+                (angle.cos() * MAX_OUTPUT).round() as i16,
+            );
+            for magnitude in [1.0, 1000.0, MAX_OUTPUT / 2.0, MAX_OUTPUT] {
+                let phase = angle * 7.0; // Vector not aligned with the frame
+                let vector = (
+                    (phase.sin() * magnitude).round() as i16,
+                    (phase.cos() * magnitude).round() as i16,
+                );
+                check_park_pair(vector, sincos);
+            }
+        }
+    }
+
+    /// All 2^32 input pairs, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn svpwm_duty_exhaustive() {
+        for sin in i16::MIN..=i16::MAX {
+            for cos in i16::MIN..=i16::MAX {
+                check_range(sin, cos);
+                check_golden(sin, cos);
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Implements a fixed-point back-EMF observer estimating the rotor angle and speed of a BLDC
+// motor from the applied voltages and the measured currents, without a position sensor.
+
+// Key Features:
+// - Luenberger observer of the winding current and back-EMF in the stationary alpha/beta frame.
+// - Both observer poles placed at one bandwidth, the gains follow from the motor data.
+// - Phase-locked loop on the back-EMF vector for a smooth angle and speed.
+// - Commutation angle along the torque axis in both directions of rotation.
+
+// Detailed Operation:
+// The winding follows L * di/dt = v - R * i - e with the back-EMF e changing slowly compared
+// to the current. The observer runs the same model on its estimates and corrects both with
+// the current error, every control tick:
+//   i^ += Ts / L * (v - R * i^ - e^) + l1 * Ts * (i - i^),  e^ -= l2 * Ts * (i - i^)
+// With l1 = 2 * wo - R / L and l2 = wo^2 * L both poles of the error dynamics sit at -wo, so
+// the estimate settles within a few 1 / wo without a derivative of the noisy current. The
+// bandwidth has to stay well below the tick rate (limited to a quarter of it).
+// The back-EMF vector is perpendicular to the rotor flux and turns with the rotor. Its angle
+// (CORDIC) is tracked by a type 2 PLL with a quarter of the observer bandwidth, which filters
+// the angle and gives the electrical speed. The vector points along the torque axis rotating
+// forward and against it rotating backward, so the commutation angle is the tracked angle
+// turned by half a revolution at negative speed.
+// The back-EMF grows with the speed, below a few percent of the rated speed it drowns in the
+// model errors (resistance, dead time) and the angle is meaningless; `magnitude()` tells the
+// caller when the estimate can be trusted.
+// Angles are 65536 per electrical revolution, the PLL keeps them as the upper half of a u32.
+// Voltages and currents are aligned like the rest of the driver: alpha with the sine, beta
+// with the cosine component of an angle.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::trigonometry::vector2mag_angle;
+
+/// Back-EMF observer with angle and speed tracking.
+pub struct BemfObserver {
+    frequency: u16,      // Update frequency (ticks per second)
+    resistance: i64,     // Phase resistance (mOhm)
+    gain_v: i64,         // Current change per voltage (mA per mV per tick as i48.16)
+    gain_i: i64,         // Current correction per tick as i48.16
+    gain_e: i64,         // Back-EMF correction (mV per mA per tick as i48.16)
+    pll_kp: i64,         // PLL proportional gain per tick as i48.16
+    pll_ki: i64,         // PLL integral gain per tick as i48.16
+    current: (i64, i64), // Estimated alpha/beta current (mA as i48.16)
+    emf: (i64, i64),     // Estimated alpha/beta back-EMF (mV as i48.16)
+    magnitude: u32,      // Back-EMF magnitude (mV)
+    angle: u32,          // Tracked back-EMF angle (65536 per revolution as u16.16)
+    velocity: i32,       // Tracked speed (revolution per tick as i0.32)
+}
+
+impl BemfObserver {
+    /// Creates an observer without motor data, configure it before use.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency: frequency.max(1),
+            resistance: 0,
+            gain_v: 0,
+            gain_i: 0,
+            gain_e: 0,
+            pll_kp: 0,
+            pll_ki: 0,
+            current: (0, 0),
+            emf: (0, 0),
+            magnitude: 0,
+            angle: 0,
+            velocity: 0,
+        }
+    }
+
+    /// Sets the motor data and the bandwidth, the estimate restarts from zero.
+    ///
+    /// # Arguments
+    /// * `resistance` - Phase resistance (mOhm)
+    /// * `inductance` - Phase inductance (uH)
+    /// * `bandwidth` - Observer bandwidth (rad/s), limited to a quarter of the tick rate
+    pub fn configure(&mut self, resistance: i32, inductance: i32, bandwidth: u32) {
+        let frequency = self.frequency as i64;
+        let resistance = resistance.max(0) as i64;
+        let inductance = inductance.max(1) as i64;
+        let omega = (bandwidth as i64).min(frequency / 4);
+
+        self.resistance = resistance;
+        // Ts / L = 1e6 / (f * L[uH]) mA per mV
+        self.gain_v = (1_000_000i64 << 16) / (frequency * inductance);
+        // l1 * Ts = (2 * wo - R / L) / f, R / L = R[mOhm] * 1000 / L[uH] per second
+        let l1 = 2 * omega - resistance * 1000 / inductance;
+        self.gain_i = ((l1 << 16) / frequency).clamp(0, 1 << 16);
+        // l2 * Ts = wo^2 * L / f = wo^2 * L[uH] / (f * 1e6) mV per mA
+        self.gain_e = ((omega * omega * inductance) << 16) / (frequency * 1_000_000);
+        // Critically damped PLL at a quarter of the bandwidth: kp = 2 * wp * Ts, ki = (wp * Ts)^2
+        let omega_pll = omega / 4;
+        self.pll_kp = ((2 * omega_pll) << 16) / frequency;
+        self.pll_ki = ((omega_pll * omega_pll) << 16) / (frequency * frequency);
+        self.reset();
+    }
+
+    /// Math call, updates the estimate with the voltage applied and the current measured over
+    /// the last tick.
+    ///
+    /// # Arguments
+    /// * `voltage` - Applied alpha/beta voltage (mV)
+    /// * `current` - Measured alpha/beta current (mA)
+    pub fn tick(&mut self, voltage: (i32, i32), current: (i16, i16)) {
+        self.current.0 = self.update(0, voltage.0, current.0);
+        self.current.1 = self.update(1, voltage.1, current.1);
+
+        // Angle of the back-EMF vector: cosine (beta) along X, sine (alpha) along Y
+        let emf = ((self.emf.0 >> 16) as i32, (self.emf.1 >> 16) as i32);
+        let (magnitude, angle) = vector2mag_angle(emf.1, emf.0);
+        self.magnitude = magnitude;
+
+        // PLL follows the measured angle, the error is the shortest turn to it
+        let error = ((angle as u32) << 16).wrapping_sub(self.angle) as i32 as i64;
+        self.velocity = self
+            .velocity
+            .wrapping_add(((error * self.pll_ki) >> 16) as i32);
+        let step = self.velocity as i64 + ((error * self.pll_kp) >> 16);
+        self.angle = self.angle.wrapping_add(step as i32 as u32);
+    }
+
+    /// Updates the current and back-EMF estimate of one axis, returns the new current estimate
+    #[inline(always)]
+    fn update(&mut self, axis: usize, voltage: i32, current: i16) -> i64 {
+        let (estimate, emf) = if axis == 0 {
+            (self.current.0, &mut self.emf.0)
+        } else {
+            (self.current.1, &mut self.emf.1)
+        };
+        let error = ((current as i64) << 16) - estimate;
+        let drive = ((voltage as i64) << 16) - estimate * self.resistance / 1000 - *emf;
+        *emf -= (error * self.gain_e) >> 16;
+        estimate + ((drive * self.gain_v) >> 16) + ((error * self.gain_i) >> 16)
+    }
+
+    /// Estimated alpha/beta back-EMF (mV)
+    pub fn emf(&self) -> (i32, i32) {
+        ((self.emf.0 >> 16) as i32, (self.emf.1 >> 16) as i32)
+    }
+
+    /// Back-EMF magnitude (mV), the angle is only valid well above the model errors
+    pub fn magnitude(&self) -> u32 {
+        self.magnitude
+    }
+
+    /// Tracked angle of the back-EMF vector (65536 per electrical revolution)
+    pub fn angle(&self) -> u16 {
+        (self.angle >> 16) as u16
+    }
+
+    /// Commutation angle along the torque axis (65536 per electrical revolution)
+    pub fn commutation_angle(&self) -> u16 {
+        if self.velocity < 0 {
+            self.angle().wrapping_add(32768)
+        } else {
+            self.angle()
+        }
+    }
+
+    /// Electrical speed (65536 counts per electrical revolution per second)
+    pub fn speed(&self) -> i32 {
+        ((self.velocity as i64 * self.frequency as i64) >> 16) as i32
+    }
+
+    /// Clears the estimate
+    pub fn reset(&mut self) {
+        self.current = (0, 0);
+        self.emf = (0, 0);
+        self.magnitude = 0;
+        self.angle = 0;
+        self.velocity = 0;
+    }
+}
@@ -0,0 +1,243 @@
+// Implements the frequency response (Bode) measurement driving the signal generator.
+
+// Key Features:
+// - Logarithmically spaced sine excitation between a start and an end frequency.
+// - Single bin DFT of the excitation and the response at the excitation frequency.
+// - Gain and phase of the response relative to the actually injected excitation.
+// - Results of every point kept on-device for readout by the host.
+// - Compiled out without the `tuning` feature together with the generator.
+
+// Detailed Operation:
+// For every point the owner switches the generator to a constant frequency sine. The analyzer
+// first waits `SETTLE_PERIODS` periods for transients to decay and then correlates both
+// signals with the sine and cosine of the generator phase over a whole number of periods
+// (rounded up to whole ticks):
+//   X = sum(x * e^(-j*phase)),  Y = sum(y * e^(-j*phase))
+// The ratio Y / X is the frequency response at that point. Correlating the injected signal
+// instead of assuming the commanded amplitude keeps the result correct if the excitation is
+// limited. Magnitude and angle of X and Y are found with CORDIC, the gain is reported in i16.16
+// and the phase as angle (65536 per full turn, negative values - response lags).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::trigonometry::{angle2sincos, vector2mag_angle};
+
+/// Result of one measurement point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResponsePoint {
+    pub frequency_mhz: u32, // Excitation frequency (mHz)
+    pub gain: u32,          // Response to excitation amplitude ratio (i16.16)
+    pub phase: i16,         // Response phase relative to the excitation (65536 per turn)
+}
+
+/// Next action the owner has to take with the signal generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyzerStep {
+    /// Keep the generator running
+    Continue,
+    /// Switch the generator sine to the given frequency (mHz)
+    Frequency(u32),
+    /// Measurement finished, stop the generator
+    Done,
+}
+
+/// Frequency response analyzer with up to `N` points.
+pub struct FrequencyResponse<const N: usize> {
+    frequency: u16,             // Update frequency (ticks per second)
+    points: [ResponsePoint; N], // Measured points
+    count: usize,               // Number of points to measure
+    index: usize,               // Point being measured
+    ratio: f32,                 // Frequency ratio between neighbouring points
+    periods: u16,               // Number of measured periods per point
+    settle_ticks: u32,          // Ticks left before correlation starts
+    measure_ticks: u32,         // Ticks left until the point is complete
+    input: (i64, i64),          // Correlation of the excitation (real, imaginary)
+    response: (i64, i64),       // Correlation of the response (real, imaginary)
+    running: bool,              // Measurement in progress
+}
+
+impl<const N: usize> FrequencyResponse<N> {
+    /// Periods waited at every point before correlation starts
+    const SETTLE_PERIODS: u32 = 2;
+
+    /// Creates an idle analyzer.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            points: [ResponsePoint::default(); N],
+            count: 0,
+            index: 0,
+            ratio: 1.0,
+            periods: 1,
+            settle_ticks: 0,
+            measure_ticks: 0,
+            input: (0, 0),
+            response: (0, 0),
+            running: false,
+        }
+    }
+
+    /// Starts a measurement, returns the frequency of the first point.
+    ///
+    /// # Arguments
+    /// * `start_mhz` - Frequency of the first point (mHz)
+    /// * `end_mhz` - Frequency of the last point (mHz)
+    /// * `count` - Number of points, limited to `N`
+    /// * `periods` - Number of measured periods per point
+    pub fn start(&mut self, start_mhz: u32, end_mhz: u32, count: usize, periods: u16) -> u32 {
+        self.count = count.clamp(1, N);
+        self.periods = periods.max(1);
+        self.ratio = Self::step_ratio(start_mhz.max(1), end_mhz.max(1), self.count);
+        self.points = [ResponsePoint::default(); N];
+        self.points[0].frequency_mhz = start_mhz.max(1);
+        self.index = 0;
+        self.running = true;
+        self.begin_point();
+        self.points[0].frequency_mhz
+    }
+
+    /// Aborts the measurement, points measured so far are kept
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    /// Correlates one sample, call once per control tick while running.
+    ///
+    /// # Arguments
+    /// * `phase` - Phase of the generator (full period = 2^32)
+    /// * `input` - Injected excitation
+    /// * `response` - Measured response signal
+    pub fn tick(&mut self, phase: u32, input: i32, response: i32) -> AnalyzerStep {
+        if !self.is_running() {
+            return AnalyzerStep::Continue;
+        }
+        if self.settle_ticks > 0 {
+            self.settle_ticks -= 1;
+            return AnalyzerStep::Continue;
+        }
+
+        let (sin, cos) = angle2sincos((phase >> 16) as i16);
+        let (sin, cos) = (sin as i64, cos as i64);
+        self.input.0 += (input as i64 * cos) >> 15;
+        self.input.1 -= (input as i64 * sin) >> 15;
+        self.response.0 += (response as i64 * cos) >> 15;
+        self.response.1 -= (response as i64 * sin) >> 15;
+
+        self.measure_ticks -= 1;
+        if self.measure_ticks > 0 {
+            return AnalyzerStep::Continue;
+        }
+
+        self.finish_point();
+        self.index += 1;
+        if self.index >= self.count {
+            self.running = false;
+            return AnalyzerStep::Done;
+        }
+        let previous = self.points[self.index - 1].frequency_mhz as f32;
+        let frequency = (previous * self.ratio) as u32;
+        self.points[self.index].frequency_mhz = frequency;
+        self.begin_point();
+        AnalyzerStep::Frequency(frequency)
+    }
+
+    /// Returns true while measuring
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Returns a measured point, `None` if the index wasn't measured (yet)
+    pub fn point(&self, index: usize) -> Option<ResponsePoint> {
+        let measured = if self.running { self.index } else { self.count };
+        if index < measured {
+            Some(self.points[index])
+        } else {
+            None
+        }
+    }
+
+    /// Number of points of the last started measurement
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Resets correlation and timing for the current point
+    fn begin_point(&mut self) {
+        let frequency = self.points[self.index].frequency_mhz.max(1) as u64;
+        let period_ticks = (self.frequency as u64 * 1000).div_ceil(frequency);
+        self.settle_ticks =
+            (period_ticks * Self::SETTLE_PERIODS as u64).min(u32::MAX as u64) as u32;
+        self.measure_ticks = (period_ticks * self.periods as u64).clamp(1, u32::MAX as u64) as u32;
+        self.input = (0, 0);
+        self.response = (0, 0);
+    }
+
+    /// Computes gain and phase of the current point
+    fn finish_point(&mut self) {
+        let (input_mag, input_angle, input_shift) = Self::polar(self.input);
+        let (response_mag, response_angle, response_shift) = Self::polar(self.response);
+
+        let point = &mut self.points[self.index];
+        point.phase = response_angle.wrapping_sub(input_angle) as i16;
+        point.gain = if input_mag == 0 {
+            0
+        } else {
+            // gain = (response_mag << response_shift) / (input_mag << input_shift) in i16.16
+            let shift = response_shift as i32 - input_shift as i32 + 16;
+            let response_mag = response_mag as u128;
+            let gain = if shift >= 0 {
+                (response_mag << shift) / input_mag as u128
+            } else {
+                (response_mag >> -shift) / input_mag as u128
+            };
+            gain.min(u32::MAX as u128) as u32
+        };
+        defmt::info!(
+            "BODE: {} mHz, gain {} (i16.16), phase {}",
+            point.frequency_mhz,
+            point.gain,
+            point.phase
+        );
+    }
+
+    /// Magnitude, angle and scaling shift of a correlation result
+    fn polar(value: (i64, i64)) -> (u32, u16, u32) {
+        // Scale both components into the CORDIC input range
+        let largest = value.0.unsigned_abs().max(value.1.unsigned_abs());
+        let shift = (64 - largest.leading_zeros()).saturating_sub(28);
+        let (x, y) = ((value.0 >> shift) as i32, (value.1 >> shift) as i32);
+        let (magnitude, angle) = vector2mag_angle(x, y);
+        (magnitude, angle, shift)
+    }
+
+    /// Frequency ratio between points for logarithmic spacing
+    fn step_ratio(start_mhz: u32, end_mhz: u32, count: usize) -> f32 {
+        if count < 2 {
+            return 1.0;
+        }
+        // Find r with r^(count - 1) = end / start by bisection
+        let target = end_mhz as f32 / start_mhz as f32;
+        let (mut low, mut high) = if target >= 1.0 {
+            (1.0, target)
+        } else {
+            (target, 1.0)
+        };
+        for _ in 0..32 {
+            let mid = (low + high) * 0.5;
+            let mut power = 1.0;
+            for _ in 1..count {
+                power *= mid;
+            }
+            if power < target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (low + high) * 0.5
+    }
+}
@@ -0,0 +1,251 @@
+// Implements the test signal generator used to excite a control loop node.
+
+// Key Features:
+// - Step, square, sine sweep (linear chirp) and PRBS waveforms.
+// - Frequency resolution of 1 mHz from a 32-bit phase accumulator.
+// - Injection points (current, velocity, position reference) with amplitude limits.
+// - Fixed duration or continuous operation.
+// - Compiled out without the `tuning` feature, the excitation commands are then refused.
+
+// Detailed Operation:
+// The generator is ticked at the control loop rate and returns the value to add to the excited
+// reference. A 32-bit phase accumulator advances by `f * 2^32 / fs` every tick:
+// - Sweep: sine of the phase. The increment changes linearly from the start to the end
+//   frequency over the duration; with equal frequencies it generates a single sine.
+// - Square: +amplitude in the first half of the period, -amplitude in the second half.
+// - PRBS: on every phase wrap a 15-bit LFSR (x^15 + x^14 + 1) is shifted and its output bit
+//   selects +amplitude or -amplitude, so the frequency sets the bit rate.
+// - Step: constant amplitude.
+// When the duration expires the output returns to zero. A zero duration runs until stopped,
+// except for the sweep which needs a duration to move between the frequencies.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::trigonometry::angle2sincos;
+
+/// Shape of the generated signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Waveform {
+    /// Constant amplitude
+    Step = 0,
+    /// Square wave at the start frequency
+    Square = 1,
+    /// Sine with frequency moving from start to end frequency
+    Sweep = 2,
+    /// Pseudo random binary sequence with bit rate equal to the start frequency
+    Prbs = 3,
+}
+
+impl Waveform {
+    /// Converts raw value received over protocol
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(Waveform::Step),
+            1 => Some(Waveform::Square),
+            2 => Some(Waveform::Sweep),
+            3 => Some(Waveform::Prbs),
+            _ => None,
+        }
+    }
+}
+
+/// Loop node the generated signal is added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InjectionPoint {
+    /// Torque current reference (mA)
+    Current = 0,
+    /// Velocity reference (counts per second)
+    Velocity = 1,
+    /// Position reference (counts, 65536 per revolution)
+    Position = 2,
+}
+
+impl InjectionPoint {
+    /// Converts raw value received over protocol
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(InjectionPoint::Current),
+            1 => Some(InjectionPoint::Velocity),
+            2 => Some(InjectionPoint::Position),
+            _ => None,
+        }
+    }
+
+    /// Maximum amplitude allowed at the injection point
+    pub const fn limit(self) -> i32 {
+        match self {
+            InjectionPoint::Current => 2000,     // 2A
+            InjectionPoint::Velocity => 1 << 16, // 1 rev/s
+            InjectionPoint::Position => 1 << 14, // Quarter revolution
+        }
+    }
+}
+
+/// Test signal generator.
+pub struct SignalGenerator {
+    frequency: u16,     // Update frequency (ticks per second)
+    waveform: Waveform, // Shape of the signal
+    amplitude: i32,     // Peak value of the signal
+    phase: u32,         // Phase accumulator (full period = 2^32)
+    increment: i64,     // Phase increment per tick (i48.16)
+    sweep_step: i64,    // Change of the phase increment per tick (i48.16)
+    remaining: u32,     // Ticks until the end of the signal
+    continuous: bool,   // Run until stopped
+    lfsr: u16,          // PRBS shift register
+    output: i32,        // Last output value
+    running: bool,      // Signal is being generated
+}
+
+impl SignalGenerator {
+    /// Initial value of the PRBS shift register (any non zero value)
+    const LFSR_SEED: u16 = 0x7FFF;
+
+    /// Creates a stopped generator.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            waveform: Waveform::Step,
+            amplitude: 0,
+            phase: 0,
+            increment: 0,
+            sweep_step: 0,
+            remaining: 0,
+            continuous: false,
+            lfsr: Self::LFSR_SEED,
+            output: 0,
+            running: false,
+        }
+    }
+
+    /// Starts generating a signal.
+    ///
+    /// # Arguments
+    /// * `waveform` - Shape of the signal
+    /// * `amplitude` - Peak value, already limited by the caller
+    /// * `start_mhz` - Start frequency (or bit rate) in mHz
+    /// * `end_mhz` - End frequency of a sweep in mHz
+    /// * `duration_ms` - Duration of the signal, 0 runs until stopped
+    pub fn start(
+        &mut self,
+        waveform: Waveform,
+        amplitude: i32,
+        start_mhz: u32,
+        end_mhz: u32,
+        duration_ms: u32,
+    ) {
+        let ticks = (duration_ms as u64 * self.frequency as u64 / 1000).min(u32::MAX as u64);
+        let start = self.phase_increment(start_mhz);
+        let end = self.phase_increment(end_mhz);
+
+        self.waveform = waveform;
+        self.amplitude = amplitude;
+        self.phase = 0;
+        self.increment = start;
+        self.sweep_step = if ticks > 0 {
+            (end - start) / ticks as i64
+        } else {
+            0
+        };
+        self.remaining = ticks as u32;
+        self.continuous = ticks == 0;
+        self.lfsr = Self::LFSR_SEED;
+        self.output = 0;
+        self.running = true;
+    }
+
+    /// Changes the frequency of a running signal keeping its phase continuous
+    ///
+    /// # Arguments
+    /// * `frequency_mhz` - New frequency (or bit rate) in mHz
+    pub fn set_frequency(&mut self, frequency_mhz: u32) {
+        self.increment = self.phase_increment(frequency_mhz);
+        self.sweep_step = 0;
+    }
+
+    /// Stops the signal, output returns to zero
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.output = 0;
+    }
+
+    /// Advances the signal by one tick and returns the output value
+    pub fn tick(&mut self) -> i32 {
+        if !self.is_running() {
+            return 0;
+        }
+        if !self.continuous {
+            if self.remaining == 0 {
+                self.stop();
+                return 0;
+            }
+            self.remaining -= 1;
+        }
+
+        let previous = self.phase;
+        self.phase = self.phase.wrapping_add((self.increment >> 16) as u32);
+        self.increment += self.sweep_step;
+
+        self.output = match self.waveform {
+            Waveform::Step => self.amplitude,
+            Waveform::Square => {
+                if self.phase < 1 << 31 {
+                    self.amplitude
+                } else {
+                    -self.amplitude
+                }
+            }
+            Waveform::Sweep => {
+                let sine = angle2sincos((self.phase >> 16) as i16).0 as i32;
+                (sine * self.amplitude) >> 15
+            }
+            Waveform::Prbs => {
+                if self.phase < previous {
+                    // Next bit on every period
+                    let bit = ((self.lfsr >> 14) ^ (self.lfsr >> 13)) & 1;
+                    self.lfsr = ((self.lfsr << 1) | bit) & 0x7FFF;
+                }
+                if self.lfsr & 1 != 0 {
+                    self.amplitude
+                } else {
+                    -self.amplitude
+                }
+            }
+        };
+        self.output
+    }
+
+    /// Returns the last output value
+    pub fn output(&self) -> i32 {
+        self.output
+    }
+
+    /// Returns true while a signal is generated
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Returns the phase of the signal (full period = 2^32)
+    pub fn phase(&self) -> u32 {
+        self.phase
+    }
+
+    /// Returns the current frequency in mHz
+    pub fn frequency_mhz(&self) -> u32 {
+        let increment = (self.increment >> 16) as u64;
+        ((increment * 1000 * self.frequency as u64) >> 32) as u32
+    }
+
+    /// Phase increment per tick (i48.16) of a frequency in mHz
+    fn phase_increment(&self, frequency_mhz: u32) -> i64 {
+        // Frequencies above Nyquist are limited to it
+        let nyquist = self.frequency as u64 * 500;
+        let frequency = (frequency_mhz as u64).min(nyquist);
+        (((frequency << 32) / (1000 * self.frequency as u64)) << 16) as i64
+    }
+}
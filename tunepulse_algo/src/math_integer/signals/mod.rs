@@ -0,0 +1,6 @@
+#[cfg(feature = "tuning")]
+pub mod frequency_response;
+#[cfg(feature = "tuning")]
+pub mod generator;
+#[cfg(feature = "tuning")]
+pub mod step_response;
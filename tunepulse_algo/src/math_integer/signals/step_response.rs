@@ -0,0 +1,227 @@
+// Implements the on-device step response test, a quick health check of the tuning reporting
+// rise time, overshoot and steady-state error without host-side analysis.
+
+// Key Features:
+// - Two small amplitude step tests run back to back: current step, then velocity step.
+// - Rise time (10 % to 90 %), overshoot and steady-state error computed on-device.
+// - Results kept for readout by the host, no sample streaming needed.
+// - Owner aborts the test on excessive travel, faults or when the axis is held.
+// - Compiled out without the `tuning` feature together with the generator.
+
+// Detailed Operation:
+// Every test runs three phases:
+// - Baseline (duration / 4): no step, the response is averaged as the zero level.
+// - Step (duration): the step is injected, the 10 % and 90 % crossings and the peak of the
+//   response above the baseline are tracked. The last quarter is averaged as steady state.
+// - Recovery (duration / 4): no step, the loop returns before the next test starts.
+// The current test injects into the torque command and measures the current command after
+// friction and disturbance compensation. The winding current is driven open loop from the
+// resistance, so this path is what the tuning shapes. The velocity test moves the reference
+// position with the step speed and measures the estimated speed, showing how the axis follows
+// a speed step.
+// Overshoot and steady-state error are reported in per-mille of the step (positive error -
+// response stays below the step), the rise time is `None` if the response never reached 90 %.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::generator::InjectionPoint;
+
+/// Number of step tests
+pub const STEP_TEST_COUNT: usize = 2;
+
+/// Tests in the order they are run
+pub const STEP_TESTS: [InjectionPoint; STEP_TEST_COUNT] =
+    [InjectionPoint::Current, InjectionPoint::Velocity];
+
+/// Result of one step test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    pub rise_us: Option<u32>, // Time from 10 % to 90 % of the step (µs)
+    pub overshoot: u32,       // Peak above the step (per-mille of the step)
+    pub error: i32,           // Step minus steady-state level (per-mille of the step)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Baseline,
+    Step,
+    Recovery,
+}
+
+/// Runs the step tests and keeps their results.
+pub struct StepResponse {
+    frequency: u16,                                 // Update frequency (ticks per second)
+    steps: [i32; STEP_TEST_COUNT], // Step height of every test (units of the point)
+    duration: u32,                 // Length of the step (ticks, at least 4)
+    results: [Option<StepResult>; STEP_TEST_COUNT], // Results of the finished tests
+    test: usize,                   // Test being run
+    phase: Phase,                  // Phase of the running test
+    ticks: u32,                    // Ticks spent in the phase
+    sum: i64,                      // Response sum of the baseline and steady state
+    baseline: i32,                 // Response level before the step
+    rise_start: Option<u32>,       // Step tick reaching 10 %
+    rise_end: Option<u32>,         // Step tick reaching 90 %
+    peak: i32,                     // Highest response above the baseline
+    origin: i32,                   // Position the test started at
+}
+
+impl StepResponse {
+    /// Creates an idle test without results.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            steps: [1; STEP_TEST_COUNT],
+            duration: 4,
+            results: [None; STEP_TEST_COUNT],
+            test: 0,
+            phase: Phase::Idle,
+            ticks: 0,
+            sum: 0,
+            baseline: 0,
+            rise_start: None,
+            rise_end: None,
+            peak: 0,
+            origin: 0,
+        }
+    }
+
+    /// Configures the steps, takes effect on the next start.
+    ///
+    /// # Arguments
+    /// * `current` - Height of the current step (mA)
+    /// * `speed` - Height of the velocity step (counts per second)
+    /// * `duration_ms` - Length of the step
+    pub fn configure(&mut self, current: i32, speed: i32, duration_ms: u32) {
+        self.steps = [current.max(1), speed.max(1)];
+        let ticks = duration_ms as u64 * self.frequency as u64 / 1000;
+        self.duration = (ticks as u32).max(4);
+    }
+
+    /// Starts the tests, results of the previous run are cleared
+    ///
+    /// # Arguments
+    /// * `position` - Current position, travel is measured from it
+    pub fn start(&mut self, position: i32) {
+        self.results = [None; STEP_TEST_COUNT];
+        self.origin = position;
+        self.test = 0;
+        self.enter(Phase::Baseline);
+    }
+
+    /// Aborts the tests, results of finished tests are kept
+    pub fn stop(&mut self) {
+        self.phase = Phase::Idle;
+    }
+
+    /// Returns true while a test is running
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    /// Loop node of the running test
+    #[inline(always)]
+    pub fn point(&self) -> InjectionPoint {
+        STEP_TESTS[self.test]
+    }
+
+    /// Value to inject at `point()` in this tick, `None` while idle
+    pub fn output(&self) -> Option<i32> {
+        match self.phase {
+            Phase::Idle => None,
+            Phase::Step => Some(self.steps[self.test]),
+            Phase::Baseline | Phase::Recovery => Some(0),
+        }
+    }
+
+    /// Distance from the start position (counts)
+    #[inline(always)]
+    pub fn travel(&self, position: i32) -> u32 {
+        position.wrapping_sub(self.origin).unsigned_abs()
+    }
+
+    /// Result of the test at index `test` of `STEP_TESTS`, `None` if it didn't finish
+    pub fn result(&self, test: usize) -> Option<StepResult> {
+        self.results.get(test).copied().flatten()
+    }
+
+    /// Processes the response to this tick's output, returns true when all tests finished
+    ///
+    /// # Arguments
+    /// * `response` - Current command (mA) or speed (counts per second) of the running test
+    pub fn tick(&mut self, response: i32) -> bool {
+        let settle = self.duration / 4;
+        self.ticks += 1;
+        match self.phase {
+            Phase::Idle => return false,
+            Phase::Baseline => {
+                self.sum += response as i64;
+                if self.ticks >= settle {
+                    self.baseline = (self.sum / self.ticks as i64) as i32;
+                    self.enter(Phase::Step);
+                }
+            }
+            Phase::Step => {
+                let step = self.steps[self.test] as i64;
+                let level = response.saturating_sub(self.baseline);
+                if self.rise_start.is_none() && level as i64 * 10 >= step {
+                    self.rise_start = Some(self.ticks);
+                }
+                if self.rise_end.is_none() && level as i64 * 10 >= step * 9 {
+                    self.rise_end = Some(self.ticks);
+                }
+                self.peak = self.peak.max(level);
+                if self.ticks > self.duration - settle {
+                    self.sum += level as i64; // Steady state over the last quarter
+                }
+                if self.ticks >= self.duration {
+                    self.results[self.test] = Some(self.evaluate(settle));
+                    self.enter(Phase::Recovery);
+                }
+            }
+            Phase::Recovery => {
+                if self.ticks >= settle {
+                    self.test += 1;
+                    if self.test == STEP_TEST_COUNT {
+                        self.test = 0;
+                        self.phase = Phase::Idle;
+                        return true;
+                    }
+                    self.enter(Phase::Baseline);
+                }
+            }
+        }
+        false
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.ticks = 0;
+        self.sum = 0;
+        self.rise_start = None;
+        self.rise_end = None;
+        self.peak = 0;
+    }
+
+    /// Result of the finished step, `samples` steady-state samples were summed
+    fn evaluate(&self, samples: u32) -> StepResult {
+        let step = self.steps[self.test] as i64;
+        let steady = self.sum / samples as i64;
+        let rise_us = match (self.rise_start, self.rise_end) {
+            (Some(start), Some(end)) => {
+                Some(((end - start) as u64 * 1_000_000 / self.frequency as u64) as u32)
+            }
+            _ => None,
+        };
+        StepResult {
+            rise_us,
+            overshoot: ((self.peak as i64 - step).max(0) * 1000 / step) as u32,
+            error: ((step - steady) * 1000 / step) as i32,
+        }
+    }
+}
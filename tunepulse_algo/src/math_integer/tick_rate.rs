@@ -0,0 +1,31 @@
+// Implements the selection between a compile-time and a runtime control-loop frequency.
+
+// Key Features:
+// - Blocks parameterized by `const FREQ: u16` fix their tick rate at compile time.
+// - `RUNTIME` (0) keeps the frequency passed to the constructor, the default of every block.
+// - Resolving is a `const fn`, with a fixed rate every conversion folds into a constant.
+
+// Detailed Operation:
+// Time constants of the controller are given in ms or Hz and converted to ticks with the tick
+// rate, some of them on every tick (speed scaling, velocity excitation). With the rate known
+// only at runtime these are multiplications and divisions by a variable, on the Cortex-M4 a
+// division takes up to 12 cycles. A block declared with a fixed `FREQ` resolves its rate to that
+// constant, the compiler then turns the conversions into constant multiplications or shifts.
+// Blocks keep their runtime frequency field in both cases, so the variants share the code and
+// only differ in the value `resolve` returns; a fixed rate overrides the constructor argument.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// `FREQ` of blocks taking the tick rate from their constructor
+pub const RUNTIME: u16 = 0;
+
+/// Tick rate of a block: the compile-time `fixed` rate, `runtime` if it is `RUNTIME`
+#[inline(always)]
+pub const fn resolve(fixed: u16, runtime: u16) -> u16 {
+    if fixed != RUNTIME {
+        fixed
+    } else {
+        runtime
+    }
+}
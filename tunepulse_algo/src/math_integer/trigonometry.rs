@@ -122,4 +122,46 @@ pub fn rotate_sincos(source: (i16, i16), offset: (i16, i16)) -> (i16, i16) {
 
     // Return the rotated sine and cosine components
     (out_sin, out_cos)
-}
\ No newline at end of file
+}
+/// Arctangent of 2^-i for the CORDIC iterations, 65536 per full turn
+const CORDIC_ATAN: [u16; 16] = [
+    8192, 4836, 2555, 1297, 651, 326, 163, 81, 41, 20, 10, 5, 3, 1, 1, 0,
+];
+
+/// Computes magnitude and angle of a vector using CORDIC in vectoring mode.
+///
+/// ### Arguments
+/// * `x` - X (cosine) component of the vector, absolute value must stay below 2^29
+/// * `y` - Y (sine) component of the vector, absolute value must stay below 2^29
+///
+/// ### Returns
+/// * A tuple `(magnitude, angle)` - Length of the vector and its angle (65536 per full turn,
+///   0 along positive X, counter-clockwise).
+///
+/// ### Notes
+/// * The vector is first folded into the right half-plane, then rotated towards the X axis
+///   with shift-and-add steps while the applied rotations are summed up.
+/// * The CORDIC gain (~1.647) is removed from the magnitude at the end.
+pub fn vector2mag_angle(x: i32, y: i32) -> (u32, u16) {
+    // Fold into the right half-plane
+    let (mut x, mut y, mut angle) = if x < 0 { (-x, -y, 32768u16) } else { (x, y, 0u16) };
+
+    for (i, step) in CORDIC_ATAN.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            // Rotate clockwise
+            x += dx;
+            y -= dy;
+            angle = angle.wrapping_add(*step);
+        } else {
+            // Rotate counter-clockwise
+            x -= dx;
+            y += dy;
+            angle = angle.wrapping_sub(*step);
+        }
+    }
+
+    // Remove the CORDIC gain (1 / 1.647 = 39797 / 65536)
+    let magnitude = ((x as u64 * 39797) >> 16) as u32;
+    (magnitude, angle)
+}
@@ -1,8 +1,14 @@
 use defmt_rtt as _; // Use the defmt_rtt crate for logging via RTT (Real-Time Transfer)
 
-use super::CalibrationTable;
+use super::load_check::{LoadCheck, LoadVerdict};
+use super::{
+    cal_image_size, CalibrationError, CalibrationStage, CalibrationTable, CAL_POINTS_PER_POLE,
+    CAL_TABLE_RAM_BUDGET, CAL_TABLE_SIZE,
+};
+use crate::math_integer::tick_rate::{self, RUNTIME};
 
 /// Represents the current stage of the calibration process.
+#[derive(Copy, Clone)]
 enum CalStage {
     /// Test the motor's ability to respond linearly and consistently by performing a few test steps.
     Reset = 0, // Initial setup stage for calibration
@@ -13,6 +19,7 @@ enum CalStage {
     Check = 5, // State for verifying the calibration
     Ready = 6, // Calibration is complete and ready
     Error = 7, // An error occurred during calibration
+    Trim = 8,  // Quick recalibration of the zero offset against the stored table
 }
 
 /// Represents the state within each calibration cycle quarter:
@@ -30,7 +37,10 @@ enum CalSamplingState {
 }
 
 /// The main driver struct for the motor, holding all the state required for operation and calibration.
-pub struct AngleCalibrator {
+///
+/// `N` is the calibration table size, see `cal_table_size()` to derive it from the pole pair count.
+/// `FREQ` fixes the update frequency at compile time, `RUNTIME` takes it from `new`.
+pub struct AngleCalibrator<const N: usize = CAL_TABLE_SIZE, const FREQ: u16 = RUNTIME> {
     frequency: u16,    // Update frequency (ticks per second)
     pub position: i32, // Current encoder position reading
 
@@ -43,8 +53,12 @@ pub struct AngleCalibrator {
     cal_idx: usize, // Index for counting steps during calibration cycles
     // cal_table: [i32; Self::CAL_TABLE_SIZE], // Array for storing sampled encoder data during full calibration
     oversampled_pos: i32, // Accumulator for averaging positions during oversampling (Sampling stage)
+    half_pos: i32,        // Sum of the first half of the oversampled positions
+    creep: i32,           // Rotor movement within the last sampling window
 
     time_in_state: usize, // Counter for how many ticks remain in the current calibration sub-stage
+    stage_ticks: usize,   // Ticks left before the current stage times out
+    stage_planned: usize, // Expected duration of the current stage (ticks)
 
     direction: isize, // Current rotation direction (1 for forward, -1 for backward)
     speed: isize,     // Speed (steps per tick) during calibration
@@ -55,18 +69,36 @@ pub struct AngleCalibrator {
     dif_max: i32,  // Maximum difference in step measurement for consistency checks
     dif_min: i32,  // Minimum difference in step measurement for consistency checks
 
-    cal_table: CalibrationTable<200>,
+    cal_table: CalibrationTable<N>,
     el_step_idx: u16,
+
+    load: LoadCheck,                   // Pass comparison detecting a loaded axis
+    load_verdict: Option<LoadVerdict>, // Outcome of the load check after the passes
+    error: Option<CalibrationError>,   // Reason of the Error stage
+    trim_spread: Option<i32>,          // Deviation between the points of the last finished trim
+
+    trim_sum: i32,     // Accumulated electrical angle error during quick recalibration
+    trim_points: u16,  // Points sampled by the running trim
+    unit_trim: bool,   // Running trim aligns a table transferred from another unit
+    trim_total: i32,   // Total offset trim applied since the last full calibration
+    drift_misses: u16, // Leaky counter of table lookups that found no matching segment
+    needs_recal: bool, // Set when the stored table no longer matches the encoder
 }
 
 // Constants used during calibration
-impl AngleCalibrator {
+impl<const N: usize, const FREQ: u16> AngleCalibrator<N, FREQ> {
     const CAL_SETTLING_TIME_US: usize = 25000; // Settling time in milliseconds
     const CAL_SPEED_US: usize = 2500; // Speed in angle increments per millisecond
 
     const CAL_OVERSEMPLING: usize = 100; // Number of samples per oversampling period for averaging
     const CAL_FIRST_STEP_USTEPS: u16 = 16;
-    const CAL_POINTS_PER_360EL: u16 = 4;
+    const CAL_POINTS_PER_360EL: u16 = CAL_POINTS_PER_POLE as u16;
+
+    const TRIM_MAX_SPREAD: i32 = (u16::MAX / 16) as i32; // Max deviation between trim points (22.5° el)
+    const TRIM_MAX_DRIFT: i32 = (u16::MAX / 8) as i32; // Max total offset trim before full recalibration (45° el)
+    const DRIFT_MISS_WEIGHT: u16 = 64; // Penalty added to drift counter per failed table lookup
+    const DRIFT_MISS_LIMIT: u16 = 4096; // Drift counter level that requests full recalibration
+    const STAGE_TIMEOUT_MARGIN: usize = 2; // Stage timeout as multiple of its expected duration
 
     //---------------------------------------------------------
     // Description of the Calibration Algorithm and Steps:
@@ -95,11 +127,19 @@ impl AngleCalibrator {
     /// # Arguments
     /// * `motor` - Motor type configuration
     /// * `connection` - Phase pattern configuration
-    /// * `frequency` - Number of ticks per second
+    /// * `frequency` - Number of ticks per second, ignored with a fixed `FREQ`
     pub fn new(frequency: u16) -> Self {
+        // Reject table sizes that don't fit into the RAM budget at compile time
+        const {
+            assert!(
+                N * core::mem::size_of::<u16>() <= CAL_TABLE_RAM_BUDGET,
+                "Calibration table exceeds RAM budget"
+            )
+        };
+        let frequency = tick_rate::resolve(FREQ, frequency);
         let settling_time = Self::calculate_settling_time(frequency, Self::CAL_SETTLING_TIME_US);
 
-        Self {
+        let mut calibrator = Self {
             frequency,   // Store the update frequency
             position: 0, // Initialize encoder position to 0
 
@@ -111,7 +151,11 @@ impl AngleCalibrator {
 
             // cal_table: [0; Self::CAL_TABLE_SIZE], // Data array for storing calibration samples, initialized to 0
             oversampled_pos: 0, // Oversampling accumulator is initially 0
+            half_pos: 0,        // No samples collected yet
+            creep: 0,           // No movement measured yet
             time_in_state: 0,   // No time spent in current state initially
+            stage_ticks: 0,     // Set by the stage entry below
+            stage_planned: 0,   // Set by the stage entry below
 
             ang_el_step: 0, // Initialize calibration steps counter
             direction: 0,   // No direction initially
@@ -125,8 +169,21 @@ impl AngleCalibrator {
 
             cal_table: CalibrationTable::new(),
             el_step_idx: 0,
-            
-        }
+
+            load: LoadCheck::new(),
+            load_verdict: None,
+            error: None,
+
+            trim_sum: 0,
+            trim_points: Self::CAL_POINTS_PER_360EL,
+            unit_trim: false,
+            trim_spread: None,
+            trim_total: 0,
+            drift_misses: 0,
+            needs_recal: false,
+        };
+        calibrator.enter_stage(CalStage::Setup, 1);
+        calibrator
     }
 
     //---------------------------------------------------------
@@ -145,6 +202,15 @@ impl AngleCalibrator {
     pub fn tick(&mut self, encoder_pos: i32) -> u16 {
         self.position = encoder_pos; // Update the internal position from the sensor
                                      // defmt::println!("Angle: {}", encoder_pos);
+        if self.is_running() && Self::iter(&mut self.stage_ticks) {
+            // The stage took far longer than planned, the motor doesn't follow
+            defmt::error!(
+                "CALIBRATION: Stage {} timed out",
+                self.calibration_stage as u8
+            );
+            self.fail(CalibrationError::Timeout);
+            return self.angle_el;
+        }
         let stable_pos = self.run_sampling_cycle(self.ang_el_step); // Perform a calibration cycle and get stable position
 
         if stable_pos != i32::MIN {
@@ -154,8 +220,8 @@ impl AngleCalibrator {
                 CalStage::Setup => {
                     // After settling, move to the Setup stage
                     self.cal_idx = 10; // Arbitrary index setting for demonstration
-                    self.calibration_stage = CalStage::Reset;
-                    self.speed = Self::calculate_speed(self.frequency, Self::CAL_SPEED_US);
+                    self.speed = Self::calculate_speed(self.frequency(), Self::CAL_SPEED_US);
+                    self.enter_stage(CalStage::Reset, self.cal_idx + 1);
                     return self.angle_el;
                 }
 
@@ -173,7 +239,7 @@ impl AngleCalibrator {
                         // Set up for the FirstStep stage (16 steps)
                         self.ang_el_step = u16::MAX / Self::CAL_FIRST_STEP_USTEPS;
                         self.cal_idx = Self::CAL_FIRST_STEP_USTEPS as usize;
-                        self.calibration_stage = CalStage::Pass0;
+                        self.enter_stage(CalStage::Pass0, self.cal_idx + 1);
                         defmt::info!("CALIBRATION: Test single pole motion");
                     }
                 }
@@ -196,10 +262,10 @@ impl AngleCalibrator {
                         let avg_step = (travel * direction) / Self::CAL_FIRST_STEP_USTEPS as i32;
                         let deviation = self.dif_max - self.dif_min;
 
-                        if avg_step < deviation {
+                        if avg_step < deviation || travel == 0 {
                             // If the variation is too large, calibration fails
                             defmt::error!("CALIBRATION: Too much deviation while moving");
-                            self.calibration_stage = CalStage::Error;
+                            self.fail(CalibrationError::Motion);
                             return self.angle_el;
                         }
 
@@ -207,12 +273,17 @@ impl AngleCalibrator {
                         defmt::debug!("CALIBRATION: Detected motion direction: {}", self.direction);
 
                         // Prepare for the Pass1 stage
-                        self.calibration_stage = CalStage::Pass1;
                         self.ang_el_step = u16::MAX / Self::CAL_POINTS_PER_360EL;
                         self.speed = -self.speed * self.direction; // Adjust speed direction
                         self.cal_idx = 0;
                         self.init_pos = stable_pos;
                         self.cal_table.reset(Self::CAL_POINTS_PER_360EL);
+                        // Points of a turn as estimated from the test steps, plus margin
+                        let point_travel = avg_step.max(1) as usize
+                            * Self::CAL_FIRST_STEP_USTEPS as usize
+                            / Self::CAL_POINTS_PER_360EL as usize;
+                        let points = u16::MAX as usize / point_travel.max(1) + 2;
+                        self.enter_stage(CalStage::Pass1, points.min(N + 1));
                         defmt::info!(
                             "CALIBRATION: Full rotation in positive direction with sampling"
                         );
@@ -225,7 +296,7 @@ impl AngleCalibrator {
                     let avg_step = (stable_pos - self.init_pos) / (self.cal_idx as i32 + 1);
                     if stable_pos - self.init_pos > u16::MAX as i32 + (avg_step / 3) {
                         // Once we exceed the maximum range, switch to CCW run
-                        self.calibration_stage = CalStage::Pass2;
+                        self.enter_stage(CalStage::Pass2, self.cal_idx);
                         defmt::debug!("CALIBRATION: Position count: {}", self.cal_idx);
                         defmt::info!(
                             "CALIBRATION: Full rotation in negative direction with sampling"
@@ -233,26 +304,33 @@ impl AngleCalibrator {
                         self.speed = -self.speed;
                         return self.angle_el;
                     }
-                    // if self.cal_idx != Self::CAL_TABLE_SIZE as u16 {
+                    if self.cal_idx == N {
+                        // Motor has more poles than the table can hold
+                        defmt::error!(
+                            "CALIBRATION: Pole count exceeds table capacity ({} points)",
+                            N
+                        );
+                        self.fail(CalibrationError::TableCapacity);
+                        return self.angle_el;
+                    }
+                    self.load.add_creep(self.creep);
                     self.cal_table.fill_first(self.cal_idx, stable_pos as u16);
-                    // }
-                    // Increment index as we collect data (data storage commented out)
+                    // Increment index as we collect data
                     self.cal_idx += 1;
-                    // if self.cal_idx == Self::CAL_TABLE_SIZE as u16 {
-                    //     defmt::println!("Calibration: ERROR POLE COUNT");
-                    //     self.motor_status = MotorStatus::Error;
-                    // }
                 }
 
                 CalStage::Pass2 => {
                     // Perform a full rotation in CCW direction
                     self.cal_idx -= 1;
+                    let forward = self.cal_table.sample(self.cal_idx);
+                    self.load.add_point(forward, stable_pos as u16);
+                    self.load.add_creep(self.creep);
                     self.cal_table.fill_second(self.cal_idx, stable_pos as u16);
 
                     if self.cal_idx == 0 {
                         // Once we return to zero, calibration is complete
                         // self.motor_status = MotorStatus::Ready;
-                        self.calibration_stage = CalStage::Check;
+                        self.enter_stage(CalStage::Check, 1);
                         defmt::info!("CALIBRATION: Finished. Next => NORMAL RUN");
 
                         self.angle_el = 0;
@@ -262,10 +340,29 @@ impl AngleCalibrator {
 
                 CalStage::Check => {
                     self.cal_table.check();
+                    if !self.check_load() {
+                        return self.angle_el;
+                    }
                     self.calibration_stage = CalStage::Ready;
+                    self.trim_total = 0;
+                    self.drift_misses = 0;
+                    self.needs_recal = false;
                     // self.calibration_stage = CalStage::Setup;
                 }
 
+                CalStage::Trim => {
+                    // Compare the electrical angle reported by the table with the commanded one
+                    let table_el = self.cal_table.correct_pos(stable_pos as u16).1;
+                    let err = table_el.wrapping_sub(self.angle_el) as i16 as i32;
+                    self.trim_sum += err;
+                    self.dif_min = self.dif_min.min(err);
+                    self.dif_max = self.dif_max.max(err);
+
+                    if Self::iter(&mut self.cal_idx) {
+                        self.finish_trim();
+                    }
+                }
+
                 CalStage::Error => {}
                 CalStage::Ready => {}
             }
@@ -330,13 +427,18 @@ impl AngleCalibrator {
             }
 
             CalSamplingState::Sampling => {
+                if self.time_in_state == Self::CAL_OVERSEMPLING / 2 {
+                    self.half_pos = self.oversampled_pos; // First half of the window collected
+                }
                 // Oversample the encoder position to get a stable reading
                 if Self::cal_oversampling(
                     self.position,
                     &mut self.time_in_state,
                     &mut self.oversampled_pos,
                 ) {
-                    // Once oversampling is complete:
+                    // Once oversampling is complete, compare the averages of both halves
+                    let half = (Self::CAL_OVERSEMPLING / 2) as i32;
+                    self.creep = 2 * (self.oversampled_pos - self.half_pos / half);
                     self.cal_cycle_stage = CalSamplingState::Setup; // Reset cycle stage
                     return self.oversampled_pos; // Return the averaged stable position
                 }
@@ -373,6 +475,112 @@ impl AngleCalibrator {
         matches!(self.calibration_stage, CalStage::Ready) // Returns true if Ready
     }
 
+    /// Check if calibration failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(self.calibration_stage, CalStage::Error)
+    }
+
+    /// Sub-stage of the running calibration or trim, `None` once ready or failed.
+    pub fn stage(&self) -> Option<CalibrationStage> {
+        Some(match self.calibration_stage {
+            CalStage::Setup | CalStage::Reset => CalibrationStage::Settling,
+            CalStage::Pass0 => CalibrationStage::MotionTest,
+            CalStage::Pass1 => CalibrationStage::ForwardPass,
+            CalStage::Pass2 => CalibrationStage::BackwardPass,
+            CalStage::Check => CalibrationStage::Check,
+            CalStage::Trim => CalibrationStage::Trim,
+            CalStage::Ready | CalStage::Error => return None,
+        })
+    }
+
+    /// Progress of the running calibration or trim (percent), 100 once ready, 0 after a failure.
+    ///
+    /// Every stage covers a fixed range, advanced by the time spent against its expected
+    /// duration. The passes are planned from the pole count estimated by the test steps.
+    pub fn progress(&self) -> u8 {
+        let (start, end) = match self.calibration_stage {
+            CalStage::Setup => (0, 5),
+            CalStage::Reset => (5, 10),
+            CalStage::Pass0 => (10, 20),
+            CalStage::Pass1 => (20, 58),
+            CalStage::Pass2 => (58, 96),
+            CalStage::Check => (96, 100),
+            CalStage::Trim => (0, 100),
+            CalStage::Ready => return 100,
+            CalStage::Error => return 0,
+        };
+        let timeout = self.stage_planned * Self::STAGE_TIMEOUT_MARGIN;
+        let elapsed = (timeout - self.stage_ticks).min(self.stage_planned);
+        start + ((end - start) as usize * elapsed / self.stage_planned.max(1)) as u8
+    }
+
+    /// Reason of the failed calibration, `None` unless `is_failed()`.
+    #[inline(always)]
+    pub fn error(&self) -> Option<CalibrationError> {
+        self.error
+    }
+
+    /// Outcome of the load check of the last calibration, `None` before its passes finished.
+    #[inline(always)]
+    pub fn load_verdict(&self) -> Option<LoadVerdict> {
+        self.load_verdict
+    }
+
+    /// Mean hysteresis between the passes of the last calibration (counts).
+    #[inline(always)]
+    pub fn pass_hysteresis(&self) -> Option<i32> {
+        self.load.hysteresis()
+    }
+
+    /// Returns true while a calibration or trim stage is in progress.
+    #[inline(always)]
+    fn is_running(&self) -> bool {
+        !matches!(self.calibration_stage, CalStage::Ready | CalStage::Error)
+    }
+
+    /// Switches to `stage` expected to take `cycles` sampling cycles of the current step and
+    /// speed, the stage times out after `STAGE_TIMEOUT_MARGIN` times that duration.
+    fn enter_stage(&mut self, stage: CalStage, cycles: usize) {
+        let rotating = self.ang_el_step as usize / self.speed.unsigned_abs().max(1);
+        let cycle = rotating + self.settling_time + Self::CAL_OVERSEMPLING + 3; // + state switches
+        self.stage_planned = cycle * cycles;
+        self.stage_ticks = self.stage_planned * Self::STAGE_TIMEOUT_MARGIN;
+        self.calibration_stage = stage;
+    }
+
+    /// Enters the Error stage for `error`.
+    fn fail(&mut self, error: CalibrationError) {
+        self.error = Some(error);
+        self.calibration_stage = CalStage::Error;
+    }
+
+    /// Evaluates the passes for a loaded axis, returns false if the table was rejected.
+    fn check_load(&mut self) -> bool {
+        self.load.log();
+        let verdict = self.load.verdict();
+        self.load_verdict = Some(verdict);
+        match verdict {
+            LoadVerdict::Unloaded => true,
+            LoadVerdict::Warning(flags) => {
+                defmt::warn!(
+                    "CALIBRATION: Axis seems loaded (flags {}), table may be skewed",
+                    flags
+                );
+                true
+            }
+            LoadVerdict::Loaded(flags) => {
+                defmt::error!("CALIBRATION: Axis loaded (flags {}), table rejected", flags);
+                self.fail(CalibrationError::Loaded);
+                false
+            }
+        }
+    }
+
+    /// Check if a valid calibration table is available for position correction.
+    pub fn has_table(&self) -> bool {
+        matches!(self.calibration_stage, CalStage::Ready | CalStage::Trim)
+    }
+
     //---------------------------------------------------------
     // cal_oversampling() Method Steps:
     //
@@ -425,9 +633,167 @@ impl AngleCalibrator {
         }
     }
 
+    /// Corrects the encoder position using the calibration table.
+    ///
+    /// Also feeds the drift monitor: lookups that find no matching table segment mean the
+    /// encoder no longer agrees with the stored table and a full recalibration is needed.
+    #[inline(always)]
+    pub fn get_correction(&mut self, pos: u16) -> (u16, u16) {
+        match self.cal_table.lookup(pos) {
+            Some(ideal) => {
+                self.drift_misses = self.drift_misses.saturating_sub(1);
+                self.cal_table.angles(ideal)
+            }
+            None => {
+                self.drift_misses = self.drift_misses.saturating_add(Self::DRIFT_MISS_WEIGHT);
+                if self.drift_misses >= Self::DRIFT_MISS_LIMIT && !self.needs_recal {
+                    defmt::warn!(
+                        "CALIBRATION: Table lookups keep failing, full recalibration needed"
+                    );
+                    self.needs_recal = true;
+                }
+                self.cal_table.angles(u16::MAX)
+            }
+        }
+    }
+
+    //---------------------------------------------------------
+    // Quick recalibration (Trim stage):
+    //
+    // 1. Starting from the current electrical angle, step through one electrical period
+    //    in CAL_POINTS_PER_360EL steps using the regular sampling cycle.
+    // 2. At each point compare the electrical angle reported by the stored table with the commanded one.
+    // 3. Shift the table offset by the average error, the table itself is kept.
+    // 4. If errors differ too much between points or the accumulated trim is too large,
+    //    the table is considered stale and a full recalibration is requested.
+    // 5. The unit trim of a transferred table samples a single point at the applied angle and
+    //    restarts the accumulated trim instead of checking it.
+    //---------------------------------------------------------
+
+    /// Starts quick recalibration refining only the zero electrical angle against the stored table.
+    ///
+    /// # Arguments
+    /// * `angle_el` - Electrical angle currently applied to the motor
+    ///
+    /// Returns `false` if there is no valid table to refine.
+    pub fn start_trim(&mut self, angle_el: u16) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+        self.begin_trim(angle_el, Self::CAL_POINTS_PER_360EL);
+        self.unit_trim = false;
+        defmt::info!("CALIBRATION: Quick offset recalibration");
+        true
+    }
+
+    /// Starts the per-unit offset trim of a table transferred from another (golden) unit.
+    ///
+    /// A single point is measured at `angle_el`: the rotor settles at the applied current vector
+    /// and the table offset is shifted by the error. The trim becomes the reference of the drift
+    /// monitor, later quick recalibrations are compared against it.
+    ///
+    /// Returns `false` if there is no valid table to trim.
+    pub fn start_unit_trim(&mut self, angle_el: u16) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+        self.begin_trim(angle_el, 1);
+        self.ang_el_step = 0; // Sample at the applied vector, no step before the measurement
+        self.unit_trim = true;
+        defmt::info!("CALIBRATION: Unit offset trim of transferred table");
+        true
+    }
+
+    /// Prepares the Trim stage sampling `points` points one step apart.
+    fn begin_trim(&mut self, angle_el: u16, points: u16) {
+        self.angle_el = angle_el;
+        self.ang_el_step = u16::MAX / Self::CAL_POINTS_PER_360EL;
+        self.speed = Self::calculate_speed(self.frequency(), Self::CAL_SPEED_US);
+        self.cal_cycle_stage = CalSamplingState::Setup;
+        self.cal_idx = points as usize - 1;
+        self.trim_points = points;
+        self.trim_sum = 0;
+        self.dif_max = i32::MIN;
+        self.dif_min = i32::MAX;
+        self.enter_stage(CalStage::Trim, points as usize);
+    }
+
+    /// Applies the averaged offset error collected in the Trim stage and checks for drift.
+    fn finish_trim(&mut self) {
+        let avg_err = self.trim_sum / self.trim_points as i32;
+        let spread = self.dif_max - self.dif_min;
+        self.trim_spread = Some(spread);
+
+        self.cal_table.trim_offset(avg_err as i16);
+        if self.unit_trim {
+            // The transferred offset belonged to the source unit, drift is counted from here
+            defmt::info!(
+                "CALIBRATION: Transferred table aligned, offset trimmed by {}",
+                avg_err
+            );
+            self.trim_total = 0;
+            self.calibration_stage = CalStage::Ready;
+            return;
+        }
+        self.trim_total += avg_err;
+
+        if spread > Self::TRIM_MAX_SPREAD || self.trim_total.abs() > Self::TRIM_MAX_DRIFT {
+            defmt::warn!(
+                "CALIBRATION: Table drift detected [Spread: {}; Total trim: {}], full recalibration needed",
+                spread,
+                self.trim_total
+            );
+            self.needs_recal = true;
+        }
+        defmt::info!("CALIBRATION: Offset trimmed by {}", avg_err);
+        self.calibration_stage = CalStage::Ready;
+    }
+
+    /// Write the calibration table as image into `buf` (at least `cal_image_size(N)` bytes).
+    ///
+    /// Returns the image size, `None` without a valid table or if `buf` is too small.
+    pub fn export_table(&self, buf: &mut [u8]) -> Option<usize> {
+        if !self.has_table() || buf.len() < cal_image_size(N) {
+            return None;
+        }
+        Some(self.cal_table.write_image(buf))
+    }
+
+    /// Replace the calibration table by an image from `export_table()`, the calibration is
+    /// finished afterwards. Returns false and leaves the calibrator unchanged if the image is
+    /// invalid.
+    pub fn import_table(&mut self, image: &[u8]) -> bool {
+        if !self.cal_table.read_image(image) {
+            return false;
+        }
+        self.trim_total = 0;
+        self.drift_misses = 0;
+        self.needs_recal = false;
+        self.calibration_stage = CalStage::Ready;
+        true
+    }
+
+    /// Largest deviation of the calibration table from an ideal encoder, `None` without table.
+    pub fn table_deviation(&self) -> Option<u16> {
+        self.has_table().then(|| self.cal_table.max_deviation())
+    }
+
+    /// Deviation between the points of the last finished trim (65536 per 360° el).
+    #[inline(always)]
+    pub fn trim_spread(&self) -> Option<i32> {
+        self.trim_spread
+    }
+
+    /// Returns true when the drift monitor decided that only a full calibration can restore accuracy.
+    #[inline(always)]
+    pub fn needs_full_recal(&self) -> bool {
+        self.needs_recal
+    }
+
+    /// Update frequency (ticks per second), a constant with a fixed `FREQ`
     #[inline(always)]
-    pub fn get_correction(&self, pos: u16) -> (u16, u16) {
-        self.cal_table.correct_pos(pos)
+    fn frequency(&self) -> u16 {
+        tick_rate::resolve(FREQ, self.frequency)
     }
 
     /// Calculate speed in ticks per millisecond.
@@ -20,6 +20,8 @@
 // Licensed under the Apache License, Version 2.0
 // Copyright 2024 Anton Khrustalev, creapunk.com
 
+use super::cal_image_size;
+use crate::diagnostics::overflow::{self, OverflowSite};
 use defmt_rtt as _; // Use the defmt_rtt crate for logging via RTT (Real-Time Transfer)
 
 /// The main driver struct for the motor, holding all the state required for operation and calibration.
@@ -127,6 +129,18 @@ impl<const N: usize> CalibrationTable<N> {
         return false; // Indicate failure
     }
 
+    /// Maximum absolute deviation between ideal and actual calibration values.
+    #[inline(always)]
+    pub fn max_deviation(&self) -> u16 {
+        self.max_deviation
+    }
+
+    /// Raw sample stored at `idx` by the passes, valid until `check()` normalizes the table.
+    #[inline(always)]
+    pub fn sample(&self, idx: usize) -> u16 {
+        self.cal_table[idx]
+    }
+
     /// Retrieves a calibration value by an index relative to the `start_idx`.
     /// The resulting index is wrapped around `cal_size` to handle modulo arithmetic over a circular table.
     #[inline(always)]
@@ -175,18 +189,25 @@ impl<const N: usize> CalibrationTable<N> {
     }
 
     /// Corrects a given position using the calibration table.
+    /// Returns `(corrected_angle, el_angle)`, see `lookup()` and `angles()` for details.
+    pub fn correct_pos(&self, position: u16) -> (u16, u16) {
+        let result = self.lookup(position).unwrap_or(u16::MAX); // Fall back to max if no segment matched
+        self.angles(result)
+    }
+
+    /// Searches the table for the ideal (offset-aligned) position of a given encoder `position`.
     /// Given an actual encoder `position`, it accounts for the offset and searches near the expected index.
     /// Uses a small loop to find the segment where real_pos transitions from positive to negative difference,
     /// then interpolates the ideal position to achieve a corrected angle.
-    pub fn correct_pos(&self, position: u16) -> (u16, u16) {
+    ///
+    /// Returns `None` if no matching segment was found near the expected index.
+    pub fn lookup(&self, position: u16) -> Option<u16> {
         // Align the position so that zero aligns with the table's zero-offset point.
         let real_pos = position.wrapping_sub(self.offst_val);
 
         // Estimate a starting index by scaling `real_pos` down.
         let mut idx = (real_pos.wrapping_sub(self.max_deviation) as usize * self.cal_size) >> 16;
 
-        let mut result: u16 = u16::MAX; // Initialize result to max as a fallback
-
         // Starting comparison points
         let mut cal_pos1 = self.get_val_by_idx(idx); // Retrieve calibration value at current index
         let mut idl_pos1 = get_ideal(idx, self.cal_size); // Retrieve ideal value at current index
@@ -203,23 +224,105 @@ impl<const N: usize> CalibrationTable<N> {
             // Once we find a boundary where diff changes sign (diff1 >= 0, diff2 < 0),
             // we interpolate the exact ideal position within that segment.
             if (diff1 >= 0) && (diff2 < 0) {
-                result = interpolate(cal_pos1, idl_pos1, cal_pos2, idl_pos2, real_pos); // Perform interpolation
-                break; // Exit the loop after interpolation
+                return Some(interpolate(cal_pos1, idl_pos1, cal_pos2, idl_pos2, real_pos));
             }
 
             // Move to the next segment
             cal_pos1 = cal_pos2; // Update previous calibration position
             idl_pos1 = idl_pos2; // Update previous ideal position
         }
+        None
+    }
 
+    /// Converts an ideal (offset-aligned) position into `(corrected_angle, el_angle)`.
+    #[inline(always)]
+    pub fn angles(&self, ideal: u16) -> (u16, u16) {
         // Re-apply offset to return the corrected angle to the global coordinate system.
-        let corrected_angle = result.wrapping_add(self.offst_val); // Adjust corrected angle with offset
+        let corrected_angle = ideal.wrapping_add(self.offst_val); // Adjust corrected angle with offset
 
         // Compute the mechanical angle mapped into one electrical period.
-        let mech_el_angle = ((result as usize * self.cal_size) / self.el_angle_div) as u16; // Calculate mechanical to electrical angle
+        let mech_el_angle = ((ideal as usize * self.cal_size) / self.el_angle_div) as u16; // Calculate mechanical to electrical angle
 
         (corrected_angle, mech_el_angle) // Return the corrected angles
     }
+
+    /// Shifts the zero offset of the table by an electrical angle error without touching the table itself.
+    /// Positive `el_error` means the table reports an electrical angle ahead of the real one.
+    pub fn trim_offset(&mut self, el_error: i16) {
+        // Convert electrical angle error into encoder counts (one electrical period spans 65536 / pole pairs)
+        let delta = (el_error as i32 * self.el_angle_div as i32) / self.cal_size as i32;
+        self.offst_val = self.offst_val.wrapping_add(delta as u16);
+        defmt::debug!("CAL TABLE: Offset trimmed by {} to {}", delta, self.offst_val);
+    }
+}
+
+// Image of the validated table, used to back it up or clone it to identical units
+impl<const N: usize> CalibrationTable<N> {
+    /// Writes the table into `buf`, returns the image size (`cal_image_size(cal_size)`).
+    /// Layout (u16 LE): [cal_size, el_angle_div, offst_idx, offst_val, normalized points...]
+    pub fn write_image(&self, buf: &mut [u8]) -> usize {
+        let header = [
+            self.cal_size as u16,
+            self.el_angle_div as u16,
+            self.offst_idx as u16,
+            self.offst_val,
+        ];
+        let values = header.iter().chain(&self.cal_table[..self.cal_size]);
+        for (word, value) in buf.chunks_exact_mut(2).zip(values) {
+            word.copy_from_slice(&value.to_le_bytes());
+        }
+        cal_image_size(self.cal_size)
+    }
+
+    /// Replaces the table by an image written by `write_image()`.
+    /// The points pass the same deviation check as after a calibration, the maximum deviation
+    /// used by `lookup()` is derived from them. Returns false and keeps the table otherwise.
+    pub fn read_image(&mut self, image: &[u8]) -> bool {
+        let word = |i: usize| u16::from_le_bytes([image[2 * i], image[2 * i + 1]]);
+        if image.len() < cal_image_size(0) {
+            return false;
+        }
+        let (size, div, idx) = (word(0) as usize, word(1) as usize, word(2) as usize);
+        if size == 0 || size > N || div == 0 || div > size || idx >= size {
+            return false;
+        }
+        if image.len() != cal_image_size(size) {
+            return false;
+        }
+
+        // Points are stored normalized, check them against the ideal progression unchanged
+        let avg_step = u16::MAX / size as u16;
+        let mut max_deviation = 0;
+        for i in 0..size {
+            let corrected_idx = (size + i - idx) % size;
+            max_deviation = max_deviation.max(abs_deviation(word(4 + i), corrected_idx, size));
+        }
+        if max_deviation >= avg_step {
+            defmt::warn!(
+                "CAL TABLE: Image rejected [Avg step: {}; Max deviation: {}]",
+                avg_step,
+                max_deviation
+            );
+            return false;
+        }
+
+        for i in 0..size {
+            self.cal_table[i] = word(4 + i);
+        }
+        self.cal_size = size;
+        self.el_angle_div = div;
+        self.offst_idx = idx;
+        self.offst_val = word(3);
+        self.max_deviation = max_deviation;
+        self.temp_idx = 0;
+        defmt::info!(
+            "CAL TABLE: Image loaded! Offset val: {}; Offset idx: {}, Max deviation: {};",
+            self.offst_val,
+            self.offst_idx,
+            self.max_deviation
+        );
+        true
+    }
 }
 
 /// Computes an ideal value for the given index `i` within a range.
@@ -266,7 +369,9 @@ fn interpolate(
 
     // Interpolate this fraction into the ideal domain
     let c2_ofst = (c1_ofst as u32 * trgt_range as u32) / ref_range as u32;
+    // Beyond the segment when C lies outside of A-B
+    let c2_ofst = overflow::to_u16(c2_ofst, OverflowSite::CalibrationInterpolation);
 
     // Return the interpolated ideal value
-    (a2 as u32 + c2_ofst) as u16
+    a2.wrapping_add(c2_ofst)
 }
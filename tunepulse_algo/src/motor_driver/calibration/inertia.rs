@@ -0,0 +1,139 @@
+// Implements a test move identifying the inertia of the motor and attached load.
+
+// Key Features:
+// - Applies a known symmetric torque profile (accelerate, then decelerate).
+// - Estimates inertia from measured acceleration with friction and gravity cancelled out.
+// - Reports the result through the calibration log.
+
+// Detailed Operation:
+// The test applies a constant current +I for a fixed number of ticks and then -I for the same
+// number of ticks, so the axis speeds up and returns close to standstill. The speed is sampled
+// at the start, at the torque reversal and at the end. As the axis keeps moving in one direction
+// during both phases, constant friction and gravity loads are equal in both and cancel out:
+//   I - F = J * a_up,  -I - F = J * a_down  =>  J = 2I / (a_up - a_down)
+// The inertia is expressed in mA per rev/s^2 as i24.8 fixed point, which directly converts
+// a required acceleration into a feedforward current.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Encoder counts per mechanical revolution
+const COUNTS_PER_REV: i64 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InertiaStage {
+    Idle,
+    Accelerate,
+    Decelerate,
+}
+
+/// Inertia identification routine driving a bang-bang torque profile.
+pub struct InertiaIdentifier {
+    frequency: u16,       // Update frequency (ticks per second)
+    stage: InertiaStage,  // Current stage of the test move
+    current: i16,         // Test current (mA)
+    phase_ticks: u16,     // Duration of each torque phase (ticks)
+    ticks: u16,           // Ticks elapsed in current stage
+    speed_start: i32,     // Speed at the start of acceleration
+    speed_peak: i32,      // Speed at torque reversal
+    inertia: Option<i32>, // Identified inertia (mA per rev/s^2 as i24.8)
+}
+
+impl InertiaIdentifier {
+    /// Creates a new idle identifier.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            stage: InertiaStage::Idle,
+            current: 0,
+            phase_ticks: 0,
+            ticks: 0,
+            speed_start: 0,
+            speed_peak: 0,
+            inertia: None,
+        }
+    }
+
+    /// Starts the test move.
+    ///
+    /// # Arguments
+    /// * `current` - Test current (mA), sign selects the direction of motion
+    /// * `phase_ms` - Duration of each torque phase in milliseconds
+    pub fn start(&mut self, current: i16, phase_ms: u16) {
+        self.current = current;
+        self.phase_ticks = ((phase_ms as u32 * self.frequency as u32) / 1000).max(1) as u16;
+        self.ticks = 0;
+        self.stage = InertiaStage::Accelerate;
+        defmt::info!("CALIBRATION: Inertia test move");
+    }
+
+    /// Math call, returns the test current (mA) while the test is running.
+    ///
+    /// # Arguments
+    /// * `speed` - Measured speed (counts/s)
+    pub fn tick(&mut self, speed: i32) -> Option<i16> {
+        match self.stage {
+            InertiaStage::Idle => return None,
+            InertiaStage::Accelerate => {
+                if self.ticks == 0 {
+                    self.speed_start = speed;
+                }
+                self.ticks += 1;
+                if self.ticks >= self.phase_ticks {
+                    self.speed_peak = speed;
+                    self.ticks = 0;
+                    self.stage = InertiaStage::Decelerate;
+                }
+                return Some(self.current);
+            }
+            InertiaStage::Decelerate => {
+                self.ticks += 1;
+                if self.ticks < self.phase_ticks {
+                    return Some(-self.current);
+                }
+                self.stage = InertiaStage::Idle;
+                self.inertia = self.estimate(speed);
+            }
+        }
+        None
+    }
+
+    /// Calculates inertia from sampled speeds, `None` if the axis didn't accelerate.
+    fn estimate(&self, speed_end: i32) -> Option<i32> {
+        // (a_up - a_down) * phase_ticks / frequency
+        let dv = 2 * self.speed_peak as i64 - self.speed_start as i64 - speed_end as i64;
+        let dv = dv * (self.current as i64).signum();
+        if dv <= 0 {
+            defmt::error!("CALIBRATION: Inertia test produced no acceleration");
+            return None;
+        }
+        let num = 2 * (self.current as i64).abs() * COUNTS_PER_REV * self.phase_ticks as i64;
+        let inertia = ((num << 8) / (dv * self.frequency as i64)).min(i32::MAX as i64) as i32;
+        defmt::info!("CALIBRATION: Inertia: {} mA/(rev/s^2) (i24.8)", inertia);
+        Some(inertia)
+    }
+
+    /// Returns true while the test move is running
+    pub fn is_running(&self) -> bool {
+        self.stage != InertiaStage::Idle
+    }
+
+    /// Getter for identified inertia (mA per rev/s^2 as i24.8)
+    pub fn inertia(&self) -> Option<i32> {
+        self.inertia
+    }
+
+    /// Stores inertia obtained elsewhere (e.g. restored from configuration)
+    pub fn set_inertia(&mut self, inertia: i32) {
+        self.inertia = Some(inertia);
+    }
+
+    /// Current (mA) needed to reach the given acceleration (counts/s^2)
+    pub fn torque_for(&self, accel: i32) -> i32 {
+        let inertia = self.inertia.unwrap_or(0) as i64;
+        ((accel as i64 * inertia / COUNTS_PER_REV) >> 8) as i32
+    }
+}
@@ -0,0 +1,139 @@
+// Implements the detection of a loaded axis during the angle calibration passes.
+
+// Key Features:
+// - Hysteresis between the forward and backward pass compared point by point.
+// - Spread of the hysteresis along the turn showing a load depending on the position.
+// - Rotor creep while sampling a point showing a load the rotor keeps yielding to.
+// - Warning level keeping the table, abort level rejecting it.
+
+// Detailed Operation:
+// The calibration table assumes the rotor settles where the current vector points, offset
+// only by the encoder nonlinearity. An external load (friction, gravity, a spring) adds a load
+// angle that the table would learn as encoder error. The passes reveal it:
+// - Friction lags the rotor behind the vector in the direction of travel, the backward pass
+//   samples every point displaced against the forward pass. The mean of this hysteresis is
+//   compared against the point spacing. The detent torque of the motor causes some hysteresis
+//   of its own, the limits leave room for it.
+// - Gravity and other position dependent loads pull the rotor by a different amount along the
+//   turn, the hysteresis varies between the points: its spread (max - min) is checked.
+// - A rotor still moving while a point is sampled hasn't found its equilibrium, the averages
+//   of both halves of the sampling window are compared.
+// The table is averaged from both passes, a light load cancels out and only raises a warning.
+// Beyond the abort limits the averaged table is skewed too, the calibration is aborted.
+// All values are in encoder counts, limits are fractions of the point spacing (90° el).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Mean hysteresis between the passes exceeded the limit (friction)
+pub const LOAD_HYSTERESIS: u8 = 1 << 0;
+
+/// Hysteresis varied too much along the turn (position dependent load)
+pub const LOAD_ASYMMETRY: u8 = 1 << 1;
+
+/// Rotor kept moving while a point was sampled (yielding load)
+pub const LOAD_SETTLING: u8 = 1 << 2;
+
+/// Outcome of the load check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadVerdict {
+    /// No sign of a load
+    Unloaded,
+    /// Light load, the table is usable, arg: flags of the exceeded warning limits
+    Warning(u8),
+    /// The table is skewed by the load, arg: flags of the exceeded abort limits
+    Loaded(u8),
+}
+
+/// Warning limits as divisors of the point spacing: hysteresis, spread, creep
+const WARN_DIVISORS: [i32; 3] = [4, 2, 16];
+
+/// Abort limits as divisors of the point spacing: hysteresis, spread, creep
+const ABORT_DIVISORS: [i32; 3] = [2, 1, 8];
+
+/// Accumulates the pass differences of one calibration.
+pub struct LoadCheck {
+    points: i32,    // Points compared between the passes
+    lag_sum: i32,   // Sum of the hysteresis of all points
+    lag_min: i32,   // Smallest hysteresis of a point
+    lag_max: i32,   // Largest hysteresis of a point
+    creep_max: i32, // Largest rotor movement within a sampling window
+}
+
+impl LoadCheck {
+    /// Creates an empty check
+    pub const fn new() -> Self {
+        Self {
+            points: 0,
+            lag_sum: 0,
+            lag_min: i32::MAX,
+            lag_max: i32::MIN,
+            creep_max: 0,
+        }
+    }
+
+    /// Adds the hysteresis of one point
+    ///
+    /// # Arguments
+    /// * `forward` - Position sampled by the forward pass (rising encoder counts)
+    /// * `backward` - Position sampled by the backward pass at the same vector angle
+    pub fn add_point(&mut self, forward: u16, backward: u16) {
+        let lag = backward.wrapping_sub(forward) as i16 as i32; // Positive if lagging behind
+        self.points += 1;
+        self.lag_sum += lag;
+        self.lag_min = self.lag_min.min(lag);
+        self.lag_max = self.lag_max.max(lag);
+    }
+
+    /// Adds the rotor movement within the sampling window of one point (counts)
+    pub fn add_creep(&mut self, creep: i32) {
+        self.creep_max = self.creep_max.max(creep.abs());
+    }
+
+    /// Mean hysteresis between the passes, `None` before the backward pass
+    pub fn hysteresis(&self) -> Option<i32> {
+        (self.points > 0).then(|| self.lag_sum / self.points)
+    }
+
+    /// Evaluates the collected passes against the limits
+    pub fn verdict(&self) -> LoadVerdict {
+        let Some(hysteresis) = self.hysteresis() else {
+            return LoadVerdict::Unloaded;
+        };
+        let spacing = u16::MAX as i32 / self.points;
+        let values = [hysteresis, self.lag_max - self.lag_min, self.creep_max];
+        let exceeded = |divisors: [i32; 3]| {
+            let mut flags = 0;
+            for (i, flag) in [LOAD_HYSTERESIS, LOAD_ASYMMETRY, LOAD_SETTLING]
+                .into_iter()
+                .enumerate()
+            {
+                if values[i] > spacing / divisors[i] {
+                    flags |= flag;
+                }
+            }
+            flags
+        };
+        match (exceeded(ABORT_DIVISORS), exceeded(WARN_DIVISORS)) {
+            (0, 0) => LoadVerdict::Unloaded,
+            (0, flags) => LoadVerdict::Warning(flags),
+            (flags, _) => LoadVerdict::Loaded(flags),
+        }
+    }
+
+    /// Prints the collected values to the log
+    pub fn log(&self) {
+        defmt::info!(
+            "CALIBRATION: Pass hysteresis {} (spread {}), settling creep {}",
+            self.hysteresis().unwrap_or(0),
+            self.lag_max.saturating_sub(self.lag_min).max(0),
+            self.creep_max
+        );
+    }
+}
+
+impl Default for LoadCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
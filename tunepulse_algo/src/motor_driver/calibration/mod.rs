@@ -1,4 +1,108 @@
 pub mod angle_calibrator;
+pub mod inertia;
+pub mod load_check;
+#[cfg(feature = "ripple-test")]
+pub mod torque_ripple;
 mod calibration_table;
 
-use calibration_table::CalibrationTable;
\ No newline at end of file
+use calibration_table::CalibrationTable;
+
+/// Size of the table image header: point count, divider, offset index and offset value.
+const CAL_IMAGE_HEADER_SIZE: usize = 8;
+
+/// Number of calibration points sampled per electrical period (360° el).
+pub const CAL_POINTS_PER_POLE: usize = 4;
+
+/// Largest pole pair count covered by the default table (0.9° steppers have 100 pole pairs).
+pub const CAL_MAX_POLE_PAIRS: usize = 100;
+
+/// Upper bound of RAM (in bytes) a single calibration table may occupy.
+pub const CAL_TABLE_RAM_BUDGET: usize = 1024;
+
+/// Default calibration table size used by `AngleCalibrator`.
+pub const CAL_TABLE_SIZE: usize = cal_table_size(CAL_MAX_POLE_PAIRS);
+
+/// Calculates the number of table entries needed for a motor with `pole_pairs` pole pairs.
+pub const fn cal_table_size(pole_pairs: usize) -> usize {
+    pole_pairs * CAL_POINTS_PER_POLE
+}
+
+/// Calculates the size of the image of a table with `points` entries (see `export_table()`).
+pub const fn cal_image_size(points: usize) -> usize {
+    CAL_IMAGE_HEADER_SIZE + points * 2
+}
+
+/// Size of the image of a full default calibration table.
+pub const CAL_IMAGE_SIZE: usize = cal_image_size(CAL_TABLE_SIZE);
+
+/// Metrics of the calibration report, the value is the index read by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CalibrationMetric {
+    /// Largest deviation of the calibration table from an ideal encoder (counts)
+    TableDeviation = 0,
+    /// Spread of the electrical offset between the points of the last trim (65536 per 360° el)
+    TrimSpread = 1,
+    /// Torque ripple on the table corrected angle (per mille of the speed)
+    TorqueRipple = 2,
+    /// Torque ripple on the raw encoder angle (per mille of the speed)
+    TorqueRippleRaw = 3,
+    /// Mean hysteresis between the calibration passes (counts)
+    PassHysteresis = 4,
+    /// Load check limits exceeded by the last calibration (`load_check::LOAD_*` flags)
+    LoadFlags = 5,
+    /// Peak-to-peak filtered load angle in operation since the calibration, reflects the table
+    /// error under constant load (65536 per 360° el)
+    LoadAngleRipple = 6,
+}
+
+impl CalibrationMetric {
+    /// Converts raw identifier received over protocol
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        Some(match raw {
+            0 => CalibrationMetric::TableDeviation,
+            1 => CalibrationMetric::TrimSpread,
+            2 => CalibrationMetric::TorqueRipple,
+            3 => CalibrationMetric::TorqueRippleRaw,
+            4 => CalibrationMetric::PassHysteresis,
+            5 => CalibrationMetric::LoadFlags,
+            6 => CalibrationMetric::LoadAngleRipple,
+            _ => return None,
+        })
+    }
+}
+
+/// Sub-stage of the running calibration, the value is reported to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CalibrationStage {
+    /// Waiting for the supply to stabilize or the winding beeps to finish
+    Startup = 0,
+    /// Rotor settling at the first current vector
+    Settling = 1,
+    /// Test steps checking the motion and detecting the direction
+    MotionTest = 2,
+    /// Table points sampled in positive direction
+    ForwardPass = 3,
+    /// Table points sampled in negative direction
+    BackwardPass = 4,
+    /// Table normalized and checked
+    Check = 5,
+    /// Torque ripple measurement after the table is ready
+    TorqueRipple = 6,
+    /// Quick offset recalibration against the stored table
+    Trim = 7,
+}
+
+/// Reason of a failed calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationError {
+    /// Rotor didn't follow the test steps consistently
+    Motion,
+    /// Motor has more poles than the table can hold
+    TableCapacity,
+    /// Axis load skewed the passes beyond the abort limits
+    Loaded,
+    /// A stage took far longer than planned, the motor doesn't follow the current vector
+    Timeout,
+}
@@ -0,0 +1,184 @@
+// Implements the torque ripple measurement run after the angle calibration.
+
+// Key Features:
+// - Slow open-loop rotation of the current vector at constant amplitude.
+// - Speed ripple of the rotor as torque ripple metric (RMS, per mille of the mean speed).
+// - Measured on the raw and on the table corrected encoder angle, showing the compensation.
+// - Compiled out without the `ripple-test` feature, the calibration then skips it.
+
+// Detailed Operation:
+// The current vector turns at `RIPPLE_SPEED` electrical revolutions per second with the
+// calibration current. A motor without torque ripple follows with constant load angle and so
+// at constant speed; cogging and detent torque make the load angle, and with it the speed,
+// vary along the electrical period. The rotor position is sampled every time the vector passes
+// one of `RIPPLE_POINTS` points per electrical period, the vector moves at constant speed, so
+// the travel between two samples is proportional to the mean rotor speed over the segment.
+// The first `SETTLE_PERIODS` let the rotor catch up with the vector, the following
+// `MEASURE_PERIODS` are evaluated:
+//   ripple = sqrt(n * sum(d^2) - sum(d)^2) / |sum(d)| * 1000
+// the standard deviation of the segment travel `d` relative to its mean. The raw encoder adds
+// its own nonlinearity to the travel, the table corrected angle removes it, the difference of
+// both figures is the improvement the calibration brings. Travels are taken modulo one turn,
+// the direction of the encoder doesn't matter.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Rotation speed of the current vector (electrical revolutions per second)
+const RIPPLE_SPEED: u64 = 4;
+
+/// Sampling points per electrical period
+const RIPPLE_POINTS: u32 = 32;
+
+/// Electrical periods letting the rotor catch up before the measurement
+const SETTLE_PERIODS: u32 = 1;
+
+/// Electrical periods measured
+const MEASURE_PERIODS: u32 = 4;
+
+/// Sums of the segment travels of one angle source
+#[derive(Debug, Clone, Copy, Default)]
+struct TravelSums {
+    last: u16,   // Angle at the last sampling point
+    sum: i64,    // Sum of the travels
+    sum_sq: i64, // Sum of the squared travels
+}
+
+impl TravelSums {
+    fn add(&mut self, angle: u16, measure: bool) {
+        let travel = angle.wrapping_sub(self.last) as i16 as i64;
+        self.last = angle;
+        if measure {
+            self.sum += travel;
+            self.sum_sq += travel * travel;
+        }
+    }
+
+    /// Standard deviation of the travel relative to its mean (per mille), `None` at standstill
+    fn ripple(&self, count: u32) -> Option<i32> {
+        let count = count as i64;
+        let variance = (count * self.sum_sq - self.sum * self.sum).max(0) as u64;
+        (self.sum != 0).then(|| (variance.isqrt() * 1000 / self.sum.unsigned_abs()) as i32)
+    }
+}
+
+/// Result of the torque ripple measurement (per mille of the mean speed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RippleResult {
+    pub corrected: i32, // Ripple on the table corrected angle
+    pub raw: i32,       // Ripple on the raw encoder angle
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RippleStage {
+    Idle,
+    Running,
+    Done,
+}
+
+/// Constant current rotation measuring the speed ripple.
+pub struct TorqueRipple {
+    step: u32,                    // Vector phase increment per tick (2^32 per el revolution)
+    phase: u32,                   // Vector phase, the upper 16 bits are the electrical angle
+    stage: RippleStage,           // Current stage of the test
+    points: u32,                  // Sampling points passed since the start
+    raw: TravelSums,              // Travels of the raw encoder angle
+    corrected: TravelSums,        // Travels of the corrected angle
+    result: Option<RippleResult>, // Result of the last measurement
+}
+
+impl TorqueRipple {
+    /// Creates an idle test without result.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            step: ((RIPPLE_SPEED << 32) / frequency as u64) as u32,
+            phase: 0,
+            stage: RippleStage::Idle,
+            points: 0,
+            raw: TravelSums::default(),
+            corrected: TravelSums::default(),
+            result: None,
+        }
+    }
+
+    /// Starts the rotation from the applied electrical angle, the last result is cleared.
+    pub fn start(&mut self, angle_el: u16) {
+        self.phase = (angle_el as u32) << 16;
+        self.stage = RippleStage::Running;
+        self.points = 0;
+        self.raw = TravelSums::default();
+        self.corrected = TravelSums::default();
+        self.result = None;
+        defmt::info!("CALIBRATION: Torque ripple measurement");
+    }
+
+    /// Math call, returns the electrical angle to apply, `None` once the measurement finished.
+    ///
+    /// # Arguments
+    /// * `raw` - Raw encoder angle
+    /// * `corrected` - Encoder angle corrected by the calibration table
+    pub fn tick(&mut self, raw: u16, corrected: u16) -> Option<u16> {
+        if !self.is_running() {
+            return None;
+        }
+        let segment = u32::MAX / RIPPLE_POINTS + 1;
+        let (phase, _) = self.phase.overflowing_add(self.step);
+        if phase / segment != self.phase / segment {
+            self.points += 1;
+            let measure = self.points > SETTLE_PERIODS * RIPPLE_POINTS;
+            self.raw.add(raw, measure);
+            self.corrected.add(corrected, measure);
+            if self.points == (SETTLE_PERIODS + MEASURE_PERIODS) * RIPPLE_POINTS {
+                self.finish();
+                return None;
+            }
+        }
+        self.phase = phase;
+        Some((self.phase >> 16) as u16)
+    }
+
+    fn finish(&mut self) {
+        self.stage = RippleStage::Done;
+        let count = MEASURE_PERIODS * RIPPLE_POINTS;
+        match (self.corrected.ripple(count), self.raw.ripple(count)) {
+            (Some(corrected), Some(raw)) => {
+                defmt::info!(
+                    "CALIBRATION: Torque ripple {}/1000 of the speed ({}/1000 uncorrected)",
+                    corrected,
+                    raw
+                );
+                self.result = Some(RippleResult { corrected, raw });
+            }
+            _ => defmt::warn!("CALIBRATION: Rotor didn't follow the ripple measurement"),
+        }
+    }
+
+    /// Returns true while the rotation is running
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.stage == RippleStage::Running
+    }
+
+    /// Progress of the measurement (percent), 100 once finished
+    pub fn progress(&self) -> u8 {
+        (self.points * 100 / ((SETTLE_PERIODS + MEASURE_PERIODS) * RIPPLE_POINTS)) as u8
+    }
+
+    /// Returns true once after the measurement finished
+    pub fn take_done(&mut self) -> bool {
+        let done = self.stage == RippleStage::Done;
+        if done {
+            self.stage = RippleStage::Idle;
+        }
+        done
+    }
+
+    /// Result of the last measurement, `None` if it didn't run or the rotor stood still
+    #[inline(always)]
+    pub fn result(&self) -> Option<RippleResult> {
+        self.result
+    }
+}
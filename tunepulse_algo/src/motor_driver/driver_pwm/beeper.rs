@@ -0,0 +1,134 @@
+// Implements audible status beeps produced by the motor windings.
+
+// Key Features:
+// - Short melodies for startup, calibration complete and fault.
+// - Square wave voltage modulation added on top of the normal output.
+// - Strict amplitude limit independent of the requested current.
+
+// Detailed Operation:
+// A melody is a list of notes given by tone frequency and duration, a zero frequency is a pause.
+// While a melody plays the beeper toggles the sign of a small voltage on the alpha axis at twice
+// the tone frequency, which makes the windings vibrate audibly without producing a net torque.
+// The voltage is derived from the requested beep current through the winding resistance and is
+// always clamped to `MAX_SCALE` of the supply voltage, so a wrong configuration can't overheat
+// the motor.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Note of a melody: (tone frequency in Hz, duration in ms), zero frequency is a pause
+pub type Note = (u16, u16);
+
+/// Predefined status melodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Melody {
+    Startup,
+    CalibrationDone,
+    Fault,
+}
+
+impl Melody {
+    fn notes(self) -> &'static [Note] {
+        match self {
+            Melody::Startup => &[(1000, 80), (0, 40), (1500, 80), (0, 40), (2000, 120)],
+            Melody::CalibrationDone => &[(2000, 60), (0, 60), (2000, 60)],
+            Melody::Fault => &[(500, 400)],
+        }
+    }
+}
+
+/// Winding beep generator.
+pub struct Beeper {
+    notes: &'static [Note], // Melody being played
+    note: usize,            // Index of the note being played
+    note_ticks: u32,        // Remaining ticks of the note
+    half_period: u32,       // Ticks between sign toggles of the current note
+    phase_ticks: u32,       // Ticks since last sign toggle
+    positive: bool,         // Sign of the output
+    frequency: u16,         // Update frequency (ticks per second)
+}
+
+impl Beeper {
+    /// Highest beep voltage relative to supply (i1.15): ~5%
+    pub const MAX_SCALE: i16 = i16::MAX / 20;
+
+    pub const fn new() -> Self {
+        Self {
+            notes: &[],
+            note: 0,
+            note_ticks: 0,
+            half_period: 0,
+            phase_ticks: 0,
+            positive: true,
+            frequency: 1,
+        }
+    }
+
+    /// Starts a melody, replacing the one currently played.
+    ///
+    /// # Arguments
+    /// * `melody` - Melody to be played
+    /// * `frequency` - Number of ticks per second
+    pub fn play(&mut self, melody: Melody, frequency: u16) {
+        self.notes = melody.notes();
+        self.frequency = frequency.max(1);
+        self.start_note(0);
+    }
+
+    fn start_note(&mut self, note: usize) {
+        self.note = note;
+        if let Some(&(tone, duration)) = self.notes.get(note) {
+            let frequency = self.frequency as u32;
+            self.note_ticks = (duration as u32 * frequency / 1000).max(1);
+            self.half_period = if tone == 0 {
+                0
+            } else {
+                (frequency / (2 * tone as u32)).max(1)
+            };
+            self.phase_ticks = 0;
+        }
+    }
+
+    /// Math call, returns alpha axis voltage (i1.15) to be added to the output.
+    ///
+    /// # Arguments
+    /// * `scale` - Requested beep voltage (i1.15), clamped to `MAX_SCALE`
+    pub fn tick(&mut self, scale: i16) -> i16 {
+        if !self.is_playing() {
+            return 0;
+        }
+
+        let output = if self.half_period == 0 {
+            0 // Pause
+        } else {
+            self.phase_ticks += 1;
+            if self.phase_ticks >= self.half_period {
+                self.phase_ticks = 0;
+                self.positive = !self.positive;
+            }
+            let scale = scale.clamp(0, Self::MAX_SCALE);
+            if self.positive {
+                scale
+            } else {
+                -scale
+            }
+        };
+
+        self.note_ticks -= 1;
+        if self.note_ticks == 0 {
+            self.start_note(self.note + 1);
+        }
+        output
+    }
+
+    /// Returns true while a melody is played
+    pub fn is_playing(&self) -> bool {
+        self.note < self.notes.len()
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
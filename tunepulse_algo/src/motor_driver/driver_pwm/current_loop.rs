@@ -0,0 +1,107 @@
+// Implements the field-oriented current loop closing the commanded current vector on the
+// measured phase currents.
+
+// Key Features:
+// - Park transform of the measured alpha/beta currents into the frame of the commanded vector.
+// - PI regulators of the direct and quadrature current with integral anti-windup.
+// - Resistive model of the winding as feed-forward, the regulators only correct its error.
+// - Inverse Park transform back to alpha/beta, the result feeds the SVPWM path unchanged.
+
+// Detailed Operation:
+// The controller commands the current as vector: electrical angle and amplitude, plus the
+// quadrature current of the active damping. The loop works in the frame rotating with that
+// vector: its direct axis points along the commanded angle `(sin, cos)`, the quadrature axis
+// 90° ahead `(cos, -sin)`, the same axes `DriverPWM` applies the currents on. The measured
+// alpha/beta currents are projected on both axes (Park transform):
+//   d = a * sin + b * cos,  q = a * cos - b * sin
+// so in steady state both references and measurements are DC values regardless of the speed.
+// Each axis runs a PI regulator on the current error in mA. Its output is the current the
+// resistive model `V = I * R` is driven with; the reference itself enters as 100 % feed-forward,
+// so zero gains leave the open-loop behaviour of the driver, and the regulators only make up
+// for a wrong resistance, back-EMF and the winding inductance. Both outputs are limited to the
+// current the supply can drive through the resistance, the integrators stop at the same limit.
+// `DriverPWM` turns the outputs into voltages and applies them on the same axes, which is the
+// inverse Park transform. Without a fresh current sample the loop is reset and the driver falls
+// back to the model, so a missing current sense never leaves a wound-up integrator behind.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::controllers::pid::PID;
+use crate::math_integer::motor::bldc::park_transform;
+
+/// Field-oriented current regulator of the direct and quadrature axis.
+pub struct CurrentLoop {
+    kp: i32,             // Proportional gain (%)
+    ki: i32,             // Integral gain (% per tick)
+    pid_d: PID,          // Direct axis regulator
+    pid_q: PID,          // Quadrature axis regulator
+    current: (i16, i16), // Measured direct and quadrature current (mA)
+    active: bool,        // Regulators ran in the last tick
+}
+
+impl CurrentLoop {
+    /// Creates a reset loop.
+    ///
+    /// # Arguments
+    /// * `kp` - Proportional gain (%), 0 disables the loop together with `ki`
+    /// * `ki` - Integral gain (% per tick)
+    pub fn new(kp: i32, ki: i32) -> Self {
+        Self {
+            kp,
+            ki,
+            pid_d: PID::new(kp, ki, 0, 100),
+            pid_q: PID::new(kp, ki, 0, 100),
+            current: (0, 0),
+            active: false,
+        }
+    }
+
+    /// Changes the gains, the regulators restart from zero
+    pub fn set_gains(&mut self, kp: i32, ki: i32) {
+        *self = Self::new(kp, ki);
+    }
+
+    /// Returns true if the gains close the loop
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.kp != 0 || self.ki != 0
+    }
+
+    /// Clears the integrators, the next tick starts from the model
+    pub fn reset(&mut self) {
+        if self.active {
+            self.set_gains(self.kp, self.ki);
+        }
+    }
+
+    /// Runs the regulators, returns the direct and quadrature current for the resistive model.
+    ///
+    /// # Arguments
+    /// * `sincos` - Sine and cosine of the commanded vector angle (i1.15)
+    /// * `reference` - Commanded direct and quadrature current (mA)
+    /// * `measured` - Measured alpha/beta current (mA)
+    /// * `limit` - Largest current the supply drives through the winding resistance (mA)
+    pub fn tick(
+        &mut self,
+        sincos: (i16, i16),
+        reference: (i16, i16),
+        measured: (i16, i16),
+        limit: i16,
+    ) -> (i16, i16) {
+        self.current = park_transform(measured, sincos);
+        self.active = true;
+        let error_d = reference.0.saturating_sub(self.current.0);
+        let error_q = reference.1.saturating_sub(self.current.1);
+        self.pid_d.tick(error_d, reference.0, limit);
+        self.pid_q.tick(error_q, reference.1, limit);
+        (self.pid_d.output(), self.pid_q.output())
+    }
+
+    /// Measured direct and quadrature current of the last tick (mA)
+    #[inline(always)]
+    pub fn current(&self) -> (i16, i16) {
+        self.current
+    }
+}
+
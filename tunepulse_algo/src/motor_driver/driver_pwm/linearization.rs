@@ -0,0 +1,165 @@
+// Implements the duty linearization compensating the nonlinearity of real bridges at small duty.
+
+// Key Features:
+// - Odd symmetric table over the low duty region, linear interpolation between the points.
+// - Default model from the dead time lost per PWM period, or a table measured by a sweep.
+// - Constant offset above the table range, continuous with the last point.
+
+// Detailed Operation:
+// During the dead time both switches of a half bridge are off and the freewheeling current
+// decides the phase voltage, so the bridge loses part of the period against the current
+// direction. Together with the switch drops this adds a dead zone to the voltage the coil sees:
+// small duties produce less voltage than commanded and at low speed the current vector stalls
+// around the zero crossings of each coil. The linearizer maps the requested duty (coil voltage
+// as i1.15 of the supply) to the duty commanded to the bridge. The table holds the commanded
+// duty at `LINEARIZATION_POINTS` equally spaced requested duties from 0 to
+// `LINEARIZATION_RANGE`, negative duties are mirrored and duties above the range get the offset
+// of the last point, where the loss no longer changes.
+// The default model lets the loss grow linearly from zero (the current ripple crosses zero and
+// the dead time averages out) to the full dead time at the end of the range. A sweep measuring
+// the response (e.g. coil current) at a set of commanded duties gives a measured table: the
+// upper sweep points, where the bridge is linear, set the ideal slope through zero and every
+// table point takes the duty that reaches the ideal response of its requested duty.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of table points, including zero and the end of the range
+pub const LINEARIZATION_POINTS: usize = 17;
+
+/// Requested duty covered by the table (i1.15 of the supply, 1/8 of full scale)
+pub const LINEARIZATION_RANGE: i16 = 4096;
+
+/// Requested duty between two table points
+const POINT_SHIFT: u32 = 8;
+const _: () = assert!(
+    (LINEARIZATION_POINTS as i32 - 1) << POINT_SHIFT == LINEARIZATION_RANGE as i32,
+    "Table points must split the range in powers of two"
+);
+
+/// Duty linearization table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DutyLinearizer {
+    table: [i16; LINEARIZATION_POINTS], // Commanded duty at every requested table point
+}
+
+impl DutyLinearizer {
+    /// Creates a linearizer passing every duty unchanged
+    pub const fn identity() -> Self {
+        let mut table = [0; LINEARIZATION_POINTS];
+        let mut i = 0;
+        while i < LINEARIZATION_POINTS {
+            table[i] = (i << POINT_SHIFT) as i16;
+            i += 1;
+        }
+        Self { table }
+    }
+
+    /// Creates the default model of a bridge losing `dead_time` of the duty
+    ///
+    /// # Arguments
+    /// * `dead_time` - Duty lost per PWM period at full dead time effect (i1.15 of the period)
+    pub fn from_dead_time(dead_time: i16) -> Self {
+        let dead_time = dead_time.clamp(0, LINEARIZATION_RANGE) as i32;
+        let mut table = Self::identity().table;
+        for point in table.iter_mut() {
+            let requested = *point as i32;
+            *point = (requested + dead_time * requested / LINEARIZATION_RANGE as i32) as i16;
+        }
+        Self { table }
+    }
+
+    /// Creates a linearizer from a table, `None` if the duties decrease or are negative
+    ///
+    /// # Arguments
+    /// * `table` - Commanded duty at the requested duties `i * LINEARIZATION_RANGE / (n - 1)`
+    pub fn from_table(table: [i16; LINEARIZATION_POINTS]) -> Option<Self> {
+        let monotonic = table.windows(2).all(|pair| pair[0] <= pair[1]);
+        (table[0] >= 0 && monotonic).then_some(Self { table })
+    }
+
+    /// Creates a linearizer from a sweep of the bridge, `None` if the sweep is unusable
+    ///
+    /// # Arguments
+    /// * `duties` - Commanded duties of the sweep, rising and starting at zero
+    /// * `responses` - Measured response at each duty, rising with the duty
+    ///
+    /// The last two points have to lie in the linear region of the bridge.
+    pub fn from_sweep(duties: &[i16], responses: &[i32]) -> Option<Self> {
+        let n = duties.len();
+        if n < 3 || responses.len() != n || duties[0] != 0 {
+            return None;
+        }
+        let rising = duties.windows(2).all(|pair| pair[0] < pair[1])
+            && responses.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !rising {
+            return None;
+        }
+
+        // Ideal slope of the linear region as response per duty (i48.16)
+        let zero = responses[0] as i64;
+        let response_span = (responses[n - 1] - responses[n - 2]) as i64;
+        let duty_span = (duties[n - 1] - duties[n - 2]) as i64;
+        let slope = (response_span << 16) / duty_span;
+        if slope <= 0 {
+            return None;
+        }
+
+        let mut table = Self::identity().table;
+        for point in table.iter_mut() {
+            let target = zero + ((*point as i64 * slope) >> 16);
+            // Duty reaching the target on the measured curve, extrapolated above the sweep
+            let segment = (1..n).find(|&i| responses[i] as i64 >= target).unwrap_or(n - 1);
+            let (d0, d1) = (duties[segment - 1] as i64, duties[segment] as i64);
+            let (r0, r1) = (responses[segment - 1] as i64, responses[segment] as i64);
+            let duty = if r1 == r0 {
+                d1
+            } else {
+                d0 + (target - r0) * (d1 - d0) / (r1 - r0)
+            };
+            *point = duty.clamp(0, i16::MAX as i64) as i16;
+        }
+        Self::from_table(table)
+    }
+
+    /// Commanded duty at every requested table point
+    pub fn table(&self) -> &[i16; LINEARIZATION_POINTS] {
+        &self.table
+    }
+
+    /// Returns true if duties pass unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Maps a requested duty to the duty commanded to the bridge
+    ///
+    /// # Arguments
+    /// * `duty` - Requested coil voltage (i1.15 of the supply)
+    #[inline]
+    pub fn apply(&self, duty: i16) -> i16 {
+        let requested = (duty as i32).abs();
+        let commanded = if requested >= LINEARIZATION_RANGE as i32 {
+            // Constant loss above the range
+            let offset = self.table[LINEARIZATION_POINTS - 1] as i32 - LINEARIZATION_RANGE as i32;
+            requested + offset
+        } else {
+            let idx = (requested >> POINT_SHIFT) as usize;
+            let frac = requested & ((1 << POINT_SHIFT) - 1);
+            let (low, high) = (self.table[idx] as i32, self.table[idx + 1] as i32);
+            low + (((high - low) * frac) >> POINT_SHIFT)
+        };
+        let commanded = commanded.min(i16::MAX as i32) as i16;
+        if duty < 0 {
+            -commanded
+        } else {
+            commanded
+        }
+    }
+}
+
+impl Default for DutyLinearizer {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
@@ -6,6 +6,8 @@
 // - Defines MotorType enum for various motor types
 // - Implements MotorPWM struct to manage motor and phase selectors
 // - Provides methods to update motor control and change motor or phase modes
+// - Optionally linearizes the coil voltages against dead time and switch drops at small duty
+// - Closes the commanded current vector on the measured phase currents (field-oriented loop)
 
 // Detailed Operation:
 // The motor_pwm module manages PWM signals for different motor types using MotorSelector and PhaseSelector.
@@ -14,6 +16,10 @@
 // and provides methods to update PWM signals based on input voltages or angles.
 // It uses mathematical transformations for voltage calculations and allows dynamic changing
 // of motor and phase modes.
+// Current samples passed to `tick_current` are converted to alpha/beta by the current sense
+// selector and consumed by the next `tick_control`: with current loop gains the commanded
+// current is regulated on them (see `current_loop`), otherwise and on ticks without a fresh
+// sample the voltage follows the resistive model of the winding.
 
 // Licensed under the Apache License, Version 2.0
 // Copyright 2024 Anton Khrustalev, creapunk.com
@@ -21,17 +27,29 @@
 mod sel_motor; // Imports the motor_selector module
 mod sel_phase; // Imports the phase_selector module
 mod sel_current;
+pub mod beeper;
+pub mod current_loop;
+pub mod linearization;
 
 use sel_motor::MotorSelector; // Imports the MotorSelector struct from motor_selector module
 use sel_phase::PhaseSelector; // Imports the PhaseSelector struct from phase_selector module
+use sel_current::{CurrentSenseAB, Setup};
+use beeper::{Beeper, Melody};
+use current_loop::CurrentLoop;
+use linearization::DutyLinearizer;
 
+use crate::diagnostics::overflow::{self, OverflowSite};
 use crate::math_integer::motor;
 
-use crate::math_integer::{normalization::value_to_norm, trigonometry as math}; // Imports trigonometry module as math
+use crate::math_integer::normalization::{norm_to_value, value_to_norm};
+use crate::math_integer::trigonometry as math; // Imports trigonometry module as math
 
 
 use super::{ControlMode, DriverStatus, Motor, MotorDriver, MotorType, PhasePattern};
 
+/// Current probes of the board: one bipolar probe per coil (A and B)
+const SENSE_PROBES: u32 = Setup::BiAB as u32;
+
 pub struct DriverPWM {
     // COMMON
     /// Duty of brake mode
@@ -49,8 +67,29 @@ pub struct DriverPWM {
     // ####### Related to step-dir driver ########
     /// Motor resistance
     pub angle: i16,
-    /// Motor resistance
-    current: i16,
+    /// Last measured alpha/beta current (mA)
+    current: (i16, i16),
+    /// Current injected in quadrature to the commanded current vector (mA)
+    current_q: i16,
+    /// Alpha/beta voltage applied in the last control tick (i1.15 of supply voltage)
+    voltage: (i16, i16),
+
+    /// Conversion of the probe currents to alpha/beta
+    sense: CurrentSenseAB<SENSE_PROBES>,
+    /// Alpha/beta current sampled for the next control tick
+    sample: Option<(i16, i16)>,
+    /// Regulation of the commanded current on the measured one
+    current_loop: CurrentLoop,
+
+    /// Audible status beeps through the windings
+    beeper: Beeper,
+    /// Current used for beeps (mA)
+    beep_current: i16,
+
+    /// Compensation of the bridge nonlinearity at small duty
+    linearizer: DutyLinearizer,
+    /// Linearization is applied, false for the identity table
+    linearize: bool,
 
     /// Motor rotation direction
     pub direction: isize,
@@ -62,20 +101,154 @@ pub struct DriverPWM {
 
 impl DriverPWM {
     #[inline(always)]
-    fn normal_run(&mut self, ab: (i16, i16), supply: i16) -> (i16, i16) {
+    fn normal_run(
+        &mut self,
+        ab: (i16, i16),
+        supply: i16,
+        sample: Option<(i16, i16)>,
+    ) -> (i16, i16) {
         match self.control_mode {
             ControlMode::CurrentAB => {
                 let sincos_ab = math::angle2sincos(ab.0); // Converts angle to sine and cosine voltages
-                let targ_voltage = (ab.1 as i32 * self.motor.resistance) / 1000; // ma * mOhm -> mV
-                let norm_targ_voltage = value_to_norm(targ_voltage, 69000);
-                let mut scale = ((norm_targ_voltage as i32) << 15) / supply as i32;
-                if scale > i16::MAX as i32 { scale = i16::MAX as i32};
-                let scale = scale as i16;
-                math::scale_sincos(sincos_ab, scale) // Scales sine and cosine voltages based on input
+                let reference = (ab.1, self.current_q);
+                // Regulated on the measured current if sampled, the model alone otherwise
+                let (current_d, current_q) = match sample {
+                    Some(measured) if self.current_loop.is_enabled() => {
+                        let limit = self.saturation_current(supply);
+                        self.current_loop.tick(sincos_ab, reference, measured, limit)
+                    }
+                    _ => {
+                        self.current_loop.reset();
+                        reference
+                    }
+                };
+                let scale = self.current2scale(current_d, supply);
+                let voltage_ab = math::scale_sincos(sincos_ab, scale); // Scales sine and cosine voltages based on input
+                if current_q == 0 {
+                    return voltage_ab;
+                }
+                // Quadrature component is perpendicular to the main current vector: (cos, -sin)
+                let scale_q = self.current2scale(current_q, supply);
+                let voltage_q = math::scale_sincos((sincos_ab.1, -sincos_ab.0), scale_q);
+                (
+                    overflow::add_i16(voltage_ab.0, voltage_q.0, OverflowSite::DutyScale),
+                    overflow::add_i16(voltage_ab.1, voltage_q.1, OverflowSite::DutyScale),
+                )
+            }
+            ControlMode::VoltageAB => {
+                self.current_loop.reset();
+                ab
             }
-            ControlMode::VoltageAB => ab,
         }
     }
+
+    /// Largest current the supply drives through the winding resistance (mA)
+    #[inline(always)]
+    fn saturation_current(&self, supply: i16) -> i16 {
+        let supply_mv = norm_to_value(supply.max(0), 69000);
+        (supply_mv * 1000 / self.motor.resistance).min(i16::MAX as i32) as i16
+    }
+
+    /// Converts current in milliamps to the voltage scale (i1.15 of supply voltage)
+    #[inline(always)]
+    fn current2scale(&self, current: i16, supply: i16) -> i16 {
+        if supply <= 0 {
+            return 0; // Supply is not measured yet
+        }
+        let targ_voltage = (current as i32 * self.motor.resistance) / 1000; // ma * mOhm -> mV
+        let norm_targ_voltage = value_to_norm(targ_voltage, 69000);
+        let scale = ((norm_targ_voltage as i32) << 15) / supply as i32;
+        overflow::clamp_i16(scale, i16::MAX, OverflowSite::DutyScale)
+    }
+
+    /// Sets the current (mA) injected in quadrature to the commanded current vector.
+    /// Used for active damping, zero disables injection.
+    #[inline(always)]
+    pub fn set_current_q(&mut self, current: i16) {
+        self.current_q = current;
+    }
+
+    /// Sets the gains of the current loop, both 0 drive the voltage from the resistive model only.
+    ///
+    /// # Arguments
+    /// * `kp` - Proportional gain (%)
+    /// * `ki` - Integral gain (% per tick)
+    pub fn set_current_loop(&mut self, kp: i32, ki: i32) {
+        self.current_loop.set_gains(kp, ki);
+    }
+
+    /// Measured current along and in quadrature to the commanded vector (mA), zero while the
+    /// loop is open
+    #[inline(always)]
+    pub fn current_dq(&self) -> (i16, i16) {
+        self.current_loop.current()
+    }
+
+    /// Alpha/beta voltage applied in the last control tick (i1.15 of supply voltage), before the
+    /// duty linearization and beeps
+    #[inline(always)]
+    pub fn voltage_ab(&self) -> (i16, i16) {
+        self.voltage
+    }
+
+    /// Plays a status melody through the windings.
+    ///
+    /// # Arguments
+    /// * `melody` - Melody to be played
+    /// * `frequency` - Number of control ticks per second
+    #[inline(always)]
+    pub fn beep(&mut self, melody: Melody, frequency: u16) {
+        self.beeper.play(melody, frequency);
+    }
+
+    /// Returns true while a melody is played
+    #[inline(always)]
+    pub fn is_beeping(&self) -> bool {
+        self.beeper.is_playing()
+    }
+
+    /// Sets the current (mA) used for beeps, the voltage is limited by `Beeper::MAX_SCALE`.
+    #[inline(always)]
+    pub fn set_beep_current(&mut self, current: i16) {
+        self.beep_current = current;
+    }
+
+    /// Sets the duty linearization applied to the alpha/beta voltages.
+    pub fn set_linearization(&mut self, linearizer: DutyLinearizer) {
+        self.linearize = !linearizer.is_identity();
+        self.linearizer = linearizer;
+    }
+
+    /// Returns the duty linearization
+    pub fn linearization(&self) -> &DutyLinearizer {
+        &self.linearizer
+    }
+
+    /// Returns the driven motor
+    #[inline(always)]
+    pub fn motor(&self) -> &Motor {
+        &self.motor
+    }
+
+    /// Updates the electrical data of the driven motor.
+    ///
+    /// # Arguments
+    /// * `pole_pairs` - Nominal pole pairs
+    /// * `resistance` - Phase resistance (mOhm)
+    /// * `inductance` - Phase inductance (uH)
+    /// * `max_current` - Maximum current (mA)
+    pub fn set_motor_data(
+        &mut self,
+        pole_pairs: u16,
+        resistance: i32,
+        inductance: i32,
+        max_current: i32,
+    ) {
+        self.motor.pole_count = pole_pairs as usize * 2;
+        self.motor.resistance = resistance.max(1);
+        self.motor.inductance = inductance;
+        self.motor.max_current = max_current;
+    }
 }
 
 impl MotorDriver for DriverPWM {
@@ -84,7 +257,20 @@ impl MotorDriver for DriverPWM {
             
             brake: 0,
             angle: 0,
-            current: 0,
+            current: (0, 0),
+            current_q: 0,
+            voltage: (0, 0),
+            sense: {
+                let mut sense = CurrentSenseAB::new();
+                sense.change_mode(motor.pole_type);
+                sense
+            },
+            sample: None,
+            current_loop: CurrentLoop::new(0, 0),
+            beeper: Beeper::new(),
+            beep_current: 300,
+            linearizer: DutyLinearizer::identity(),
+            linearize: false,
             direction: motor.direction,
             control_mode,
             status: DriverStatus::Ready,
@@ -96,20 +282,44 @@ impl MotorDriver for DriverPWM {
     }
 
     fn tick_control(&mut self, ab_inpt: (i16, i16), supply: i16) -> [i16; 4] {
-        let voltage_ab = match self.status {
-            DriverStatus::Ready => ab_inpt,
-            DriverStatus::Error => (0, 0),
-            DriverStatus::Calibrating => (0, 0),
+        // A sample is only used once, the loop stays open if none arrives
+        let sample = self.sample.take();
+        let (voltage_ab, sample) = match self.status {
+            DriverStatus::Ready => (ab_inpt, sample),
+            DriverStatus::Error => ((0, 0), None),
+            DriverStatus::Calibrating => ((0, 0), None),
+        };
+        let voltage_ab = self.normal_run(voltage_ab, supply, sample);
+        self.voltage = voltage_ab;
+        // Each coil voltage is linearized, beeps only need to be audible
+        let voltage_ab = if self.linearize {
+            (
+                self.linearizer.apply(voltage_ab.0),
+                self.linearizer.apply(voltage_ab.1),
+            )
+        } else {
+            voltage_ab
+        };
+        let voltage_ab = if self.beeper.is_playing() {
+            let scale = self.current2scale(self.beep_current, supply);
+            let beep = self.beeper.tick(scale);
+            let voltage_a = overflow::add_i16(voltage_ab.0, beep, OverflowSite::DutyScale);
+            (voltage_a, voltage_ab.1)
+        } else {
+            voltage_ab
         };
-        let voltage_ab = self.normal_run(voltage_ab, supply);
         let motor_voltages = self.motor_type.tick(voltage_ab);
         self.ch_1234 = self.phase_sel.tick(motor_voltages);
         self.ch_1234
     }
 
     fn tick_current(&mut self, currents: [i16; 4]) -> (i16, i16) {
-        let i_abcd = self.phase_sel.tick(currents);
-        (0, 0)
+        self.sense.tick(currents);
+        self.sample = self.sense.output();
+        if let Some(current) = self.sample {
+            self.current = current;
+        }
+        self.current
     }
 
     fn calibrate(&mut self) -> bool {
@@ -130,12 +340,14 @@ impl MotorDriver for DriverPWM {
 
     fn get_current(&mut self) -> (i16, i16) {
         // Return AB current for PWM driver
-        (self.current, 0)
+        self.current
     }
 
     #[inline(always)]
     fn change_motor_mode(&mut self, motor_type: MotorType) -> bool {
         self.motor_type.change_mode(motor_type); // Updates motor selector with new motor type
+        self.sense.change_mode(motor_type);
+        self.motor.pole_type = motor_type;
         true
     }
 
@@ -143,6 +355,7 @@ impl MotorDriver for DriverPWM {
     #[inline(always)]
     fn change_phase_mode(&mut self, connection: PhasePattern) -> bool {
         self.phase_sel.change_mode(connection as u8); // Updates phase selector with new phase pattern
+        self.motor.connection = connection;
         true
     }
 
@@ -65,6 +65,21 @@ impl<const PROBES: u32> CurrentSenseAB<PROBES> {
         }
     }
 
+    /// Changes the motor type the probes are converted for
+    #[inline(always)]
+    pub fn change_mode(&mut self, motor_type: MotorType) {
+        self.motor_type = motor_type;
+    }
+
+    /// Alpha/beta current of the last tick, `None` if the probes can't measure the motor type
+    #[inline(always)]
+    pub fn output(&self) -> Option<(i16, i16)> {
+        if self.ab_output.0 == i16::MIN {
+            return None;
+        }
+        Some(self.ab_output)
+    }
+
     /// Обработка для биполярного режима
     fn tick_bipolar(&mut self) {
         match probe_amount(PROBES) {
@@ -166,5 +181,5 @@ const fn probe_amount(setup: u32) -> u32 {
 }
 
 const fn is_bipolar(setup: u32) -> bool {
-    return (setup & 0b0000) != 0;
+    return (setup & UNIPOLAR) == 0;
 }
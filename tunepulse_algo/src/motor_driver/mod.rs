@@ -2,7 +2,9 @@ pub mod driver_pulse; // Module handling pulse-related logic
 pub mod driver_pwm; // Module handling PWM-related logic
 
 pub mod calibration;
+pub mod presets;
 pub use calibration::angle_calibrator::AngleCalibrator;
+pub use calibration::CalibrationStage;
 pub use driver_pwm::DriverPWM;
 
 pub struct Motor {
@@ -47,6 +49,18 @@ pub enum MotorType {
     STEP = 4,
 }
 
+impl MotorType {
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            1 => Some(MotorType::UNDEFINED),
+            0xFFFF => Some(MotorType::DC),
+            3 => Some(MotorType::BLDC),
+            4 => Some(MotorType::STEP),
+            _ => None,
+        }
+    }
+}
+
 /// PhasePattern enumeration for PWM patterns
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PhasePattern {
@@ -57,6 +71,19 @@ pub enum PhasePattern {
     NONE = 0b00000000,
 }
 
+impl PhasePattern {
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0b11100100 => Some(PhasePattern::ABCD),
+            0b01111000 => Some(PhasePattern::ACDB),
+            0b10011100 => Some(PhasePattern::ADBC),
+            0b01001011 => Some(PhasePattern::DCAB),
+            0b00000000 => Some(PhasePattern::NONE),
+            _ => None,
+        }
+    }
+}
+
 /// PhasePattern enumeration for PWM patterns
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControlMode {
@@ -77,6 +104,28 @@ pub enum DriverStatus {
     Error,
 }
 
+/// Reason a motor reconfiguration was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconfigError {
+    /// Drive output is enabled, disable it first
+    Enabled,
+    /// Bridge still drives current through the windings (e.g. calibration or braking)
+    Energized,
+    /// A test or identification is running
+    Busy,
+}
+
+/// Detailed operating state for application indication and protocols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverState {
+    pub status: DriverStatus,            // Overall status
+    pub stage: Option<CalibrationStage>, // Calibration sub-stage, `None` unless calibrating
+    pub progress: u8,                    // Calibration progress (percent), 100 once ready
+    pub energized: bool,                 // Bridge drives current through the windings
+    pub at_velocity: bool,               // Speed settled within the velocity window
+    pub zero_speed: bool,                // Speed settled below the zero speed threshold
+}
+
 /// Common interface for motor drivers
 pub trait MotorDriver {
     /// Constructor for new driver
@@ -85,7 +134,7 @@ pub trait MotorDriver {
     /// Updates motor control based on the current mode and input voltages
     fn tick_control(&mut self, ab_inpt: (i16, i16), supply: i16) -> [i16; 4];
 
-    /// Updates motor control based on the current mode and input voltages
+    /// Passes the probe currents (mA) to the next control tick, returns the alpha/beta current
     fn tick_current(&mut self, current: [i16; 4]) -> (i16, i16);
 
     /// Run calibration cycle
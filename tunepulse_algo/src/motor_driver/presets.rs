@@ -0,0 +1,116 @@
+// Implements the motor presets bundling the data of common motors for first-spin setup.
+
+// Key Features:
+// - Common steppers and gimbal BLDC motors selectable by a single parameter.
+// - Motor type, pole pairs, phase resistance and inductance of every preset.
+// - Conservative current limit and suggested position loop gains.
+
+// Detailed Operation:
+// A new user mostly knows the motor by its frame size, the preset turns that into the data the
+// controller needs: the motor type (a different type swaps the motor as a reconfiguration
+// does), the phase resistance converting current commands into voltages, the torque limit of
+// the position loop and gains which spin the motor stable without load. Pole pairs and
+// inductance are nominal values for reference, the calibration measures its own pole count.
+// The values describe typical motors of the class (e.g. 17HS4401, 17HM19-2004S, 23HS22-2804S,
+// 2208 and 4108 gimbal motors), the current limits stay below their rating so an unknown motor
+// of the class doesn't overheat. `Custom` keeps the configuration of the board or the host.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::MotorType;
+
+/// Motor presets, the value is the `MotorPreset` parameter, names in
+/// `tunepulse_params::codes::MOTOR_PRESETS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MotorPreset {
+    /// Data configured by the board or the host
+    Custom = 0,
+    /// NEMA17 stepper, 1.8° (200 steps per turn), ~1.7 A
+    Nema17 = 1,
+    /// NEMA17 stepper, 0.9° (400 steps per turn), ~2 A
+    Nema17Fine = 2,
+    /// NEMA23 stepper, 1.8°, ~2.8 A
+    Nema23 = 3,
+    /// 2208 gimbal BLDC, 12N14P
+    Gimbal2208 = 4,
+    /// 4108 gimbal BLDC, 24N22P
+    Gimbal4108 = 5,
+}
+
+/// Number of presets including `Custom`
+pub const PRESET_COUNT: usize = 6;
+
+/// Data of a preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresetData {
+    pub motor_type: MotorType,  // Motor type
+    pub pole_pairs: u16,        // Nominal pole pairs
+    pub resistance: i32,        // Phase resistance (mOhm)
+    pub inductance: i32,        // Phase inductance (uH)
+    pub current_limit: i16,     // Maximum torque command (mA)
+    pub gains: (i32, i32, i32), // Position loop gains (percent): proportional, integral, derivative
+}
+
+impl MotorPreset {
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            0 => Some(MotorPreset::Custom),
+            1 => Some(MotorPreset::Nema17),
+            2 => Some(MotorPreset::Nema17Fine),
+            3 => Some(MotorPreset::Nema23),
+            4 => Some(MotorPreset::Gimbal2208),
+            5 => Some(MotorPreset::Gimbal4108),
+            _ => None,
+        }
+    }
+
+    /// Returns the data of the preset, `None` for `Custom`
+    pub const fn data(self) -> Option<PresetData> {
+        let data = match self {
+            MotorPreset::Custom => return None,
+            MotorPreset::Nema17 => PresetData {
+                motor_type: MotorType::STEP,
+                pole_pairs: 50,
+                resistance: 1500,
+                inductance: 2800,
+                current_limit: 1200,
+                gains: (50, 0, 0),
+            },
+            MotorPreset::Nema17Fine => PresetData {
+                motor_type: MotorType::STEP,
+                pole_pairs: 100,
+                resistance: 1450,
+                inductance: 4000,
+                current_limit: 1500,
+                gains: (60, 0, 0),
+            },
+            MotorPreset::Nema23 => PresetData {
+                motor_type: MotorType::STEP,
+                pole_pairs: 50,
+                resistance: 900,
+                inductance: 3600,
+                current_limit: 2000,
+                gains: (40, 0, 0),
+            },
+            MotorPreset::Gimbal2208 => PresetData {
+                motor_type: MotorType::BLDC,
+                pole_pairs: 7,
+                resistance: 10000,
+                inductance: 2000,
+                current_limit: 400,
+                gains: (20, 0, 0),
+            },
+            MotorPreset::Gimbal4108 => PresetData {
+                motor_type: MotorType::BLDC,
+                pole_pairs: 11,
+                resistance: 11000,
+                inductance: 3000,
+                current_limit: 600,
+                gains: (25, 0, 0),
+            },
+        };
+        Some(data)
+    }
+}
@@ -0,0 +1,118 @@
+// Implements the parameter registry holding the runtime configuration of the controller.
+
+// Key Features:
+// - Every parameter is addressed by a stable numeric identifier usable over any protocol.
+// - Parameter definitions carry default value and valid range.
+// - Writes are validated against the range before being stored.
+// - Host writes are limited to the parameters of the unlocked access level.
+// - CRC of the live values to detect changes which weren't saved.
+
+// Detailed Operation:
+// Parameters are described by the static table of `ParamDef` entries indexed by `ParamId`,
+// shared with host tools through the `tunepulse_params` crate.
+// The registry stores the current values as raw u32 words; signed parameters are stored in
+// two's complement and interpreted by their users. A write checks the identifier and range and
+// returns an error without modifying the registry if either is invalid. The owner of the
+// registry applies accepted values to the affected modules. The `storage` module serializes
+// the registry for non-volatile memory and migrates images written by older firmware. Hot-tunable
+// parameters can be staged by the `staging` module and applied together between control ticks.
+// Writes received over protocol are checked against the access level unlocked by the host with
+// `check_access()` first, values restored from flash are trusted and skip the check.
+// The owner keeps the `crc()` of the values as loaded or last saved, the live values differ
+// from the persisted set whenever the CRC doesn't match it.
+// The `writer` module puts images into flash from background work without delaying the control
+// loop.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+pub mod staging;
+pub mod storage;
+pub mod writer;
+
+pub use tunepulse_params::{
+    find_by_name, AccessLevel, ParamDef, ParamId, ParamType, PARAMS, PARAM_COUNT,
+    PARAM_LAYOUT_VERSION,
+};
+
+/// Errors reported on parameter access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamError {
+    /// No parameter with the given identifier
+    UnknownId,
+    /// Value outside of the parameter range
+    OutOfRange,
+    /// Parameter can't be changed while running
+    NotTunable,
+    /// No space left for staged values
+    StageFull,
+    /// Parameter needs a higher access level than unlocked
+    Locked,
+}
+
+/// Checks that `level` allows writing the parameter with raw identifier `raw`.
+///
+/// Unknown identifiers pass, the write itself reports them.
+pub fn check_access(raw: u16, level: AccessLevel) -> Result<(), ParamError> {
+    match ParamId::from_raw(raw) {
+        Some(id) if PARAMS[id as usize].access > level => Err(ParamError::Locked),
+        _ => Ok(()),
+    }
+}
+
+/// Registry holding current parameter values.
+pub struct ParamRegistry {
+    values: [u32; PARAM_COUNT],
+}
+
+impl ParamRegistry {
+    /// Creates a registry with all parameters set to their defaults.
+    pub const fn new() -> Self {
+        let mut values = [0; PARAM_COUNT];
+        let mut i = 0;
+        while i < PARAM_COUNT {
+            values[i] = PARAMS[i].default;
+            i += 1;
+        }
+        Self { values }
+    }
+
+    /// Returns current value of a parameter
+    pub fn get(&self, id: ParamId) -> u32 {
+        self.values[id as usize]
+    }
+
+    /// Validates and stores a new parameter value
+    pub fn set(&mut self, id: ParamId, value: u32) -> Result<(), ParamError> {
+        let def = &PARAMS[id as usize];
+        if value < def.min || value > def.max {
+            return Err(ParamError::OutOfRange);
+        }
+        self.values[id as usize] = value;
+        Ok(())
+    }
+
+    /// CRC-32 of all values, changes whenever a value differs from the set it was taken of
+    pub fn crc(&self) -> u32 {
+        writer::crc32_words(&self.values)
+    }
+
+    /// Stores a parameter addressed by raw identifier received over protocol
+    pub fn set_raw(&mut self, raw: u16, value: u32) -> Result<ParamId, ParamError> {
+        let id = ParamId::from_raw(raw).ok_or(ParamError::UnknownId)?;
+        self.set(id, value)?;
+        Ok(id)
+    }
+
+    /// Returns a parameter addressed by raw identifier received over protocol
+    pub fn get_raw(&self, raw: u16) -> Result<u32, ParamError> {
+        let id = ParamId::from_raw(raw).ok_or(ParamError::UnknownId)?;
+        Ok(self.get(id))
+    }
+}
+
+impl Default for ParamRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
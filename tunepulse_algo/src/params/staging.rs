@@ -0,0 +1,102 @@
+// Implements staging of hot-tunable parameter values for atomic application while running.
+
+// Key Features:
+// - Collects values of hot-tunable parameters (gains, filter alphas, limits) without applying them.
+// - Commit makes the whole set available at once, so related values never mix old and new.
+// - Validation of identifier, tunability and range at staging time.
+
+// Detailed Operation:
+// The host stages any number of values (up to the capacity), then commits them. Staging the
+// same parameter twice keeps the latest value. The control loop takes the committed set at the
+// start of its next tick and applies every value before running the controllers, so a tick
+// always runs with a consistent set of parameters. Staging is refused while a committed set is
+// still pending.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{ParamError, ParamId, PARAMS};
+
+/// Set of staged parameter values.
+#[derive(Clone, Copy)]
+pub struct ParamStage<const N: usize> {
+    entries: [(ParamId, u32); N], // Staged (parameter, value) pairs
+    len: usize,                   // Number of valid entries
+    committed: bool,              // Set is complete and waits for the next tick
+}
+
+impl<const N: usize> ParamStage<N> {
+    /// Creates an empty stage
+    pub const fn new() -> Self {
+        Self {
+            entries: [(ParamId::EventMask, 0); N],
+            len: 0,
+            committed: false,
+        }
+    }
+
+    /// Stages a value of a hot-tunable parameter
+    ///
+    /// # Arguments
+    /// * `raw` - Parameter identifier received over protocol
+    /// * `value` - New value
+    pub fn stage(&mut self, raw: u16, value: u32) -> Result<ParamId, ParamError> {
+        let id = ParamId::from_raw(raw).ok_or(ParamError::UnknownId)?;
+        let def = &PARAMS[id as usize];
+        if !def.hot {
+            return Err(ParamError::NotTunable);
+        }
+        if value < def.min || value > def.max {
+            return Err(ParamError::OutOfRange);
+        }
+        if self.committed {
+            // Previous set wasn't applied yet
+            return Err(ParamError::StageFull);
+        }
+        if let Some(entry) = self.entries[..self.len].iter_mut().find(|e| e.0 == id) {
+            entry.1 = value;
+            return Ok(id);
+        }
+        if self.len == N {
+            return Err(ParamError::StageFull);
+        }
+        self.entries[self.len] = (id, value);
+        self.len += 1;
+        Ok(id)
+    }
+
+    /// Marks the staged set as complete, returns the number of staged values
+    pub fn commit(&mut self) -> usize {
+        self.committed = self.len > 0;
+        self.len
+    }
+
+    /// Drops all staged values
+    pub fn discard(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Takes the committed set, leaving the stage empty
+    pub fn take_committed(&mut self) -> Option<Self> {
+        if !self.committed {
+            return None;
+        }
+        Some(core::mem::take(self))
+    }
+
+    /// Staged (parameter, value) pairs
+    pub fn entries(&self) -> &[(ParamId, u32)] {
+        &self.entries[..self.len]
+    }
+
+    /// True if a committed set waits to be applied
+    pub fn is_committed(&self) -> bool {
+        self.committed
+    }
+}
+
+impl<const N: usize> Default for ParamStage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,191 @@
+// Implements the non-volatile image of the parameter registry and its migration between
+// firmware versions.
+
+// Key Features:
+// - Self-describing image: every value is stored together with its parameter identifier.
+// - Header with magic, layout version and checksum to detect blank or corrupted storage.
+// - Versioned migration steps instead of wiping the configuration after an update.
+// - Migration report listing loaded, defaulted, discarded and rejected parameters.
+
+// Detailed Operation:
+// Image layout (little endian, 8 byte records matching the flash double word):
+// - Header: [magic (u16), layout version (u16), entry count (u16), checksum (u16)]
+// - Entry:  [id (u16), 0 (u16), value (u32)]
+// Loading checks the header and checksum first. Every entry is then passed through the
+// migration steps between the stored and the current `PARAM_LAYOUT_VERSION`, in order. A step
+// may renumber a parameter, convert its value or drop it. The result is applied through the
+// owner of the registry, so the usual identifier, range and consistency checks are performed:
+// - Accepted values are counted as loaded.
+// - Unknown identifiers (parameters removed in this firmware) are discarded.
+// - Out of range values are rejected and keep their default.
+// Parameters missing from the image (added in this firmware) keep their defaults. Images
+// written by newer firmware are loaded the same way, identifiers unknown to this firmware are
+// discarded, which keeps downgrades safe as long as identifiers are never reused.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{ParamError, ParamRegistry, PARAMS, PARAM_COUNT, PARAM_LAYOUT_VERSION};
+
+/// Marks a valid parameter image ("TP")
+pub const PARAM_IMAGE_MAGIC: u16 = 0x5054;
+
+/// Size of the image header in bytes
+pub const HEADER_SIZE: usize = 8;
+
+/// Size of a single entry in bytes
+pub const ENTRY_SIZE: usize = 8;
+
+/// Size of an image holding all parameters of this firmware
+pub const PARAM_IMAGE_SIZE: usize = HEADER_SIZE + PARAM_COUNT * ENTRY_SIZE;
+
+/// Errors reported by image access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum StorageError {
+    /// No image present (erased storage or foreign data)
+    Blank,
+    /// Image header or checksum invalid
+    Corrupted,
+    /// Buffer too small for the image
+    BufferTooSmall,
+}
+
+/// Step converting entries of one layout version into the next one.
+pub struct Migration {
+    /// Layout version the step converts from (to `from_version + 1`)
+    pub from_version: u16,
+    /// Converts an entry, `None` drops it
+    pub migrate: fn(id: u16, value: u32) -> Option<(u16, u32)>,
+}
+
+/// Migration steps ordered by version, one step per `PARAM_LAYOUT_VERSION` increment.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Summary of an image load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MigrationReport {
+    pub from_version: u16, // Layout version of the loaded image
+    pub loaded: u16,       // Parameters restored from the image
+    pub defaulted: u16,    // Parameters missing in the image, kept at default
+    pub discarded: u16,    // Entries of parameters unknown to this firmware
+    pub rejected: u16,     // Entries with invalid value, kept at default
+}
+
+impl MigrationReport {
+    /// True if the image was written by a different layout version
+    pub fn migrated(&self) -> bool {
+        self.from_version != PARAM_LAYOUT_VERSION
+    }
+
+    /// Prints the report to the log
+    pub fn log(&self) {
+        if self.migrated() {
+            defmt::warn!(
+                "PARAMS: Migrated layout v{} -> v{}",
+                self.from_version,
+                PARAM_LAYOUT_VERSION
+            );
+        }
+        defmt::info!(
+            "PARAMS: {} loaded, {} defaulted, {} discarded, {} rejected",
+            self.loaded,
+            self.defaulted,
+            self.discarded,
+            self.rejected
+        );
+    }
+}
+
+/// Writes the registry into `buf`, returns the image size
+///
+/// # Arguments
+/// * `registry` - Registry to store
+/// * `buf` - Destination, at least `PARAM_IMAGE_SIZE` bytes
+pub fn store(registry: &ParamRegistry, buf: &mut [u8]) -> Result<usize, StorageError> {
+    if buf.len() < PARAM_IMAGE_SIZE {
+        return Err(StorageError::BufferTooSmall);
+    }
+    for (def, entry) in PARAMS
+        .iter()
+        .zip(buf[HEADER_SIZE..].chunks_exact_mut(ENTRY_SIZE))
+    {
+        entry[0..2].copy_from_slice(&(def.id as u16).to_le_bytes());
+        entry[2..4].copy_from_slice(&[0, 0]);
+        entry[4..8].copy_from_slice(&registry.get(def.id).to_le_bytes());
+    }
+    let checksum = checksum(&buf[HEADER_SIZE..PARAM_IMAGE_SIZE]);
+    buf[0..2].copy_from_slice(&PARAM_IMAGE_MAGIC.to_le_bytes());
+    buf[2..4].copy_from_slice(&PARAM_LAYOUT_VERSION.to_le_bytes());
+    buf[4..6].copy_from_slice(&(PARAM_COUNT as u16).to_le_bytes());
+    buf[6..8].copy_from_slice(&checksum.to_le_bytes());
+    Ok(PARAM_IMAGE_SIZE)
+}
+
+/// Loads an image, migrating it to the current layout
+///
+/// # Arguments
+/// * `image` - Stored image
+/// * `apply` - Validates and applies a parameter (identifier, value)
+pub fn load<F>(image: &[u8], mut apply: F) -> Result<MigrationReport, StorageError>
+where
+    F: FnMut(u16, u32) -> Result<(), ParamError>,
+{
+    if image.len() < HEADER_SIZE || read_u16(image, 0) != PARAM_IMAGE_MAGIC {
+        return Err(StorageError::Blank);
+    }
+    let version = read_u16(image, 2);
+    let end = HEADER_SIZE + read_u16(image, 4) as usize * ENTRY_SIZE;
+    if end > image.len() || checksum(&image[HEADER_SIZE..end]) != read_u16(image, 6) {
+        return Err(StorageError::Corrupted);
+    }
+
+    let mut report = MigrationReport {
+        from_version: version,
+        ..MigrationReport::default()
+    };
+    let mut restored = [false; PARAM_COUNT];
+    for entry in image[HEADER_SIZE..end].chunks_exact(ENTRY_SIZE) {
+        let id = read_u16(entry, 0);
+        let value = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+        let (id, value) = match migrate(version, id, value) {
+            Some(entry) => entry,
+            None => {
+                report.discarded += 1;
+                continue;
+            }
+        };
+        match apply(id, value) {
+            Ok(()) => {
+                report.loaded += 1;
+                restored[id as usize] = true;
+            }
+            Err(ParamError::UnknownId) => report.discarded += 1,
+            Err(_) => report.rejected += 1,
+        }
+    }
+    report.defaulted = restored.iter().filter(|restored| !**restored).count() as u16;
+    Ok(report)
+}
+
+/// Passes an entry through all migration steps following the stored layout version
+fn migrate(version: u16, id: u16, value: u32) -> Option<(u16, u32)> {
+    MIGRATIONS
+        .iter()
+        .filter(|step| step.from_version >= version)
+        .try_fold((id, value), |(id, value), step| (step.migrate)(id, value))
+}
+
+/// Fletcher-16 checksum of the entries
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum1: u16 = 0;
+    let mut sum2: u16 = 0;
+    for byte in data {
+        sum1 = (sum1 + *byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
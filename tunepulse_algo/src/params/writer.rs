@@ -0,0 +1,428 @@
+// Implements writing of images (parameters, calibration) into flash as background work that
+// never delays a control tick, committed atomically so a power loss can't corrupt them.
+
+// Key Features:
+// - Hardware independent: the platform provides erase and program through `FlashOps`.
+// - Erase, program and verify split into single operations, one per admitted window.
+// - Every operation is admitted by the scheduler only if its bus stall is acceptable.
+// - Two-phase commit over two slots: the previous record stays valid until the new one is
+//   written, verified and committed.
+// - The image is copied on start, the caller may reuse its buffer immediately.
+
+// Detailed Operation:
+// Flash erase and program stall every flash read, the control interrupt included. On the G4
+// a double word program (~90 µs) already exceeds a PWM period, so a write can't be hidden
+// between two ticks while the motor is driven. `FlashWriter` is a `BackgroundTask` asking
+// `Budget::allow_stall()` before every operation with the worst case stall reported by the
+// platform. The platform clock decides what is acceptable: time until the next control
+// interrupt while the motor is driven, unlimited while the motor tolerates missed ticks
+// (output disabled or faulted). A write requested while driving therefore stays pending and
+// completes as soon as the drive allows it, without disturbing the motor.
+//
+// The flash area holds two slots of one page each. Slot layout (little endian double words):
+// - 0:  [magic (u32), sequence (u32)]
+// - 8:  [payload length (u32), CRC-32 of the payload (u32)]
+// - 16: commit marker, erased until the record is complete
+// - 24: invalidation marker, erased while the record is current
+// - 32: payload, padded with 0xFF to double words
+// A record is valid if magic and commit marker are set, the invalidation marker is erased and
+// the CRC matches. A write goes to the slot not holding the current record:
+// 1. Erase the slot and program header and payload, commit marker left erased.
+// 2. Read the record back and validate length and CRC.
+// 3. Program the commit marker, from now on the new record is valid.
+// 4. Program the invalidation marker of the previous record.
+// A power loss before step 3 leaves the previous record current, between steps 3 and 4 both
+// records are valid and `load()` picks the higher sequence number.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::scheduler::{BackgroundTask, Budget, Poll};
+
+/// Size of a flash programming unit in bytes
+pub const DOUBLE_WORD: usize = 8;
+
+/// Marks a slot holding a record ("TPCF")
+const RECORD_MAGIC: u32 = 0x4643_5054;
+/// Value of the commit marker of a complete record
+const COMMITTED: [u8; DOUBLE_WORD] = [0x43, 0x4F, 0x4D, 0x4D, 0x49, 0x54, 0x00, 0x00];
+/// Value of the invalidation marker of a superseded record
+const INVALIDATED: [u8; DOUBLE_WORD] = [0; DOUBLE_WORD];
+/// Content of an erased double word
+const ERASED: [u8; DOUBLE_WORD] = [0xFF; DOUBLE_WORD];
+
+const COMMIT_OFFSET: usize = 16;
+const INVALIDATE_OFFSET: usize = 24;
+/// Size of the record header in bytes
+pub const RECORD_HEADER_SIZE: usize = 32;
+
+/// Number of record slots in the flash area
+const SLOTS: usize = 2;
+
+const CRC_INIT: u32 = 0xFFFF_FFFF;
+
+/// Errors reported by flash writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum FlashError {
+    /// Area outside of the writable flash or misaligned
+    OutOfRange,
+    /// Flash controller can't be unlocked
+    Locked,
+    /// Area is write protected
+    WriteProtected,
+    /// Erase or program failed
+    Program,
+    /// Read back differs from the written record
+    Verify,
+}
+
+impl FlashError {
+    /// Error code reported to the host, 0 is left for success
+    pub const fn code(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// Converts an error code from `code()`, `None` for unknown codes
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Self::OutOfRange),
+            2 => Some(Self::Locked),
+            3 => Some(Self::WriteProtected),
+            4 => Some(Self::Program),
+            5 => Some(Self::Verify),
+            _ => None,
+        }
+    }
+}
+
+/// Flash area access implemented by the platform, offsets are relative to the area start.
+pub trait FlashOps {
+    /// Size of an erase page in bytes
+    const PAGE_SIZE: usize;
+    /// Worst case stall of a page erase (scheduler clock cycles)
+    const ERASE_CYCLES: u32;
+    /// Worst case stall of a double word program (scheduler clock cycles)
+    const PROGRAM_CYCLES: u32;
+
+    /// Erases the page starting at `offset`
+    fn erase_page(&mut self, offset: usize) -> Result<(), FlashError>;
+    /// Programs the erased double word at `offset`, 8 byte aligned
+    fn program(&mut self, offset: usize, word: [u8; DOUBLE_WORD]) -> Result<(), FlashError>;
+    /// Reads `buf.len()` bytes starting at `offset`
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), FlashError>;
+}
+
+/// Valid record found in a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Record {
+    slot: usize,   // Slot index
+    sequence: u32, // Incremented with every write
+    len: usize,    // Payload length
+}
+
+/// Reads the payload of the current record into `buf`, returns its length or `None` if no
+/// valid record is stored (blank area, interrupted first write) or `buf` is too small
+pub fn load<F: FlashOps>(flash: &F, buf: &mut [u8]) -> Option<usize> {
+    let record = current(flash)?;
+    let payload = buf.get_mut(..record.len)?;
+    flash
+        .read(record.slot * F::PAGE_SIZE + RECORD_HEADER_SIZE, payload)
+        .ok()?;
+    Some(record.len)
+}
+
+/// Returns the newest valid record
+fn current<F: FlashOps>(flash: &F) -> Option<Record> {
+    (0..SLOTS)
+        .filter_map(|slot| validate(flash, slot))
+        .reduce(|a, b| {
+            if (b.sequence.wrapping_sub(a.sequence) as i32) > 0 {
+                b
+            } else {
+                a
+            }
+        })
+}
+
+/// Checks markers and CRC of the record in `slot`
+fn validate<F: FlashOps>(flash: &F, slot: usize) -> Option<Record> {
+    let base = slot * F::PAGE_SIZE;
+    let mut header = [0; RECORD_HEADER_SIZE];
+    flash.read(base, &mut header).ok()?;
+    let word = |offset: usize| {
+        u32::from_le_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+    let len = word(8) as usize;
+    if word(0) != RECORD_MAGIC
+        || header[COMMIT_OFFSET..INVALIDATE_OFFSET] != COMMITTED
+        || header[INVALIDATE_OFFSET..RECORD_HEADER_SIZE] != ERASED
+        || len > F::PAGE_SIZE - RECORD_HEADER_SIZE
+    {
+        return None;
+    }
+    if flash_crc(flash, base + RECORD_HEADER_SIZE, len)? != word(12) {
+        return None;
+    }
+    Some(Record {
+        slot,
+        sequence: word(4),
+        len,
+    })
+}
+
+/// CRC-32 of `len` bytes of flash starting at `offset`
+fn flash_crc<F: FlashOps>(flash: &F, offset: usize, len: usize) -> Option<u32> {
+    let mut crc = CRC_INIT;
+    let mut chunk = [0; DOUBLE_WORD];
+    let mut done = 0;
+    while done < len {
+        let n = (len - done).min(DOUBLE_WORD);
+        flash.read(offset + done, &mut chunk[..n]).ok()?;
+        crc = crc32_update(crc, &chunk[..n]);
+        done += n;
+    }
+    Some(!crc)
+}
+
+/// CRC-32 of `data`, shared with other images exchanged with the host
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(CRC_INIT, data)
+}
+
+/// CRC-32 of `words` in little endian byte order
+pub(crate) fn crc32_words(words: &[u32]) -> u32 {
+    let crc = words
+        .iter()
+        .fold(CRC_INIT, |crc, word| crc32_update(crc, &word.to_le_bytes()));
+    !crc
+}
+
+/// Feeds `data` into a CRC-32 (IEEE 802.3, reflected), bitwise to keep the table out of flash
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    crc
+}
+
+/// Progress of a write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteState {
+    Idle,
+    Erase,
+    Program { offset: usize },
+    Verify,
+    Commit,
+    Invalidate,
+}
+
+/// Background task committing records with a payload of up to `N` bytes
+pub struct FlashWriter<F: FlashOps, const N: usize> {
+    flash: F,
+    image: [u8; N],                         // Copy of the payload being written
+    len: usize,                             // Payload size
+    slot: usize,                            // Slot receiving the new record
+    previous: Option<usize>,                // Slot of the record being replaced
+    sequence: u32,                          // Sequence number of the new record
+    crc: u32,                               // CRC-32 of the payload
+    state: WriteState,                      // Current step
+    result: Option<Result<(), FlashError>>, // Outcome of the last finished write
+}
+
+impl<F: FlashOps, const N: usize> FlashWriter<F, N> {
+    pub const fn new(flash: F) -> Self {
+        Self {
+            flash,
+            image: [0xFF; N],
+            len: 0,
+            slot: 0,
+            previous: None,
+            sequence: 0,
+            crc: 0,
+            state: WriteState::Idle,
+            result: None,
+        }
+    }
+
+    /// Starts committing `image`, returns false if a write is running or the image doesn't fit
+    pub fn start(&mut self, image: &[u8]) -> bool {
+        if self.is_busy() || image.len() > N || image.len() > F::PAGE_SIZE - RECORD_HEADER_SIZE {
+            return false;
+        }
+        self.len = image.len();
+        self.image[..image.len()].copy_from_slice(image);
+        self.image[image.len()..].fill(0xFF);
+        self.crc = crc32(image);
+
+        // Write into the other slot, the current record stays untouched until committed
+        let current = current(&self.flash);
+        self.previous = current.map(|record| record.slot);
+        self.slot = current.map_or(0, |record| (record.slot + 1) % SLOTS);
+        self.sequence = current.map_or(1, |record| record.sequence.wrapping_add(1));
+
+        self.result = None;
+        self.state = WriteState::Erase;
+        true
+    }
+
+    /// Returns true while a write is in progress
+    pub fn is_busy(&self) -> bool {
+        self.state != WriteState::Idle
+    }
+
+    /// Returns the outcome of the last finished write, once
+    pub fn take_result(&mut self) -> Option<Result<(), FlashError>> {
+        self.result.take()
+    }
+
+    /// Flash area of the writer, `None` while a write is running. Lets the owner access other
+    /// areas of the same flash controller between writes.
+    pub fn flash_mut(&mut self) -> Option<&mut F> {
+        (!self.is_busy()).then_some(&mut self.flash)
+    }
+
+    /// Reads the payload of the current record, see `load()`
+    pub fn load(&self, buf: &mut [u8]) -> Option<usize> {
+        load(&self.flash, buf)
+    }
+
+    fn finish(&mut self, result: Result<(), FlashError>) {
+        if let Err(error) = result {
+            defmt::warn!("FLASH: Write failed ({})", error);
+        }
+        self.result = Some(result);
+        self.state = WriteState::Idle;
+    }
+
+    /// Returns the double word of the new record at `offset` within the slot
+    fn record_word(&self, offset: usize) -> [u8; DOUBLE_WORD] {
+        let mut word = [0; DOUBLE_WORD];
+        match offset {
+            0 => {
+                word[..4].copy_from_slice(&RECORD_MAGIC.to_le_bytes());
+                word[4..].copy_from_slice(&self.sequence.to_le_bytes());
+            }
+            8 => {
+                word[..4].copy_from_slice(&(self.len as u32).to_le_bytes());
+                word[4..].copy_from_slice(&self.crc.to_le_bytes());
+            }
+            _ => {
+                let start = offset - RECORD_HEADER_SIZE;
+                word.copy_from_slice(&self.image[start..start + DOUBLE_WORD]);
+            }
+        }
+        word
+    }
+
+    /// Validates the written record before it is committed
+    fn verify(&self) -> Result<(), FlashError> {
+        let base = self.slot * F::PAGE_SIZE;
+        let crc = flash_crc(&self.flash, base + RECORD_HEADER_SIZE, self.len);
+        let mut header = [0; COMMIT_OFFSET];
+        self.flash.read(base, &mut header)?;
+        if crc != Some(self.crc) || header[..DOUBLE_WORD] != self.record_word(0) {
+            return Err(FlashError::Verify);
+        }
+        if header[DOUBLE_WORD..] != self.record_word(DOUBLE_WORD) {
+            return Err(FlashError::Verify);
+        }
+        Ok(())
+    }
+
+    /// Runs a program operation if admitted, returns false to yield
+    fn program(
+        &mut self,
+        budget: &Budget,
+        offset: usize,
+        word: [u8; DOUBLE_WORD],
+        next: WriteState,
+    ) -> bool {
+        if !budget.allow_stall(F::PROGRAM_CYCLES) {
+            return false;
+        }
+        match self.flash.program(offset, word) {
+            Ok(()) => self.state = next,
+            Err(error) => self.finish(Err(error)),
+        }
+        true
+    }
+}
+
+impl<F: FlashOps, const N: usize> BackgroundTask for FlashWriter<F, N> {
+    fn poll(&mut self, budget: &Budget) -> Poll {
+        let base = self.slot * F::PAGE_SIZE;
+        let end = RECORD_HEADER_SIZE + self.len.div_ceil(DOUBLE_WORD) * DOUBLE_WORD;
+        while !budget.expired() {
+            let admitted = match self.state {
+                WriteState::Idle => return Poll::Idle,
+                WriteState::Erase => {
+                    if !budget.allow_stall(F::ERASE_CYCLES) {
+                        return Poll::Pending;
+                    }
+                    match self.flash.erase_page(base) {
+                        Ok(()) => self.state = WriteState::Program { offset: 0 },
+                        Err(error) => self.finish(Err(error)),
+                    }
+                    true
+                }
+                // Commit and invalidation markers stay erased for now
+                WriteState::Program {
+                    offset: COMMIT_OFFSET,
+                } => {
+                    self.state = WriteState::Program {
+                        offset: RECORD_HEADER_SIZE,
+                    };
+                    true
+                }
+                WriteState::Program { offset } if offset >= end => {
+                    self.state = WriteState::Verify;
+                    true
+                }
+                WriteState::Program { offset } => {
+                    let next = WriteState::Program {
+                        offset: offset + DOUBLE_WORD,
+                    };
+                    self.program(budget, base + offset, self.record_word(offset), next)
+                }
+                WriteState::Verify => {
+                    match self.verify() {
+                        Ok(()) => self.state = WriteState::Commit,
+                        Err(error) => self.finish(Err(error)),
+                    }
+                    true
+                }
+                WriteState::Commit => {
+                    let next = WriteState::Invalidate;
+                    self.program(budget, base + COMMIT_OFFSET, COMMITTED, next)
+                }
+                WriteState::Invalidate => {
+                    let Some(previous) = self.previous else {
+                        self.finish(Ok(()));
+                        continue;
+                    };
+                    if !budget.allow_stall(F::PROGRAM_CYCLES) {
+                        return Poll::Pending;
+                    }
+                    // The new record is committed, a failure here leaves an older valid record
+                    // behind which loses on the sequence number
+                    let offset = previous * F::PAGE_SIZE + INVALIDATE_OFFSET;
+                    let result = self.flash.program(offset, INVALIDATED);
+                    self.finish(result);
+                    true
+                }
+            };
+            if !admitted {
+                return Poll::Pending;
+            }
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,107 @@
+// Implements the mapping of the protocol frames onto classic CAN frames, so several drives and
+// a host share one bus.
+
+// Key Features:
+// - One protocol frame per CAN frame: the 8 data bytes are the frame, nothing is segmented.
+// - 11 bit identifier of a function and the node number, no extended identifiers.
+// - Bus priority by function: events before requests, replies and periodic telemetry.
+// - Acceptance filter of the requests addressed to a node, for the hardware filter.
+
+// Detailed Operation:
+// The identifier is `function << 7 | node`, the node number (1..127) is the `can_node`
+// parameter. The host addresses a drive by sending its requests (setpoint, status read,
+// parameter access, commands, ...) with the `Request` function and the node number, every
+// request is answered by one frame with the `Reply` function in the order received.
+// Frames the drive sends on its own use the function of their frame type: motion events, the
+// odometry and electrical telemetry and the pages of the status reports, so a host or a bus
+// logger can pick streams by identifier alone. The arbitration lets the lowest identifier win,
+// events come first, followed by the host requests, so a setpoint stream isn't delayed by the
+// periodic telemetry of other drives.
+// The bit rate (`can_bitrate`, kbit/s) is applied by the platform at startup, a new node number
+// once the host has written it. With the `can_protocol` parameter set to CANopen the link runs
+// the CiA 402 slave of `canopen` instead, its identifiers overlap the ones of this module.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{Frame, FrameType};
+
+/// Protocol carried by the CAN link (`can_protocol` parameter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CanProtocol {
+    /// TunePulse frames with the identifiers of this module
+    TunePulse = 0,
+    /// CANopen CiA 402 drive profile (see `canopen`)
+    CanOpen = 1,
+}
+
+impl CanProtocol {
+    /// Converts raw value of the CAN protocol parameter
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => CanProtocol::CanOpen,
+            _ => CanProtocol::TunePulse,
+        }
+    }
+}
+
+/// Identifier bits of the node number
+pub const NODE_MASK: u16 = 0x7F;
+
+/// Identifier bits compared by the request filter (all of the 11 bit identifier)
+pub const FILTER_MASK: u16 = 0x7FF;
+
+/// Function code in the upper 4 bits of the identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CanFunction {
+    /// Asynchronous motion event
+    Event = 1,
+    /// Host request addressed to the node
+    Request = 2,
+    /// Reply to a host request
+    Reply = 3,
+    /// Periodic odometry and electrical sample
+    Telemetry = 4,
+    /// Page of the periodic status report
+    Report = 5,
+}
+
+impl CanFunction {
+    /// Function of a frame sent by the drive
+    pub fn of(frame: &Frame) -> Self {
+        match FrameType::from_raw(frame[0]) {
+            Some(FrameType::Event) => CanFunction::Event,
+            Some(FrameType::Odometry | FrameType::Electrical) => CanFunction::Telemetry,
+            Some(FrameType::StatusReport) => CanFunction::Report,
+            _ => CanFunction::Reply,
+        }
+    }
+}
+
+/// Identifier of a function of a node
+#[inline(always)]
+pub fn identifier(function: CanFunction, node: u8) -> u16 {
+    (function as u16) << 7 | node as u16 & NODE_MASK
+}
+
+/// Identifier a frame sent by the drive is carried with
+#[inline(always)]
+pub fn frame_identifier(frame: &Frame, node: u8) -> u16 {
+    identifier(CanFunction::of(frame), node)
+}
+
+/// Identifier and mask of the acceptance filter passing the requests to the node
+#[inline(always)]
+pub fn request_filter(node: u8) -> (u16, u16) {
+    (identifier(CanFunction::Request, node), FILTER_MASK)
+}
+
+/// Protocol frame carried by a received CAN frame, shorter frames are padded with zeros
+pub fn frame_from_data(data: &[u8]) -> Frame {
+    let mut frame = [0; super::FRAME_SIZE];
+    let len = data.len().min(super::FRAME_SIZE);
+    frame[..len].copy_from_slice(&data[..len]);
+    frame
+}
@@ -0,0 +1,274 @@
+// Implements the device control state machine of the CiA 402 drive profile: the controlword of
+// the master walks the drive from Switch On Disabled to Operation Enabled and back.
+
+// Key Features:
+// - States and transitions of CiA 402 (device control), the statusword reporting them.
+// - Drive faults enter the Fault state, a rising fault reset bit leaves it.
+// - Quick stop brakes the axis and holds it until the master disables or enables again.
+// - Cyclic synchronous position (8) and velocity (9) modes of operation.
+
+// Detailed Operation:
+// `update()` follows the drive: Not Ready To Switch On while it calibrates, Switch On Disabled
+// once it is ready, Fault as soon as it reports a fault. The controlword command moves the
+// state along the transitions of the profile: Shutdown to Ready To Switch On, Switch On to
+// Switched On, Enable Operation to Operation Enabled (also straight from Ready To Switch On),
+// Disable Voltage back to Switch On Disabled. Quick Stop from Operation Enabled stops the axis
+// and stays in Quick Stop Active (quick stop option 6), from the other states it disables the
+// voltage. A fault reset clears the fault of the drive without moving the motor and returns
+// to Switch On Disabled, a fault the drive still reports enters the Fault state again.
+// The drive output is enabled in Operation Enabled and Quick Stop Active. An output disabled by
+// another source (e.g. the UART host) drops the state to Switched On, so the master sees it.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::motor_driver::DriverStatus;
+
+/// Controlword bits
+const CW_SWITCH_ON: u16 = 1 << 0;
+const CW_ENABLE_VOLTAGE: u16 = 1 << 1;
+const CW_QUICK_STOP: u16 = 1 << 2;
+const CW_ENABLE_OPERATION: u16 = 1 << 3;
+const CW_FAULT_RESET: u16 = 1 << 7;
+
+/// Statusword bits
+const SW_READY_TO_SWITCH_ON: u16 = 1 << 0;
+const SW_SWITCHED_ON: u16 = 1 << 1;
+const SW_OPERATION_ENABLED: u16 = 1 << 2;
+const SW_FAULT: u16 = 1 << 3;
+const SW_VOLTAGE_ENABLED: u16 = 1 << 4;
+const SW_QUICK_STOP: u16 = 1 << 5;
+const SW_SWITCH_ON_DISABLED: u16 = 1 << 6;
+const SW_REMOTE: u16 = 1 << 9;
+const SW_TARGET_REACHED: u16 = 1 << 10;
+const SW_FOLLOWING: u16 = 1 << 12;
+
+/// Modes of operation supported (0x6060)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i8)]
+pub enum OperationMode {
+    /// Cyclic synchronous position
+    CyclicPosition = 8,
+    /// Cyclic synchronous velocity
+    CyclicVelocity = 9,
+}
+
+impl OperationMode {
+    /// Converts raw modes of operation value, `None` for a mode the drive doesn't support
+    pub fn from_raw(raw: i8) -> Option<Self> {
+        match raw {
+            8 => Some(OperationMode::CyclicPosition),
+            9 => Some(OperationMode::CyclicVelocity),
+            _ => None,
+        }
+    }
+}
+
+/// Supported drive modes (0x6502): bit per cyclic synchronous mode
+pub const SUPPORTED_MODES: u32 = 1 << 7 | 1 << 8;
+
+/// State of the device control state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveState {
+    NotReadyToSwitchOn,
+    SwitchOnDisabled,
+    ReadyToSwitchOn,
+    SwitchedOn,
+    OperationEnabled,
+    QuickStopActive,
+    Fault,
+}
+
+/// Command decoded from the controlword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlCommand {
+    Shutdown,
+    SwitchOn,
+    EnableOperation,
+    DisableVoltage,
+    QuickStop,
+}
+
+impl ControlCommand {
+    fn decode(controlword: u16) -> Self {
+        if controlword & CW_ENABLE_VOLTAGE == 0 {
+            ControlCommand::DisableVoltage
+        } else if controlword & CW_QUICK_STOP == 0 {
+            ControlCommand::QuickStop
+        } else if controlword & CW_SWITCH_ON == 0 {
+            ControlCommand::Shutdown
+        } else if controlword & CW_ENABLE_OPERATION == 0 {
+            ControlCommand::SwitchOn
+        } else {
+            ControlCommand::EnableOperation
+        }
+    }
+}
+
+/// Request of the state machine to the drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveAction {
+    /// Nothing to change
+    None,
+    /// Enable the output and follow the setpoint of the mode of operation
+    Enable,
+    /// Stop the axis, the output stays enabled
+    QuickStop,
+    /// Disable the output
+    Disable,
+    /// Clear the fault, the output stays disabled
+    FaultReset,
+}
+
+/// CiA 402 device control of one axis.
+pub struct Cia402 {
+    state: DriveState, // Current state
+    controlword: u16,  // Last controlword received
+}
+
+impl Cia402 {
+    pub fn new() -> Self {
+        Self {
+            state: DriveState::NotReadyToSwitchOn,
+            controlword: 0,
+        }
+    }
+
+    /// Current state
+    #[inline(always)]
+    pub fn state(&self) -> DriveState {
+        self.state
+    }
+
+    /// Last controlword received
+    #[inline(always)]
+    pub fn controlword(&self) -> u16 {
+        self.controlword
+    }
+
+    /// Returns true while the drive output is enabled by the state machine
+    #[inline(always)]
+    pub fn output_enabled(&self) -> bool {
+        matches!(
+            self.state,
+            DriveState::OperationEnabled | DriveState::QuickStopActive
+        )
+    }
+
+    /// Applies a controlword written by the master, returns the action for the drive.
+    pub fn write_controlword(&mut self, controlword: u16) -> DriveAction {
+        let reset = controlword & CW_FAULT_RESET != 0 && self.controlword & CW_FAULT_RESET == 0;
+        self.controlword = controlword;
+        if self.state == DriveState::Fault {
+            if reset {
+                self.state = DriveState::SwitchOnDisabled;
+                return DriveAction::FaultReset;
+            }
+            return DriveAction::None;
+        }
+        if self.state == DriveState::NotReadyToSwitchOn {
+            return DriveAction::None; // Transition 1 happens on its own
+        }
+        self.command(ControlCommand::decode(controlword))
+    }
+
+    /// Follows the drive, returns the action for the drive.
+    ///
+    /// # Arguments
+    /// * `status` - Operating state of the driver
+    /// * `enabled` - Drive output enabled
+    pub fn update(&mut self, status: DriverStatus, enabled: bool) -> DriveAction {
+        match (self.state, status) {
+            (DriveState::Fault, _) => DriveAction::None,
+            (_, DriverStatus::Error) => {
+                self.state = DriveState::Fault;
+                DriveAction::Disable
+            }
+            (DriveState::NotReadyToSwitchOn, DriverStatus::Ready) => {
+                self.state = DriveState::SwitchOnDisabled;
+                DriveAction::None
+            }
+            (_, DriverStatus::Calibrating) if self.state != DriveState::NotReadyToSwitchOn => {
+                self.state = DriveState::NotReadyToSwitchOn;
+                DriveAction::Disable
+            }
+            _ if self.output_enabled() && !enabled => {
+                self.state = DriveState::SwitchedOn;
+                DriveAction::None
+            }
+            _ => DriveAction::None,
+        }
+    }
+
+    /// Disables the voltage, e.g. once the master stopped the communication
+    pub fn disable(&mut self) -> DriveAction {
+        match self.state {
+            DriveState::NotReadyToSwitchOn | DriveState::Fault => DriveAction::None,
+            _ => self.command(ControlCommand::DisableVoltage),
+        }
+    }
+
+    /// Statusword of the current state.
+    ///
+    /// # Arguments
+    /// * `powered` - Supply is up and the bridge can be driven
+    /// * `target_reached` - Axis settled at the target
+    pub fn statusword(&self, powered: bool, target_reached: bool) -> u16 {
+        let mut word = match self.state {
+            DriveState::NotReadyToSwitchOn => 0,
+            DriveState::SwitchOnDisabled => SW_SWITCH_ON_DISABLED,
+            DriveState::ReadyToSwitchOn => SW_QUICK_STOP | SW_READY_TO_SWITCH_ON,
+            DriveState::SwitchedOn => SW_QUICK_STOP | SW_SWITCHED_ON | SW_READY_TO_SWITCH_ON,
+            DriveState::OperationEnabled => {
+                SW_QUICK_STOP | SW_OPERATION_ENABLED | SW_SWITCHED_ON | SW_READY_TO_SWITCH_ON
+            }
+            DriveState::QuickStopActive => {
+                SW_OPERATION_ENABLED | SW_SWITCHED_ON | SW_READY_TO_SWITCH_ON
+            }
+            DriveState::Fault => SW_FAULT,
+        };
+        word |= SW_REMOTE;
+        if powered {
+            word |= SW_VOLTAGE_ENABLED;
+        }
+        if self.state == DriveState::OperationEnabled {
+            word |= SW_FOLLOWING;
+            if target_reached {
+                word |= SW_TARGET_REACHED;
+            }
+        }
+        word
+    }
+
+    /// Runs the transition of a command from the current state
+    fn command(&mut self, command: ControlCommand) -> DriveAction {
+        use ControlCommand as C;
+        use DriveState as S;
+        let (state, action) = match (self.state, command) {
+            (S::SwitchOnDisabled, C::Shutdown) => (S::ReadyToSwitchOn, DriveAction::None),
+            (S::ReadyToSwitchOn, C::SwitchOn) => (S::SwitchedOn, DriveAction::None),
+            (S::ReadyToSwitchOn | S::SwitchedOn | S::QuickStopActive, C::EnableOperation) => {
+                (S::OperationEnabled, DriveAction::Enable)
+            }
+            (S::SwitchedOn, C::Shutdown) => (S::ReadyToSwitchOn, DriveAction::None),
+            (S::OperationEnabled, C::SwitchOn) => (S::SwitchedOn, DriveAction::Disable),
+            (S::OperationEnabled, C::Shutdown) => (S::ReadyToSwitchOn, DriveAction::Disable),
+            (S::OperationEnabled, C::QuickStop) => (S::QuickStopActive, DriveAction::QuickStop),
+            (S::OperationEnabled | S::QuickStopActive, C::DisableVoltage) => {
+                (S::SwitchOnDisabled, DriveAction::Disable)
+            }
+            (S::ReadyToSwitchOn | S::SwitchedOn, C::DisableVoltage | C::QuickStop) => {
+                (S::SwitchOnDisabled, DriveAction::None)
+            }
+            (state, _) => (state, DriveAction::None),
+        };
+        self.state = state;
+        action
+    }
+}
+
+impl Default for Cia402 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
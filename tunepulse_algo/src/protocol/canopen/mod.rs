@@ -0,0 +1,656 @@
+// Implements a CANopen slave with the CiA 402 drive profile, so a standard CANopen master can
+// control the drive over the CAN link instead of the TunePulse frames.
+
+// Key Features:
+// - NMT slave with boot-up message and producer heartbeat.
+// - Expedited SDO server on the object dictionary of `od`.
+// - 4 receive and 4 transmit PDOs with the mapping of the CiA 402 predefined connection set,
+//   remappable by the master (see `pdo`).
+// - CiA 402 device control (`cia402`) in cyclic synchronous position and velocity mode.
+// - Emergency message on every drive fault and once it is cleared.
+
+// Detailed Operation:
+// The node uses the identifiers of the predefined connection set: NMT 0x000, SYNC 0x080, EMCY,
+// TPDO1 - 4, RPDO1 - 4, SDO and heartbeat at their function base plus the node number (the
+// `can_node` parameter). After reset it sends the boot-up message and waits in Pre-Operational,
+// where the master configures it over SDO; PDOs are only exchanged in Operational. Leaving
+// Operational disables the voltage of the drive, a master that stops the network stops the
+// axis.
+// The platform passes every received frame to `receive()` and calls `update()` and `flush()`
+// after the control tick. Both return a `DriveCommand` for the controller: the output enable
+// of the state machine and the setpoint of the mode of operation, the controller owns the
+// drive and applies it. `DriveFeedback` carries the drive values the objects read, it is taken
+// by the controller right before every call.
+// Receive PDOs are applied when they arrive, the controlword last, so a target and the command
+// enabling the operation can share one PDO. In cyclic synchronous position mode the target
+// starts at the actual position when the operation gets enabled without a target in the same
+// write, the axis doesn't jump to a stale target. Transmit PDOs are packed at the SYNC they
+// are due with, or when their event timer expires, and sent by `flush()`.
+// Segmented and block SDO transfers, SYNC producers, the heartbeat consumer, LSS and storing the
+// configuration (0x1010) are not implemented; the master configures the node after every boot.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+pub mod cia402;
+pub mod od;
+pub mod pdo;
+
+use crate::fault::FaultCode;
+use crate::motor_driver::DriverStatus;
+use crate::setpoint::Setpoint;
+use cia402::{Cia402, DriveAction, DriveState, OperationMode};
+use od::{Access, SdoAbort, PDO_COUNT};
+use pdo::{Direction, Pdo};
+
+/// Function bases of the identifiers, the node number is added to the node specific ones
+pub const NMT_ID: u16 = 0x000;
+pub const SYNC_ID: u16 = 0x080;
+pub const EMCY_BASE: u16 = 0x080;
+pub const TPDO_BASE: u16 = 0x180;
+pub const RPDO_BASE: u16 = 0x200;
+pub const SDO_TX_BASE: u16 = 0x580;
+pub const SDO_RX_BASE: u16 = 0x600;
+pub const HEARTBEAT_BASE: u16 = 0x700;
+/// Distance of the identifiers of consecutive PDOs
+const PDO_STEP: u16 = 0x100;
+
+/// Identifier bits of the node number
+pub const NODE_MASK: u16 = 0x7F;
+
+/// NMT command specifiers
+const NMT_START: u8 = 0x01;
+const NMT_STOP: u8 = 0x02;
+const NMT_PRE_OPERATIONAL: u8 = 0x80;
+const NMT_RESET_NODE: u8 = 0x81;
+const NMT_RESET_COMMUNICATION: u8 = 0x82;
+
+/// SDO command specifiers (upper 3 bits of the first byte)
+const SDO_DOWNLOAD: u8 = 1;
+const SDO_UPLOAD: u8 = 2;
+const SDO_ABORT: u8 = 4;
+/// SDO initiate flags: expedited, size indicated
+const SDO_EXPEDITED: u8 = 1 << 1;
+const SDO_SIZE: u8 = 1 << 0;
+/// SDO server replies
+const SDO_DOWNLOAD_REPLY: u8 = 0x60;
+const SDO_UPLOAD_REPLY: u8 = 0x43;
+const SDO_ABORT_REPLY: u8 = 0x80;
+
+/// Error code of a drive fault: manufacturer specific range, fault code in the low byte
+const ERROR_CODE_FAULT: u16 = 0xFF00;
+/// Error register bit: generic error
+const ERROR_GENERIC: u8 = 1 << 0;
+
+/// Maximum data bytes of a classic CAN frame
+pub const MAX_DATA: usize = 8;
+
+/// Acceptance filters of a node: NMT, SYNC and the identifiers of the node
+pub fn filters(node: u8) -> [(u16, u16); 3] {
+    [
+        (NMT_ID, 0x7FF),
+        (SYNC_ID, 0x7FF),
+        (node as u16 & NODE_MASK, NODE_MASK),
+    ]
+}
+
+/// CAN frame sent by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanMessage {
+    pub id: u16,              // Standard identifier (11 bit)
+    pub len: u8,              // Number of data bytes
+    pub data: [u8; MAX_DATA], // Data bytes, zero beyond `len`
+}
+
+impl CanMessage {
+    fn new(id: u16, data: &[u8]) -> Self {
+        let len = data.len().min(MAX_DATA);
+        let mut message = Self {
+            id,
+            len: len as u8,
+            data: [0; MAX_DATA],
+        };
+        message.data[..len].copy_from_slice(&data[..len]);
+        message
+    }
+
+    /// Data bytes
+    #[inline(always)]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// CAN link able to send frames with any identifier.
+pub trait CanTransport {
+    /// Queues a frame for sending, returns false if the link is busy.
+    fn transmit(&mut self, id: u16, data: &[u8]) -> bool;
+}
+
+/// NMT state of the node, the value is the one of the heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NmtState {
+    Stopped = 0x04,
+    Operational = 0x05,
+    PreOperational = 0x7F,
+}
+
+/// Drive values read by the objects, taken by the controller before every call.
+#[derive(Debug, Clone, Copy)]
+pub struct DriveFeedback {
+    pub status: DriverStatus, // Operating state of the driver
+    pub fault: FaultCode,     // Reason of the error state
+    pub enabled: bool,        // Drive output enabled
+    pub powered: bool,        // Supply is up and the bridge can be driven
+    pub position: i32,        // Actual position (i16 rotations + u16 angle)
+    pub velocity: i32,        // Actual velocity (counts/s)
+    pub in_position: bool,    // Position settled within the in-position window
+    pub at_velocity: bool,    // Speed settled within the velocity window
+    pub identity: [u32; 3],   // Product code, revision and serial number
+}
+
+/// Changes of the drive requested by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriveCommand {
+    pub action: DriveAction,        // Output enable, quick stop or fault reset
+    pub setpoint: Option<Setpoint>, // New setpoint, applied before the action
+}
+
+/// CANopen slave of one drive.
+pub struct CanOpenNode {
+    node: u8,                                  // Node number
+    nmt: NmtState,                             // NMT state
+    boot: bool,                                // Boot-up message waiting for the transport
+    cia402: Cia402,                            // Device control state machine
+    mode: i8,                                  // Modes of operation, 0 none
+    target_position: i32,                      // Target position (i16 rotations + u16 angle)
+    target_velocity: i32,                      // Target velocity (counts/s)
+    target_written: bool,                      // Target written since the last command
+    setpoint_changed: bool,                    // Mode or target written since the last command
+    action: DriveAction,                       // Action of the last controlword written
+    heartbeat_ms: u16,                         // Producer heartbeat time, 0 off
+    heartbeat_at: u32,                         // Tick of the last heartbeat
+    rpdo: [Pdo; PDO_COUNT],                    // Receive PDOs
+    tpdo: [Pdo; PDO_COUNT],                    // Transmit PDOs
+    tpdo_due: [Option<CanMessage>; PDO_COUNT], // Transmit PDOs packed at a SYNC, not sent yet
+    tpdo_at: [u32; PDO_COUNT],                 // Tick of the last event timer transmission
+    sdo_reply: Option<CanMessage>,             // SDO reply waiting for the transport
+    emcy: Option<CanMessage>,                  // Emergency message waiting for the transport
+    fault: FaultCode,                          // Fault reported by the last emergency message
+}
+
+impl CanOpenNode {
+    /// Creates the node in its state after reset, the boot-up message pending
+    pub fn new(node: u8) -> Self {
+        let mut canopen = Self {
+            node,
+            nmt: NmtState::PreOperational,
+            boot: true,
+            cia402: Cia402::new(),
+            mode: 0,
+            target_position: 0,
+            target_velocity: 0,
+            target_written: false,
+            setpoint_changed: false,
+            action: DriveAction::None,
+            heartbeat_ms: 0,
+            heartbeat_at: 0,
+            rpdo: [Pdo::new(Direction::Receive, 0, 0, &[]); PDO_COUNT],
+            tpdo: [Pdo::new(Direction::Transmit, 0, 0, &[]); PDO_COUNT],
+            tpdo_due: [None; PDO_COUNT],
+            tpdo_at: [0; PDO_COUNT],
+            sdo_reply: None,
+            emcy: None,
+            fault: FaultCode::None,
+        };
+        canopen.reset_communication();
+        canopen
+    }
+
+    /// Node number
+    #[inline(always)]
+    pub fn node(&self) -> u8 {
+        self.node
+    }
+
+    /// NMT state
+    #[inline(always)]
+    pub fn nmt_state(&self) -> NmtState {
+        self.nmt
+    }
+
+    /// State of the device control
+    #[inline(always)]
+    pub fn drive_state(&self) -> DriveState {
+        self.cia402.state()
+    }
+
+    /// Changes the node number, the node boots again with the identifiers of the new number
+    pub fn set_node(&mut self, node: u8) {
+        if node != self.node {
+            self.node = node;
+            self.reset_communication();
+        }
+    }
+
+    /// Handles a received frame, returns the changes of the drive.
+    ///
+    /// # Arguments
+    /// * `id` - Standard identifier of the frame
+    /// * `data` - Data bytes of the frame
+    /// * `drive` - Drive values at reception
+    pub fn receive(&mut self, id: u16, data: &[u8], drive: &DriveFeedback) -> DriveCommand {
+        let node = self.node as u16;
+        match id {
+            NMT_ID => self.nmt_command(data),
+            SYNC_ID if self.nmt == NmtState::Operational => {
+                for i in 0..PDO_COUNT {
+                    if self.tpdo[i].sync() {
+                        self.tpdo_due[i] = self.pack(i, drive);
+                    }
+                }
+            }
+            _ if id == SDO_RX_BASE + node && self.nmt != NmtState::Stopped => {
+                self.sdo_reply = self.sdo_request(data, drive);
+            }
+            _ if self.nmt == NmtState::Operational => {
+                let pdo = (0..PDO_COUNT).find(|&i| self.rpdo[i].id() == Some(id));
+                if let Some(i) = pdo {
+                    self.unpack(i, data);
+                }
+            }
+            _ => {}
+        }
+        self.command(drive)
+    }
+
+    /// Follows the drive (calibration, faults, output disabled elsewhere), call after the control
+    /// tick, returns the changes of the drive.
+    pub fn update(&mut self, drive: &DriveFeedback) -> DriveCommand {
+        let action = self.cia402.update(drive.status, drive.enabled);
+        if action != DriveAction::None {
+            self.action = action;
+        }
+        if drive.fault != self.fault && self.nmt != NmtState::Stopped {
+            self.fault = drive.fault;
+            let code = Self::error_code(drive.fault).to_le_bytes();
+            let register = Self::error_register(drive.fault);
+            let emcy = [code[0], code[1], register, drive.fault as u8, 0, 0, 0, 0];
+            self.emcy = Some(CanMessage::new(EMCY_BASE + self.node as u16, &emcy));
+        }
+        self.command(drive)
+    }
+
+    /// Sends the pending frames: boot-up, SDO reply, emergency, transmit PDOs and heartbeat,
+    /// frames the transport can't take are kept for the next call.
+    ///
+    /// # Arguments
+    /// * `drive` - Drive values for the PDOs sent by event timer
+    /// * `tick` - Control tick counter
+    /// * `frequency` - Control ticks per second
+    pub fn flush<T: CanTransport>(
+        &mut self,
+        transport: &mut T,
+        drive: &DriveFeedback,
+        tick: u32,
+        frequency: u16,
+    ) {
+        let node = self.node as u16;
+        if self.boot {
+            if !transport.transmit(HEARTBEAT_BASE + node, &[0]) {
+                return;
+            }
+            self.boot = false;
+            self.heartbeat_at = tick;
+        }
+        for pending in [&mut self.sdo_reply, &mut self.emcy] {
+            if let Some(message) = pending {
+                if !transport.transmit(message.id, message.data()) {
+                    return;
+                }
+                *pending = None;
+            }
+        }
+        let ticks = |ms: u16| ms as u32 * frequency as u32 / 1000;
+        if self.nmt == NmtState::Operational {
+            for i in 0..PDO_COUNT {
+                let timer = self.tpdo[i].event_timer();
+                if timer != 0 && tick.wrapping_sub(self.tpdo_at[i]) >= ticks(timer) {
+                    self.tpdo_due[i] = self.pack(i, drive).or(self.tpdo_due[i]);
+                    self.tpdo_at[i] = tick;
+                }
+                if let Some(message) = self.tpdo_due[i] {
+                    if !transport.transmit(message.id, message.data()) {
+                        return;
+                    }
+                    self.tpdo_due[i] = None;
+                }
+            }
+        }
+        if self.heartbeat_ms != 0
+            && tick.wrapping_sub(self.heartbeat_at) >= ticks(self.heartbeat_ms)
+            && transport.transmit(HEARTBEAT_BASE + node, &[self.nmt as u8])
+        {
+            self.heartbeat_at = tick;
+        }
+    }
+
+    /// Runs an NMT command addressed to this node or to all nodes
+    fn nmt_command(&mut self, data: &[u8]) {
+        let [command, node, ..] = *data else {
+            return;
+        };
+        if node != 0 && node != self.node {
+            return;
+        }
+        match command {
+            NMT_START => self.nmt = NmtState::Operational,
+            NMT_STOP => self.enter(NmtState::Stopped),
+            NMT_PRE_OPERATIONAL => self.enter(NmtState::PreOperational),
+            NMT_RESET_NODE => {
+                self.mode = 0;
+                self.target_position = 0;
+                self.target_velocity = 0;
+                self.reset_communication();
+            }
+            NMT_RESET_COMMUNICATION => self.reset_communication(),
+            _ => {}
+        }
+    }
+
+    /// Enters an NMT state other than Operational, the voltage of the drive is disabled
+    fn enter(&mut self, state: NmtState) {
+        let action = self.cia402.disable();
+        if action != DriveAction::None {
+            self.action = action;
+        }
+        self.nmt = state;
+        self.tpdo_due = [None; PDO_COUNT];
+    }
+
+    /// Restores the communication objects and boots again
+    fn reset_communication(&mut self) {
+        use od::*;
+        self.enter(NmtState::PreOperational);
+        let node = self.node as u16;
+        let control = mapping(CONTROLWORD, 0, 16);
+        let status = mapping(STATUSWORD, 0, 16);
+        let rpdo: [&[u32]; PDO_COUNT] = [
+            &[control],
+            &[control, mapping(MODES_OF_OPERATION, 0, 8)],
+            &[control, mapping(TARGET_POSITION, 0, 32)],
+            &[control, mapping(TARGET_VELOCITY, 0, 32)],
+        ];
+        let tpdo: [&[u32]; PDO_COUNT] = [
+            &[status],
+            &[status, mapping(MODES_DISPLAY, 0, 8)],
+            &[status, mapping(POSITION_ACTUAL, 0, 32)],
+            &[status, mapping(VELOCITY_ACTUAL, 0, 32)],
+        ];
+        for (i, id) in (0..PDO_COUNT as u16)
+            .map(|i| i * PDO_STEP + node)
+            .enumerate()
+        {
+            self.rpdo[i] = Pdo::new(Direction::Receive, RPDO_BASE + id, 255, rpdo[i]);
+            self.tpdo[i] = Pdo::new(Direction::Transmit, TPDO_BASE + id, 1, tpdo[i]);
+        }
+        self.heartbeat_ms = 0;
+        self.sdo_reply = None;
+        self.emcy = None;
+        self.boot = true;
+    }
+
+    /// Answers an SDO request, `None` for a request that takes no reply
+    fn sdo_request(&mut self, data: &[u8], drive: &DriveFeedback) -> Option<CanMessage> {
+        if data.len() < MAX_DATA {
+            return None;
+        }
+        let index = u16::from_le_bytes([data[1], data[2]]);
+        let sub = data[3];
+        let value = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        let id = SDO_TX_BASE + self.node as u16;
+        let result = match data[0] >> 5 {
+            SDO_DOWNLOAD => self.sdo_download(index, sub, data[0], value),
+            SDO_UPLOAD => self.sdo_upload(index, sub, drive),
+            SDO_ABORT => return None,
+            _ => Err(SdoAbort::InvalidCommand),
+        };
+        let reply = match result {
+            Ok(reply) => reply,
+            Err(abort) => {
+                let code = (abort as u32).to_le_bytes();
+                [
+                    SDO_ABORT_REPLY,
+                    data[1],
+                    data[2],
+                    sub,
+                    code[0],
+                    code[1],
+                    code[2],
+                    code[3],
+                ]
+            }
+        };
+        Some(CanMessage::new(id, &reply))
+    }
+
+    /// Expedited download (write) of an object
+    fn sdo_download(
+        &mut self,
+        index: u16,
+        sub: u8,
+        command: u8,
+        value: u32,
+    ) -> Result<[u8; MAX_DATA], SdoAbort> {
+        if command & SDO_EXPEDITED == 0 {
+            return Err(SdoAbort::InvalidCommand); // Segmented transfer
+        }
+        let entry = od::entry(index, sub)?;
+        if entry.access != Access::ReadWrite {
+            return Err(SdoAbort::ReadOnly);
+        }
+        if command & SDO_SIZE != 0 && 4 - (command >> 2 & 0b11) != entry.size {
+            return Err(SdoAbort::LengthMismatch);
+        }
+        let mask = u32::MAX >> (32 - 8 * entry.size as u32);
+        self.write_object(index, sub, value & mask)?;
+        let index = index.to_le_bytes();
+        Ok([SDO_DOWNLOAD_REPLY, index[0], index[1], sub, 0, 0, 0, 0])
+    }
+
+    /// Expedited upload (read) of an object
+    fn sdo_upload(
+        &self,
+        index: u16,
+        sub: u8,
+        drive: &DriveFeedback,
+    ) -> Result<[u8; MAX_DATA], SdoAbort> {
+        let entry = od::entry(index, sub)?;
+        let value = self.read_object(index, sub, drive).to_le_bytes();
+        let index = index.to_le_bytes();
+        Ok([
+            SDO_UPLOAD_REPLY | (4 - entry.size) << 2,
+            index[0],
+            index[1],
+            sub,
+            value[0],
+            value[1],
+            value[2],
+            value[3],
+        ])
+    }
+
+    /// Value of an existing object, signed values sign extended
+    fn read_object(&self, index: u16, sub: u8, drive: &DriveFeedback) -> u32 {
+        use od::*;
+        if let Some(i) = pdo_number(index, RPDO_COMMUNICATION) {
+            return self.rpdo[i].communication(sub);
+        }
+        if let Some(i) = pdo_number(index, TPDO_COMMUNICATION) {
+            return self.tpdo[i].communication(sub);
+        }
+        if let Some(i) = pdo_number(index, RPDO_MAPPING) {
+            return self.rpdo[i].mapping_entry(sub);
+        }
+        if let Some(i) = pdo_number(index, TPDO_MAPPING) {
+            return self.tpdo[i].mapping_entry(sub);
+        }
+        match index {
+            DEVICE_TYPE => DEVICE_TYPE_SERVO,
+            ERROR_REGISTER => Self::error_register(drive.fault) as u32,
+            HEARTBEAT_TIME => self.heartbeat_ms as u32,
+            IDENTITY => match sub {
+                0 => 4,
+                1 => 0, // No vendor identifier assigned
+                _ => drive.identity[sub as usize - 2],
+            },
+            ERROR_CODE => Self::error_code(drive.fault) as u32,
+            CONTROLWORD => self.cia402.controlword() as u32,
+            STATUSWORD => {
+                let reached = match OperationMode::from_raw(self.mode) {
+                    Some(OperationMode::CyclicPosition) => drive.in_position,
+                    Some(OperationMode::CyclicVelocity) => drive.at_velocity,
+                    _ => false,
+                };
+                self.cia402.statusword(drive.powered, reached) as u32
+            }
+            MODES_OF_OPERATION => self.mode as u32,
+            MODES_DISPLAY => self.mode_display() as u32,
+            POSITION_ACTUAL => drive.position as u32,
+            VELOCITY_ACTUAL => drive.velocity as u32,
+            TARGET_POSITION => self.target_position as u32,
+            TARGET_VELOCITY => self.target_velocity as u32,
+            SUPPORTED_DRIVE_MODES => cia402::SUPPORTED_MODES,
+            _ => 0,
+        }
+    }
+
+    /// Writes a writable object, `value` holds the bytes of its size
+    fn write_object(&mut self, index: u16, sub: u8, value: u32) -> Result<(), SdoAbort> {
+        use od::*;
+        if let Some(i) = pdo_number(index, RPDO_COMMUNICATION) {
+            return self.rpdo[i].set_communication(sub, value);
+        }
+        if let Some(i) = pdo_number(index, TPDO_COMMUNICATION) {
+            return self.tpdo[i].set_communication(sub, value);
+        }
+        if let Some(i) = pdo_number(index, RPDO_MAPPING) {
+            return self.rpdo[i].set_mapping_entry(sub, value);
+        }
+        if let Some(i) = pdo_number(index, TPDO_MAPPING) {
+            return self.tpdo[i].set_mapping_entry(sub, value);
+        }
+        match index {
+            HEARTBEAT_TIME => self.heartbeat_ms = value as u16,
+            CONTROLWORD => self.action = self.cia402.write_controlword(value as u16),
+            MODES_OF_OPERATION => {
+                let mode = value as u8 as i8;
+                if mode != 0 && OperationMode::from_raw(mode).is_none() {
+                    return Err(SdoAbort::InvalidValue);
+                }
+                self.mode = mode;
+                self.setpoint_changed = true;
+            }
+            TARGET_POSITION => {
+                self.target_position = value as i32;
+                self.target_written = true;
+                self.setpoint_changed = true;
+            }
+            TARGET_VELOCITY => {
+                self.target_velocity = value as i32;
+                self.target_written = true;
+                self.setpoint_changed = true;
+            }
+            _ => return Err(SdoAbort::ReadOnly),
+        }
+        Ok(())
+    }
+
+    /// Packs a transmit PDO, `None` while it is switched off
+    fn pack(&self, pdo: usize, drive: &DriveFeedback) -> Option<CanMessage> {
+        let id = self.tpdo[pdo].id()?;
+        let mut data = [0u8; MAX_DATA];
+        let mut len = 0;
+        for &entry in self.tpdo[pdo].mapping() {
+            let (index, sub, bits) = od::unmap(entry);
+            let size = bits as usize / 8;
+            let value = self.read_object(index, sub, drive).to_le_bytes();
+            data[len..len + size].copy_from_slice(&value[..size]);
+            len += size;
+        }
+        Some(CanMessage::new(id, &data[..len]))
+    }
+
+    /// Applies a receive PDO, the controlword after the other objects
+    fn unpack(&mut self, pdo: usize, data: &[u8]) {
+        let rpdo = self.rpdo[pdo];
+        if data.len() < rpdo.len() {
+            return;
+        }
+        let mut offset = 0;
+        let mut controlword = None;
+        for &entry in rpdo.mapping() {
+            let (index, sub, bits) = od::unmap(entry);
+            let size = bits as usize / 8;
+            let mut bytes = [0u8; 4];
+            bytes[..size].copy_from_slice(&data[offset..offset + size]);
+            offset += size;
+            let value = u32::from_le_bytes(bytes);
+            if index == od::CONTROLWORD {
+                controlword = Some(value);
+            } else {
+                let _ = self.write_object(index, sub, value); // Unsupported modes are ignored
+            }
+        }
+        if let Some(value) = controlword {
+            let _ = self.write_object(od::CONTROLWORD, 0, value);
+        }
+    }
+
+    /// Collects the changes of the drive since the last call
+    fn command(&mut self, drive: &DriveFeedback) -> DriveCommand {
+        let action = core::mem::replace(&mut self.action, DriveAction::None);
+        let changed = core::mem::take(&mut self.setpoint_changed);
+        let written = core::mem::take(&mut self.target_written);
+        let setpoint = match self.cia402.state() {
+            DriveState::OperationEnabled if action == DriveAction::Enable || changed => {
+                match OperationMode::from_raw(self.mode) {
+                    Some(OperationMode::CyclicPosition) => {
+                        if action == DriveAction::Enable && !written {
+                            self.target_position = drive.position;
+                        }
+                        Some(Setpoint::Position(self.target_position))
+                    }
+                    Some(OperationMode::CyclicVelocity) => {
+                        Some(Setpoint::Velocity(self.target_velocity))
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        DriveCommand { action, setpoint }
+    }
+
+    /// Mode of operation in effect, 0 if none is selected
+    fn mode_display(&self) -> i8 {
+        OperationMode::from_raw(self.mode).map_or(0, |mode| mode as i8)
+    }
+
+    /// Error code (0x603F) of a drive fault
+    fn error_code(fault: FaultCode) -> u16 {
+        match fault {
+            FaultCode::None => 0,
+            fault => ERROR_CODE_FAULT | fault as u16,
+        }
+    }
+
+    /// Error register (0x1001) of a drive fault
+    fn error_register(fault: FaultCode) -> u8 {
+        match fault {
+            FaultCode::None => 0,
+            _ => ERROR_GENERIC,
+        }
+    }
+}
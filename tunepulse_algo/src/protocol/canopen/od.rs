@@ -0,0 +1,186 @@
+// Implements the layout of the CANopen object dictionary: the objects of the node, their size,
+// access and PDO mapping, and the abort codes of a refused access.
+
+// Key Features:
+// - Communication objects of CiA 301: device type, error register, heartbeat, identity and
+//   the parameters and mappings of 4 receive and 4 transmit PDOs.
+// - Drive objects of CiA 402: controlword, statusword, modes of operation, actual and target
+//   position and velocity, error code and supported drive modes.
+// - Values up to 32 bit, every object fits an expedited SDO transfer.
+
+// Detailed Operation:
+// `entry()` answers whether an object exists and how it may be accessed, the node checks every
+// SDO access and every PDO mapping against it before touching a value; the values themselves
+// live with the node and the drive. Only the drive objects can be mapped into PDOs.
+// Positions are in the user frame of the drive (65536 counts per revolution, i16 rotations +
+// u16 angle as i32), velocities in counts per second, there are no factor group objects.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of receive and of transmit PDOs
+pub const PDO_COUNT: usize = 4;
+
+/// Device type (u32, ro): CiA 402 servo drive
+pub const DEVICE_TYPE: u16 = 0x1000;
+/// Error register (u8, ro)
+pub const ERROR_REGISTER: u16 = 0x1001;
+/// Producer heartbeat time (u16, rw, ms)
+pub const HEARTBEAT_TIME: u16 = 0x1017;
+/// Identity (record of u32, ro): vendor, product code, revision, serial number
+pub const IDENTITY: u16 = 0x1018;
+/// First receive PDO communication parameter (record, rw): COB-ID, transmission type
+pub const RPDO_COMMUNICATION: u16 = 0x1400;
+/// First receive PDO mapping (record, rw): number of objects, mapped objects
+pub const RPDO_MAPPING: u16 = 0x1600;
+/// First transmit PDO communication parameter (record, rw): COB-ID, transmission type, event
+/// timer at sub-index 5
+pub const TPDO_COMMUNICATION: u16 = 0x1800;
+/// First transmit PDO mapping (record, rw): number of objects, mapped objects
+pub const TPDO_MAPPING: u16 = 0x1A00;
+/// Error code (u16, ro)
+pub const ERROR_CODE: u16 = 0x603F;
+/// Controlword (u16, rw)
+pub const CONTROLWORD: u16 = 0x6040;
+/// Statusword (u16, ro)
+pub const STATUSWORD: u16 = 0x6041;
+/// Modes of operation (i8, rw)
+pub const MODES_OF_OPERATION: u16 = 0x6060;
+/// Modes of operation display (i8, ro)
+pub const MODES_DISPLAY: u16 = 0x6061;
+/// Position actual value (i32, ro, counts)
+pub const POSITION_ACTUAL: u16 = 0x6064;
+/// Velocity actual value (i32, ro, counts/s)
+pub const VELOCITY_ACTUAL: u16 = 0x606C;
+/// Target position (i32, rw, counts)
+pub const TARGET_POSITION: u16 = 0x607A;
+/// Target velocity (i32, rw, counts/s)
+pub const TARGET_VELOCITY: u16 = 0x60FF;
+/// Supported drive modes (u32, ro)
+pub const SUPPORTED_DRIVE_MODES: u16 = 0x6502;
+
+/// Device type value: additional information 2 (servo drive), profile 402
+pub const DEVICE_TYPE_SERVO: u32 = 0x0002_0192;
+
+/// Reason of a refused SDO access, the value is the abort code of CiA 301.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SdoAbort {
+    /// Client/server command specifier not valid or unknown (also segmented transfers)
+    InvalidCommand = 0x0504_0001,
+    /// Attempt to write a read only object
+    ReadOnly = 0x0601_0002,
+    /// Object does not exist in the object dictionary
+    NoObject = 0x0602_0000,
+    /// Object cannot be mapped to the PDO
+    NotMappable = 0x0604_0041,
+    /// Number and length of the mapped objects would exceed the PDO length
+    MappingTooLong = 0x0604_0042,
+    /// Data type does not match, length of service parameter does not match
+    LengthMismatch = 0x0607_0010,
+    /// Sub-index does not exist
+    NoSubIndex = 0x0609_0011,
+    /// Invalid value for parameter
+    InvalidValue = 0x0609_0030,
+    /// Data cannot be transferred or stored because of the present device state
+    DeviceState = 0x0800_0022,
+}
+
+/// Access of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Description of an object (a sub-index of an index).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub size: u8,       // Size of the value in bytes
+    pub access: Access, // Allowed access
+    pub mappable: bool, // Object can be mapped into a PDO
+}
+
+impl Entry {
+    const fn new(size: u8, access: Access) -> Self {
+        Self {
+            size,
+            access,
+            mappable: false,
+        }
+    }
+
+    const fn mapped(size: u8, access: Access) -> Self {
+        Self {
+            size,
+            access,
+            mappable: true,
+        }
+    }
+}
+
+/// Description of an object, the abort code if it doesn't exist.
+///
+/// # Arguments
+/// * `index` - Index of the object
+/// * `sub` - Sub-index of the object
+pub fn entry(index: u16, sub: u8) -> Result<Entry, SdoAbort> {
+    use Access::{ReadOnly as Ro, ReadWrite as Rw};
+    let (entry, highest) = match index {
+        DEVICE_TYPE | SUPPORTED_DRIVE_MODES => (Entry::new(4, Ro), 0),
+        ERROR_REGISTER => (Entry::new(1, Ro), 0),
+        HEARTBEAT_TIME => (Entry::new(2, Rw), 0),
+        IDENTITY => (Entry::new(4, Ro), 4),
+        _ if is_pdo_index(index, RPDO_COMMUNICATION) => {
+            (Entry::new(if sub == 2 { 1 } else { 4 }, Rw), 2)
+        }
+        _ if is_pdo_index(index, TPDO_COMMUNICATION) => match sub {
+            3 | 4 => return Err(SdoAbort::NoSubIndex), // No inhibit time, no sync start value
+            5 => (Entry::new(2, Rw), 5),
+            2 => (Entry::new(1, Rw), 5),
+            _ => (Entry::new(4, Rw), 5),
+        },
+        _ if is_pdo_index(index, RPDO_MAPPING) || is_pdo_index(index, TPDO_MAPPING) => {
+            (Entry::new(4, Rw), PDO_COUNT as u8)
+        }
+        ERROR_CODE => (Entry::mapped(2, Ro), 0),
+        CONTROLWORD => (Entry::mapped(2, Rw), 0),
+        STATUSWORD => (Entry::mapped(2, Ro), 0),
+        MODES_OF_OPERATION => (Entry::mapped(1, Rw), 0),
+        MODES_DISPLAY => (Entry::mapped(1, Ro), 0),
+        POSITION_ACTUAL | VELOCITY_ACTUAL => (Entry::mapped(4, Ro), 0),
+        TARGET_POSITION | TARGET_VELOCITY => (Entry::mapped(4, Rw), 0),
+        _ => return Err(SdoAbort::NoObject),
+    };
+    let is_mapping = is_pdo_index(index, RPDO_MAPPING) || is_pdo_index(index, TPDO_MAPPING);
+    match sub {
+        // Sub-index 0 of a record holds its highest sub-index, of a mapping the number of
+        // mapped objects
+        0 if is_mapping => Ok(Entry::new(1, Rw)),
+        0 if highest > 0 => Ok(Entry::new(1, Ro)),
+        0 => Ok(entry),
+        _ if sub <= highest => Ok(entry),
+        _ => Err(SdoAbort::NoSubIndex),
+    }
+}
+
+/// Number of the PDO of a communication or mapping index, `None` outside the range of `base`
+pub fn pdo_number(index: u16, base: u16) -> Option<usize> {
+    is_pdo_index(index, base).then(|| (index - base) as usize)
+}
+
+fn is_pdo_index(index: u16, base: u16) -> bool {
+    (base..base + PDO_COUNT as u16).contains(&index)
+}
+
+/// Mapping entry of an object: index, sub-index and length in bits
+#[inline(always)]
+pub const fn mapping(index: u16, sub: u8, bits: u8) -> u32 {
+    (index as u32) << 16 | (sub as u32) << 8 | bits as u32
+}
+
+/// Index, sub-index and length in bits of a mapping entry
+#[inline(always)]
+pub const fn unmap(entry: u32) -> (u16, u8, u8) {
+    ((entry >> 16) as u16, (entry >> 8) as u8, entry as u8)
+}
@@ -0,0 +1,218 @@
+// Implements the process data objects (PDO) of the CANopen node: their communication parameters
+// and the mapping of drive objects into the 8 data bytes.
+
+// Key Features:
+// - Up to 4 objects and 64 bit per PDO, mapping changeable by the master over SDO.
+// - Transmit PDOs on every n-th SYNC (types 1 - 240) or by event timer (types 254, 255).
+// - Receive PDOs applied on reception, whatever their transmission type.
+// - COB-IDs of the predefined connection set, only their valid bit can change.
+
+// Detailed Operation:
+// A mapping entry names index, sub-index and length in bits of an object, the objects are packed
+// little endian in the order of the entries. The mapping follows the procedure of CiA 301: the
+// number of mapped objects (sub-index 0) is set to 0, the entries are written, then the number
+// is set again; an entry is checked against the object dictionary when written, the total
+// length when the number is set. A receive PDO shorter than its mapping is ignored.
+// A transmit PDO with a sync transmission type counts the SYNC objects and is due on every n-th
+// of them, with the event-driven types it is due whenever its event timer expires, a timer of
+// 0 never sends it. There is no inhibit time, the event timer is the only rate limit.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::od::{self, Access, SdoAbort};
+
+/// Objects a PDO can map
+pub const MAX_MAPPED: usize = 4;
+
+/// Bits of the data bytes of a PDO
+const MAX_BITS: u32 = 64;
+
+/// COB-ID bit of a PDO which doesn't exist or is switched off
+pub const COB_ID_INVALID: u32 = 1 << 31;
+
+/// Highest transmission type counting SYNC objects
+const SYNC_TYPE_MAX: u8 = 240;
+/// Event-driven transmission types (manufacturer and profile specific)
+const EVENT_TYPES: [u8; 2] = [254, 255];
+
+/// Direction of a PDO seen from the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Receive,
+    Transmit,
+}
+
+/// Communication parameters and mapping of one PDO.
+#[derive(Debug, Clone, Copy)]
+pub struct Pdo {
+    direction: Direction,       // Receive or transmit PDO
+    cob_id: u32,                // Identifier, `COB_ID_INVALID` while switched off
+    transmission: u8,           // Transmission type
+    event_timer: u16,           // Period of an event-driven transmit PDO (ms), 0 off
+    mapping: [u32; MAX_MAPPED], // Mapped objects: index, sub-index, length in bits
+    mapped: u8,                 // Number of mapped objects
+    syncs: u8,                  // SYNC objects counted since the last transmission
+}
+
+impl Pdo {
+    /// Creates a valid PDO
+    ///
+    /// # Arguments
+    /// * `direction` - Receive or transmit PDO
+    /// * `id` - Identifier of the PDO
+    /// * `transmission` - Transmission type
+    /// * `mapping` - Mapped objects, at most `MAX_MAPPED`
+    pub fn new(direction: Direction, id: u16, transmission: u8, mapping: &[u32]) -> Self {
+        let mut pdo = Self {
+            direction,
+            cob_id: id as u32,
+            transmission,
+            event_timer: 0,
+            mapping: [0; MAX_MAPPED],
+            mapped: mapping.len().min(MAX_MAPPED) as u8,
+            syncs: 0,
+        };
+        pdo.mapping[..pdo.mapped as usize].copy_from_slice(&mapping[..pdo.mapped as usize]);
+        pdo
+    }
+
+    /// Identifier, `None` while the PDO is switched off
+    #[inline(always)]
+    pub fn id(&self) -> Option<u16> {
+        (self.cob_id & COB_ID_INVALID == 0).then_some(self.cob_id as u16 & 0x7FF)
+    }
+
+    /// Mapped objects in packing order
+    #[inline(always)]
+    pub fn mapping(&self) -> &[u32] {
+        &self.mapping[..self.mapped as usize]
+    }
+
+    /// Length of the mapped data in bytes
+    pub fn len(&self) -> usize {
+        let bits: u32 = self
+            .mapping()
+            .iter()
+            .map(|&entry| od::unmap(entry).2 as u32)
+            .sum();
+        (bits / 8) as usize
+    }
+
+    /// Returns true if no object is mapped
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.mapped == 0
+    }
+
+    /// Event timer period (ms), 0 if the PDO isn't sent by timer
+    #[inline(always)]
+    pub fn event_timer(&self) -> u16 {
+        if EVENT_TYPES.contains(&self.transmission) {
+            self.event_timer
+        } else {
+            0
+        }
+    }
+
+    /// Counts a SYNC object, returns true if a transmit PDO is due with it
+    pub fn sync(&mut self) -> bool {
+        if self.direction != Direction::Transmit
+            || !(1..=SYNC_TYPE_MAX).contains(&self.transmission)
+        {
+            return false;
+        }
+        self.syncs += 1;
+        if self.syncs < self.transmission {
+            return false;
+        }
+        self.syncs = 0;
+        true
+    }
+
+    /// Reads a sub-index of the communication parameter
+    pub fn communication(&self, sub: u8) -> u32 {
+        match sub {
+            0 if self.direction == Direction::Receive => 2,
+            0 => 5,
+            1 => self.cob_id,
+            2 => self.transmission as u32,
+            _ => self.event_timer as u32,
+        }
+    }
+
+    /// Writes a sub-index of the communication parameter.
+    ///
+    /// Only the valid bit of the COB-ID can change, the identifiers are the ones of the node.
+    pub fn set_communication(&mut self, sub: u8, value: u32) -> Result<(), SdoAbort> {
+        match sub {
+            1 if value & !COB_ID_INVALID != self.cob_id & !COB_ID_INVALID => {
+                Err(SdoAbort::InvalidValue)
+            }
+            1 => {
+                self.cob_id = value;
+                Ok(())
+            }
+            2 => {
+                let transmission = value as u8;
+                let lowest = match self.direction {
+                    Direction::Receive => 0,
+                    Direction::Transmit => 1, // Acyclic synchronous not supported
+                };
+                if !(lowest..=SYNC_TYPE_MAX).contains(&transmission)
+                    && !EVENT_TYPES.contains(&transmission)
+                {
+                    return Err(SdoAbort::InvalidValue);
+                }
+                self.transmission = transmission;
+                self.syncs = 0;
+                Ok(())
+            }
+            5 => {
+                self.event_timer = value as u16;
+                Ok(())
+            }
+            _ => Err(SdoAbort::ReadOnly),
+        }
+    }
+
+    /// Reads a sub-index of the mapping
+    pub fn mapping_entry(&self, sub: u8) -> u32 {
+        match sub {
+            0 => self.mapped as u32,
+            _ => self.mapping[sub as usize - 1],
+        }
+    }
+
+    /// Writes a sub-index of the mapping, entries only while no object is mapped
+    pub fn set_mapping_entry(&mut self, sub: u8, value: u32) -> Result<(), SdoAbort> {
+        if sub == 0 {
+            let count = value as usize;
+            if count > MAX_MAPPED || self.mapping[..count].contains(&0) {
+                return Err(SdoAbort::InvalidValue);
+            }
+            let entries = self.mapping[..count].iter();
+            let bits: u32 = entries.map(|&entry| od::unmap(entry).2 as u32).sum();
+            if bits > MAX_BITS {
+                return Err(SdoAbort::MappingTooLong);
+            }
+            self.mapped = count as u8;
+            return Ok(());
+        }
+        if self.mapped != 0 {
+            return Err(SdoAbort::DeviceState);
+        }
+        let (index, object_sub, bits) = od::unmap(value);
+        let entry = od::entry(index, object_sub)?;
+        if !entry.mappable
+            || (self.direction == Direction::Receive && entry.access != Access::ReadWrite)
+        {
+            return Err(SdoAbort::NotMappable);
+        }
+        if bits != entry.size * 8 {
+            return Err(SdoAbort::LengthMismatch);
+        }
+        self.mapping[sub as usize - 1] = value;
+        Ok(())
+    }
+}
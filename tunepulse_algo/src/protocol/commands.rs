@@ -0,0 +1,660 @@
+// Implements decoding of host requests and encoding of their replies.
+
+// Key Features:
+// - Parameter read and write addressed by numeric parameter identifier.
+// - Staging of hot-tunable parameters applied together on command.
+// - Commands triggering actions such as calibration or sequence start.
+// - Status read for host tools and self-tests.
+// - Setpoint (mode and target) of normal operation, answered by the status.
+// - Device information read for compatibility checks.
+// - Readout of frequency response points.
+// - Readout of the position latched by the capture input.
+// - Streaming of the master position followed by the electronic gearing.
+// - Readout of the step response test results.
+// - Download and upload of the calibration table image in chunks.
+// - Readout of the production test report.
+// - Staging and readout of the factory data block.
+// - Readout of the calibration report.
+// - Detailed state read: calibration sub-stage, progress, energized windings, at target
+//   velocity and zero speed.
+// - Unlocking of the advanced and factory access levels by key.
+// - Motor type and phase connection swap of a disabled drive.
+// - Start of the setup wizard and readout of its results.
+// - Readout and clearing of the peak-hold registers.
+// - Time beacon answered by the tick counter (see `time_sync`).
+
+// Detailed Operation:
+// Every request is answered by exactly one reply frame, so hosts can match them in order.
+// Frame layouts:
+// - ParamRead:     [type, 0, id (u16 LE), 0, 0, 0, 0]
+// - ParamWrite:    [type, 0, id (u16 LE), value (u32 LE)]
+// - ParamStage:    [type, 0, id (u16 LE), value (u32 LE)]
+// - ParamValue:    [type, result, id (u16 LE), value (u32 LE)]
+// - Command:       [type, command, arg (u16 LE), arg (u32 LE)]
+//   - Unlock takes the key of the access level as u32 argument
+//   - ReconfigureMotor takes [motor type (u16 LE), phase pattern, 0] as u32 argument
+// - CommandResult: [type, result, command, 0, 0, 0, 0, 0]
+// - Setpoint:      [type, mode, 0, 0, value (i32 LE)], answered by Status
+//   - mode: 0 current (mA), 1 velocity (counts/s), 2 position (i16 rotations + u16 angle),
+//     3 voltage angle (angle u16 LE, amplitude i16 LE), CommandResult OutOfRange if unknown
+// - StatusRead:    [type, 0, 0, 0, 0, 0, 0, 0]
+// - Status:        [type, status, fault, flags, position (i32 LE)]
+//   - status: bits 0..6 driver status, bit 7 parameters changed since they were saved
+// - DeviceInfoRead: [type, page, 0, 0, 0, 0, 0, 0]
+// - DeviceInfo:    [type, page, payload (6 bytes)], page 0xFF if the requested page doesn't exist
+// - ResponseRead:  [type, index, page, 0, 0, 0, 0, 0]
+// - Response:      [type, index, page, payload (5 bytes)], page 0xFF if the point wasn't measured
+//   - page 0: [frequency (mHz, u24 LE), phase (i16 LE, 65536 per turn)]
+//   - page 1: [gain (i16.16, u32 LE), 0]
+// - CaptureRead:   [type, 0, 0, 0, 0, 0, 0, 0]
+// - Capture:       [type, flags, count (u16 LE), position (i32 LE)]
+//   - flags: bit 0 position valid, bit 1 armed
+// - MasterPosition: [type, 0, 0, 0, position (i32 LE)], answered by Status
+// - StepResultRead: [type, test, 0, 0, 0, 0, 0, 0]
+// - StepResult:    [type, test, rise (u16 LE, 0.1 ms), overshoot (u16 LE, ‰), error (i16 LE, ‰)]
+//   - test 0xFF if the test didn't finish, rise 0xFFFF if the response never reached 90 %
+// - TableRead:     [type, 0, offset (u16 LE), 0, 0, 0, 0]
+// - TableWrite:    [type, 0, offset (u16 LE), data (4 bytes)]
+// - TableData:     [type, result, offset (u16 LE), data (4 bytes)], answers both table requests
+// - ProductionResultRead: [type, metric, 0, 0, 0, 0, 0, 0]
+// - ProductionResult: [type, metric, flags, 0, value (i32 LE)]
+//   - metric 0xFF if there's no report or the metric doesn't exist
+//   - flags: bit 0 metric passed, bit 1 metric measured, bit 2 unit passed
+// - FactoryRead:   [type, page, 0, 0, 0, 0, 0, 0]
+// - FactoryWrite:  [type, page, payload (6 bytes)]
+// - FactoryData:   [type, page, payload (6 bytes)], answers both factory requests, page 0xFF
+//   if the page doesn't exist, no factory data is stored or staging is refused (also without
+//   the factory access level)
+// - CalibrationReportRead: [type, metric, 0, 0, 0, 0, 0, 0]
+// - CalibrationReport: [type, metric, flags, 0, value (i32 LE)]
+//   - metric 0xFF if the metric doesn't exist
+//   - flags: bit 0 metric measured
+// - WizardResultRead: [type, result, 0, 0, 0, 0, 0, 0]
+// - WizardResult:  [type, result, outcome, flags, value (i32 LE)]
+//   - result 0xFF if the result doesn't exist
+//   - outcome: `StepOutcome` of the step measuring the result
+//   - flags: bit 0 result measured
+// - PeakRead:      [type, value, flags, 0, 0, 0, 0, 0]
+//   - flags: bit 0 clear the register once read
+// - Peak:          [type, value, flags, 0, value (u32 LE)]
+//   - value 0xFF if the register doesn't exist
+//   - flags: bit 0 sampled since the register was cleared
+// - StateRead:     [type, 0, 0, 0, 0, 0, 0, 0]
+// - State:         [type, status, stage, progress (%), flags, 0, 0, 0]
+//   - status: driver status as in Status (without the unsaved bit)
+//   - stage: `CalibrationStage`, 0xFF unless calibrating
+//   - flags: bit 0 windings energized, bit 1 at target velocity, bit 2 zero speed
+// - TimeBeacon:    [type, seq, 0, 0, host time (u32 LE, ms)]
+// - TimeSync:      [type, seq, 0, 0, tick (u32 LE)], tick as in the telemetry points
+// `result` is a `ReplyResult` value.
+// Commands and requests of subsystems compiled out by the crate features aren't decoded, they
+// are answered as unknown ones.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::table_transfer::TABLE_CHUNK_SIZE;
+use super::{Frame, FrameType};
+use crate::device_info::DEVICE_INFO_PAGE_SIZE;
+#[cfg(feature = "production-test")]
+use crate::diagnostics::production_test::ProductionReport;
+#[cfg(feature = "tuning")]
+use crate::math_integer::signals::frequency_response::ResponsePoint;
+#[cfg(feature = "tuning")]
+use crate::math_integer::signals::step_response::StepResult;
+use crate::motor_driver::DriverState;
+use crate::params::ParamError;
+#[cfg(feature = "wizard")]
+use crate::wizard::StepOutcome;
+
+/// Commands executed on host request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Command {
+    /// Run full encoder calibration
+    Calibrate = 1,
+    /// Refine the zero electrical angle against the stored table
+    QuickRecalibrate = 2,
+    /// Start stored motion sequence
+    #[cfg(feature = "sequencer")]
+    StartSequence = 3,
+    /// Stop running motion sequence
+    #[cfg(feature = "sequencer")]
+    StopSequence = 4,
+    /// Enable drive output
+    Enable = 5,
+    /// Disable drive output
+    Disable = 6,
+    /// Apply staged hot-tunable parameters at the next control tick
+    ApplyStaged = 7,
+    /// Drop staged hot-tunable parameters
+    DiscardStaged = 8,
+    /// Start the test signal configured by the excitation parameters
+    #[cfg(feature = "tuning")]
+    StartExcitation = 9,
+    /// Stop the test signal and a running frequency response measurement
+    #[cfg(feature = "tuning")]
+    StopExcitation = 10,
+    /// Start the frequency response measurement configured by the excitation parameters
+    #[cfg(feature = "tuning")]
+    StartFrequencyResponse = 11,
+    /// Write the current parameters to non-volatile memory
+    SaveParams = 12,
+    /// Arm the position capture for the next edge
+    ArmCapture = 13,
+    /// Follow the master position through the electronic gearing
+    StartFollow = 14,
+    /// Stop following the master, the axis holds the last target
+    StopFollow = 15,
+    /// Re-arm collision detection and release the collision reaction
+    ClearCollision = 16,
+    /// Jog forward, repeat faster than the jog timeout while the motion is wanted
+    JogForward = 17,
+    /// Jog backward, repeat faster than the jog timeout while the motion is wanted
+    JogBackward = 18,
+    /// Stop jogging immediately
+    JogStop = 19,
+    /// Run the current and velocity step response tests
+    #[cfg(feature = "tuning")]
+    StartStepTest = 20,
+    /// Encode the calibration table into the transfer buffer for `TableRead`
+    ExportTable = 21,
+    /// Replace the calibration table by the image written with `TableWrite`
+    ImportTable = 22,
+    /// Replace the calibration table by a golden unit image and trim its offset to this unit
+    ImportGoldenTable = 23,
+    /// Run the production test: self-test, offset trim and metrics against the limits
+    #[cfg(feature = "production-test")]
+    StartProductionTest = 24,
+    /// Store the staged factory data, only once per unit, needs the factory access level
+    WriteFactoryData = 25,
+    /// Unlock the access level of the key given as argument
+    Unlock = 26,
+    /// Return to the user access level
+    Lock = 27,
+    /// Swap motor type and phase connection of the disabled drive and recalibrate
+    ReconfigureMotor = 28,
+    /// Run the setup wizard on the disabled drive
+    #[cfg(feature = "wizard")]
+    StartWizard = 29,
+    /// Abort the setup wizard
+    #[cfg(feature = "wizard")]
+    AbortWizard = 30,
+    /// Clear all peak-hold registers
+    ClearPeaks = 31,
+}
+
+impl Command {
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            1 => Some(Command::Calibrate),
+            2 => Some(Command::QuickRecalibrate),
+            #[cfg(feature = "sequencer")]
+            3 => Some(Command::StartSequence),
+            #[cfg(feature = "sequencer")]
+            4 => Some(Command::StopSequence),
+            5 => Some(Command::Enable),
+            6 => Some(Command::Disable),
+            7 => Some(Command::ApplyStaged),
+            8 => Some(Command::DiscardStaged),
+            #[cfg(feature = "tuning")]
+            9 => Some(Command::StartExcitation),
+            #[cfg(feature = "tuning")]
+            10 => Some(Command::StopExcitation),
+            #[cfg(feature = "tuning")]
+            11 => Some(Command::StartFrequencyResponse),
+            12 => Some(Command::SaveParams),
+            13 => Some(Command::ArmCapture),
+            14 => Some(Command::StartFollow),
+            15 => Some(Command::StopFollow),
+            16 => Some(Command::ClearCollision),
+            17 => Some(Command::JogForward),
+            18 => Some(Command::JogBackward),
+            19 => Some(Command::JogStop),
+            #[cfg(feature = "tuning")]
+            20 => Some(Command::StartStepTest),
+            21 => Some(Command::ExportTable),
+            22 => Some(Command::ImportTable),
+            23 => Some(Command::ImportGoldenTable),
+            #[cfg(feature = "production-test")]
+            24 => Some(Command::StartProductionTest),
+            25 => Some(Command::WriteFactoryData),
+            26 => Some(Command::Unlock),
+            27 => Some(Command::Lock),
+            28 => Some(Command::ReconfigureMotor),
+            #[cfg(feature = "wizard")]
+            29 => Some(Command::StartWizard),
+            #[cfg(feature = "wizard")]
+            30 => Some(Command::AbortWizard),
+            31 => Some(Command::ClearPeaks),
+            _ => None,
+        }
+    }
+}
+
+/// Result code reported in replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ReplyResult {
+    Ok = 0,
+    UnknownParam = 1,
+    OutOfRange = 2,
+    UnknownCommand = 3,
+    Rejected = 4,
+    InvalidFrame = 5,
+    AccessDenied = 6,
+}
+
+impl From<ParamError> for ReplyResult {
+    fn from(error: ParamError) -> Self {
+        match error {
+            ParamError::UnknownId => ReplyResult::UnknownParam,
+            ParamError::OutOfRange => ReplyResult::OutOfRange,
+            ParamError::NotTunable | ParamError::StageFull => ReplyResult::Rejected,
+            ParamError::Locked => ReplyResult::AccessDenied,
+        }
+    }
+}
+
+/// Decoded host request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    ParamRead { id: u16 },
+    ParamWrite { id: u16, value: u32 },
+    ParamStage { id: u16, value: u32 },
+    Command { command: u8, arg: u32 },
+    Setpoint { mode: u8, value: u32 },
+    StatusRead,
+    DeviceInfoRead { page: u8 },
+    #[cfg(feature = "tuning")]
+    ResponseRead { index: u8, page: u8 },
+    CaptureRead,
+    MasterPosition { position: i32 },
+    #[cfg(feature = "tuning")]
+    StepResultRead { test: u8 },
+    TableRead { offset: u16 },
+    TableWrite { offset: u16, data: u32 },
+    #[cfg(feature = "production-test")]
+    ProductionResultRead { metric: u8 },
+    FactoryRead { page: u8 },
+    FactoryWrite { page: u8, data: [u8; 6] },
+    CalibrationReportRead { metric: u8 },
+    #[cfg(feature = "wizard")]
+    WizardResultRead { result: u8 },
+    PeakRead { value: u8, clear: bool },
+    StateRead,
+    TimeBeacon { seq: u8, host_ms: u32 },
+}
+
+impl Request {
+    /// Decodes a request frame, `None` if the frame is not a request
+    pub fn decode(frame: &Frame) -> Option<Self> {
+        let id = u16::from_le_bytes([frame[2], frame[3]]);
+        let value = u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        match FrameType::from_raw(frame[0])? {
+            FrameType::ParamRead => Some(Request::ParamRead { id }),
+            FrameType::ParamWrite => Some(Request::ParamWrite { id, value }),
+            FrameType::ParamStage => Some(Request::ParamStage { id, value }),
+            FrameType::Command => Some(Request::Command {
+                command: frame[1],
+                arg: value,
+            }),
+            FrameType::Setpoint => Some(Request::Setpoint {
+                mode: frame[1],
+                value,
+            }),
+            FrameType::StatusRead => Some(Request::StatusRead),
+            FrameType::DeviceInfoRead => Some(Request::DeviceInfoRead { page: frame[1] }),
+            #[cfg(feature = "tuning")]
+            FrameType::ResponseRead => Some(Request::ResponseRead {
+                index: frame[1],
+                page: frame[2],
+            }),
+            FrameType::CaptureRead => Some(Request::CaptureRead),
+            FrameType::MasterPosition => Some(Request::MasterPosition {
+                position: value as i32,
+            }),
+            #[cfg(feature = "tuning")]
+            FrameType::StepResultRead => Some(Request::StepResultRead { test: frame[1] }),
+            FrameType::TableRead => Some(Request::TableRead { offset: id }),
+            FrameType::TableWrite => Some(Request::TableWrite {
+                offset: id,
+                data: value,
+            }),
+            #[cfg(feature = "production-test")]
+            FrameType::ProductionResultRead => {
+                Some(Request::ProductionResultRead { metric: frame[1] })
+            }
+            FrameType::FactoryRead => Some(Request::FactoryRead { page: frame[1] }),
+            FrameType::FactoryWrite => {
+                let mut data = [0; DEVICE_INFO_PAGE_SIZE];
+                data.copy_from_slice(&frame[2..]);
+                Some(Request::FactoryWrite {
+                    page: frame[1],
+                    data,
+                })
+            }
+            FrameType::CalibrationReportRead => {
+                Some(Request::CalibrationReportRead { metric: frame[1] })
+            }
+            #[cfg(feature = "wizard")]
+            FrameType::WizardResultRead => Some(Request::WizardResultRead { result: frame[1] }),
+            FrameType::PeakRead => Some(Request::PeakRead {
+                value: frame[1],
+                clear: frame[2] & PEAK_READ_CLEAR != 0,
+            }),
+            FrameType::StateRead => Some(Request::StateRead),
+            FrameType::TimeBeacon => Some(Request::TimeBeacon {
+                seq: frame[1],
+                host_ms: value,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes a parameter reply
+pub fn param_reply(id: u16, result: Result<u32, ParamError>) -> Frame {
+    let (code, value) = match result {
+        Ok(value) => (ReplyResult::Ok, value),
+        Err(error) => (error.into(), 0),
+    };
+    let id = id.to_le_bytes();
+    let value = value.to_le_bytes();
+    [
+        FrameType::ParamValue as u8,
+        code as u8,
+        id[0],
+        id[1],
+        value[0],
+        value[1],
+        value[2],
+        value[3],
+    ]
+}
+
+/// Encodes a command reply
+pub fn command_reply(command: u8, result: ReplyResult) -> Frame {
+    [
+        FrameType::CommandResult as u8,
+        result as u8,
+        command,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]
+}
+
+/// Status flags reported in the status reply
+pub const STATUS_ENABLED: u8 = 1 << 0;
+pub const STATUS_IN_POSITION: u8 = 1 << 1;
+pub const STATUS_STANDSTILL: u8 = 1 << 2;
+pub const STATUS_SEQUENCE: u8 = 1 << 3;
+pub const STATUS_EXCITATION: u8 = 1 << 4;
+pub const STATUS_MEASURING: u8 = 1 << 5;
+pub const STATUS_FOLLOWING: u8 = 1 << 6;
+pub const STATUS_COLLISION: u8 = 1 << 7;
+
+/// Bit of the status byte (not `flags`) set while the parameters differ from the saved set
+pub const DRIVER_STATUS_UNSAVED: u8 = 1 << 7;
+
+/// Encodes a status reply
+pub fn status_reply(status: u8, fault: u8, flags: u8, position: i32) -> Frame {
+    let position = position.to_le_bytes();
+    [
+        FrameType::Status as u8,
+        status,
+        fault,
+        flags,
+        position[0],
+        position[1],
+        position[2],
+        position[3],
+    ]
+}
+
+/// Page number reported for a device information page that doesn't exist
+pub const DEVICE_INFO_INVALID_PAGE: u8 = 0xFF;
+
+/// Encodes a device information reply
+pub fn device_info_reply(page: u8, payload: Option<[u8; DEVICE_INFO_PAGE_SIZE]>) -> Frame {
+    let (page, payload) = match payload {
+        Some(payload) => (page, payload),
+        None => (DEVICE_INFO_INVALID_PAGE, [0; DEVICE_INFO_PAGE_SIZE]),
+    };
+    let mut frame = [FrameType::DeviceInfo as u8, page, 0, 0, 0, 0, 0, 0];
+    frame[2..].copy_from_slice(&payload);
+    frame
+}
+
+/// Page number reported for a frequency response point that wasn't measured
+pub const RESPONSE_INVALID_PAGE: u8 = 0xFF;
+
+/// Encodes a frequency response reply
+#[cfg(feature = "tuning")]
+pub fn response_reply(index: u8, page: u8, point: Option<ResponsePoint>) -> Frame {
+    let mut frame = [FrameType::Response as u8, index, page, 0, 0, 0, 0, 0];
+    match (point, page) {
+        (Some(point), 0) => {
+            frame[3..6].copy_from_slice(&point.frequency_mhz.to_le_bytes()[..3]);
+            frame[6..8].copy_from_slice(&point.phase.to_le_bytes());
+        }
+        (Some(point), 1) => frame[3..7].copy_from_slice(&point.gain.to_le_bytes()),
+        _ => frame[2] = RESPONSE_INVALID_PAGE,
+    }
+    frame
+}
+
+/// Capture flags reported in the capture reply
+pub const CAPTURE_VALID: u8 = 1 << 0;
+pub const CAPTURE_ARMED: u8 = 1 << 1;
+
+/// Encodes a capture reply
+pub fn capture_reply(position: Option<i32>, armed: bool, count: u16) -> Frame {
+    let mut flags = 0;
+    if position.is_some() {
+        flags |= CAPTURE_VALID;
+    }
+    if armed {
+        flags |= CAPTURE_ARMED;
+    }
+    let count = count.to_le_bytes();
+    let position = position.unwrap_or(0).to_le_bytes();
+    [
+        FrameType::Capture as u8,
+        flags,
+        count[0],
+        count[1],
+        position[0],
+        position[1],
+        position[2],
+        position[3],
+    ]
+}
+
+/// Test number reported for a step test that didn't finish
+pub const STEP_INVALID_TEST: u8 = 0xFF;
+
+/// Rise time reported if the response never reached 90 % of the step
+pub const STEP_NOT_RISEN: u16 = 0xFFFF;
+
+/// Encodes a step response test reply
+#[cfg(feature = "tuning")]
+pub fn step_reply(test: u8, result: Option<StepResult>) -> Frame {
+    let mut frame = [FrameType::StepResult as u8, test, 0, 0, 0, 0, 0, 0];
+    let Some(result) = result else {
+        frame[1] = STEP_INVALID_TEST;
+        return frame;
+    };
+    let rise = result.rise_us.map_or(STEP_NOT_RISEN, |rise| {
+        (rise / 100).min(STEP_NOT_RISEN as u32 - 1) as u16
+    });
+    let overshoot = result.overshoot.min(u16::MAX as u32) as u16;
+    let error = result.error.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    frame[2..4].copy_from_slice(&rise.to_le_bytes());
+    frame[4..6].copy_from_slice(&overshoot.to_le_bytes());
+    frame[6..8].copy_from_slice(&error.to_le_bytes());
+    frame
+}
+
+/// Encodes a table chunk reply, `None` data reports an invalid offset
+pub fn table_reply(offset: u16, data: Option<[u8; TABLE_CHUNK_SIZE]>) -> Frame {
+    let result = match data {
+        Some(_) => ReplyResult::Ok,
+        None => ReplyResult::OutOfRange,
+    };
+    let offset = offset.to_le_bytes();
+    let data = data.unwrap_or_default();
+    [
+        FrameType::TableData as u8,
+        result as u8,
+        offset[0],
+        offset[1],
+        data[0],
+        data[1],
+        data[2],
+        data[3],
+    ]
+}
+
+/// Metric number reported if there's no report or the metric doesn't exist
+pub const PRODUCTION_INVALID_METRIC: u8 = 0xFF;
+
+/// Production result flags reported in the production result reply
+pub const PRODUCTION_METRIC_PASSED: u8 = 1 << 0;
+pub const PRODUCTION_METRIC_MEASURED: u8 = 1 << 1;
+pub const PRODUCTION_UNIT_PASSED: u8 = 1 << 2;
+
+/// Encodes a production test metric reply
+#[cfg(feature = "production-test")]
+pub fn production_reply(metric: u8, report: Option<&ProductionReport>) -> Frame {
+    let mut frame = [FrameType::ProductionResult as u8, metric, 0, 0, 0, 0, 0, 0];
+    let Some((report, result)) =
+        report.and_then(|report| Some((report, report.metrics.get(metric as usize)?)))
+    else {
+        frame[1] = PRODUCTION_INVALID_METRIC;
+        return frame;
+    };
+    if result.passed {
+        frame[2] |= PRODUCTION_METRIC_PASSED;
+    }
+    if result.value.is_some() {
+        frame[2] |= PRODUCTION_METRIC_MEASURED;
+    }
+    if report.passed() {
+        frame[2] |= PRODUCTION_UNIT_PASSED;
+    }
+    frame[4..8].copy_from_slice(&result.value.unwrap_or(0).to_le_bytes());
+    frame
+}
+
+/// Page number reported for a factory data page that doesn't exist or isn't available
+pub const FACTORY_INVALID_PAGE: u8 = 0xFF;
+
+/// Encodes a factory data reply
+pub fn factory_reply(page: u8, payload: Option<[u8; DEVICE_INFO_PAGE_SIZE]>) -> Frame {
+    let (page, payload) = match payload {
+        Some(payload) => (page, payload),
+        None => (FACTORY_INVALID_PAGE, [0; DEVICE_INFO_PAGE_SIZE]),
+    };
+    let mut frame = [FrameType::FactoryData as u8, page, 0, 0, 0, 0, 0, 0];
+    frame[2..].copy_from_slice(&payload);
+    frame
+}
+
+/// Metric number reported for a calibration metric that doesn't exist
+pub const CALIBRATION_INVALID_METRIC: u8 = 0xFF;
+
+/// Calibration report flag: the metric was measured
+pub const CALIBRATION_METRIC_MEASURED: u8 = 1 << 0;
+
+/// Encodes a calibration report reply, `None` metric reports a metric that doesn't exist
+pub fn calibration_reply(metric: u8, value: Option<Option<i32>>) -> Frame {
+    let mut frame = [FrameType::CalibrationReport as u8, metric, 0, 0, 0, 0, 0, 0];
+    let Some(value) = value else {
+        frame[1] = CALIBRATION_INVALID_METRIC;
+        return frame;
+    };
+    if value.is_some() {
+        frame[2] |= CALIBRATION_METRIC_MEASURED;
+    }
+    frame[4..8].copy_from_slice(&value.unwrap_or(0).to_le_bytes());
+    frame
+}
+
+/// Result number reported for a wizard result that doesn't exist
+pub const WIZARD_INVALID_RESULT: u8 = 0xFF;
+
+/// Wizard result flag: the result was measured
+pub const WIZARD_RESULT_MEASURED: u8 = 1 << 0;
+
+/// Encodes a wizard result reply, `None` reports a result that doesn't exist
+#[cfg(feature = "wizard")]
+pub fn wizard_reply(result: u8, value: Option<(StepOutcome, Option<i32>)>) -> Frame {
+    let mut frame = [FrameType::WizardResult as u8, result, 0, 0, 0, 0, 0, 0];
+    let Some((outcome, value)) = value else {
+        frame[1] = WIZARD_INVALID_RESULT;
+        return frame;
+    };
+    frame[2] = outcome as u8;
+    if value.is_some() {
+        frame[3] |= WIZARD_RESULT_MEASURED;
+    }
+    frame[4..8].copy_from_slice(&value.unwrap_or(0).to_le_bytes());
+    frame
+}
+
+/// Peak read flag: clear the register once read
+pub const PEAK_READ_CLEAR: u8 = 1 << 0;
+
+/// Value number reported for a peak-hold register that doesn't exist
+pub const PEAK_INVALID_VALUE: u8 = 0xFF;
+
+/// Peak reply flag: the register was sampled since it was cleared
+pub const PEAK_SAMPLED: u8 = 1 << 0;
+
+/// Encodes a peak-hold reply, `None` reports a register that doesn't exist
+pub fn peak_reply(value: u8, peak: Option<Option<u32>>) -> Frame {
+    let mut frame = [FrameType::Peak as u8, value, 0, 0, 0, 0, 0, 0];
+    let Some(peak) = peak else {
+        frame[1] = PEAK_INVALID_VALUE;
+        return frame;
+    };
+    if peak.is_some() {
+        frame[2] |= PEAK_SAMPLED;
+    }
+    frame[4..8].copy_from_slice(&peak.unwrap_or(0).to_le_bytes());
+    frame
+}
+
+/// Stage reported while no calibration is running
+pub const STATE_NO_STAGE: u8 = 0xFF;
+
+/// State flag: the bridge drives current through the windings
+pub const STATE_ENERGIZED: u8 = 1 << 0;
+/// State flag: the speed settled within the velocity window of the velocity setpoint
+pub const STATE_AT_VELOCITY: u8 = 1 << 1;
+/// State flag: the speed settled below the zero speed threshold
+pub const STATE_ZERO_SPEED: u8 = 1 << 2;
+
+/// Encodes a detailed state reply
+pub fn state_reply(state: &DriverState) -> Frame {
+    let mut frame = [FrameType::State as u8, 0, STATE_NO_STAGE, 0, 0, 0, 0, 0];
+    frame[1] = state.status as u8;
+    if let Some(stage) = state.stage {
+        frame[2] = stage as u8;
+    }
+    frame[3] = state.progress;
+    if state.energized {
+        frame[4] |= STATE_ENERGIZED;
+    }
+    if state.at_velocity {
+        frame[4] |= STATE_AT_VELOCITY;
+    }
+    if state.zero_speed {
+        frame[4] |= STATE_ZERO_SPEED;
+    }
+    frame
+}
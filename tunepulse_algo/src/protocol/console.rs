@@ -0,0 +1,386 @@
+// Implements the line-based text console of the drive: short commands typed in a terminal on a
+// virtual serial port (USB CDC-ACM), each answered with one line of text.
+
+// Key Features:
+// - Line assembly from a byte stream: CR, LF or CRLF end a line, backspace edits it.
+// - Commands for status, parameters by name, position loop gains, motor type, enabling,
+//   calibration and saving the parameters.
+// - Numbers in decimal or hexadecimal (0x prefix), negative values for signed parameters.
+
+// Detailed Operation:
+// `LineBuffer` collects the printable ASCII characters of a line and hands the line over once
+// it ends, a line longer than `LINE_SIZE` is refused as a whole. `ConsoleCommand::parse()`
+// splits the line at whitespace, the first word selects the command, case is ignored, and
+// resolves parameter names. The controller executes the command and returns a
+// `ConsoleReply`, `write_reply()` answers `ok`, the requested values as `name=value` pairs or
+// `error: ` with the reason. Parsing and answering need no controller, a link can do both
+// outside of the control context and only hand the command over. Names of status, faults and
+// reply results come from the code tables shared with the host tools. Parameters are written
+// with the same checks as over the binary protocol (range, access level, hot-tunable while
+// running).
+// Commands:
+// - `status`: driver status, fault, enable, position, speed, at-velocity and zero-speed flags
+//   and supply voltage.
+// - `get <param>`, `set <param> <value>`: parameter by the name of the parameter table.
+// - `pid`, `pid <kp> <ki> <kd> [limit_ma]`: gains of the position loop, not stored. The limit
+//   needs the advanced access level and is clamped to the current limit of the motor.
+// - `motor`, `motor <dc|bldc|step>`: motor type, changed with the drive disabled.
+// - `enable`, `disable`, `calibrate`, `save`, `help`.
+// The console doesn't echo, terminals have to echo locally.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::fmt::{self, Write};
+
+use crate::fault::FaultCode;
+use crate::motor_driver::{DriverStatus, MotorType, ReconfigError};
+use crate::params::{self, ParamId, ParamType, PARAMS};
+use tunepulse_params::codes;
+
+/// Longest command line (characters)
+pub const LINE_SIZE: usize = 80;
+
+/// Line ending of the answers
+pub const EOL: &str = "\r\n";
+
+/// Answer of the `help` command
+pub const HELP: &str = "commands: status | get <param> | set <param> <value> | \
+                        pid [<kp> <ki> <kd> [limit_ma]] | motor [dc|bldc|step] | enable | \
+                        disable | calibrate | save | help";
+
+/// Reason a line isn't a valid command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleError {
+    /// Line longer than `LINE_SIZE`
+    TooLong,
+    /// First word names no command
+    UnknownCommand,
+    /// Missing, surplus or malformed argument
+    InvalidArgument,
+    /// No parameter with the given name
+    UnknownParam,
+}
+
+impl ConsoleError {
+    pub fn description(self) -> &'static str {
+        match self {
+            ConsoleError::TooLong => "line too long",
+            ConsoleError::UnknownCommand => "unknown command, try help",
+            ConsoleError::InvalidArgument => "invalid argument",
+            ConsoleError::UnknownParam => "unknown parameter",
+        }
+    }
+}
+
+/// Gains of the `pid` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PidGains {
+    pub kp: i32,            // Proportional gain (percent)
+    pub ki: i32,            // Integral gain (percent)
+    pub kd: i32,            // Derivative gain (percent)
+    pub limit: Option<i16>, // Maximum torque command (mA), `None` keeps the current one
+}
+
+/// Command of a console line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    Help,
+    Status,
+    /// Read a parameter
+    Get(ParamId),
+    /// Write a parameter with the raw value
+    Set(ParamId, u32),
+    /// Read (`None`) or change the position loop gains
+    Pid(Option<PidGains>),
+    /// Read (`None`) or change the motor type
+    Motor(Option<MotorType>),
+    Enable,
+    Disable,
+    Calibrate,
+    Save,
+}
+
+/// Command words
+#[derive(Clone, Copy)]
+enum Keyword {
+    Help,
+    Status,
+    Get,
+    Set,
+    Pid,
+    Motor,
+    Enable,
+    Disable,
+    Calibrate,
+    Save,
+}
+
+const KEYWORDS: [(&str, Keyword); 10] = [
+    ("help", Keyword::Help),
+    ("status", Keyword::Status),
+    ("get", Keyword::Get),
+    ("set", Keyword::Set),
+    ("pid", Keyword::Pid),
+    ("motor", Keyword::Motor),
+    ("enable", Keyword::Enable),
+    ("disable", Keyword::Disable),
+    ("calibrate", Keyword::Calibrate),
+    ("save", Keyword::Save),
+];
+
+/// Motor types by their console name
+const MOTOR_TYPES: [(&str, MotorType); 3] = [
+    ("dc", MotorType::DC),
+    ("bldc", MotorType::BLDC),
+    ("step", MotorType::STEP),
+];
+
+impl ConsoleCommand {
+    /// Parses a command line.
+    ///
+    /// # Arguments
+    /// * `line` - Line without its line ending
+    pub fn parse(line: &str) -> Result<Self, ConsoleError> {
+        let mut words = line.split_ascii_whitespace();
+        let keyword = words.next().unwrap_or("");
+        let keyword = KEYWORDS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(keyword))
+            .map(|&(_, keyword)| keyword)
+            .ok_or(ConsoleError::UnknownCommand)?;
+        let mut args = [""; 4];
+        let mut count = 0;
+        for word in words {
+            if count == args.len() {
+                return Err(ConsoleError::InvalidArgument);
+            }
+            args[count] = word;
+            count += 1;
+        }
+        let args = &args[..count];
+
+        let command = match (keyword, args) {
+            (Keyword::Help, []) => ConsoleCommand::Help,
+            (Keyword::Status, []) => ConsoleCommand::Status,
+            (Keyword::Get, [name]) => ConsoleCommand::Get(param(name)?),
+            (Keyword::Set, [name, value]) => {
+                let value = parse_number(value).ok_or(ConsoleError::InvalidArgument)?;
+                // Negative values are stored in two's complement
+                if value < i32::MIN as i64 || value > u32::MAX as i64 {
+                    return Err(ConsoleError::InvalidArgument);
+                }
+                ConsoleCommand::Set(param(name)?, value as u32)
+            }
+            (Keyword::Pid, []) => ConsoleCommand::Pid(None),
+            (Keyword::Pid, [kp, ki, kd, limit @ ..]) if limit.len() <= 1 => {
+                let gain = |word: &str| {
+                    parse_number(word)
+                        .and_then(|value| i32::try_from(value).ok())
+                        .ok_or(ConsoleError::InvalidArgument)
+                };
+                let limit = match limit.first() {
+                    Some(word) => Some(
+                        parse_number(word)
+                            .and_then(|value| i16::try_from(value).ok())
+                            .ok_or(ConsoleError::InvalidArgument)?,
+                    ),
+                    None => None,
+                };
+                ConsoleCommand::Pid(Some(PidGains {
+                    kp: gain(kp)?,
+                    ki: gain(ki)?,
+                    kd: gain(kd)?,
+                    limit,
+                }))
+            }
+            (Keyword::Motor, []) => ConsoleCommand::Motor(None),
+            (Keyword::Motor, [name]) => {
+                let motor = MOTOR_TYPES
+                    .iter()
+                    .find(|(motor, _)| motor.eq_ignore_ascii_case(name))
+                    .map(|&(_, motor)| motor)
+                    .ok_or(ConsoleError::InvalidArgument)?;
+                ConsoleCommand::Motor(Some(motor))
+            }
+            (Keyword::Enable, []) => ConsoleCommand::Enable,
+            (Keyword::Disable, []) => ConsoleCommand::Disable,
+            (Keyword::Calibrate, []) => ConsoleCommand::Calibrate,
+            (Keyword::Save, []) => ConsoleCommand::Save,
+            _ => return Err(ConsoleError::InvalidArgument),
+        };
+        Ok(command)
+    }
+}
+
+/// Looks up a parameter by the name of the parameter table
+fn param(name: &str) -> Result<ParamId, ConsoleError> {
+    params::find_by_name(name)
+        .map(|def| def.id)
+        .ok_or(ConsoleError::UnknownParam)
+}
+
+/// Values of the `status` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsoleStatus {
+    pub status: DriverStatus, // Operating state of the driver
+    pub fault: FaultCode,     // Reason of the Error status
+    pub enabled: bool,        // Drive output enabled
+    pub position: i32,        // Actual position (counts)
+    pub speed: i32,           // Speed in the configured unit
+    pub at_velocity: bool,    // Speed within the window of the velocity setpoint
+    pub zero_speed: bool,     // Speed within the zero speed window
+    pub supply_mv: i32,       // Supply voltage (mV)
+}
+
+/// Answer of an executed console command, written by `write_reply()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleReply {
+    Help,
+    Status(ConsoleStatus),
+    /// Raw value of a parameter
+    Param(ParamId, u32),
+    /// Position loop gains with the torque limit
+    Pid(PidGains),
+    Motor(MotorType),
+    /// `ok` or the reason the command was refused
+    Done(Result<(), &'static str>),
+}
+
+/// Writes the answer line of an executed command
+pub fn write_reply<W: Write>(out: &mut W, reply: &ConsoleReply) -> fmt::Result {
+    match *reply {
+        ConsoleReply::Help => write!(out, "{}{}", HELP, EOL),
+        ConsoleReply::Status(status) => write!(
+            out,
+            "status={} fault={} enabled={} position={} speed={} at_velocity={} zero_speed={} \
+             supply_mv={}{}",
+            codes::DRIVER_STATUS[status.status as usize].name,
+            codes::FAULT_CODES[status.fault as usize].name,
+            status.enabled as u8,
+            status.position,
+            status.speed,
+            status.at_velocity as u8,
+            status.zero_speed as u8,
+            status.supply_mv,
+            EOL
+        ),
+        ConsoleReply::Param(id, value) => {
+            let def = &PARAMS[id as usize];
+            match def.kind {
+                ParamType::Signed => write!(out, "{}={}{}", def.name, value as i32, EOL),
+                _ => write!(out, "{}={}{}", def.name, value, EOL),
+            }
+        }
+        ConsoleReply::Pid(gains) => write!(
+            out,
+            "kp={} ki={} kd={} limit_ma={}{}",
+            gains.kp,
+            gains.ki,
+            gains.kd,
+            gains.limit.unwrap_or(0),
+            EOL
+        ),
+        ConsoleReply::Motor(motor) => write!(out, "motor={}{}", motor_name(motor), EOL),
+        ConsoleReply::Done(result) => write_result(out, result),
+    }
+}
+
+/// Console name of a motor type
+pub fn motor_name(motor: MotorType) -> &'static str {
+    MOTOR_TYPES
+        .iter()
+        .find(|&&(_, known)| known == motor)
+        .map_or("undefined", |&(name, _)| name)
+}
+
+/// Reason a motor change was refused
+pub fn reconfig_reason(error: ReconfigError) -> &'static str {
+    match error {
+        ReconfigError::Enabled => "drive enabled, disable it first",
+        ReconfigError::Energized => "windings still energized",
+        ReconfigError::Busy => "test or identification running",
+    }
+}
+
+/// Writes `ok` or the error line of a refused command
+pub fn write_result<W: Write>(out: &mut W, result: Result<(), &str>) -> fmt::Result {
+    match result {
+        Ok(()) => write!(out, "ok{}", EOL),
+        Err(reason) => write!(out, "error: {}{}", reason, EOL),
+    }
+}
+
+/// Parses a decimal or hexadecimal (0x prefix) number, optionally negative
+fn parse_number(word: &str) -> Option<i64> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Assembles command lines from received bytes.
+pub struct LineBuffer {
+    line: [u8; LINE_SIZE], // Characters of the current line
+    len: usize,            // Number of characters
+    overflow: bool,        // Line exceeded `LINE_SIZE`, refused once it ends
+}
+
+impl LineBuffer {
+    pub const fn new() -> Self {
+        Self {
+            line: [0; LINE_SIZE],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Adds a received byte, returns the line once it ended, `None` while it continues or for
+    /// an empty line.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, ConsoleError>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let (len, overflow) = (self.len, self.overflow);
+                self.len = 0;
+                self.overflow = false;
+                if overflow {
+                    return Some(Err(ConsoleError::TooLong));
+                }
+                // Only printable ASCII is stored, always valid UTF-8
+                let line = core::str::from_utf8(&self.line[..len]).ok()?.trim();
+                (!line.is_empty()).then_some(Ok(line))
+            }
+            0x08 | 0x7F => {
+                self.len = self.len.saturating_sub(1);
+                None
+            }
+            0x20..=0x7E if self.len < LINE_SIZE => {
+                self.line[self.len] = byte;
+                self.len += 1;
+                None
+            }
+            0x20..=0x7E => {
+                self.overflow = true;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,170 @@
+// Implements asynchronous motion event notifications pushed to the host.
+
+// Key Features:
+// - Events for target reached, homing complete, fault raised, limit hit, calibration done,
+//   position captured, collision detected, jog aborted
+//   step test done, production test done, unsaved parameter changes, encoder degraded
+//   operation, calibration load warning, setup wizard progress and failed flash writes.
+// - Subscription mask selecting which events are pushed.
+// - Fixed size queue decoupling the control loop from the transport.
+
+// Detailed Operation:
+// The control loop raises events with `push()`. Events not enabled in the subscription mask
+// are dropped immediately, others are stored in a circular queue together with a 32-bit
+// argument and a sequence number. A lower priority task calls `flush()` which encodes queued
+// events into frames and hands them to the transport until the queue is empty or the link is
+// busy. If the queue overflows the oldest event is dropped and the overflow counter incremented,
+// the host can detect lost events by gaps in the sequence numbers.
+
+// Frame layout: [FrameType::Event, event, seq (u16 LE), arg (u32 LE)]
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{Frame, FrameType, Transport};
+
+/// Motion events pushed to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MotionEvent {
+    /// Position settled within in-position window, arg: position
+    TargetReached = 0,
+    /// Homing sequence finished, arg: home position
+    HomingComplete = 1,
+    /// Fault was raised, arg: fault code
+    FaultRaised = 2,
+    /// Limit switch or soft limit hit, arg: position
+    LimitHit = 3,
+    /// Calibration finished successfully, arg: unused
+    CalibrationDone = 4,
+    /// Capture input latched the position, arg: captured position
+    PositionCaptured = 5,
+    /// Load torque step detected as collision, arg: load torque step (mA, i32)
+    CollisionDetected = 6,
+    /// Jog stopped at the torque limit, arg: requested torque (mA, i32)
+    JogAborted = 7,
+    /// Step response test finished, arg: 0 - completed, 1 - aborted
+    StepTestDone = 8,
+    /// Production test finished, arg: failed metric mask (0 - unit passed)
+    ProductionTestDone = 9,
+    /// Drive disabled or supply lost with parameters changed since the last save,
+    /// arg: CRC of the live parameters
+    UnsavedChanges = 10,
+    /// EMC profile started bridging rejected encoder samples, arg: rejected samples so far
+    EncoderDegraded = 11,
+    /// Encoder samples accepted again for the recovery time, arg: rejected samples so far
+    EncoderRecovered = 12,
+    /// Calibration finished on a lightly loaded axis, arg: load check flags
+    CalibrationLoadWarning = 13,
+    /// Setup wizard step started or finished, arg: step (bits 0..7), outcome (bits 8..15)
+    WizardProgress = 14,
+    /// Flash write failed, arg: area (bits 0..7: 0 - parameters, 1 - factory data), flash
+    /// error code (bits 8..15)
+    FlashWriteFailed = 15,
+    /// Load angle reached the stall threshold close to pull-out, arg: filtered load angle (i16,
+    /// 65536 per 360° el)
+    StallWarning = 16,
+}
+
+impl MotionEvent {
+    /// Bit of the event in the subscription mask
+    pub const fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Queue of pending motion events with subscription mask.
+pub struct EventQueue<const N: usize> {
+    queue: [(MotionEvent, u32, u16); N], // Pending events with argument and sequence number
+    head: usize,                         // Index of the oldest event
+    len: usize,                          // Number of pending events
+    seq: u16,                            // Sequence number of the next event
+    mask: u32,                           // Subscription mask
+    overflows: u32,                      // Number of dropped events
+}
+
+impl<const N: usize> EventQueue<N> {
+    /// Creates an empty queue.
+    ///
+    /// # Arguments
+    /// * `mask` - Subscription mask, bit per `MotionEvent`
+    pub const fn new(mask: u32) -> Self {
+        Self {
+            queue: [(MotionEvent::TargetReached, 0, 0); N],
+            head: 0,
+            len: 0,
+            seq: 0,
+            mask,
+            overflows: 0,
+        }
+    }
+
+    /// Raises an event, dropped if not subscribed.
+    pub fn push(&mut self, event: MotionEvent, arg: u32) {
+        if self.mask & event.mask() == 0 {
+            return;
+        }
+        if self.len == N {
+            // Drop the oldest event to keep the latest state
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+        let idx = (self.head + self.len) % N;
+        self.queue[idx] = (event, arg, self.seq);
+        self.seq = self.seq.wrapping_add(1);
+        self.len += 1;
+    }
+
+    /// Sends pending events over the transport, returns number of sent events.
+    pub fn flush<T: Transport>(&mut self, transport: &mut T) -> usize {
+        let mut sent = 0;
+        while self.len > 0 {
+            let frame = Self::encode(self.queue[self.head]);
+            if !transport.send(&frame) {
+                break; // Link busy, retry on next flush
+            }
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Encodes an event into a protocol frame
+    fn encode(entry: (MotionEvent, u32, u16)) -> Frame {
+        let (event, arg, seq) = entry;
+        let seq = seq.to_le_bytes();
+        let arg = arg.to_le_bytes();
+        [
+            FrameType::Event as u8,
+            event as u8,
+            seq[0],
+            seq[1],
+            arg[0],
+            arg[1],
+            arg[2],
+            arg[3],
+        ]
+    }
+
+    /// Changes subscription mask
+    pub fn set_mask(&mut self, mask: u32) {
+        self.mask = mask;
+    }
+
+    /// Number of pending events
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no events are pending
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of events dropped due to queue overflow
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+}
@@ -0,0 +1,232 @@
+// Implements the transport independent part of the host communication protocol.
+
+// Key Features:
+// - Fixed 8 byte frames fitting a classic CAN frame and cheap to send over UART.
+// - `Transport` trait implemented by the physical links (CAN, UART, ...).
+// - Mapping of the frames onto CAN identifiers of a node (see `can`).
+// - CANopen slave with the CiA 402 drive profile as alternative on CAN (see `canopen`).
+// - Line-based text console for terminals on a virtual serial port (see `console`).
+// - Numeric codes only, their names are kept by the host tools.
+
+// Detailed Operation:
+// Every frame starts with a frame type byte followed by type specific payload. Frames are
+// built by the protocol modules and handed to a `Transport`, which reports whether the frame
+// could be queued. Frames which couldn't be sent are kept by their producer and retried later.
+// Byte stream links (UART, USB CDC) carry telemetry points interleaved with frames, every
+// point is prefixed by `TELEMETRY_SYNC`, a value no frame type uses.
+// Status, faults, events, results and report fields are sent as numbers, the firmware carries
+// no text for the host. The names, units and descriptions of every code are tabulated in
+// `tunepulse_params::codes`, which the host tools use; the tables are checked against the
+// enums of the firmware at compile time so a new code can't go unnamed. Only the text console
+// names the codes on the device, with the same tables.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#[cfg(feature = "can")]
+pub mod can;
+#[cfg(feature = "can")]
+pub mod canopen;
+pub mod commands;
+#[cfg(feature = "console")]
+pub mod console;
+pub mod events;
+pub mod odometry;
+pub mod status_report;
+pub mod table_transfer;
+pub mod time_sync;
+
+/// Protocol revision, incremented on incompatible frame layout changes
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Size of a protocol frame in bytes
+pub const FRAME_SIZE: usize = 8;
+
+/// Protocol frame
+pub type Frame = [u8; FRAME_SIZE];
+
+/// Prefix of a telemetry point on byte stream links
+pub const TELEMETRY_SYNC: u8 = 0xA5;
+
+/// Frame type identifiers (first byte of a frame).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    /// Host request: read parameter
+    ParamRead = 0x10,
+    /// Host request: write parameter
+    ParamWrite = 0x11,
+    /// Reply: parameter value or access error
+    ParamValue = 0x12,
+    /// Host request: stage hot-tunable parameter
+    ParamStage = 0x13,
+    /// Host request: execute command
+    Command = 0x20,
+    /// Reply: command result
+    CommandResult = 0x21,
+    /// Host request: set the command followed in normal operation
+    Setpoint = 0x22,
+    /// Host request: read status
+    StatusRead = 0x30,
+    /// Reply: driver status
+    Status = 0x31,
+    /// Host request: read device information page
+    DeviceInfoRead = 0x40,
+    /// Reply: device information page
+    DeviceInfo = 0x41,
+    /// Host request: read frequency response point
+    ResponseRead = 0x50,
+    /// Reply: frequency response point
+    Response = 0x51,
+    /// Host request: read captured position
+    CaptureRead = 0x60,
+    /// Reply: captured position
+    Capture = 0x61,
+    /// Host request: master position for electronic gearing
+    MasterPosition = 0x70,
+    /// Host request: read step response test result
+    StepResultRead = 0x80,
+    /// Reply: step response test result
+    StepResult = 0x81,
+    /// Host request: read chunk of the table image
+    TableRead = 0x90,
+    /// Host request: write chunk of the table image
+    TableWrite = 0x91,
+    /// Reply: chunk of the table image
+    TableData = 0x92,
+    /// Host request: read production test metric
+    ProductionResultRead = 0xA0,
+    /// Reply: production test metric
+    ProductionResult = 0xA1,
+    /// Host request: read factory data page
+    FactoryRead = 0xB0,
+    /// Host request: stage factory data page
+    FactoryWrite = 0xB1,
+    /// Reply: factory data page
+    FactoryData = 0xB2,
+    /// Host request: read calibration report metric
+    CalibrationReportRead = 0xC0,
+    /// Reply: calibration report metric
+    CalibrationReport = 0xC1,
+    /// Host request: read setup wizard result
+    WizardResultRead = 0xC2,
+    /// Reply: setup wizard result
+    WizardResult = 0xC3,
+    /// Host request: read (and clear) a peak-hold register
+    PeakRead = 0xC4,
+    /// Reply: peak-hold register
+    Peak = 0xC5,
+    /// Host request: read detailed driver state
+    StateRead = 0xD0,
+    /// Reply: calibration sub-stage, progress and energized windings
+    State = 0xD1,
+    /// Asynchronous motion event
+    Event = 0xE0,
+    /// Periodic position and velocity sample
+    Odometry = 0xE1,
+    /// Periodic health and statistics page of the slow status channel
+    StatusReport = 0xE2,
+    /// Periodic torque current and supply voltage sample
+    Electrical = 0xE3,
+    /// Host request: time beacon carrying the host wall clock
+    TimeBeacon = 0xF0,
+    /// Reply: tick counter at the reception of the time beacon
+    TimeSync = 0xF1,
+}
+
+impl FrameType {
+    /// Converts the first byte of a frame
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0x10 => Some(FrameType::ParamRead),
+            0x11 => Some(FrameType::ParamWrite),
+            0x12 => Some(FrameType::ParamValue),
+            0x13 => Some(FrameType::ParamStage),
+            0x20 => Some(FrameType::Command),
+            0x21 => Some(FrameType::CommandResult),
+            0x22 => Some(FrameType::Setpoint),
+            0x30 => Some(FrameType::StatusRead),
+            0x31 => Some(FrameType::Status),
+            0x40 => Some(FrameType::DeviceInfoRead),
+            0x41 => Some(FrameType::DeviceInfo),
+            0x50 => Some(FrameType::ResponseRead),
+            0x51 => Some(FrameType::Response),
+            0x60 => Some(FrameType::CaptureRead),
+            0x61 => Some(FrameType::Capture),
+            0x70 => Some(FrameType::MasterPosition),
+            0x80 => Some(FrameType::StepResultRead),
+            0x81 => Some(FrameType::StepResult),
+            0x90 => Some(FrameType::TableRead),
+            0x91 => Some(FrameType::TableWrite),
+            0x92 => Some(FrameType::TableData),
+            0xA0 => Some(FrameType::ProductionResultRead),
+            0xA1 => Some(FrameType::ProductionResult),
+            0xB0 => Some(FrameType::FactoryRead),
+            0xB1 => Some(FrameType::FactoryWrite),
+            0xB2 => Some(FrameType::FactoryData),
+            0xC0 => Some(FrameType::CalibrationReportRead),
+            0xC1 => Some(FrameType::CalibrationReport),
+            0xC2 => Some(FrameType::WizardResultRead),
+            0xC3 => Some(FrameType::WizardResult),
+            0xC4 => Some(FrameType::PeakRead),
+            0xC5 => Some(FrameType::Peak),
+            0xD0 => Some(FrameType::StateRead),
+            0xD1 => Some(FrameType::State),
+            0xE0 => Some(FrameType::Event),
+            0xE1 => Some(FrameType::Odometry),
+            0xE2 => Some(FrameType::StatusReport),
+            0xE3 => Some(FrameType::Electrical),
+            0xF0 => Some(FrameType::TimeBeacon),
+            0xF1 => Some(FrameType::TimeSync),
+            _ => None,
+        }
+    }
+}
+
+// Every code of the firmware enums has an entry in the tables of the host tools
+const _: () = {
+    use crate::diagnostics::peak_hold::{PeakValue, PEAK_VALUES};
+    use crate::fault::FaultCode;
+    use crate::motor_driver::calibration::CalibrationMetric;
+    use crate::motor_driver::presets::{MotorPreset, PRESET_COUNT};
+    use crate::motor_driver::DriverStatus;
+    use commands::ReplyResult;
+    use events::MotionEvent;
+    use tunepulse_params::codes;
+
+    assert!(codes::DRIVER_STATUS.len() == DriverStatus::Error as usize + 1);
+    assert!(codes::FAULT_CODES.len() == FaultCode::FollowingError as usize + 1);
+    assert!(codes::MOTION_EVENTS.len() == MotionEvent::StallWarning as usize + 1);
+    assert!(codes::REPLY_RESULTS.len() == ReplyResult::AccessDenied as usize + 1);
+    assert!(codes::CALIBRATION_METRICS.len() == CalibrationMetric::LoadAngleRipple as usize + 1);
+    assert!(codes::PEAK_VALUES.len() == PEAK_VALUES);
+    assert!(codes::PEAK_VALUES.len() == PeakValue::FollowingError as usize + 1);
+    assert!(codes::MOTOR_PRESETS.len() == PRESET_COUNT);
+    assert!(codes::MOTOR_PRESETS.len() == MotorPreset::Gimbal4108 as usize + 1);
+};
+
+#[cfg(feature = "production-test")]
+const _: () = {
+    use crate::diagnostics::production_test::ProductionMetric;
+    use tunepulse_params::codes;
+
+    assert!(codes::PRODUCTION_METRICS.len() == ProductionMetric::TrimSpread as usize + 1);
+};
+
+#[cfg(feature = "wizard")]
+const _: () = {
+    use crate::wizard::{StepOutcome, WizardResult, WizardStep, WIZARD_RESULTS, WIZARD_STEPS};
+    use tunepulse_params::codes;
+
+    assert!(codes::WIZARD_STEPS.len() == WIZARD_STEPS);
+    assert!(codes::WIZARD_STEPS.len() == WizardStep::GainSuggestion as usize + 1);
+    assert!(codes::STEP_OUTCOMES.len() == StepOutcome::Aborted as usize + 1);
+    assert!(codes::WIZARD_RESULTS.len() == WIZARD_RESULTS);
+    assert!(codes::WIZARD_RESULTS.len() == WizardResult::SuggestedPreset as usize + 1);
+};
+
+/// Physical link able to send protocol frames.
+pub trait Transport {
+    /// Queues a frame for sending, returns false if the link is busy.
+    fn send(&mut self, frame: &Frame) -> bool;
+}
@@ -0,0 +1,148 @@
+// Implements the virtual encoder output: position and velocity published at a fixed rate for
+// robotics stacks (odometry, SLAM), independent of the debug telemetry, together with the
+// electrical state of the drive.
+
+// Key Features:
+// - Compact 8 byte frame carried by every transport, including classic CAN.
+// - Fixed publishing rate derived from the control tick, no host polling.
+// - Sequence number doubling as device time base and loss detection.
+// - Torque current and supply voltage sampled at the same instant, in a second frame.
+// - Latest value wins: a busy link drops stale samples instead of delaying fresh ones.
+
+// Detailed Operation:
+// The control loop calls `tick()` every control tick, every `frequency / rate` ticks the
+// current position and velocity are encoded into a frame with the next sequence number, the
+// measured currents and the supply voltage into an electrical frame with the same number. Only
+// one sample is held, if the previous one wasn't sent yet it is replaced and counted as
+// dropped, the host sees the gap in the sequence numbers. Because samples are taken at a fixed
+// rate, the sample time on the device is `sequence / rate`, the host unwraps the 8-bit sequence
+// to get a jitter free time base for the odometry even if the link delays frames. The rate
+// should divide the tick rate (e.g. 100, 250, 500 or 1000 Hz at 20 kHz), otherwise the period
+// is rounded down to whole ticks.
+// A lower priority task calls `flush()` to hand the frames to the transport, odometry first.
+
+// Frame layouts:
+// - Odometry:   [type, seq, position (i32 LE), velocity (i16 LE)]
+//   - position: i16 rotations + u16 angle
+//   - velocity: 1/256 rev/s, saturated
+// - Electrical: [type, seq, current q (i16 LE, mA), current d (i16 LE, mA), supply (u16 LE, mV)]
+//   - currents along (d) and in quadrature to (q) the commanded vector, zero while the current
+//     loop is open
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{Frame, FrameType, Transport};
+
+/// Position counts per velocity unit (1/256 rev/s)
+const VELOCITY_SCALE: i32 = 256;
+
+/// Periodic position and velocity publisher.
+pub struct OdometryPublisher {
+    frequency: u16,            // Update frequency (ticks per second)
+    period: u32,               // Ticks between samples (0 - disabled)
+    counter: u32,              // Ticks since the last sample
+    seq: u8,                   // Sequence number of the next sample
+    pending: Option<Frame>,    // Sample waiting for the transport
+    electrical: Option<Frame>, // Electrical sample waiting for the transport
+    dropped: u32,              // Number of samples replaced before being sent
+}
+
+impl OdometryPublisher {
+    /// Creates a publisher.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `rate` - Samples per second (0 - disabled)
+    pub fn new(frequency: u16, rate: u32) -> Self {
+        let mut odometry = Self {
+            frequency,
+            period: 0,
+            counter: 0,
+            seq: 0,
+            pending: None,
+            electrical: None,
+            dropped: 0,
+        };
+        odometry.set_rate(rate);
+        odometry
+    }
+
+    /// Sets the publishing rate (samples per second, 0 - disabled), limited to the tick rate
+    pub fn set_rate(&mut self, rate: u32) {
+        self.period = (self.frequency as u32)
+            .checked_div(rate)
+            .map_or(0, |period| period.max(1));
+        self.counter = 0;
+    }
+
+    /// Samples position, velocity and the electrical state if due, call once per control tick
+    ///
+    /// # Arguments
+    /// * `position` - Position (i16 rotations + u16 angle)
+    /// * `speed` - Speed estimate (counts per second)
+    /// * `current` - Measured d/q current (mA)
+    /// * `supply` - Supply voltage (mV)
+    pub fn tick(&mut self, position: i32, speed: i32, current: (i16, i16), supply: i32) {
+        if self.period == 0 {
+            return;
+        }
+        self.counter += 1;
+        if self.counter < self.period {
+            return;
+        }
+        self.counter = 0;
+
+        if self.pending.is_some() || self.electrical.is_some() {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        let velocity = (speed / VELOCITY_SCALE).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let position = position.to_le_bytes();
+        let velocity = velocity.to_le_bytes();
+        self.pending = Some([
+            FrameType::Odometry as u8,
+            self.seq,
+            position[0],
+            position[1],
+            position[2],
+            position[3],
+            velocity[0],
+            velocity[1],
+        ]);
+        let current_q = current.1.to_le_bytes();
+        let current_d = current.0.to_le_bytes();
+        let supply = (supply.clamp(0, u16::MAX as i32) as u16).to_le_bytes();
+        self.electrical = Some([
+            FrameType::Electrical as u8,
+            self.seq,
+            current_q[0],
+            current_q[1],
+            current_d[0],
+            current_d[1],
+            supply[0],
+            supply[1],
+        ]);
+        self.seq = self.seq.wrapping_add(1);
+    }
+
+    /// Sends the pending frames over the transport, returns true if a frame was sent
+    pub fn flush<T: Transport>(&mut self, transport: &mut T) -> bool {
+        let mut sent = false;
+        for pending in [&mut self.pending, &mut self.electrical] {
+            match *pending {
+                Some(frame) if transport.send(&frame) => {
+                    *pending = None;
+                    sent = true;
+                }
+                Some(_) => break, // Keep the order, the electrical frame follows the odometry
+                None => {}
+            }
+        }
+        sent
+    }
+
+    /// Number of samples dropped because the link didn't keep up
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
@@ -0,0 +1,139 @@
+// Implements the slow status channel: driver health and statistics published at a low rate,
+// separate from the telemetry points and the odometry stream.
+
+// Key Features:
+// - Low fixed publishing rate (1 - 10 Hz) derived from the control tick, no host polling.
+// - One report spans `STATUS_REPORT_PAGES` frames sent back to back.
+// - Latest report wins: a busy link drops a stale report instead of delaying the fresh one.
+
+// Detailed Operation:
+// Dashboards showing the basic health of a drive shouldn't have to poll it or decimate the
+// fast signal stream. The control loop calls `tick()` every control tick, once a report is due
+// the owner gathers the values and hands them to `publish()`, which encodes all pages. A lower
+// priority task calls `flush()` to send the pages in order, on RTT links the owner uses a
+// dedicated `status` up channel so the reports never queue behind telemetry points. A report
+// not completely sent when the next one is due is replaced and counted as dropped.
+
+// Frame layout: [FrameType::StatusReport, page, payload (6 bytes)]
+// - page 0 (health): status byte as in the status reply, fault code, supply voltage (u16 LE,
+//   mV), temperature sensor reading (u16 LE, raw ADC)
+// - page 1 (statistics): rejected encoder samples, repeated encoder reads and arithmetic
+//   overflows since power-up (u16 LE each, saturated)
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{Frame, FrameType, Transport, FRAME_SIZE};
+
+/// Number of frames of one status report
+pub const STATUS_REPORT_PAGES: usize = 2;
+
+/// Highest publishing rate (reports per second)
+pub const STATUS_RATE_MAX: u32 = 10;
+
+/// Values of one status report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatusReport {
+    pub status: u8,            // Status byte as in the status reply
+    pub fault: u8,             // Reason of the error state (`FaultCode`)
+    pub supply_mv: i32,        // Filtered supply voltage
+    pub temperature: u16,      // Temperature sensor reading (raw ADC)
+    pub encoder_rejected: u32, // Encoder samples rejected since power-up
+    pub encoder_retries: u32,  // Encoder read pairs repeated since power-up
+    pub overflows: u32,        // Arithmetic overflows since power-up
+}
+
+impl StatusReport {
+    /// Encodes the report into its pages
+    pub fn encode(&self) -> [Frame; STATUS_REPORT_PAGES] {
+        let saturate = |value: u32| value.min(u16::MAX as u32) as u16;
+        let supply = self.supply_mv.clamp(0, u16::MAX as i32) as u16;
+
+        let mut health = [FrameType::StatusReport as u8, 0, 0, 0, 0, 0, 0, 0];
+        health[2] = self.status;
+        health[3] = self.fault;
+        health[4..6].copy_from_slice(&supply.to_le_bytes());
+        health[6..8].copy_from_slice(&self.temperature.to_le_bytes());
+
+        let mut statistics = [FrameType::StatusReport as u8, 1, 0, 0, 0, 0, 0, 0];
+        statistics[2..4].copy_from_slice(&saturate(self.encoder_rejected).to_le_bytes());
+        statistics[4..6].copy_from_slice(&saturate(self.encoder_retries).to_le_bytes());
+        statistics[6..8].copy_from_slice(&saturate(self.overflows).to_le_bytes());
+
+        [health, statistics]
+    }
+}
+
+/// Periodic status report publisher.
+pub struct StatusPublisher {
+    frequency: u16,                        // Update frequency (ticks per second)
+    period: u32,                           // Ticks between reports (0 - disabled)
+    counter: u32,                          // Ticks since the last report
+    pending: [Frame; STATUS_REPORT_PAGES], // Pages of the last report
+    sent: usize,                           // Pages of the last report already sent
+    dropped: u32,                          // Reports replaced before being sent completely
+}
+
+impl StatusPublisher {
+    /// Creates a publisher.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    /// * `rate` - Reports per second (0 - disabled)
+    pub fn new(frequency: u16, rate: u32) -> Self {
+        let mut publisher = Self {
+            frequency,
+            period: 0,
+            counter: 0,
+            pending: [[0; FRAME_SIZE]; STATUS_REPORT_PAGES],
+            sent: STATUS_REPORT_PAGES,
+            dropped: 0,
+        };
+        publisher.set_rate(rate);
+        publisher
+    }
+
+    /// Sets the publishing rate (reports per second, 0 - disabled)
+    pub fn set_rate(&mut self, rate: u32) {
+        self.period = (self.frequency as u32)
+            .checked_div(rate.min(STATUS_RATE_MAX))
+            .map_or(0, |period| period.max(1));
+        self.counter = 0;
+    }
+
+    /// Returns true once a report is due, call once per control tick
+    pub fn tick(&mut self) -> bool {
+        if self.period == 0 {
+            return false;
+        }
+        self.counter += 1;
+        if self.counter < self.period {
+            return false;
+        }
+        self.counter = 0;
+        true
+    }
+
+    /// Encodes the report for the transport, replacing a report not completely sent
+    pub fn publish(&mut self, report: &StatusReport) {
+        if self.sent < STATUS_REPORT_PAGES {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        self.pending = report.encode();
+        self.sent = 0;
+    }
+
+    /// Sends the pending pages over the transport, returns the number of frames sent
+    pub fn flush<T: Transport>(&mut self, transport: &mut T) -> usize {
+        let start = self.sent;
+        while self.sent < STATUS_REPORT_PAGES && transport.send(&self.pending[self.sent]) {
+            self.sent += 1;
+        }
+        self.sent - start
+    }
+
+    /// Number of reports dropped because the link didn't keep up
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
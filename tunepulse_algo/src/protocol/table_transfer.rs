@@ -0,0 +1,166 @@
+// Implements the exchange of calibration tables with the host as binary image.
+
+// Key Features:
+// - Self-describing image: table kind, payload length and CRC-32 in a fixed header.
+// - Chunked access through protocol frames, 4 bytes per frame addressed by byte offset.
+// - Same image for download and upload, tables can be backed up, inspected and cloned.
+
+// Detailed Operation:
+// A table doesn't fit into a frame, it is exchanged through a transfer buffer. On the export
+// command the owner encodes the table into the buffer with `export()`, the host then reads the
+// header, the payload length tells how many chunks follow, and checks the CRC of the payload.
+// An upload writes the chunks into the buffer and issues the import command, the owner gets
+// the payload through `payload()` only if header and CRC are intact and hands it to the table,
+// which validates the content itself. Chunks are addressed by byte offset, a lost reply is
+// simply requested again.
+// Image layout (little endian):
+// - 0:  [magic (u16), kind (u8), version (u8)]
+// - 4:  [payload length (u32)]
+// - 8:  [CRC-32 of the payload (u32)]
+// - 12: payload, layout defined by the table (see `CalibrationTable::write_image()`)
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ops::Range;
+
+use crate::motor_driver::calibration::CAL_IMAGE_SIZE;
+use crate::params::writer::crc32;
+
+/// Bytes carried by a table frame
+pub const TABLE_CHUNK_SIZE: usize = 4;
+
+/// Size of the image header in bytes
+pub const TABLE_HEADER_SIZE: usize = 12;
+
+/// Size of the transfer buffer, the largest table rounded up to whole chunks
+pub const TABLE_IMAGE_SIZE: usize =
+    (TABLE_HEADER_SIZE + CAL_IMAGE_SIZE).div_ceil(TABLE_CHUNK_SIZE) * TABLE_CHUNK_SIZE;
+
+/// Marks a table image ("TC")
+const TABLE_IMAGE_MAGIC: u16 = 0x4354;
+
+/// Revision of the image layout, incremented when a payload layout changes
+const TABLE_IMAGE_VERSION: u8 = 1;
+
+/// Tables which can be exchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TableKind {
+    /// Encoder calibration table of the angle calibrator
+    Calibration = 0,
+}
+
+impl TableKind {
+    /// Converts raw kind stored in an image
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(TableKind::Calibration),
+            _ => None,
+        }
+    }
+}
+
+/// Errors reported for an uploaded image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum TableError {
+    /// Magic, version or kind unknown
+    Header,
+    /// Payload longer than the transfer buffer
+    Length,
+    /// CRC doesn't match the payload
+    Checksum,
+}
+
+/// Buffer holding the image during a transfer.
+pub struct TableTransfer {
+    image: [u8; TABLE_IMAGE_SIZE], // Image being read or written by the host
+}
+
+impl TableTransfer {
+    /// Creates an empty buffer, reads return zeros until a table is exported
+    pub const fn new() -> Self {
+        Self {
+            image: [0; TABLE_IMAGE_SIZE],
+        }
+    }
+
+    /// Encodes a table into the buffer, returns false if there's no table to export.
+    ///
+    /// # Arguments
+    /// * `kind` - Table stored in the image
+    /// * `write` - Writes the payload into the given buffer, returns its size or `None`
+    pub fn export<F>(&mut self, kind: TableKind, write: F) -> bool
+    where
+        F: FnOnce(&mut [u8]) -> Option<usize>,
+    {
+        let (header, payload) = self.image.split_at_mut(TABLE_HEADER_SIZE);
+        let Some(len) = write(payload) else {
+            return false;
+        };
+        payload[len..].fill(0);
+        let crc = crc32(&payload[..len]);
+        header[0..2].copy_from_slice(&TABLE_IMAGE_MAGIC.to_le_bytes());
+        header[2] = kind as u8;
+        header[3] = TABLE_IMAGE_VERSION;
+        header[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+        header[8..12].copy_from_slice(&crc.to_le_bytes());
+        true
+    }
+
+    /// Chunk at byte `offset`, `None` if the offset isn't chunk aligned or outside the buffer
+    pub fn read(&self, offset: u16) -> Option<[u8; TABLE_CHUNK_SIZE]> {
+        let chunk = Self::chunk(offset)?;
+        let mut data = [0; TABLE_CHUNK_SIZE];
+        data.copy_from_slice(&self.image[chunk]);
+        Some(data)
+    }
+
+    /// Stores a chunk at byte `offset`, returns false if the offset is invalid
+    pub fn write(&mut self, offset: u16, data: [u8; TABLE_CHUNK_SIZE]) -> bool {
+        match Self::chunk(offset) {
+            Some(chunk) => {
+                self.image[chunk].copy_from_slice(&data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Kind and payload of the image in the buffer, after checking header and CRC
+    pub fn payload(&self) -> Result<(TableKind, &[u8]), TableError> {
+        let word = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&self.image[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let magic = u16::from_le_bytes([self.image[0], self.image[1]]);
+        let kind = TableKind::from_raw(self.image[2]).ok_or(TableError::Header)?;
+        if magic != TABLE_IMAGE_MAGIC || self.image[3] != TABLE_IMAGE_VERSION {
+            return Err(TableError::Header);
+        }
+        let len = word(4) as usize;
+        if len > TABLE_IMAGE_SIZE - TABLE_HEADER_SIZE {
+            return Err(TableError::Length);
+        }
+        let payload = &self.image[TABLE_HEADER_SIZE..TABLE_HEADER_SIZE + len];
+        if crc32(payload) != word(8) {
+            return Err(TableError::Checksum);
+        }
+        Ok((kind, payload))
+    }
+
+    /// Byte range of the chunk at `offset`
+    fn chunk(offset: u16) -> Option<Range<usize>> {
+        let offset = offset as usize;
+        let valid = offset.is_multiple_of(TABLE_CHUNK_SIZE)
+            && offset + TABLE_CHUNK_SIZE <= TABLE_IMAGE_SIZE;
+        valid.then_some(offset..offset + TABLE_CHUNK_SIZE)
+    }
+}
+
+impl Default for TableTransfer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,76 @@
+// Implements the time beacon exchange mapping the device tick counter to the host wall clock.
+
+// Key Features:
+// - Host sends its wall clock, the device replies with the tick counter of the same instant.
+// - Same tick counter as the telemetry point timestamps, no extra clock on the device.
+// - Last beacon kept on the device to estimate the host time of local events.
+
+// Detailed Operation:
+// Ticks are counted from power-up and drift with the controller oscillator, wall clock time
+// is only known to the host. The host sends a beacon with its time in milliseconds every few
+// seconds, the device answers immediately with the tick counter the telemetry points are
+// stamped with. The host takes the midpoint of its send and receive time as the instant of
+// the reply and fits offset and drift over several beacons, every device on the bus answers
+// its own beacon, so the samples of several axes end up on one time axis. The sequence number
+// is echoed so the host can match replies and discard late ones. The device keeps the last
+// beacon and extrapolates the host time with its tick rate, good enough for log messages.
+
+// Frame layouts:
+// - TimeBeacon:    [type, seq, 0, 0, host time (u32 LE, ms)]
+// - TimeSync:      [type, seq, 0, 0, tick (u32 LE)]
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use super::{Frame, FrameType};
+
+/// Encodes the reply to a time beacon
+///
+/// # Arguments
+/// * `seq` - Sequence number of the beacon
+/// * `tick` - Tick counter at the reception of the beacon
+pub fn time_sync_reply(seq: u8, tick: u32) -> Frame {
+    let tick = tick.to_le_bytes();
+    [
+        FrameType::TimeSync as u8,
+        seq,
+        0,
+        0,
+        tick[0],
+        tick[1],
+        tick[2],
+        tick[3],
+    ]
+}
+
+/// Host wall clock as seen by the last beacon.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostClock {
+    beacon: Option<(u32, u32)>, // Host time (ms) and tick counter of the last beacon
+}
+
+impl HostClock {
+    /// Creates a clock without beacon
+    pub const fn new() -> Self {
+        Self { beacon: None }
+    }
+
+    /// Records a beacon received at the given tick
+    pub fn beacon(&mut self, host_ms: u32, tick: u32) {
+        if self.beacon.is_none() {
+            defmt::info!("SYNC: First time beacon at tick {}", tick);
+        }
+        self.beacon = Some((host_ms, tick));
+    }
+
+    /// Estimated host time (ms, wrapping) at the given tick, `None` before the first beacon
+    ///
+    /// # Arguments
+    /// * `tick` - Tick counter
+    /// * `frequency` - Number of ticks per second
+    pub fn host_time_ms(&self, tick: u32, frequency: u16) -> Option<u32> {
+        let (host_ms, beacon_tick) = self.beacon?;
+        let elapsed = tick.wrapping_sub(beacon_tick) as u64 * 1000 / frequency.max(1) as u64;
+        Some(host_ms.wrapping_add(elapsed as u32))
+    }
+}
@@ -0,0 +1,165 @@
+// Implements a cooperative scheduler for background work running in the idle time left by the
+// control interrupt (communication parsing, flash writes, statistics).
+
+// Key Features:
+// - Round-robin execution of registered tasks with a cycle budget per task.
+// - Tasks yield voluntarily by checking their `Budget`, no preemption or stack per task.
+// - Bus stall admission: operations freezing the CPU (flash program/erase) only start when
+//   they complete before the next control interrupt.
+// - Per-task statistics: runs, longest run and budget overruns.
+
+// Detailed Operation:
+// The scheduler runs at the lowest priority (idle loop), so the control interrupt always
+// preempts it and background work can't delay a control tick by itself. The remaining risk is
+// code that blocks the bus: while the flash controller programs or erases, instruction fetch
+// from flash stalls and the control interrupt is serviced late. Tasks therefore ask
+// `Budget::allow_stall()` before such an operation, it returns true only if the stall ends
+// before the next control interrupt with a safety margin, otherwise the task yields and
+// retries in a later window.
+// `Scheduler::run()` polls every task once, starting with the one after the last polled, with
+// a budget limiting how long the task keeps the CPU before it must return. Time is measured by
+// a platform `Clock` (e.g. the DWT cycle counter) which also reports the time left until the
+// next control interrupt. A task exceeding its budget is counted as an overrun, which points
+// to a task yielding too rarely.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Platform time source
+pub trait Clock {
+    /// Free running cycle counter, wraps around
+    fn now(&self) -> u32;
+    /// Cycles until the next control interrupt
+    fn until_tick(&self) -> u32;
+}
+
+/// Result of polling a background task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Poll {
+    /// Nothing left to do
+    Idle,
+    /// Yielded with work remaining
+    Pending,
+}
+
+/// Background work run by the scheduler
+pub trait BackgroundTask {
+    /// Does a piece of work, returning once done or when `budget` is exhausted
+    fn poll(&mut self, budget: &Budget) -> Poll;
+}
+
+/// Time granted to a task for one poll
+pub struct Budget<'a> {
+    clock: &'a dyn Clock,
+    start: u32,  // Cycle count at the start of the poll
+    cycles: u32, // Granted cycles
+    margin: u32, // Cycles kept free before a control interrupt
+}
+
+impl Budget<'_> {
+    /// Cycles used since the start of the poll
+    pub fn used(&self) -> u32 {
+        self.clock.now().wrapping_sub(self.start)
+    }
+
+    /// Cycles left before the task has to yield
+    pub fn remaining(&self) -> u32 {
+        self.cycles.saturating_sub(self.used())
+    }
+
+    /// True if the task has to yield
+    pub fn expired(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// True if an operation stalling the bus for `cycles` ends before the next control
+    /// interrupt, the task has to yield if not
+    pub fn allow_stall(&self, cycles: u32) -> bool {
+        cycles.saturating_add(self.margin) <= self.clock.until_tick()
+    }
+}
+
+/// Statistics of a background task, times include interrupts preempting the task
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TaskStats {
+    pub runs: u32,       // Number of polls
+    pub max_cycles: u32, // Longest poll
+    pub overruns: u32,   // Polls exceeding the budget
+}
+
+/// Round-robin scheduler of `N` background tasks
+pub struct Scheduler<const N: usize> {
+    budgets: [u32; N],     // Cycles granted per poll
+    stats: [TaskStats; N], // Execution statistics
+    next: usize,           // Task polled first in the next run
+    margin: u32,           // Cycles kept free before a control interrupt
+}
+
+impl<const N: usize> Scheduler<N> {
+    /// Creates a scheduler
+    ///
+    /// # Arguments
+    /// * `budgets` - Cycles granted to each task per poll
+    /// * `margin` - Cycles kept free before a control interrupt when admitting bus stalls
+    pub const fn new(budgets: [u32; N], margin: u32) -> Self {
+        Self {
+            budgets,
+            stats: [TaskStats {
+                runs: 0,
+                max_cycles: 0,
+                overruns: 0,
+            }; N],
+            next: 0,
+            margin,
+        }
+    }
+
+    /// Changes the budget of a task
+    pub fn set_budget(&mut self, task: usize, cycles: u32) {
+        if let Some(budget) = self.budgets.get_mut(task) {
+            *budget = cycles;
+        }
+    }
+
+    /// Polls every task once, returns true if any task has work remaining
+    ///
+    /// # Arguments
+    /// * `tasks` - Tasks in the order of their budgets
+    /// * `clock` - Platform time source
+    pub fn run(&mut self, tasks: &mut [&mut dyn BackgroundTask; N], clock: &dyn Clock) -> bool {
+        let mut pending = false;
+        for offset in 0..N {
+            let idx = (self.next + offset) % N;
+            let budget = Budget {
+                clock,
+                start: clock.now(),
+                cycles: self.budgets[idx],
+                margin: self.margin,
+            };
+            let poll = tasks[idx].poll(&budget);
+            let used = budget.used();
+
+            let stats = &mut self.stats[idx];
+            stats.runs = stats.runs.wrapping_add(1);
+            stats.max_cycles = stats.max_cycles.max(used);
+            if used > budget.cycles {
+                stats.overruns = stats.overruns.wrapping_add(1);
+            }
+            pending |= poll == Poll::Pending;
+        }
+        if N > 0 {
+            self.next = (self.next + 1) % N;
+        }
+        pending
+    }
+
+    /// Statistics of a task
+    pub fn stats(&self, task: usize) -> Option<TaskStats> {
+        self.stats.get(task).copied()
+    }
+
+    /// Clears the statistics of all tasks
+    pub fn reset_stats(&mut self) {
+        self.stats = [TaskStats::default(); N];
+    }
+}
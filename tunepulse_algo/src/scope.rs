@@ -0,0 +1,435 @@
+// Implements the two channel scope capturing internal control signals selected by identifier.
+
+// Key Features:
+// - Signal routing matrix: any internal signal can be routed to either channel at runtime.
+// - Capture at control loop rate with configurable decimation.
+// - Min/max/mean envelope of the decimation window per channel, spikes survive low rates.
+// - Fixed size sample buffer decoupling the control loop from the telemetry link.
+// - Encoding into the telemetry point format understood by the plotter and CLI.
+// - `TelemetryLink` trait implemented by the transports streaming the points (ITM/SWO, ...).
+
+// Detailed Operation:
+// Every control tick the owner reads the selected signals and passes them to `capture()`.
+// Every `decimation`-th call stores a sample with both channel values and the tick counter in
+// a circular buffer. A lower priority task drains the buffer with `pop()` and sends the samples
+// to the host. If the buffer overflows the oldest sample is dropped and the overflow counter is
+// incremented. Selecting a different signal only changes the routing, no recompile is needed.
+// Plain decimation keeps the value of the last tick of each window and hides everything in
+// between, a current spike lasting a few ticks is gone at a decimation of 100. Channels with
+// the envelope enabled track the minimum, maximum and sum of every tick of the window instead
+// and store the mean as value, the minimum and maximum travel along in the sample.
+// Telemetry point layout (9 bytes, packed): [signal id (u8), tick (u32 LE), value (f32 LE)]
+// An enveloped channel is sent as three points: the mean with the plain signal id, then the
+// minimum and maximum with `ENVELOPE_MIN` and `ENVELOPE_MAX` set in the id.
+// The time base point (`TIMEBASE_ID`) carries the tick rate in ticks per second as value, it is
+// sent when streaming starts and periodically after that so the host can convert ticks to time.
+// `flush()` does both for any `TelemetryLink`: it sends the time base point when a channel gets
+// enabled and every `TIMEBASE_INTERVAL` samples, then the samples until the link is busy. A
+// sample is only removed once the link took all of its points, a busy link loses nothing until
+// the buffer overflows.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Number of scope channels
+pub const SCOPE_CHANNELS: usize = 2;
+
+/// Size of an encoded telemetry point
+pub const POINT_SIZE: usize = 9;
+
+/// Identifier of the time base point, never used by a signal
+pub const TIMEBASE_ID: u8 = 0xFF;
+
+/// Flag of the signal id marking the window minimum of an enveloped channel
+pub const ENVELOPE_MIN: u8 = 0x40;
+
+/// Flag of the signal id marking the window maximum of an enveloped channel
+pub const ENVELOPE_MAX: u8 = 0x80;
+
+/// Largest encoded sample: mean, minimum and maximum of every channel
+pub const SAMPLE_SIZE: usize = POINT_SIZE * SCOPE_CHANNELS * 3;
+
+/// Samples streamed between two time base points
+pub const TIMEBASE_INTERVAL: u16 = 1000;
+
+/// Link streaming telemetry points to the host.
+pub trait TelemetryLink {
+    /// Queues encoded telemetry points, returns false if the link can't take all of them.
+    fn send(&mut self, points: &[u8]) -> bool;
+}
+
+/// Internal signals available for capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ScopeSignal {
+    /// Channel disabled
+    None = 0,
+    /// Encoder position (i16 rotations + u16 angle)
+    Position = 1,
+    /// Estimated speed (unit selected by the speed unit parameter, counts per second by default)
+    Speed = 2,
+    /// Target position
+    Target = 3,
+    /// Position error after deadband
+    PositionError = 4,
+    /// Commanded electrical angle
+    AngleEl = 5,
+    /// Torque current command after compensation (mA)
+    CurrentCommand = 6,
+    /// Friction and gravity feedforward (mA)
+    FrictionCurrent = 7,
+    /// Active damping current (mA)
+    DampingCurrent = 8,
+    /// Estimated load torque (mA)
+    LoadTorque = 9,
+    /// Load angle (i1.15 of electrical revolution)
+    LoadAngle = 10,
+    /// Supply voltage (mV)
+    SupplyVoltage = 11,
+    /// PWM duty of channels A to D (i1.15)
+    DutyA = 12,
+    DutyB = 13,
+    DutyC = 14,
+    DutyD = 15,
+    /// Output of the test signal generator
+    Excitation = 16,
+    /// Overflows counted by the `overflow-check` feature, 0 without it
+    Overflows = 17,
+    /// Encoder sample instant relative to the PWM period center (ns)
+    SampleOffset = 18,
+    /// Encoder angle as read, before filtering and calibration
+    AngleRaw = 19,
+    /// Encoder angle after the position filter
+    AngleFiltered = 20,
+    /// Electrical angle of the raw encoder angle from the calibration table, 0 without table
+    AngleElCorrected = 21,
+    /// Encoder samples rejected by the double read check since power-up
+    EncoderGlitches = 22,
+    /// Encoder samples rejected for any reason since power-up (EMC profile)
+    EncoderErrors = 23,
+    /// Measured current along the commanded current vector (mA), 0 while the current loop is open
+    CurrentD = 24,
+    /// Measured current in quadrature to the commanded vector (mA), 0 while the loop is open
+    CurrentQ = 25,
+}
+
+impl ScopeSignal {
+    /// Converts raw identifier received over protocol
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        Some(match raw {
+            0 => ScopeSignal::None,
+            1 => ScopeSignal::Position,
+            2 => ScopeSignal::Speed,
+            3 => ScopeSignal::Target,
+            4 => ScopeSignal::PositionError,
+            5 => ScopeSignal::AngleEl,
+            6 => ScopeSignal::CurrentCommand,
+            7 => ScopeSignal::FrictionCurrent,
+            8 => ScopeSignal::DampingCurrent,
+            9 => ScopeSignal::LoadTorque,
+            10 => ScopeSignal::LoadAngle,
+            11 => ScopeSignal::SupplyVoltage,
+            12 => ScopeSignal::DutyA,
+            13 => ScopeSignal::DutyB,
+            14 => ScopeSignal::DutyC,
+            15 => ScopeSignal::DutyD,
+            16 => ScopeSignal::Excitation,
+            17 => ScopeSignal::Overflows,
+            18 => ScopeSignal::SampleOffset,
+            19 => ScopeSignal::AngleRaw,
+            20 => ScopeSignal::AngleFiltered,
+            21 => ScopeSignal::AngleElCorrected,
+            22 => ScopeSignal::EncoderGlitches,
+            23 => ScopeSignal::EncoderErrors,
+            24 => ScopeSignal::CurrentD,
+            25 => ScopeSignal::CurrentQ,
+            _ => return None,
+        })
+    }
+
+    /// Routing of the angle signals selected by a mask, `None` for an empty mask.
+    ///
+    /// Bit 0 selects the raw, bit 1 the filtered and bit 2 the corrected electrical angle. The
+    /// selected signals fill the channels in this order, unused channels are disabled.
+    pub fn angle_selection(mask: u32) -> Option<[ScopeSignal; SCOPE_CHANNELS]> {
+        const ANGLES: [ScopeSignal; 3] = [
+            ScopeSignal::AngleRaw,
+            ScopeSignal::AngleFiltered,
+            ScopeSignal::AngleElCorrected,
+        ];
+        if mask & 0b111 == 0 {
+            return None;
+        }
+        let mut signals = [ScopeSignal::None; SCOPE_CHANNELS];
+        let selected = ANGLES
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask & (1 << bit) != 0);
+        for (slot, (_, signal)) in signals.iter_mut().zip(selected) {
+            *slot = *signal;
+        }
+        Some(signals)
+    }
+}
+
+/// Captured values of both channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScopeSample {
+    pub tick: u32,                              // Control tick of the capture
+    pub signals: [ScopeSignal; SCOPE_CHANNELS], // Signals routed at capture time
+    pub values: [i32; SCOPE_CHANNELS],          // Raw signal values, mean if enveloped
+    pub envelope: u8,                           // Enveloped channels, bit per channel
+    pub min: [i32; SCOPE_CHANNELS],             // Window minimum of enveloped channels
+    pub max: [i32; SCOPE_CHANNELS],             // Window maximum of enveloped channels
+}
+
+impl ScopeSample {
+    /// Encodes enabled channels as telemetry points, returns the number of bytes written
+    pub fn encode(&self, buf: &mut [u8; SAMPLE_SIZE]) -> usize {
+        let mut len = 0;
+        for (channel, signal) in self.signals.iter().enumerate() {
+            if *signal == ScopeSignal::None {
+                continue;
+            }
+            let id = *signal as u8;
+            len += self.encode_point(&mut buf[len..], id, self.values[channel]);
+            if self.envelope & (1 << channel) != 0 {
+                len += self.encode_point(&mut buf[len..], id | ENVELOPE_MIN, self.min[channel]);
+                len += self.encode_point(&mut buf[len..], id | ENVELOPE_MAX, self.max[channel]);
+            }
+        }
+        len
+    }
+
+    /// Encodes one telemetry point at the start of `buf`, returns its size
+    fn encode_point(&self, buf: &mut [u8], id: u8, value: i32) -> usize {
+        let point = &mut buf[..POINT_SIZE];
+        point[0] = id;
+        point[1..5].copy_from_slice(&self.tick.to_le_bytes());
+        point[5..9].copy_from_slice(&(value as f32).to_le_bytes());
+        POINT_SIZE
+    }
+}
+
+/// Minimum, maximum and sum of a channel over the decimation window.
+#[derive(Debug, Clone, Copy)]
+struct Envelope {
+    min: i32, // Smallest value of the window
+    max: i32, // Largest value of the window
+    sum: i64, // Sum of the values of the window
+}
+
+impl Envelope {
+    const EMPTY: Envelope = Envelope {
+        min: i32::MAX,
+        max: i32::MIN,
+        sum: 0,
+    };
+
+    #[inline(always)]
+    fn add(&mut self, value: i32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as i64;
+    }
+}
+
+/// Two channel capture buffer.
+pub struct SignalScope<const N: usize> {
+    signals: [ScopeSignal; SCOPE_CHANNELS], // Routing of the channels
+    decimation: u16,                        // Capture every n-th tick
+    counter: u16,                           // Ticks since the last capture
+    tick: u32,                              // Control tick counter
+    buffer: [ScopeSample; N],               // Captured samples
+    head: usize,                            // Index of the oldest sample
+    len: usize,                             // Number of captured samples
+    overflows: u32,                         // Number of dropped samples
+    timebase_in: u16,                       // Samples to stream before the next time base
+    envelope: u8,                           // Enveloped channels, bit per channel
+    window: [Envelope; SCOPE_CHANNELS],     // Envelope of the running decimation window
+}
+
+impl<const N: usize> SignalScope<N> {
+    const EMPTY: ScopeSample = ScopeSample {
+        tick: 0,
+        signals: [ScopeSignal::None; SCOPE_CHANNELS],
+        values: [0; SCOPE_CHANNELS],
+        envelope: 0,
+        min: [0; SCOPE_CHANNELS],
+        max: [0; SCOPE_CHANNELS],
+    };
+
+    /// Creates a scope with both channels disabled
+    pub const fn new() -> Self {
+        Self {
+            signals: [ScopeSignal::None; SCOPE_CHANNELS],
+            decimation: 1,
+            counter: 0,
+            tick: 0,
+            buffer: [Self::EMPTY; N],
+            head: 0,
+            len: 0,
+            overflows: 0,
+            timebase_in: 0,
+            envelope: 0,
+            window: [Envelope::EMPTY; SCOPE_CHANNELS],
+        }
+    }
+
+    /// Routes a signal to a channel
+    ///
+    /// # Arguments
+    /// * `channel` - Channel index (0 or 1)
+    /// * `signal` - Signal to capture, `ScopeSignal::None` disables the channel
+    pub fn select(&mut self, channel: usize, signal: ScopeSignal) {
+        if channel < SCOPE_CHANNELS {
+            if !self.is_enabled() {
+                self.timebase_in = 0; // Streaming starts, the host needs the time base first
+            }
+            self.signals[channel] = signal;
+            self.window[channel] = Envelope::EMPTY; // Window of the old signal is meaningless
+        }
+    }
+
+    /// Sets capture decimation, 1 captures every tick
+    pub fn set_decimation(&mut self, decimation: u16) {
+        self.decimation = decimation.max(1);
+        self.counter = 0;
+        self.window = [Envelope::EMPTY; SCOPE_CHANNELS];
+    }
+
+    /// Selects the channels sent as min/max/mean envelope of the decimation window
+    ///
+    /// # Arguments
+    /// * `mask` - Bit per channel, 0 sends the last value of each window on all channels
+    pub fn set_envelope(&mut self, mask: u8) {
+        self.envelope = mask & ((1 << SCOPE_CHANNELS) - 1);
+        self.counter = 0;
+        self.window = [Envelope::EMPTY; SCOPE_CHANNELS];
+    }
+
+    /// Signals routed to the channels
+    pub fn signals(&self) -> [ScopeSignal; SCOPE_CHANNELS] {
+        self.signals
+    }
+
+    /// True if at least one channel is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.signals
+            .iter()
+            .any(|signal| *signal != ScopeSignal::None)
+    }
+
+    /// Stores a sample if due, call once per control tick
+    ///
+    /// # Arguments
+    /// * `values` - Current values of the routed signals
+    pub fn capture(&mut self, values: [i32; SCOPE_CHANNELS]) {
+        self.tick = self.tick.wrapping_add(1);
+        if !self.is_enabled() {
+            self.counter = 0;
+            self.window = [Envelope::EMPTY; SCOPE_CHANNELS];
+            return;
+        }
+        for (window, value) in self.window.iter_mut().zip(values) {
+            window.add(value);
+        }
+        self.counter += 1;
+        if self.counter < self.decimation {
+            return;
+        }
+        let count = self.counter as i64;
+        self.counter = 0;
+
+        let mut sample = ScopeSample {
+            tick: self.tick,
+            signals: self.signals,
+            values,
+            envelope: self.envelope,
+            min: [0; SCOPE_CHANNELS],
+            max: [0; SCOPE_CHANNELS],
+        };
+        for (channel, window) in self.window.iter_mut().enumerate() {
+            if self.envelope & (1 << channel) != 0 {
+                sample.values[channel] = (window.sum / count) as i32;
+                sample.min[channel] = window.min;
+                sample.max[channel] = window.max;
+            }
+            *window = Envelope::EMPTY;
+        }
+        if self.len == N {
+            // Drop the oldest sample
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+        self.buffer[(self.head + self.len) % N] = sample;
+        self.len += 1;
+    }
+
+    /// Removes the oldest captured sample
+    pub fn pop(&mut self) -> Option<ScopeSample> {
+        if self.len == 0 {
+            return None;
+        }
+        let sample = self.buffer[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(sample)
+    }
+
+    /// Streams the captured samples over the link until it is busy, returns the number of
+    /// samples sent
+    ///
+    /// # Arguments
+    /// * `link` - Telemetry transport
+    /// * `frequency` - Number of control ticks per second
+    pub fn flush<L: TelemetryLink>(&mut self, link: &mut L, frequency: u16) -> usize {
+        let mut sent = 0;
+        while self.len > 0 {
+            if self.timebase_in == 0 {
+                if !link.send(&self.timebase(frequency)) {
+                    break;
+                }
+                self.timebase_in = TIMEBASE_INTERVAL;
+            }
+            let mut points = [0; SAMPLE_SIZE];
+            let len = self.buffer[self.head].encode(&mut points);
+            if !link.send(&points[..len]) {
+                break;
+            }
+            self.pop();
+            self.timebase_in -= 1;
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Control tick counter, the time base of the telemetry point timestamps
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Number of samples dropped due to a full buffer
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    /// Encodes the time base point for the current tick
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of control ticks per second
+    pub fn timebase(&self, frequency: u16) -> [u8; POINT_SIZE] {
+        let mut point = [0; POINT_SIZE];
+        point[0] = TIMEBASE_ID;
+        point[1..5].copy_from_slice(&self.tick.to_le_bytes());
+        point[5..9].copy_from_slice(&(frequency as f32).to_le_bytes());
+        point
+    }
+}
+
+impl<const N: usize> Default for SignalScope<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,195 @@
+// Implements sensorless commutation of BLDC motors from the back-EMF observer, replacing the
+// SPI encoder as source of the rotor angle.
+
+// Key Features:
+// - Angle source selection between the encoder and the back-EMF observer.
+// - Open-loop start: the current vector turns at a fixed speed until the back-EMF is trusted.
+// - Lock with hysteresis on the back-EMF magnitude, back to open loop once it drops.
+// - Synthesized mechanical encoder angle, the position, speed and motion logic run unchanged.
+
+// Detailed Operation:
+// The back-EMF is proportional to the speed, at standstill there is nothing to observe. The
+// motor is started open loop: the commanded current vector turns at the start speed in the
+// direction of the torque command with its magnitude, the rotor follows like a stepper.
+// Once the observed back-EMF exceeds the minimum the commutation switches to the observer
+// angle, the torque command then sets the quadrature current as with the encoder. It drops
+// back to open loop below half the minimum, continuing from the last observed angle.
+// The rest of the controller works on a mechanical encoder angle. Each tick the change of the
+// electrical angle in use (open-loop or observed) is divided by the pole pairs, the remainder
+// is kept for the next tick, and the result advances a synthesized 16 bit encoder angle. The
+// absolute position of a sensorless drive is only known relative to its start.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::observers::bemf::BemfObserver;
+
+/// Source of the rotor angle used for the commutation.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum AngleSource {
+    /// SPI encoder with the calibration table
+    Encoder = 0,
+    /// Back-EMF observer, BLDC motors with current sense only
+    Sensorless = 1,
+}
+
+impl AngleSource {
+    /// Converts raw value of the angle source parameter
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Encoder),
+            1 => Some(Self::Sensorless),
+            _ => None,
+        }
+    }
+}
+
+/// Commutation and encoder angle of a sensorless drive.
+pub struct SensorlessAngle {
+    frequency: u16,         // Update frequency (ticks per second)
+    observer: BemfObserver, // Back-EMF and angle estimation
+    pole_pairs: i32,        // Electrical revolutions per mechanical one
+    min_emf: u32,           // Back-EMF the observer angle is trusted above (mV)
+    start_step: u32,        // Open-loop angle change per tick (65536 per revolution as u16.16)
+    open_angle: u32,        // Open-loop electrical angle (65536 per revolution as u16.16)
+    locked: bool,           // Commutation follows the observer
+    angle_el: u16,          // Electrical angle in use during the last tick
+    remainder: i32,         // Electrical angle change not yet turned into encoder counts
+    angle_raw: u16,         // Synthesized mechanical encoder angle
+}
+
+impl SensorlessAngle {
+    /// Creates an unconfigured sensorless angle at zero.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            frequency,
+            observer: BemfObserver::new(frequency),
+            pole_pairs: 1,
+            min_emf: 0,
+            start_step: 0,
+            open_angle: 0,
+            locked: false,
+            angle_el: 0,
+            remainder: 0,
+            angle_raw: 0,
+        }
+    }
+
+    /// Sets the motor data and the tuning, the drive restarts open loop.
+    ///
+    /// # Arguments
+    /// * `resistance` - Phase resistance (mOhm)
+    /// * `inductance` - Phase inductance (uH)
+    /// * `pole_pairs` - Electrical revolutions per mechanical one
+    /// * `bandwidth` - Observer bandwidth (rad/s)
+    /// * `min_emf` - Back-EMF the observer angle is trusted above (mV)
+    /// * `start_speed` - Open-loop speed (electrical revolutions per second)
+    pub fn configure(
+        &mut self,
+        resistance: i32,
+        inductance: i32,
+        pole_pairs: u16,
+        bandwidth: u32,
+        min_emf: u32,
+        start_speed: u32,
+    ) {
+        self.observer.configure(resistance, inductance, bandwidth);
+        self.pole_pairs = pole_pairs.max(1) as i32;
+        self.min_emf = min_emf;
+        self.start_step = ((start_speed as u64) << 32)
+            .checked_div(self.frequency as u64)
+            .unwrap_or(0)
+            .min(i32::MAX as u64) as u32;
+        self.reset();
+    }
+
+    /// Math call, feeds the observer with the voltage applied and the current measured over the
+    /// last tick.
+    ///
+    /// # Arguments
+    /// * `voltage` - Applied alpha/beta voltage (mV)
+    /// * `current` - Measured alpha/beta current (mA)
+    pub fn tick(&mut self, voltage: (i32, i32), current: (i16, i16)) {
+        self.observer.tick(voltage, current);
+        let magnitude = self.observer.magnitude();
+        if !self.locked && magnitude > self.min_emf {
+            self.locked = true;
+            defmt::debug!("SENSORLESS: Locked at {}mV", magnitude);
+        } else if self.locked && magnitude < self.min_emf / 2 {
+            self.locked = false;
+            // Open loop continues along the torque axis of the last estimate
+            self.open_angle = (self.observer.commutation_angle() as u32) << 16;
+            defmt::debug!("SENSORLESS: Lost lock at {}mV", magnitude);
+        }
+    }
+
+    /// Commutation of the torque command, returns the electrical angle and amplitude of the
+    /// current vector and advances the synthesized encoder angle.
+    ///
+    /// # Arguments
+    /// * `current` - Torque command (mA), its sign sets the open-loop direction
+    pub fn commutate(&mut self, current: i16) -> (u16, i16) {
+        let (angle, amplitude) = if self.locked {
+            (self.observer.commutation_angle(), current)
+        } else {
+            if current > 0 {
+                self.open_angle = self.open_angle.wrapping_add(self.start_step);
+            } else if current < 0 {
+                self.open_angle = self.open_angle.wrapping_sub(self.start_step);
+            }
+            ((self.open_angle >> 16) as u16, current.saturating_abs())
+        };
+
+        // Encoder angle follows the electrical one scaled down by the pole pairs
+        self.remainder += angle.wrapping_sub(self.angle_el) as i16 as i32;
+        self.angle_el = angle;
+        let step = self.remainder / self.pole_pairs;
+        self.remainder -= step * self.pole_pairs;
+        self.angle_raw = self.angle_raw.wrapping_add(step as u16);
+        (angle, amplitude)
+    }
+
+    /// Synthesized mechanical encoder angle (65536 per revolution)
+    #[inline(always)]
+    pub fn angle_raw(&self) -> u16 {
+        self.angle_raw
+    }
+
+    /// Continues the synthesized encoder angle from the given one, the position doesn't jump
+    /// when the angle source changes
+    #[inline(always)]
+    pub fn set_angle_raw(&mut self, angle: u16) {
+        self.angle_raw = angle;
+        self.remainder = 0;
+    }
+
+    /// Electrical angle used by the last commutation (65536 per revolution)
+    #[inline(always)]
+    pub fn angle_el(&self) -> u16 {
+        self.angle_el
+    }
+
+    /// Returns true while the commutation follows the observer
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Returns the back-EMF observer
+    #[inline(always)]
+    pub fn observer(&self) -> &BemfObserver {
+        &self.observer
+    }
+
+    /// Restarts open loop from the current electrical angle, the encoder angle is kept
+    pub fn reset(&mut self) {
+        self.observer.reset();
+        self.locked = false;
+        self.open_angle = (self.angle_el as u32) << 16;
+        self.remainder = 0;
+    }
+}
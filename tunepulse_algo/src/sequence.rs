@@ -0,0 +1,240 @@
+// Implements a tiny motion sequence engine running stored move programs without a host.
+
+// Key Features:
+// - Absolute and relative moves, dwell times, counted loops and digital input waits.
+// - Compact fixed size step encoding suitable for storing programs in flash.
+// - Started by a protocol command or GPIO trigger, stopped any time.
+// - Compiled out without the `sequencer` feature, the start command is then refused.
+
+// Detailed Operation:
+// A program is a list of `SeqStep` entries. The engine executes one step at a time: move steps
+// set a new target position and wait until the controller reports the axis in position, dwell
+// steps wait for a number of milliseconds, input waits block until the selected input reaches
+// the requested level, loop steps jump back to an earlier step a given number of times (0 loops
+// forever) and the end step stops the program. Each step is stored as 8 bytes
+// [tag, arg8, arg16 (LE), arg32 (LE)] so a program can be copied to and from flash as is.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Size of an encoded step in bytes
+pub const STEP_SIZE: usize = 8;
+
+/// Single step of a motion sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqStep {
+    /// Move to absolute position (i16 rotations + u16 angle)
+    MoveTo(i32),
+    /// Move relative to current target
+    MoveBy(i32),
+    /// Wait for a number of milliseconds
+    Dwell(u32),
+    /// Wait until digital input reaches the level
+    WaitInput { input: u8, level: bool },
+    /// Jump to step, `count` times (0 - forever)
+    Loop { step: u8, count: u16 },
+    /// Stop the program
+    End,
+}
+
+impl SeqStep {
+    /// Encodes the step for storage
+    pub fn encode(&self) -> [u8; STEP_SIZE] {
+        let (tag, arg8, arg16, arg32): (u8, u8, u16, u32) = match *self {
+            SeqStep::MoveTo(pos) => (1, 0, 0, pos as u32),
+            SeqStep::MoveBy(dist) => (2, 0, 0, dist as u32),
+            SeqStep::Dwell(ms) => (3, 0, 0, ms),
+            SeqStep::WaitInput { input, level } => (4, input, level as u16, 0),
+            SeqStep::Loop { step, count } => (5, step, count, 0),
+            SeqStep::End => (0, 0, 0, 0),
+        };
+        let arg16 = arg16.to_le_bytes();
+        let arg32 = arg32.to_le_bytes();
+        [
+            tag, arg8, arg16[0], arg16[1], arg32[0], arg32[1], arg32[2], arg32[3],
+        ]
+    }
+
+    /// Decodes a stored step, `None` for unknown tags
+    pub fn decode(raw: &[u8; STEP_SIZE]) -> Option<Self> {
+        let arg8 = raw[1];
+        let arg16 = u16::from_le_bytes([raw[2], raw[3]]);
+        let arg32 = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+        match raw[0] {
+            0 | 0xFF => Some(SeqStep::End), // Erased flash reads as end of program
+            1 => Some(SeqStep::MoveTo(arg32 as i32)),
+            2 => Some(SeqStep::MoveBy(arg32 as i32)),
+            3 => Some(SeqStep::Dwell(arg32)),
+            4 => Some(SeqStep::WaitInput {
+                input: arg8,
+                level: arg16 != 0,
+            }),
+            5 => Some(SeqStep::Loop {
+                step: arg8,
+                count: arg16,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Motion sequence engine executing a program of up to `N` steps.
+pub struct SequenceEngine<const N: usize> {
+    program: [SeqStep; N], // Stored program
+    loops: [u16; N],       // Remaining iterations of each loop step
+    frequency: u16,        // Update frequency (ticks per second)
+    step: usize,           // Index of the executed step
+    entered: bool,         // Current step was already started
+    dwell: u32,            // Remaining dwell ticks
+    target: i32,           // Current target position
+    running: bool,         // Program is running
+}
+
+impl<const N: usize> SequenceEngine<N> {
+    /// Creates an engine with empty program.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        Self {
+            program: [SeqStep::End; N],
+            loops: [0; N],
+            frequency,
+            step: 0,
+            entered: false,
+            dwell: 0,
+            target: 0,
+            running: false,
+        }
+    }
+
+    /// Loads program from storage, returns false if it contains invalid steps.
+    pub fn load(&mut self, raw: &[u8]) -> bool {
+        let mut program = [SeqStep::End; N];
+        for (step, chunk) in program.iter_mut().zip(raw.chunks_exact(STEP_SIZE)) {
+            match SeqStep::decode(chunk.try_into().unwrap()) {
+                Some(decoded) => *step = decoded,
+                None => return false,
+            }
+        }
+        self.running = false;
+        self.program = program;
+        true
+    }
+
+    /// Encodes program into storage buffer, returns number of written bytes.
+    pub fn store(&self, raw: &mut [u8]) -> usize {
+        let mut len = 0;
+        for (step, chunk) in self.program.iter().zip(raw.chunks_exact_mut(STEP_SIZE)) {
+            chunk.copy_from_slice(&step.encode());
+            len += STEP_SIZE;
+        }
+        len
+    }
+
+    /// Replaces a single step of the program
+    pub fn set_step(&mut self, idx: usize, step: SeqStep) -> bool {
+        if idx >= N {
+            return false;
+        }
+        self.program[idx] = step;
+        true
+    }
+
+    /// Starts the program from the first step.
+    ///
+    /// # Arguments
+    /// * `position` - Current target position used as base for relative moves
+    pub fn start(&mut self, position: i32) {
+        self.loops = [0; N];
+        for (remaining, step) in self.loops.iter_mut().zip(self.program.iter()) {
+            if let SeqStep::Loop { count, .. } = step {
+                *remaining = *count;
+            }
+        }
+        self.step = 0;
+        self.entered = false;
+        self.target = position;
+        self.running = true;
+        defmt::info!("SEQUENCE: Started");
+    }
+
+    /// Stops the program
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    /// Math call, returns target position while the program is running.
+    ///
+    /// # Arguments
+    /// * `in_position` - Axis settled at the current target
+    /// * `inputs` - Digital input levels, bit per input
+    pub fn tick(&mut self, in_position: bool, inputs: u32) -> Option<i32> {
+        if !self.is_running() {
+            return None;
+        }
+        if self.step >= N {
+            self.finish();
+            return None;
+        }
+
+        let first = !self.entered;
+        self.entered = true;
+        let done = match self.program[self.step] {
+            SeqStep::MoveTo(pos) => {
+                self.target = pos;
+                !first && in_position // Give the controller a tick to clear the flag
+            }
+            SeqStep::MoveBy(dist) => {
+                if first {
+                    self.target = self.target.wrapping_add(dist);
+                }
+                !first && in_position
+            }
+            SeqStep::Dwell(ms) => {
+                if first {
+                    self.dwell = ms.saturating_mul(self.frequency as u32) / 1000;
+                }
+                self.dwell = self.dwell.saturating_sub(1);
+                self.dwell == 0
+            }
+            SeqStep::WaitInput { input, level } => ((inputs >> (input & 31)) & 1 != 0) == level,
+            SeqStep::Loop { step, count } => {
+                let remaining = &mut self.loops[self.step];
+                if count == 0 || *remaining > 0 {
+                    *remaining = remaining.saturating_sub(1);
+                    self.step = step as usize;
+                    self.entered = false;
+                    return Some(self.target);
+                }
+                *remaining = count; // Rearm for outer loops
+                true
+            }
+            SeqStep::End => {
+                self.finish();
+                return None;
+            }
+        };
+
+        if done {
+            self.step += 1;
+            self.entered = false;
+        }
+        Some(self.target)
+    }
+
+    fn finish(&mut self) {
+        self.running = false;
+        defmt::info!("SEQUENCE: Finished");
+    }
+
+    /// Returns true while the program is running
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Index of the executed step
+    pub fn step(&self) -> usize {
+        self.step
+    }
+}
@@ -0,0 +1,187 @@
+// Implements the command interface of the controller: a typed setpoint delivered independently
+// of the sensor tick, and the position loop turning motion setpoints into a torque command.
+
+// Key Features:
+// - Current, velocity, position and open-loop voltage angle setpoints.
+// - Setpoint is stored by the owner and applied on the next control tick.
+// - Velocity setpoints advance the target position, sub-count accurate at low speeds.
+// - One position loop (PID) serving both the velocity and the position setpoint.
+
+// Detailed Operation:
+// The command source (host, pin, C firmware) changes the setpoint whenever a new command
+// arrives, the control tick only consumes the latest one. A current setpoint is the torque
+// command directly. A position setpoint is the target position, the velocity setpoint moves
+// the target by `speed / frequency` every tick, keeping the remainder of the division so low
+// speeds stay exact. Both close the loop on the position error, the loop output is the torque
+// command limited to the configured current. A voltage angle setpoint bypasses the commutation
+// and applies the given vector in open loop, e.g. for commissioning and wiring checks.
+// Positions are in the user frame (i16 rotations + u16 angle), speeds in counts per second.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::math_integer::controllers::pid::PID;
+
+/// Default position loop gains (percent): proportional, integral, derivative
+const DEFAULT_GAINS: (i32, i32, i32) = (50, 0, 0);
+
+/// Default torque limit of the position loop (mA)
+const DEFAULT_LIMIT: i16 = 1000;
+
+/// Command the controller follows in normal operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setpoint {
+    /// Torque current (mA)
+    Current(i32),
+    /// Speed (counts per second)
+    Velocity(i32),
+    /// Target position (i16 rotations + u16 angle)
+    Position(i32),
+    /// Open-loop voltage vector: electrical angle and amplitude
+    VoltageAngle { angle: u16, amplitude: i16 },
+}
+
+impl Setpoint {
+    /// Decodes a setpoint received over protocol, `None` for an unknown mode.
+    ///
+    /// # Arguments
+    /// * `mode` - 0 current, 1 velocity, 2 position, 3 voltage angle
+    /// * `value` - Setpoint value, angle (low half) and amplitude (high half) for voltage angle
+    pub fn from_raw(mode: u8, value: u32) -> Option<Self> {
+        match mode {
+            0 => Some(Setpoint::Current(value as i32)),
+            1 => Some(Setpoint::Velocity(value as i32)),
+            2 => Some(Setpoint::Position(value as i32)),
+            3 => Some(Setpoint::VoltageAngle {
+                angle: value as u16,
+                amplitude: (value >> 16) as i16,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the setpoint is followed through the position loop
+    pub fn uses_position_loop(self) -> bool {
+        matches!(self, Setpoint::Velocity(_) | Setpoint::Position(_))
+    }
+}
+
+impl Default for Setpoint {
+    fn default() -> Self {
+        Setpoint::Current(0)
+    }
+}
+
+/// Position loop shared by the velocity and position setpoints.
+pub struct PositionLoop {
+    frequency: i32,         // Number of ticks per second
+    gains: (i32, i32, i32), // Proportional, integral, derivative gain (percent)
+    limit: i16,             // Maximum torque command (mA)
+    pid: PID,               // Position error to torque command
+    remainder: i32,         // Velocity integration remainder (counts * frequency)
+}
+
+impl PositionLoop {
+    /// Creates the loop with the default gains.
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub fn new(frequency: u16) -> Self {
+        let (kp, ki, kd) = DEFAULT_GAINS;
+        Self {
+            frequency: frequency as i32,
+            gains: DEFAULT_GAINS,
+            limit: DEFAULT_LIMIT,
+            pid: PID::new(kp, ki, kd, 0),
+            remainder: 0,
+        }
+    }
+
+    /// Changes the gains and the torque limit, the loop restarts
+    ///
+    /// # Arguments
+    /// * `kp` - Proportional gain (percent)
+    /// * `ki` - Integral gain (percent)
+    /// * `kd` - Derivative gain (percent)
+    /// * `limit` - Maximum torque command (mA)
+    pub fn configure(&mut self, kp: i32, ki: i32, kd: i32, limit: i16) {
+        self.gains = (kp, ki, kd);
+        self.limit = limit.saturating_abs();
+        self.reset();
+    }
+
+    /// Proportional, integral and derivative gain (percent)
+    #[inline(always)]
+    pub fn gains(&self) -> (i32, i32, i32) {
+        self.gains
+    }
+
+    /// Maximum torque command (mA)
+    #[inline(always)]
+    pub fn limit(&self) -> i16 {
+        self.limit
+    }
+
+    /// Clears the integral and derivative history, call when the loop takes over
+    pub fn reset(&mut self) {
+        let (kp, ki, kd) = self.gains;
+        self.pid = PID::new(kp, ki, kd, 0);
+        self.remainder = 0;
+    }
+
+    /// Returns the step (counts) the target moves this tick at the given speed
+    pub fn integrate(&mut self, speed: i32) -> i32 {
+        self.remainder = self.remainder.saturating_add(speed);
+        let step = self.remainder / self.frequency;
+        self.remainder -= step * self.frequency;
+        step
+    }
+
+    /// Math call, returns the torque command (mA)
+    ///
+    /// # Arguments
+    /// * `reference` - Reference position
+    /// * `position` - Measured position
+    pub fn tick(&mut self, reference: i32, position: i32) -> i32 {
+        self.tick_error(reference.wrapping_sub(position))
+    }
+
+    /// Math call on an error prepared by the owner, e.g. with the deadband applied, returns the
+    /// torque command (mA)
+    ///
+    /// # Arguments
+    /// * `error` - Position error, reference minus measured position (counts)
+    pub fn tick_error(&mut self, error: i32) -> i32 {
+        let error = error.clamp(i16::MIN as i32 + 1, i16::MAX as i32) as i16;
+        self.pid.tick(error, 0, self.limit);
+        self.pid.output() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math_integer::motion::in_position::InPosition;
+
+    #[test]
+    fn error_within_deadband_gives_no_torque() {
+        let mut in_position = InPosition::new(8, 91, 18, 200);
+        let mut position_loop = PositionLoop::new(20_000);
+        position_loop.configure(100, 0, 0, 1000);
+
+        // Raw error of 6 counts would be corrected
+        assert_ne!(position_loop.tick(1006, 1000), 0);
+
+        position_loop.reset();
+        for _ in 0..10 {
+            let error = in_position.tick(1006, 1000);
+            assert_eq!(error, 0);
+            assert_eq!(position_loop.tick_error(error), 0);
+        }
+
+        // Outside the deadband the full error is corrected
+        let error = in_position.tick(1100, 1000);
+        assert_eq!(error, 100);
+        assert!(position_loop.tick_error(error) > 0);
+    }
+}
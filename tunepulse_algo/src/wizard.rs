@@ -0,0 +1,557 @@
+// Implements the first-run setup wizard guiding a new user from power-up to a spinning motor.
+
+// Key Features:
+// - Fixed sequence: supply check, wiring check, impedance, encoder check, angle calibration
+//   and gain suggestion.
+// - Open-loop current vectors of the wiring, impedance and encoder steps driven by the wizard.
+// - Progress of every step reported for the motion events, results kept for readout.
+// - A failed step ends the wizard, the remaining steps stay pending.
+// - Compiled out without the `wizard` feature, the start command is then refused.
+
+// Detailed Operation:
+// The owner starts the wizard on a disabled drive and calls `tick()` every control tick with
+// the measurements of the tick. The output tells the owner what to do with the bridge: leave it
+// to the normal operation (`Released`), keep it off (`Idle`), apply an open-loop current vector
+// (`Vector`) or start the angle calibration once (`Calibrate`).
+// - Supply check: waits until the supply charged and the bridge is enabled.
+// - Wiring check: averages the current sense readings without current as zero, then drives
+//   each coil in turn (0° and 90° el). A coil whose reading doesn't move is reported open.
+// - Impedance: drives coil A again from zero current. The applied voltage follows from the
+//   test current and the configured resistance, the measured current from the sense scale,
+//   their ratio is the resistance. The rise to 63 % of the wiring check reading is the time
+//   constant, times the resistance the inductance. Skipped without a current sense scale.
+// - Encoder check: turns the vector by one electrical turn and back. The encoder has to follow
+//   by at least `ENCODER_MIN_TRAVEL` and return to the start, the travel per electrical turn
+//   gives the pole pairs.
+// - Calibration: the owner runs the regular angle calibration and reports when it finished.
+// - Gain suggestion: the preset of the motor type closest in pole pairs and resistance, its
+//   gains are a safe start. The host applies it by writing the `MotorPreset` parameter.
+// A fault in any step fails it. The owner aborts the wizard if the drive gets enabled.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use crate::motor_driver::presets::{MotorPreset, PRESET_COUNT};
+use crate::motor_driver::MotorType;
+
+/// Number of wizard steps
+pub const WIZARD_STEPS: usize = 6;
+
+/// Number of wizard results
+pub const WIZARD_RESULTS: usize = 7;
+
+/// Time the supply may take to charge (ms)
+const SUPPLY_TIMEOUT_MS: u32 = 2000;
+/// Time the current settles after a change of the vector (ms)
+const SETTLE_MS: u32 = 50;
+/// Time the current readings are averaged (ms)
+const AVERAGE_MS: u32 = 50;
+/// Time of one electrical turn of the encoder check (ms)
+const TURN_MS: u32 = 500;
+
+/// Smallest current sense reading of a connected coil (ADC counts)
+const WIRING_MIN_READING: i32 = 20;
+/// Smallest encoder travel per electrical turn (counts, 65536 per revolution)
+const ENCODER_MIN_TRAVEL: i32 = 256;
+
+/// Electrical angle of the vector driving coil B (90° el)
+const COIL_B_ANGLE: u16 = 16384;
+
+/// Steps of the wizard in order of execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WizardStep {
+    SupplyCheck = 0,
+    WiringCheck = 1,
+    Impedance = 2,
+    EncoderCheck = 3,
+    Calibration = 4,
+    GainSuggestion = 5,
+}
+
+impl WizardStep {
+    /// Returns the step following this one, `None` after the last step
+    fn next(self) -> Option<Self> {
+        match self {
+            WizardStep::SupplyCheck => Some(WizardStep::WiringCheck),
+            WizardStep::WiringCheck => Some(WizardStep::Impedance),
+            WizardStep::Impedance => Some(WizardStep::EncoderCheck),
+            WizardStep::EncoderCheck => Some(WizardStep::Calibration),
+            WizardStep::Calibration => Some(WizardStep::GainSuggestion),
+            WizardStep::GainSuggestion => None,
+        }
+    }
+}
+
+/// Outcome of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum StepOutcome {
+    /// Step didn't run yet
+    #[default]
+    Pending = 0,
+    /// Step is running
+    Running = 1,
+    Passed = 2,
+    Failed = 3,
+    /// Step couldn't measure on this board (e.g. no current sense scale)
+    Skipped = 4,
+    /// Wizard was aborted during the step
+    Aborted = 5,
+}
+
+/// Results of the wizard, the value is the index read by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WizardResult {
+    /// Supply voltage once charged (mV)
+    SupplyVoltage = 0,
+    /// Bit per coil without current (bit 0 - A, bit 1 - B), passes at 0
+    OpenCoils = 1,
+    /// Phase resistance (mOhm)
+    Resistance = 2,
+    /// Phase inductance (uH)
+    Inductance = 3,
+    /// Encoder travel per electrical turn (counts, 65536 per revolution)
+    EncoderTravel = 4,
+    /// Pole pairs estimated from the encoder travel
+    PolePairs = 5,
+    /// Suggested `MotorPreset` (0 - no preset of the motor type)
+    SuggestedPreset = 6,
+}
+
+impl WizardResult {
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(WizardResult::SupplyVoltage),
+            1 => Some(WizardResult::OpenCoils),
+            2 => Some(WizardResult::Resistance),
+            3 => Some(WizardResult::Inductance),
+            4 => Some(WizardResult::EncoderTravel),
+            5 => Some(WizardResult::PolePairs),
+            6 => Some(WizardResult::SuggestedPreset),
+            _ => None,
+        }
+    }
+
+    /// Step measuring the result
+    pub fn step(self) -> WizardStep {
+        match self {
+            WizardResult::SupplyVoltage => WizardStep::SupplyCheck,
+            WizardResult::OpenCoils => WizardStep::WiringCheck,
+            WizardResult::Resistance | WizardResult::Inductance => WizardStep::Impedance,
+            WizardResult::EncoderTravel | WizardResult::PolePairs => WizardStep::EncoderCheck,
+            WizardResult::SuggestedPreset => WizardStep::GainSuggestion,
+        }
+    }
+}
+
+/// Progress of a step reported to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WizardProgress {
+    pub step: WizardStep,
+    pub outcome: StepOutcome,
+}
+
+impl WizardProgress {
+    /// Event argument: step (bits 0..7), outcome (bits 8..15)
+    pub fn arg(self) -> u32 {
+        self.step as u32 | (self.outcome as u32) << 8
+    }
+}
+
+/// Measurements of one control tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WizardInputs {
+    pub supply_ready: bool, // Supply charged and bridge enabled
+    pub supply_mv: i32,     // Supply voltage
+    pub currents: [i32; 2], // Current sense readings of coil A and B (raw ADC)
+    pub position: i32,      // Encoder position (65536 per revolution)
+    pub calibrated: bool,   // Angle calibration finished
+    pub fault: bool,        // Controller is in the error state
+}
+
+/// What the owner does with the bridge this tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardOutput {
+    /// Normal operation drives the bridge
+    Released,
+    /// No current
+    Idle,
+    /// Open-loop current vector: electrical angle and amplitude (mA)
+    Vector { angle: u16, current: i16 },
+    /// Start the angle calibration, normal operation runs it
+    Calibrate,
+}
+
+/// Setup wizard state machine.
+pub struct SetupWizard {
+    frequency: u32,                         // Ticks per second
+    step: Option<WizardStep>,               // Running step, `None` while idle
+    phase: u8,                              // Sub-state of the running step
+    ticks: u32,                             // Ticks spent in the phase
+    sum: [i32; 2],                          // Current readings summed over the average window
+    offsets: [i32; 2],                      // Current readings without current
+    readings: [i32; 2],                     // Current readings of each driven coil above zero
+    rise_ticks: Option<u32>,                // Ticks of the impedance current to 63 %
+    positions: [i32; 2],                    // Encoder position before and after the turn
+    current: i16,                           // Test current (mA)
+    resistance: i32,                        // Configured phase resistance (mOhm)
+    current_scale: u32,                     // Current sense scale (uA per ADC count, 0 - unknown)
+    motor_type: MotorType,                  // Motor type of the suggested presets
+    outcomes: [StepOutcome; WIZARD_STEPS],  // Outcome of every step
+    results: [Option<i32>; WIZARD_RESULTS], // Results indexed by `WizardResult`
+    pending: [Option<WizardProgress>; 2],   // Progress not yet taken by the owner
+}
+
+impl SetupWizard {
+    /// Creates an idle wizard
+    ///
+    /// # Arguments
+    /// * `frequency` - Number of ticks per second
+    pub const fn new(frequency: u16) -> Self {
+        Self {
+            frequency: frequency as u32,
+            step: None,
+            phase: 0,
+            ticks: 0,
+            sum: [0; 2],
+            offsets: [0; 2],
+            readings: [0; 2],
+            rise_ticks: None,
+            positions: [0; 2],
+            current: 0,
+            resistance: 0,
+            current_scale: 0,
+            motor_type: MotorType::UNDEFINED,
+            outcomes: [StepOutcome::Pending; WIZARD_STEPS],
+            results: [None; WIZARD_RESULTS],
+            pending: [None; 2],
+        }
+    }
+
+    /// Starts the wizard, results of the previous run are cleared.
+    ///
+    /// # Arguments
+    /// * `current` - Test current of the open-loop steps (mA)
+    /// * `resistance` - Configured phase resistance (mOhm), converts the current to voltage
+    /// * `current_scale` - Current sense scale (uA per ADC count, 0 - impedance is skipped)
+    /// * `motor_type` - Motor type of the suggested preset
+    pub fn start(
+        &mut self,
+        current: i16,
+        resistance: i32,
+        current_scale: u32,
+        motor_type: MotorType,
+    ) {
+        self.current = current;
+        self.resistance = resistance;
+        self.current_scale = current_scale;
+        self.motor_type = motor_type;
+        self.outcomes = [StepOutcome::Pending; WIZARD_STEPS];
+        self.results = [None; WIZARD_RESULTS];
+        self.pending = [None; 2];
+        self.enter(WizardStep::SupplyCheck);
+    }
+
+    /// Aborts the running step, the bridge is released
+    pub fn abort(&mut self) {
+        if self.step.is_some() {
+            self.finish(StepOutcome::Aborted);
+            self.step = None;
+        }
+    }
+
+    /// Returns true while the wizard runs
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Running step, `None` while idle
+    #[inline(always)]
+    pub fn step(&self) -> Option<WizardStep> {
+        self.step
+    }
+
+    /// Outcome of a step of the last run
+    pub fn outcome(&self, step: WizardStep) -> StepOutcome {
+        self.outcomes[step as usize]
+    }
+
+    /// Result of the last run and the outcome of its step, the value is `None` if not measured
+    pub fn result(&self, result: WizardResult) -> (StepOutcome, Option<i32>) {
+        (self.outcome(result.step()), self.results[result as usize])
+    }
+
+    /// Takes the oldest progress not reported yet
+    pub fn take_progress(&mut self) -> Option<WizardProgress> {
+        let progress = self.pending[0].take();
+        self.pending.rotate_left(1);
+        progress
+    }
+
+    /// Math call, returns what to do with the bridge this tick
+    pub fn tick(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        let Some(step) = self.step else {
+            return WizardOutput::Released;
+        };
+        if inputs.fault {
+            self.fail();
+            return WizardOutput::Released;
+        }
+        self.ticks += 1;
+        match step {
+            WizardStep::SupplyCheck => self.tick_supply(inputs),
+            WizardStep::WiringCheck => self.tick_wiring(inputs),
+            WizardStep::Impedance => self.tick_impedance(inputs),
+            WizardStep::EncoderCheck => self.tick_encoder(inputs),
+            WizardStep::Calibration => self.tick_calibration(inputs),
+            WizardStep::GainSuggestion => {
+                self.suggest_preset();
+                WizardOutput::Released
+            }
+        }
+    }
+
+    fn tick_supply(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        if inputs.supply_ready {
+            self.results[WizardResult::SupplyVoltage as usize] = Some(inputs.supply_mv);
+            self.pass();
+        } else if self.ticks >= self.ms(SUPPLY_TIMEOUT_MS) {
+            self.results[WizardResult::SupplyVoltage as usize] = Some(inputs.supply_mv);
+            self.fail();
+        }
+        WizardOutput::Idle
+    }
+
+    fn tick_wiring(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        let output = match self.phase {
+            0 => WizardOutput::Idle,
+            1 => self.vector(0),
+            _ => self.vector(COIL_B_ANGLE),
+        };
+        let Some(average) = self.average(inputs) else {
+            return output;
+        };
+        match self.phase {
+            0 => self.offsets = average,
+            1 => self.readings[0] = (average[0] - self.offsets[0]).abs(),
+            _ => {
+                self.readings[1] = (average[1] - self.offsets[1]).abs();
+                let open = (0..2)
+                    .filter(|&coil| self.readings[coil] < WIRING_MIN_READING)
+                    .fold(0, |mask, coil| mask | 1 << coil);
+                self.results[WizardResult::OpenCoils as usize] = Some(open);
+                if open == 0 {
+                    self.pass();
+                } else {
+                    self.fail();
+                }
+                return WizardOutput::Idle;
+            }
+        }
+        self.next_phase();
+        output
+    }
+
+    fn tick_impedance(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        if self.current_scale == 0 {
+            self.finish(StepOutcome::Skipped);
+            self.advance();
+            return WizardOutput::Idle;
+        }
+        if self.phase == 0 {
+            // Current of the wiring check decays first
+            if self.ticks >= self.ms(SETTLE_MS) {
+                self.next_phase();
+            }
+            return WizardOutput::Idle;
+        }
+        let reading = (inputs.currents[0] - self.offsets[0]).abs();
+        if self.rise_ticks.is_none() && reading >= self.readings[0] * 632 / 1000 {
+            self.rise_ticks = Some(self.ticks);
+        }
+        let output = self.vector(0);
+        let Some(average) = self.average(inputs) else {
+            return output;
+        };
+        // Applied voltage from the configured resistance, current from the sense scale
+        let reading = (average[0] - self.offsets[0]).abs() as i64;
+        let current_ma = reading * self.current_scale as i64 / 1000;
+        if current_ma <= 0 {
+            self.fail();
+            return WizardOutput::Idle;
+        }
+        let voltage_mv = self.current as i64 * self.resistance as i64 / 1000;
+        let resistance = (voltage_mv * 1000 / current_ma).min(i32::MAX as i64) as i32;
+        self.results[WizardResult::Resistance as usize] = Some(resistance);
+        if let Some(ticks) = self.rise_ticks {
+            let inductance = resistance as i64 * ticks as i64 * 1000 / self.frequency as i64;
+            self.results[WizardResult::Inductance as usize] =
+                Some(inductance.min(i32::MAX as i64) as i32);
+        }
+        self.pass();
+        WizardOutput::Idle
+    }
+
+    fn tick_encoder(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        let settle = self.ms(SETTLE_MS);
+        let turn = self.ms(TURN_MS);
+        match self.phase {
+            // Rotor aligns to 0° el
+            0 => {
+                if self.ticks >= settle {
+                    self.positions[0] = inputs.position;
+                    self.next_phase();
+                }
+                self.vector(0)
+            }
+            // One electrical turn forward and back, then settle
+            1 | 2 => {
+                let travel = (self.ticks.min(turn) as u64 * 65536 / turn as u64) as u16;
+                let angle = if self.phase == 1 {
+                    travel
+                } else {
+                    travel.wrapping_neg()
+                };
+                if self.ticks < turn + settle {
+                    return self.vector(angle);
+                }
+                if self.phase == 1 {
+                    self.positions[1] = inputs.position;
+                    self.next_phase();
+                    return self.vector(0);
+                }
+                let travel = self.positions[1].wrapping_sub(self.positions[0]).abs();
+                let back = inputs.position.wrapping_sub(self.positions[0]).abs();
+                self.results[WizardResult::EncoderTravel as usize] = Some(travel);
+                if travel >= ENCODER_MIN_TRAVEL {
+                    let pole_pairs = (65536 + travel / 2) / travel;
+                    self.results[WizardResult::PolePairs as usize] = Some(pole_pairs);
+                }
+                if travel >= ENCODER_MIN_TRAVEL && back <= travel / 4 {
+                    self.pass();
+                } else {
+                    self.fail();
+                }
+                WizardOutput::Idle
+            }
+            _ => WizardOutput::Idle,
+        }
+    }
+
+    fn tick_calibration(&mut self, inputs: &WizardInputs) -> WizardOutput {
+        if self.phase == 0 {
+            self.next_phase();
+            return WizardOutput::Calibrate;
+        }
+        if inputs.calibrated {
+            self.pass();
+        }
+        WizardOutput::Released
+    }
+
+    /// Picks the preset of the motor type closest in pole pairs, then in resistance
+    fn suggest_preset(&mut self) {
+        let pole_pairs = self.results[WizardResult::PolePairs as usize];
+        let resistance = self.results[WizardResult::Resistance as usize];
+        let distance = |preset: MotorPreset| {
+            let data = preset.data()?;
+            if data.motor_type != self.motor_type {
+                return None;
+            }
+            let poles = pole_pairs.map_or(0, |pairs| (data.pole_pairs as i32 - pairs).abs());
+            let ohms = resistance.map_or(0, |r| (data.resistance - r).abs());
+            Some((poles, ohms))
+        };
+        let suggested = (1..PRESET_COUNT as u32)
+            .filter_map(MotorPreset::from_raw)
+            .filter_map(|preset| Some((distance(preset)?, preset)))
+            .min_by_key(|&(distance, _)| distance)
+            .map_or(MotorPreset::Custom, |(_, preset)| preset);
+        self.results[WizardResult::SuggestedPreset as usize] = Some(suggested as i32);
+        self.pass();
+    }
+
+    /// Sums the current readings after the settle time, returns the average once the window
+    /// is complete
+    fn average(&mut self, inputs: &WizardInputs) -> Option<[i32; 2]> {
+        let settle = self.ms(SETTLE_MS);
+        if self.ticks <= settle {
+            return None;
+        }
+        self.sum[0] += inputs.currents[0];
+        self.sum[1] += inputs.currents[1];
+        let count = (self.ticks - settle) as i32;
+        if count < self.ms(AVERAGE_MS) as i32 {
+            return None;
+        }
+        Some([self.sum[0] / count, self.sum[1] / count])
+    }
+
+    fn vector(&self, angle: u16) -> WizardOutput {
+        WizardOutput::Vector {
+            angle,
+            current: self.current,
+        }
+    }
+
+    /// Converts a duration to ticks, at least one
+    fn ms(&self, ms: u32) -> u32 {
+        (ms * self.frequency / 1000).max(1)
+    }
+
+    fn next_phase(&mut self) {
+        self.phase += 1;
+        self.ticks = 0;
+        self.sum = [0; 2];
+    }
+
+    fn enter(&mut self, step: WizardStep) {
+        self.step = Some(step);
+        self.phase = 0;
+        self.ticks = 0;
+        self.sum = [0; 2];
+        self.rise_ticks = None;
+        self.outcomes[step as usize] = StepOutcome::Running;
+        self.report(step, StepOutcome::Running);
+    }
+
+    /// Records the outcome of the running step
+    fn finish(&mut self, outcome: StepOutcome) {
+        if let Some(step) = self.step {
+            self.outcomes[step as usize] = outcome;
+            self.report(step, outcome);
+        }
+    }
+
+    fn pass(&mut self) {
+        self.finish(StepOutcome::Passed);
+        self.advance();
+    }
+
+    fn fail(&mut self) {
+        self.finish(StepOutcome::Failed);
+        self.step = None;
+    }
+
+    fn advance(&mut self) {
+        match self.step.and_then(WizardStep::next) {
+            Some(step) => self.enter(step),
+            None => self.step = None,
+        }
+    }
+
+    fn report(&mut self, step: WizardStep, outcome: StepOutcome) {
+        let progress = WizardProgress { step, outcome };
+        match self.pending.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(progress),
+            None => {
+                // Owner didn't take the older progress, keep the latest
+                self.pending.rotate_left(1);
+                self.pending[1] = Some(progress);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Implements the driver of the motor bridge enable and reset pins.
+
+// Key Features:
+// - Takes the bridge out of reset at start-up, the outputs stay disabled until enabled.
+// - Switches the bridge outputs on and off at runtime, off leaves the phases floating.
+// - Works with any embedded-hal output pins, on-board pins by default.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use hal::gpio::Pin;
+
+use crate::interfaces::OutputPin;
+use crate::pinout::driver;
+
+pub struct BridgeEnable<P: OutputPin = Pin> {
+    enable: P,
+    enabled: bool,
+}
+
+impl BridgeEnable {
+    /// Initializes on-board driver pins with the bridge out of reset and disabled
+    pub fn new() -> Self {
+        let mut reset = driver::RESET.init();
+        reset.set_high();
+        Self::from_pins(driver::ENABLE.init())
+    }
+}
+
+impl<P: OutputPin> BridgeEnable<P> {
+    /// Takes the active-high enable pin and disables the bridge
+    pub fn from_pins(enable: P) -> Self {
+        let mut bridge = Self {
+            enable,
+            enabled: true,
+        };
+        bridge.set(false);
+        bridge
+    }
+
+    /// Switches the bridge outputs, the pin is only written on changes
+    pub fn set(&mut self, enabled: bool) {
+        if enabled == self.enabled {
+            return;
+        }
+        // A failed update is retried by the next call
+        let result = if enabled {
+            self.enable.set_high()
+        } else {
+            self.enable.set_low()
+        };
+        if result.is_ok() {
+            self.enabled = enabled;
+        }
+    }
+}
+
+impl Default for BridgeEnable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,279 @@
+// Implements the driver of the FDCAN1 peripheral as classic CAN link for the host protocol.
+
+// Key Features:
+// - Classic CAN 2.0A frames: 11 bit identifiers, up to 8 data bytes, up to 1 Mbit/s.
+// - Hardware acceptance filters, frames of other nodes never reach the receive FIFO.
+// - Non-blocking: frames go to the 3 entry transmit FIFO, a full FIFO is reported.
+// - Receive FIFO interrupt on line 0 (FDCAN1_INTR0_IT), error counters and bus-off recovery.
+
+// Detailed Operation:
+// The peripheral runs from PCLK1. A bit takes 17 time quanta: sync, 13 before and 3 after the
+// sample point (82 %, within the CiA recommendation), the prescaler follows from the clock and
+// the bit rate, e.g. 20 at 170 MHz and 500 kbit/s. Bit rates the clock can't divide exactly
+// are rounded to the nearest prescaler.
+// The message RAM of the G4 has a fixed layout per instance (RM0440 44.3.3), this driver uses
+// the standard filter elements, receive FIFO 0 and the transmit FIFO. Every filter stores the
+// frames matching its `id & mask` in FIFO 0, any other, extended or remote frame is rejected.
+// The filters can only change in the initialization mode: `set_filters()` takes the node off
+// the bus for the update, pending transmissions are kept.
+// `transmit()` writes the frame to the put index of the transmit FIFO and requests it, the
+// peripheral retransmits until it wins the arbitration and gets an acknowledge. `receive()`
+// reads the oldest frame of FIFO 0 and releases it. FIFO 0 holds 3 frames: at 1 Mbit/s a frame
+// takes at least 47 us, so reading it from the receive interrupt or every control tick keeps up.
+// After 256 transmit errors the peripheral goes bus-off and stops in the initialization mode,
+// `recover()` leaves it, the node rejoins the bus after 128 occurrences of 11 recessive bits.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr;
+
+use hal::clocks::Clocks;
+use hal::pac::{FDCAN1, RCC};
+
+use super::pinout;
+
+/// Start of the message RAM of FDCAN1
+const MESSAGE_RAM: usize = 0x4000_A400;
+/// Size of the message RAM of one instance in words
+const MESSAGE_RAM_WORDS: usize = 212;
+/// Word offsets of the sections used in the message RAM
+const STD_FILTER: usize = 0;
+const RX_FIFO0: usize = 0x0B0 / 4;
+const TX_BUFFER: usize = 0x278 / 4;
+/// Size of a receive or transmit element in words (header and 64 data bytes)
+const ELEMENT_WORDS: usize = 18;
+
+/// Time quanta per bit and their split around the sample point
+const BIT_QUANTA: u32 = 17;
+const SEG1_QUANTA: u32 = 13;
+const SEG2_QUANTA: u32 = 3;
+/// Maximum nominal bit rate prescaler
+const MAX_PRESCALER: u32 = 512;
+
+/// CCCR bits
+const CCCR_INIT: u32 = 1 << 0;
+const CCCR_CCE: u32 = 1 << 1;
+/// RXGFC: non-matching and remote frames rejected, the number of standard filters at bit 16
+const RXGFC_CONFIG: u32 = 0b10 << 4 | 0b10 << 2 | 1 << 1 | 1;
+/// Number of standard filter elements in the message RAM
+pub const MAX_FILTERS: usize = 28;
+/// Standard filter element: classic filter with mask, matches stored in FIFO 0
+const FILTER_TO_FIFO0: u32 = 0b10 << 30 | 0b001 << 27;
+/// Interrupt bits: new frame in receive FIFO 0, frame lost in receive FIFO 0
+const IR_RF0N: u32 = 1 << 0;
+const IR_RF0L: u32 = 1 << 2;
+/// Interrupt line enable of line 0
+const ILE_EINT0: u32 = 1 << 0;
+/// PSR bus-off state
+const PSR_BO: u32 = 1 << 7;
+/// TXFQS transmit FIFO full
+const TXFQS_TFQF: u32 = 1 << 21;
+
+/// Maximum data bytes of a classic CAN frame
+pub const MAX_DATA: usize = 8;
+
+/// Received CAN frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanFrame {
+    pub id: u16,              // Standard identifier (11 bit)
+    pub len: u8,              // Number of data bytes
+    pub data: [u8; MAX_DATA], // Data bytes, zero beyond `len`
+}
+
+impl CanFrame {
+    /// Received data bytes
+    #[inline(always)]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+pub struct CanBus {
+    can: FDCAN1,
+}
+
+impl CanBus {
+    /// Configures the pins and the peripheral and joins the bus
+    ///
+    /// # Arguments
+    /// * `bitrate` - Nominal bit rate (bit/s)
+    /// * `filters` - Accepted identifier and the identifier bits compared, per filter
+    pub fn new(can: FDCAN1, clock_cfg: &Clocks, bitrate: u32, filters: &[(u16, u16)]) -> Self {
+        // SAFETY: read-modify-writes during initialization, before any other user of the
+        // registers runs
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.ccipr.modify(|_, w| w.fdcansel().pclk());
+        rcc.apb1enr1.modify(|_, w| w.fdcanen().set_bit());
+
+        pinout::can::RX.init();
+        pinout::can::TX.init();
+
+        let mut bus = Self { can };
+        bus.enter_init();
+        for word in 0..MESSAGE_RAM_WORDS {
+            Self::write_ram(word, 0);
+        }
+        let prescaler = ((clock_cfg.apb1() + bitrate * BIT_QUANTA / 2) / (bitrate * BIT_QUANTA))
+            .clamp(1, MAX_PRESCALER);
+        // SAFETY: raw values are valid settings of the written registers
+        unsafe {
+            bus.can.nbtp.write(|w| {
+                // Resynchronization jump as wide as the phase after the sample point
+                w.bits(
+                    (SEG2_QUANTA - 1) << 25
+                        | (prescaler - 1) << 16
+                        | (SEG1_QUANTA - 1) << 8
+                        | (SEG2_QUANTA - 1),
+                )
+            });
+            bus.can.txbc.write(|w| w.bits(0)); // FIFO mode, frames leave in order
+            bus.can.ie.write(|w| w.bits(IR_RF0N));
+            bus.can.ils.write(|w| w.bits(0)); // Receive FIFO 0 group on line 0
+            bus.can.ile.write(|w| w.bits(ILE_EINT0));
+        }
+        bus.write_filters(filters);
+        bus.leave_init();
+        bus
+    }
+
+    /// Changes the acceptance filters, the node leaves the bus during the update
+    ///
+    /// # Arguments
+    /// * `filters` - Accepted identifier and the identifier bits compared, per filter
+    pub fn set_filters(&mut self, filters: &[(u16, u16)]) {
+        self.enter_init();
+        self.write_filters(filters);
+        self.leave_init();
+    }
+
+    /// Queues a frame for transmission, returns false if the transmit FIFO is full
+    ///
+    /// # Arguments
+    /// * `id` - Standard identifier (11 bit)
+    /// * `data` - Data bytes, truncated to 8
+    pub fn transmit(&mut self, id: u16, data: &[u8]) -> bool {
+        let status = self.can.txfqs.read().bits();
+        if status & TXFQS_TFQF != 0 || self.is_bus_off() {
+            return false;
+        }
+        let index = ((status >> 16) & 0b11) as usize;
+        let len = data.len().min(MAX_DATA);
+        let mut bytes = [0u8; MAX_DATA];
+        bytes[..len].copy_from_slice(&data[..len]);
+
+        let element = TX_BUFFER + index * ELEMENT_WORDS;
+        Self::write_ram(element, ((id & 0x7FF) as u32) << 18);
+        Self::write_ram(element + 1, (len as u32) << 16);
+        Self::write_ram(
+            element + 2,
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        );
+        Self::write_ram(
+            element + 3,
+            u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        );
+        // SAFETY: the bit requests the element just written
+        unsafe { self.can.txbar.write(|w| w.bits(1 << index)) };
+        true
+    }
+
+    /// Takes the oldest received frame, `None` if the receive FIFO is empty
+    pub fn receive(&mut self) -> Option<CanFrame> {
+        let status = self.can.rxf0s.read().bits();
+        if status & 0xF == 0 {
+            return None;
+        }
+        let index = ((status >> 8) & 0b11) as usize;
+        let element = RX_FIFO0 + index * ELEMENT_WORDS;
+        let header = Self::read_ram(element);
+        let len = ((Self::read_ram(element + 1) >> 16) & 0xF).min(MAX_DATA as u32) as u8;
+        let mut data = [0u8; MAX_DATA];
+        data[..4].copy_from_slice(&Self::read_ram(element + 2).to_le_bytes());
+        data[4..].copy_from_slice(&Self::read_ram(element + 3).to_le_bytes());
+        data[len as usize..].fill(0);
+        // SAFETY: acknowledging the element just read releases it
+        unsafe { self.can.rxf0a.write(|w| w.bits(index as u32)) };
+        Some(CanFrame {
+            id: ((header >> 18) & 0x7FF) as u16,
+            len,
+            data,
+        })
+    }
+
+    /// Clears the receive interrupt flags, returns true if frames were lost since the last call
+    pub fn clear_interrupts(&mut self) -> bool {
+        let flags = self.can.ir.read().bits();
+        // SAFETY: writing ones clears the flags read
+        unsafe { self.can.ir.write(|w| w.bits(flags & (IR_RF0N | IR_RF0L))) };
+        flags & IR_RF0L != 0
+    }
+
+    /// True while the node is bus-off and doesn't take part in the traffic
+    #[inline(always)]
+    pub fn is_bus_off(&self) -> bool {
+        self.can.psr.read().bits() & PSR_BO != 0
+    }
+
+    /// Starts the bus-off recovery, returns true if the node was bus-off
+    pub fn recover(&mut self) -> bool {
+        if !self.is_bus_off() {
+            return false;
+        }
+        self.leave_init();
+        true
+    }
+
+    /// Transmit and receive error counters
+    pub fn errors(&self) -> (u8, u8) {
+        let ecr = self.can.ecr.read().bits();
+        (ecr as u8, (ecr >> 8) as u8 & 0x7F)
+    }
+
+    /// Stops the protocol controller and unlocks the configuration registers
+    fn enter_init(&mut self) {
+        // SAFETY: INIT and CCE are the only bits set, the others keep their defaults
+        unsafe {
+            self.can.cccr.write(|w| w.bits(CCCR_INIT));
+            while self.can.cccr.read().bits() & CCCR_INIT == 0 {}
+            self.can.cccr.write(|w| w.bits(CCCR_INIT | CCCR_CCE));
+        }
+    }
+
+    /// Joins the bus after 11 recessive bits, classic CAN with automatic retransmission
+    fn leave_init(&mut self) {
+        // SAFETY: clearing INIT also locks the configuration registers again
+        unsafe { self.can.cccr.write(|w| w.bits(0)) };
+    }
+
+    /// Writes the filter elements and their number, beyond `MAX_FILTERS` they are ignored
+    fn write_filters(&mut self, filters: &[(u16, u16)]) {
+        let count = filters.len().min(MAX_FILTERS);
+        for (element, &(id, mask)) in filters[..count].iter().enumerate() {
+            Self::write_ram(STD_FILTER + element, Self::filter_element(id, mask));
+        }
+        // SAFETY: raw value is a valid setting of the global filter
+        unsafe {
+            self.can
+                .rxgfc
+                .write(|w| w.bits(RXGFC_CONFIG | (count as u32) << 16))
+        };
+    }
+
+    /// Standard filter element accepting `id & mask` into receive FIFO 0
+    fn filter_element(id: u16, mask: u16) -> u32 {
+        FILTER_TO_FIFO0 | ((id & 0x7FF) as u32) << 16 | (mask & 0x7FF) as u32
+    }
+
+    #[inline(always)]
+    fn write_ram(word: usize, value: u32) {
+        // SAFETY: the offsets stay within the message RAM of FDCAN1 owned by this driver
+        unsafe { ptr::write_volatile((MESSAGE_RAM as *mut u32).add(word), value) };
+    }
+
+    #[inline(always)]
+    fn read_ram(word: usize) -> u32 {
+        // SAFETY: the offsets stay within the message RAM of FDCAN1 owned by this driver
+        unsafe { ptr::read_volatile((MESSAGE_RAM as *const u32).add(word)) }
+    }
+}
@@ -0,0 +1,20 @@
+// Implements reading of the factory programmed unique device identifier of the MCU.
+
+// Key Features:
+// - Returns the 96 bit UID as stored in the system memory of STM32G4 devices.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Address of the unique device identifier (RM0440, 48.1)
+const UID_BASE: usize = 0x1FFF_7590;
+
+/// Reads the 96 bit unique device identifier, lowest address first
+pub fn uid() -> [u8; 12] {
+    let mut uid = [0; 12];
+    for (i, byte) in uid.iter_mut().enumerate() {
+        // SAFETY: the UID area is always mapped and read-only
+        *byte = unsafe { core::ptr::read_volatile((UID_BASE + i) as *const u8) };
+    }
+    uid
+}
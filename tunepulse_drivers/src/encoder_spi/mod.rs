@@ -2,21 +2,48 @@ use hal::{
     self,
     gpio::Pin,
     pac::SPI1,
-    spi::{BaudRate, Spi, SpiConfig, SpiMode},
+    spi::{BaudRate, Spi, SpiConfig},
 };
 
+use crate::interfaces::{AngleSensor, SpiDevice};
+
 use super::pinout;
 
+/// Angle read request, the answer is returned in the last two bytes
+pub const READ_COMMAND: [u8; 4] = [0x80, 0x20, 0x00, 0x00];
+
+/// Extracts the angle (0..65535) from the answer to `READ_COMMAND`
+pub fn decode_angle(buf: [u8; 4]) -> u16 {
+    let respond = ((buf[2] as u16) << 8) | buf[3] as u16;
+    respond << 1
+}
+
+/// What follows a completed transfer of `Spi1DMA`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadStep {
+    /// Sample complete
+    Done,
+    /// Second read of the pair has to follow
+    Check,
+    /// Reads of the pair disagreed, the pair has to be repeated
+    Retry,
+}
+
 pub struct Spi1DMA {
     pub spi: Spi<SPI1>,
     cs_pin: Pin,
     angle: u16,
+    check: u16,     // Angle of the second read, equal to `angle` with single reads
+    threshold: u16, // Largest accepted difference of a read pair, 0 - single read
+    retry: bool,    // Repeat a disagreeing pair once per sample
+    retried: bool,  // The pair of the running sample was repeated
+    second: bool,   // The running transfer is the second read of a pair
 }
 
 impl Spi1DMA {
     pub fn new(spi_reg: SPI1) -> Self {
         let spi_cfg = SpiConfig {
-            mode: SpiMode::mode1(),
+            mode: embedded_hal::spi::MODE_1,
             ..Default::default()
         };
 
@@ -32,6 +59,11 @@ impl Spi1DMA {
             spi: spi1,
             cs_pin,
             angle: 0,
+            check: 0,
+            threshold: 0,
+            retry: false,
+            retried: false,
+            second: false,
         }
     }
 
@@ -47,14 +79,82 @@ impl Spi1DMA {
         self.angle
     }
 
+    /// Angle of the second read of the last pair, the angle itself with single reads
+    pub fn get_check(&mut self) -> u16 {
+        self.check
+    }
+
+    /// Configures the read pair check of the next sample
+    ///
+    /// # Arguments
+    /// * `threshold` - Largest accepted difference of the two reads, 0 - single read
+    /// * `retry` - Repeat the pair once if the reads disagree
+    pub fn set_check(&mut self, threshold: u16, retry: bool) {
+        self.threshold = threshold;
+        self.retry = retry;
+        self.retried = false;
+    }
+
     pub fn start(&mut self) {
         self.cs_pin.set_low();
     }
 
-    pub fn end(&mut self, buf: [u8; 4]) -> u16 {
+    /// Completes a transfer, returns the transfer which has to follow
+    pub fn end(&mut self, buf: [u8; 4]) -> ReadStep {
         self.cs_pin.set_high();
-        let respond = ((buf[2] as u16) << 8) | buf[3] as u16;
-        self.angle = respond << 1;
+        let angle = decode_angle(buf);
+        if !self.second {
+            self.angle = angle;
+            self.check = angle;
+            self.second = self.threshold != 0;
+            return if self.second {
+                ReadStep::Check
+            } else {
+                ReadStep::Done
+            };
+        }
+        self.second = false;
+        self.check = angle;
+        let difference = (self.check.wrapping_sub(self.angle) as i16).unsigned_abs();
+        if self.retry && !self.retried && difference > self.threshold {
+            self.retried = true;
+            return ReadStep::Retry;
+        }
+        ReadStep::Done
+    }
+}
+
+impl AngleSensor for Spi1DMA {
+    /// Angle of the last completed DMA transfer
+    fn angle(&mut self) -> u16 {
         self.angle
     }
 }
+
+/// Encoder read by blocking transfers on an embedded-hal SPI device (mode 1),
+/// for targets other than STM32G4
+pub struct SpiEncoder<SPI: SpiDevice> {
+    spi: SPI,
+    angle: u16,
+}
+
+impl<SPI: SpiDevice> SpiEncoder<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self { spi, angle: 0 }
+    }
+
+    /// Reads the angle from the encoder
+    pub fn read_angle(&mut self) -> Result<u16, SPI::Error> {
+        let mut buf = READ_COMMAND;
+        self.spi.transfer_in_place(&mut buf)?;
+        self.angle = decode_angle(buf);
+        Ok(self.angle)
+    }
+}
+
+impl<SPI: SpiDevice> AngleSensor for SpiEncoder<SPI> {
+    /// Reads the angle, the last one is kept if the transfer fails
+    fn angle(&mut self) -> u16 {
+        self.read_angle().unwrap_or(self.angle)
+    }
+}
@@ -0,0 +1,172 @@
+// Implements erase and programming of the internal flash of the STM32G431 at register level.
+
+// Key Features:
+// - Page erase and double word programming of the single bank flash.
+// - Controller unlocked only for the duration of an operation.
+// - Worst case stall times so callers can schedule operations around the control loop.
+// - Location of the configuration and factory data areas reserved in `memory.x`.
+
+// Detailed Operation:
+// While the flash controller erases or programs, every read from flash (instruction fetch,
+// vector fetch, constants) stalls until the operation ends, interrupts included. The
+// functions here start an operation and wait for BSY to clear, so they return only after the
+// stall. The caller decides when such a stall is acceptable, see
+// `tunepulse_algo::scheduler::Budget::allow_stall()`. Error flags left by a previous operation
+// are cleared before starting, the flags set by the operation are reported as `FlashError`.
+// The data cache is reset after an erase so reads don't return the old content.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use hal::pac::FLASH;
+
+/// Start of the main flash
+pub const FLASH_BASE: u32 = 0x0800_0000;
+/// Size of the main flash
+pub const FLASH_SIZE: usize = 128 * 1024;
+/// Size of an erase page
+pub const PAGE_SIZE: usize = 2048;
+
+/// Start of the configuration area (CONFIG region in `memory.x`)
+pub const CONFIG_ADDRESS: u32 = 0x0801_F000;
+/// Size of the configuration area
+pub const CONFIG_SIZE: usize = 4096;
+
+/// Start of the factory data area (FACTORY region in `memory.x`)
+pub const FACTORY_ADDRESS: u32 = 0x0801_E800;
+/// Size of the factory data area
+pub const FACTORY_SIZE: usize = 2048;
+
+/// Worst case stall of a page erase in core cycles (24.5 ms at 170 MHz)
+pub const ERASE_CYCLES: u32 = 4_165_000;
+/// Worst case stall of a double word program in core cycles (91 µs at 170 MHz)
+pub const PROGRAM_CYCLES: u32 = 15_470;
+
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+
+/// Errors reported by flash operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashError {
+    /// Address outside of the main flash or not aligned
+    OutOfRange,
+    /// Controller stays locked after writing the keys (locked until reset)
+    Locked,
+    /// Page is write protected
+    WriteProtected,
+    /// Programming sequence, alignment or size error reported by the controller
+    Program,
+}
+
+/// Internal flash controller
+pub struct Flash {
+    regs: FLASH,
+}
+
+impl Flash {
+    pub fn new(regs: FLASH) -> Self {
+        Self { regs }
+    }
+
+    /// Erases the page containing `address`, stalls for up to `ERASE_CYCLES`
+    pub fn erase_page(&mut self, address: u32) -> Result<(), FlashError> {
+        let page = Self::offset(address, 1)? / PAGE_SIZE as u32;
+        self.unlock()?;
+        self.regs
+            .cr
+            .modify(|_, w| unsafe { w.per().set_bit().pnb().bits(page as u8) });
+        self.regs.cr.modify(|_, w| w.strt().set_bit());
+        let result = self.finish();
+        self.regs.cr.modify(|_, w| w.per().clear_bit());
+        self.lock();
+
+        // Drop cached lines of the erased page
+        self.regs.acr.modify(|_, w| w.dcen().clear_bit());
+        self.regs.acr.modify(|_, w| w.dcrst().set_bit());
+        self.regs.acr.modify(|_, w| w.dcrst().clear_bit());
+        self.regs.acr.modify(|_, w| w.dcen().set_bit());
+        result
+    }
+
+    /// Programs the erased double word at `address`, stalls for up to `PROGRAM_CYCLES`
+    ///
+    /// # Arguments
+    /// * `address` - Target address, 8 byte aligned
+    /// * `word` - Value stored little endian
+    pub fn program(&mut self, address: u32, word: u64) -> Result<(), FlashError> {
+        Self::offset(address, 8)?;
+        self.unlock()?;
+        self.regs.cr.modify(|_, w| w.pg().set_bit());
+        // SAFETY: address checked to be an aligned double word of the main flash, the
+        // controller latches both words and starts programming after the second one
+        unsafe {
+            core::ptr::write_volatile(address as *mut u32, word as u32);
+            core::ptr::write_volatile((address + 4) as *mut u32, (word >> 32) as u32);
+        }
+        let result = self.finish();
+        self.regs.cr.modify(|_, w| w.pg().clear_bit());
+        self.lock();
+        result
+    }
+
+    /// Reads `buf.len()` bytes starting at `address`
+    pub fn read(&self, address: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+        let offset = Self::offset(address, 1)?;
+        if offset as usize + buf.len() > FLASH_SIZE {
+            return Err(FlashError::OutOfRange);
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            // SAFETY: the range lies within the main flash, which is always mapped
+            *byte = unsafe { core::ptr::read_volatile((address as usize + i) as *const u8) };
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of `address` in the main flash, checking range and alignment
+    fn offset(address: u32, align: u32) -> Result<u32, FlashError> {
+        let offset = address.wrapping_sub(FLASH_BASE);
+        if offset >= FLASH_SIZE as u32 || !offset.is_multiple_of(align) {
+            return Err(FlashError::OutOfRange);
+        }
+        Ok(offset)
+    }
+
+    fn unlock(&mut self) -> Result<(), FlashError> {
+        while self.regs.sr.read().bsy().bit_is_set() {}
+        // Clear flags of previous operations, they would block the next one
+        self.regs.sr.write(|w| unsafe { w.bits(0xC3FB) });
+        if self.regs.cr.read().lock().bit_is_set() {
+            self.regs.keyr.write(|w| unsafe { w.bits(KEY1) });
+            self.regs.keyr.write(|w| unsafe { w.bits(KEY2) });
+        }
+        if self.regs.cr.read().lock().bit_is_set() {
+            return Err(FlashError::Locked);
+        }
+        Ok(())
+    }
+
+    fn lock(&mut self) {
+        self.regs.cr.modify(|_, w| w.lock().set_bit());
+    }
+
+    /// Waits for the end of the operation and checks its error flags
+    fn finish(&mut self) -> Result<(), FlashError> {
+        while self.regs.sr.read().bsy().bit_is_set() {}
+        let sr = self.regs.sr.read();
+        if sr.wrperr().bit_is_set() {
+            return Err(FlashError::WriteProtected);
+        }
+        if sr.progerr().bit_is_set()
+            || sr.pgaerr().bit_is_set()
+            || sr.sizerr().bit_is_set()
+            || sr.pgserr().bit_is_set()
+            || sr.miserr().bit_is_set()
+            || sr.fasterr().bit_is_set()
+            || sr.operr().bit_is_set()
+        {
+            return Err(FlashError::Program);
+        }
+        self.regs.sr.write(|w| w.eop().set_bit());
+        Ok(())
+    }
+}
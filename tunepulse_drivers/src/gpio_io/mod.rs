@@ -0,0 +1,99 @@
+// Implements the driver of spare digital pins with runtime configurable direction.
+
+// Key Features:
+// - Reads and writes all spare pins as a single bit mask.
+// - Switches pins between input and output whenever the function mapping changes.
+
+// Detailed Operation:
+// The driver owns the spare pins defined in `pinout::io`. The function mapping itself lives in
+// the controller, which reports the pins used as inputs by a bit mask. `set_inputs()` configures
+// these pins as inputs with pull-down and all other pins as push-pull outputs. `read()` collects
+// levels of all pins and `write()` drives the output pins, skipping the input ones.
+// Pins handed to another peripheral (e.g. the STEP/DIR timer inputs) are reserved: they keep
+// their configuration, read as low and are never driven.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use hal::gpio::{Pin, PinMode, Pull};
+
+use crate::pinout::io::IO_PINS;
+
+/// Number of spare digital pins
+pub const PIN_COUNT: usize = IO_PINS.len();
+
+pub struct GpioIo {
+    pins: [Pin; PIN_COUNT],
+    input_mask: u32,
+    reserved: u32,
+}
+
+impl GpioIo {
+    /// Initializes all spare pins as inputs
+    pub fn new() -> Self {
+        let mut pins = IO_PINS.map(|def| def.init());
+        for pin in pins.iter_mut() {
+            pin.pull(Pull::Dn);
+        }
+        Self {
+            pins,
+            input_mask: u32::MAX,
+            reserved: 0,
+        }
+    }
+
+    /// Leaves pins in the mask to another peripheral, call after it configured them
+    pub fn reserve(&mut self, mask: u32) {
+        self.reserved |= mask;
+    }
+
+    /// Configures pins in the mask as inputs and the rest as outputs
+    pub fn set_inputs(&mut self, mask: u32) {
+        if mask == self.input_mask {
+            return;
+        }
+        for (idx, pin) in self.pins.iter_mut().enumerate() {
+            if self.reserved & (1 << idx) != 0 {
+                continue;
+            }
+            if mask & (1 << idx) != 0 {
+                pin.mode(PinMode::Input);
+                pin.pull(Pull::Dn);
+            } else {
+                pin.set_low();
+                pin.pull(Pull::Floating);
+                pin.mode(PinMode::Output);
+            }
+        }
+        self.input_mask = mask;
+    }
+
+    /// Reads levels of all pins, bit per pin
+    pub fn read(&self) -> u32 {
+        let mut levels = 0;
+        for (idx, pin) in self.pins.iter().enumerate() {
+            levels |= (pin.is_high() as u32) << idx;
+        }
+        levels & !self.reserved
+    }
+
+    /// Drives output pins to the levels, bit per pin
+    pub fn write(&mut self, levels: u32) {
+        for (idx, pin) in self.pins.iter_mut().enumerate() {
+            if (self.input_mask | self.reserved) & (1 << idx) != 0 {
+                continue;
+            }
+            if levels & (1 << idx) != 0 {
+                pin.set_high();
+            } else {
+                pin.set_low();
+            }
+        }
+    }
+}
+
+impl Default for GpioIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,58 @@
+// Implements the hardware independent interfaces of the driver layer, allowing the
+// algo+driver stack to run on other MCU families (RP2040, ESP32, other STM32 series).
+
+// Key Features:
+// - Driver level traits (`PhasePwm`, `AngleSensor`, `AdcChannel`) used by the firmware loop.
+// - Generic drivers built on embedded-hal 1.0 (`SetDutyCycle`, `SpiDevice`, `OutputPin`).
+// - STM32G4 drivers implement the same traits, so application code can be written once.
+
+// Detailed Operation:
+// Every driver module provides the STM32G4 implementation using stm32-hal2 (DMA, timer
+// alignment and other device specific features) and, where embedded-hal 1.0 has a matching
+// abstraction, a generic implementation over the embedded-hal traits. A port to another MCU
+// only needs the HAL of that MCU to implement embedded-hal:
+// - Phase PWM: four `SetDutyCycle` channels, see `pwm::BridgePwm`.
+// - Encoder: an `SpiDevice` with chip select handling, see `encoder_spi::SpiEncoder`.
+// - Status LED: three `OutputPin`s, see `status_led::StatusLed`.
+// - Bridge enable: an `OutputPin`, see `bridge::BridgeEnable`.
+// - ADC: embedded-hal 1.0 has no ADC trait, the target implements `AdcChannel` directly.
+// Spare pins with runtime direction switching (`gpio_io`) have no embedded-hal equivalent
+// and stay device specific.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+pub use embedded_hal::digital::{InputPin, OutputPin};
+pub use embedded_hal::pwm::SetDutyCycle;
+pub use embedded_hal::spi::SpiDevice;
+
+/// Output stage driving the motor phases
+pub trait PhasePwm {
+    /// Applies the duty cycles of the four half bridges (i1.15, negative values as 0)
+    fn apply_pwm(&mut self, pwm: [i16; 4]);
+}
+
+/// Sensor of the rotor angle
+pub trait AngleSensor {
+    /// Latest rotor angle (0..65535 = 0..360°)
+    fn angle(&mut self) -> u16;
+}
+
+/// Analog input channel
+pub trait AdcChannel {
+    /// Latest conversion result, left aligned to 16 bits
+    fn read(&mut self) -> u16;
+}
+
+/// Converts an i1.15 duty cycle to a fraction of `period`
+///
+/// # Arguments
+/// * `duty` - Duty cycle (0..32767 = 0..100%), negative values are treated as 0
+/// * `period` - Value corresponding to 100% duty
+pub fn duty2period(duty: i16, period: u32) -> u32 {
+    if duty > 0 {
+        (duty as u32 * period) >> 15
+    } else {
+        0
+    }
+}
@@ -0,0 +1,101 @@
+// Implements the driver of an ITM stimulus port, the byte stream behind SWO trace output.
+
+// Key Features:
+// - Non-blocking: bytes are queued and moved to the stimulus port while its FIFO has room.
+// - Whole words are written when available, 4 bytes per ITM packet instead of 1.
+// - All-or-nothing writes, a message either fits the queue or is refused.
+
+// Detailed Operation:
+// The stimulus port FIFO holds a single word and drains at the SWO rate, waiting for it would
+// stall the caller for the whole message. `write()` only copies the bytes into a circular queue,
+// `poll()` moves them to the port as long as the FIFO reports ready and returns when it is full.
+// Call `poll()` regularly, e.g. after every control tick or from the background loop.
+// The debug probe enables ITM, the stimulus port and the TPIU when it starts the SWO capture.
+// Until then the port reports disabled and `write()` refuses every message, so the queue never
+// fills with data nobody reads.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use cortex_m::peripheral::ITM;
+
+/// ITM trace control register: ITM enabled
+const TCR_ITMENA: u32 = 1 << 0;
+
+/// Stimulus port with a queue of `N` bytes
+pub struct ItmPort<const N: usize> {
+    itm: ITM,
+    port: usize,     // Stimulus port index (0..31)
+    queue: [u8; N],  // Bytes waiting for the port
+    head: usize,     // Index of the oldest queued byte
+    len: usize,      // Number of queued bytes
+}
+
+impl<const N: usize> ItmPort<N> {
+    /// Takes the ITM peripheral and writes to the given stimulus port (0..31)
+    pub fn new(itm: ITM, port: u8) -> Self {
+        Self {
+            itm,
+            port: (port & 0x1F) as usize,
+            queue: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// True if ITM and the stimulus port are enabled by the debug probe
+    pub fn is_enabled(&self) -> bool {
+        self.itm.tcr.read() & TCR_ITMENA != 0 && self.itm.ter[0].read() & (1 << self.port) != 0
+    }
+
+    /// Free space of the queue in bytes
+    #[inline(always)]
+    pub fn free(&self) -> usize {
+        N - self.len
+    }
+
+    /// Queues all bytes, returns false if the port is disabled or they don't fit
+    pub fn write(&mut self, bytes: &[u8]) -> bool {
+        if bytes.len() > self.free() || !self.is_enabled() {
+            return false;
+        }
+        for byte in bytes {
+            self.queue[(self.head + self.len) % N] = *byte;
+            self.len += 1;
+        }
+        true
+    }
+
+    /// Moves queued bytes to the stimulus port while its FIFO is ready, returns the number of
+    /// bytes written
+    pub fn poll(&mut self) -> usize {
+        let mut written = 0;
+        while self.len > 0 {
+            let stim = &mut self.itm.stim[self.port];
+            if !stim.is_fifo_ready() {
+                break;
+            }
+            if self.len >= 4 {
+                let mut word = [0u8; 4];
+                for (i, byte) in word.iter_mut().enumerate() {
+                    *byte = self.queue[(self.head + i) % N];
+                }
+                // ITM packets carry the payload little endian, the first byte goes first
+                stim.write_u32(u32::from_le_bytes(word));
+                self.consume(4);
+                written += 4;
+            } else {
+                stim.write_u8(self.queue[self.head]);
+                self.consume(1);
+                written += 1;
+            }
+        }
+        written
+    }
+
+    /// Removes `count` bytes from the front of the queue
+    fn consume(&mut self, count: usize) {
+        self.head = (self.head + count) % N;
+        self.len -= count;
+    }
+}
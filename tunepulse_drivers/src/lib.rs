@@ -1,5 +1,19 @@
 #![no_std]
 
+pub mod interfaces;
 pub mod pinout;
 pub mod pwm;
 pub mod encoder_spi;
+pub mod gpio_io;
+pub mod status_led;
+pub mod bridge;
+pub mod device_id;
+pub mod flash;
+#[cfg(feature = "itm")]
+pub mod itm;
+#[cfg(feature = "step-dir")]
+pub mod step_dir_input;
+#[cfg(feature = "can")]
+pub mod can;
+#[cfg(feature = "usb")]
+pub mod usb;
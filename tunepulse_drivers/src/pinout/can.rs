@@ -0,0 +1,17 @@
+//! CAN bus transceiver on FDCAN1.
+use super::PinDef;
+use super::{PinMode, Port};
+
+/// Receive line from the transceiver, FDCAN1_RX
+pub const RX: PinDef = PinDef {
+    port: Port::B,
+    pin: 8,
+    mode: PinMode::Alt(9),
+};
+
+/// Transmit line to the transceiver, FDCAN1_TX
+pub const TX: PinDef = PinDef {
+    port: Port::B,
+    pin: 9,
+    mode: PinMode::Alt(9),
+};
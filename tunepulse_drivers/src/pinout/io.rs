@@ -0,0 +1,34 @@
+//! Spare digital pins available for user configurable functions.
+use super::PinDef;
+use super::{PinMode, Port};
+
+/// Spare digital pin labeled IO0
+pub const IO0: PinDef = PinDef {
+    port: Port::B,
+    pin: 6,
+    mode: PinMode::Input,
+};
+
+/// Spare digital pin labeled IO1
+pub const IO1: PinDef = PinDef {
+    port: Port::B,
+    pin: 7,
+    mode: PinMode::Input,
+};
+
+/// Spare digital pin labeled IO2
+pub const IO2: PinDef = PinDef {
+    port: Port::A,
+    pin: 8,
+    mode: PinMode::Input,
+};
+
+/// Spare digital pin labeled IO3
+pub const IO3: PinDef = PinDef {
+    port: Port::A,
+    pin: 15,
+    mode: PinMode::Input,
+};
+
+/// All spare digital pins in order of their index
+pub const IO_PINS: [PinDef; 4] = [IO0, IO1, IO2, IO3];
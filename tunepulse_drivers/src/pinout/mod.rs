@@ -3,6 +3,13 @@ use hal::gpio::{Pin, PinMode, Port};
 pub mod led;
 pub mod encoder;
 pub mod driver;
+pub mod io;
+#[cfg(feature = "step-dir")]
+pub mod step_dir;
+#[cfg(feature = "can")]
+pub mod can;
+#[cfg(feature = "usb")]
+pub mod usb;
 
 /// Represents the definition of a GPIO pin.
 pub struct PinDef {
@@ -0,0 +1,20 @@
+//! STEP/DIR input on the timer inputs of the spare pins IO0 and IO1.
+use super::PinDef;
+use super::{PinMode, Port};
+
+/// Direction or quadrature channel A, TIM4_CH1 on the pin labeled IO0
+pub const DIR: PinDef = PinDef {
+    port: Port::B,
+    pin: 6,
+    mode: PinMode::Alt(2),
+};
+
+/// Step clock or quadrature channel B, TIM4_CH2 on the pin labeled IO1
+pub const STEP: PinDef = PinDef {
+    port: Port::B,
+    pin: 7,
+    mode: PinMode::Alt(2),
+};
+
+/// Spare pins taken by the STEP/DIR input, bit per index of `io::IO_PINS`
+pub const IO_MASK: u32 = 0b11;
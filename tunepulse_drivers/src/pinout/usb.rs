@@ -0,0 +1,17 @@
+//! USB full-speed device port.
+use super::PinDef;
+use super::{PinMode, Port};
+
+/// Data minus line, USB_DM
+pub const DM: PinDef = PinDef {
+    port: Port::A,
+    pin: 11,
+    mode: PinMode::Alt(14),
+};
+
+/// Data plus line with the internal pull-up, USB_DP
+pub const DP: PinDef = PinDef {
+    port: Port::A,
+    pin: 12,
+    mode: PinMode::Alt(14),
+};
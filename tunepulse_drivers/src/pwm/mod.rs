@@ -7,6 +7,8 @@ use hal::{
     },
 };
 
+use crate::interfaces::{duty2period, PhasePwm, SetDutyCycle};
+
 use super::pinout;
 pub struct TimPWM {
     tim: Timer<TIM2>,
@@ -58,24 +60,87 @@ impl TimPWM {
         pinout::driver::PWM_B2.init();
     }
 
+    /// Core clock cycles until the next update interrupt (underflow or overflow of the
+    /// center aligned counter), assumes the timer runs from the core clock (APB1 prescaler 1)
+    pub fn cycles_to_update() -> u32 {
+        // SAFETY: read-only access to counter registers, no state is modified
+        let tim = unsafe { &*TIM2::ptr() };
+        let count = tim.cnt.read().bits();
+        let counting_down = tim.cr1.read().dir().bit_is_set();
+        let counts = if counting_down {
+            count
+        } else {
+            tim.arr.read().bits().saturating_sub(count)
+        };
+        counts * (tim.psc.read().bits() + 1)
+    }
+
+    /// Position of the counter relative to the PWM period center (counter peak) in i1.15 of
+    /// the period, negative before the center. Read at a sampling instant it tells how far the
+    /// measurement is from the middle of the modulation
+    pub fn phase_from_center() -> i16 {
+        // SAFETY: read-only access to counter registers, no state is modified
+        let tim = unsafe { &*TIM2::ptr() };
+        let count = tim.cnt.read().bits() as i32;
+        let arr = (tim.arr.read().bits() as i32).max(1);
+        let counting_down = tim.cr1.read().dir().bit_is_set();
+        let counts = if counting_down {
+            arr - count
+        } else {
+            count - arr
+        };
+        // One period spans 2 * ARR counts of the center aligned counter
+        (counts * (1 << 15) / (2 * arr)) as i16
+    }
+
+    /// True if the center aligned counter counts up, i.e. the last update was an underflow
+    pub fn is_counting_up(&self) -> bool {
+        // SAFETY: read-only access to the control register
+        let tim = unsafe { &*TIM2::ptr() };
+        tim.cr1.read().dir().bit_is_clear()
+    }
+
     pub fn apply_pwm(&mut self, pwm: [i16; 4]) {
         let period = self.tim.get_max_duty();
         self.tim
-            .set_duty(TimChannel::C1, Self::duty2period(pwm[0], period));
+            .set_duty(TimChannel::C1, duty2period(pwm[0], period));
         self.tim
-            .set_duty(TimChannel::C2, Self::duty2period(pwm[1], period));
+            .set_duty(TimChannel::C2, duty2period(pwm[1], period));
         self.tim
-            .set_duty(TimChannel::C3, Self::duty2period(pwm[2], period));
+            .set_duty(TimChannel::C3, duty2period(pwm[2], period));
         self.tim
-            .set_duty(TimChannel::C4, Self::duty2period(pwm[3], period));
+            .set_duty(TimChannel::C4, duty2period(pwm[3], period));
     }
+}
 
-    fn duty2period(duty: i16, period: u32) -> u32 {
-        // Calculate the duty cycle value based on the input value and maximum period
-        if duty > 0 {
-            (duty as u32 * period) >> 15
-        } else {
-            0
+impl PhasePwm for TimPWM {
+    fn apply_pwm(&mut self, pwm: [i16; 4]) {
+        TimPWM::apply_pwm(self, pwm);
+    }
+}
+
+/// Phase PWM built from four embedded-hal channels, for targets other than STM32G4.
+/// Timer setup (frequency, center alignment, update interrupt) is done by the target HAL.
+pub struct BridgePwm<P: SetDutyCycle> {
+    channels: [P; 4], // Half bridges A1, A2, B1, B2
+}
+
+impl<P: SetDutyCycle> BridgePwm<P> {
+    pub fn new(channels: [P; 4]) -> Self {
+        Self { channels }
+    }
+
+    pub fn channels(&mut self) -> &mut [P; 4] {
+        &mut self.channels
+    }
+}
+
+impl<P: SetDutyCycle> PhasePwm for BridgePwm<P> {
+    fn apply_pwm(&mut self, pwm: [i16; 4]) {
+        for (channel, duty) in self.channels.iter_mut().zip(pwm) {
+            let period = channel.max_duty_cycle() as u32;
+            // Failed update keeps the previous duty cycle until the next tick
+            let _ = channel.set_duty_cycle(duty2period(duty, period) as u16);
         }
     }
 }
@@ -0,0 +1,55 @@
+// Implements the driver of the on-board RGB status LED.
+
+// Key Features:
+// - Controls the red, green and blue channels of the status LED.
+// - Hides the active-low wiring of the LED pins.
+// - Works with any embedded-hal output pins, on-board pins by default.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use hal::gpio::Pin;
+
+use crate::interfaces::OutputPin;
+use crate::pinout::led;
+
+pub struct StatusLed<P: OutputPin = Pin> {
+    red: P,
+    green: P,
+    blue: P,
+}
+
+impl StatusLed {
+    /// Initializes on-board LED pins with the LED turned off
+    pub fn new() -> Self {
+        Self::from_pins(led::RED.init(), led::GRN.init(), led::BLU.init())
+    }
+}
+
+impl<P: OutputPin> StatusLed<P> {
+    /// Takes active-low LED pins and turns the LED off
+    pub fn from_pins(red: P, green: P, blue: P) -> Self {
+        let mut status_led = Self { red, green, blue };
+        status_led.set(false, false, false);
+        status_led
+    }
+
+    /// Turns color channels on or off
+    pub fn set(&mut self, red: bool, green: bool, blue: bool) {
+        Self::set_channel(&mut self.red, red);
+        Self::set_channel(&mut self.green, green);
+        Self::set_channel(&mut self.blue, blue);
+    }
+
+    /// LEDs are connected to supply, so the pin has to be pulled low to turn them on
+    fn set_channel(pin: &mut P, on: bool) {
+        // Indication only, a failed pin update is corrected by the next call
+        let _ = if on { pin.set_low() } else { pin.set_high() };
+    }
+}
+
+impl Default for StatusLed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,46 @@
+// Descriptors of the virtual serial port: one configuration with the CDC communication interface
+// (notification endpoint) and the CDC data interface (bulk endpoints).
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+/// Descriptor types
+pub const DEVICE_TYPE: u8 = 1;
+pub const CONFIGURATION_TYPE: u8 = 2;
+pub const STRING_TYPE: u8 = 3;
+
+/// Device: USB 2.0, CDC class, 64 byte control endpoint, ST virtual COM port IDs
+#[rustfmt::skip]
+pub const DEVICE: [u8; 18] = [
+    18, DEVICE_TYPE, 0x00, 0x02, // Length, type, USB 2.0
+    0x02, 0x00, 0x00, 64,        // CDC class, subclass and protocol per interface, EP0 size
+    0x83, 0x04, 0x40, 0x57,      // Vendor 0x0483, product 0x5740
+    0x00, 0x01,                  // Device release 1.00
+    1, 2, 3,                     // Manufacturer, product and serial number strings
+    1,                           // Number of configurations
+];
+
+/// Configuration with all its interface, functional and endpoint descriptors
+#[rustfmt::skip]
+pub const CONFIGURATION: [u8; 67] = [
+    9, CONFIGURATION_TYPE, 67, 0, // Length, type, total length
+    2, 1, 0, 0xC0, 50,            // 2 interfaces, value 1, no string, self-powered, 100 mA
+    // Communication interface: abstract control model, no protocol
+    9, 4, 0, 0, 1, 0x02, 0x02, 0x00, 0,
+    5, 0x24, 0x00, 0x10, 0x01,    // Header, CDC 1.10
+    5, 0x24, 0x01, 0x00, 1,       // Call management by the host, data interface 1
+    4, 0x24, 0x02, 0x02,          // Abstract control management: line coding and state
+    5, 0x24, 0x06, 0, 1,          // Union of interfaces 0 and 1
+    7, 5, 0x81, 0x03, 8, 0, 255,  // Endpoint 1 IN: interrupt, 8 bytes, every 255 ms
+    // Data interface
+    9, 4, 1, 0, 2, 0x0A, 0x00, 0x00, 0,
+    7, 5, 0x02, 0x02, 64, 0, 0,   // Endpoint 2 OUT: bulk, 64 bytes
+    7, 5, 0x82, 0x02, 64, 0, 0,   // Endpoint 2 IN: bulk, 64 bytes
+];
+
+/// String 0: supported languages, US English only
+pub const LANGUAGES: [u8; 4] = [4, STRING_TYPE, 0x09, 0x04];
+
+/// Strings 1 and 2, the serial number (string 3) is the unique device identifier
+pub const MANUFACTURER: &str = "creapunk";
+pub const PRODUCT: &str = "TunePulse";
@@ -0,0 +1,669 @@
+// Implements the driver of the USB full-speed device peripheral as virtual serial port (CDC-ACM)
+// for the text console.
+
+// Key Features:
+// - Enumerates as CDC-ACM device, served by the drivers built into Linux, macOS and Windows 10+.
+// - Control, notification and bulk data endpoints, serviced from the USB_LP interrupt.
+// - Non-blocking byte stream: received bytes wait in a receive buffer, written bytes in a
+//   transmit buffer until the host fetches them.
+// - 48 MHz clock from the HSI48 oscillator, trimmed to the start of frame of the host by the CRS.
+
+// Detailed Operation:
+// `new()` starts the HSI48 and the clock recovery system, powers up the peripheral and connects
+// to the bus with the internal pull-up of DP. `poll()` handles the events of the peripheral: a
+// bus reset configures endpoint 0, a completed transfer is handled per endpoint.
+// Endpoint 0 answers the standard requests of the enumeration (descriptors, address,
+// configuration) and the CDC requests for line coding and control line state. The line coding
+// is stored and reported back but has no effect, the data isn't sent over a UART. Endpoint 1 is
+// the notification endpoint the CDC class requires, it never sends.
+// Endpoint 2 carries the data in packets of up to 64 bytes. A received packet is copied into the
+// receive buffer; while the buffer has no room for another packet the endpoint stays NAK and
+// the host retries, `read()` opens it again. `write()` queues bytes and starts a packet if the
+// endpoint is idle, each completed packet starts the next one. A transfer ending with a full
+// packet is closed by a zero length packet, the host returns the data at once.
+// The packet buffers live in the 1 KB packet memory of the peripheral, accessed in halfwords,
+// the buffer descriptor table sits at its start. Without a configured host written bytes are
+// dropped, the console doesn't stall on an unplugged cable. `poll()` and the other methods must
+// not preempt each other.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::ptr;
+
+use hal::pac::{CRS, RCC, USB};
+
+use super::{device_id, pinout};
+
+mod descriptors;
+
+/// Start of the packet memory
+const PMA: usize = 0x4000_6000;
+/// Byte offsets of the packet buffers, the buffer descriptor table takes the first 64 bytes
+const EP0_RX_BUF: u16 = 0x040;
+const EP0_TX_BUF: u16 = 0x080;
+const EP1_TX_BUF: u16 = 0x0C0;
+const EP2_RX_BUF: u16 = 0x100;
+const EP2_TX_BUF: u16 = 0x140;
+/// COUNT_RX of a 64 byte receive buffer: 2 blocks of 32 bytes
+const COUNT_RX_64: u16 = 1 << 15 | 1 << 10;
+
+/// Maximum packet size of the control and the data endpoints
+pub const MAX_PACKET: usize = 64;
+/// Size of the receive buffer (bytes)
+pub const RX_BUFFER: usize = 128;
+/// Size of the transmit buffer (bytes)
+pub const TX_BUFFER: usize = 512;
+/// Longest data stage of a control transfer, the configuration descriptor
+const CONTROL_BUFFER: usize = 128;
+
+/// Endpoint numbers
+const EP_CONTROL: usize = 0;
+const EP_NOTIFY: usize = 1;
+const EP_DATA: usize = 2;
+const EP_COUNT: usize = 8;
+
+/// Cycles the analog transceiver needs after the power-up (t_STARTUP 1 us at up to 170 MHz)
+const STARTUP_CYCLES: u32 = 200;
+
+/// CNTR bits
+const CNTR_FRES: u32 = 1 << 0;
+const CNTR_RESETM: u32 = 1 << 10;
+const CNTR_CTRM: u32 = 1 << 15;
+/// ISTR bits
+const ISTR_EP_ID: u32 = 0xF;
+const ISTR_RESET: u32 = 1 << 10;
+const ISTR_SUSP: u32 = 1 << 11;
+const ISTR_WKUP: u32 = 1 << 12;
+const ISTR_CTR: u32 = 1 << 15;
+/// DADDR function enable
+const DADDR_EF: u32 = 1 << 7;
+/// BCDR pull-up of DP
+const BCDR_DPPU: u32 = 1 << 15;
+
+/// EPnR bits, CTR flags are cleared by writing 0, DTOG and STAT toggled by writing 1
+const EP_CTR_RX: u32 = 1 << 15;
+const EP_DTOG_RX: u32 = 1 << 14;
+const EP_STAT_RX: u32 = 0b11 << 12;
+const EP_SETUP: u32 = 1 << 11;
+const EP_CTR_TX: u32 = 1 << 7;
+const EP_DTOG_TX: u32 = 1 << 6;
+const EP_STAT_TX: u32 = 0b11 << 4;
+/// Plain read-write bits: type, kind and address
+const EP_KEEP: u32 = 0x070F;
+/// Endpoint types
+const EP_BULK: u32 = 0b00 << 9;
+const EP_CONTROL_TYPE: u32 = 0b01 << 9;
+const EP_INTERRUPT: u32 = 0b11 << 9;
+
+/// Standard requests
+const GET_STATUS: u8 = 0;
+const CLEAR_FEATURE: u8 = 1;
+const SET_FEATURE: u8 = 3;
+const SET_ADDRESS: u8 = 5;
+const GET_DESCRIPTOR: u8 = 6;
+const GET_CONFIGURATION: u8 = 8;
+const SET_CONFIGURATION: u8 = 9;
+const GET_INTERFACE: u8 = 10;
+const SET_INTERFACE: u8 = 11;
+/// CDC requests
+const SET_LINE_CODING: u8 = 0x20;
+const GET_LINE_CODING: u8 = 0x21;
+const SET_CONTROL_LINE_STATE: u8 = 0x22;
+const SEND_BREAK: u8 = 0x23;
+/// Request types: direction, type (standard, class) and recipient (device, interface, endpoint)
+const DEVICE_OUT: u8 = 0x00;
+const INTERFACE_OUT: u8 = 0x01;
+const ENDPOINT_OUT: u8 = 0x02;
+const DEVICE_IN: u8 = 0x80;
+const INTERFACE_IN: u8 = 0x81;
+const ENDPOINT_IN: u8 = 0x82;
+const CLASS_OUT: u8 = 0x21;
+const CLASS_IN: u8 = 0xA1;
+
+/// Line coding reported until the host sets one: 115200 baud, 8 data bits, no parity, 1 stop bit
+const DEFAULT_LINE_CODING: [u8; 7] = [0x00, 0xC2, 0x01, 0x00, 0, 0, 8];
+
+/// State of an endpoint direction (STAT_RX, STAT_TX).
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum Stat {
+    Disabled = 0b00,
+    Stall = 0b01,
+    Nak = 0b10,
+    Valid = 0b11,
+}
+
+/// Stage of the control transfer on endpoint 0.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ControlStage {
+    Idle,
+    DataIn,    // Sending the answer
+    DataOut,   // Waiting for the data of a request
+    StatusIn,  // Sending the zero length status packet
+    StatusOut, // Waiting for the zero length status packet
+}
+
+/// Byte ring buffer between the endpoints and the user.
+struct ByteQueue<const N: usize> {
+    data: [u8; N], // Stored bytes
+    head: usize,   // Index of the oldest byte
+    len: usize,    // Number of stored bytes
+}
+
+impl<const N: usize> ByteQueue<N> {
+    const fn new() -> Self {
+        Self {
+            data: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Free space (bytes)
+    fn free(&self) -> usize {
+        N - self.len
+    }
+
+    /// Appends as many bytes as fit, returns their number
+    fn push(&mut self, bytes: &[u8]) -> usize {
+        let count = bytes.len().min(self.free());
+        for &byte in &bytes[..count] {
+            self.data[(self.head + self.len) % N] = byte;
+            self.len += 1;
+        }
+        count
+    }
+
+    /// Takes the oldest bytes into `out`, returns their number
+    fn pop(&mut self, out: &mut [u8]) -> usize {
+        let count = out.len().min(self.len);
+        for byte in &mut out[..count] {
+            *byte = self.data[self.head];
+            self.head = (self.head + 1) % N;
+        }
+        self.len -= count;
+        count
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+pub struct UsbSerial {
+    usb: USB,
+    stage: ControlStage,           // Stage of the control transfer
+    control: [u8; CONTROL_BUFFER], // Data stage of the control transfer
+    control_len: usize,            // Bytes of the data stage
+    control_sent: usize,           // Bytes of the data stage already sent
+    control_zlp: bool,             // Data stage shorter than requested ends with an empty packet
+    address: Option<u8>,           // Address applied once SET_ADDRESS completed
+    configured: bool,              // Host selected the configuration
+    line_coding: [u8; 7],          // Line coding set by the host
+    dtr: bool,                     // Terminal ready, set by the host when the port is opened
+    rx: ByteQueue<RX_BUFFER>,      // Received bytes
+    rx_paused: bool,               // Data OUT endpoint NAK until the buffer has room
+    tx: ByteQueue<TX_BUFFER>,      // Bytes to send
+    tx_busy: bool,                 // Data IN packet waiting for the host
+    tx_full: bool,                 // Last packet was full, the transfer needs an ending
+}
+
+impl UsbSerial {
+    /// Starts the 48 MHz clock, configures the pins and the peripheral and connects to the bus
+    pub fn new(usb: USB) -> Self {
+        // SAFETY: read-modify-writes during initialization, before any other user of the
+        // registers runs
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
+        while rcc.crrcr.read().hsi48rdy().bit_is_clear() {}
+        rcc.ccipr.modify(|_, w| w.clk48sel().hsi48());
+        rcc.apb1enr1
+            .modify(|_, w| w.usben().set_bit().crsen().set_bit());
+        // SAFETY: the clock recovery system is only used here
+        let crs = unsafe { &*CRS::ptr() };
+        // SAFETY: 0b10 selects the USB start of frame as synchronization source
+        crs.cfgr.modify(|_, w| unsafe { w.syncsrc().bits(0b10) });
+        crs.cr
+            .modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
+
+        pinout::usb::DM.init();
+        pinout::usb::DP.init();
+
+        // SAFETY: raw values are valid settings of the written registers
+        unsafe {
+            usb.cntr.write(|w| w.bits(CNTR_FRES)); // Powered, held in reset
+            cortex_m::asm::delay(STARTUP_CYCLES);
+            usb.cntr.write(|w| w.bits(0));
+            usb.istr.write(|w| w.bits(0));
+            usb.btable.write(|w| w.bits(0));
+            usb.cntr.write(|w| w.bits(CNTR_CTRM | CNTR_RESETM));
+            usb.bcdr.write(|w| w.bits(BCDR_DPPU));
+        }
+
+        Self {
+            usb,
+            stage: ControlStage::Idle,
+            control: [0; CONTROL_BUFFER],
+            control_len: 0,
+            control_sent: 0,
+            control_zlp: false,
+            address: None,
+            configured: false,
+            line_coding: DEFAULT_LINE_CODING,
+            dtr: false,
+            rx: ByteQueue::new(),
+            rx_paused: false,
+            tx: ByteQueue::new(),
+            tx_busy: false,
+            tx_full: false,
+        }
+    }
+
+    /// Returns true once the host selected the configuration
+    #[inline(always)]
+    pub fn is_configured(&self) -> bool {
+        self.configured
+    }
+
+    /// Returns true while a terminal has the port open (DTR set)
+    #[inline(always)]
+    pub fn is_open(&self) -> bool {
+        self.configured && self.dtr
+    }
+
+    /// Handles the pending events of the peripheral, called from the USB_LP interrupt
+    pub fn poll(&mut self) {
+        let istr = self.usb.istr.read().bits();
+        if istr & ISTR_RESET != 0 {
+            self.clear_istr(ISTR_RESET);
+            self.reset();
+        }
+        if istr & (ISTR_SUSP | ISTR_WKUP) != 0 {
+            // No low power mode, the board doesn't draw its supply from the bus
+            self.clear_istr(ISTR_SUSP | ISTR_WKUP);
+        }
+        loop {
+            let istr = self.usb.istr.read().bits();
+            if istr & ISTR_CTR == 0 {
+                break;
+            }
+            let ep = (istr & ISTR_EP_ID) as usize;
+            let reg = Self::ep_read(ep);
+            if reg & EP_CTR_RX != 0 {
+                Self::ep_clear_ctr(ep, EP_CTR_RX);
+                match ep {
+                    EP_CONTROL => self.control_out(reg & EP_SETUP != 0),
+                    EP_DATA => self.data_out(),
+                    _ => {}
+                }
+            }
+            if reg & EP_CTR_TX != 0 {
+                Self::ep_clear_ctr(ep, EP_CTR_TX);
+                match ep {
+                    EP_CONTROL => self.control_in(),
+                    EP_DATA => {
+                        self.tx_busy = false;
+                        self.start_tx();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Takes received bytes into `buf`, returns their number
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = self.rx.pop(buf);
+        if self.rx_paused && self.rx.free() >= MAX_PACKET {
+            self.rx_paused = false;
+            Self::ep_set_stat(EP_DATA, Some(Stat::Valid), None);
+        }
+        count
+    }
+
+    /// Queues bytes for the host, returns the number queued (0 without a configured host)
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        if !self.configured {
+            return 0;
+        }
+        let count = self.tx.push(data);
+        self.start_tx();
+        count
+    }
+
+    /// Configures endpoint 0 after a bus reset, the other endpoints wait for the configuration
+    fn reset(&mut self) {
+        Self::set_buffers(EP_CONTROL, EP0_TX_BUF, EP0_RX_BUF);
+        Self::set_buffers(EP_NOTIFY, EP1_TX_BUF, 0);
+        Self::set_buffers(EP_DATA, EP2_TX_BUF, EP2_RX_BUF);
+        Self::ep_init(EP_CONTROL, EP_CONTROL_TYPE, Stat::Valid, Stat::Nak);
+        for ep in EP_NOTIFY..EP_COUNT {
+            Self::ep_init(ep, EP_BULK, Stat::Disabled, Stat::Disabled);
+        }
+        // SAFETY: address 0 with the function enabled
+        unsafe { self.usb.daddr.write(|w| w.bits(DADDR_EF)) };
+
+        self.stage = ControlStage::Idle;
+        self.address = None;
+        self.configured = false;
+        self.dtr = false;
+        self.rx.clear();
+        self.rx_paused = false;
+        self.tx.clear();
+        self.tx_busy = false;
+        self.tx_full = false;
+    }
+
+    /// Completed OUT or SETUP packet on endpoint 0
+    fn control_out(&mut self, setup: bool) {
+        let len = Self::rx_count(EP_CONTROL);
+        let mut packet = [0u8; MAX_PACKET];
+        Self::pma_read(EP0_RX_BUF, &mut packet[..len]);
+        // Ready for the next packet, a refused request stalls it again
+        Self::ep_set_stat(EP_CONTROL, Some(Stat::Valid), None);
+        if setup {
+            if !self.setup(&packet[..len]) {
+                self.stage = ControlStage::Idle;
+                Self::ep_set_stat(EP_CONTROL, Some(Stat::Stall), Some(Stat::Stall));
+            }
+        } else if self.stage == ControlStage::DataOut {
+            // SET_LINE_CODING is the only request with a data stage
+            if len == self.line_coding.len() {
+                self.line_coding.copy_from_slice(&packet[..len]);
+            }
+            self.status_in();
+        } else {
+            // Status stage of an answered request
+            self.stage = ControlStage::Idle;
+        }
+    }
+
+    /// Completed IN packet on endpoint 0
+    fn control_in(&mut self) {
+        match self.stage {
+            ControlStage::DataIn if self.control_sent < self.control_len || self.control_zlp => {
+                self.control_zlp &= self.control_sent < self.control_len;
+                self.send_control();
+            }
+            ControlStage::DataIn => self.stage = ControlStage::StatusOut,
+            ControlStage::StatusIn => {
+                if let Some(address) = self.address.take() {
+                    // SAFETY: 7 bit address with the function enabled
+                    unsafe { self.usb.daddr.write(|w| w.bits(DADDR_EF | address as u32)) };
+                }
+                self.stage = ControlStage::Idle;
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a SETUP packet, returns false to stall the request
+    fn setup(&mut self, packet: &[u8]) -> bool {
+        let &[request_type, request, value_low, value_high, _, _, length_low, length_high] = packet
+        else {
+            return false;
+        };
+        let value = u16::from_le_bytes([value_low, value_high]);
+        let length = u16::from_le_bytes([length_low, length_high]) as usize;
+        self.stage = ControlStage::Idle;
+
+        match (request_type, request) {
+            // Self-powered device, no remote wakeup, nothing halted
+            (DEVICE_IN, GET_STATUS) => self.reply(&[1, 0], length),
+            (INTERFACE_IN | ENDPOINT_IN, GET_STATUS) => self.reply(&[0, 0], length),
+            (DEVICE_OUT, SET_ADDRESS) => {
+                self.address = Some(value as u8 & 0x7F);
+                self.status_in();
+                true
+            }
+            (DEVICE_IN, GET_DESCRIPTOR) => self.descriptor(value, length),
+            (DEVICE_IN, GET_CONFIGURATION) => self.reply(&[self.configured as u8], length),
+            (DEVICE_OUT, SET_CONFIGURATION) => self.set_configuration(value),
+            (INTERFACE_IN, GET_INTERFACE) => self.reply(&[0], length),
+            // Features (remote wakeup, endpoint halt) and alternate settings are accepted and
+            // ignored
+            (DEVICE_OUT | INTERFACE_OUT | ENDPOINT_OUT, CLEAR_FEATURE | SET_FEATURE)
+            | (INTERFACE_OUT, SET_INTERFACE)
+            | (CLASS_OUT, SEND_BREAK) => {
+                self.status_in();
+                true
+            }
+            (CLASS_OUT, SET_LINE_CODING) => {
+                self.stage = ControlStage::DataOut;
+                true
+            }
+            (CLASS_IN, GET_LINE_CODING) => {
+                let line_coding = self.line_coding;
+                self.reply(&line_coding, length)
+            }
+            (CLASS_OUT, SET_CONTROL_LINE_STATE) => {
+                self.dtr = value & 1 != 0;
+                self.status_in();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Answers GET_DESCRIPTOR, returns false for an unknown descriptor
+    fn descriptor(&mut self, value: u16, length: usize) -> bool {
+        let (kind, index) = ((value >> 8) as u8, value as u8);
+        match (kind, index) {
+            (descriptors::DEVICE_TYPE, 0) => self.reply(&descriptors::DEVICE, length),
+            (descriptors::CONFIGURATION_TYPE, 0) => self.reply(&descriptors::CONFIGURATION, length),
+            (descriptors::STRING_TYPE, 0) => self.reply(&descriptors::LANGUAGES, length),
+            (descriptors::STRING_TYPE, 1) => {
+                self.reply_string(descriptors::MANUFACTURER.bytes(), length)
+            }
+            (descriptors::STRING_TYPE, 2) => {
+                self.reply_string(descriptors::PRODUCT.bytes(), length)
+            }
+            (descriptors::STRING_TYPE, 3) => {
+                // Serial number: the unique device identifier in hexadecimal
+                const HEX: &[u8; 16] = b"0123456789ABCDEF";
+                let uid = device_id::uid();
+                let digits = uid
+                    .iter()
+                    .flat_map(|&byte| [HEX[(byte >> 4) as usize], HEX[(byte & 0xF) as usize]]);
+                self.reply_string(digits, length)
+            }
+            _ => false,
+        }
+    }
+
+    /// Selects or drops the configuration, returns false for an unknown configuration
+    fn set_configuration(&mut self, value: u16) -> bool {
+        match value {
+            0 => {
+                self.configured = false;
+                Self::ep_init(EP_NOTIFY, EP_INTERRUPT, Stat::Disabled, Stat::Disabled);
+                Self::ep_init(EP_DATA, EP_BULK, Stat::Disabled, Stat::Disabled);
+            }
+            1 => {
+                self.configured = true;
+                Self::ep_init(EP_NOTIFY, EP_INTERRUPT, Stat::Disabled, Stat::Nak);
+                Self::ep_init(EP_DATA, EP_BULK, Stat::Valid, Stat::Nak);
+            }
+            _ => return false,
+        }
+        self.rx.clear();
+        self.rx_paused = false;
+        self.tx.clear();
+        self.tx_busy = false;
+        self.tx_full = false;
+        self.status_in();
+        true
+    }
+
+    /// Starts the data stage of an answer, cut to the length the host asked for
+    fn reply(&mut self, data: &[u8], length: usize) -> bool {
+        let len = data.len().min(CONTROL_BUFFER);
+        self.control[..len].copy_from_slice(&data[..len]);
+        self.start_data_in(len, length);
+        true
+    }
+
+    /// Starts the data stage of a string descriptor made of ASCII characters
+    fn reply_string(&mut self, text: impl Iterator<Item = u8>, length: usize) -> bool {
+        let mut len = 2;
+        for byte in text.take((CONTROL_BUFFER - 2) / 2) {
+            // UTF-16LE, ASCII maps to the low byte
+            self.control[len] = byte;
+            self.control[len + 1] = 0;
+            len += 2;
+        }
+        self.control[0] = len as u8;
+        self.control[1] = descriptors::STRING_TYPE;
+        self.start_data_in(len, length);
+        true
+    }
+
+    fn start_data_in(&mut self, len: usize, length: usize) {
+        self.control_len = len.min(length);
+        self.control_sent = 0;
+        // A short answer ending on a full packet needs an empty packet to end the data stage
+        self.control_zlp = self.control_len < length && self.control_len.is_multiple_of(MAX_PACKET);
+        self.stage = ControlStage::DataIn;
+        self.send_control();
+    }
+
+    /// Sends the next packet of the data stage
+    fn send_control(&mut self) {
+        let count = (self.control_len - self.control_sent).min(MAX_PACKET);
+        let start = self.control_sent;
+        Self::pma_write(EP0_TX_BUF, &self.control[start..start + count]);
+        Self::set_tx_count(EP_CONTROL, count);
+        self.control_sent += count;
+        Self::ep_set_stat(EP_CONTROL, None, Some(Stat::Valid));
+    }
+
+    /// Sends the zero length status packet of a request without data to return
+    fn status_in(&mut self) {
+        Self::set_tx_count(EP_CONTROL, 0);
+        self.stage = ControlStage::StatusIn;
+        Self::ep_set_stat(EP_CONTROL, None, Some(Stat::Valid));
+    }
+
+    /// Completed OUT packet on the data endpoint
+    fn data_out(&mut self) {
+        let len = Self::rx_count(EP_DATA);
+        let mut packet = [0u8; MAX_PACKET];
+        Self::pma_read(EP2_RX_BUF, &mut packet[..len]);
+        // Room for the packet was checked before the endpoint was opened
+        self.rx.push(&packet[..len]);
+        if self.rx.free() >= MAX_PACKET {
+            Self::ep_set_stat(EP_DATA, Some(Stat::Valid), None);
+        } else {
+            self.rx_paused = true;
+        }
+    }
+
+    /// Sends the next data packet if the endpoint is idle
+    fn start_tx(&mut self) {
+        if !self.configured || self.tx_busy || (self.tx.len == 0 && !self.tx_full) {
+            return;
+        }
+        let mut packet = [0u8; MAX_PACKET];
+        let count = self.tx.pop(&mut packet);
+        Self::pma_write(EP2_TX_BUF, &packet[..count]);
+        Self::set_tx_count(EP_DATA, count);
+        self.tx_busy = true;
+        self.tx_full = count == MAX_PACKET;
+        Self::ep_set_stat(EP_DATA, None, Some(Stat::Valid));
+    }
+
+    /// Sets the transmit and receive buffers of an endpoint in the buffer descriptor table
+    fn set_buffers(ep: usize, tx: u16, rx: u16) {
+        let entry = (ep * 8) as u16;
+        Self::pma_set(entry, tx);
+        Self::pma_set(entry + 2, 0);
+        Self::pma_set(entry + 4, rx);
+        Self::pma_set(entry + 6, if rx == 0 { 0 } else { COUNT_RX_64 });
+    }
+
+    fn set_tx_count(ep: usize, count: usize) {
+        Self::pma_set((ep * 8 + 2) as u16, count as u16);
+    }
+
+    /// Number of bytes received in the last packet of an endpoint
+    fn rx_count(ep: usize) -> usize {
+        (Self::pma_get((ep * 8 + 6) as u16) & 0x3FF) as usize
+    }
+
+    /// Configures an endpoint: type, address, both data toggles cleared and the states
+    fn ep_init(ep: usize, kind: u32, rx: Stat, tx: Stat) {
+        let reg = Self::ep_read(ep);
+        let wanted = (rx as u32) << 12 | (tx as u32) << 4;
+        let toggle = (reg ^ wanted) & (EP_DTOG_RX | EP_STAT_RX | EP_DTOG_TX | EP_STAT_TX);
+        // CTR flags written 0 clear stale transfers
+        Self::ep_write(ep, kind | ep as u32 | toggle);
+    }
+
+    /// Changes the receive and/or transmit state of an endpoint, leaving everything else
+    fn ep_set_stat(ep: usize, rx: Option<Stat>, tx: Option<Stat>) {
+        let reg = Self::ep_read(ep);
+        let mut value = reg & EP_KEEP | EP_CTR_RX | EP_CTR_TX;
+        if let Some(rx) = rx {
+            value |= (reg ^ (rx as u32) << 12) & EP_STAT_RX;
+        }
+        if let Some(tx) = tx {
+            value |= (reg ^ (tx as u32) << 4) & EP_STAT_TX;
+        }
+        Self::ep_write(ep, value);
+    }
+
+    /// Clears one transfer flag of an endpoint
+    fn ep_clear_ctr(ep: usize, flag: u32) {
+        let reg = Self::ep_read(ep);
+        Self::ep_write(ep, reg & EP_KEEP | (EP_CTR_RX | EP_CTR_TX) & !flag);
+    }
+
+    fn clear_istr(&self, flags: u32) {
+        // SAFETY: the flags are cleared by writing 0, the other bits are read-only or kept
+        unsafe { self.usb.istr.write(|w| w.bits(!flags & 0xFFFF)) };
+    }
+
+    fn ep_read(ep: usize) -> u32 {
+        // SAFETY: EPnR registers are the first 8 words of the peripheral
+        unsafe { ptr::read_volatile((USB::ptr() as *const u32).add(ep)) }
+    }
+
+    fn ep_write(ep: usize, value: u32) {
+        // SAFETY: EPnR registers are the first 8 words of the peripheral
+        unsafe { ptr::write_volatile((USB::ptr() as *mut u32).add(ep), value) };
+    }
+
+    fn pma_get(offset: u16) -> u16 {
+        // SAFETY: halfword inside the packet memory
+        unsafe { ptr::read_volatile((PMA + offset as usize) as *const u16) }
+    }
+
+    fn pma_set(offset: u16, value: u16) {
+        // SAFETY: halfword inside the packet memory
+        unsafe { ptr::write_volatile((PMA + offset as usize) as *mut u16, value) };
+    }
+
+    fn pma_read(offset: u16, buf: &mut [u8]) {
+        for (i, pair) in buf.chunks_mut(2).enumerate() {
+            let bytes = Self::pma_get(offset + 2 * i as u16).to_le_bytes();
+            pair.copy_from_slice(&bytes[..pair.len()]);
+        }
+    }
+
+    fn pma_write(offset: u16, data: &[u8]) {
+        for (i, pair) in data.chunks(2).enumerate() {
+            let half = u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)]);
+            Self::pma_set(offset + 2 * i as u16, half);
+        }
+    }
+}
+
+impl core::fmt::Write for UsbSerial {
+    /// Queues the text, what doesn't fit the transmit buffer is dropped
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+// Regenerates include/tunepulse.h from the extern "C" API in src/lib.rs.
+// The header is checked in, so C projects don't need a Rust toolchain to read it.
+
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml"))
+        .expect("invalid cbindgen.toml");
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{crate_dir}/include/tunepulse.h"));
+        }
+        // Keep the checked in header, the library itself doesn't depend on it
+        Err(e) => println!("cargo:warning=tunepulse.h not regenerated: {e}"),
+    }
+}
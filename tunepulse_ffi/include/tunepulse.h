@@ -0,0 +1,235 @@
+// C interface of the TunePulse algorithm layer (libtunepulse_ffi.a).
+// Generated by cbindgen from tunepulse_ffi/src/lib.rs, do not edit.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#ifndef TUNEPULSE_H
+#define TUNEPULSE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+// Motor type, mirrors `MotorType`
+enum TpMotorType {
+  TP_MOTOR_TYPE_DC,
+  TP_MOTOR_TYPE_BLDC,
+  TP_MOTOR_TYPE_STEP,
+};
+
+// Phase connection pattern, mirrors `PhasePattern`
+enum TpPhasePattern {
+  TP_PHASE_PATTERN_ABCD,
+  TP_PHASE_PATTERN_ACDB,
+  TP_PHASE_PATTERN_ADBC,
+  TP_PHASE_PATTERN_DCAB,
+};
+
+// Result of parameter access
+enum TpResult {
+  TP_RESULT_OK,
+  TP_RESULT_UNKNOWN_PARAM,
+  TP_RESULT_OUT_OF_RANGE,
+  TP_RESULT_NOT_TUNABLE,
+  TP_RESULT_STAGE_FULL,
+  TP_RESULT_LOCKED,
+};
+
+// Kind of the command followed in normal operation, mirrors `Setpoint`
+enum TpSetpointKind {
+  TP_SETPOINT_KIND_CURRENT,
+  TP_SETPOINT_KIND_VELOCITY,
+  TP_SETPOINT_KIND_POSITION,
+  TP_SETPOINT_KIND_VOLTAGE_ANGLE,
+};
+
+// Operating state of the controller
+enum TpStatus {
+  TP_STATUS_CALIBRATING,
+  TP_STATUS_READY,
+  TP_STATUS_ERROR,
+};
+
+// Motor controller storage, initialize with `tp_controller_init`
+struct TpController {
+  uint64_t storage[1024];
+};
+
+// PWM duty cycles of the four half bridges (i1.15)
+struct TpPwm {
+  int16_t duty[4];
+};
+
+// Sampled inputs of one control tick, mirrors `DataInputs`
+struct TpInputs {
+  uint16_t supply_adc;
+  uint16_t temper_adc;
+  uint16_t current_adc[4];
+  uint16_t angle_raw;
+  int16_t sample_phase;
+  uint16_t angle_check;
+};
+
+// Detailed operating state of the controller
+struct TpState {
+  enum TpStatus status;
+  uint8_t stage;
+  uint8_t progress;
+  bool energized;
+  bool at_velocity;
+  bool zero_speed;
+};
+
+// Command followed in normal operation
+struct TpSetpoint {
+  enum TpSetpointKind kind;
+  int32_t value;
+  uint16_t angle;
+};
+
+// Protocol frame, see `tunepulse_algo::protocol`
+struct TpFrame {
+  uint8_t bytes[8];
+};
+
+// Encoded telemetry points of one scope sample
+struct TpTelemetry {
+  uint8_t bytes[54];
+};
+
+// PID controller storage, initialize with `tp_pid_init`
+struct TpPid {
+  uint32_t storage[8];
+};
+
+// Low-pass filter storage, initialize with `tp_lpf_init`
+struct TpLpf {
+  uint32_t storage[4];
+};
+
+// Sine and cosine (i1.15)
+struct TpSinCos {
+  int16_t sin;
+  int16_t cos;
+};
+
+// Vector in polar form
+struct TpPolar {
+  uint32_t magnitude;
+  uint16_t angle;
+};
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Constructs a motor controller in `ctrl`, starting with encoder calibration
+//
+// # Arguments
+// * `ctrl` - Storage of the controller
+// * `motor` - Motor type
+// * `connection` - Phase connection pattern
+// * `frequency` - Number of `tp_controller_tick` calls per second
+// * `max_sup_voltage` - Maximum supply voltage (mV)
+// * `resistance` - Phase resistance (mOhm)
+void tp_controller_init(struct TpController *ctrl,
+                        enum TpMotorType motor,
+                        enum TpPhasePattern connection,
+                        uint16_t frequency,
+                        int32_t max_sup_voltage,
+                        int32_t resistance);
+
+// Runs one control tick, returns the PWM duty cycles to apply
+//
+// # Arguments
+// * `inputs` - Inputs sampled for this tick
+struct TpPwm tp_controller_tick(struct TpController *ctrl, const struct TpInputs *inputs);
+
+// Operating state of the controller
+enum TpStatus tp_controller_status(const struct TpController *ctrl);
+
+// Detailed operating state: calibration sub-stage and progress, energized windings, at target
+// velocity and zero speed
+struct TpState tp_controller_state(const struct TpController *ctrl);
+
+// Reason of the error state (see `FaultCode`, 0 if no fault)
+uint8_t tp_controller_fault(const struct TpController *ctrl);
+
+// Estimated speed (encoder counts per second)
+int32_t tp_controller_speed(const struct TpController *ctrl);
+
+// Enables or disables the drive output
+void tp_controller_set_enabled(struct TpController *ctrl, bool enabled);
+
+// Sets the target position (i16 rotations + u16 angle)
+void tp_controller_set_target_position(struct TpController *ctrl, int32_t target);
+
+// Changes the command followed in normal operation, applied on the next tick
+void tp_controller_set_setpoint(struct TpController *ctrl, struct TpSetpoint setpoint);
+
+// Restarts the full encoder calibration
+void tp_controller_recalibrate(struct TpController *ctrl);
+
+// Swaps motor type and phase connection of the disabled drive and restarts the calibration,
+// returns false while the output is enabled, the windings are energized or a test runs
+bool tp_controller_reconfigure(struct TpController *ctrl,
+                               enum TpMotorType motor,
+                               enum TpPhasePattern connection);
+
+// Writes a parameter (see `ParamId` in tunepulse_params)
+enum TpResult tp_controller_set_param(struct TpController *ctrl, uint16_t id, uint32_t value);
+
+// Reads a parameter into `value`, which is left unchanged on error
+enum TpResult tp_controller_get_param(const struct TpController *ctrl,
+                                      uint16_t id,
+                                      uint32_t *value);
+
+// Handles a protocol request frame received from the host, returns the reply frame
+struct TpFrame tp_controller_handle_request(struct TpController *ctrl, struct TpFrame request);
+
+// Takes the oldest captured scope sample, returns the number of bytes written to `out`
+// (0 if none is pending)
+uint32_t tp_controller_scope_read(struct TpController *ctrl, struct TpTelemetry *out);
+
+// Constructs a PID controller in `pid`, gains in percent (-10000..10000)
+void tp_pid_init(struct TpPid *pid, int32_t kp, int32_t ki, int32_t kd, int32_t kff);
+
+// Updates the controller, returns the output clamped to ±`limit`
+int16_t tp_pid_tick(struct TpPid *pid, int16_t error, int16_t feedfwd, int16_t limit);
+
+// Constructs a low-pass filter in `lpf`
+//
+// # Arguments
+// * `input_default` - Initial output
+// * `alpha` - Filter coefficient (0..255 = 0.0..1.0, higher is slower)
+void tp_lpf_init(struct TpLpf *lpf, uint16_t input_default, uint8_t alpha);
+
+// Filters one sample, returns the filtered value
+uint16_t tp_lpf_tick(struct TpLpf *lpf, uint16_t input);
+
+// Changes the filter coefficient
+void tp_lpf_set_alpha(struct TpLpf *lpf, uint8_t alpha);
+
+// Changes the filter order (1 - first, 2 - second), other values are rejected
+enum TpResult tp_lpf_set_order(struct TpLpf *lpf, uint8_t order);
+
+// Settles the filter at `value`, the next output starts from it
+void tp_lpf_reset(struct TpLpf *lpf, uint16_t value);
+
+// Sine and cosine of an angle (-32768..32767 = -180..180°)
+struct TpSinCos tp_angle2sincos(int16_t angle);
+
+// Scales a sine/cosine pair by `scale` (i1.15)
+struct TpSinCos tp_scale_sincos(struct TpSinCos input, int16_t scale);
+
+// Rotates a sine/cosine pair by the angle given as another sine/cosine pair
+struct TpSinCos tp_rotate_sincos(struct TpSinCos source, struct TpSinCos offset);
+
+// Magnitude and angle of a vector
+struct TpPolar tp_vector2mag_angle(int32_t x, int32_t y);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TUNEPULSE_H */
@@ -0,0 +1,518 @@
+// Implements the C interface of the TunePulse algorithm layer, allowing existing C firmware
+// to reuse the motor controller, PID, filters and trigonometry without a Rust main loop.
+
+// Key Features:
+// - `extern "C"` wrappers with a `tp_` prefix, header generated by cbindgen (include/tunepulse.h).
+// - No heap: C owns the storage of every object, sized by the opaque structs in the header.
+// - Plain value types for inputs, outputs and protocol frames, no Rust types cross the boundary.
+// - Built as a static library (libtunepulse_ffi.a) for the firmware target.
+
+// Detailed Operation:
+// Every Rust object is placed in an opaque, suitably aligned C struct (`TpController`,
+// `TpPid`, `TpLpf`). The `*_init` function constructs the object in place and must be called
+// before any other function taking the same struct. Compile time checks ensure the opaque
+// storage stays large enough when the wrapped types grow. Functions never retain pointers
+// passed to them, so the caller decides on static or stack allocation and on locking when the
+// same object is used from an interrupt and the main loop.
+// Panics halt the core (panic-halt), they are only possible on internal invariant violations.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#![no_std]
+
+use core::mem::{align_of, size_of};
+
+use panic_halt as _;
+
+use tunepulse_algo::inputs_dump::DataInputs;
+use tunepulse_algo::math_integer::controllers::pid::PID;
+use tunepulse_algo::math_integer::filters::lpf::{FilterLPF, FilterOrder};
+use tunepulse_algo::math_integer::trigonometry;
+use tunepulse_algo::motor_driver::{DriverStatus, MotorType, PhasePattern};
+use tunepulse_algo::params::ParamError;
+use tunepulse_algo::protocol::commands::STATE_NO_STAGE;
+use tunepulse_algo::protocol::FRAME_SIZE;
+use tunepulse_algo::scope::SAMPLE_SIZE;
+use tunepulse_algo::setpoint::Setpoint;
+use tunepulse_algo::MotorController;
+
+/// Motor controller storage, initialize with `tp_controller_init`
+#[repr(C)]
+pub struct TpController {
+    storage: [u64; 1024],
+}
+
+/// PID controller storage, initialize with `tp_pid_init`
+#[repr(C)]
+pub struct TpPid {
+    storage: [u32; 8],
+}
+
+/// Low-pass filter storage, initialize with `tp_lpf_init`
+#[repr(C)]
+pub struct TpLpf {
+    storage: [u32; 4],
+}
+
+// Opaque storage must hold the wrapped type
+const _: () = assert!(size_of::<MotorController>() <= size_of::<TpController>());
+const _: () = assert!(align_of::<MotorController>() <= align_of::<TpController>());
+const _: () = assert!(size_of::<PID>() <= size_of::<TpPid>());
+const _: () = assert!(align_of::<PID>() <= align_of::<TpPid>());
+const _: () = assert!(size_of::<FilterLPF>() <= size_of::<TpLpf>());
+const _: () = assert!(align_of::<FilterLPF>() <= align_of::<TpLpf>());
+
+// Sizes spelled out for cbindgen must match the protocol
+const _: () = assert!(size_of::<TpFrame>() == FRAME_SIZE);
+const _: () = assert!(size_of::<TpTelemetry>() == SAMPLE_SIZE);
+
+/// Motor type, mirrors `MotorType`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum TpMotorType {
+    Dc,
+    Bldc,
+    Step,
+}
+
+/// Phase connection pattern, mirrors `PhasePattern`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum TpPhasePattern {
+    Abcd,
+    Acdb,
+    Adbc,
+    Dcab,
+}
+
+/// Operating state of the controller
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum TpStatus {
+    Calibrating,
+    Ready,
+    Error,
+}
+
+/// Detailed operating state of the controller
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpState {
+    pub status: TpStatus,  // Overall status
+    pub stage: u8,         // Calibration sub-stage (see `CalibrationStage`), 0xFF unless calibrating
+    pub progress: u8,      // Calibration progress (percent), 100 once ready
+    pub energized: bool,   // Bridge drives current through the windings
+    pub at_velocity: bool, // Speed settled within the velocity window
+    pub zero_speed: bool,  // Speed settled below the zero speed threshold
+}
+
+/// Kind of the command followed in normal operation, mirrors `Setpoint`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum TpSetpointKind {
+    Current,
+    Velocity,
+    Position,
+    VoltageAngle,
+}
+
+/// Command followed in normal operation
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpSetpoint {
+    pub kind: TpSetpointKind,
+    pub value: i32, // Current (mA), speed (counts/s), position or voltage amplitude
+    pub angle: u16, // Electrical angle of the voltage vector, unused otherwise
+}
+
+/// Result of parameter access
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum TpResult {
+    Ok,
+    UnknownParam,
+    OutOfRange,
+    NotTunable,
+    StageFull,
+    Locked,
+}
+
+/// Sampled inputs of one control tick, mirrors `DataInputs`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpInputs {
+    pub supply_adc: u16,       // Supply voltage ADC reading
+    pub temper_adc: u16,       // Temperature ADC reading
+    pub current_adc: [u16; 4], // Phase current ADC readings
+    pub angle_raw: u16,        // Raw encoder angle (0..65535 = 0..360°)
+    pub sample_phase: i16,     // Encoder sample instant from the PWM period center (i1.15)
+    pub angle_check: u16,      // Second read of the angle, equal to `angle_raw` with single reads
+}
+
+/// PWM duty cycles of the four half bridges (i1.15)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpPwm {
+    pub duty: [i16; 4],
+}
+
+/// Protocol frame, see `tunepulse_algo::protocol`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpFrame {
+    pub bytes: [u8; 8],
+}
+
+/// Encoded telemetry points of one scope sample
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpTelemetry {
+    pub bytes: [u8; 54],
+}
+
+/// Sine and cosine (i1.15)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpSinCos {
+    pub sin: i16,
+    pub cos: i16,
+}
+
+/// Vector in polar form
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TpPolar {
+    pub magnitude: u32,
+    pub angle: u16, // 0..65535 = 0..360°
+}
+
+fn controller(ctrl: &mut TpController) -> &mut MotorController {
+    // SAFETY: storage is large and aligned enough (checked above) and initialized by
+    // tp_controller_init as required by the API contract
+    unsafe { &mut *(ctrl as *mut TpController).cast::<MotorController>() }
+}
+
+fn controller_ref(ctrl: &TpController) -> &MotorController {
+    // SAFETY: see controller()
+    unsafe { &*(ctrl as *const TpController).cast::<MotorController>() }
+}
+
+fn pid(pid: &mut TpPid) -> &mut PID {
+    // SAFETY: see controller(), initialized by tp_pid_init
+    unsafe { &mut *(pid as *mut TpPid).cast::<PID>() }
+}
+
+fn lpf(lpf: &mut TpLpf) -> &mut FilterLPF {
+    // SAFETY: see controller(), initialized by tp_lpf_init
+    unsafe { &mut *(lpf as *mut TpLpf).cast::<FilterLPF>() }
+}
+
+fn param_result(result: Result<(), ParamError>) -> TpResult {
+    match result {
+        Ok(()) => TpResult::Ok,
+        Err(ParamError::UnknownId) => TpResult::UnknownParam,
+        Err(ParamError::OutOfRange) => TpResult::OutOfRange,
+        Err(ParamError::NotTunable) => TpResult::NotTunable,
+        Err(ParamError::StageFull) => TpResult::StageFull,
+        Err(ParamError::Locked) => TpResult::Locked,
+    }
+}
+
+fn tp_status(status: DriverStatus) -> TpStatus {
+    match status {
+        DriverStatus::Calibrating => TpStatus::Calibrating,
+        DriverStatus::Ready => TpStatus::Ready,
+        DriverStatus::Error => TpStatus::Error,
+    }
+}
+
+fn motor_type(motor: TpMotorType) -> MotorType {
+    match motor {
+        TpMotorType::Dc => MotorType::DC,
+        TpMotorType::Bldc => MotorType::BLDC,
+        TpMotorType::Step => MotorType::STEP,
+    }
+}
+
+fn phase_pattern(connection: TpPhasePattern) -> PhasePattern {
+    match connection {
+        TpPhasePattern::Abcd => PhasePattern::ABCD,
+        TpPhasePattern::Acdb => PhasePattern::ACDB,
+        TpPhasePattern::Adbc => PhasePattern::ADBC,
+        TpPhasePattern::Dcab => PhasePattern::DCAB,
+    }
+}
+
+// ############################### MOTOR CONTROLLER ###################################
+
+/// Constructs a motor controller in `ctrl`, starting with encoder calibration
+///
+/// # Arguments
+/// * `ctrl` - Storage of the controller
+/// * `motor` - Motor type
+/// * `connection` - Phase connection pattern
+/// * `frequency` - Number of `tp_controller_tick` calls per second
+/// * `max_sup_voltage` - Maximum supply voltage (mV)
+/// * `resistance` - Phase resistance (mOhm)
+#[no_mangle]
+pub extern "C" fn tp_controller_init(
+    ctrl: &mut TpController,
+    motor: TpMotorType,
+    connection: TpPhasePattern,
+    frequency: u16,
+    max_sup_voltage: i32,
+    resistance: i32,
+) {
+    let controller = MotorController::new(
+        motor_type(motor),
+        phase_pattern(connection),
+        frequency,
+        max_sup_voltage,
+        resistance,
+    );
+    // SAFETY: storage fits the controller (checked above), previous content is not dropped
+    unsafe {
+        (ctrl as *mut TpController)
+            .cast::<MotorController>()
+            .write(controller)
+    };
+}
+
+/// Runs one control tick, returns the PWM duty cycles to apply
+///
+/// # Arguments
+/// * `inputs` - Inputs sampled for this tick
+#[no_mangle]
+pub extern "C" fn tp_controller_tick(ctrl: &mut TpController, inputs: &TpInputs) -> TpPwm {
+    let input = DataInputs {
+        supply_adc: inputs.supply_adc,
+        temper_adc: inputs.temper_adc,
+        currnt_adc: inputs.current_adc,
+        angle_raw: inputs.angle_raw,
+        sample_phase: inputs.sample_phase,
+        angle_check: inputs.angle_check,
+    };
+    TpPwm {
+        duty: controller(ctrl).tick(input),
+    }
+}
+
+/// Operating state of the controller
+#[no_mangle]
+pub extern "C" fn tp_controller_status(ctrl: &TpController) -> TpStatus {
+    tp_status(controller_ref(ctrl).status())
+}
+
+/// Detailed operating state: calibration sub-stage and progress, energized windings, at target
+/// velocity and zero speed
+#[no_mangle]
+pub extern "C" fn tp_controller_state(ctrl: &TpController) -> TpState {
+    let state = controller_ref(ctrl).state();
+    TpState {
+        status: tp_status(state.status),
+        stage: state.stage.map_or(STATE_NO_STAGE, |stage| stage as u8),
+        progress: state.progress,
+        energized: state.energized,
+        at_velocity: state.at_velocity,
+        zero_speed: state.zero_speed,
+    }
+}
+
+/// Reason of the error state (see `FaultCode`, 0 if no fault)
+#[no_mangle]
+pub extern "C" fn tp_controller_fault(ctrl: &TpController) -> u8 {
+    controller_ref(ctrl).fault() as u8
+}
+
+/// Estimated speed (encoder counts per second)
+#[no_mangle]
+pub extern "C" fn tp_controller_speed(ctrl: &TpController) -> i32 {
+    controller_ref(ctrl).speed()
+}
+
+/// Enables or disables the drive output
+#[no_mangle]
+pub extern "C" fn tp_controller_set_enabled(ctrl: &mut TpController, enabled: bool) {
+    controller(ctrl).set_enabled(enabled);
+}
+
+/// Sets the target position (i16 rotations + u16 angle)
+#[no_mangle]
+pub extern "C" fn tp_controller_set_target_position(ctrl: &mut TpController, target: i32) {
+    controller(ctrl).set_target_position(target);
+}
+
+/// Changes the command followed in normal operation, applied on the next tick
+#[no_mangle]
+pub extern "C" fn tp_controller_set_setpoint(ctrl: &mut TpController, setpoint: TpSetpoint) {
+    let value = setpoint.value;
+    controller(ctrl).set_setpoint(match setpoint.kind {
+        TpSetpointKind::Current => Setpoint::Current(value),
+        TpSetpointKind::Velocity => Setpoint::Velocity(value),
+        TpSetpointKind::Position => Setpoint::Position(value),
+        TpSetpointKind::VoltageAngle => Setpoint::VoltageAngle {
+            angle: setpoint.angle,
+            amplitude: value.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+        },
+    });
+}
+
+/// Restarts the full encoder calibration
+#[no_mangle]
+pub extern "C" fn tp_controller_recalibrate(ctrl: &mut TpController) {
+    controller(ctrl).recalibrate();
+}
+
+/// Swaps motor type and phase connection of the disabled drive and restarts the calibration,
+/// returns false while the output is enabled, the windings are energized or a test runs
+#[no_mangle]
+pub extern "C" fn tp_controller_reconfigure(
+    ctrl: &mut TpController,
+    motor: TpMotorType,
+    connection: TpPhasePattern,
+) -> bool {
+    controller(ctrl)
+        .reconfigure_motor(motor_type(motor), phase_pattern(connection))
+        .is_ok()
+}
+
+/// Writes a parameter (see `ParamId` in tunepulse_params)
+#[no_mangle]
+pub extern "C" fn tp_controller_set_param(
+    ctrl: &mut TpController,
+    id: u16,
+    value: u32,
+) -> TpResult {
+    param_result(controller(ctrl).set_param(id, value))
+}
+
+/// Reads a parameter into `value`, which is left unchanged on error
+#[no_mangle]
+pub extern "C" fn tp_controller_get_param(
+    ctrl: &TpController,
+    id: u16,
+    value: &mut u32,
+) -> TpResult {
+    param_result(controller_ref(ctrl).get_param(id).map(|raw| *value = raw))
+}
+
+/// Handles a protocol request frame received from the host, returns the reply frame
+#[no_mangle]
+pub extern "C" fn tp_controller_handle_request(
+    ctrl: &mut TpController,
+    request: TpFrame,
+) -> TpFrame {
+    TpFrame {
+        bytes: controller(ctrl).handle_request(&request.bytes),
+    }
+}
+
+/// Takes the oldest captured scope sample, returns the number of bytes written to `out`
+/// (0 if none is pending)
+#[no_mangle]
+pub extern "C" fn tp_controller_scope_read(ctrl: &mut TpController, out: &mut TpTelemetry) -> u32 {
+    match controller(ctrl).scope().pop() {
+        Some(sample) => sample.encode(&mut out.bytes) as u32,
+        None => 0,
+    }
+}
+
+// ##################################### PID ##########################################
+
+/// Constructs a PID controller in `pid`, gains in percent (-10000..10000)
+#[no_mangle]
+pub extern "C" fn tp_pid_init(pid: &mut TpPid, kp: i32, ki: i32, kd: i32, kff: i32) {
+    // SAFETY: storage fits the controller (checked above)
+    unsafe {
+        (pid as *mut TpPid)
+            .cast::<PID>()
+            .write(PID::new(kp, ki, kd, kff))
+    };
+}
+
+/// Updates the controller, returns the output clamped to ±`limit`
+#[no_mangle]
+pub extern "C" fn tp_pid_tick(pid: &mut TpPid, error: i16, feedfwd: i16, limit: i16) -> i16 {
+    let pid = self::pid(pid);
+    pid.tick(error, feedfwd, limit);
+    pid.output()
+}
+
+// ################################ LOW-PASS FILTER ###################################
+
+/// Constructs a low-pass filter in `lpf`
+///
+/// # Arguments
+/// * `input_default` - Initial output
+/// * `alpha` - Filter coefficient (0..255 = 0.0..1.0, higher is slower)
+#[no_mangle]
+pub extern "C" fn tp_lpf_init(lpf: &mut TpLpf, input_default: u16, alpha: u8) {
+    // SAFETY: storage fits the filter (checked above)
+    unsafe {
+        (lpf as *mut TpLpf)
+            .cast::<FilterLPF>()
+            .write(FilterLPF::new(input_default, alpha))
+    };
+}
+
+/// Filters one sample, returns the filtered value
+#[no_mangle]
+pub extern "C" fn tp_lpf_tick(lpf: &mut TpLpf, input: u16) -> u16 {
+    self::lpf(lpf).tick(input)
+}
+
+/// Changes the filter coefficient
+#[no_mangle]
+pub extern "C" fn tp_lpf_set_alpha(lpf: &mut TpLpf, alpha: u8) {
+    self::lpf(lpf).set_alpha(alpha);
+}
+
+/// Changes the filter order (1 - first, 2 - second), other values are rejected
+#[no_mangle]
+pub extern "C" fn tp_lpf_set_order(lpf: &mut TpLpf, order: u8) -> TpResult {
+    match FilterOrder::from_u8(order) {
+        Some(order) => {
+            self::lpf(lpf).set_order(order);
+            TpResult::Ok
+        }
+        None => TpResult::OutOfRange,
+    }
+}
+
+/// Settles the filter at `value`, the next output starts from it
+#[no_mangle]
+pub extern "C" fn tp_lpf_reset(lpf: &mut TpLpf, value: u16) {
+    self::lpf(lpf).reset(value);
+}
+
+// ################################# TRIGONOMETRY #####################################
+
+/// Sine and cosine of an angle (-32768..32767 = -180..180°)
+#[no_mangle]
+pub extern "C" fn tp_angle2sincos(angle: i16) -> TpSinCos {
+    let (sin, cos) = trigonometry::angle2sincos(angle);
+    TpSinCos { sin, cos }
+}
+
+/// Scales a sine/cosine pair by `scale` (i1.15)
+#[no_mangle]
+pub extern "C" fn tp_scale_sincos(input: TpSinCos, scale: i16) -> TpSinCos {
+    let (sin, cos) = trigonometry::scale_sincos((input.sin, input.cos), scale);
+    TpSinCos { sin, cos }
+}
+
+/// Rotates a sine/cosine pair by the angle given as another sine/cosine pair
+#[no_mangle]
+pub extern "C" fn tp_rotate_sincos(source: TpSinCos, offset: TpSinCos) -> TpSinCos {
+    let (sin, cos) =
+        trigonometry::rotate_sincos((source.sin, source.cos), (offset.sin, offset.cos));
+    TpSinCos { sin, cos }
+}
+
+/// Magnitude and angle of a vector
+#[no_mangle]
+pub extern "C" fn tp_vector2mag_angle(x: i32, y: i32) -> TpPolar {
+    let (magnitude, angle) = trigonometry::vector2mag_angle(x, y);
+    TpPolar { magnitude, angle }
+}
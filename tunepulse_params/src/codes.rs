@@ -0,0 +1,372 @@
+// Implements the tables naming the numeric codes of status, event and report frames.
+
+// Key Features:
+// - One table per code set: driver status, faults, events, reply results, status flags,
+//   calibration and production metrics, setup wizard steps and results, motor presets.
+// - Names, units and descriptions live on the host side only, the firmware sends numbers.
+// - JSON export of all tables for tools written in other languages.
+
+// Detailed Operation:
+// Frames from the drive carry every status, fault, event and report field as a number, the
+// firmware never formats text for the host. The 128 kB parts have no room for name strings of
+// every diagnostic, so the names are kept here: host tools look the codes up, the firmware only
+// uses the enums whose values the tables describe and the linker drops the unused strings.
+// Every table lists its codes in ascending order without gaps (`code == index`, checked at
+// compile time), the firmware checks its enums against the table length. A new code therefore
+// needs the enum variant in the firmware and a table entry here, like a new parameter.
+// For `STATUS_FLAGS` the code is the bit number in the flags byte of the status reply.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+use core::fmt::Write;
+
+/// Numeric code with its name for host tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeDef {
+    pub code: u8,                  // Value sent by the firmware
+    pub name: &'static str,        // Short snake_case name
+    pub unit: &'static str,        // Unit of a reported value, empty if none
+    pub description: &'static str, // Shown to the user
+}
+
+/// Driver status, the status byte without `DRIVER_STATUS_UNSAVED`
+pub const DRIVER_STATUS: [CodeDef; 3] = [
+    code(0, "calibrating", "Encoder calibration running"),
+    code(1, "ready", "Calibrated, normal operation"),
+    code(2, "error", "Error state, see the fault code"),
+];
+
+/// Fault codes of the status reply, fault events and the LED blink count
+pub const FAULT_CODES: [CodeDef; 9] = [
+    code(0, "none", "No fault present"),
+    code(1, "calibration_failed", "Encoder calibration failed"),
+    code(
+        2,
+        "supply_undervoltage",
+        "Supply voltage too low for operation",
+    ),
+    code(
+        3,
+        "supply_overvoltage",
+        "Supply voltage exceeds the allowed maximum",
+    ),
+    code(4, "stall", "Motor stalled or lost synchronism"),
+    code(
+        5,
+        "master_lost",
+        "Master position of the electronic gearing stopped updating",
+    ),
+    code(
+        6,
+        "calibration_loaded",
+        "Calibration aborted, the axis load skewed the table",
+    ),
+    code(
+        7,
+        "calibration_timeout",
+        "Calibration stage timed out, the motor doesn't follow",
+    ),
+    code(
+        8,
+        "following_error",
+        "Position error stayed outside the following error window",
+    ),
+];
+
+/// Motion events, the code is also the bit in the subscription mask
+pub const MOTION_EVENTS: [CodeDef; 17] = [
+    code(
+        0,
+        "target_reached",
+        "Position settled within the in-position window",
+    ),
+    code(1, "homing_complete", "Homing sequence finished"),
+    code(2, "fault_raised", "Fault raised, argument: fault code"),
+    code(3, "limit_hit", "Limit switch or soft limit hit"),
+    code(4, "calibration_done", "Calibration finished"),
+    code(5, "position_captured", "Capture input latched the position"),
+    code(
+        6,
+        "collision_detected",
+        "Load torque step detected as collision",
+    ),
+    code(7, "jog_aborted", "Jog stopped at the torque limit"),
+    code(
+        8,
+        "step_test_done",
+        "Step response test finished, argument 1 if aborted",
+    ),
+    code(
+        9,
+        "production_test_done",
+        "Production test finished, argument: failed metrics",
+    ),
+    code(
+        10,
+        "unsaved_changes",
+        "Disabled with unsaved parameter changes",
+    ),
+    code(11, "encoder_degraded", "Rejected encoder samples bridged"),
+    code(12, "encoder_recovered", "Encoder samples accepted again"),
+    code(
+        13,
+        "calibration_load_warning",
+        "Calibrated on a lightly loaded axis",
+    ),
+    code(
+        14,
+        "wizard_progress",
+        "Setup wizard step started or finished",
+    ),
+    code(
+        15,
+        "flash_write_failed",
+        "Parameter save or factory data write failed",
+    ),
+    code(
+        16,
+        "stall_warning",
+        "Load angle close to pull-out, argument: filtered load angle",
+    ),
+];
+
+/// Results of command and parameter replies
+pub const REPLY_RESULTS: [CodeDef; 7] = [
+    code(0, "ok", "accepted"),
+    code(1, "unknown_param", "unknown parameter"),
+    code(2, "out_of_range", "value out of range"),
+    code(3, "unknown_command", "unknown command"),
+    code(4, "rejected", "rejected in current state"),
+    code(5, "invalid_frame", "invalid frame"),
+    code(6, "access_denied", "access level locked, unlock it first"),
+];
+
+/// Bits of the flags byte of the status reply
+pub const STATUS_FLAGS: [CodeDef; 8] = [
+    code(0, "enabled", "Drive output enabled"),
+    code(1, "in_position", "Position within the in-position window"),
+    code(2, "standstill", "Position hold at standstill"),
+    code(3, "sequence", "Motion sequence running"),
+    code(4, "excitation", "Test signal running"),
+    code(5, "measuring", "Measurement, test or setup wizard running"),
+    code(6, "following", "Electronic gearing engaged"),
+    code(7, "collision", "Collision latched"),
+];
+
+/// Metrics of the calibration report
+pub const CALIBRATION_METRICS: [CodeDef; 7] = [
+    metric(
+        0,
+        "table_deviation",
+        "counts",
+        "Largest table deviation from an ideal encoder",
+    ),
+    metric(
+        1,
+        "trim_spread",
+        "el counts",
+        "Electrical offset spread of the last trim",
+    ),
+    metric(
+        2,
+        "torque_ripple",
+        "‰",
+        "Torque ripple on the corrected angle",
+    ),
+    metric(
+        3,
+        "torque_ripple_raw",
+        "‰",
+        "Torque ripple on the raw encoder angle",
+    ),
+    metric(
+        4,
+        "pass_hysteresis",
+        "counts",
+        "Mean hysteresis between the calibration passes",
+    ),
+    metric(
+        5,
+        "load_flags",
+        "",
+        "Load check flags: 1 friction, 2 asymmetry, 4 settling",
+    ),
+    metric(
+        6,
+        "load_angle_ripple",
+        "el counts",
+        "Load angle ripple in operation since the calibration",
+    ),
+];
+
+/// Metrics of the production test
+pub const PRODUCTION_METRICS: [CodeDef; 5] = [
+    metric(0, "self_test", "", "Failed self-test checks"),
+    metric(1, "supply_mv", "mV", "Supply voltage"),
+    metric(2, "temperature_adc", "ADC", "Temperature sensor reading"),
+    metric(
+        3,
+        "table_deviation",
+        "counts",
+        "Largest table deviation from an ideal encoder",
+    ),
+    metric(
+        4,
+        "trim_spread",
+        "el counts",
+        "Electrical offset spread of the trim",
+    ),
+];
+
+/// Steps of the setup wizard in run order
+pub const WIZARD_STEPS: [CodeDef; 6] = [
+    code(0, "supply_check", "Supply charged and bridge enabled"),
+    code(1, "wiring_check", "Current through both coils"),
+    code(2, "impedance", "Winding resistance and inductance"),
+    code(3, "encoder_check", "Encoder follows an electrical turn"),
+    code(4, "calibration", "Encoder calibration"),
+    code(5, "gain_suggestion", "Closest motor preset"),
+];
+
+/// Outcome of a setup wizard step
+pub const STEP_OUTCOMES: [CodeDef; 6] = [
+    code(0, "pending", "Not reached"),
+    code(1, "running", "Running"),
+    code(2, "passed", "Passed"),
+    code(3, "failed", "Failed"),
+    code(4, "skipped", "Skipped, not measurable on this board"),
+    code(5, "aborted", "Aborted"),
+];
+
+/// Results of the setup wizard
+pub const WIZARD_RESULTS: [CodeDef; 7] = [
+    metric(0, "supply_mv", "mV", "Supply voltage"),
+    metric(1, "open_coils", "", "Open coils: 1 coil A, 2 coil B"),
+    metric(2, "resistance", "mOhm", "Phase resistance"),
+    metric(3, "inductance", "uH", "Phase inductance"),
+    metric(
+        4,
+        "encoder_travel",
+        "counts",
+        "Encoder travel of one electrical turn",
+    ),
+    metric(5, "pole_pairs", "", "Pole pairs from the encoder travel"),
+    metric(
+        6,
+        "suggested_preset",
+        "",
+        "Closest motor preset, see `MOTOR_PRESETS`",
+    ),
+];
+
+/// Registers of the peak-hold diagnostics
+pub const PEAK_VALUES: [CodeDef; 4] = [
+    metric(0, "current", "mA", "Largest measured current magnitude"),
+    metric(
+        1,
+        "temperature_adc",
+        "ADC",
+        "Largest temperature sensor reading",
+    ),
+    metric(2, "speed", "counts/s", "Largest speed magnitude"),
+    metric(
+        3,
+        "following_error",
+        "counts",
+        "Largest position error beyond the deadband",
+    ),
+];
+
+/// Values of the `MotorPreset` parameter
+pub const MOTOR_PRESETS: [CodeDef; 6] = [
+    code(0, "custom", "Data configured by the board or the host"),
+    code(1, "nema17", "NEMA17 stepper, 1.8°, ~1.7 A"),
+    code(2, "nema17_fine", "NEMA17 stepper, 0.9°, ~2 A"),
+    code(3, "nema23", "NEMA23 stepper, 1.8°, ~2.8 A"),
+    code(4, "gimbal2208", "2208 gimbal BLDC, 12N14P"),
+    code(5, "gimbal4108", "4108 gimbal BLDC, 24N22P"),
+];
+
+/// All tables by the name used in the JSON export
+pub const CODE_TABLES: [(&str, &[CodeDef]); 12] = [
+    ("driver_status", &DRIVER_STATUS),
+    ("fault_codes", &FAULT_CODES),
+    ("motion_events", &MOTION_EVENTS),
+    ("reply_results", &REPLY_RESULTS),
+    ("status_flags", &STATUS_FLAGS),
+    ("calibration_metrics", &CALIBRATION_METRICS),
+    ("production_metrics", &PRODUCTION_METRICS),
+    ("wizard_steps", &WIZARD_STEPS),
+    ("step_outcomes", &STEP_OUTCOMES),
+    ("wizard_results", &WIZARD_RESULTS),
+    ("peak_values", &PEAK_VALUES),
+    ("motor_presets", &MOTOR_PRESETS),
+];
+
+/// Looks up a code of a table, `None` for codes the table doesn't know (e.g. newer firmware)
+pub fn find_code(table: &'static [CodeDef], code: u8) -> Option<&'static CodeDef> {
+    table.get(code as usize)
+}
+
+/// Name of a code, `"unknown"` if the table doesn't know it
+pub fn code_name(table: &'static [CodeDef], code: u8) -> &'static str {
+    find_code(table, code).map_or("unknown", |def| def.name)
+}
+
+/// Writes all tables as JSON object of arrays, the format read by the Python code generator
+pub fn write_codes_json<W: Write>(out: &mut W) -> core::fmt::Result {
+    out.write_str("{\n")?;
+    for (idx, (table, defs)) in CODE_TABLES.iter().enumerate() {
+        writeln!(out, "  \"{}\": [", table)?;
+        for (i, def) in defs.iter().enumerate() {
+            write!(
+                out,
+                "    {{\"code\": {}, \"name\": \"{}\", \"unit\": \"{}\", \"description\": \"{}\"}}",
+                def.code, def.name, def.unit, def.description
+            )?;
+            out.write_str(if i + 1 < defs.len() { ",\n" } else { "\n" })?;
+        }
+        out.write_str(if idx + 1 < CODE_TABLES.len() {
+            "  ],\n"
+        } else {
+            "  ]\n"
+        })?;
+    }
+    out.write_str("}\n")
+}
+
+const fn code(code: u8, name: &'static str, description: &'static str) -> CodeDef {
+    metric(code, name, "", description)
+}
+
+const fn metric(
+    code: u8,
+    name: &'static str,
+    unit: &'static str,
+    description: &'static str,
+) -> CodeDef {
+    CodeDef {
+        code,
+        name,
+        unit,
+        description,
+    }
+}
+
+// Table index has to match the code, checked at compile time
+const _: () = {
+    let mut t = 0;
+    while t < CODE_TABLES.len() {
+        let defs = CODE_TABLES[t].1;
+        let mut i = 0;
+        while i < defs.len() {
+            assert!(
+                defs[i].code as usize == i,
+                "Code tables must list codes without gaps"
+            );
+            i += 1;
+        }
+        t += 1;
+    }
+};
@@ -0,0 +1,1362 @@
+// Implements the single source of truth for parameter identifiers, types and ranges shared by
+// the firmware and all host tools.
+
+// Key Features:
+// - Dependency free no_std crate usable by firmware and host tools alike.
+// - Stable numeric identifiers, names, types, units, defaults and ranges of all parameters.
+// - Marking of hot-tunable parameters (gains, filter alphas, limits) safe to change while running.
+// - Access level per parameter (user, advanced, factory) with the keys unlocking the levels.
+// - JSON export of the table for tools written in other languages.
+// - Names of the numeric status, fault, event and report codes (`codes`).
+
+// Detailed Operation:
+// `PARAMS` lists one `ParamDef` per `ParamId`, the index in the table equals the identifier
+// value. The firmware registry validates writes against the table and host tools use it to
+// address parameters by name and to check values before sending them. Adding a parameter only
+// requires a new `ParamId` variant and a table entry, both sides pick it up on next build.
+// Parameters above the user level (current limits, brake and encoder setup, fault handling,
+// production limits) are only written after the host unlocked their level with its key. The
+// keys are published here on purpose: the levels guard against accidental changes through the
+// tuning interface, they are not a security boundary.
+
+// Licensed under the Apache License, Version 2.0
+// Copyright 2024 Anton Khrustalev, creapunk.com
+
+#![no_std]
+
+pub mod codes;
+
+/// Version of the parameter layout, incremented whenever an identifier is reused or the meaning
+/// of a stored value changes. Each increment needs a migration step in the firmware storage.
+pub const PARAM_LAYOUT_VERSION: u16 = 1;
+
+/// Flag of a digital pin configuration word inverting the pin level
+pub const IO_INVERT: u32 = 1 << 8;
+
+/// Key unlocking the advanced access level
+pub const ADVANCED_KEY: u32 = 0x4144_5643; // "ADVC"
+
+/// Key unlocking the factory access level
+pub const FACTORY_KEY: u32 = 0x4641_4354; // "FACT"
+
+/// Access level required to write a parameter, levels include all lower ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum AccessLevel {
+    /// Tuning and application settings, always writable
+    User = 0,
+    /// Current limits, brake, encoder and fault handling setup
+    Advanced = 1,
+    /// Production settings and factory data
+    Factory = 2,
+}
+
+impl AccessLevel {
+    /// Level unlocked by `key`, `None` if the key is unknown
+    pub const fn from_key(key: u32) -> Option<Self> {
+        match key {
+            ADVANCED_KEY => Some(AccessLevel::Advanced),
+            FACTORY_KEY => Some(AccessLevel::Factory),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            AccessLevel::User => "user",
+            AccessLevel::Advanced => "advanced",
+            AccessLevel::Factory => "factory",
+        }
+    }
+}
+
+/// Identifiers of all parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ParamId {
+    /// Bit mask of motion events pushed to the host (see `MotionEvent`)
+    EventMask = 0,
+    /// Function of digital pins (see `IoFunction`, `IO_INVERT` inverts the level)
+    IoPin0 = 1,
+    IoPin1 = 2,
+    IoPin2 = 3,
+    IoPin3 = 4,
+    /// Delay between brake release and motion (ms)
+    BrakeReleaseDelay = 5,
+    /// Delay between brake engage and torque removal (ms)
+    BrakeEngageDelay = 6,
+    /// Audible status beeps through the windings (0 - off, 1 - on)
+    BeepEnable = 7,
+    /// Current used for beeps (mA)
+    BeepCurrent = 8,
+    /// Active damping gain (mA per speed unit as i24.8, 0 - disabled)
+    DampingGain = 9,
+    /// Maximum current injected by active damping (mA)
+    DampingLimit = 10,
+    /// Feedback of the estimated load torque (0 - disabled, 255 - full compensation)
+    DisturbanceFeedback = 11,
+    /// Position filter alpha at standstill (higher - narrower bandwidth)
+    HoldFilterAlpha = 12,
+    /// Signal captured by scope channel (see `ScopeSignal`, 0 - disabled)
+    ScopeChannel0 = 13,
+    ScopeChannel1 = 14,
+    /// Scope capture every n-th control tick
+    ScopeDecimation = 15,
+    /// Loop node excited by the signal generator (0 - current, 1 - velocity, 2 - position)
+    ExcitationPoint = 16,
+    /// Waveform of the signal generator (0 - step, 1 - square, 2 - sweep, 3 - PRBS)
+    ExcitationWaveform = 17,
+    /// Amplitude of the excitation in units of the excited node
+    ExcitationAmplitude = 18,
+    /// Start frequency (or PRBS bit rate) of the excitation (mHz)
+    ExcitationFrequency = 19,
+    /// End frequency of a sweep (mHz)
+    ExcitationFrequencyEnd = 20,
+    /// Duration of the excitation (ms, 0 - until stopped)
+    ExcitationDuration = 21,
+    /// Number of frequency response points between start and end frequency
+    BodePoints = 22,
+    /// Number of periods measured at every frequency response point
+    BodePeriods = 23,
+    /// Response signal of the frequency response measurement (see `ScopeSignal`)
+    BodeResponse = 24,
+    /// Position capture after an edge (0 - single shot, re-armed by command, 1 - continuous)
+    CaptureMode = 25,
+    /// Positions passed by the compare output (i16 rotations + u16 angle)
+    ComparePosition0 = 26,
+    ComparePosition1 = 27,
+    ComparePosition2 = 28,
+    ComparePosition3 = 29,
+    /// Number of active compare positions (0 - compare output disabled)
+    CompareCount = 30,
+    /// Compare output action (0 - pulse, 1 - toggle)
+    CompareAction = 31,
+    /// Direction of travel triggering the compare output (0 - both, 1 - forward, 2 - backward)
+    CompareDirection = 32,
+    /// Width of a compare output pulse (µs, at least one control tick)
+    ComparePulseWidth = 33,
+    /// Electronic gearing ratio numerator, negative values reverse the direction
+    GearNumerator = 34,
+    /// Electronic gearing ratio denominator
+    GearDenominator = 35,
+    /// Phase offset added to the geared master position (i16 rotations + u16 angle)
+    GearOffset = 36,
+    /// Speed limit of the axis following the master (counts per second, 0 - unlimited)
+    FollowMaxSpeed = 37,
+    /// Master position timeout raising a fault while following (ms, 0 - disabled)
+    FollowTimeout = 38,
+    /// Rate of the position and velocity frames for robotics stacks (Hz, 0 - disabled)
+    OdometryRate = 39,
+    /// Speed estimation window, longer is finer and smoother but lags more (ticks)
+    SpeedWindow = 40,
+    /// Low-pass filter of the speed estimate (0 - off, 255 - strongest)
+    SpeedFilterAlpha = 41,
+    /// Unit of the reported speed (0 - counts/s, 1 - counts/tick, 2 - RPM, 3 - mrad/s)
+    SpeedUnit = 42,
+    /// Load torque step detected as collision (mA, 0 - disabled, needs identified inertia)
+    CollisionThreshold = 43,
+    /// Time the load torque step has to persist before a collision is detected (ms)
+    CollisionDebounce = 44,
+    /// Reaction on a collision (0 - stop, 1 - back off, 2 - reduce torque)
+    CollisionReaction = 45,
+    /// Back off distance of the reverse reaction (i16 rotations + u16 angle)
+    CollisionReverse = 46,
+    /// Torque limit of the reduce torque reaction (mA)
+    CollisionTorque = 47,
+    /// Speed of the commissioning jog (counts per second)
+    JogSpeed = 48,
+    /// Torque limit while jogging, the jog stops when it is reached (mA)
+    JogTorque = 49,
+    /// Time the host has to repeat the jog command in, the jog stops otherwise (ms)
+    JogTimeout = 50,
+    /// Height of the current step of the step response test (mA)
+    StepCurrent = 51,
+    /// Height of the velocity step of the step response test (counts per second)
+    StepSpeed = 52,
+    /// Length of every step of the step response test (ms)
+    StepDuration = 53,
+    /// Travel from the start position aborting the step response test (counts)
+    StepTravel = 54,
+    /// User position counts against the encoder, the calibration table stays valid
+    EncoderInvert = 55,
+    /// User position of the encoder zero (i16 rotations + u16 angle)
+    EncoderOffset = 56,
+    /// Position filter alpha at and below the slow speed (higher - narrower bandwidth)
+    FilterAlphaSlow = 57,
+    /// Position filter alpha at and above the fast speed, at any speed if the schedule is off
+    FilterAlphaFast = 58,
+    /// Speed up to which the slow filter alpha applies (counts per second)
+    FilterSpeedSlow = 59,
+    /// Speed from which the fast filter alpha applies (counts per second, 0 - schedule off)
+    FilterSpeedFast = 60,
+    /// Position filter order (1 - first, 2 - second: steeper roll-off, more lag)
+    FilterOrder = 61,
+    /// Reaction to sensor faults (0 - short brake, 1 - freewheel, 2 - ramp down, 3 - open loop)
+    FaultReactionSensor = 62,
+    /// Reaction to supply faults (0 - short brake, 1 - freewheel, 2 - ramp down, 3 - open loop)
+    FaultReactionSupply = 63,
+    /// Reaction to motion faults (0 - short brake, 1 - freewheel, 2 - ramp down, 3 - open loop)
+    FaultReactionMotion = 64,
+    /// Length of the fault torque and speed ramps
+    FaultRampTime = 65,
+    /// Supply voltage required before the bridge is enabled at power-up
+    SupplyEnableVoltage = 66,
+    /// Maximum supply slope at power-up, faster changes mean the capacitors still charge
+    SupplyMaxSlope = 67,
+    /// Time the supply has to stay stable before the bridge is enabled
+    SupplySettleTime = 68,
+    /// Angle traces routed to the scope (bit 0 - raw, 1 - filtered, 2 - corrected electrical),
+    /// fill channel 0 then 1 and override the channel parameters while non-zero
+    ScopeAngleMask = 69,
+    /// Lowest supply voltage passing the production test
+    ProductionSupplyMin = 70,
+    /// Highest supply voltage passing the production test
+    ProductionSupplyMax = 71,
+    /// Lowest temperature sensor reading passing the production test (raw ADC)
+    ProductionTempMin = 72,
+    /// Highest temperature sensor reading passing the production test (raw ADC)
+    ProductionTempMax = 73,
+    /// Largest calibration table deviation passing the production test (counts)
+    ProductionDeviationMax = 74,
+    /// Largest spread of the trim points passing the production test (65536 per 360° el)
+    ProductionSpreadMax = 75,
+    /// Largest difference of two back-to-back encoder reads (counts, 0 - single read)
+    EncoderGlitchThreshold = 76,
+    /// EMC-robust encoder profile: read retry, plausibility check, bridging of rejected samples
+    EncoderEmcMode = 77,
+    /// Measure the torque ripple after each calibration (slow rotation, ~1.3 s)
+    RippleTest = 78,
+    /// Supply voltage below which the undervoltage fault trips (0 - off)
+    SupplyUndervoltage = 79,
+    /// Supply voltage above which the overvoltage fault trips (0 - off)
+    SupplyOvervoltage = 80,
+    /// Time the supply has to stay out of range continuously before the fault trips
+    SupplyMissTime = 81,
+    /// Time after the bridge is enabled before the supply monitoring starts
+    SupplyGraceTime = 82,
+    /// Rate of the health and statistics reports of the slow status channel (Hz, 0 - disabled)
+    StatusRate = 83,
+    /// Duty lost to the bridge dead time, compensated at small duty (i1.15 of the PWM period,
+    /// 0 - off)
+    DeadTimeCompensation = 84,
+    /// Current samples within this time of a switching edge are replaced by the last clean one
+    /// (0 - off)
+    CurrentBlanking = 85,
+    /// Data of a common motor applied at once (see `MotorPreset`, 0 - custom)
+    MotorPreset = 86,
+    /// Proportional gain of the current loop on the measured currents (%, 0 with the integral
+    /// gain - voltage from the winding resistance only)
+    CurrentLoopKp = 87,
+    /// Integral gain of the current loop (% per control tick)
+    CurrentLoopKi = 88,
+    /// Source of the rotor angle (0 - encoder, 1 - sensorless back-EMF observer, BLDC only)
+    AngleSource = 89,
+    /// Bandwidth of the sensorless back-EMF observer
+    ObserverBandwidth = 90,
+    /// Back-EMF above which the sensorless commutation follows the observer
+    SensorlessMinEmf = 91,
+    /// Electrical speed of the open-loop sensorless start
+    SensorlessStartSpeed = 92,
+    /// Scope channels sent as min/max/mean of the decimation window (bit per channel)
+    ScopeEnvelope = 93,
+    /// Master position counted by the STEP/DIR input (0 - off, 1 - step/dir, 2 - quadrature),
+    /// followed through the electronic gearing once the drive is ready
+    StepInput = 94,
+    /// Node number on the CAN bus, the identifiers of its frames follow from it
+    CanNode = 95,
+    /// Bit rate of the CAN bus, applied at startup
+    CanBitrate = 96,
+    /// Protocol of the CAN link (0 - TunePulse frames, 1 - CANopen CiA 402), applied at startup
+    CanProtocol = 97,
+    /// Largest following error of the position loop (i16 rotations + u16 angle, 0 - disabled)
+    FollowingErrorWindow = 98,
+    /// Time the following error may stay outside the window before the fault trips (ms)
+    FollowingErrorTimeout = 99,
+    /// Speed deviation from the velocity setpoint reported as at target velocity (counts/s,
+    /// 0 - disabled)
+    VelocityWindow = 100,
+    /// Time the speed has to stay within the velocity window (ms)
+    VelocityWindowTime = 101,
+    /// Absolute speed reported as zero speed (counts/s, 0 - disabled)
+    ZeroSpeedThreshold = 102,
+    /// Time the speed has to stay below the zero speed threshold (ms)
+    ZeroSpeedTime = 103,
+    /// Current waveform deviation treated as step loss in open-loop (%, 0 - disabled)
+    StepLossThreshold = 104,
+    /// Lowest commanded electrical frequency the step loss detection runs at (Hz)
+    StepLossMinSpeed = 105,
+    /// Time the deviation has to persist before step loss is reported (ms)
+    StepLossDebounce = 106,
+}
+
+impl ParamId {
+    /// Converts raw identifier received over protocol
+    pub fn from_raw(raw: u16) -> Option<Self> {
+        PARAMS.get(raw as usize).map(|def| def.id)
+    }
+}
+
+/// Interpretation of the raw u32 parameter value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    /// Unsigned integer
+    Unsigned,
+    /// Signed integer stored in two's complement
+    Signed,
+    /// 0 - false, 1 - true
+    Bool,
+    /// Bit mask
+    Mask,
+}
+
+impl ParamType {
+    pub const fn name(self) -> &'static str {
+        match self {
+            ParamType::Unsigned => "unsigned",
+            ParamType::Signed => "signed",
+            ParamType::Bool => "bool",
+            ParamType::Mask => "mask",
+        }
+    }
+}
+
+/// Static description of a parameter.
+pub struct ParamDef {
+    pub id: ParamId,
+    pub name: &'static str,
+    pub kind: ParamType,
+    pub unit: &'static str,
+    pub default: u32,
+    pub min: u32,
+    pub max: u32,
+    /// Parameter may be tuned while running, staged values are applied between control ticks
+    pub hot: bool,
+    /// Level the host has to unlock before writing the parameter
+    pub access: AccessLevel,
+}
+
+/// Table of parameter definitions, index matches `ParamId` value.
+pub const PARAMS: [ParamDef; PARAM_COUNT] = [
+    ParamDef {
+        id: ParamId::EventMask,
+        name: "event_mask",
+        kind: ParamType::Mask,
+        unit: "",
+        default: u32::MAX, // All events enabled
+        min: 0,
+        max: u32::MAX,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    io_pin(ParamId::IoPin0, "io_pin0"),
+    io_pin(ParamId::IoPin1, "io_pin1"),
+    io_pin(ParamId::IoPin2, "io_pin2"),
+    io_pin(ParamId::IoPin3, "io_pin3"),
+    ParamDef {
+        id: ParamId::BrakeReleaseDelay,
+        name: "brake_release_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 0, // No brake
+        min: 0,
+        max: 2000,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::BrakeEngageDelay,
+        name: "brake_engage_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 0, // No brake
+        min: 0,
+        max: 2000,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::BeepEnable,
+        name: "beep_enable",
+        kind: ParamType::Bool,
+        unit: "",
+        default: 1,
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::BeepCurrent,
+        name: "beep_current_ma",
+        kind: ParamType::Unsigned,
+        unit: "mA",
+        default: 300,
+        min: 0,
+        max: 1000,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::DampingGain,
+        name: "damping_gain",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Disabled
+        min: 0,
+        max: 0xFFFF,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::DampingLimit,
+        name: "damping_limit_ma",
+        kind: ParamType::Unsigned,
+        unit: "mA",
+        default: 0,
+        min: 0,
+        max: 2000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::DisturbanceFeedback,
+        name: "disturbance_feedback",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Estimation only
+        min: 0,
+        max: 255,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::HoldFilterAlpha,
+        name: "hold_filter_alpha",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 224,
+        min: 0,
+        max: 255,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    scope_channel(ParamId::ScopeChannel0, "scope_ch0"),
+    scope_channel(ParamId::ScopeChannel1, "scope_ch1"),
+    ParamDef {
+        id: ParamId::ScopeDecimation,
+        name: "scope_decimation",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 1,
+        min: 1,
+        max: 0xFFFF,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    excitation(ParamId::ExcitationPoint, "excitation_point", "", 0, 2),
+    excitation(ParamId::ExcitationWaveform, "excitation_waveform", "", 0, 3),
+    excitation(
+        ParamId::ExcitationAmplitude,
+        "excitation_amplitude",
+        "",
+        0,
+        1 << 16,
+    ),
+    excitation(
+        ParamId::ExcitationFrequency,
+        "excitation_freq_mhz",
+        "mHz",
+        1000,
+        10_000_000,
+    ),
+    excitation(
+        ParamId::ExcitationFrequencyEnd,
+        "excitation_freq_end_mhz",
+        "mHz",
+        1000,
+        10_000_000,
+    ),
+    excitation(
+        ParamId::ExcitationDuration,
+        "excitation_duration_ms",
+        "ms",
+        1000,
+        600_000,
+    ),
+    excitation(ParamId::BodePoints, "bode_points", "", 20, 64),
+    excitation(ParamId::BodePeriods, "bode_periods", "", 5, 1000),
+    excitation(ParamId::BodeResponse, "bode_response", "", 1, 16),
+    ParamDef {
+        id: ParamId::CaptureMode,
+        name: "capture_mode",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Single shot
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    compare_position(ParamId::ComparePosition0, "compare_pos0"),
+    compare_position(ParamId::ComparePosition1, "compare_pos1"),
+    compare_position(ParamId::ComparePosition2, "compare_pos2"),
+    compare_position(ParamId::ComparePosition3, "compare_pos3"),
+    compare(ParamId::CompareCount, "compare_count", "", 0, 4),
+    compare(ParamId::CompareAction, "compare_action", "", 0, 1),
+    compare(ParamId::CompareDirection, "compare_direction", "", 0, 2),
+    compare(
+        ParamId::ComparePulseWidth,
+        "compare_pulse_us",
+        "us",
+        1000,
+        1_000_000,
+    ),
+    ParamDef {
+        id: ParamId::GearNumerator,
+        name: "gear_numerator",
+        kind: ParamType::Signed,
+        unit: "",
+        default: 1,
+        min: 0,
+        max: u32::MAX,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::GearDenominator,
+        name: "gear_denominator",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 1,
+        min: 1,
+        max: i32::MAX as u32,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::GearOffset,
+        name: "gear_offset",
+        kind: ParamType::Signed,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: u32::MAX,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::FollowMaxSpeed,
+        name: "follow_max_speed",
+        kind: ParamType::Unsigned,
+        unit: "cnt/s",
+        default: 0, // Unlimited
+        min: 0,
+        max: i32::MAX as u32,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::FollowTimeout,
+        name: "follow_timeout_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 100,
+        min: 0,
+        max: 60_000,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::OdometryRate,
+        name: "odometry_rate_hz",
+        kind: ParamType::Unsigned,
+        unit: "Hz",
+        default: 0, // Disabled
+        min: 0,
+        max: 2000,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::SpeedWindow,
+        name: "speed_window",
+        kind: ParamType::Unsigned,
+        unit: "ticks",
+        default: 8,
+        min: 1,
+        max: 32,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::SpeedFilterAlpha,
+        name: "speed_filter_alpha",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Disabled
+        min: 0,
+        max: 255,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::SpeedUnit,
+        name: "speed_unit",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Counts per second
+        min: 0,
+        max: 3,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    collision(
+        ParamId::CollisionThreshold,
+        "collision_threshold_ma",
+        "mA",
+        0,
+        5000,
+    ),
+    collision(
+        ParamId::CollisionDebounce,
+        "collision_debounce_ms",
+        "ms",
+        5,
+        1000,
+    ),
+    collision(ParamId::CollisionReaction, "collision_reaction", "", 0, 2),
+    collision(
+        ParamId::CollisionReverse,
+        "collision_reverse",
+        "",
+        8192,
+        i32::MAX as u32,
+    ),
+    collision(
+        ParamId::CollisionTorque,
+        "collision_torque_ma",
+        "mA",
+        200,
+        5000,
+    ),
+    ParamDef {
+        id: ParamId::JogSpeed,
+        name: "jog_speed",
+        kind: ParamType::Unsigned,
+        unit: "counts/s",
+        default: 16384, // 1/4 revolution per second
+        min: 0,
+        max: 655360,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::JogTorque,
+        name: "jog_torque_ma",
+        kind: ParamType::Unsigned,
+        unit: "mA",
+        default: 300,
+        min: 0,
+        max: 5000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::JogTimeout,
+        name: "jog_timeout_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 200,
+        min: 20,
+        max: 2000,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    step_test(ParamId::StepCurrent, "step_current_ma", "mA", 100, 1, 1000),
+    step_test(
+        ParamId::StepSpeed,
+        "step_speed",
+        "counts/s",
+        16384,
+        1,
+        65536,
+    ),
+    step_test(
+        ParamId::StepDuration,
+        "step_duration_ms",
+        "ms",
+        200,
+        10,
+        2000,
+    ),
+    step_test(
+        ParamId::StepTravel,
+        "step_travel",
+        "",
+        65536,
+        0,
+        i32::MAX as u32,
+    ),
+    ParamDef {
+        id: ParamId::EncoderInvert,
+        name: "encoder_invert",
+        kind: ParamType::Bool,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::EncoderOffset,
+        name: "encoder_offset",
+        kind: ParamType::Signed,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: u32::MAX,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    filter_schedule(ParamId::FilterAlphaSlow, "filter_alpha_slow", "", 128, 255),
+    filter_schedule(ParamId::FilterAlphaFast, "filter_alpha_fast", "", 0, 255),
+    filter_schedule(
+        ParamId::FilterSpeedSlow,
+        "filter_speed_slow",
+        "counts/s",
+        6554, // 0.1 rev/s
+        i32::MAX as u32,
+    ),
+    filter_schedule(
+        ParamId::FilterSpeedFast,
+        "filter_speed_fast",
+        "counts/s",
+        0, // Schedule off
+        i32::MAX as u32,
+    ),
+    ParamDef {
+        id: ParamId::FilterOrder,
+        name: "filter_order",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 1,
+        min: 1,
+        max: 2,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    fault_reaction(ParamId::FaultReactionSensor, "fault_reaction_sensor"),
+    fault_reaction(ParamId::FaultReactionSupply, "fault_reaction_supply"),
+    fault_reaction(ParamId::FaultReactionMotion, "fault_reaction_motion"),
+    ParamDef {
+        id: ParamId::FaultRampTime,
+        name: "fault_ramp_time",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 200,
+        min: 1,
+        max: 10000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    supply_startup(
+        ParamId::SupplyEnableVoltage,
+        "supply_enable_voltage",
+        "mV",
+        8000,
+    ),
+    supply_startup(ParamId::SupplyMaxSlope, "supply_max_slope", "mV/s", 5000),
+    supply_startup(ParamId::SupplySettleTime, "supply_settle_time", "ms", 100),
+    ParamDef {
+        id: ParamId::ScopeAngleMask,
+        name: "scope_angle_mask",
+        kind: ParamType::Mask,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 0b111,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    production_limit(
+        ParamId::ProductionSupplyMin,
+        "production_supply_min",
+        "mV",
+        10000,
+        100000,
+    ),
+    production_limit(
+        ParamId::ProductionSupplyMax,
+        "production_supply_max",
+        "mV",
+        50000,
+        100000,
+    ),
+    production_limit(
+        ParamId::ProductionTempMin,
+        "production_temp_min",
+        "",
+        0,
+        65535,
+    ),
+    production_limit(
+        ParamId::ProductionTempMax,
+        "production_temp_max",
+        "",
+        65535, // Any reading, boards without sensor pass
+        65535,
+    ),
+    production_limit(
+        ParamId::ProductionDeviationMax,
+        "production_deviation_max",
+        "",
+        1024, // 5.6°
+        65535,
+    ),
+    production_limit(
+        ParamId::ProductionSpreadMax,
+        "production_spread_max",
+        "",
+        2048, // 11.25° el
+        65535,
+    ),
+    ParamDef {
+        id: ParamId::EncoderGlitchThreshold,
+        name: "encoder_glitch_threshold",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 32767,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::EncoderEmcMode,
+        name: "encoder_emc_mode",
+        kind: ParamType::Bool,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 1,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::RippleTest,
+        name: "ripple_test",
+        kind: ParamType::Bool,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    supply_monitor(
+        ParamId::SupplyUndervoltage,
+        "supply_undervoltage",
+        "mV",
+        7000,
+    ),
+    supply_monitor(
+        ParamId::SupplyOvervoltage,
+        "supply_overvoltage",
+        "mV",
+        55000,
+    ),
+    supply_monitor(ParamId::SupplyMissTime, "supply_miss_time", "ms", 5),
+    supply_monitor(ParamId::SupplyGraceTime, "supply_grace_time", "ms", 100),
+    ParamDef {
+        id: ParamId::StatusRate,
+        name: "status_rate_hz",
+        kind: ParamType::Unsigned,
+        unit: "Hz",
+        default: 0, // Disabled
+        min: 0,
+        max: 10,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::DeadTimeCompensation,
+        name: "dead_time_compensation",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Disabled
+        min: 0,
+        max: 4096, // Range of the linearization table
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::CurrentBlanking,
+        name: "current_blanking_ns",
+        kind: ParamType::Unsigned,
+        unit: "ns",
+        default: 500,
+        min: 0,
+        max: 5000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    },
+    ParamDef {
+        id: ParamId::MotorPreset,
+        name: "motor_preset",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Custom, board or host data
+        min: 0,
+        max: 5,
+        hot: false,
+        access: AccessLevel::User, // Presets only carry limits safe for their motor class
+    },
+    current_loop(ParamId::CurrentLoopKp, "current_loop_kp", 100),
+    current_loop(ParamId::CurrentLoopKi, "current_loop_ki", 10),
+    ParamDef {
+        id: ParamId::AngleSource,
+        name: "angle_source",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Encoder
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::Advanced,
+    },
+    sensorless(ParamId::ObserverBandwidth, "observer_bandwidth", "rad/s", 2000, 100, 10000),
+    sensorless(ParamId::SensorlessMinEmf, "sensorless_min_emf", "mV", 500, 10, 20000),
+    sensorless(ParamId::SensorlessStartSpeed, "sensorless_start_speed", "Hz", 5, 1, 100),
+    ParamDef {
+        id: ParamId::ScopeEnvelope,
+        name: "scope_envelope",
+        kind: ParamType::Mask,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 0b11,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::StepInput,
+        name: "step_input",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Off
+        min: 0,
+        max: 2,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::CanNode,
+        name: "can_node",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 1,
+        min: 1,
+        max: 127,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::CanBitrate,
+        name: "can_bitrate",
+        kind: ParamType::Unsigned,
+        unit: "kbit/s",
+        default: 500,
+        min: 10,
+        max: 1000,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::CanProtocol,
+        name: "can_protocol",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // TunePulse frames
+        min: 0,
+        max: 1,
+        hot: false,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::FollowingErrorWindow,
+        name: "following_error_window",
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0, // Disabled
+        min: 0,
+        max: i32::MAX as u32,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::FollowingErrorTimeout,
+        name: "following_error_timeout_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 50,
+        min: 0,
+        max: 10_000,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::VelocityWindow,
+        name: "velocity_window",
+        kind: ParamType::Unsigned,
+        unit: "counts/s",
+        default: 6554, // 1/10 revolution per second
+        min: 0,
+        max: 655360,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::VelocityWindowTime,
+        name: "velocity_window_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 20,
+        min: 0,
+        max: 10_000,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::ZeroSpeedThreshold,
+        name: "zero_speed_threshold",
+        kind: ParamType::Unsigned,
+        unit: "counts/s",
+        default: 3277, // 1/20 revolution per second
+        min: 0,
+        max: 655360,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    ParamDef {
+        id: ParamId::ZeroSpeedTime,
+        name: "zero_speed_ms",
+        kind: ParamType::Unsigned,
+        unit: "ms",
+        default: 20,
+        min: 0,
+        max: 10_000,
+        hot: true,
+        access: AccessLevel::User,
+    },
+    collision(ParamId::StepLossThreshold, "step_loss_threshold", "%", 0, 100),
+    collision(ParamId::StepLossMinSpeed, "step_loss_min_speed_hz", "Hz", 5, 1000),
+    collision(ParamId::StepLossDebounce, "step_loss_debounce_ms", "ms", 5, 1000),
+];
+
+/// Number of parameters
+pub const PARAM_COUNT: usize = 107;
+
+/// Definition of a digital pin function parameter, pins are unused by default
+const fn io_pin(id: ParamId, name: &'static str) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: IO_INVERT | 0xFF,
+        hot: false,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a scope channel routing parameter, channels are disabled by default
+const fn scope_channel(id: ParamId, name: &'static str) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 25,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a signal generator parameter, read when the excitation is started
+const fn excitation(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a compare output position, any position is valid
+const fn compare_position(id: ParamId, name: &'static str) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Signed,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: u32::MAX,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a compare output setting
+const fn compare(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a collision or step loss detection setting
+const fn collision(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a step response test setting, read when the test is started
+const fn step_test(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    min: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min,
+        max,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a speed-adaptive position filter setting
+const fn filter_schedule(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max,
+        hot: true,
+        access: AccessLevel::User,
+    }
+}
+
+/// Definition of a fault class reaction, short brake by default, read when a fault is raised
+const fn fault_reaction(id: ParamId, name: &'static str) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit: "",
+        default: 0,
+        min: 0,
+        max: 3,
+        hot: true,
+        access: AccessLevel::Advanced,
+    }
+}
+
+/// Definition of a staged power-up setting, only used until the bridge is enabled
+const fn supply_startup(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max: 100000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    }
+}
+
+/// Definition of a supply monitoring setting, applied immediately
+const fn supply_monitor(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max: 100000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    }
+}
+
+/// Definition of a production test limit, read when the test finishes
+const fn production_limit(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min: 0,
+        max,
+        hot: true,
+        access: AccessLevel::Factory,
+    }
+}
+
+/// Definition of a current loop gain, only used on boards with a known current sense scale
+const fn current_loop(id: ParamId, name: &'static str, default: u32) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit: "%",
+        default,
+        min: 0,
+        max: 10000,
+        hot: true,
+        access: AccessLevel::Advanced,
+    }
+}
+
+/// Definition of a sensorless commutation parameter, applied while the drive is not energized
+const fn sensorless(
+    id: ParamId,
+    name: &'static str,
+    unit: &'static str,
+    default: u32,
+    min: u32,
+    max: u32,
+) -> ParamDef {
+    ParamDef {
+        id,
+        name,
+        kind: ParamType::Unsigned,
+        unit,
+        default,
+        min,
+        max,
+        hot: false,
+        access: AccessLevel::Advanced,
+    }
+}
+
+/// Finds a parameter by name
+pub fn find_by_name(name: &str) -> Option<&'static ParamDef> {
+    PARAMS.iter().find(|def| def.name == name)
+}
+
+/// Writes the parameter table as JSON array
+pub fn write_json<W: core::fmt::Write>(out: &mut W) -> core::fmt::Result {
+    out.write_str("[\n")?;
+    for (idx, def) in PARAMS.iter().enumerate() {
+        write!(
+            out,
+            "  {{\"id\": {}, \"name\": \"{}\", \"type\": \"{}\", \"unit\": \"{}\", \
+             \"default\": {}, \"min\": {}, \"max\": {}, \"hot\": {}, \"access\": \"{}\"}}",
+            def.id as u16,
+            def.name,
+            def.kind.name(),
+            def.unit,
+            def.default,
+            def.min,
+            def.max,
+            def.hot,
+            def.access.name()
+        )?;
+        out.write_str(if idx + 1 < PARAMS.len() { ",\n" } else { "\n" })?;
+    }
+    out.write_str("]\n")
+}
+
+// Table index has to match the identifier, checked at compile time
+const _: () = {
+    let mut i = 0;
+    while i < PARAM_COUNT {
+        assert!(
+            PARAMS[i].id as usize == i,
+            "PARAMS order must match ParamId values"
+        );
+        i += 1;
+    }
+};